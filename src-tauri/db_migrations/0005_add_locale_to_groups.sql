@@ -0,0 +1,3 @@
+-- Add an optional locale hint to groups, set by group admins and used for
+-- formatting system messages, exported timestamps and structured payloads
+ALTER TABLE groups ADD COLUMN locale TEXT;
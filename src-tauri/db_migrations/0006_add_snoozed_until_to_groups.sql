@@ -0,0 +1,3 @@
+-- Add snooze support to groups. Notifications are suppressed until this timestamp
+-- unless the user is mentioned or replied to.
+ALTER TABLE groups ADD COLUMN snoozed_until INTEGER;
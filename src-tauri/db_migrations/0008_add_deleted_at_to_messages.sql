@@ -0,0 +1,4 @@
+-- Tombstones for messages retracted by their author with a kind 5 deletion.
+-- The row is kept (so replies, reactions and the deletion itself still resolve)
+-- but its content is cleared and deleted_at is set.
+ALTER TABLE messages ADD COLUMN deleted_at INTEGER;
@@ -0,0 +1,3 @@
+-- Edits replace a message's content in place; the original content stays in the
+-- stored event JSON and every edit is kept as its own row, so the history can be rebuilt.
+ALTER TABLE messages ADD COLUMN edited_at INTEGER;
@@ -0,0 +1,3 @@
+-- Groups flagged as sensitive by an admin. Frontends blur previews and the backend
+-- asks the OS to keep the window out of screenshots and screen recordings.
+ALTER TABLE groups ADD COLUMN sensitive BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,15 @@
+-- Key migrations announced by contacts: a statement signed by the old key endorsing a new one.
+-- One row per old key; a newer statement replaces an older one.
+CREATE TABLE contact_key_migrations (
+    account_pubkey TEXT NOT NULL,
+    old_pubkey TEXT NOT NULL,
+    new_pubkey TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    dismissed BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE,
+    PRIMARY KEY (account_pubkey, old_pubkey)
+);
+
+-- Messages written with a key its owner has since migrated away from
+ALTER TABLE messages ADD COLUMN author_migrated_to TEXT;
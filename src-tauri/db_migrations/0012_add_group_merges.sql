@@ -0,0 +1,5 @@
+-- Merging a duplicate group moves its transcript into the group it's merged into and
+-- archives it. Moved messages remember the group they were originally received in.
+ALTER TABLE groups ADD COLUMN archived_at INTEGER;
+ALTER TABLE groups ADD COLUMN merged_into BLOB;
+ALTER TABLE messages ADD COLUMN origin_group_id BLOB;
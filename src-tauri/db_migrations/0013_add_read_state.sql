@@ -0,0 +1,14 @@
+-- Local read cursor: the latest message the user has read in each group
+ALTER TABLE groups ADD COLUMN last_read_message_id TEXT;
+ALTER TABLE groups ADD COLUMN last_read_message_at INTEGER;
+
+-- The latest read receipt from each member of a group
+CREATE TABLE read_receipts (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    reader_pubkey TEXT NOT NULL,
+    event_id TEXT NOT NULL, -- The latest message the reader has read
+    read_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, mls_group_id, reader_pubkey),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+-- Delivery status of outgoing group messages
+CREATE TABLE message_outbox (
+    event_id TEXT NOT NULL, -- The inner UnsignedEvent's id, as in the messages table
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    outer_event_id TEXT NOT NULL,
+    outer_event TEXT NOT NULL, -- JSON of the signed kind 445 event, kept so it can be republished
+    state TEXT NOT NULL, -- 'Pending', 'Sent' or 'Failed'
+    acked_relays TEXT NOT NULL DEFAULT '[]', -- JSON array of relays that accepted the event
+    failed_relays TEXT NOT NULL DEFAULT '{}', -- JSON object of relay URL to error message
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (event_id, account_pubkey),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_message_outbox_state ON message_outbox(account_pubkey, state);
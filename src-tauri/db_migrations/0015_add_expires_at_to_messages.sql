@@ -0,0 +1,4 @@
+-- Messages sent with a NIP-40 expiration are deleted locally once it passes
+ALTER TABLE messages ADD COLUMN expires_at INTEGER;
+
+CREATE INDEX idx_messages_expires_at ON messages(expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Messages that no relay accepted stay queued and are republished with exponential backoff
+ALTER TABLE message_outbox ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE message_outbox ADD COLUMN next_attempt_at INTEGER;
+
+CREATE INDEX idx_message_outbox_next_attempt ON message_outbox(account_pubkey, state, next_attempt_at);
@@ -0,0 +1,2 @@
+-- Per-group overrides of the account's content filter settings (JSON)
+ALTER TABLE groups ADD COLUMN content_filter TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,13 @@
+-- Relays that acknowledged each published key package and welcome
+CREATE TABLE published_artifacts (
+    event_id TEXT NOT NULL, -- For welcomes, the gift wrap's id
+    account_pubkey TEXT NOT NULL,
+    kind TEXT NOT NULL, -- 'key_package' or 'welcome'
+    recipient_pubkey TEXT, -- The member a welcome was sent to
+    relays TEXT NOT NULL DEFAULT '[]', -- JSON array of relays that accepted the event
+    used_fallback INTEGER NOT NULL DEFAULT 0,
+    published_at INTEGER NOT NULL,
+    PRIMARY KEY (event_id, account_pubkey)
+);
+
+CREATE INDEX idx_published_artifacts_kind ON published_artifacts(account_pubkey, kind);
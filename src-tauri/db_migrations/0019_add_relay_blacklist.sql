@@ -0,0 +1,19 @@
+-- Relays that events are never published to, per account
+CREATE TABLE relay_blacklist (
+    url TEXT NOT NULL,
+    account_pubkey TEXT NOT NULL,
+    source TEXT NOT NULL, -- 'user' or 'automatic'
+    reason TEXT,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (url, account_pubkey)
+);
+
+-- Protocol violations (blocked/restricted rejections) per relay, used for automatic blacklisting
+CREATE TABLE relay_violations (
+    url TEXT NOT NULL,
+    account_pubkey TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    last_message TEXT,
+    last_violation_at INTEGER NOT NULL,
+    PRIMARY KEY (url, account_pubkey)
+);
@@ -0,0 +1,10 @@
+-- Index of group members, kept in sync with the MLS group state so groups can be looked up by member
+CREATE TABLE group_members (
+    group_id BLOB NOT NULL,
+    account_pubkey TEXT NOT NULL,
+    member_pubkey TEXT NOT NULL,
+    PRIMARY KEY (group_id, account_pubkey, member_pubkey),
+    FOREIGN KEY (group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_group_members_member ON group_members(account_pubkey, member_pubkey);
@@ -0,0 +1,8 @@
+-- How far each group's messages have been fetched by background refreshes
+CREATE TABLE background_sync_checkpoints (
+    group_id BLOB NOT NULL,
+    account_pubkey TEXT NOT NULL,
+    synced_until INTEGER NOT NULL,
+    PRIMARY KEY (group_id, account_pubkey),
+    FOREIGN KEY (group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- IDs of the key package events an account currently has published, as a JSON array
+ALTER TABLE accounts ADD COLUMN key_package_ids TEXT NOT NULL DEFAULT '[]';
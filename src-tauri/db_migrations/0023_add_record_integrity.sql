@@ -0,0 +1,17 @@
+-- Content hashes of stored records, compared by verify_data_integrity
+CREATE TABLE record_checksums (
+    table_name TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    PRIMARY KEY (table_name, record_id)
+);
+
+-- Records that failed verification, moved out of their table so they can't break loading
+CREATE TABLE quarantined_records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_name TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    data TEXT NOT NULL,  -- JSON of the original row
+    reason TEXT NOT NULL,
+    quarantined_at INTEGER NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Whether the account reads from and writes to each of its relays
+ALTER TABLE account_relays ADD COLUMN read BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE account_relays ADD COLUMN write BOOLEAN NOT NULL DEFAULT TRUE;
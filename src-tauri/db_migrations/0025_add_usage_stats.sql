@@ -0,0 +1,14 @@
+-- Local-only daily usage counters per group, updated as messages are stored
+CREATE TABLE usage_stats (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    day INTEGER NOT NULL,  -- Days since the Unix epoch (UTC)
+    messages_sent INTEGER NOT NULL DEFAULT 0,
+    messages_received INTEGER NOT NULL DEFAULT 0,
+    media_count INTEGER NOT NULL DEFAULT 0,
+    media_bytes INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (account_pubkey, mls_group_id, day),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_usage_stats_account_day ON usage_stats(account_pubkey, day);
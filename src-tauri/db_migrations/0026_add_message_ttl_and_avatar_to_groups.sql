@@ -0,0 +1,4 @@
+-- Default expiry of messages the account sends to the group, in seconds
+ALTER TABLE groups ADD COLUMN message_ttl INTEGER;
+-- Local avatar for the group, e.g. set from a group template
+ALTER TABLE groups ADD COLUMN avatar_url TEXT;
@@ -0,0 +1,2 @@
+-- Muted groups never produce notifications, unlike snoozed ones
+ALTER TABLE groups ADD COLUMN muted BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,19 @@
+-- The account's NIP-02 contact list, edited locally and published as a kind 3 event
+CREATE TABLE contacts (
+    account_pubkey TEXT NOT NULL,
+    pubkey TEXT NOT NULL,
+    petname TEXT,
+    relay_url TEXT,
+    enriched TEXT,  -- JSON encoded EnrichedContact, cached from the last lookup
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+-- Which kind 3 event the contacts table mirrors, and whether it has unpublished changes
+CREATE TABLE contact_lists (
+    account_pubkey TEXT PRIMARY KEY,
+    synced_at INTEGER NOT NULL,  -- created_at of the last imported or published kind 3 event
+    dirty BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
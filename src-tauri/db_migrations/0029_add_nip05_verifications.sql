@@ -0,0 +1,8 @@
+-- Results of checking users' NIP-05 identifiers against their pubkeys. Not tied to an account:
+-- whether a domain vouches for a pubkey is the same for everyone.
+CREATE TABLE nip05_verifications (
+    pubkey TEXT PRIMARY KEY,
+    nip05 TEXT NOT NULL,  -- lowercased identifier that was checked
+    verified BOOLEAN NOT NULL,
+    checked_at INTEGER NOT NULL
+);
@@ -0,0 +1,18 @@
+-- Latest state of each group's collaborative notes, built from GROUP_NOTE_KIND messages
+CREATE TABLE group_notes (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    note_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    author_pubkey TEXT NOT NULL,
+    event_id TEXT NOT NULL,      -- the update that set the current content
+    updated_at INTEGER NOT NULL,
+    -- A concurrent edit that lost under last-writer-wins, kept until a newer version replaces it
+    conflict_content TEXT,
+    conflict_author_pubkey TEXT,
+    conflict_event_id TEXT,
+    conflict_at INTEGER,
+    PRIMARY KEY (account_pubkey, mls_group_id, note_id),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Namespaced per-group state set by frontend features, built from GROUP_CUSTOM_DATA_KIND messages
+CREATE TABLE group_custom_data (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    namespace TEXT NOT NULL,
+    value TEXT NOT NULL,         -- JSON; null once the namespace is cleared
+    author_pubkey TEXT NOT NULL,
+    event_id TEXT NOT NULL,      -- the message that set the current value
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, mls_group_id, namespace),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,8 @@
+-- Users whose group messages are dropped and whose invites are declined automatically
+CREATE TABLE blocked_users (
+    account_pubkey TEXT NOT NULL,
+    pubkey TEXT NOT NULL,
+    blocked_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
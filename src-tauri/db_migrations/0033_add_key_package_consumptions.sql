@@ -0,0 +1,12 @@
+-- Published key packages that were used up, for the account health view
+CREATE TABLE key_package_consumptions (
+    account_pubkey TEXT NOT NULL,
+    key_package_id TEXT NOT NULL,
+    consumed_at INTEGER NOT NULL,
+    source TEXT NOT NULL,        -- 'welcome' or 'relay_deletion'
+    mls_group_id BLOB,           -- the group it was used to join, when known
+    group_name TEXT,
+    inviter TEXT,
+    PRIMARY KEY (account_pubkey, key_package_id),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,26 @@
+-- Attachments and links shared in each group, for the shared media view
+CREATE TABLE group_media (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    event_id TEXT NOT NULL,      -- the message that shared it
+    position INTEGER NOT NULL,   -- order within the message
+    category TEXT NOT NULL,      -- 'image', 'video', 'file' or 'link'
+    url TEXT NOT NULL,
+    mime_type TEXT,
+    filename TEXT,
+    size INTEGER,
+    author_pubkey TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, event_id, position),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_group_media_group ON group_media(account_pubkey, mls_group_id, category, created_at);
+
+-- Groups whose messages from before the media index existed have been indexed
+CREATE TABLE group_media_backfills (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    PRIMARY KEY (account_pubkey, mls_group_id),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,48 @@
+-- Recovery shares other accounts gave us to hold
+CREATE TABLE recovery_shares (
+    account_pubkey TEXT NOT NULL,
+    owner_pubkey TEXT NOT NULL,  -- the account the share helps recover
+    share_index INTEGER NOT NULL,
+    share TEXT NOT NULL,         -- hex encoded
+    threshold INTEGER NOT NULL,
+    commitment TEXT NOT NULL,    -- hex encoded SHA256 of the recovery secret
+    received_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, owner_pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+-- Requests from new identities to help recover an account we hold a share for
+CREATE TABLE recovery_requests (
+    account_pubkey TEXT NOT NULL,
+    old_pubkey TEXT NOT NULL,
+    new_pubkey TEXT NOT NULL,
+    requested_at INTEGER NOT NULL,
+    approved_at INTEGER,
+    PRIMARY KEY (account_pubkey, old_pubkey, new_pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+-- Old accounts we're recovering, and the contacts we asked
+CREATE TABLE account_recoveries (
+    account_pubkey TEXT NOT NULL,
+    old_pubkey TEXT NOT NULL,
+    contacts TEXT NOT NULL,      -- JSON array of hex pubkeys
+    requested_at INTEGER NOT NULL,
+    completed_at INTEGER,
+    PRIMARY KEY (account_pubkey, old_pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+-- Shares the contacts released to us while recovering an old account
+CREATE TABLE released_recovery_shares (
+    account_pubkey TEXT NOT NULL,
+    old_pubkey TEXT NOT NULL,
+    holder_pubkey TEXT NOT NULL,
+    share_index INTEGER NOT NULL,
+    share TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    commitment TEXT NOT NULL,
+    received_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, old_pubkey, holder_pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
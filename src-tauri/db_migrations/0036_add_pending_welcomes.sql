@@ -0,0 +1,13 @@
+-- Welcomes to new group members that couldn't be sent, kept for retrying
+CREATE TABLE pending_welcomes (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    member_pubkey TEXT NOT NULL,
+    key_package_event_id TEXT NOT NULL,  -- the key package the welcome was made for
+    welcome TEXT NOT NULL,               -- hex encoded serialized MLS welcome message
+    error TEXT NOT NULL,                 -- why the last attempt failed
+    attempts INTEGER NOT NULL DEFAULT 1,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, mls_group_id, member_pubkey),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
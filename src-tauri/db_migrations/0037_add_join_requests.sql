@@ -0,0 +1,11 @@
+-- Requests to join our groups through invite messages we created
+CREATE TABLE join_requests (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    requester_pubkey TEXT NOT NULL,
+    invite_id TEXT NOT NULL,             -- the signed invite the request accepts
+    key_package_event_id TEXT NOT NULL,  -- the requester's key package to add them with
+    requested_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, mls_group_id, requester_pubkey),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,24 @@
+-- Raw group message events and what processing them gave, kept in dev mode for replaying
+CREATE TABLE group_event_log (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    event_id TEXT NOT NULL,
+    event TEXT NOT NULL,                 -- the encrypted event as received, JSON
+    message_event_id TEXT,               -- the decrypted message, if processing gave one
+    error TEXT,                          -- why processing failed, otherwise
+    received_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, event_id),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_group_event_log_group ON group_event_log(account_pubkey, mls_group_id);
+
+-- The MLS state from before the first logged event of a group, replays start from it
+CREATE TABLE group_event_log_baselines (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    state TEXT NOT NULL,                 -- JSON list of MLS storage files
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, mls_group_id),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
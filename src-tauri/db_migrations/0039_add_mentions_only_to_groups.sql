@@ -0,0 +1,2 @@
+-- Groups that only notify for mentions and replies. Muted groups don't notify at all.
+ALTER TABLE groups ADD COLUMN mentions_only BOOLEAN NOT NULL DEFAULT FALSE;
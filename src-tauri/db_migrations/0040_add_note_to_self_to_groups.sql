@@ -0,0 +1,3 @@
+-- Marks the account's single-member "Note to Self" group. Its group_type is stored as 'Group'
+-- because of the CHECK constraint on that column.
+ALTER TABLE groups ADD COLUMN note_to_self BOOLEAN NOT NULL DEFAULT FALSE;
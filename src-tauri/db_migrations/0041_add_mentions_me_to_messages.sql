@@ -0,0 +1,9 @@
+-- Messages that mention the account that owns them, as a `p` tag or a nostr: URI in the content
+ALTER TABLE messages ADD COLUMN mentions_me BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Mentions in the content of existing messages can't be found from SQL, only their `p` tags
+UPDATE messages SET mentions_me = TRUE
+WHERE author_pubkey != account_pubkey
+  AND tags LIKE '%["p","' || account_pubkey || '"%';
+
+CREATE INDEX idx_messages_mentions ON messages(account_pubkey, mls_group_id, mentions_me);
@@ -0,0 +1,16 @@
+-- Invite links we created for our groups. The signed invite is published under the link's code,
+-- so the link is enough to find it; join requests through it count against max_uses.
+CREATE TABLE invite_links (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    code TEXT NOT NULL,
+    invite_id TEXT NOT NULL,          -- the signed invite published under the code
+    max_uses INTEGER,                 -- NULL for unlimited
+    uses INTEGER NOT NULL DEFAULT 0,  -- approved join requests through the link
+    expires_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, invite_id),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX idx_invite_links_code ON invite_links(account_pubkey, code);
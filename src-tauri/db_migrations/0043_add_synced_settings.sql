@@ -0,0 +1,10 @@
+-- The last change of every synced setting, so changes from the account's other devices can be
+-- merged last-write-wins per key.
+CREATE TABLE synced_settings (
+    account_pubkey TEXT NOT NULL,
+    key TEXT NOT NULL,            -- e.g. `notifications` or `group_archived:<hex group ID>`
+    value TEXT NOT NULL,          -- JSON of the setting, compared to break ties
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, key),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+-- The epoch each member was first seen in. Members indexed before this was kept have none.
+ALTER TABLE group_members ADD COLUMN joined_epoch INTEGER;
@@ -0,0 +1,2 @@
+-- When each member's leaf was last known to get fresh keys, by joining or by a self update
+ALTER TABLE group_members ADD COLUMN leaf_updated_at INTEGER;
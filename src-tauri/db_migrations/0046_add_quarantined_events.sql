@@ -0,0 +1,13 @@
+-- Group message events no known export secret decrypts, kept for retrying
+CREATE TABLE quarantined_events (
+    account_pubkey TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    event TEXT NOT NULL,                 -- the event as JSON
+    reason TEXT NOT NULL,                -- why the last attempt failed
+    attempts INTEGER NOT NULL DEFAULT 1,
+    quarantined_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, event_id),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
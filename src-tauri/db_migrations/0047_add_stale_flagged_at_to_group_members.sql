@@ -0,0 +1,2 @@
+-- When admins were last told a member is stale, cleared once the member is fresh again
+ALTER TABLE group_members ADD COLUMN stale_flagged_at INTEGER;
@@ -0,0 +1,11 @@
+-- Invite messages we created. The signed invite doesn't name the group, so join requests are
+-- matched to it through here.
+CREATE TABLE issued_invites (
+    account_pubkey TEXT NOT NULL,
+    mls_group_id BLOB NOT NULL,
+    invite_id TEXT NOT NULL,  -- the signed invite in the message
+    expires_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, invite_id),
+    FOREIGN KEY (mls_group_id, account_pubkey) REFERENCES groups(mls_group_id, account_pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+-- Each member's leaf encryption key, so a leaf update shows as a changed key.
+-- The update times recorded so far were mostly when members were first seen, not when their
+-- leaves got fresh keys, so they're dropped.
+ALTER TABLE group_members ADD COLUMN leaf_key TEXT;
+UPDATE group_members SET leaf_updated_at = NULL;
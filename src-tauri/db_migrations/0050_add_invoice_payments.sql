@@ -0,0 +1,10 @@
+-- Invoices paid from chat, recorded before they're paid so the same invoice isn't paid twice
+CREATE TABLE invoice_payments (
+    account_pubkey TEXT NOT NULL,
+    payment_hash TEXT NOT NULL,     -- Hex encoded payment hash of the BOLT11 invoice
+    invoice_event_id TEXT NOT NULL, -- The message the invoice was shared in
+    state TEXT NOT NULL,            -- 'pending', 'paid' or 'failed'
+    preimage TEXT,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, payment_hash)
+);
@@ -0,0 +1,4 @@
+-- Baselines used to be stored in plaintext. They're encrypted now, so the old ones and the
+-- events logged against them are dropped; dev builds take new baselines as events arrive.
+DELETE FROM group_event_log;
+DELETE FROM group_event_log_baselines;
@@ -0,0 +1,4 @@
+-- Quarantined events are read back from the nostr database when they're retried, so only their
+-- IDs are kept
+ALTER TABLE quarantined_events DROP COLUMN event;
+CREATE INDEX idx_quarantined_events_group ON quarantined_events(account_pubkey, mls_group_id);
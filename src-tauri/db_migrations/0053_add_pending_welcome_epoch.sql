@@ -0,0 +1,3 @@
+-- The epoch a pending welcome was made at. Welcomes from earlier epochs can't be used to join
+-- anymore, so they're dropped instead of retried; NULL for the ones stored before this was kept.
+ALTER TABLE pending_welcomes ADD COLUMN epoch INTEGER;
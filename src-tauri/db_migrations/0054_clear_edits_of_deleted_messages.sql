@@ -0,0 +1,26 @@
+-- Deleting a message now clears its edits too. Clear the edits of messages deleted before, which
+-- also takes their content out of messages_fts through the messages_au trigger.
+UPDATE messages
+SET deleted_at = (
+        SELECT target.deleted_at FROM messages AS target, json_each(messages.tags) AS t
+        WHERE json_extract(t.value, '$[0]') = 'e'
+          AND target.event_id = json_extract(t.value, '$[1]')
+          AND target.account_pubkey = messages.account_pubkey
+          AND target.mls_group_id = messages.mls_group_id
+          AND target.deleted_at IS NOT NULL
+        LIMIT 1
+    ),
+    content = '',
+    tokens = '[]',
+    tags = '[]',
+    event = json_set(event, '$.content', '', '$.tags', json('[]'))
+WHERE event_kind = 1010
+  AND deleted_at IS NULL
+  AND EXISTS (
+      SELECT 1 FROM messages AS target, json_each(messages.tags) AS t
+      WHERE json_extract(t.value, '$[0]') = 'e'
+        AND target.event_id = json_extract(t.value, '$[1]')
+        AND target.account_pubkey = messages.account_pubkey
+        AND target.mls_group_id = messages.mls_group_id
+        AND target.deleted_at IS NOT NULL
+  );
@@ -0,0 +1,10 @@
+-- Key packages replaced by a rotation. Their private key material stays in MLS storage for a
+-- grace period so that welcomes already in flight for them can still be processed, and is
+-- purged after that. The serialized key package is kept since the event is deleted from relays.
+CREATE TABLE rotated_key_packages (
+    account_pubkey TEXT NOT NULL,
+    key_package_id TEXT NOT NULL,
+    key_package TEXT NOT NULL,
+    rotated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, key_package_id)
+);
@@ -0,0 +1,9 @@
+-- Users unblocked since the mute list was last published, so that publishing drops them from the
+-- mute list while keeping the pubkeys other clients muted
+CREATE TABLE unblocked_users (
+    account_pubkey TEXT NOT NULL,
+    pubkey TEXT NOT NULL,
+    unblocked_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, pubkey),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
@@ -0,0 +1,19 @@
+-- Admin notices (kind 1014) are no longer counted as messages. Take the ones stored before out of
+-- the daily usage counters.
+UPDATE usage_stats
+SET messages_sent = MAX(0, messages_sent - (
+        SELECT COUNT(*) FROM messages m
+        WHERE m.account_pubkey = usage_stats.account_pubkey
+          AND m.mls_group_id = usage_stats.mls_group_id
+          AND m.event_kind = 1014
+          AND m.created_at / 86400 = usage_stats.day
+          AND m.author_pubkey = m.account_pubkey
+    )),
+    messages_received = MAX(0, messages_received - (
+        SELECT COUNT(*) FROM messages m
+        WHERE m.account_pubkey = usage_stats.account_pubkey
+          AND m.mls_group_id = usage_stats.mls_group_id
+          AND m.event_kind = 1014
+          AND m.created_at / 86400 = usage_stats.day
+          AND m.author_pubkey != m.account_pubkey
+    ));
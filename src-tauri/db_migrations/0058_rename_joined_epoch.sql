@@ -0,0 +1,3 @@
+-- The epoch a member was first indexed in isn't necessarily the one they joined in: members of
+-- groups indexed late, or after several commits, joined before it
+ALTER TABLE group_members RENAME COLUMN joined_epoch TO first_seen_epoch;
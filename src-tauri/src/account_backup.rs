@@ -0,0 +1,769 @@
+//! Encrypted account backups.
+//!
+//! A backup bundles everything needed to pick up an account on another device: the private
+//! key, the account's settings and relays, its groups with their relays and export secrets, and
+//! the MLS group state kept by `nostr_mls`. Without the MLS state a restored account could read
+//! nothing until it was re-added to every group.
+//!
+//! The bundle is encrypted with ChaCha20-Poly1305 under a key derived from the user's
+//! passphrase with Argon2id (see `kdf`) and written as a single JSON file. Files written before
+//! Argon2id was adopted, with a key stretched by iterated SHA-256, can still be opened.
+
+use crate::accounts::{Account, AccountError};
+use crate::atomic_file;
+use crate::groups::{Group, GroupError, GroupState};
+use crate::kdf::{self, KdfError, KdfParams};
+use crate::relays::RelayType;
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use nostr_openmls::NostrMls;
+use nostr_sdk::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Version of the backup format
+const BACKUP_VERSION: u32 = 1;
+
+/// Version of the encrypted file format, whose key is derived with Argon2id
+const FILE_VERSION: u32 = 2;
+
+/// Version of the encrypted file format whose key was stretched with iterated SHA-256
+const LEGACY_FILE_VERSION: u32 = 1;
+
+/// Most SHA-256 rounds a legacy file may ask for. The app wrote 200,000.
+const MAX_LEGACY_KDF_ITERATIONS: u32 = 1_000_000;
+
+/// Minimum backup passphrase length. Backups leave the device, so this is stricter than the
+/// app lock.
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+/// Directory in the data dir exported backups are written to
+const BACKUPS_DIR: &str = "backups";
+
+/// Directory in the data dir where `nostr_mls` keeps each identity's group state
+const MLS_STORAGE_DIR: &str = "mls_storage";
+
+/// File extension of backup files
+pub const BACKUP_EXTENSION: &str = "wnbackup";
+
+#[derive(Error, Debug)]
+pub enum AccountBackupError {
+    #[error("Invalid passphrase: {0}")]
+    InvalidPassphrase(String),
+
+    #[error("Wrong passphrase or corrupted backup")]
+    DecryptionFailed,
+
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+
+    #[error("Unsupported backup version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Key derivation error: {0}")]
+    KdfError(#[from] KdfError),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("Failed to parse key: {0}")]
+    KeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Base64 error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+}
+
+pub type Result<T> = std::result::Result<T, AccountBackupError>;
+
+/// An export secret of a group
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackupExportSecret {
+    pub epoch: u64,
+    /// Hex encoded secret
+    pub secret: String,
+}
+
+/// A group and the data kept alongside it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupGroup {
+    pub group: Group,
+    pub relays: Vec<String>,
+    pub export_secrets: Vec<BackupExportSecret>,
+}
+
+/// A file from the MLS storage directory
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackupStateFile {
+    /// Path relative to the account's MLS storage directory, `/` separated
+    pub path: String,
+    /// Base64 encoded contents
+    pub data: String,
+}
+
+/// The decrypted contents of a backup
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountBackup {
+    pub version: u32,
+    pub created_at: Timestamp,
+    /// Hex encoded private key
+    pub private_key: String,
+    pub account: Account,
+    /// Account relays by relay type
+    pub relays: BTreeMap<String, Vec<String>>,
+    pub groups: Vec<BackupGroup>,
+    pub mls_state: Vec<BackupStateFile>,
+}
+
+/// The backup file as written to disk
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct BackupFile {
+    version: u32,
+    /// Hex encoded passphrase salt
+    salt: String,
+    /// Argon2id parameters of the key, from [`FILE_VERSION`] on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<KdfParams>,
+    /// SHA-256 rounds of the key in [`LEGACY_FILE_VERSION`] files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iterations: Option<u32>,
+    /// Hex encoded nonce
+    nonce: String,
+    /// Base64 encoded encrypted [`AccountBackup`]
+    ciphertext: String,
+}
+
+pub fn validate_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(AccountBackupError::InvalidPassphrase(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// The Argon2id parameters new files are encrypted with. Tests use cheap ones.
+fn kdf_params() -> KdfParams {
+    if cfg!(test) {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    } else {
+        KdfParams::default()
+    }
+}
+
+/// Encrypts `plaintext` with a key derived from the passphrase, returning the file contents
+pub(crate) fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; kdf::SALT_LENGTH];
+    rand::rng().fill_bytes(&mut salt);
+    let mut nonce = [0u8; 12];
+    rand::rng().fill_bytes(&mut nonce);
+
+    let params = kdf_params();
+    let key = kdf::derive_key(passphrase, &salt, &params)?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|e| AccountBackupError::Encryption(e.to_string()))?;
+
+    let file = BackupFile {
+        version: FILE_VERSION,
+        salt: hex::encode(salt),
+        kdf: Some(params),
+        iterations: None,
+        nonce: hex::encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    };
+    Ok(serde_json::to_vec_pretty(&file)?)
+}
+
+/// Stretches a passphrase into a 32 byte key with iterated, salted SHA-256, the key derivation
+/// of [`LEGACY_FILE_VERSION`] files
+fn stretch(passphrase: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update(passphrase.as_bytes())
+        .finalize();
+    for _ in 1..iterations {
+        digest = Sha256::new()
+            .chain_update(digest)
+            .chain_update(salt)
+            .chain_update(passphrase.as_bytes())
+            .finalize();
+    }
+    digest.to_vec()
+}
+
+/// Derives the key of a file from the passphrase, with the parameters bounded so a crafted file
+/// can't make opening it exhaust the device
+fn file_key(file: &BackupFile, salt: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>> {
+    match (file.version, file.kdf, file.iterations) {
+        (FILE_VERSION, Some(params), _) => Ok(Zeroizing::new(
+            kdf::derive_key(passphrase, salt, &params)?.to_vec(),
+        )),
+        (LEGACY_FILE_VERSION, _, Some(iterations))
+            if (1..=MAX_LEGACY_KDF_ITERATIONS).contains(&iterations) =>
+        {
+            Ok(Zeroizing::new(stretch(passphrase, salt, iterations)))
+        }
+        (FILE_VERSION | LEGACY_FILE_VERSION, _, _) => Err(AccountBackupError::InvalidBackup(
+            "Missing or invalid key derivation parameters".to_string(),
+        )),
+        (version, _, _) => Err(AccountBackupError::UnsupportedVersion(version)),
+    }
+}
+
+/// Decrypts the contents of a file written by [`encrypt`]
+pub(crate) fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let file: BackupFile = serde_json::from_slice(data)
+        .map_err(|e| AccountBackupError::InvalidBackup(e.to_string()))?;
+
+    let salt = hex::decode(&file.salt)
+        .map_err(|e| AccountBackupError::InvalidBackup(format!("Invalid salt: {}", e)))?;
+    let nonce = hex::decode(&file.nonce)
+        .map_err(|e| AccountBackupError::InvalidBackup(format!("Invalid nonce: {}", e)))?;
+    if nonce.len() != 12 {
+        return Err(AccountBackupError::InvalidBackup(
+            "Invalid nonce".to_string(),
+        ));
+    }
+    let ciphertext = general_purpose::STANDARD.decode(&file.ciphertext)?;
+
+    let key = file_key(&file, &salt, passphrase)?;
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| AccountBackupError::DecryptionFailed)
+}
+
+/// Encrypts a backup with a key derived from the passphrase
+pub fn seal(backup: &AccountBackup, passphrase: &str) -> Result<Vec<u8>> {
+    encrypt(&serde_json::to_vec(backup)?, passphrase)
+}
+
+/// Decrypts a backup file's contents
+pub fn open(data: &[u8], passphrase: &str) -> Result<AccountBackup> {
+    let backup: AccountBackup = serde_json::from_slice(&decrypt(data, passphrase)?)?;
+    if backup.version != BACKUP_VERSION {
+        return Err(AccountBackupError::UnsupportedVersion(backup.version));
+    }
+    Ok(backup)
+}
+
+/// The directory `nostr_mls` keeps an identity's group state in
+fn mls_storage_dir(data_dir: &Path, pubkey: &PublicKey) -> PathBuf {
+    data_dir.join(MLS_STORAGE_DIR).join(pubkey.to_hex())
+}
+
+/// Reads every file below `root`, with paths relative to it
+fn read_state_files(root: &Path) -> Result<Vec<BackupStateFile>> {
+    let mut files = Vec::new();
+    if !root.exists() {
+        return Ok(files);
+    }
+
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let relative = path.strip_prefix(root).map_err(|e| {
+                AccountBackupError::InvalidBackup(format!("{}: {}", path.display(), e))
+            })?;
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push(BackupStateFile {
+                path: relative,
+                data: general_purpose::STANDARD.encode(fs::read(&path)?),
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Resolves a backed up file path below `root`, rejecting paths that would escape it
+fn state_file_path(root: &Path, relative: &str) -> Result<PathBuf> {
+    let relative = Path::new(relative);
+    let is_safe = !relative.as_os_str().is_empty()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !is_safe {
+        return Err(AccountBackupError::InvalidBackup(format!(
+            "Invalid MLS state path: {}",
+            relative.display()
+        )));
+    }
+    Ok(root.join(relative))
+}
+
+/// Replaces the contents of `root` with the backed up files
+fn write_state_files(root: &Path, files: &[BackupStateFile]) -> Result<()> {
+    // Validate everything before touching the existing state
+    let files = files
+        .iter()
+        .map(|file| {
+            Ok((
+                state_file_path(root, &file.path)?,
+                general_purpose::STANDARD.decode(&file.data)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Write the new state next to the old one and swap them, so a crash midway leaves the old
+    // state intact
+    let staging = root.with_extension("restoring");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+    for (path, data) in files {
+        let path = staging.join(path.strip_prefix(root).unwrap_or(&path));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        atomic_file::write(&path, data)?;
+    }
+
+    let previous = root.with_extension("previous");
+    if previous.exists() {
+        fs::remove_dir_all(&previous)?;
+    }
+    if root.exists() {
+        fs::rename(root, &previous)?;
+    }
+    fs::rename(&staging, root)?;
+    if previous.exists() {
+        fs::remove_dir_all(&previous)?;
+    }
+    Ok(())
+}
+
+/// Copies an identity's MLS state, for putting it back with [`restore_mls_state`]. The caller
+/// holds the `nostr_mls` lock so the state isn't written to while it's copied.
+pub(crate) fn snapshot_mls_state(
+    data_dir: &Path,
+    pubkey: &PublicKey,
+) -> Result<Vec<BackupStateFile>> {
+    read_state_files(&mls_storage_dir(data_dir, pubkey))
+}
+
+/// Writes an identity's MLS state into another data dir, for a `nostr_mls` opened on it
+pub(crate) fn write_mls_state(
+    data_dir: &Path,
+    pubkey: &PublicKey,
+    files: &[BackupStateFile],
+) -> Result<()> {
+    write_state_files(&mls_storage_dir(data_dir, pubkey), files)
+}
+
+/// Replaces an identity's MLS state with the given files
+pub(crate) async fn restore_mls_state(
+    pubkey: &PublicKey,
+    files: &[BackupStateFile],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await.ok();
+    let mut nostr_mls = wn.nostr_mls.lock().await;
+    // Release the identity's storage before it's replaced, then reopen the active identity
+    *nostr_mls = NostrMls::new(wn.data_dir.clone(), None);
+    let result = write_state_files(&mls_storage_dir(&wn.data_dir, pubkey), files);
+    *nostr_mls = NostrMls::new(
+        wn.data_dir.clone(),
+        active_pubkey.map(|pubkey| pubkey.to_hex()),
+    );
+    result
+}
+
+/// Collects an account's keys, groups and MLS state into a backup
+pub async fn create(pubkey: &PublicKey, wn: tauri::State<'_, Whitenoise>) -> Result<AccountBackup> {
+    let account = Account::find_by_pubkey(pubkey, wn.clone()).await?;
+    let keys = account.keys(wn.clone())?;
+
+    let mut relays = BTreeMap::new();
+    for relay_type in [RelayType::Nostr, RelayType::Inbox, RelayType::KeyPackage] {
+        relays.insert(
+            String::from(relay_type),
+            account.relays(relay_type, wn.clone()).await?,
+        );
+    }
+
+    let mut groups = Vec::new();
+    for group in account.groups(wn.clone()).await? {
+        let group_relays = sqlx::query_scalar::<_, String>(
+            "SELECT url FROM group_relays WHERE group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&group.mls_group_id)
+        .bind(pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+        let export_secrets =
+            secrets_store::get_export_secrets_for_group(&group.mls_group_id, &wn.data_dir)?
+                .into_iter()
+                .map(|(epoch, secret)| BackupExportSecret {
+                    epoch,
+                    secret: secret.to_string(),
+                })
+                .collect();
+        groups.push(BackupGroup {
+            group,
+            relays: group_relays,
+            export_secrets,
+        });
+    }
+
+    // Hold the MLS lock so the state isn't written to while it's copied
+    let mls_state = {
+        let _nostr_mls = wn.nostr_mls.lock().await;
+        snapshot_mls_state(&wn.data_dir, pubkey)?
+    };
+
+    Ok(AccountBackup {
+        version: BACKUP_VERSION,
+        created_at: Timestamp::now(),
+        private_key: keys.secret_key().to_secret_hex(),
+        account,
+        relays,
+        groups,
+        mls_state,
+    })
+}
+
+/// Writes an encrypted backup of an account to the backups directory
+///
+/// # Returns
+/// * `Ok(PathBuf)` - The path of the backup file
+pub async fn export(
+    pubkey: &PublicKey,
+    passphrase: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<PathBuf> {
+    validate_passphrase(passphrase)?;
+    if wn.app_lock.lock().await.locked {
+        return Err(AccountError::AppLocked.into());
+    }
+    let backup = create(pubkey, wn.clone()).await?;
+    let sealed = seal(&backup, passphrase)?;
+
+    let backups_dir = wn.data_dir.join(BACKUPS_DIR);
+    fs::create_dir_all(&backups_dir)?;
+    let path = backups_dir.join(format!(
+        "whitenoise-{}-{}.{}",
+        &pubkey.to_hex()[..8],
+        backup.created_at.as_u64(),
+        BACKUP_EXTENSION
+    ));
+    atomic_file::write(&path, sealed)?;
+
+    tracing::info!(
+        target: "whitenoise::account_backup::export",
+        "Exported backup of {} with {} groups to {:?}",
+        pubkey.to_hex(),
+        backup.groups.len(),
+        path
+    );
+    Ok(path)
+}
+
+/// Restores an account from a backup file. The account isn't made active.
+///
+/// The MLS state in the backup replaces the account's current state, see [`restore`].
+pub async fn import(
+    path: &Path,
+    passphrase: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account> {
+    let backup = open(&fs::read(path)?, passphrase)?;
+    restore(&backup, wn).await
+}
+
+/// Drops what an existing account keeps alongside the MLS state that a restore replaces: the
+/// export secrets and quarantined messages of its groups. Groups that aren't in the backup lose
+/// their MLS state and are marked inactive; the others take the backup's epoch.
+async fn clear_replaced_state(
+    pubkey: &PublicKey,
+    backup: &AccountBackup,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let account = Account::find_by_pubkey(pubkey, wn.clone()).await?;
+    for group in account.groups(wn.clone()).await? {
+        secrets_store::remove_export_secrets_for_group(&group.mls_group_id, &wn.data_dir)?;
+        let backed_up = backup
+            .groups
+            .iter()
+            .find(|backup_group| backup_group.group.mls_group_id == group.mls_group_id);
+        let (epoch, state) = match backed_up {
+            Some(backup_group) => (backup_group.group.epoch, backup_group.group.state.clone()),
+            None => (group.epoch, GroupState::Inactive),
+        };
+        sqlx::query(
+            "UPDATE groups SET epoch = ?, state = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(epoch as i64)
+        .bind(String::from(state))
+        .bind(&group.mls_group_id)
+        .bind(pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+    }
+    sqlx::query("DELETE FROM quarantined_events WHERE account_pubkey = ?")
+        .bind(pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+    Ok(())
+}
+
+/// Restores an account from a decrypted backup, see [`import`]
+///
+/// If the account is already on the device, the backup's MLS state replaces its current one,
+/// so the state kept alongside it is cleared first. Groups the account already has keep their
+/// messages and settings.
+pub async fn restore(backup: &AccountBackup, wn: tauri::State<'_, Whitenoise>) -> Result<Account> {
+    let keys = Keys::parse(&backup.private_key)?;
+    if keys.public_key() != backup.account.pubkey {
+        return Err(AccountBackupError::InvalidBackup(
+            "Private key doesn't match the account".to_string(),
+        ));
+    }
+    let pubkey = keys.public_key();
+
+    let account = match Account::find_by_pubkey(&pubkey, wn.clone()).await {
+        Ok(existing) => {
+            if !backup.mls_state.is_empty() {
+                clear_replaced_state(&pubkey, backup, wn.clone()).await?;
+            }
+            existing
+        }
+        Err(_) => {
+            let mut account = backup.account.clone();
+            account.active = false;
+            account.last_used = Timestamp::now();
+            account.save(wn.clone()).await?
+        }
+    };
+    secrets_store::store_private_key(&keys, &wn.data_dir)?;
+
+    for (relay_type, relays) in &backup.relays {
+        account
+            .update_relays(RelayType::from(relay_type.clone()), relays, wn.clone())
+            .await?;
+    }
+
+    for backup_group in &backup.groups {
+        let group = &backup_group.group;
+        for export_secret in &backup_group.export_secrets {
+            secrets_store::store_mls_export_secret(
+                group.mls_group_id.clone(),
+                export_secret.epoch,
+                export_secret.secret.clone(),
+                &wn.data_dir,
+            )?;
+        }
+
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM groups WHERE mls_group_id = ? AND account_pubkey = ?)",
+        )
+        .bind(&group.mls_group_id)
+        .bind(pubkey.to_hex())
+        .fetch_one(&wn.database.pool)
+        .await?;
+        if exists {
+            continue;
+        }
+
+        group.save(wn.clone()).await?;
+        for relay in &backup_group.relays {
+            sqlx::query("INSERT OR REPLACE INTO group_relays (url, relay_type, account_pubkey, group_id) VALUES (?, ?, ?, ?)")
+                .bind(relay)
+                .bind("group")
+                .bind(pubkey.to_hex())
+                .bind(&group.mls_group_id)
+                .execute(&wn.database.pool)
+                .await?;
+        }
+    }
+
+    if !backup.mls_state.is_empty() {
+        restore_mls_state(&pubkey, &backup.mls_state, wn.clone()).await?;
+    }
+
+    tracing::info!(
+        target: "whitenoise::account_backup::import",
+        "Imported backup of {} with {} groups",
+        pubkey.to_hex(),
+        backup.groups.len()
+    );
+    Ok(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::{AccountOnboarding, AccountSettings};
+    use tempfile::TempDir;
+
+    fn backup() -> AccountBackup {
+        let keys = Keys::generate();
+        AccountBackup {
+            version: BACKUP_VERSION,
+            created_at: Timestamp::now(),
+            private_key: keys.secret_key().to_secret_hex(),
+            account: Account {
+                pubkey: keys.public_key(),
+                metadata: Metadata::default(),
+                settings: AccountSettings::default(),
+                onboarding: AccountOnboarding::default(),
+                last_used: Timestamp::now(),
+                last_synced: Timestamp::zero(),
+                active: true,
+                key_package_ids: Vec::new(),
+            },
+            relays: BTreeMap::new(),
+            groups: Vec::new(),
+            mls_state: vec![BackupStateFile {
+                path: "db/state".to_string(),
+                data: general_purpose::STANDARD.encode(b"state"),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let backup = backup();
+        let sealed = seal(&backup, "correct horse").unwrap();
+        let opened = open(&sealed, "correct horse").unwrap();
+
+        assert_eq!(opened.private_key, backup.private_key);
+        assert_eq!(opened.account.pubkey, backup.account.pubkey);
+        assert_eq!(opened.mls_state, backup.mls_state);
+    }
+
+    #[test]
+    fn test_open_with_wrong_passphrase() {
+        let sealed = seal(&backup(), "correct horse").unwrap();
+        assert!(matches!(
+            open(&sealed, "battery staple"),
+            Err(AccountBackupError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn test_open_legacy_file() {
+        let backup = backup();
+        let salt = [7u8; 16];
+        let nonce = [9u8; 12];
+        let key = stretch("correct horse", &salt, 10);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                serde_json::to_vec(&backup).unwrap().as_slice(),
+            )
+            .unwrap();
+        let mut file = BackupFile {
+            version: LEGACY_FILE_VERSION,
+            salt: hex::encode(salt),
+            kdf: None,
+            iterations: Some(10),
+            nonce: hex::encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        };
+
+        let opened = open(&serde_json::to_vec(&file).unwrap(), "correct horse").unwrap();
+        assert_eq!(opened.private_key, backup.private_key);
+
+        // A crafted round count is rejected before any work is done
+        file.iterations = Some(u32::MAX);
+        assert!(matches!(
+            open(&serde_json::to_vec(&file).unwrap(), "correct horse"),
+            Err(AccountBackupError::InvalidBackup(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_rejects_unbounded_kdf_params() {
+        let sealed = seal(&backup(), "correct horse").unwrap();
+        let mut file: BackupFile = serde_json::from_slice(&sealed).unwrap();
+        assert_eq!(file.version, FILE_VERSION);
+        file.kdf = Some(KdfParams {
+            memory_kib: u32::MAX,
+            iterations: 1,
+            parallelism: 1,
+        });
+        assert!(matches!(
+            open(&serde_json::to_vec(&file).unwrap(), "correct horse"),
+            Err(AccountBackupError::KdfError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_passphrase() {
+        assert!(validate_passphrase("short").is_err());
+        assert!(validate_passphrase("long enough").is_ok());
+    }
+
+    #[test]
+    fn test_state_files_round_trip() {
+        let source = TempDir::new().unwrap();
+        fs::create_dir_all(source.path().join("db")).unwrap();
+        fs::write(source.path().join("db/state"), b"state").unwrap();
+        fs::write(source.path().join("conf"), b"conf").unwrap();
+
+        let files = read_state_files(source.path()).unwrap();
+        assert_eq!(
+            files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+            vec!["conf", "db/state"]
+        );
+
+        let target = TempDir::new().unwrap();
+        let root = target.path().join("restored");
+        write_state_files(&root, &files).unwrap();
+        assert_eq!(fs::read(root.join("db/state")).unwrap(), b"state");
+        assert_eq!(fs::read(root.join("conf")).unwrap(), b"conf");
+    }
+
+    #[test]
+    fn test_state_file_path_rejects_escapes() {
+        let root = Path::new("/data/mls");
+        assert!(state_file_path(root, "../secrets").is_err());
+        assert!(state_file_path(root, "/etc/passwd").is_err());
+        assert!(state_file_path(root, "").is_err());
+        assert!(state_file_path(root, "db/state").is_ok());
+    }
+}
@@ -1,15 +1,31 @@
+use crate::app_lock;
+use crate::capture_protection;
+use crate::content_filters::ContentFilterSettings;
 use crate::database::DatabaseError;
-use crate::groups::{Group, GroupRow};
+use crate::epoch_recovery;
+use crate::group_templates::GroupTemplate;
+use crate::groups::{Group, GroupRow, GroupState};
+use crate::integrity;
 use crate::invites::{Invite, InviteRow};
+use crate::key_packages;
+use crate::media::MediaServerSettings;
 use crate::nostr_manager;
+use crate::notifications::NotificationPreferences;
+use crate::profiling::{self, OperationKind};
+use crate::recovery::RecoveryContacts;
 use crate::relays::RelayType;
 use crate::secrets_store;
+use crate::settings_sync::SettingScope;
+use crate::sync_scheduler::SyncSchedule;
+use crate::sync_throttle::SyncPolicy;
 use crate::Whitenoise;
 use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 use tauri::Emitter;
 use thiserror::Error;
+use zeroize::Zeroizing;
 
 #[derive(Error, Debug)]
 pub enum AccountError {
@@ -31,6 +47,9 @@ pub enum AccountError {
     #[error("No active account found")]
     NoActiveAccount,
 
+    #[error("App is locked")]
+    AppLocked,
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
@@ -50,6 +69,70 @@ pub struct AccountSettings {
     pub dark_theme: bool,
     pub dev_mode: bool,
     pub lockdown_mode: bool,
+    /// When enabled, only welcomes and messages from contacts are processed
+    #[serde(default)]
+    pub whitelist_only_mode: bool,
+    /// When enabled, marking a group as read lets the other members know
+    #[serde(default)]
+    pub send_read_receipts: bool,
+    /// Words and patterns masked in outgoing and/or displayed messages
+    #[serde(default)]
+    #[sqlx(json)]
+    pub content_filter: ContentFilterSettings,
+    /// Where attachments are uploaded
+    #[serde(default)]
+    #[sqlx(json)]
+    pub media_server: MediaServerSettings,
+    /// Relays key packages and welcomes are published to when the primary relays fail
+    #[serde(default)]
+    #[sqlx(json)]
+    pub fallback_relays: Vec<String>,
+    /// When enabled, joined groups and new epoch secrets are shared with the account's other
+    /// devices through encrypted events to itself
+    #[serde(default)]
+    pub device_sync: bool,
+    /// Minutes without activity after which the app locks itself, if an app passphrase is set
+    #[serde(default)]
+    pub auto_lock_minutes: Option<u32>,
+    /// How much syncing is throttled on battery power and metered connections
+    #[serde(default)]
+    #[sqlx(json)]
+    pub sync_policy: SyncPolicy,
+    /// How often each background sync task runs
+    #[serde(default)]
+    #[sqlx(json)]
+    pub sync_schedule: SyncSchedule,
+    /// How many key packages are kept published; consumed ones are replaced up to this number
+    #[serde(default = "default_key_package_pool_size")]
+    pub key_package_pool_size: u32,
+    /// Presets for creating groups
+    #[serde(default)]
+    #[sqlx(json)]
+    pub group_templates: Vec<GroupTemplate>,
+    /// Contacts that can help recover the account after losing its key
+    #[serde(default)]
+    #[sqlx(json)]
+    pub recovery_contacts: RecoveryContacts,
+    /// Quiet hours and whether invites notify
+    #[serde(default)]
+    #[sqlx(json)]
+    pub notifications: NotificationPreferences,
+    /// Settings this device neither shares with nor takes from the account's other devices
+    #[serde(default)]
+    #[sqlx(json)]
+    pub device_local_settings: Vec<SettingScope>,
+    /// How many epochs before a group's current one its export secrets are kept for, to decrypt
+    /// messages that arrive late
+    #[serde(default = "default_export_secret_retention_epochs")]
+    pub export_secret_retention_epochs: u32,
+}
+
+fn default_key_package_pool_size() -> u32 {
+    key_packages::DEFAULT_KEY_PACKAGE_POOL_SIZE
+}
+
+fn default_export_secret_retention_epochs() -> u32 {
+    epoch_recovery::DEFAULT_EXPORT_SECRET_RETENTION_EPOCHS
 }
 
 impl Default for AccountSettings {
@@ -58,6 +141,21 @@ impl Default for AccountSettings {
             dark_theme: true,
             dev_mode: false,
             lockdown_mode: false,
+            whitelist_only_mode: false,
+            send_read_receipts: false,
+            content_filter: ContentFilterSettings::default(),
+            media_server: MediaServerSettings::default(),
+            fallback_relays: Vec::new(),
+            device_sync: false,
+            auto_lock_minutes: None,
+            sync_policy: SyncPolicy::default(),
+            sync_schedule: SyncSchedule::default(),
+            key_package_pool_size: default_key_package_pool_size(),
+            group_templates: Vec::new(),
+            recovery_contacts: RecoveryContacts::default(),
+            notifications: NotificationPreferences::default(),
+            device_local_settings: Vec::new(),
+            export_secret_retention_epochs: default_export_secret_retention_epochs(),
         }
     }
 }
@@ -79,6 +177,7 @@ pub struct AccountRow {
     pub last_used: u64,
     pub last_synced: u64,
     pub active: bool,
+    pub key_package_ids: String, // JSON string
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -90,6 +189,9 @@ pub struct Account {
     pub last_used: Timestamp,
     pub last_synced: Timestamp,
     pub active: bool,
+    /// IDs of the key package events currently published for the account
+    #[serde(default)]
+    pub key_package_ids: Vec<EventId>,
 }
 
 impl Account {
@@ -104,6 +206,7 @@ impl Account {
             last_used: Timestamp::now(),
             last_synced: Timestamp::zero(),
             active: false,
+            key_package_ids: Vec::new(),
         };
         let account = account.save(wn.clone()).await?;
 
@@ -186,6 +289,7 @@ impl Account {
             last_used: Timestamp::now(),
             last_synced: Timestamp::zero(),
             active: false,
+            key_package_ids: Vec::new(),
         };
 
         tracing::debug!(target: "whitenoise::accounts", "Saving new account to database");
@@ -221,6 +325,20 @@ impl Account {
         Ok(account)
     }
 
+    /// Builds an account from its database row
+    fn from_row(row: AccountRow) -> Result<Self> {
+        Ok(Self {
+            pubkey: PublicKey::parse(row.pubkey.as_str())?,
+            metadata: serde_json::from_str(&row.metadata)?,
+            settings: serde_json::from_str(&row.settings)?,
+            onboarding: serde_json::from_str(&row.onboarding)?,
+            last_used: Timestamp::from(row.last_used),
+            last_synced: Timestamp::from(row.last_synced),
+            active: row.active,
+            key_package_ids: serde_json::from_str(&row.key_package_ids)?,
+        })
+    }
+
     /// Finds an account by its public key
     pub async fn find_by_pubkey(
         pubkey: &PublicKey,
@@ -233,38 +351,48 @@ impl Account {
             .fetch_one(&mut *txn)
             .await?;
 
-        Ok(Self {
-            pubkey: PublicKey::parse(row.pubkey.as_str())?,
-            metadata: serde_json::from_str(&row.metadata)?,
-            settings: serde_json::from_str(&row.settings)?,
-            onboarding: serde_json::from_str(&row.onboarding)?,
-            last_used: Timestamp::from(row.last_used),
-            last_synced: Timestamp::from(row.last_synced),
-            active: row.active,
-        })
+        Self::from_row(row)
     }
 
-    /// Returns all accounts
+    /// Returns all visible accounts
+    ///
+    /// The duress decoy account is hidden, unless the app was unlocked with the duress
+    /// passphrase, in which case it's the only account returned.
     pub async fn all(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Self>> {
+        let decoy_active = wn.app_lock.lock().await.decoy_active;
+        let decoy_pubkey = app_lock::decoy_pubkey(&wn.data_dir);
+        let accounts = Self::all_including_hidden(wn.clone()).await?;
+        Ok(accounts
+            .into_iter()
+            .filter(|account| (Some(account.pubkey) == decoy_pubkey) == decoy_active)
+            .collect())
+    }
+
+    /// Returns all accounts, including the duress decoy account
+    pub async fn all_including_hidden(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Self>> {
         let mut txn = wn.database.pool.begin().await?;
 
         let iter = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts")
             .fetch_all(&mut *txn)
             .await?;
 
-        iter.into_iter()
-            .map(|row| -> Result<Self> {
-                Ok(Self {
-                    pubkey: PublicKey::parse(row.pubkey.as_str())?,
-                    metadata: serde_json::from_str(&row.metadata)?,
-                    settings: serde_json::from_str(&row.settings)?,
-                    onboarding: serde_json::from_str(&row.onboarding)?,
-                    last_used: Timestamp::from(row.last_used),
-                    last_synced: Timestamp::from(row.last_synced),
-                    active: row.active,
-                })
+        // A corrupt account shouldn't hide the others, verify_data_integrity quarantines it
+        Ok(iter
+            .into_iter()
+            .filter_map(|row| {
+                let pubkey = row.pubkey.clone();
+                Self::from_row(row)
+                    .map_err(|e| {
+                        tracing::error!(
+                            target: "whitenoise::accounts::all_including_hidden",
+                            "Skipping corrupt account {}: {}",
+                            pubkey,
+                            e
+                        )
+                    })
+                    .ok()
             })
-            .collect::<Result<Vec<_>>>()
+            .collect())
     }
 
     /// Returns the currently active account
@@ -279,15 +407,7 @@ impl Account {
             .await?;
 
         match row {
-            Some(row) => Ok(Self {
-                pubkey: PublicKey::parse(row.pubkey.as_str())?,
-                metadata: serde_json::from_str(&row.metadata)?,
-                settings: serde_json::from_str(&row.settings)?,
-                onboarding: serde_json::from_str(&row.onboarding)?,
-                last_used: Timestamp::from(row.last_used),
-                last_synced: Timestamp::from(row.last_synced),
-                active: row.active,
-            }),
+            Some(row) => Self::from_row(row),
             None => Err(AccountError::NoActiveAccount),
         }
     }
@@ -334,6 +454,10 @@ impl Account {
             self.pubkey.to_hex()
         );
 
+        if wn.app_lock.lock().await.locked {
+            return Err(AccountError::AppLocked);
+        }
+
         let mut txn = wn.database.pool.begin().await?;
 
         // First set all accounts to inactive
@@ -385,6 +509,20 @@ impl Account {
             self.pubkey.to_hex()
         );
 
+        capture_protection::refresh(&self.pubkey, wn.clone(), app_handle).await;
+
+        // Quarantined messages can only be retried while their account's MLS state is loaded
+        let retry_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = epoch_recovery::retry(&retry_handle).await {
+                tracing::warn!(
+                    target: "whitenoise::accounts::set_active",
+                    "Failed to retry quarantined messages: {}",
+                    e
+                );
+            }
+        });
+
         app_handle.emit("account_changed", ())?;
 
         tracing::debug!(
@@ -407,19 +545,8 @@ impl Account {
 
         iter.into_iter()
             .map(|row| -> Result<Group> {
-                Ok(Group {
-                    mls_group_id: row.mls_group_id,
-                    account_pubkey: PublicKey::parse(row.account_pubkey.as_str())?,
-                    nostr_group_id: row.nostr_group_id,
-                    name: row.name,
-                    description: row.description,
-                    admin_pubkeys: serde_json::from_str(&row.admin_pubkeys)?,
-                    last_message_id: row.last_message_id,
-                    last_message_at: row.last_message_at.map(Timestamp::from),
-                    group_type: row.group_type.into(),
-                    epoch: row.epoch,
-                    state: row.state.into(),
-                })
+                let account_pubkey = PublicKey::parse(row.account_pubkey.as_str())?;
+                Ok(Group::from_row(row, account_pubkey)?)
             })
             .collect::<Result<Vec<_>>>()
     }
@@ -452,16 +579,19 @@ impl Account {
                     member_count: row.member_count,
                     state: row.state.into(),
                     outer_event_id: row.outer_event_id,
+                    activity: None,
                 })
             })
             .collect::<Result<Vec<_>>>()
     }
 
+    /// The Nostr group IDs of the groups the account is still in, for subscriptions
     pub async fn nostr_group_ids(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<String>> {
         Ok(self
             .groups(wn)
             .await?
             .iter()
+            .filter(|g| matches!(g.state, GroupState::Active))
             .map(|g| g.nostr_group_id.clone())
             .collect())
     }
@@ -535,29 +665,44 @@ impl Account {
             self.pubkey.to_hex()
         );
 
+        let started = Instant::now();
         let mut txn = wn.database.pool.begin().await?;
 
+        let metadata = serde_json::to_string(&self.metadata)?;
+        let settings = serde_json::to_string(&self.settings)?;
+        let onboarding = serde_json::to_string(&self.onboarding)?;
+
         let result = sqlx::query(
-            "INSERT INTO accounts (pubkey, metadata, settings, onboarding, last_used, last_synced, active)
-             VALUES (?, ?, ?, ?, ?, ?, ?)
+            "INSERT INTO accounts (pubkey, metadata, settings, onboarding, last_used, last_synced, active, key_package_ids)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
              ON CONFLICT(pubkey) DO UPDATE SET
                 metadata = excluded.metadata,
                 settings = excluded.settings,
                 onboarding = excluded.onboarding,
                 last_used = excluded.last_used,
                 last_synced = excluded.last_synced,
-                active = excluded.active"
+                active = excluded.active,
+                key_package_ids = excluded.key_package_ids"
         )
         .bind(self.pubkey.to_hex())
-        .bind(&serde_json::to_string(&self.metadata)?)
-        .bind(&serde_json::to_string(&self.settings)?)
-        .bind(&serde_json::to_string(&self.onboarding)?)
+        .bind(&metadata)
+        .bind(&settings)
+        .bind(&onboarding)
         .bind(self.last_used.to_string())
         .bind(self.last_synced.to_string())
         .bind(self.active)
+        .bind(&serde_json::to_string(&self.key_package_ids)?)
         .execute(&mut *txn)
         .await?;
 
+        integrity::record_checksum(
+            &mut *txn,
+            integrity::ACCOUNTS_TABLE,
+            &self.pubkey.to_hex(),
+            &integrity::account_checksum(&metadata, &settings, &onboarding),
+        )
+        .await?;
+
         tracing::debug!(
             target: "whitenoise::accounts::save",
             "Query executed. Rows affected: {}",
@@ -565,6 +710,11 @@ impl Account {
         );
 
         txn.commit().await?;
+        profiling::record(
+            "db.save_account",
+            OperationKind::Database,
+            started.elapsed(),
+        );
 
         tracing::debug!(
             target: "whitenoise::accounts::save",
@@ -575,6 +725,62 @@ impl Account {
         Ok(self.clone())
     }
 
+    /// Changes the account's settings without writing back the rest of this snapshot
+    ///
+    /// The settings are read again inside the transaction, so changes made since the account
+    /// was loaded, e.g. a sync moving `last_synced` or a setting synced from another device,
+    /// aren't overwritten. Returns the account as stored afterwards.
+    pub async fn update_settings<F>(
+        &self,
+        change: F,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Self>
+    where
+        F: FnOnce(&mut AccountSettings),
+    {
+        let pubkey = self.pubkey.to_hex();
+        let mut txn = wn.database.pool.begin().await?;
+
+        let row = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE pubkey = ?")
+            .bind(&pubkey)
+            .fetch_one(&mut *txn)
+            .await?;
+        let (metadata, onboarding) = (row.metadata.clone(), row.onboarding.clone());
+        let mut account = Self::from_row(row)?;
+        change(&mut account.settings);
+        let settings = serde_json::to_string(&account.settings)?;
+
+        sqlx::query("UPDATE accounts SET settings = ? WHERE pubkey = ?")
+            .bind(&settings)
+            .bind(&pubkey)
+            .execute(&mut *txn)
+            .await?;
+        integrity::record_checksum(
+            &mut *txn,
+            integrity::ACCOUNTS_TABLE,
+            &pubkey,
+            &integrity::account_checksum(&metadata, &settings, &onboarding),
+        )
+        .await?;
+
+        txn.commit().await?;
+        Ok(account)
+    }
+
+    /// Records how far the account is synced, without writing back the rest of this snapshot
+    pub async fn update_last_synced(
+        &self,
+        last_synced: Timestamp,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE accounts SET last_synced = ? WHERE pubkey = ?")
+            .bind(last_synced.to_string())
+            .bind(self.pubkey.to_hex())
+            .execute(&wn.database.pool)
+            .await?;
+        Ok(())
+    }
+
     /// Removes the account from the database
     pub async fn remove(
         &self,
@@ -617,6 +823,8 @@ impl Account {
 
         // Remove the old account's private key from the secrets store
         secrets_store::remove_private_key_for_pubkey(&hex_pubkey, &wn.data_dir)?;
+        // and the export secrets of its groups from memory
+        secrets_store::clear_secret_cache();
 
         // Update Nostr client & Nostr MLS
         let account = Self::get_active(wn.clone()).await?;
@@ -702,12 +910,12 @@ impl Account {
     /// Retrieves the Nostr Wallet Connect URI for this account
     ///
     /// # Returns
-    /// * `Result<Option<String>>` - Some(uri) if a URI is stored, None if no URI is stored,
-    ///   or an error if the operation fails
+    /// * `Result<Option<Zeroizing<String>>>` - Some(uri) if a URI is stored, None if no URI is
+    ///   stored, or an error if the operation fails
     pub fn get_nostr_wallet_connect_uri(
         &self,
         wn: tauri::State<'_, Whitenoise>,
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<Zeroizing<String>>> {
         secrets_store::get_nostr_wallet_connect_uri(&self.pubkey.to_hex(), &wn.data_dir)
             .map_err(AccountError::SecretsStoreError)
     }
@@ -0,0 +1,306 @@
+//! Encrypted archives of the whole app, for moving to another device.
+//!
+//! An archive holds an account backup (see `account_backup`) for every account on the device,
+//! so keys, groups, export secrets and MLS state move along and group membership survives, plus
+//! each account's message transcripts and Nostr Wallet Connect URI. It's encrypted the same way
+//! as account backups, with a key derived from a passphrase the user picks with Argon2id.
+
+use crate::account_backup::{self, AccountBackup, AccountBackupError};
+use crate::accounts::{Account, AccountError};
+use crate::atomic_file;
+use crate::media_library;
+use crate::messages::MessageRow;
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+
+/// Identifies app data archives, so account backups aren't mistaken for them
+const ARCHIVE_FORMAT: &str = "whitenoise-app-data";
+
+/// Version of the archive format
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Largest archive that's imported, since it's read and decrypted in memory
+const MAX_ARCHIVE_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum AppDataError {
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+
+    #[error("Invalid archive: {0}")]
+    InvalidArchive(String),
+
+    #[error("Unsupported archive version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Backup error: {0}")]
+    AccountBackupError(#[from] AccountBackupError),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, AppDataError>;
+
+/// An account and the data that isn't part of its backup
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedAccount {
+    pub backup: AccountBackup,
+    /// Every stored message of the account's groups
+    pub messages: Vec<MessageRow>,
+    pub nostr_wallet_connect_uri: Option<String>,
+}
+
+/// The decrypted contents of an archive
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppDataArchive {
+    pub format: String,
+    pub version: u32,
+    pub created_at: Timestamp,
+    /// The account that was active when the archive was created
+    pub active_account: Option<PublicKey>,
+    pub accounts: Vec<ArchivedAccount>,
+}
+
+/// Encrypts an archive with a key stretched from the passphrase
+pub fn seal(archive: &AppDataArchive, passphrase: &str) -> Result<Vec<u8>> {
+    Ok(account_backup::encrypt(
+        &serde_json::to_vec(archive)?,
+        passphrase,
+    )?)
+}
+
+/// Decrypts an archive file's contents
+pub fn open(data: &[u8], passphrase: &str) -> Result<AppDataArchive> {
+    let archive: AppDataArchive =
+        serde_json::from_slice(&account_backup::decrypt(data, passphrase)?)
+            .map_err(|e| AppDataError::InvalidArchive(e.to_string()))?;
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(AppDataError::InvalidArchive(format!(
+            "Unknown format: {}",
+            archive.format
+        )));
+    }
+    if archive.version != ARCHIVE_VERSION {
+        return Err(AppDataError::UnsupportedVersion(archive.version));
+    }
+    Ok(archive)
+}
+
+/// Collects every account on the device into an archive
+pub async fn create(wn: tauri::State<'_, Whitenoise>) -> Result<AppDataArchive> {
+    let mut accounts = Vec::new();
+    // Under duress this only sees the decoy account, and otherwise leaves it out
+    for account in Account::all(wn.clone()).await? {
+        let backup = account_backup::create(&account.pubkey, wn.clone()).await?;
+        let messages =
+            sqlx::query_as::<_, MessageRow>("SELECT * FROM messages WHERE account_pubkey = ?")
+                .bind(account.pubkey.to_hex())
+                .fetch_all(&wn.database.pool)
+                .await?;
+        let nostr_wallet_connect_uri =
+            secrets_store::get_nostr_wallet_connect_uri(&account.pubkey.to_hex(), &wn.data_dir)?
+                .map(|uri| uri.to_string());
+        accounts.push(ArchivedAccount {
+            backup,
+            messages,
+            nostr_wallet_connect_uri,
+        });
+    }
+
+    Ok(AppDataArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        created_at: Timestamp::now(),
+        active_account: Account::get_active_pubkey(wn.clone()).await.ok(),
+        accounts,
+    })
+}
+
+/// Writes an encrypted archive of the app's data to `path`
+///
+/// # Returns
+/// * `Ok(PathBuf)` - The path of the archive
+pub async fn export(
+    path: &Path,
+    passphrase: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<PathBuf> {
+    account_backup::validate_passphrase(passphrase)?;
+    if !path.is_absolute() || path.is_dir() {
+        return Err(AppDataError::InvalidPath(format!(
+            "{} isn't an absolute file path",
+            path.display()
+        )));
+    }
+    if wn.app_lock.lock().await.locked {
+        return Err(AccountError::AppLocked.into());
+    }
+    let archive = create(wn.clone()).await?;
+    atomic_file::write(path, seal(&archive, passphrase)?)?;
+
+    tracing::info!(
+        target: "whitenoise::app_data::export",
+        "Exported {} accounts to {:?}",
+        archive.accounts.len(),
+        path
+    );
+    Ok(path.to_path_buf())
+}
+
+/// Reads an archive, refusing files larger than [`MAX_ARCHIVE_SIZE`]
+async fn read_archive(path: &Path) -> Result<Vec<u8>> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut data = Vec::new();
+    file.take(MAX_ARCHIVE_SIZE + 1)
+        .read_to_end(&mut data)
+        .await?;
+    if data.len() as u64 > MAX_ARCHIVE_SIZE {
+        return Err(AppDataError::InvalidArchive(format!(
+            "Archives can't be larger than {} MiB",
+            MAX_ARCHIVE_SIZE / 1024 / 1024
+        )));
+    }
+    Ok(data)
+}
+
+/// Stores archived messages, skipping the ones that are already stored, and indexes the media
+/// of the new ones
+async fn restore_messages(
+    pubkey: &PublicKey,
+    messages: &[MessageRow],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut txn = wn.database.pool.begin().await?;
+    for message in messages {
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO messages (
+                event_id, account_pubkey, author_pubkey, event_kind, mls_group_id, created_at,
+                content, tags, event, outer_event_id, tokens, deleted_at, edited_at,
+                author_migrated_to, origin_group_id, expires_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&message.event_id)
+        .bind(&message.account_pubkey)
+        .bind(&message.author_pubkey)
+        .bind(i64::from(message.event_kind))
+        .bind(&message.mls_group_id)
+        .bind(message.created_at as i64)
+        .bind(&message.content)
+        .bind(&message.tags)
+        .bind(&message.event)
+        .bind(&message.outer_event_id)
+        .bind(&message.tokens)
+        .bind(message.deleted_at.map(|t| t as i64))
+        .bind(message.edited_at.map(|t| t as i64))
+        .bind(&message.author_migrated_to)
+        .bind(&message.origin_group_id)
+        .bind(message.expires_at.map(|t| t as i64))
+        .execute(&mut *txn)
+        .await?;
+        if inserted.rows_affected() > 0 {
+            media_library::index_stored_message(&mut *txn, pubkey, message).await?;
+        }
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Restores every account in an archive. None of them is made active.
+///
+/// Accounts and groups already on the device are kept, though the archived MLS state replaces
+/// theirs, see `account_backup::restore`. Refused while the app is locked.
+///
+/// # Returns
+/// * `Ok((Vec<Account>, Option<PublicKey>))` - The restored accounts, and the one that was
+///   active when the archive was created
+pub async fn import(
+    path: &Path,
+    passphrase: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(Vec<Account>, Option<PublicKey>)> {
+    if wn.app_lock.lock().await.locked {
+        return Err(AccountError::AppLocked.into());
+    }
+    let archive = open(&read_archive(path).await?, passphrase)?;
+
+    let mut accounts = Vec::new();
+    for archived in &archive.accounts {
+        let pubkey = archived.backup.account.pubkey;
+        if archived
+            .messages
+            .iter()
+            .any(|message| message.account_pubkey != pubkey.to_hex())
+        {
+            return Err(AppDataError::InvalidArchive(format!(
+                "Messages of {} belong to another account",
+                pubkey.to_hex()
+            )));
+        }
+        let account = account_backup::restore(&archived.backup, wn.clone()).await?;
+        restore_messages(&pubkey, &archived.messages, wn.clone()).await?;
+        if let Some(uri) = &archived.nostr_wallet_connect_uri {
+            secrets_store::store_nostr_wallet_connect_uri(&pubkey.to_hex(), uri, &wn.data_dir)?;
+        }
+        accounts.push(account);
+    }
+
+    tracing::info!(
+        target: "whitenoise::app_data::import",
+        "Imported {} accounts",
+        accounts.len()
+    );
+    Ok((accounts, archive.active_account))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> AppDataArchive {
+        AppDataArchive {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            created_at: Timestamp::now(),
+            active_account: Some(Keys::generate().public_key()),
+            accounts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let archive = archive();
+        let sealed = seal(&archive, "correct horse").unwrap();
+        let opened = open(&sealed, "correct horse").unwrap();
+        assert_eq!(opened.active_account, archive.active_account);
+        assert!(open(&sealed, "battery staple").is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_other_formats() {
+        let mut archive = archive();
+        archive.format = "something-else".to_string();
+        let sealed = seal(&archive, "correct horse").unwrap();
+        assert!(matches!(
+            open(&sealed, "correct horse"),
+            Err(AppDataError::InvalidArchive(_))
+        ));
+    }
+}
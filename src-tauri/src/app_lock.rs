@@ -0,0 +1,627 @@
+//! App lock: passphrase protection for opening the app.
+//!
+//! Besides the regular passphrase, users under coercion threat can configure a duress
+//! passphrase. Entering it at the lock screen opens a decoy profile (a separate, innocuous
+//! account that is otherwise hidden) instead of the real accounts, and can optionally wipe the
+//! real accounts in the background.
+//!
+//! Passphrase hashes are derived with Argon2id (see `kdf`) and kept in the secrets store, never
+//! in the database.
+//!
+//! While the app is locked the Nostr signer is dropped, subscriptions are closed and the MLS
+//! state is unloaded, so nothing can be signed or decrypted until [`unlock`] restores them for
+//! the active account. The app starts locked when a passphrase is set, and locks itself after
+//! the active account's auto-lock timeout without activity (see [`start`]).
+
+use crate::accounts::{Account, AccountError, AccountOnboarding};
+use crate::kdf::{self, KdfError, KdfParams};
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::sensitive_actions::ConfirmationTokens;
+use crate::Whitenoise;
+use nostr_openmls::NostrMls;
+use nostr_sdk::prelude::*;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+
+/// Minimum passphrase length; short numeric PINs are allowed
+const MIN_PASSPHRASE_LENGTH: usize = 4;
+
+/// Wrong passphrases accepted at the lock screen before unlocking is throttled
+const FREE_UNLOCK_ATTEMPTS: u32 = 5;
+
+/// Wait after the first throttled attempt, doubled with every further wrong passphrase
+const UNLOCK_BACKOFF_BASE: Duration = Duration::from_secs(30);
+
+/// Longest wait between unlock attempts
+const UNLOCK_BACKOFF_MAX: Duration = Duration::from_secs(60 * 60);
+
+/// How often the auto-lock task checks for inactivity
+const AUTO_LOCK_TICK: Duration = Duration::from_secs(15);
+
+/// Common first names the decoy profile is given, so it doesn't stand out as a placeholder
+const DECOY_NAMES: [&str; 8] = [
+    "Alex", "Sam", "Jordan", "Maria", "Daniel", "Laura", "Chris", "Ana",
+];
+
+#[derive(Error, Debug)]
+pub enum AppLockError {
+    #[error("Invalid passphrase: {0}")]
+    InvalidPassphrase(String),
+
+    #[error("App lock isn't configured")]
+    NotConfigured,
+
+    #[error("App is locked")]
+    Locked,
+
+    #[error("Too many wrong passphrases, try again in {0} seconds")]
+    TooManyAttempts(u64),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Key derivation error: {0}")]
+    KdfError(#[from] KdfError),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Failed to parse public key: {0}")]
+    PublicKeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, AppLockError>;
+
+/// A salted passphrase hash
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PassphraseHash {
+    salt: String,
+    hash: String,
+    /// Argon2id parameters of the hash
+    kdf: KdfParams,
+}
+
+impl PassphraseHash {
+    pub fn new(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; kdf::SALT_LENGTH];
+        rand::rng().fill_bytes(&mut salt);
+        let params = kdf_params();
+        let hash = kdf::derive_key(passphrase, &salt, &params)?;
+        Ok(Self {
+            salt: hex::encode(salt),
+            hash: hex::encode(hash.as_slice()),
+            kdf: params,
+        })
+    }
+
+    pub fn matches(&self, passphrase: &str) -> bool {
+        let Ok(salt) = hex::decode(&self.salt) else {
+            return false;
+        };
+        let Ok(expected) = hex::decode(&self.hash) else {
+            return false;
+        };
+        kdf::derive_key(passphrase, &salt, &self.kdf)
+            .is_ok_and(|hash| constant_time_eq(hash.as_slice(), &expected))
+    }
+}
+
+/// The Argon2id parameters new hashes are derived with. Tests use cheap ones.
+fn kdf_params() -> KdfParams {
+    if cfg!(test) {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    } else {
+        KdfParams::default()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn validate_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(AppLockError::InvalidPassphrase(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Duress passphrase settings
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuressConfig {
+    pub passphrase: PassphraseHash,
+    /// Hex pubkey of the decoy account opened under duress
+    pub decoy_pubkey: String,
+    /// Remove the real accounts after the duress passphrase is used
+    pub wipe_real_data: bool,
+}
+
+/// Persistent app lock configuration
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AppLockConfig {
+    pub passphrase: Option<PassphraseHash>,
+    pub duress: Option<DuressConfig>,
+    /// Wrong passphrases entered at the lock screen since it was last unlocked. Kept with the
+    /// config so restarting the app doesn't reset the throttling.
+    #[serde(default)]
+    pub failed_attempts: u32,
+    /// Unix time before which the lock screen doesn't check passphrases
+    #[serde(default)]
+    pub retry_after: Option<u64>,
+}
+
+/// Result of checking a passphrase entered at the lock screen
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum UnlockOutcome {
+    /// The real passphrase was entered
+    Unlocked,
+    /// The duress passphrase was entered
+    Duress,
+    /// Neither passphrase matched
+    Invalid,
+}
+
+impl AppLockConfig {
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        match secrets_store::get_app_lock_config(data_dir)? {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        secrets_store::store_app_lock_config(&serde_json::to_string(self)?, data_dir)?;
+        Ok(())
+    }
+
+    /// Checks a passphrase against the real and duress passphrases
+    pub fn check(&self, passphrase: &str) -> UnlockOutcome {
+        if self
+            .passphrase
+            .as_ref()
+            .is_some_and(|hash| hash.matches(passphrase))
+        {
+            UnlockOutcome::Unlocked
+        } else if self
+            .duress
+            .as_ref()
+            .is_some_and(|duress| duress.passphrase.matches(passphrase))
+        {
+            UnlockOutcome::Duress
+        } else {
+            UnlockOutcome::Invalid
+        }
+    }
+
+    pub fn decoy_pubkey(&self) -> Option<&str> {
+        self.duress.as_ref().map(|d| d.decoy_pubkey.as_str())
+    }
+}
+
+/// In-memory app lock state
+#[derive(Debug, Default)]
+pub struct AppLockState {
+    /// The app was opened with the duress passphrase and only the decoy profile is visible
+    pub decoy_active: bool,
+    /// Signing and decryption are unavailable until the app is unlocked
+    pub locked: bool,
+    /// Last time the user was active, for the auto-lock timeout
+    pub last_activity: Option<Instant>,
+    /// Tokens authorizing sensitive commands, see [`crate::sensitive_actions`]
+    pub confirmations: ConfirmationTokens,
+}
+
+impl AppLockState {
+    /// The state at startup: locked if an app passphrase is set
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            locked: is_configured(data_dir),
+            ..Default::default()
+        }
+    }
+}
+
+/// How long the lock screen waits after `failed_attempts` wrong passphrases
+fn unlock_delay(failed_attempts: u32) -> Option<Duration> {
+    let throttled = failed_attempts.checked_sub(FREE_UNLOCK_ATTEMPTS)?;
+    let delay = UNLOCK_BACKOFF_BASE.saturating_mul(2u32.saturating_pow(throttled.min(16)));
+    Some(delay.min(UNLOCK_BACKOFF_MAX))
+}
+
+/// Whether an app passphrase is set
+pub fn is_configured(data_dir: &Path) -> bool {
+    AppLockConfig::load(data_dir).is_ok_and(|config| config.passphrase.is_some())
+}
+
+/// Sets (or changes) the app passphrase
+///
+/// # Arguments
+/// * `passphrase` - The new passphrase. Must differ from the duress passphrase.
+/// * `current_passphrase` - The current passphrase, required when one is already set
+/// * `wn` - The Whitenoise application state
+pub fn set_app_passphrase(
+    passphrase: &str,
+    current_passphrase: Option<&str>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    validate_passphrase(passphrase)?;
+    let mut config = AppLockConfig::load(&wn.data_dir)?;
+    if let Some(existing) = &config.passphrase {
+        if !current_passphrase.is_some_and(|current| existing.matches(current)) {
+            return Err(AppLockError::InvalidPassphrase(
+                "Current passphrase is incorrect".to_string(),
+            ));
+        }
+    }
+    if config.check(passphrase) == UnlockOutcome::Duress {
+        return Err(AppLockError::InvalidPassphrase(
+            "App passphrase must differ from the duress passphrase".to_string(),
+        ));
+    }
+
+    config.passphrase = Some(PassphraseHash::new(passphrase)?);
+    config.save(&wn.data_dir)
+}
+
+/// Returns `Err(AppLockError::Locked)` while the app is locked
+pub async fn ensure_unlocked(wn: &tauri::State<'_, Whitenoise>) -> Result<()> {
+    if wn.app_lock.lock().await.locked {
+        return Err(AppLockError::Locked);
+    }
+    Ok(())
+}
+
+/// Records user activity, postponing the auto-lock
+pub async fn touch(wn: &tauri::State<'_, Whitenoise>) {
+    wn.app_lock.lock().await.last_activity = Some(Instant::now());
+}
+
+/// Locks the app: closes subscriptions, drops the signer and unloads the MLS state, then emits
+/// `app_locked`. Locking an already locked app does nothing.
+pub async fn lock(wn: tauri::State<'_, Whitenoise>, app_handle: &AppHandle) -> Result<()> {
+    if !is_configured(&wn.data_dir) {
+        return Err(AppLockError::NotConfigured);
+    }
+    {
+        let mut state = wn.app_lock.lock().await;
+        if state.locked {
+            return Ok(());
+        }
+        state.locked = true;
+        state.confirmations.clear();
+    }
+
+    wn.nostr.client.unsubscribe_all().await;
+    wn.nostr.client.unset_signer().await;
+    {
+        let mut nostr_mls = wn.nostr_mls.lock().await;
+        *nostr_mls = NostrMls::new(wn.data_dir.clone(), None);
+    }
+    secrets_store::clear_secret_cache();
+
+    tracing::info!(target: "whitenoise::app_lock::lock", "App locked");
+    app_handle.emit("app_locked", ())?;
+    Ok(())
+}
+
+/// Unlocks the app with a passphrase entered at the lock screen and emits `app_unlocked`.
+///
+/// The real passphrase restores the signer, subscriptions and MLS state of the active account
+/// (leaving the decoy profile if it was open). The duress passphrase opens the decoy profile,
+/// see [`enter_duress_mode`]. A wrong passphrase leaves the app locked; after
+/// [`FREE_UNLOCK_ATTEMPTS`] of them, passphrases are refused for an exponentially growing wait
+/// so short PINs can't be guessed.
+pub async fn unlock(
+    passphrase: &str,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &AppHandle,
+) -> Result<UnlockOutcome> {
+    let mut config = AppLockConfig::load(&wn.data_dir)?;
+    let now = Timestamp::now().as_u64();
+    if let Some(retry_after) = config.retry_after.filter(|retry_after| *retry_after > now) {
+        return Err(AppLockError::TooManyAttempts(retry_after - now));
+    }
+
+    let outcome = config.check(passphrase);
+    if outcome == UnlockOutcome::Invalid {
+        config.failed_attempts = config.failed_attempts.saturating_add(1);
+        config.retry_after =
+            unlock_delay(config.failed_attempts).map(|delay| now + delay.as_secs());
+        config.save(&wn.data_dir)?;
+        return Ok(outcome);
+    }
+    if config.failed_attempts > 0 {
+        config.failed_attempts = 0;
+        config.retry_after = None;
+        config.save(&wn.data_dir)?;
+    }
+    {
+        let mut state = wn.app_lock.lock().await;
+        state.locked = false;
+        state.last_activity = Some(Instant::now());
+        if outcome == UnlockOutcome::Unlocked {
+            state.decoy_active = false;
+        }
+    }
+
+    match outcome {
+        UnlockOutcome::Duress => enter_duress_mode(wn.clone(), app_handle).await?,
+        _ => {
+            // The decoy account stays active in the database after duress mode, so fall back to
+            // the most recently used real account
+            let account = Account::all(wn.clone())
+                .await?
+                .into_iter()
+                .max_by_key(|account| (account.active, account.last_used));
+            if let Some(account) = account {
+                account.set_active(wn.clone(), app_handle).await?;
+            }
+        }
+    }
+
+    tracing::info!(target: "whitenoise::app_lock::unlock", "App unlocked");
+    app_handle.emit("app_unlocked", ())?;
+    Ok(outcome)
+}
+
+/// Whether an app idle for `idle` should lock with a timeout of `timeout_minutes`
+fn should_auto_lock(idle: Duration, timeout_minutes: u32) -> bool {
+    idle >= Duration::from_secs(u64::from(timeout_minutes) * 60)
+}
+
+/// Starts the background task that locks the app once the user has been inactive for the
+/// active account's auto-lock timeout
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(AUTO_LOCK_TICK);
+        loop {
+            interval.tick().await;
+            let wn = app_handle.state::<Whitenoise>();
+
+            let Ok(account) = Account::get_active(wn.clone()).await else {
+                continue;
+            };
+            let Some(timeout_minutes) = account.settings.auto_lock_minutes else {
+                continue;
+            };
+            if !is_configured(&wn.data_dir) {
+                continue;
+            }
+
+            let idle = {
+                let mut state = wn.app_lock.lock().await;
+                if state.locked {
+                    continue;
+                }
+                *state.last_activity.get_or_insert_with(Instant::now)
+            }
+            .elapsed();
+            if !should_auto_lock(idle, timeout_minutes) {
+                continue;
+            }
+
+            tracing::debug!(
+                target: "whitenoise::app_lock::start",
+                "Locking after {} minutes of inactivity",
+                timeout_minutes
+            );
+            if let Err(e) = lock(wn.clone(), &app_handle).await {
+                tracing::error!(
+                    target: "whitenoise::app_lock::start",
+                    "Failed to auto-lock: {}",
+                    e
+                );
+            }
+        }
+    });
+}
+
+/// Configures (or replaces) the duress passphrase, creating the decoy account if needed
+///
+/// # Arguments
+/// * `passphrase` - The duress passphrase. Must differ from the real passphrase.
+/// * `wipe_real_data` - Whether the real accounts are removed when the duress passphrase is used
+/// * `wn` - The Whitenoise application state
+pub async fn set_duress_passphrase(
+    passphrase: &str,
+    wipe_real_data: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    validate_passphrase(passphrase)?;
+    let mut config = AppLockConfig::load(&wn.data_dir)?;
+    if config.check(passphrase) == UnlockOutcome::Unlocked {
+        return Err(AppLockError::InvalidPassphrase(
+            "Duress passphrase must differ from the app passphrase".to_string(),
+        ));
+    }
+
+    let decoy_pubkey = match config.decoy_pubkey() {
+        Some(pubkey) => pubkey.to_string(),
+        None => {
+            let mut decoy = Account::new(wn.clone()).await?;
+            fill_decoy_profile(&mut decoy);
+            decoy.save(wn.clone()).await?;
+            decoy.pubkey.to_hex()
+        }
+    };
+
+    config.duress = Some(DuressConfig {
+        passphrase: PassphraseHash::new(passphrase)?,
+        decoy_pubkey,
+        wipe_real_data,
+    });
+    config.save(&wn.data_dir)
+}
+
+/// Gives a new decoy account an ordinary looking profile that's past onboarding, so opening it
+/// under duress doesn't show a blank, freshly created account
+fn fill_decoy_profile(decoy: &mut Account) {
+    let name = DECOY_NAMES[rand::rng().random_range(0..DECOY_NAMES.len())];
+    decoy.metadata = Metadata::new().name(name).display_name(name);
+    decoy.onboarding = AccountOnboarding {
+        inbox_relays: true,
+        key_package_relays: true,
+        publish_key_package: true,
+    };
+}
+
+/// Removes the duress passphrase and its decoy account
+pub async fn clear_duress_passphrase(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<()> {
+    let mut config = AppLockConfig::load(&wn.data_dir)?;
+    let Some(duress) = config.duress.take() else {
+        return Ok(());
+    };
+    config.save(&wn.data_dir)?;
+
+    let decoy_pubkey = PublicKey::from_hex(&duress.decoy_pubkey)?;
+    if let Ok(decoy) = Account::find_by_pubkey(&decoy_pubkey, wn.clone()).await {
+        decoy.remove(wn.clone(), app_handle).await?;
+    }
+    Ok(())
+}
+
+/// Returns the pubkey of the duress decoy account, if one is configured
+pub fn decoy_pubkey(data_dir: &Path) -> Option<PublicKey> {
+    AppLockConfig::load(data_dir)
+        .ok()?
+        .decoy_pubkey()
+        .and_then(|pk| PublicKey::from_hex(pk).ok())
+}
+
+/// Switches the app into the decoy profile after the duress passphrase was entered,
+/// scheduling a wipe of the real accounts if configured
+pub async fn enter_duress_mode(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let config = AppLockConfig::load(&wn.data_dir)?;
+    let duress = config.duress.ok_or(AppLockError::NotConfigured)?;
+    let decoy_pubkey = PublicKey::from_hex(&duress.decoy_pubkey)?;
+
+    wn.app_lock.lock().await.decoy_active = true;
+
+    let decoy = Account::find_by_pubkey(&decoy_pubkey, wn.clone()).await?;
+    decoy.set_active(wn.clone(), app_handle).await?;
+
+    if duress.wipe_real_data {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let wn = app_handle.state::<Whitenoise>();
+            if let Err(e) = wipe_real_accounts(&decoy_pubkey, wn, app_handle.clone()).await {
+                tracing::error!(
+                    target: "whitenoise::app_lock::enter_duress_mode",
+                    "Failed to wipe real accounts: {}",
+                    e
+                );
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn wipe_real_accounts(
+    decoy_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<()> {
+    for account in Account::all_including_hidden(wn.clone()).await? {
+        if account.pubkey != *decoy_pubkey {
+            account.remove(wn.clone(), app_handle.clone()).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(passphrase: &str, duress: &str) -> AppLockConfig {
+        AppLockConfig {
+            passphrase: Some(PassphraseHash::new(passphrase).unwrap()),
+            duress: Some(DuressConfig {
+                passphrase: PassphraseHash::new(duress).unwrap(),
+                decoy_pubkey: Keys::generate().public_key().to_hex(),
+                wipe_real_data: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_passphrase_hash_matches() {
+        let hash = PassphraseHash::new("correct horse").unwrap();
+        assert!(hash.matches("correct horse"));
+        assert!(!hash.matches("correct horse "));
+        assert!(!hash.matches(""));
+    }
+
+    #[test]
+    fn test_passphrase_hash_is_salted() {
+        let a = PassphraseHash::new("1234").unwrap();
+        let b = PassphraseHash::new("1234").unwrap();
+        assert_ne!(a.hash, b.hash);
+    }
+
+    #[test]
+    fn test_unlock_delay() {
+        for attempts in 0..FREE_UNLOCK_ATTEMPTS {
+            assert_eq!(unlock_delay(attempts), None);
+        }
+        assert_eq!(
+            unlock_delay(FREE_UNLOCK_ATTEMPTS),
+            Some(UNLOCK_BACKOFF_BASE)
+        );
+        assert_eq!(
+            unlock_delay(FREE_UNLOCK_ATTEMPTS + 1),
+            Some(UNLOCK_BACKOFF_BASE * 2)
+        );
+        assert_eq!(unlock_delay(u32::MAX), Some(UNLOCK_BACKOFF_MAX));
+    }
+
+    #[test]
+    fn test_check_outcomes() {
+        let config = config_with("real-pass", "4321");
+        assert_eq!(config.check("real-pass"), UnlockOutcome::Unlocked);
+        assert_eq!(config.check("4321"), UnlockOutcome::Duress);
+        assert_eq!(config.check("nope"), UnlockOutcome::Invalid);
+        assert_eq!(
+            AppLockConfig::default().check("anything"),
+            UnlockOutcome::Invalid
+        );
+    }
+
+    #[test]
+    fn test_should_auto_lock() {
+        assert!(!should_auto_lock(Duration::from_secs(59), 1));
+        assert!(should_auto_lock(Duration::from_secs(60), 1));
+        assert!(should_auto_lock(Duration::from_secs(3600), 5));
+    }
+
+    #[test]
+    fn test_validate_passphrase() {
+        assert!(validate_passphrase("1234").is_ok());
+        assert!(validate_passphrase("123").is_err());
+    }
+}
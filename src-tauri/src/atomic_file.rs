@@ -0,0 +1,314 @@
+//! Crash-safe file writes.
+//!
+//! [`write`] never leaves a truncated file behind: data goes to a temporary file of its own next
+//! to the target, is fsynced and then renamed over the target. [`write_checked`] additionally
+//! prefixes a SHA-256 checksum and keeps the previous good copy as `<name>.bak`; [`read_checked`]
+//! verifies the checksum and falls back to (and restores) the backup when the file is missing or
+//! corrupt. Writes that remove something that must not be recoverable, like a secret, use
+//! [`write_checked_discarding_backup`] instead.
+//!
+//! Checked files are read and written through a [`FileLock`], so two threads updating the same
+//! file can't interleave their reads and writes and lose one of the changes.
+//!
+//! Files written before checksums were introduced have no checksum header and are read as they
+//! are.
+
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// Starts the checksum header of a checked file
+const CHECKSUM_MAGIC: &[u8; 8] = b"WNCKSUM1";
+
+/// Length of the header: the magic followed by the SHA-256 digest
+const HEADER_LEN: usize = CHECKSUM_MAGIC.len() + 32;
+
+/// Tells apart the temporary files of concurrent writes
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The paths currently locked, and the condition to wait on for one to be released
+static LOCKED: Lazy<(Mutex<HashSet<PathBuf>>, Condvar)> =
+    Lazy::new(|| (Mutex::new(HashSet::new()), Condvar::new()));
+
+/// Exclusive access to a checked file within the process, held from reading it until the write
+/// based on what was read. Released when dropped.
+pub struct FileLock {
+    path: PathBuf,
+}
+
+/// Waits until no other thread holds `path` and locks it
+pub fn lock(path: &Path) -> FileLock {
+    let (locked, released) = &*LOCKED;
+    let mut locked = locked.lock().unwrap_or_else(|e| e.into_inner());
+    while locked.contains(path) {
+        locked = released.wait(locked).unwrap_or_else(|e| e.into_inner());
+    }
+    locked.insert(path.to_path_buf());
+    FileLock {
+        path: path.to_path_buf(),
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let (locked, released) = &*LOCKED;
+        locked
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.path);
+        released.notify_all();
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The last good copy of a checked file
+fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, ".bak")
+}
+
+/// Writes `data` to `path` atomically: a crash leaves either the old or the new contents
+pub fn write(path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp_path = sibling(
+        path,
+        &format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let written = File::create_new(&temp_path).and_then(|mut file| {
+        file.write_all(data.as_ref())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    // Persist the rename itself. Directories can't be opened for syncing on Windows.
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn with_checksum(data: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(HEADER_LEN + data.len());
+    contents.extend_from_slice(CHECKSUM_MAGIC);
+    contents.extend_from_slice(&Sha256::digest(data));
+    contents.extend_from_slice(data);
+    contents
+}
+
+/// Strips and verifies the checksum header. Returns `None` if the checksum doesn't match or the
+/// header itself is cut off.
+fn verify(contents: &[u8]) -> Option<&[u8]> {
+    let magic_len = CHECKSUM_MAGIC.len().min(contents.len());
+    if contents.is_empty() || contents[..magic_len] != CHECKSUM_MAGIC[..magic_len] {
+        // Written before checksums were added
+        return Some(contents);
+    }
+    if contents.len() < HEADER_LEN {
+        return None;
+    }
+    let (checksum, data) = contents[CHECKSUM_MAGIC.len()..].split_at(32);
+    (Sha256::digest(data).as_slice() == checksum).then_some(data)
+}
+
+/// Reads and verifies a checked file. `Ok(None)` if it doesn't exist, an `InvalidData` error if
+/// it's corrupt.
+fn read_verified(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match verify(&contents) {
+        Some(data) => Ok(Some(data.to_vec())),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Checksum mismatch in {}", path.display()),
+        )),
+    }
+}
+
+/// Atomically writes `data` with a checksum, keeping the current contents as the backup if
+/// they're intact
+pub fn write_checked(file: &FileLock, data: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = file.path.as_path();
+    if let Ok(Some(current)) = read_verified(path) {
+        write(&backup_path(path), with_checksum(&current))?;
+    }
+    write(path, with_checksum(data.as_ref()))
+}
+
+/// Atomically writes `data` with a checksum and makes it the backup as well, so whatever the
+/// current contents had that `data` doesn't can't be restored from the backup
+pub fn write_checked_discarding_backup(file: &FileLock, data: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = file.path.as_path();
+    let contents = with_checksum(data.as_ref());
+    write(&backup_path(path), &contents)?;
+    write(path, contents)
+}
+
+/// Reads a file written with [`write_checked`], without the checksum.
+///
+/// If the file is missing or corrupt but the backup is intact, the backup is restored and
+/// returned. `Ok(None)` if neither exists.
+pub fn read_checked(file: &FileLock) -> io::Result<Option<Vec<u8>>> {
+    let path = file.path.as_path();
+    let error = match read_verified(path) {
+        Ok(Some(data)) => return Ok(Some(data)),
+        Ok(None) => None,
+        Err(e) => Some(e),
+    };
+
+    match read_verified(&backup_path(path)) {
+        Ok(Some(data)) => {
+            tracing::warn!(
+                target: "whitenoise::atomic_file::read_checked",
+                "Restoring {} from its last good copy",
+                path.display()
+            );
+            write(path, with_checksum(&data))?;
+            Ok(Some(data))
+        }
+        _ => match error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_checked_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+
+        let file = lock(&path);
+        write_checked(&file, b"{\"a\":1}").unwrap();
+        assert_eq!(read_checked(&file).unwrap().unwrap(), b"{\"a\":1}");
+        assert!(fs::read_dir(dir.path()).unwrap().all(|entry| !entry
+            .unwrap()
+            .path()
+            .to_string_lossy()
+            .ends_with(".tmp")));
+        assert_eq!(
+            read_checked(&lock(&dir.path().join("missing"))).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_discarding_backup_replaces_the_previous_contents() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+        let file = lock(&path);
+        write_checked(&file, b"secret").unwrap();
+        write_checked(&file, b"secret").unwrap();
+
+        write_checked_discarding_backup(&file, b"removed").unwrap();
+        assert_eq!(
+            read_verified(&backup_path(&path)).unwrap().unwrap(),
+            b"removed"
+        );
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read_checked(&file).unwrap().unwrap(), b"removed");
+    }
+
+    #[test]
+    fn test_corrupt_file_is_restored_from_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+        let file = lock(&path);
+        write_checked(&file, b"first").unwrap();
+        write_checked(&file, b"second").unwrap();
+
+        // Simulate a torn write of the current file
+        let contents = fs::read(&path).unwrap();
+        fs::write(&path, &contents[..contents.len() - 3]).unwrap();
+
+        assert_eq!(read_checked(&file).unwrap().unwrap(), b"first");
+        // The restored copy verifies again
+        assert_eq!(read_verified(&path).unwrap().unwrap(), b"first");
+    }
+
+    #[test]
+    fn test_missing_file_is_restored_from_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+        let file = lock(&path);
+        write_checked(&file, b"first").unwrap();
+        write_checked(&file, b"second").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_checked(&file).unwrap().unwrap(), b"first");
+    }
+
+    #[test]
+    fn test_corrupt_file_without_backup_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+        let file = lock(&path);
+        write_checked(&file, b"only").unwrap();
+        let mut contents = fs::read(&path).unwrap();
+        *contents.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, contents).unwrap();
+
+        let err = read_checked(&file).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_serialized() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("counter");
+        write_checked(&lock(&path), b"0").unwrap();
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        let file = lock(&path);
+                        let count: u32 = String::from_utf8(read_checked(&file).unwrap().unwrap())
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        write_checked(&file, (count + 1).to_string()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(read_checked(&lock(&path)).unwrap().unwrap(), b"80");
+    }
+
+    #[test]
+    fn test_legacy_files_without_checksum_are_read_as_is() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("store.json");
+        let file = lock(&path);
+        fs::write(&path, b"{}").unwrap();
+        assert_eq!(read_checked(&file).unwrap().unwrap(), b"{}");
+    }
+}
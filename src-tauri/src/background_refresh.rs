@@ -0,0 +1,283 @@
+//! Background refresh for mobile OS schedulers.
+//!
+//! iOS background app refresh and Android's WorkManager wake the app for a short, OS-chosen time
+//! budget. [`run`] spends it fetching new messages for the most important groups first and
+//! queueing them for processing, which posts notifications just like in the foreground, then
+//! updates the unread badge. How far each group got is checkpointed, so the next run continues
+//! from there instead of fetching everything again. Opening a conversation syncs its group the
+//! same way with [`sync_group`].
+//!
+//! Fetched messages go through the event queue like the ones subscriptions deliver, so a group's
+//! messages are always processed one at a time and in order.
+
+use crate::groups::{Group, GroupError};
+use crate::messages::{MessageError, ProcessedMessage};
+use crate::nostr_manager::NostrManagerError;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+
+/// Time kept back from the budget to update the badge and return before the OS stops the task
+const FINISH_RESERVE: Duration = Duration::from_secs(2);
+
+/// How far back groups that were never refreshed are fetched
+const DEFAULT_LOOKBACK: u64 = 24 * 60 * 60;
+
+/// How often to check whether the queued messages were processed
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug)]
+pub enum BackgroundRefreshError {
+    #[error("Budget must be longer than {} seconds", FINISH_RESERVE.as_secs())]
+    BudgetTooShort,
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Message error: {0}")]
+    MessageError(#[from] MessageError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, BackgroundRefreshError>;
+
+/// What a background refresh got done
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct BackgroundRefreshReport {
+    /// Groups whose messages were fetched up to now
+    pub synced_groups: usize,
+    /// Groups that didn't fit in the budget
+    pub remaining_groups: usize,
+    /// Group events fetched that weren't processed before
+    pub new_messages: usize,
+    /// Unread messages across the active account's groups, for the app badge
+    pub unread_total: u64,
+}
+
+/// Orders groups by refresh priority: groups that aren't muted or snoozed first, then the most
+/// recently active ones. Archived groups are dropped.
+fn prioritize(groups: &mut Vec<Group>, now: Timestamp) {
+    groups.retain(|group| group.archived_at.is_none());
+    groups.sort_by_key(|group| {
+        (
+            group.muted || group.snoozed_until.is_some_and(|until| until > now),
+            Reverse(group.last_message_at),
+        )
+    });
+}
+
+/// Where to start fetching a group's messages: its checkpoint, everything before which was
+/// fetched. A group's latest message isn't used: it may have arrived through a subscription
+/// while older messages were still missing.
+fn fetch_since(checkpoint: Option<Timestamp>, now: Timestamp) -> Timestamp {
+    checkpoint.unwrap_or(now - DEFAULT_LOOKBACK)
+}
+
+async fn checkpoint(group: &Group, wn: &tauri::State<'_, Whitenoise>) -> Result<Option<Timestamp>> {
+    let synced_until: Option<i64> = sqlx::query_scalar(
+        "SELECT synced_until FROM background_sync_checkpoints WHERE group_id = ? AND account_pubkey = ?",
+    )
+    .bind(&group.mls_group_id)
+    .bind(group.account_pubkey.to_hex())
+    .fetch_optional(&wn.database.pool)
+    .await?;
+    Ok(synced_until.map(|t| Timestamp::from(t as u64)))
+}
+
+async fn save_checkpoint(
+    group: &Group,
+    synced_until: Timestamp,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO background_sync_checkpoints (group_id, account_pubkey, synced_until) VALUES (?, ?, ?)",
+    )
+    .bind(&group.mls_group_id)
+    .bind(group.account_pubkey.to_hex())
+    .bind(synced_until.as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(())
+}
+
+/// Fetches a group's messages since its checkpoint and queues the ones that weren't processed
+/// before. The checkpoint only moves up when the fetch finished within `timeout`.
+///
+/// Returns how many new events were queued and whether the fetch finished.
+pub(crate) async fn sync_group(
+    group: &Group,
+    timeout: Duration,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<(usize, bool)> {
+    let now = Timestamp::now();
+    let since = fetch_since(checkpoint(group, wn).await?, now);
+    let filter = Filter::new()
+        .kind(Kind::MlsGroupMessage)
+        .custom_tag(
+            SingleLetterTag::lowercase(Alphabet::H),
+            &group.nostr_group_id,
+        )
+        .since(since)
+        .until(now);
+
+    let fetch_started = Instant::now();
+    let mut events: Vec<Event> = wn
+        .nostr
+        .client
+        .fetch_events(filter, timeout)
+        .await?
+        .into_iter()
+        .collect();
+    let complete = fetch_started.elapsed() < timeout;
+
+    events.sort_by_key(|event| event.created_at);
+    let mut new_events = Vec::with_capacity(events.len());
+    for event in events {
+        if ProcessedMessage::find_by_event_id(event.id, wn.clone())
+            .await?
+            .is_none()
+        {
+            new_events.push(event);
+        }
+    }
+    let new_messages = new_events.len();
+    wn.nostr.queue_group_messages(new_events).await?;
+
+    if complete {
+        save_checkpoint(group, now, wn).await?;
+    }
+    Ok((new_messages, complete))
+}
+
+/// Syncs each of `groups` like [`sync_group`], e.g. for the scheduled sync. A group that fails is
+/// logged and skipped.
+///
+/// Returns how many new events were queued and whether every group's fetch finished.
+pub(crate) async fn sync_groups(
+    groups: &[Group],
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<(usize, bool)> {
+    let timeout = wn.nostr.timeout().await?;
+    let mut new_messages = 0;
+    let mut complete = true;
+    for group in groups {
+        match sync_group(group, timeout, wn).await {
+            Ok((group_messages, group_complete)) => {
+                new_messages += group_messages;
+                complete &= group_complete;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::background_refresh::sync_groups",
+                    "Failed to sync group {}: {}",
+                    group.nostr_group_id,
+                    e
+                );
+                complete = false;
+            }
+        }
+    }
+    Ok((new_messages, complete))
+}
+
+/// Fetches and processes new messages within `budget`, highest priority groups first, then
+/// waits for them to be processed while the budget lasts.
+///
+/// A group is only checkpointed when its fetch finished before timing out, so a group cut off by
+/// the budget is fetched again from its previous checkpoint next time.
+pub async fn run(
+    budget: Duration,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &AppHandle,
+) -> Result<BackgroundRefreshReport> {
+    let started = Instant::now();
+    let deadline = budget
+        .checked_sub(FINISH_RESERVE)
+        .filter(|d| !d.is_zero())
+        .ok_or(BackgroundRefreshError::BudgetTooShort)?;
+
+    let mut groups = Group::get_all_groups(wn.clone()).await?;
+    prioritize(&mut groups, Timestamp::now());
+
+    let mut report = BackgroundRefreshReport::default();
+    let relay_timeout = wn.nostr.timeout().await?;
+    for (index, group) in groups.iter().enumerate() {
+        let Some(remaining) = deadline.checked_sub(started.elapsed()) else {
+            report.remaining_groups = groups.len() - index;
+            break;
+        };
+
+        let timeout = remaining.min(relay_timeout);
+        let (new_messages, complete) = sync_group(group, timeout, &wn).await?;
+        report.new_messages += new_messages;
+
+        if complete {
+            report.synced_groups += 1;
+        } else {
+            report.remaining_groups = groups.len() - index;
+            break;
+        }
+    }
+
+    // Queued messages only notify and count as unread once processed
+    while started.elapsed() < deadline && wn.nostr.queue_depth().await > 0 {
+        tokio::time::sleep(QUEUE_POLL_INTERVAL).await;
+    }
+
+    report.unread_total = Group::unread_counts(wn.clone()).await?.values().sum();
+    // Android has no app badge; its scheduler gets the total from the report
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let badge = Some(report.unread_total as i64).filter(|count| *count > 0);
+        if let Err(e) = window.set_badge_count(badge) {
+            tracing::warn!(
+                target: "whitenoise::background_refresh::run",
+                "Failed to set badge count: {}",
+                e
+            );
+        }
+    }
+    app_handle.emit("unread_total_changed", report.unread_total)?;
+
+    tracing::info!(
+        target: "whitenoise::background_refresh::run",
+        "Background refresh synced {} groups ({} left) and processed {} messages in {:?}",
+        report.synced_groups,
+        report.remaining_groups,
+        report.new_messages,
+        started.elapsed()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_since() {
+        let now = Timestamp::from(1_000_000);
+        assert_eq!(
+            fetch_since(None, now),
+            Timestamp::from(1_000_000 - DEFAULT_LOOKBACK)
+        );
+        assert_eq!(
+            fetch_since(Some(Timestamp::from(30)), now),
+            Timestamp::from(30)
+        );
+    }
+}
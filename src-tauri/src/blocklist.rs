@@ -0,0 +1,301 @@
+//! Users the account has blocked.
+//!
+//! Blocked users are kept in `blocked_users`. Their group messages are dropped before they're
+//! stored and their invites are declined as they arrive. Blocking is local, but the list can be
+//! published as the account's NIP-51 mute list (kind 10000) so other clients hide those users
+//! too. Publishing merges into the account's latest mute list: the entries other clients added,
+//! like muted words and pubkeys, are kept, and only the users unblocked here since the last
+//! publish (kept in `unblocked_users`) are dropped from it.
+
+use crate::accounts::{Account, AccountError};
+use crate::nostr_manager::NostrManagerError;
+use crate::relay_blacklist::{self, RelayBlacklistError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BlocklistError {
+    #[error("Invalid pubkey: {0}")]
+    InvalidPubkey(String),
+
+    #[error("Users can't block themselves")]
+    BlockingSelf,
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("Nostr database error: {0}")]
+    NostrDatabaseError(#[from] DatabaseError),
+
+    #[error("Nostr error: {0}")]
+    NostrError(#[from] NostrManagerError),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+}
+
+pub type Result<T> = std::result::Result<T, BlocklistError>;
+
+/// A user the account blocked
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockedUser {
+    /// Hex encoded pubkey
+    pub pubkey: String,
+    pub blocked_at: Timestamp,
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<PublicKey> {
+    PublicKey::parse(pubkey).map_err(|_| BlocklistError::InvalidPubkey(pubkey.to_string()))
+}
+
+/// Blocks a user. Blocking someone who's already blocked does nothing.
+pub async fn block(
+    account: &Account,
+    pubkey: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let pubkey = parse_pubkey(pubkey)?;
+    if pubkey == account.pubkey {
+        return Err(BlocklistError::BlockingSelf);
+    }
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO blocked_users (account_pubkey, pubkey, blocked_at) VALUES (?, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(pubkey.to_hex())
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&mut *txn)
+    .await?;
+    sqlx::query("DELETE FROM unblocked_users WHERE account_pubkey = ? AND pubkey = ?")
+        .bind(account.pubkey.to_hex())
+        .bind(pubkey.to_hex())
+        .execute(&mut *txn)
+        .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Unblocks a user
+pub async fn unblock(
+    account: &Account,
+    pubkey: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let pubkey = parse_pubkey(pubkey)?;
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query("DELETE FROM blocked_users WHERE account_pubkey = ? AND pubkey = ?")
+        .bind(account.pubkey.to_hex())
+        .bind(pubkey.to_hex())
+        .execute(&mut *txn)
+        .await?;
+    sqlx::query(
+        "INSERT OR REPLACE INTO unblocked_users (account_pubkey, pubkey, unblocked_at) VALUES (?, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(pubkey.to_hex())
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// The users the account blocked, most recent first
+pub async fn list(account: &Account, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<BlockedUser>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT pubkey, blocked_at FROM blocked_users WHERE account_pubkey = ?
+         ORDER BY blocked_at DESC, pubkey",
+    )
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(pubkey, blocked_at)| BlockedUser {
+            pubkey,
+            blocked_at: Timestamp::from(blocked_at as u64),
+        })
+        .collect())
+}
+
+/// Whether the account blocked `pubkey`
+pub async fn is_blocked(
+    account: &Account,
+    pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<bool> {
+    let blocked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM blocked_users WHERE account_pubkey = ? AND pubkey = ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(pubkey.to_hex())
+    .fetch_one(&wn.database.pool)
+    .await?;
+    Ok(blocked)
+}
+
+/// The tags of a new mute list: the current list's entries, without the unblocked pubkeys,
+/// followed by the blocked pubkeys that aren't on it yet
+fn mute_list_tags(
+    current: Option<&Event>,
+    blocked: &[BlockedUser],
+    unblocked: &[String],
+) -> Vec<Tag> {
+    let is_unblocked = |tag: &Tag| {
+        tag.kind() == TagKind::p()
+            && tag
+                .content()
+                .is_some_and(|pubkey| unblocked.iter().any(|unblocked| unblocked == pubkey))
+    };
+    let mut tags: Vec<Tag> = current
+        .map(|event| {
+            event
+                .tags
+                .iter()
+                .filter(|tag| !is_unblocked(tag))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    for tag in blocked
+        .iter()
+        .filter_map(|user| PublicKey::from_hex(&user.pubkey).ok())
+        .map(Tag::public_key)
+    {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The account's latest mute list, from its relays or the local cache, whichever is newer
+async fn latest_mute_list(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<Event>> {
+    let filter = Filter::new()
+        .kind(Kind::MuteList)
+        .author(account.pubkey)
+        .limit(1);
+    let mut events: Vec<Event> = wn
+        .nostr
+        .client
+        .database()
+        .query(filter.clone())
+        .await?
+        .into_iter()
+        .collect();
+    match wn
+        .nostr
+        .client
+        .fetch_events(filter, wn.nostr.timeout().await?)
+        .await
+    {
+        Ok(fetched) => events.extend(fetched),
+        Err(e) => tracing::warn!(
+            target: "whitenoise::blocklist::latest_mute_list",
+            "Couldn't fetch the mute list, using the cached one: {}",
+            e
+        ),
+    }
+    Ok(events.into_iter().max_by_key(|event| event.created_at))
+}
+
+/// Publishes the blocked users as the account's mute list (kind 10000)
+///
+/// The blocked users are merged into the account's latest mute list. The users unblocked since
+/// the last publish are dropped from it; its other entries and any private (encrypted) content
+/// are kept as they are.
+pub async fn publish_mute_list(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<EventId> {
+    let blocked = list(account, wn.clone()).await?;
+    let unblocked = sqlx::query_scalar::<_, String>(
+        "SELECT pubkey FROM unblocked_users WHERE account_pubkey = ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+    let current = latest_mute_list(account, wn.clone()).await?;
+
+    let content = current
+        .as_ref()
+        .map(|event| event.content.clone())
+        .unwrap_or_default();
+    let tags = mute_list_tags(current.as_ref(), &blocked, &unblocked);
+    let event = wn
+        .nostr
+        .client
+        .sign_event_builder(EventBuilder::new(Kind::MuteList, content).tags(tags))
+        .await?;
+    relay_blacklist::send_event(&event, Vec::new(), wn.clone()).await?;
+
+    sqlx::query("DELETE FROM unblocked_users WHERE account_pubkey = ? AND unblocked_at <= ?")
+        .bind(account.pubkey.to_hex())
+        .bind(event.created_at.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+
+    tracing::debug!(
+        target: "whitenoise::blocklist::publish_mute_list",
+        "Published mute list with {} blocked users: {}",
+        blocked.len(),
+        event.id
+    );
+    Ok(event.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mute_list_tags_merge_pubkeys_and_keep_other_entries() {
+        let keys = Keys::generate();
+        let muted_elsewhere = Keys::generate().public_key();
+        let unblocked = Keys::generate().public_key();
+        let blocked = Keys::generate().public_key();
+        let current = EventBuilder::new(Kind::MuteList, "")
+            .tags([
+                Tag::public_key(muted_elsewhere),
+                Tag::public_key(unblocked),
+                Tag::hashtag("spoilers"),
+                Tag::public_key(blocked),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+        let blocked_user = |pubkey: PublicKey| BlockedUser {
+            pubkey: pubkey.to_hex(),
+            blocked_at: Timestamp::from(1),
+        };
+
+        let tags = mute_list_tags(
+            Some(&current),
+            &[blocked_user(blocked)],
+            &[unblocked.to_hex()],
+        );
+        assert_eq!(
+            tags,
+            vec![
+                Tag::public_key(muted_elsewhere),
+                Tag::hashtag("spoilers"),
+                Tag::public_key(blocked)
+            ]
+        );
+
+        let newly_blocked = Keys::generate().public_key();
+        let tags = mute_list_tags(None, &[blocked_user(newly_blocked)], &[]);
+        assert_eq!(tags, vec![Tag::public_key(newly_blocked)]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Actions applied to many groups at once.
+//!
+//! Archiving, muting and marking as read only change the local copies of the groups, so
+//! [`apply`] runs them for every group in a single transaction: either all groups change or none
+//! does. Leaving also stops the subscription to the groups' messages, which can't be part of a
+//! transaction; the caller handles that after the groups are marked as left.
+//!
+//! Leaving can't be announced to the group: the MLS library doesn't offer leave proposals yet, so
+//! the user stays in the group's member list until an admin removes them. The caller deletes the
+//! groups' MLS state from this device instead, so it can no longer decrypt or send to them.
+
+use crate::groups::{Group, GroupState};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What to do with each group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkGroupAction {
+    Archive,
+    Unarchive,
+    Mute,
+    Unmute,
+    MarkRead,
+    Leave,
+}
+
+/// The outcome of an action for one group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroupActionResult {
+    /// Hex encoded MLS group ID, as given
+    pub group_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl GroupActionResult {
+    pub fn succeeded(group_id: &str) -> Self {
+        Self {
+            group_id: group_id.to_string(),
+            success: true,
+            error: None,
+        }
+    }
+
+    pub fn failed(group_id: &str, error: impl ToString) -> Self {
+        Self {
+            group_id: group_id.to_string(),
+            success: false,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// Checks whether the action can be applied to the group
+pub fn validate(action: BulkGroupAction, group: &Group) -> Result<(), String> {
+    match action {
+        BulkGroupAction::Unarchive if group.merged_into.is_some() => {
+            Err("Merged groups can't be unarchived".to_string())
+        }
+        BulkGroupAction::Unarchive if matches!(group.state, GroupState::Inactive) => {
+            Err("Groups the user left can't be unarchived".to_string())
+        }
+        BulkGroupAction::Leave if matches!(group.state, GroupState::Inactive) => {
+            Err("The user already left this group".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Applies the action to the local copies of the groups in one transaction
+pub async fn apply(
+    groups: &[Group],
+    action: BulkGroupAction,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<()> {
+    let now = Timestamp::now().as_u64() as i64;
+    // Statements that take the current time bind it first
+    let (statement, timestamped) = match action {
+        BulkGroupAction::Archive => (
+            "UPDATE groups SET archived_at = COALESCE(archived_at, ?) WHERE mls_group_id = ? AND account_pubkey = ?",
+            true,
+        ),
+        BulkGroupAction::Unarchive => (
+            "UPDATE groups SET archived_at = NULL WHERE mls_group_id = ? AND account_pubkey = ?",
+            false,
+        ),
+        BulkGroupAction::Mute => (
+            "UPDATE groups SET muted = TRUE WHERE mls_group_id = ? AND account_pubkey = ?",
+            false,
+        ),
+        BulkGroupAction::Unmute => (
+            "UPDATE groups SET muted = FALSE WHERE mls_group_id = ? AND account_pubkey = ?",
+            false,
+        ),
+        BulkGroupAction::MarkRead => (
+            "UPDATE groups SET last_read_message_id = last_message_id, last_read_message_at = last_message_at
+             WHERE mls_group_id = ? AND account_pubkey = ? AND last_message_id IS NOT NULL",
+            false,
+        ),
+        BulkGroupAction::Leave => (
+            "UPDATE groups SET state = 'Inactive', muted = TRUE, archived_at = COALESCE(archived_at, ?)
+             WHERE mls_group_id = ? AND account_pubkey = ?",
+            true,
+        ),
+    };
+
+    let mut txn = wn.database.pool.begin().await?;
+    for group in groups {
+        let mut query = sqlx::query(statement);
+        if timestamped {
+            query = query.bind(now);
+        }
+        query
+            .bind(&group.mls_group_id)
+            .bind(group.account_pubkey.to_hex())
+            .execute(&mut *txn)
+            .await?;
+    }
+    txn.commit().await?;
+
+    tracing::debug!(
+        target: "whitenoise::bulk_group_actions::apply",
+        "Applied {:?} to {} groups",
+        action,
+        groups.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_deserialize_from_snake_case() {
+        let action: BulkGroupAction = serde_json::from_str("\"mark_read\"").unwrap();
+        assert_eq!(action, BulkGroupAction::MarkRead);
+    }
+
+    #[test]
+    fn test_failed_result_keeps_error() {
+        let result = GroupActionResult::failed("abcd", "Group not found");
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("Group not found"));
+        assert!(GroupActionResult::succeeded("abcd").success);
+    }
+}
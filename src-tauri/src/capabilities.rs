@@ -0,0 +1,158 @@
+//! Platform capability detection.
+//!
+//! Frontends use this to decide which security options to offer (e.g. hardware backed keys)
+//! instead of hard-coding platform checks on their side.
+
+use crate::secrets_store;
+use serde::{Deserialize, Serialize};
+
+/// Where account private keys can be kept
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStorageBackend {
+    /// The obfuscated file-based secrets store in the app data dir
+    File,
+    /// The OS keychain / credential manager
+    OsKeychain,
+    /// A non-exportable key in a secure enclave / TPM / StrongBox with signing delegated to it
+    Hardware,
+}
+
+/// Whether a capability is available and, if not, why
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Capability {
+    pub available: bool,
+    pub reason: Option<String>,
+}
+
+impl Capability {
+    fn available() -> Self {
+        Self {
+            available: true,
+            reason: None,
+        }
+    }
+
+    fn unavailable(reason: &str) -> Self {
+        Self {
+            available: false,
+            reason: Some(reason.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Capabilities {
+    /// "android", "ios", "macos", "windows" or "linux"
+    pub platform: String,
+    /// Hardware security module present on this platform, if any
+    /// ("secure_enclave", "strongbox", "tpm")
+    pub secure_hardware: Option<String>,
+    /// Keys generated and kept non-exportable in secure hardware, with signing delegated to it
+    pub hardware_backed_keys: Capability,
+    /// Secrets kept in the OS keychain / credential manager
+    pub os_keychain: Capability,
+    /// Keeping the window out of screenshots and screen recordings (used for sensitive groups)
+    pub capture_protection: Capability,
+    /// Key storage backends that can be selected on this platform
+    pub key_storage_backends: Vec<KeyStorageBackend>,
+}
+
+/// The secure hardware module we'd expect on the current platform
+fn secure_hardware() -> Option<&'static str> {
+    if cfg!(any(target_os = "ios", target_os = "macos")) {
+        Some("secure_enclave")
+    } else if cfg!(target_os = "android") {
+        Some("strongbox")
+    } else if cfg!(target_os = "windows") {
+        Some("tpm")
+    } else {
+        None
+    }
+}
+
+/// Whether the secrets store has an OS keychain backend for the current platform
+pub fn platform_has_keychain() -> bool {
+    cfg!(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "windows",
+        target_os = "linux"
+    ))
+}
+
+/// Detects what the current platform supports
+pub fn detect() -> Capabilities {
+    let platform = std::env::consts::OS.to_string();
+
+    // Secure Enclave, StrongBox and TPMs only expose NIST curves (P-256), while Nostr keys
+    // are secp256k1 and events are signed with BIP-340 Schnorr signatures. Until a platform
+    // supports that curve we can't keep a Nostr key non-exportable in hardware.
+    let hardware_backed_keys = match secure_hardware() {
+        Some(_) => Capability::unavailable(
+            "Platform secure hardware doesn't support secp256k1 Schnorr signing",
+        ),
+        None => Capability::unavailable("No secure hardware available on this platform"),
+    };
+
+    // The keychain has to actually hold secrets: e.g. Linux desktops may not run a Secret Service
+    let os_keychain = if !platform_has_keychain() {
+        Capability::unavailable("No supported OS keychain on this platform")
+    } else if !secrets_store::keychain_available() {
+        Capability::unavailable("The OS keychain can't store secrets on this device")
+    } else {
+        Capability::available()
+    };
+
+    let capture_protection = if cfg!(any(target_os = "macos", target_os = "windows")) {
+        Capability::available()
+    } else {
+        Capability::unavailable("Screen capture protection isn't supported on this platform")
+    };
+
+    let mut key_storage_backends = vec![KeyStorageBackend::File];
+    if os_keychain.available {
+        key_storage_backends.push(KeyStorageBackend::OsKeychain);
+    }
+    if hardware_backed_keys.available {
+        key_storage_backends.push(KeyStorageBackend::Hardware);
+    }
+
+    Capabilities {
+        platform,
+        secure_hardware: secure_hardware().map(String::from),
+        hardware_backed_keys,
+        os_keychain,
+        capture_protection,
+        key_storage_backends,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_backends_match_capabilities() {
+        let capabilities = detect();
+        assert!(capabilities
+            .key_storage_backends
+            .contains(&KeyStorageBackend::File));
+        assert_eq!(
+            capabilities
+                .key_storage_backends
+                .contains(&KeyStorageBackend::Hardware),
+            capabilities.hardware_backed_keys.available
+        );
+        assert_eq!(
+            capabilities
+                .key_storage_backends
+                .contains(&KeyStorageBackend::OsKeychain),
+            capabilities.os_keychain.available
+        );
+        assert!(
+            capabilities.hardware_backed_keys.available
+                || capabilities.hardware_backed_keys.reason.is_some()
+        );
+    }
+}
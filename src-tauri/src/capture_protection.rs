@@ -0,0 +1,61 @@
+//! Screen capture deterrence for sensitive groups.
+//!
+//! While the active account has at least one group flagged as sensitive, the main window is
+//! marked as content protected so the OS excludes it from screenshots and screen recordings.
+//! This is only a deterrent: it's supported on macOS and Windows, is a no-op elsewhere, and
+//! can't stop someone photographing the screen.
+
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Turns window content protection on or off, where the platform supports it
+#[cfg(desktop)]
+fn set_content_protected(app_handle: &tauri::AppHandle, enabled: bool) -> tauri::Result<()> {
+    use tauri::Manager;
+    if let Some(window) = app_handle.get_webview_window("main") {
+        window.set_content_protected(enabled)?;
+    }
+    Ok(())
+}
+
+#[cfg(mobile)]
+fn set_content_protected(_app_handle: &tauri::AppHandle, _enabled: bool) -> tauri::Result<()> {
+    Ok(())
+}
+
+/// Enables content protection if any of the account's groups is sensitive, disables it otherwise
+///
+/// Failures are logged rather than returned: capture deterrence is best effort and shouldn't
+/// break the operation that triggered the refresh.
+pub async fn refresh(
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) {
+    let any_sensitive = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM groups WHERE account_pubkey = ? AND sensitive = TRUE)",
+    )
+    .bind(account_pubkey.to_hex())
+    .fetch_one(&wn.database.pool)
+    .await;
+
+    let result = match any_sensitive {
+        Ok(enabled) => set_content_protected(app_handle, enabled),
+        Err(e) => {
+            tracing::error!(
+                target: "whitenoise::capture_protection::refresh",
+                "Failed to check for sensitive groups: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::error!(
+            target: "whitenoise::capture_protection::refresh",
+            "Failed to set window content protection: {}",
+            e
+        );
+    }
+}
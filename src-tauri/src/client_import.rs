@@ -0,0 +1,468 @@
+//! Importing accounts from other Nostr clients' exports.
+//!
+//! Two kinds of export are understood, see [`ClientExportFormat`]. Browser signing extensions
+//! export a JSON object with the private key and the relays the user configured. Clients like
+//! Damus and Iris export the account's events instead, which carry its relay lists and contact
+//! list but not its key, so the key has to be given alongside the file.
+//!
+//! Importing logs in with the key, adding the account if this device doesn't have it yet, then
+//! adds the exported relays and contacts on top of what the account already has. Relays are
+//! normalized, and blacklisted ones aren't added. Contacts are left unpublished like any other
+//! local change to the contact list.
+
+use crate::accounts::{Account, AccountError};
+use crate::contacts::{self, ContactError, ListEntry};
+use crate::relay_blacklist::{self, RelayBlacklist, RelayBlacklistError};
+use crate::relays::{self, RelayType};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+
+/// Largest export that's imported. Exports only hold a key, relay lists and a contact list, but
+/// event exports can include the account's whole history.
+const MAX_EXPORT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Fields browser extensions are known to keep the private key in
+const KEY_FIELDS: [&str; 6] = [
+    "privateKey",
+    "private_key",
+    "privkey",
+    "secretKey",
+    "secret_key",
+    "nsec",
+];
+
+#[derive(Error, Debug)]
+pub enum ClientImportError {
+    #[error("The export doesn't contain a private key, and none was given")]
+    MissingKey,
+
+    #[error("The given private key doesn't belong to the exported account")]
+    KeyMismatch,
+
+    #[error("Invalid export: {0}")]
+    InvalidExport(String),
+
+    #[error("Invalid private key: {0}")]
+    KeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Contact error: {0}")]
+    ContactError(#[from] ContactError),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+}
+
+pub type Result<T> = std::result::Result<T, ClientImportError>;
+
+/// The kind of export a file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientExportFormat {
+    /// Browser extensions like nos2x and Alby: a JSON object with the private key and the relays,
+    /// either a map of relay URLs to `read`/`write` flags or a list of URLs
+    Extension,
+    /// Event exports like Damus's and Iris's: a JSON array of signed events, or one event per
+    /// line. Only the account's latest relay list (kind 10002), inbox relays (kind 10050) and
+    /// contact list (kind 3) are used.
+    Events,
+}
+
+/// An exported relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ImportedRelay {
+    url: String,
+    read: bool,
+    write: bool,
+}
+
+/// What was found in an export
+#[derive(Debug, Default)]
+struct ClientExport {
+    keys: Option<Keys>,
+    relays: Vec<ImportedRelay>,
+    inbox_relays: Vec<String>,
+    contacts: Vec<ListEntry>,
+}
+
+/// The outcome of an import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientImport {
+    /// The imported account, which is now the active account
+    pub account: Account,
+    /// Relays that were added to the account
+    pub relays: usize,
+    /// Inbox relays that were added to the account
+    pub inbox_relays: usize,
+    /// Contacts that were added to the contact list
+    pub contacts: usize,
+}
+
+/// The first string in a JSON value, at any depth, under one of the key fields
+fn find_key(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(object) => KEY_FIELDS
+            .iter()
+            .find_map(|field| object.get(*field).and_then(Value::as_str))
+            .or_else(|| object.values().find_map(find_key)),
+        Value::Array(values) => values.iter().find_map(find_key),
+        _ => None,
+    }
+}
+
+/// Reads the relays of a browser extension export
+fn extension_relays(relays: &Value) -> Vec<ImportedRelay> {
+    let flag = |policy: &Value, name: &str| policy.get(name).and_then(Value::as_bool);
+    match relays {
+        Value::Object(relays) => relays
+            .iter()
+            .map(|(url, policy)| ImportedRelay {
+                url: url.clone(),
+                read: flag(policy, "read").unwrap_or(true),
+                write: flag(policy, "write").unwrap_or(true),
+            })
+            .collect(),
+        Value::Array(urls) => urls
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|url| ImportedRelay {
+                url: url.to_string(),
+                read: true,
+                write: true,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_extension(content: &str) -> Result<ClientExport> {
+    let json: Value = serde_json::from_str(content)
+        .map_err(|e| ClientImportError::InvalidExport(e.to_string()))?;
+    if !json.is_object() {
+        return Err(ClientImportError::InvalidExport(
+            "Expected a JSON object".to_string(),
+        ));
+    }
+    Ok(ClientExport {
+        keys: find_key(&json).map(Keys::parse).transpose()?,
+        relays: json.get("relays").map(extension_relays).unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// The values of an event's tags with the given name
+fn tag_values<'a>(event: &'a Event, name: &'a str) -> impl Iterator<Item = &'a [String]> {
+    event
+        .tags
+        .iter()
+        .map(|tag| tag.as_slice())
+        .filter(move |values| values.first().map(String::as_str) == Some(name))
+}
+
+fn parse_events(content: &str, pubkey: &PublicKey) -> Result<ClientExport> {
+    let events: Vec<Event> = match serde_json::from_str::<Vec<Event>>(content) {
+        Ok(events) => events,
+        Err(_) => content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| Event::from_json(line).ok())
+            .collect(),
+    };
+    if events.is_empty() {
+        return Err(ClientImportError::InvalidExport(
+            "No events found".to_string(),
+        ));
+    }
+
+    let latest = |kind: Kind| {
+        events
+            .iter()
+            .filter(|event| event.kind == kind && event.pubkey == *pubkey)
+            .filter(|event| event.verify().is_ok())
+            .max_by_key(|event| event.created_at)
+    };
+
+    let mut export = ClientExport::default();
+    if let Some(event) = latest(Kind::RelayList) {
+        export.relays = tag_values(event, "r")
+            .filter_map(|values| {
+                let url = values.get(1)?.clone();
+                let marker = values.get(2).map(String::as_str);
+                Some(ImportedRelay {
+                    url,
+                    read: marker != Some("write"),
+                    write: marker != Some("read"),
+                })
+            })
+            .collect();
+    }
+    if let Some(event) = latest(Kind::InboxRelays) {
+        export.inbox_relays = tag_values(event, "relay")
+            .filter_map(|values| values.get(1).cloned())
+            .collect();
+    }
+    if let Some(event) = latest(Kind::ContactList) {
+        export.contacts = contacts::entries_from_event(event);
+    }
+    Ok(export)
+}
+
+/// The keys to import with: the exported key, the given one, or both if they match
+fn resolve_keys(exported: Option<Keys>, given: Option<&str>) -> Result<Keys> {
+    let given = given.map(Keys::parse).transpose()?;
+    match (exported, given) {
+        (Some(exported), Some(given)) if exported.public_key() != given.public_key() => {
+            Err(ClientImportError::KeyMismatch)
+        }
+        (Some(keys), _) | (None, Some(keys)) => Ok(keys),
+        (None, None) => Err(ClientImportError::MissingKey),
+    }
+}
+
+/// Reads an export, refusing files larger than [`MAX_EXPORT_SIZE`]
+async fn read_export(path: &Path) -> Result<String> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut data = Vec::new();
+    file.take(MAX_EXPORT_SIZE + 1)
+        .read_to_end(&mut data)
+        .await?;
+    if data.len() as u64 > MAX_EXPORT_SIZE {
+        return Err(ClientImportError::InvalidExport(format!(
+            "Exports can't be larger than {} MiB",
+            MAX_EXPORT_SIZE / 1024 / 1024
+        )));
+    }
+    String::from_utf8(data).map_err(|e| ClientImportError::InvalidExport(e.to_string()))
+}
+
+/// The normalized form of relay URLs, leaving out invalid ones
+fn normalized(urls: &[String]) -> Vec<String> {
+    urls.iter()
+        .filter_map(|url| relay_blacklist::normalize_url(url).ok())
+        .collect()
+}
+
+/// Imports an account from another client's export and makes it the active account. Refused
+/// while the app is locked.
+///
+/// # Arguments
+/// * `path` - Path of the export file
+/// * `format` - The kind of export
+/// * `nsec_or_hex_privkey` - The account's private key, for exports that don't contain it
+pub async fn import(
+    path: &Path,
+    format: ClientExportFormat,
+    nsec_or_hex_privkey: Option<&str>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<ClientImport> {
+    if wn.app_lock.lock().await.locked {
+        return Err(AccountError::AppLocked.into());
+    }
+    let content = read_export(path).await?;
+    let (keys, export) = match format {
+        ClientExportFormat::Extension => {
+            let mut export = parse_extension(&content)?;
+            (
+                resolve_keys(export.keys.take(), nsec_or_hex_privkey)?,
+                export,
+            )
+        }
+        ClientExportFormat::Events => {
+            let keys = resolve_keys(None, nsec_or_hex_privkey)?;
+            let export = parse_events(&content, &keys.public_key())?;
+            (keys, export)
+        }
+    };
+
+    let account = match Account::find_by_pubkey(&keys.public_key(), wn.clone()).await {
+        Ok(account) => account.set_active(wn.clone(), app_handle).await?,
+        Err(_) => Account::add_from_keys(&keys, true, wn.clone(), app_handle).await?,
+    };
+
+    let mut known_relays = normalized(&account.relays(RelayType::Nostr, wn.clone()).await?);
+    let mut added_relays = 0;
+    for relay in &export.relays {
+        let url = match relay_blacklist::normalize_url(&relay.url) {
+            Ok(url) => url,
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::client_import::import",
+                    "Skipping relay {}: {}",
+                    relay.url,
+                    e
+                );
+                continue;
+            }
+        };
+        // Relays the account already has keep their read/write setting
+        if known_relays.contains(&url) {
+            continue;
+        }
+        known_relays.push(url.clone());
+        // One relay we can't use shouldn't fail the import of the rest
+        match relays::add(&url, relay.read, relay.write, wn.clone()).await {
+            Ok(_) => added_relays += 1,
+            Err(e) => tracing::warn!(
+                target: "whitenoise::client_import::import",
+                "Skipping relay {}: {}",
+                relay.url,
+                e
+            ),
+        }
+    }
+
+    let blacklist = RelayBlacklist::load(wn.clone()).await?;
+    let existing_inbox = normalized(&account.relays(RelayType::Inbox, wn.clone()).await?);
+    let mut inbox_relays: Vec<String> = Vec::new();
+    for url in normalized(&export.inbox_relays) {
+        if !blacklist.contains(&url)
+            && !existing_inbox.contains(&url)
+            && !inbox_relays.contains(&url)
+        {
+            inbox_relays.push(url);
+        }
+    }
+    account
+        .update_relays(RelayType::Inbox, &inbox_relays, wn.clone())
+        .await?;
+
+    let added_contacts = contacts::import(&account, &export.contacts, wn.clone()).await?;
+
+    tracing::info!(
+        target: "whitenoise::client_import::import",
+        "Imported {:?} export for {}: {} relays, {} inbox relays, {} contacts",
+        format,
+        account.pubkey.to_hex(),
+        added_relays,
+        inbox_relays.len(),
+        added_contacts
+    );
+    Ok(ClientImport {
+        account: Account::find_by_pubkey(&keys.public_key(), wn).await?,
+        relays: added_relays,
+        inbox_relays: inbox_relays.len(),
+        contacts: added_contacts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_export() {
+        let keys = Keys::generate();
+        let content = serde_json::json!({
+            "privateKey": keys.secret_key().to_secret_hex(),
+            "relays": {
+                "wss://relay.example.com": { "read": true, "write": false },
+                "wss://other.example.com": {}
+            }
+        })
+        .to_string();
+
+        let export = parse_extension(&content).unwrap();
+        assert_eq!(export.keys.unwrap().public_key(), keys.public_key());
+        assert_eq!(export.relays.len(), 2);
+        assert!(export.relays.contains(&ImportedRelay {
+            url: "wss://relay.example.com".to_string(),
+            read: true,
+            write: false,
+        }));
+
+        // Keys nested in a settings object and relays as a list of URLs
+        let content = serde_json::json!({
+            "nostr": { "nsec": keys.secret_key().to_bech32().unwrap() },
+            "relays": ["wss://relay.example.com"]
+        })
+        .to_string();
+        let export = parse_extension(&content).unwrap();
+        assert_eq!(export.keys.unwrap().public_key(), keys.public_key());
+        assert!(export.relays[0].read && export.relays[0].write);
+
+        assert!(parse_extension("[]").is_err());
+    }
+
+    #[test]
+    fn test_events_export_uses_the_accounts_latest_lists() {
+        let keys = Keys::generate();
+        let contact = Keys::generate().public_key();
+        let relay_list = |url: &str, at: u64| {
+            EventBuilder::new(Kind::RelayList, "")
+                .tags([Tag::parse(["r", url, "write"]).unwrap()])
+                .custom_created_at(Timestamp::from(at))
+                .sign_with_keys(&keys)
+                .unwrap()
+        };
+        let events = vec![
+            relay_list("wss://old.example.com", 1),
+            relay_list("wss://new.example.com", 2),
+            EventBuilder::new(Kind::ContactList, "")
+                .tags([Tag::public_key(contact)])
+                .sign_with_keys(&keys)
+                .unwrap(),
+            // Someone else's relay list in the same export
+            EventBuilder::new(Kind::RelayList, "")
+                .tags([Tag::parse(["r", "wss://stranger.example.com"]).unwrap()])
+                .custom_created_at(Timestamp::from(3))
+                .sign_with_keys(&Keys::generate())
+                .unwrap(),
+        ];
+
+        let content = serde_json::to_string(&events).unwrap();
+        let export = parse_events(&content, &keys.public_key()).unwrap();
+        assert_eq!(
+            export.relays,
+            vec![ImportedRelay {
+                url: "wss://new.example.com".to_string(),
+                read: false,
+                write: true,
+            }]
+        );
+        assert_eq!(export.contacts.len(), 1);
+        assert_eq!(export.contacts[0].0, contact.to_hex());
+
+        // One event per line works too
+        let lines: Vec<String> = events.iter().map(|event| event.as_json()).collect();
+        let export = parse_events(&lines.join("\n"), &keys.public_key()).unwrap();
+        assert_eq!(export.contacts.len(), 1);
+
+        assert!(parse_events("not an export", &keys.public_key()).is_err());
+    }
+
+    #[test]
+    fn test_resolve_keys() {
+        let keys = Keys::generate();
+        let hex = keys.secret_key().to_secret_hex();
+
+        assert!(matches!(
+            resolve_keys(None, None),
+            Err(ClientImportError::MissingKey)
+        ));
+        assert_eq!(
+            resolve_keys(None, Some(&hex)).unwrap().public_key(),
+            keys.public_key()
+        );
+        assert_eq!(
+            resolve_keys(Some(keys.clone()), Some(&hex))
+                .unwrap()
+                .public_key(),
+            keys.public_key()
+        );
+        assert!(matches!(
+            resolve_keys(Some(Keys::generate()), Some(&hex)),
+            Err(ClientImportError::KeyMismatch)
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
+use crate::recovery;
+use crate::whitenoise::Whitenoise;
+
+/// Approves a request to help recover an account, sending the active account's share of its
+/// recovery secret to the new identity.
+///
+/// Only approve after confirming with the person, through another channel, that the new
+/// identity is really theirs.
+///
+/// # Arguments
+///
+/// * `old_pubkey` - Hex public key of the account being recovered
+/// * `new_pubkey` - Hex public key of the new identity that asked
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the share was sent
+/// * `Err(WhitenoiseError)` - An error message if there's no such request or the share couldn't be
+///   sent
+#[tauri::command]
+pub async fn approve_recovery_request(
+    old_pubkey: PubkeyParam,
+    new_pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let old_pubkey = old_pubkey.public_key();
+    let new_pubkey = new_pubkey.public_key();
+
+    recovery::approve(&old_pubkey, &new_pubkey, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error approving recovery request: {}", e)))
+}
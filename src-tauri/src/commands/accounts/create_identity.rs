@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -12,17 +13,17 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(Account)` - The newly created account.
-/// * `Err(String)` - An error message if there was an issue creating the identity.
+/// * `Err(WhitenoiseError)` - An error message if there was an issue creating the identity.
 #[tauri::command]
 pub async fn create_identity(
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<Account, String> {
+) -> Result<Account, WhitenoiseError> {
     let account = Account::new(wn.clone())
         .await
-        .map_err(|e| format!("Error creating account: {}", e))?;
+        .context("Error creating account")?;
     account
         .set_active(wn.clone(), &app_handle)
         .await
-        .map_err(|e| format!("Error setting active account: {}", e))
+        .context("Error setting active account")
 }
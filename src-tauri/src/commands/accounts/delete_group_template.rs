@@ -0,0 +1,32 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_templates;
+use crate::whitenoise::Whitenoise;
+
+/// Removes a group template from the active account. Groups created from it are left as they
+/// are.
+///
+/// # Arguments
+///
+/// * `template_id` - ID of the template to remove
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if there's no such template or the account can't be
+///   saved
+#[tauri::command]
+pub async fn delete_group_template(
+    template_id: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    group_templates::remove(&mut account.settings.group_templates, &template_id)?;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
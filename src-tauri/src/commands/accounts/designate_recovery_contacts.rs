@@ -0,0 +1,37 @@
+use crate::accounts::Account;
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
+use crate::recovery;
+use crate::whitenoise::Whitenoise;
+
+/// Makes the given contacts the active account's recovery contacts.
+///
+/// Each contact is sent a share of a new recovery secret; any `threshold` of them can later help
+/// a new identity recover the account's group access, see `request_account_recovery`. Contacts
+/// that were recovery contacts before but aren't in the list are asked to delete their share.
+/// An empty list with a threshold of 0 turns recovery off.
+///
+/// # Arguments
+///
+/// * `pubkeys` - Hex or npub public keys of the recovery contacts, at most 16
+/// * `threshold` - How many of them it takes to recover the account
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if the contacts or threshold are invalid, or a share
+///   couldn't be sent
+#[tauri::command]
+pub async fn designate_recovery_contacts(
+    pubkeys: Vec<PubkeyParam>,
+    threshold: u8,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let pubkeys: Vec<String> = pubkeys.iter().map(PubkeyParam::to_hex).collect();
+    recovery::designate(&pubkeys, threshold, wn.clone())
+        .await
+        .map_err(|e| {
+            WhitenoiseError::Internal(format!("Error designating recovery contacts: {}", e))
+        })
+}
@@ -0,0 +1,36 @@
+use crate::account_backup;
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
+use crate::sensitive_actions::{self, SensitiveAction};
+use crate::whitenoise::Whitenoise;
+
+/// Exports an account's private key, groups, export secrets and MLS group state to a
+/// passphrase-encrypted backup file.
+///
+/// # Arguments
+///
+/// * `pubkey` - The hex encoded public key of the account to back up
+/// * `passphrase` - The passphrase the backup is encrypted with, at least 8 characters
+/// * `confirmation_token` - A token from `request_sensitive_action` for `export_account`
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(String)` - The path of the backup file
+/// * `Err(WhitenoiseError)` - An error message if the token is invalid, the account can't be found
+///   or the backup can't be written
+#[tauri::command]
+pub async fn export_account(
+    pubkey: PubkeyParam,
+    passphrase: String,
+    confirmation_token: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    sensitive_actions::authorize(SensitiveAction::ExportAccount, &confirmation_token, &wn).await?;
+    let pubkey = pubkey.public_key();
+
+    account_backup::export(&pubkey, &passphrase, wn.clone())
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| WhitenoiseError::Internal(format!("Error exporting account: {}", e)))
+}
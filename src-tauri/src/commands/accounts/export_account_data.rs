@@ -0,0 +1,41 @@
+use crate::data_export;
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
+use crate::sensitive_actions::{self, SensitiveAction};
+use crate::whitenoise::Whitenoise;
+use std::path::PathBuf;
+
+/// Exports an account's profile, settings, contacts, groups and message transcripts to a JSON
+/// file, for reading by people and other apps. See `data_export` for the format.
+///
+/// Unlike `export_account`, the file isn't encrypted and contains no keys or secrets, so it
+/// can't be used to restore the account.
+///
+/// # Arguments
+///
+/// * `pubkey` - The hex encoded public key of the account to export
+/// * `path` - The absolute path of the file to write
+/// * `confirmation_token` - A token from `request_sensitive_action` for `export_account_data`
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(String)` - The path of the export file
+/// * `Err(WhitenoiseError)` - An error message if the app is locked, the token or the path is
+///   invalid, or the export can't be written
+#[tauri::command]
+pub async fn export_account_data(
+    pubkey: PubkeyParam,
+    path: String,
+    confirmation_token: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    sensitive_actions::authorize(SensitiveAction::ExportAccountData, &confirmation_token, &wn)
+        .await?;
+    let pubkey = pubkey.public_key();
+
+    data_export::export(&pubkey, &PathBuf::from(path), wn.clone())
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| WhitenoiseError::Internal(format!("Error exporting account data: {}", e)))
+}
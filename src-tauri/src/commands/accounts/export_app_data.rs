@@ -0,0 +1,34 @@
+use crate::app_data;
+use crate::error::WhitenoiseError;
+use crate::sensitive_actions::{self, SensitiveAction};
+use crate::whitenoise::Whitenoise;
+use std::path::PathBuf;
+
+/// Exports every account with its keys, groups, MLS state, secrets and message transcripts to
+/// a passphrase-encrypted archive, for moving the app to another device.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path of the archive to write
+/// * `passphrase` - The passphrase the archive is encrypted with, at least 8 characters
+/// * `confirmation_token` - A token from `request_sensitive_action` for `export_app_data`
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(String)` - The path of the archive
+/// * `Err(WhitenoiseError)` - An error message if the app is locked, the token is invalid or the
+///   archive can't be written
+#[tauri::command]
+pub async fn export_app_data(
+    path: String,
+    passphrase: String,
+    confirmation_token: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    sensitive_actions::authorize(SensitiveAction::ExportAppData, &confirmation_token, &wn).await?;
+    app_data::export(&PathBuf::from(path), &passphrase, wn.clone())
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| WhitenoiseError::Internal(format!("Error exporting app data: {}", e)))
+}
@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -11,10 +12,12 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(Vec<Account>)` - A vector of accounts if successful.
-/// * `Err(String)` - An error message if there was an issue listing the accounts.
+/// * `Err(WhitenoiseError)` - An error message if there was an issue listing the accounts.
 #[tauri::command]
-pub async fn get_accounts(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Account>, String> {
+pub async fn get_accounts(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Account>, WhitenoiseError> {
     Account::all(wn.clone())
         .await
-        .map_err(|e| format!("Error fetching accounts: {}", e))
+        .context("Error fetching accounts")
 }
@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use nwc::prelude::*;
@@ -12,25 +13,25 @@ use nwc::prelude::*;
 /// # Returns
 ///
 /// * `Ok(u64)` - The balance in sats if successful
-/// * `Err(String)` - An error message if there was an issue getting the balance
+/// * `Err(WhitenoiseError)` - An error message if there was an issue getting the balance
 #[tauri::command]
 pub async fn get_nostr_wallet_connect_balance(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<u64, String> {
+) -> Result<u64, WhitenoiseError> {
     let active_account = Account::get_active(wn.clone())
         .await
-        .map_err(|e| format!("Error getting active account: {}", e))?;
+        .context("Error getting active account")?;
 
     let nwc_uri = active_account
         .get_nostr_wallet_connect_uri(wn.clone())
-        .map_err(|e| format!("Error getting NWC URI: {}", e))?
-        .ok_or_else(|| "No NWC URI configured".to_string())?;
+        .context("Error getting NWC URI")?
+        .ok_or_else(|| WhitenoiseError::NotFound("No NWC URI configured".to_string()))?;
 
     let uri = NostrWalletConnectURI::parse(&nwc_uri)
-        .map_err(|e| format!("Error parsing NWC URI: {}", e))?;
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Error parsing NWC URI: {}", e)))?;
     let nwc = NWC::new(uri);
 
     nwc.get_balance()
         .await
-        .map_err(|e| format!("Error getting NWC info: {}", e))
+        .map_err(|e| WhitenoiseError::Internal(format!("Error getting NWC info: {}", e)))
 }
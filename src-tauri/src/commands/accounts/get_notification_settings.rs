@@ -0,0 +1,23 @@
+use crate::error::WhitenoiseError;
+use crate::notifications::{self, NotificationSettings};
+use crate::whitenoise::Whitenoise;
+
+/// Gets the active account's notification settings: quiet hours, whether invites notify, and
+/// the notification level of each of its groups.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(NotificationSettings)` - The settings if successful
+/// * `Err(WhitenoiseError)` - An error message if the account or its groups couldn't be loaded
+#[tauri::command]
+pub async fn get_notification_settings(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<NotificationSettings, WhitenoiseError> {
+    notifications::settings(wn).await.map_err(|e| {
+        WhitenoiseError::Internal(format!("Error fetching notification settings: {}", e))
+    })
+}
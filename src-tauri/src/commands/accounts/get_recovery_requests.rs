@@ -0,0 +1,23 @@
+use crate::error::WhitenoiseError;
+use crate::recovery::{self, RecoveryRequest};
+use crate::whitenoise::Whitenoise;
+
+/// Gets the requests to help recover an account the active account is a recovery contact of,
+/// that haven't been approved yet. New ones are also emitted as `recovery_requested`.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Vec<RecoveryRequest>)` - The pending requests, newest first
+/// * `Err(WhitenoiseError)` - An error message if the requests can't be loaded
+#[tauri::command]
+pub async fn get_recovery_requests(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<RecoveryRequest>, WhitenoiseError> {
+    recovery::pending_requests(wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching recovery requests: {}", e)))
+}
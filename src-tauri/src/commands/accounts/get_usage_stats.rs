@@ -0,0 +1,29 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::usage_stats::{self, UsagePeriod, UsageStats};
+use crate::whitenoise::Whitenoise;
+
+/// Summarizes the active account's usage over a period, from statistics that are only kept on
+/// this device
+///
+/// # Arguments
+/// * `period` - The period to summarize, ending today: `day`, `week`, `month`, `year` or `all`
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(UsageStats)` - Messages sent and received per day, the most active groups and the
+///   volume of media
+/// * `Err(WhitenoiseError)` - Error message if there's no active account or the statistics can't be
+///   read
+#[tauri::command]
+pub async fn get_usage_stats(
+    period: UsagePeriod,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<UsageStats, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    usage_stats::get(&account, period, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching usage stats: {}", e)))
+}
@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -11,17 +12,17 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(bool)` - true if a NWC URI is configured, false otherwise
-/// * `Err(String)` - An error message if there was an issue checking the NWC URI
+/// * `Err(WhitenoiseError)` - An error message if there was an issue checking the NWC URI
 #[tauri::command]
 pub async fn has_nostr_wallet_connect_uri(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<bool, String> {
+) -> Result<bool, WhitenoiseError> {
     let active_account = Account::get_active(wn.clone())
         .await
-        .map_err(|e| format!("Error getting active account: {}", e))?;
+        .context("Error getting active account")?;
 
     active_account
         .get_nostr_wallet_connect_uri(wn.clone())
         .map(|opt| opt.is_some())
-        .map_err(|e| format!("Error checking NWC URI: {}", e))
+        .context("Error checking NWC URI")
 }
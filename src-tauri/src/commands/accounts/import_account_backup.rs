@@ -0,0 +1,37 @@
+use crate::account_backup;
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+use std::path::PathBuf;
+
+/// Restores an account from a backup file created by `export_account` and makes it the active
+/// account.
+///
+/// # Arguments
+///
+/// * `path` - Path of the backup file
+/// * `passphrase` - The passphrase the backup was encrypted with
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri application handle
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The restored account
+/// * `Err(WhitenoiseError)` - An error message if the passphrase is wrong or the backup can't be
+///   restored
+#[tauri::command]
+pub async fn import_account_backup(
+    path: String,
+    passphrase: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Account, WhitenoiseError> {
+    let account = account_backup::import(&PathBuf::from(path), &passphrase, wn.clone())
+        .await
+        .context("Error importing account backup")?;
+
+    account
+        .set_active(wn.clone(), &app_handle)
+        .await
+        .context("Error importing account backup")
+}
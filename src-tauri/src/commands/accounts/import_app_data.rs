@@ -0,0 +1,45 @@
+use crate::accounts::Account;
+use crate::app_data;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+use std::path::PathBuf;
+
+/// Restores every account from an archive created by `export_app_data` and makes the one that
+/// was active when it was created the active account.
+///
+/// # Arguments
+///
+/// * `path` - Path of the archive
+/// * `passphrase` - The passphrase the archive was encrypted with
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri application handle
+///
+/// # Returns
+///
+/// * `Ok(Vec<Account>)` - The restored accounts
+/// * `Err(WhitenoiseError)` - An error message if the passphrase is wrong or the archive can't be
+///   restored
+#[tauri::command]
+pub async fn import_app_data(
+    path: String,
+    passphrase: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<Account>, WhitenoiseError> {
+    let (mut accounts, active_pubkey) =
+        app_data::import(&PathBuf::from(path), &passphrase, wn.clone())
+            .await
+            .context("Error importing app data")?;
+
+    let active = accounts
+        .iter()
+        .position(|account| Some(account.pubkey) == active_pubkey)
+        .or((!accounts.is_empty()).then_some(0));
+    if let Some(index) = active {
+        accounts[index] = accounts[index]
+            .set_active(wn.clone(), &app_handle)
+            .await
+            .context("Error importing app data")?;
+    }
+    Ok(accounts)
+}
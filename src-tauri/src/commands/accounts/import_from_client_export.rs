@@ -0,0 +1,43 @@
+use crate::client_import::{self, ClientExportFormat, ClientImport};
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+use std::path::PathBuf;
+
+/// Imports an account from another Nostr client's export and makes it the active account.
+///
+/// The account's key, relays and contacts are taken from the export, whichever it has. Relays
+/// and contacts are added to those the account already has on this device.
+///
+/// # Arguments
+///
+/// * `path` - Path of the export file
+/// * `format` - The kind of export: `extension` for browser extension exports like nos2x's and
+///   Alby's, `events` for event exports like Damus's and Iris's
+/// * `nsec_or_hex_privkey` - The account's private key, required for exports that don't contain
+///   it
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri application handle
+///
+/// # Returns
+///
+/// * `Ok(ClientImport)` - The imported account and how many relays and contacts were added
+/// * `Err(WhitenoiseError)` - An error message if the app is locked, the export can't be read or
+///   has no usable key, or the account can't be added
+#[tauri::command]
+pub async fn import_from_client_export(
+    path: String,
+    format: ClientExportFormat,
+    nsec_or_hex_privkey: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<ClientImport, WhitenoiseError> {
+    client_import::import(
+        &PathBuf::from(path),
+        format,
+        nsec_or_hex_privkey.as_deref(),
+        wn,
+        &app_handle,
+    )
+    .await
+    .map_err(|e| WhitenoiseError::Internal(format!("Error importing client export: {}", e)))
+}
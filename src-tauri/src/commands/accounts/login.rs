@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -12,14 +13,15 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(Account)` - The account if login was successful.
-/// * `Err(String)` - An error message if there was an issue logging in.
+/// * `Err(WhitenoiseError)` - An error message if there was an issue logging in.
 #[tauri::command]
 pub async fn login(
     nsec_or_hex_privkey: String,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<Account, String> {
-    let keys = Keys::parse(&nsec_or_hex_privkey).map_err(|e| e.to_string())?;
+) -> Result<Account, WhitenoiseError> {
+    let keys = Keys::parse(&nsec_or_hex_privkey)
+        .map_err(|e| WhitenoiseError::InvalidKey(e.to_string()))?;
 
     match Account::find_by_pubkey(&keys.public_key, wn.clone()).await {
         Ok(account) => {
@@ -27,13 +29,13 @@ pub async fn login(
             account
                 .set_active(wn.clone(), &app_handle)
                 .await
-                .map_err(|e| format!("Error logging in: {}", e))
+                .context("Error logging in")
         }
         _ => {
             tracing::debug!(target: "whitenoise::commands::accounts","Account not found, adding from keys");
             Account::add_from_keys(&keys, true, wn.clone(), &app_handle)
                 .await
-                .map_err(|e| format!("Error logging in: {}", e))
+                .context("Error logging in")
         }
     }
 }
@@ -1,6 +1,8 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
+use crate::sensitive_actions::{self, SensitiveAction};
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Logs out the specified account.
 ///
@@ -9,28 +11,34 @@ use nostr_sdk::prelude::*;
 /// 2. Removes the private key from the secrets store
 /// 3. Updates the Nostr identity to the new active account if needed
 ///
+/// Requires a token from `request_sensitive_action` for `delete_account`.
+///
 /// # Arguments
 ///
 /// * `wn` - A reference to the Whitenoise state
 /// * `hex_pubkey` - The public key in hexadecimal format of the account to log out
+/// * `confirmation_token` - The confirmation token
 ///
 /// # Returns
 ///
 /// * `Ok(())` - If the logout was successful
-/// * `Err(String)` - An error message if there was an issue during logout
+/// * `Err(WhitenoiseError)` - An error message if there was an issue during logout
 #[tauri::command]
 pub async fn logout(
-    hex_pubkey: String,
+    hex_pubkey: PubkeyParam,
+    confirmation_token: String,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let pubkey =
-        PublicKey::parse(&hex_pubkey).map_err(|e| format!("Error parsing public key: {}", e))?;
+) -> Result<(), WhitenoiseError> {
+    sensitive_actions::authorize(SensitiveAction::DeleteAccount, &confirmation_token, &wn)
+        .await
+        .context("Error confirming logout")?;
+    let pubkey = hex_pubkey.public_key();
     let account = Account::find_by_pubkey(&pubkey, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching account: {}", e))?;
+        .context("Error fetching account")?;
     account
         .remove(wn.clone(), app_handle)
         .await
-        .map_err(|e| format!("Error logging out: {}", e))
+        .context("Error logging out")
 }
@@ -1,23 +1,79 @@
+mod approve_recovery_request;
 mod create_identity;
+mod delete_group_template;
+mod designate_recovery_contacts;
+mod export_account;
+mod export_account_data;
+mod export_app_data;
 mod get_accounts;
 mod get_nostr_wallet_connect_balance;
+mod get_notification_settings;
+mod get_recovery_requests;
+mod get_usage_stats;
 mod has_nostr_wallet_connect_uri;
+mod import_account_backup;
+mod import_app_data;
+mod import_from_client_export;
 mod login;
 mod logout;
 mod publish_metadata_event;
 mod remove_nostr_wallet_connect_uri;
+mod request_account_recovery;
+mod save_group_template;
 mod set_active_account;
+mod set_auto_lock;
+mod set_content_filter;
+mod set_device_local_settings;
+mod set_device_sync;
+mod set_export_secret_retention;
+mod set_fallback_relays;
+mod set_key_package_pool_size;
+mod set_media_server;
 mod set_nostr_wallet_connect_uri;
+mod set_notification_settings;
+mod set_send_read_receipts;
+mod set_sync_policy;
+mod set_sync_schedule;
+mod set_whitelist_only_mode;
 mod update_account_onboarding;
+mod update_profile;
 
+pub use approve_recovery_request::approve_recovery_request;
 pub use create_identity::create_identity;
+pub use delete_group_template::delete_group_template;
+pub use designate_recovery_contacts::designate_recovery_contacts;
+pub use export_account::export_account;
+pub use export_account_data::export_account_data;
+pub use export_app_data::export_app_data;
 pub use get_accounts::get_accounts;
 pub use get_nostr_wallet_connect_balance::get_nostr_wallet_connect_balance;
+pub use get_notification_settings::get_notification_settings;
+pub use get_recovery_requests::get_recovery_requests;
+pub use get_usage_stats::get_usage_stats;
 pub use has_nostr_wallet_connect_uri::has_nostr_wallet_connect_uri;
+pub use import_account_backup::import_account_backup;
+pub use import_app_data::import_app_data;
+pub use import_from_client_export::import_from_client_export;
 pub use login::login;
 pub use logout::logout;
 pub use publish_metadata_event::publish_metadata_event;
 pub use remove_nostr_wallet_connect_uri::remove_nostr_wallet_connect_uri;
+pub use request_account_recovery::request_account_recovery;
+pub use save_group_template::save_group_template;
 pub use set_active_account::set_active_account;
+pub use set_auto_lock::set_auto_lock;
+pub use set_content_filter::set_content_filter;
+pub use set_device_local_settings::set_device_local_settings;
+pub use set_device_sync::set_device_sync;
+pub use set_export_secret_retention::set_export_secret_retention;
+pub use set_fallback_relays::set_fallback_relays;
+pub use set_key_package_pool_size::set_key_package_pool_size;
+pub use set_media_server::set_media_server;
 pub use set_nostr_wallet_connect_uri::set_nostr_wallet_connect_uri;
+pub use set_notification_settings::set_notification_settings;
+pub use set_send_read_receipts::set_send_read_receipts;
+pub use set_sync_policy::set_sync_policy;
+pub use set_sync_schedule::set_sync_schedule;
+pub use set_whitelist_only_mode::set_whitelist_only_mode;
 pub use update_account_onboarding::update_account_onboarding;
+pub use update_profile::{update_profile, ProfileUpdate};
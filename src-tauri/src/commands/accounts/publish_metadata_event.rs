@@ -1,4 +1,6 @@
 use crate::accounts::Account;
+use crate::error::WhitenoiseError;
+use crate::relay_blacklist;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use tauri::Emitter;
@@ -7,42 +9,38 @@ use tauri::Emitter;
 ///
 /// This function performs two main operations:
 /// 1. Updates the local account's metadata and saves it
-/// 2. Publishes the metadata event to all connected Nostr relays
+/// 2. Publishes the metadata event to all connected Nostr relays that aren't blacklisted
 ///
 /// # Arguments
 /// * `new_metadata` - The new metadata to publish and save
 /// * `wn` - The Whitenoise application state containing the Nostr client
 ///
 /// # Returns
-/// * `Result<(), String>` - Returns Ok(()) on success, or an error message on failure
+/// * `Result<(), WhitenoiseError>` - Returns Ok(()) on success, or an error on failure
 #[tauri::command]
 pub async fn publish_metadata_event(
     new_metadata: Metadata,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let mut account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<(), WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone()).await?;
 
     account.metadata = new_metadata.clone();
-    account.save(wn.clone()).await.map_err(|e| e.to_string())?;
+    account.save(wn.clone()).await?;
     tracing::debug!("Saved updated metadata");
 
-    let metadata_json = serde_json::to_string(&new_metadata).map_err(|e| e.to_string())?;
-    let event = EventBuilder::new(Kind::Metadata, metadata_json);
-
-    wn.nostr
+    let metadata_json = serde_json::to_string(&new_metadata)?;
+    let event = wn
+        .nostr
         .client
-        .send_event_builder(event.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+        .sign_event_builder(EventBuilder::new(Kind::Metadata, metadata_json))
+        .await?;
+
+    relay_blacklist::send_event(&event, vec![], wn.clone()).await?;
 
     tracing::debug!("Published metadata event to relays: {:?}", event);
 
-    app_handle
-        .emit("account_updated", ())
-        .map_err(|e| e.to_string())?;
+    app_handle.emit("account_updated", ())?;
 
     Ok(())
 }
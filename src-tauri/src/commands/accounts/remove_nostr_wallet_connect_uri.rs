@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -11,16 +12,16 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(())` - If the URI was removed successfully
-/// * `Err(String)` - An error message if there was an issue removing the URI
+/// * `Err(WhitenoiseError)` - An error message if there was an issue removing the URI
 #[tauri::command]
 pub async fn remove_nostr_wallet_connect_uri(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
+) -> Result<(), WhitenoiseError> {
     let active_account = Account::get_active(wn.clone())
         .await
-        .map_err(|e| format!("Error getting active account: {}", e))?;
+        .context("Error getting active account")?;
 
     active_account
         .remove_nostr_wallet_connect_uri(wn.clone())
-        .map_err(|e| format!("Error removing NWC URI: {}", e))
+        .context("Error removing NWC URI")
 }
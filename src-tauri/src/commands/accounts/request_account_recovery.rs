@@ -0,0 +1,39 @@
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
+use crate::recovery;
+use crate::whitenoise::Whitenoise;
+
+/// Asks the recovery contacts of a lost account to help the active account, a new identity,
+/// take its place.
+///
+/// Each contact who approves the request releases their share of the recovery secret. Once
+/// enough shares arrived, `account_recovery_completed` is emitted and the contacts are prompted
+/// to re-invite the new identity to the groups they shared with the old one; until then each
+/// share emits `recovery_share_received` with the progress.
+///
+/// # Arguments
+///
+/// * `old_pubkey` - Hex or npub public key of the lost account
+/// * `contact_pubkeys` - Hex or npub public keys of its recovery contacts
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the request was sent to every contact
+/// * `Err(WhitenoiseError)` - An error message if a key is invalid or a request couldn't be sent
+#[tauri::command]
+pub async fn request_account_recovery(
+    old_pubkey: PubkeyParam,
+    contact_pubkeys: Vec<PubkeyParam>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let old_pubkey = old_pubkey.public_key();
+    let contacts: Vec<_> = contact_pubkeys
+        .iter()
+        .map(PubkeyParam::public_key)
+        .collect();
+
+    recovery::request(&old_pubkey, &contacts, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error requesting account recovery: {}", e)))
+}
@@ -0,0 +1,33 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_templates::{self, GroupTemplate};
+use crate::whitenoise::Whitenoise;
+
+/// Adds a group template to the active account, or replaces the template with the same ID.
+///
+/// Groups are created from templates with `create_group_from_template`.
+///
+/// # Arguments
+///
+/// * `template` - The template to save
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if the template is invalid or the account can't be
+///   saved
+#[tauri::command]
+pub async fn save_group_template(
+    template: GroupTemplate,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    group_templates::upsert(&mut account.settings.group_templates, template)?;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
@@ -1,6 +1,7 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Sets the active account.
 ///
@@ -12,26 +13,25 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(())` - If the active account was set successfully.
-/// * `Err(String)` - An error message if there was an issue setting the active account.
+/// * `Err(WhitenoiseError)` - An error message if there was an issue setting the active account.
 #[tauri::command]
 pub async fn set_active_account(
-    hex_pubkey: String,
+    hex_pubkey: PubkeyParam,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<Account, String> {
+) -> Result<Account, WhitenoiseError> {
     tracing::debug!(target: "whitenoise::commands::accounts", "Setting active account: {}", hex_pubkey);
 
-    let pubkey =
-        PublicKey::parse(&hex_pubkey).map_err(|e| format!("Error parsing public key: {}", e))?;
+    let pubkey = hex_pubkey.public_key();
 
     let mut account = Account::find_by_pubkey(&pubkey, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching account: {}", e))?;
+        .context("Error fetching account")?;
 
     account.active = true;
 
     account
         .set_active(wn.clone(), &app_handle)
         .await
-        .map_err(|e| format!("Error setting active account: {}", e))
+        .context("Error setting active account")
 }
@@ -0,0 +1,37 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Sets how long the app may stay inactive before it locks itself, for the active account.
+///
+/// Auto-lock only applies once an app passphrase is set.
+///
+/// # Arguments
+///
+/// * `minutes` - Minutes of inactivity before locking, or `None` to disable auto-lock
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if the timeout is zero or the account couldn't be
+///   updated
+#[tauri::command]
+pub async fn set_auto_lock(
+    minutes: Option<u32>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    if minutes == Some(0) {
+        return Err(WhitenoiseError::InvalidInput(
+            "Auto-lock timeout must be at least one minute".to_string(),
+        ));
+    }
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.auto_lock_minutes = minutes;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
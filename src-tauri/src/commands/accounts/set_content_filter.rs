@@ -0,0 +1,37 @@
+use crate::accounts::Account;
+use crate::content_filters::ContentFilterSettings;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Updates the content filter settings of the active account.
+///
+/// The outbound filter masks matching words and patterns in outgoing messages before they're
+/// encrypted; the inbound filter masks them when received messages are displayed. Groups can
+/// override whether either filter applies with `set_group_content_filter`.
+///
+/// # Arguments
+///
+/// * `settings` - The new content filter settings
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if a pattern is invalid or the account can't be
+///   saved
+#[tauri::command]
+pub async fn set_content_filter(
+    settings: ContentFilterSettings,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    settings.filter.validate()?;
+
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.content_filter = settings;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
@@ -0,0 +1,34 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::settings_sync::SettingScope;
+use crate::whitenoise::Whitenoise;
+
+/// Sets which settings of the active account stay on this device.
+///
+/// Device-local settings aren't shared with the account's other devices when they change here,
+/// and changes made to them on other devices aren't applied here. Every other setting is synced
+/// when device sync is enabled.
+///
+/// # Arguments
+///
+/// * `scopes` - The settings to keep device-local; an empty list syncs everything
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if there was an issue updating the account
+#[tauri::command]
+pub async fn set_device_local_settings(
+    scopes: Vec<SettingScope>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.device_local_settings = scopes;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
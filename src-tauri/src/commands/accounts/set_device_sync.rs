@@ -0,0 +1,33 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Enables or disables device sync for the active account.
+///
+/// When enabled, the groups this device joins, the export secrets of new epochs, read markers and
+/// changed settings are sent, encrypted, to the account's own pubkey so its other devices can
+/// catch up on them.
+///
+/// # Arguments
+///
+/// * `enabled` - Whether group state should be shared with the account's other devices
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if there was an issue updating the account
+#[tauri::command]
+pub async fn set_device_sync(
+    enabled: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.device_sync = enabled;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
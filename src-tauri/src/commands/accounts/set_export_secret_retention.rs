@@ -0,0 +1,47 @@
+use crate::accounts::Account;
+use crate::epoch_recovery::{
+    self, MAX_EXPORT_SECRET_RETENTION_EPOCHS, MIN_EXPORT_SECRET_RETENTION_EPOCHS,
+};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Sets how many epochs of export secrets the active account keeps for each group.
+///
+/// Older secrets are only needed to decrypt messages that arrive late, and are pruned with every
+/// group message sync. Lowering the retention prunes right away.
+///
+/// # Arguments
+///
+/// * `epochs` - How many epochs before a group's current one to keep secrets for
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if the retention is out of range or the account
+///   couldn't be updated
+#[tauri::command]
+pub async fn set_export_secret_retention(
+    epochs: u32,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    if !(MIN_EXPORT_SECRET_RETENTION_EPOCHS..=MAX_EXPORT_SECRET_RETENTION_EPOCHS).contains(&epochs)
+    {
+        return Err(WhitenoiseError::InvalidInput(format!(
+            "Export secret retention must be between {} and {} epochs",
+            MIN_EXPORT_SECRET_RETENTION_EPOCHS, MAX_EXPORT_SECRET_RETENTION_EPOCHS
+        )));
+    }
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.export_secret_retention_epochs = epochs;
+    let account = account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")?;
+    epoch_recovery::prune_all_secrets(wn.clone())
+        .await
+        .context("Error pruning export secrets")?;
+    Ok(account)
+}
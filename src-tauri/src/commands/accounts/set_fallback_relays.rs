@@ -0,0 +1,43 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Sets the relays the active account's key packages and welcomes are published to when none
+/// of the primary relays accept them.
+///
+/// # Arguments
+///
+/// * `relays` - The fallback relay URLs. An empty list disables failover.
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if a URL is invalid or the account can't be saved
+#[tauri::command]
+pub async fn set_fallback_relays(
+    relays: Vec<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut fallback_relays: Vec<String> = Vec::new();
+    for relay in relays {
+        let url = RelayUrl::parse(relay.trim())
+            .map_err(|e| {
+                WhitenoiseError::InvalidInput(format!("Invalid relay URL {}: {}", relay, e))
+            })?
+            .to_string();
+        if !fallback_relays.contains(&url) {
+            fallback_relays.push(url);
+        }
+    }
+
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.fallback_relays = fallback_relays;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
@@ -0,0 +1,46 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::key_packages::{self, MAX_KEY_PACKAGE_POOL_SIZE};
+use crate::whitenoise::Whitenoise;
+
+/// Sets how many key packages the active account keeps published.
+///
+/// Consumed key packages are replaced until this many are published again. Raising the size
+/// publishes the missing key packages right away.
+///
+/// # Arguments
+///
+/// * `size` - The minimum number of published key packages
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if the size is out of range or the account couldn't
+///   be updated
+#[tauri::command]
+pub async fn set_key_package_pool_size(
+    size: u32,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    if !(1..=MAX_KEY_PACKAGE_POOL_SIZE).contains(&size) {
+        return Err(WhitenoiseError::InvalidInput(format!(
+            "Key package pool size must be between 1 and {}",
+            MAX_KEY_PACKAGE_POOL_SIZE
+        )));
+    }
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.key_package_pool_size = size;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")?;
+    key_packages::replenish(wn.clone())
+        .await
+        .context("Error publishing key packages")?;
+    Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")
+}
@@ -0,0 +1,62 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::media::MediaServerSettings;
+use crate::whitenoise::Whitenoise;
+
+/// Trims a media server URL, returning `None` if it's empty
+fn normalize_url(url: &str) -> Option<String> {
+    Some(url.trim().trim_end_matches('/').to_string()).filter(|url| !url.is_empty())
+}
+
+/// Sets the media servers the active account uploads attachments to.
+///
+/// Blossom, NIP-96 and plain HTTP servers are supported. When the primary server is down,
+/// uploads go to the fallbacks in order, and fail if none of them accepts the file. Clearing the
+/// URL makes the app's default Blossom server the primary one. With `mirror_attachments`,
+/// attachments are also uploaded to the next server in that order.
+///
+/// # Arguments
+///
+/// * `settings` - The primary media server URL and protocol, the fallback servers and whether
+///   attachments are mirrored
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if a URL is invalid or the account can't be saved
+#[tauri::command]
+pub async fn set_media_server(
+    mut settings: MediaServerSettings,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    settings.url = settings.url.as_deref().and_then(normalize_url);
+    settings.fallbacks = settings
+        .fallbacks
+        .into_iter()
+        .filter_map(|mut endpoint| {
+            endpoint.url = normalize_url(&endpoint.url)?;
+            Some(endpoint)
+        })
+        .collect();
+    let urls = settings
+        .url
+        .iter()
+        .chain(settings.fallbacks.iter().map(|endpoint| &endpoint.url));
+    for url in urls {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(WhitenoiseError::InvalidInput(
+                "Media server URL must start with http:// or https://".to_string(),
+            ));
+        }
+    }
+
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.media_server = settings;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use nwc::prelude::*;
@@ -13,23 +14,23 @@ use nwc::prelude::*;
 /// # Returns
 ///
 /// * `Ok(())` - If the URI was stored successfully
-/// * `Err(String)` - An error message if there was an issue storing the URI
+/// * `Err(WhitenoiseError)` - An error message if there was an issue storing the URI
 #[tauri::command]
 pub async fn set_nostr_wallet_connect_uri(
     nostr_wallet_connect_uri: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
+) -> Result<(), WhitenoiseError> {
     let active_account = Account::get_active(wn.clone())
         .await
-        .map_err(|e| format!("Error getting active account: {}", e))?;
-    let uri: NostrWalletConnectURI =
-        NostrWalletConnectURI::parse(&nostr_wallet_connect_uri).expect("Failed to parse NWC URI");
+        .context("Error getting active account")?;
+    let uri: NostrWalletConnectURI = NostrWalletConnectURI::parse(&nostr_wallet_connect_uri)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Error parsing NWC URI: {}", e)))?;
     let nwc: NWC = NWC::new(uri);
     nwc.get_info()
         .await
-        .map_err(|e| format!("Error getting NWC info: {}", e))?;
+        .map_err(|e| WhitenoiseError::RelayUnreachable(format!("Error getting NWC info: {}", e)))?;
 
     active_account
         .store_nostr_wallet_connect_uri(&nostr_wallet_connect_uri, wn.clone())
-        .map_err(|e| format!("Error storing NWC URI: {}", e))
+        .context("Error storing NWC URI")
 }
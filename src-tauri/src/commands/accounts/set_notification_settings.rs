@@ -0,0 +1,26 @@
+use crate::error::WhitenoiseError;
+use crate::notifications::{self, NotificationSettings};
+use crate::whitenoise::Whitenoise;
+
+/// Sets the active account's notification settings. Groups left out of `settings.groups` keep
+/// their notification level.
+///
+/// # Arguments
+///
+/// * `settings` - Quiet hours, whether invites notify, and levels by hex encoded MLS group ID
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(NotificationSettings)` - The updated settings if successful
+/// * `Err(WhitenoiseError)` - An error message if the quiet hours are invalid, a group doesn't
+///   exist or the settings couldn't be saved
+#[tauri::command]
+pub async fn set_notification_settings(
+    settings: NotificationSettings,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<NotificationSettings, WhitenoiseError> {
+    notifications::update(settings, wn).await.map_err(|e| {
+        WhitenoiseError::Internal(format!("Error setting notification settings: {}", e))
+    })
+}
@@ -0,0 +1,32 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Enables or disables sending read receipts for the active account.
+///
+/// When disabled, marking a group as read only updates the local read cursor and the other
+/// members aren't told what the user has read.
+///
+/// # Arguments
+///
+/// * `enabled` - Whether read receipts should be sent
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if there was an issue updating the account
+#[tauri::command]
+pub async fn set_send_read_receipts(
+    enabled: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.send_read_receipts = enabled;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
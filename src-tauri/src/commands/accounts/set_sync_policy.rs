@@ -0,0 +1,37 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::sync_throttle::{self, SyncPolicy};
+use crate::whitenoise::Whitenoise;
+
+/// Sets how much the active account throttles syncing on battery power and metered connections.
+///
+/// # Arguments
+///
+/// * `policy` - The sync mode to use on battery, on low battery and on metered connections
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if the account couldn't be updated
+#[tauri::command]
+pub async fn set_sync_policy(
+    policy: SyncPolicy,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Account, WhitenoiseError> {
+    let previous = sync_throttle::current_mode(&wn).await;
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.sync_policy = policy;
+    let account = account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")?;
+    sync_throttle::apply(previous, wn, &app_handle)
+        .await
+        .context("Error applying sync policy")?;
+    Ok(account)
+}
@@ -0,0 +1,33 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::sync_scheduler::SyncSchedule;
+use crate::whitenoise::Whitenoise;
+
+/// Sets how often the active account's background sync tasks run. The intervals are stretched
+/// on battery power and metered connections according to the sync policy.
+///
+/// # Arguments
+///
+/// * `schedule` - The interval of each task, in seconds
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if an interval is too short or the account couldn't
+///   be updated
+#[tauri::command]
+pub async fn set_sync_schedule(
+    schedule: SyncSchedule,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    schedule.validate().context("Error setting sync schedule")?;
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.sync_schedule = schedule;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
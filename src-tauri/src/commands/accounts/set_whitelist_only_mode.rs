@@ -0,0 +1,32 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Enables or disables whitelist-only messaging mode for the active account.
+///
+/// In whitelist-only mode only welcomes and group messages from contacts are processed;
+/// gift wraps from anyone else are dropped before their contents are decrypted.
+///
+/// # Arguments
+///
+/// * `enabled` - Whether whitelist-only mode should be enabled
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(WhitenoiseError)` - An error message if there was an issue updating the account
+#[tauri::command]
+pub async fn set_whitelist_only_mode(
+    enabled: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, WhitenoiseError> {
+    let mut account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    account.settings.whitelist_only_mode = enabled;
+    account
+        .save(wn.clone())
+        .await
+        .context("Error saving account")
+}
@@ -1,6 +1,7 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Updates the onboarding status for a specific account.
 ///
@@ -15,26 +16,25 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(Account)` - The updated account if successful
-/// * `Err(String)` - An error message if there was an issue updating the account
+/// * `Err(WhitenoiseError)` - An error message if there was an issue updating the account
 #[tauri::command]
 pub async fn update_account_onboarding(
-    pubkey: String,
+    pubkey: PubkeyParam,
     inbox_relays: bool,
     key_package_relays: bool,
     publish_key_package: bool,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<Account, String> {
-    let pubkey =
-        PublicKey::parse(&pubkey).map_err(|e| format!("Error parsing public key: {}", e))?;
+) -> Result<Account, WhitenoiseError> {
+    let pubkey = pubkey.public_key();
     let mut account = Account::find_by_pubkey(&pubkey, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching account: {}", e))?;
+        .context("Error fetching account")?;
     account.onboarding.inbox_relays = inbox_relays;
     account.onboarding.key_package_relays = key_package_relays;
     account.onboarding.publish_key_package = publish_key_package;
     account
         .save(wn.clone())
         .await
-        .map_err(|e| format!("Error saving account: {}", e))?;
+        .context("Error saving account")?;
     Ok(account)
 }
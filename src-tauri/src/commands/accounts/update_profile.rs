@@ -0,0 +1,180 @@
+use super::publish_metadata_event;
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::nip05;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The profile fields the user can edit. Fields that are left out keep their current value and
+/// blank ones are cleared.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProfileUpdate {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub about: Option<String>,
+    /// URL of the profile picture
+    pub picture: Option<String>,
+    /// NIP-05 identifier (`name@domain`)
+    pub nip05: Option<String>,
+    /// Lightning address (`name@domain`) for zaps and payments
+    pub lud16: Option<String>,
+}
+
+impl ProfileUpdate {
+    /// Applies the update on top of the current metadata, keeping the fields it doesn't cover
+    fn apply(&self, mut metadata: Metadata) -> Result<Metadata, WhitenoiseError> {
+        fn merge(current: &mut Option<String>, update: &Option<String>) {
+            if let Some(value) = update {
+                let value = value.trim();
+                *current = (!value.is_empty()).then(|| value.to_string());
+            }
+        }
+
+        merge(&mut metadata.name, &self.name);
+        merge(&mut metadata.display_name, &self.display_name);
+        merge(&mut metadata.about, &self.about);
+        merge(&mut metadata.picture, &self.picture);
+        merge(&mut metadata.nip05, &self.nip05);
+        merge(&mut metadata.lud16, &self.lud16);
+
+        if let Some(picture) = &metadata.picture {
+            Url::parse(picture).map_err(|e| {
+                WhitenoiseError::InvalidInput(format!("Invalid picture URL: {}", e))
+            })?;
+        }
+        if let Some(nip05) = &metadata.nip05 {
+            nip05::parse_identifier(nip05)?;
+        }
+        if let Some(lud16) = &metadata.lud16 {
+            if !lud16.contains('@') || nip05::parse_identifier(lud16).is_err() {
+                return Err(WhitenoiseError::InvalidInput(format!(
+                    "Invalid lightning address: {}",
+                    lud16
+                )));
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+/// The account's latest kind 0 metadata, from its relays or the local cache, whichever is newer.
+/// Falls back to the metadata stored on the account.
+async fn latest_metadata(
+    account: &Account,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<Metadata, WhitenoiseError> {
+    let filter = Filter::new()
+        .kind(Kind::Metadata)
+        .author(account.pubkey)
+        .limit(1);
+    let mut events: Vec<Event> = wn
+        .nostr
+        .client
+        .database()
+        .query(filter.clone())
+        .await?
+        .into_iter()
+        .collect();
+    match wn
+        .nostr
+        .client
+        .fetch_events(filter, wn.nostr.timeout().await?)
+        .await
+    {
+        Ok(fetched) => events.extend(fetched),
+        Err(e) => tracing::warn!(
+            target: "whitenoise::commands::accounts::update_profile",
+            "Couldn't fetch the profile, using the cached one: {}",
+            e
+        ),
+    }
+    Ok(events
+        .into_iter()
+        .max_by_key(|event| event.created_at)
+        .and_then(|event| Metadata::from_json(&event.content).ok())
+        .unwrap_or_else(|| account.metadata.clone()))
+}
+
+/// Edits the active account's profile and publishes it as a kind 0 event.
+///
+/// Lets newly created identities set up their profile without another Nostr client. The update
+/// is applied on top of the account's latest published profile, so fields it doesn't cover, like
+/// a banner set elsewhere, are kept. Publishing goes through `publish_metadata_event`.
+///
+/// # Arguments
+/// * `metadata` - The profile fields to change
+/// * `wn` - The Whitenoise application state
+/// * `app_handle` - Tauri app handle, used to emit `account_updated`
+///
+/// # Returns
+/// * `Ok(Account)` - The account with its updated metadata
+/// * `Err(WhitenoiseError)` - Error message if a field is invalid or publishing fails
+#[tauri::command]
+pub async fn update_profile(
+    metadata: ProfileUpdate,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Account, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error getting active account")?;
+    let current = latest_metadata(&account, &wn)
+        .await
+        .context("Error loading current profile")?;
+    let new_metadata = metadata.apply(current)?;
+
+    publish_metadata_event(new_metadata, wn.clone(), app_handle)
+        .await
+        .context("Error publishing profile")?;
+    tracing::debug!(
+        target: "whitenoise::commands::accounts::update_profile",
+        "Published profile for {}",
+        account.pubkey.to_hex()
+    );
+
+    Account::find_by_pubkey(&account.pubkey, wn.clone())
+        .await
+        .context("Error loading account")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_keeps_uncovered_fields_and_clears_blank_ones() {
+        let current = Metadata::new()
+            .name("alice")
+            .about("old bio")
+            .banner(Url::parse("https://example.com/banner.png").unwrap());
+        let update = ProfileUpdate {
+            about: Some("  ".to_string()),
+            nip05: Some("alice@example.com".to_string()),
+            ..Default::default()
+        };
+
+        let metadata = update.apply(current).unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("alice"));
+        assert_eq!(metadata.about, None);
+        assert_eq!(metadata.nip05.as_deref(), Some("alice@example.com"));
+        assert!(metadata.banner.is_some());
+    }
+
+    #[test]
+    fn test_apply_rejects_invalid_fields() {
+        let invalid = [
+            ProfileUpdate {
+                picture: Some("not a url".to_string()),
+                ..Default::default()
+            },
+            ProfileUpdate {
+                lud16: Some("example.com".to_string()),
+                ..Default::default()
+            },
+        ];
+        for update in invalid {
+            assert!(update.apply(Metadata::new()).is_err());
+        }
+    }
+}
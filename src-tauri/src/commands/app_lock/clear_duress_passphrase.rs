@@ -0,0 +1,24 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Removes the duress passphrase and deletes its decoy account.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+///
+/// * `Ok(())` - If the duress passphrase was removed (or none was set)
+/// * `Err(WhitenoiseError)` - An error message if there was an issue removing it
+#[tauri::command]
+pub async fn clear_duress_passphrase(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), WhitenoiseError> {
+    app_lock::clear_duress_passphrase(wn, app_handle)
+        .await
+        .context("Error clearing duress passphrase")
+}
@@ -0,0 +1,16 @@
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Returns whether the app is locked, so the frontend knows to show the lock screen.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `true` if the app is locked
+#[tauri::command]
+pub async fn is_app_locked(wn: tauri::State<'_, Whitenoise>) -> Result<bool, WhitenoiseError> {
+    Ok(wn.app_lock.lock().await.locked)
+}
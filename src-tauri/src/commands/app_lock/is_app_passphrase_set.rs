@@ -0,0 +1,20 @@
+use crate::app_lock;
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Returns whether an app passphrase is set, so the frontend knows to ask for it before
+/// requesting a confirmation token.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `true` if an app passphrase is set
+#[tauri::command]
+pub async fn is_app_passphrase_set(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<bool, WhitenoiseError> {
+    Ok(app_lock::is_configured(&wn.data_dir))
+}
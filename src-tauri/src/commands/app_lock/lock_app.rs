@@ -0,0 +1,26 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Locks the app until it's unlocked with the app passphrase.
+///
+/// Emits `app_locked` once the signer and MLS state have been dropped.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+///
+/// * `Ok(())` - If the app is locked
+/// * `Err(WhitenoiseError)` - An error message if no app passphrase is set
+#[tauri::command]
+pub async fn lock_app(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), WhitenoiseError> {
+    app_lock::lock(wn, &app_handle)
+        .await
+        .context("Error locking app")
+}
@@ -0,0 +1,19 @@
+mod clear_duress_passphrase;
+mod is_app_locked;
+mod is_app_passphrase_set;
+mod lock_app;
+mod report_app_activity;
+mod request_sensitive_action;
+mod set_app_passphrase;
+mod set_duress_passphrase;
+mod unlock_app;
+
+pub use clear_duress_passphrase::clear_duress_passphrase;
+pub use is_app_locked::is_app_locked;
+pub use is_app_passphrase_set::is_app_passphrase_set;
+pub use lock_app::lock_app;
+pub use report_app_activity::report_app_activity;
+pub use request_sensitive_action::request_sensitive_action;
+pub use set_app_passphrase::set_app_passphrase;
+pub use set_duress_passphrase::set_duress_passphrase;
+pub use unlock_app::unlock_app;
@@ -0,0 +1,16 @@
+use crate::app_lock;
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Records user activity, postponing the auto-lock.
+///
+/// Frontends should call this (throttled) on user interaction.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+#[tauri::command]
+pub async fn report_app_activity(wn: tauri::State<'_, Whitenoise>) -> Result<(), WhitenoiseError> {
+    app_lock::touch(&wn).await;
+    Ok(())
+}
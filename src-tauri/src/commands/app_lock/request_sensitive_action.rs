@@ -0,0 +1,34 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::sensitive_actions::{self, ConfirmationToken, SensitiveAction};
+use crate::whitenoise::Whitenoise;
+
+/// Requests a confirmation token for a destructive command: `delete_all_data`, `export_nsec`,
+/// `logout`, `export_account`, `export_app_data` or `export_account_data`.
+///
+/// Without an app passphrase the user confirms the action in a native dialog instead. The token
+/// has to be passed to that command within a minute and can only be used once.
+///
+/// # Arguments
+///
+/// * `action` - `wipe_all_data`, `export_nsec`, `delete_account`, `export_account`,
+///   `export_app_data` or `export_account_data`
+/// * `passphrase` - The app passphrase, required when one is set
+/// * `app_handle` - The Tauri app handle
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(ConfirmationToken)` - The token and how many seconds it's valid for
+/// * `Err(WhitenoiseError)` - An error message if the app is locked, the passphrase is incorrect or
+///   the user declined
+#[tauri::command]
+pub async fn request_sensitive_action(
+    action: SensitiveAction,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<ConfirmationToken, WhitenoiseError> {
+    sensitive_actions::request(action, passphrase.as_deref(), &app_handle, wn)
+        .await
+        .context("Error confirming action")
+}
@@ -0,0 +1,27 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Sets or changes the passphrase that unlocks the app.
+///
+/// Once set, the app starts locked and can be locked with `lock_app`.
+///
+/// # Arguments
+///
+/// * `passphrase` - The new passphrase (at least 4 characters, different from the duress passphrase)
+/// * `current_passphrase` - The current passphrase, required to change an existing one
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the passphrase was saved
+/// * `Err(WhitenoiseError)` - An error message if a passphrase is invalid or couldn't be saved
+#[tauri::command]
+pub fn set_app_passphrase(
+    passphrase: String,
+    current_passphrase: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    app_lock::set_app_passphrase(&passphrase, current_passphrase.as_deref(), wn)
+        .context("Error setting app passphrase")
+}
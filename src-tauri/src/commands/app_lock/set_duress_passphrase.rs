@@ -0,0 +1,29 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Sets the duress passphrase that opens the decoy profile when entered at the lock screen.
+///
+/// The decoy account is created the first time a duress passphrase is set and is hidden from
+/// the account list until the duress passphrase is used.
+///
+/// # Arguments
+///
+/// * `passphrase` - The duress passphrase (at least 4 characters, different from the app passphrase)
+/// * `wipe_real_data` - Whether the real accounts are removed when the duress passphrase is used
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the duress passphrase was saved
+/// * `Err(WhitenoiseError)` - An error message if the passphrase is invalid or couldn't be saved
+#[tauri::command]
+pub async fn set_duress_passphrase(
+    passphrase: String,
+    wipe_real_data: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    app_lock::set_duress_passphrase(&passphrase, wipe_real_data, wn)
+        .await
+        .context("Error setting duress passphrase")
+}
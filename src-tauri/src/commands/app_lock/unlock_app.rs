@@ -0,0 +1,29 @@
+use crate::app_lock::{self, UnlockOutcome};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Unlocks the app with a passphrase entered at the lock screen.
+///
+/// The app passphrase reopens the active account and the duress passphrase opens the decoy
+/// profile; both emit `app_unlocked`.
+///
+/// # Arguments
+///
+/// * `passphrase` - The entered passphrase
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+///
+/// * `Ok(UnlockOutcome)` - `Unlocked`, `Duress`, or `Invalid` if the app stays locked
+/// * `Err(WhitenoiseError)` - An error message if the account couldn't be reopened
+#[tauri::command]
+pub async fn unlock_app(
+    passphrase: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<UnlockOutcome, WhitenoiseError> {
+    app_lock::unlock(&passphrase, wn, &app_handle)
+        .await
+        .context("Error unlocking app")
+}
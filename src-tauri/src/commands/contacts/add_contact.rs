@@ -0,0 +1,34 @@
+use crate::accounts::Account;
+use crate::contacts;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Adds a contact to the active account's contact list, or changes the petname of an existing
+/// contact
+///
+/// The change is local until the list is published with `publish_contact_list`.
+///
+/// # Arguments
+/// * `pubkey` - Hex or npub encoded pubkey of the contact
+/// * `petname` - The name to give the contact; blank or `None` to clear it
+/// * `wn` - Whitenoise state
+///
+/// # Errors
+/// Returns error if:
+/// - There's no active account
+/// - The pubkey is invalid
+/// - Database operations fail
+#[tauri::command]
+pub async fn add_contact(
+    pubkey: PubkeyParam,
+    petname: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    contacts::add(&account, &pubkey.to_hex(), petname, wn.clone())
+        .await
+        .context("Error adding contact")
+}
@@ -0,0 +1,70 @@
+use crate::accounts::Account;
+use crate::blocklist;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Blocks a user: their group messages are dropped and their invites are declined
+///
+/// # Arguments
+/// * `pubkey` - Hex or npub encoded pubkey of the user
+/// * `publish_mute_list` - Whether to also publish the blocked users as the account's mute list
+///   (kind 10000), so other clients hide them too
+/// * `wn` - Whitenoise state
+///
+/// # Errors
+/// Returns error if:
+/// - There's no active account
+/// - The pubkey is invalid or the account's own
+/// - Database operations or publishing fail
+#[tauri::command]
+pub async fn block_user(
+    pubkey: PubkeyParam,
+    publish_mute_list: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    blocklist::block(&account, &pubkey.to_hex(), wn.clone())
+        .await
+        .context("Error blocking user")?;
+    if publish_mute_list {
+        blocklist::publish_mute_list(&account, wn.clone())
+            .await
+            .context("Error publishing mute list")?;
+    }
+    Ok(())
+}
+
+/// Unblocks a user. Messages they sent while blocked aren't recovered.
+///
+/// # Arguments
+/// * `pubkey` - Hex or npub encoded pubkey of the user
+/// * `publish_mute_list` - Whether to also publish the updated mute list
+/// * `wn` - Whitenoise state
+///
+/// # Errors
+/// Returns error if:
+/// - There's no active account
+/// - The pubkey is invalid
+/// - Database operations or publishing fail
+#[tauri::command]
+pub async fn unblock_user(
+    pubkey: PubkeyParam,
+    publish_mute_list: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    blocklist::unblock(&account, &pubkey.to_hex(), wn.clone())
+        .await
+        .context("Error unblocking user")?;
+    if publish_mute_list {
+        blocklist::publish_mute_list(&account, wn.clone())
+            .await
+            .context("Error publishing mute list")?;
+    }
+    Ok(())
+}
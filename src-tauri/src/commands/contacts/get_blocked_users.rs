@@ -0,0 +1,24 @@
+use crate::accounts::Account;
+use crate::blocklist::{self, BlockedUser};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Lists the users the active account blocked, most recent first
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<BlockedUser>)` - The blocked users
+/// * `Err(WhitenoiseError)` - Error message if there's no active account or the query fails
+#[tauri::command]
+pub async fn get_blocked_users(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<BlockedUser>, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    blocklist::list(&account, wn.clone())
+        .await
+        .context("Error fetching blocked users")
+}
@@ -0,0 +1,53 @@
+use crate::accounts::Account;
+use crate::contacts::{self, Contact};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
+use crate::query_enriched_contact;
+use crate::whitenoise::Whitenoise;
+
+/// Lists the active account's contacts, e.g. for the group member picker
+///
+/// A newer contact list published from another client is imported first, unless there are
+/// unpublished local changes. Each contact's enriched data is refreshed from the local event
+/// cache; if that fails, the last cached copy is returned.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Vec<Contact>)` - The contacts, in the order they were added
+/// * `Err(WhitenoiseError)` - Error message if there's no active account or the contacts can't be
+///   loaded
+#[tauri::command]
+pub async fn get_contacts(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<Contact>, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    contacts::sync(&account, wn.clone())
+        .await
+        .context("Error syncing contact list")?;
+
+    let mut contact_list = contacts::list(&account, wn.clone())
+        .await
+        .context("Error fetching contacts")?;
+    for contact in contact_list.iter_mut() {
+        let Ok(pubkey) = contact.pubkey.parse::<PubkeyParam>() else {
+            continue;
+        };
+        let Ok(enriched) =
+            query_enriched_contact(pubkey, false, wn.clone(), app_handle.clone()).await
+        else {
+            continue;
+        };
+        contacts::cache_enriched(&account, &contact.pubkey, &enriched, wn.clone())
+            .await
+            .context("Error caching contact")?;
+        contact.can_join_groups = enriched.nip104;
+        contact.enriched = Some(enriched);
+    }
+    Ok(contact_list)
+}
@@ -0,0 +1,15 @@
+mod add_contact;
+mod block_user;
+mod get_blocked_users;
+mod get_contacts;
+mod publish_contact_list;
+mod remove_contact;
+mod search_contacts;
+
+pub use add_contact::add_contact;
+pub use block_user::{block_user, unblock_user};
+pub use get_blocked_users::get_blocked_users;
+pub use get_contacts::get_contacts;
+pub use publish_contact_list::publish_contact_list;
+pub use remove_contact::remove_contact;
+pub use search_contacts::search_contacts;
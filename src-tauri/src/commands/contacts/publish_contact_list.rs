@@ -0,0 +1,25 @@
+use crate::accounts::Account;
+use crate::contacts;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Publishes the active account's contacts as its NIP-02 contact list (kind 3)
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(EventId)` - The ID of the published contact list
+/// * `Err(WhitenoiseError)` - Error message if there's no active account or publishing fails
+#[tauri::command]
+pub async fn publish_contact_list(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<EventId, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    contacts::publish(&account, wn.clone())
+        .await
+        .context("Error publishing contact list")
+}
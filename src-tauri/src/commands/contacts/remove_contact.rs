@@ -0,0 +1,31 @@
+use crate::accounts::Account;
+use crate::contacts;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Removes a contact from the active account's contact list
+///
+/// The change is local until the list is published with `publish_contact_list`.
+///
+/// # Arguments
+/// * `pubkey` - Hex or npub encoded pubkey of the contact
+/// * `wn` - Whitenoise state
+///
+/// # Errors
+/// Returns error if:
+/// - There's no active account
+/// - The pubkey is invalid or isn't a contact
+/// - Database operations fail
+#[tauri::command]
+pub async fn remove_contact(
+    pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    contacts::remove(&account, &pubkey.to_hex(), wn.clone())
+        .await
+        .context("Error removing contact")
+}
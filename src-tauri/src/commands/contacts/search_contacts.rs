@@ -0,0 +1,81 @@
+use crate::contact_search::{self, ContactSearchResult};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::nip05;
+use crate::whitenoise::Whitenoise;
+
+/// Maximum number of search results returned
+const SEARCH_RESULTS_LIMIT: usize = 50;
+
+/// Relays are only asked when the local cache has fewer matches than this
+const MIN_LOCAL_RESULTS: usize = 5;
+
+/// Searches for people by name, display name or NIP-05 identifier, e.g. for the group member
+/// picker
+///
+/// The contact list and cached metadata are searched first. With `search_relays`, relays
+/// supporting NIP-50 search are asked too when few people in the cache match.
+///
+/// # Arguments
+/// * `query` - The text to search for
+/// * `search_relays` - Whether to fall back to relay search
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<ContactSearchResult>)` - The matches, best first
+/// * `Err(WhitenoiseError)` - Error message if the local search fails
+#[tauri::command]
+pub async fn search_contacts(
+    query: String,
+    search_relays: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<ContactSearchResult>, WhitenoiseError> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut results = contact_search::search_local(&query, wn.clone())
+        .await
+        .context("Error searching contacts")?;
+
+    if search_relays && results.len() < MIN_LOCAL_RESULTS {
+        match wn.nostr.search_users(query.clone(), wn.clone()).await {
+            Ok(users) => {
+                for (pubkey, contact) in users {
+                    if results.contains_key(&pubkey) {
+                        continue;
+                    }
+                    // Relays may match fields that aren't searched locally, like the bio
+                    let score = contact_search::match_score(&query, &contact, None).max(1);
+                    results.insert(
+                        pubkey.clone(),
+                        ContactSearchResult {
+                            pubkey,
+                            contact,
+                            is_contact: false,
+                            score,
+                        },
+                    );
+                }
+            }
+            // The local results are still useful when relays can't be reached
+            Err(e) => tracing::warn!(
+                target: "whitenoise::commands::contacts::search_contacts",
+                "Relay search failed: {}",
+                e
+            ),
+        }
+    }
+
+    nip05::annotate(
+        results
+            .iter_mut()
+            .map(|(pubkey, result)| (pubkey, &mut result.contact)),
+        wn.clone(),
+    )
+    .await
+    .context("Error reading NIP-05 verifications")?;
+
+    Ok(contact_search::rank(
+        results.into_values().collect(),
+        SEARCH_RESULTS_LIMIT,
+    ))
+}
@@ -0,0 +1,137 @@
+use super::create_group::{
+    current_epoch, keep_pending, publish_welcomes, wrap_welcomes, WelcomeDelivery, WelcomeTarget,
+};
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::invite_messages;
+use crate::key_packages;
+use crate::params::{GroupIdParam, PubkeyParam};
+use crate::whitenoise::Whitenoise;
+
+/// Approves a request to join a group: adds the requester and welcomes them
+///
+/// The requester is added with the key package their request named, in an Add commit published
+/// to the group. The request is forgotten and counts as a use of the invite link it came through
+/// before the member is added, so concurrent approvals can't exceed the link's uses; both are
+/// undone if adding the member fails. A welcome that can't be sent is kept for
+/// `retry_pending_welcomes`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `requester_pubkey` - Public key of the requester, hex or npub
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(WelcomeDelivery)` - Whether the requester's welcome was sent
+/// * `Err(WhitenoiseError)` - Error message if the request can't be approved
+///
+/// # Errors
+/// Returns error if:
+/// - The active account isn't an admin of the group
+/// - There's no pending request from the requester, or its invite link is used up
+/// - The requester is already a member
+/// - The requester's key package can't be fetched anymore
+/// - Adding the member fails, which it does until incoming commits are processed (see
+///   `MEMBER_ADDITIONS_ENABLED`)
+#[tauri::command]
+pub async fn approve_join_request(
+    group_id: GroupIdParam,
+    requester_pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<WelcomeDelivery, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let requester = requester_pubkey.public_key();
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    let group =
+        Group::find_by_mls_group_id_for_account(&mls_group_id, &active_account.pubkey, wn.clone())
+            .await
+            .context("Error fetching group")?;
+    if !group
+        .admin_pubkeys
+        .contains(&active_account.pubkey.to_hex())
+    {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only group admins can approve join requests".to_string(),
+        ));
+    }
+
+    let request = invite_messages::join_request(
+        &mls_group_id,
+        &requester,
+        &active_account.pubkey,
+        wn.clone(),
+    )
+    .await
+    .context("Error fetching join request")?;
+    if group
+        .members(wn.clone())
+        .await
+        .context("Error fetching group members")?
+        .contains(&requester)
+    {
+        return Err(WhitenoiseError::InvalidInput(
+            "The requester is already a member".to_string(),
+        ));
+    }
+
+    let key_package =
+        key_packages::fetch_key_package_by_id(&requester, request.key_package_event_id, wn.clone())
+            .await
+            .context("Error fetching key package")?
+            .ok_or_else(|| {
+                WhitenoiseError::NotFound(
+                    "The requester's key package is no longer available".to_string(),
+                )
+            })?;
+
+    invite_messages::reserve_use(&request, &active_account.pubkey, wn.clone())
+        .await
+        .context("Error approving join request")?;
+    let serialized_welcome_message = match group.add_member(key_package, wn.clone()).await {
+        Ok(serialized_welcome_message) => serialized_welcome_message,
+        Err(e) => {
+            if let Err(e) =
+                invite_messages::release_use(&request, &active_account.pubkey, wn.clone()).await
+            {
+                tracing::warn!(
+                    target: "whitenoise::commands::groups::approve_join_request",
+                    "Failed to restore the join request from {}: {}",
+                    requester.to_hex(),
+                    e
+                );
+            }
+            return Err(WhitenoiseError::from(e).with_context("Error adding member"));
+        }
+    };
+
+    let signer = wn.nostr.client.signer().await?;
+    let targets = vec![WelcomeTarget {
+        member_pubkey: requester.to_hex(),
+        key_package_event_id: request.key_package_event_id,
+        serialized_welcome_message,
+    }];
+    let (welcomes, mut failures) =
+        wrap_welcomes(targets, &active_account, &signer, wn.clone(), &app_handle).await;
+    let (welcomed, publish_failures) = publish_welcomes(welcomes, wn.clone()).await;
+    failures.extend(publish_failures);
+    if !failures.is_empty() {
+        match current_epoch(&mls_group_id, wn.clone()).await {
+            Ok(epoch) => {
+                keep_pending(&active_account, &mls_group_id, epoch, &failures, wn.clone()).await
+            }
+            Err(e) => tracing::warn!(
+                target: "whitenoise::commands::groups::approve_join_request",
+                "Failed to keep the welcome for {} to retry later: {}",
+                requester.to_hex(),
+                e
+            ),
+        }
+    }
+
+    Ok(WelcomeDelivery::new(&welcomed, &failures))
+}
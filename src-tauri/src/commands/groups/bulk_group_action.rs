@@ -0,0 +1,154 @@
+use super::mark_group_read::send_read_receipt;
+use crate::accounts::Account;
+use crate::bulk_group_actions::{self, BulkGroupAction, GroupActionResult};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{self, Group};
+use crate::settings_sync::{self, SyncedSetting};
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Archives, unarchives, mutes, unmutes, marks as read or leaves many groups at once
+///
+/// Groups that can't be found or don't allow the action get a failed result; the action is
+/// applied to all the others in one transaction. Marking as read moves each read cursor to the
+/// group's latest message and, if the account sends read receipts, sends one to every group whose
+/// cursor moved. Leaving also stops the subscription to the groups' messages and deletes their MLS
+/// state from this device, so it can't read or send to them anymore; the other members keep the
+/// user in their member list until an admin removes them. Archiving and muting are shared with
+/// the account's other devices.
+///
+/// # Arguments
+/// * `group_ids` - Hex encoded MLS group IDs
+/// * `action` - The action to apply
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<GroupActionResult>)` - One result per group, in the order given
+/// * `Err(WhitenoiseError)` - Error message if there's no active account
+///
+/// # Errors
+/// Returns error if there's no active account
+#[tauri::command]
+pub async fn bulk_group_action(
+    group_ids: Vec<String>,
+    action: BulkGroupAction,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<GroupActionResult>, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+
+    let mut results: Vec<GroupActionResult> = Vec::with_capacity(group_ids.len());
+    let mut groups: Vec<(usize, Group)> = Vec::new();
+    for group_id in &group_ids {
+        let group = match hex::decode(group_id) {
+            Ok(mls_group_id) => Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+                .await
+                .map_err(|e| format!("Error fetching group: {}", e)),
+            Err(e) => Err(format!("Error decoding group id: {}", e)),
+        }
+        .and_then(|group| bulk_group_actions::validate(action, &group).map(|_| group));
+
+        match group {
+            Ok(group) => {
+                groups.push((results.len(), group));
+                results.push(GroupActionResult::succeeded(group_id));
+            }
+            Err(e) => results.push(GroupActionResult::failed(group_id, e)),
+        }
+    }
+
+    let applied: Vec<Group> = groups.iter().map(|(_, group)| group.clone()).collect();
+    if let Err(e) = bulk_group_actions::apply(&applied, action, wn.clone()).await {
+        for (index, _) in &groups {
+            results[*index] = GroupActionResult::failed(
+                &results[*index].group_id,
+                format!("Database error: {}", e),
+            );
+        }
+        return Ok(results);
+    }
+
+    match action {
+        BulkGroupAction::MarkRead if account.settings.send_read_receipts => {
+            for group in &applied {
+                let Some(last_message_id) = group.last_message_id.as_deref() else {
+                    continue;
+                };
+                if group.last_read_message_id.as_deref() == Some(last_message_id) {
+                    continue;
+                }
+                // The cursor has already moved, a missing receipt isn't worth failing over
+                let sent = match EventId::from_hex(last_message_id) {
+                    Ok(event_id) => send_read_receipt(group, event_id, &wn).await,
+                    Err(e) => Err(WhitenoiseError::InvalidInput(e.to_string())),
+                };
+                if let Err(e) = sent {
+                    tracing::warn!(
+                        target: "whitenoise::commands::groups::bulk_group_action",
+                        "Failed to send read receipt to group {}: {}",
+                        hex::encode(&group.mls_group_id),
+                        e
+                    );
+                }
+            }
+        }
+        BulkGroupAction::Archive | BulkGroupAction::Unarchive => {
+            let archived = action == BulkGroupAction::Archive;
+            let changed = applied
+                .iter()
+                .map(|group| SyncedSetting::group_archived(group, archived))
+                .collect();
+            settings_sync::share(changed, wn.clone()).await;
+        }
+        BulkGroupAction::Mute | BulkGroupAction::Unmute => {
+            let changed = applied
+                .iter()
+                .map(|group| {
+                    let mut group = group.clone();
+                    group.muted = action == BulkGroupAction::Mute;
+                    SyncedSetting::group_notification_level(&group)
+                })
+                .collect();
+            settings_sync::share(changed, wn.clone()).await;
+        }
+        BulkGroupAction::Leave if !applied.is_empty() => {
+            // The groups are left either way; they just keep arriving until the next restart
+            if let Err(e) = resubscribe(&account, &wn).await {
+                tracing::warn!(
+                    target: "whitenoise::commands::groups::bulk_group_action",
+                    "Failed to update MLS group subscription: {}",
+                    e
+                );
+            }
+            let nostr_mls = wn.nostr_mls.lock().await;
+            for group in &applied {
+                if let Err(e) = groups::delete_mls_state(&nostr_mls, &group.mls_group_id) {
+                    tracing::warn!(
+                        target: "whitenoise::commands::groups::bulk_group_action",
+                        "Failed to delete the MLS state of group {}: {}",
+                        hex::encode(&group.mls_group_id),
+                        e
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(results)
+}
+
+/// Subscribes to the messages of the groups the account is still in
+async fn resubscribe(
+    account: &Account,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let group_ids = account.nostr_group_ids(wn.clone()).await?;
+    if group_ids.is_empty() {
+        wn.nostr.unsubscribe_mls_group_messages().await;
+        return Ok(());
+    }
+    wn.nostr.subscribe_mls_group_messages(group_ids).await?;
+    Ok(())
+}
@@ -1,37 +1,107 @@
 use crate::accounts::Account;
+use crate::contacts;
+use crate::device_sync;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::fetch_enriched_contact;
-use crate::groups::{Group, GroupType};
+use crate::groups::{self, Group, GroupType};
 use crate::key_packages::fetch_key_packages_for_members;
+use crate::params::PubkeyParam;
+use crate::pending_welcomes::PendingWelcome;
+use crate::profiling::{self, OperationKind};
+use crate::relay_failover::{publish_with_failover, ArtifactKind};
 use crate::whitenoise::Whitenoise;
+use futures::stream::{self, StreamExt};
 use nostr_sdk::prelude::*;
 use nostr_sdk::NostrSigner;
+use serde::{Deserialize, Serialize};
 use std::ops::Add;
+use std::sync::Arc;
 use tauri::Emitter;
 
+/// How many members' welcomes are wrapped or published at the same time
+const WELCOME_CONCURRENCY: usize = 8;
+
+/// A member to welcome to a group, with the welcome message made for their key package
+#[derive(Debug, Clone)]
+pub(crate) struct WelcomeTarget {
+    /// Hex public key of the member
+    pub member_pubkey: String,
+    pub key_package_event_id: EventId,
+    pub serialized_welcome_message: Vec<u8>,
+}
+
+/// A welcome message ready to be published to a member
+pub(crate) struct Welcome {
+    target: WelcomeTarget,
+    member_pubkey: PublicKey,
+    relay_urls: Vec<String>,
+    wrapped_event: Event,
+}
+
+/// Who was welcomed to a group and who wasn't
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct WelcomeDelivery {
+    /// Hex public key of each member whose welcome was sent
+    pub welcomed: Vec<String>,
+    /// Hex public key of each member that wasn't welcomed, with the reason. Their welcomes are
+    /// kept for `retry_pending_welcomes`.
+    pub failures: Vec<(String, String)>,
+}
+
+impl WelcomeDelivery {
+    pub(crate) fn new(welcomed: &[WelcomeTarget], failures: &[(WelcomeTarget, String)]) -> Self {
+        Self {
+            welcomed: welcomed.iter().map(|t| t.member_pubkey.clone()).collect(),
+            failures: failures
+                .iter()
+                .map(|(t, e)| (t.member_pubkey.clone(), e.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A new group, and which of its members were welcomed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupWithFailures {
+    #[serde(flatten)]
+    pub group: Group,
+    #[serde(flatten)]
+    pub delivery: WelcomeDelivery,
+}
+
 /// Creates a new MLS group with the specified members and settings
 ///
 /// # Arguments
-/// * `creator_pubkey` - Public key of the group creator (must be the active account)
-/// * `member_pubkeys` - List of public keys for group members
-/// * `admin_pubkeys` - List of public keys for group admins
+/// * `creator_pubkey` - Hex or npub public key of the group creator (must be the active account)
+/// * `member_pubkeys` - Hex or npub public keys of the group members
+/// * `admin_pubkeys` - Hex or npub public keys of the group admins
 /// * `group_name` - Name of the group
 /// * `description` - Description of the group
 /// * `wn` - Whitenoise state
 /// * `app_handle` - Tauri app handle
 ///
 /// # Returns
-/// * `Ok(Group)` - The newly created group
-/// * `Err(String)` - Error message if group creation fails
+/// * `Ok(GroupWithFailures)` - The newly created group, the members who were welcomed and the
+///   ones whose welcome couldn't be sent
+/// * `Err(WhitenoiseError)` - Error message if group creation fails
 ///
 /// # Flow
 /// 1. Validates that active account is the creator and signer
 /// 2. Validates member and admin lists
 /// 3. Fetches key packages for all members
 /// 4. Creates MLS group with NostrMls
-/// 5. Sends welcome messages to all members via Nostr
-/// 6. Adds group to GroupManager database
-/// 7. Updates account with new group ID
-/// 8. Emits group_added event
+/// 5. Wraps welcome messages for all members, several at a time
+/// 6. Saves the group to the database
+/// 7. Updates the MLS group message subscription to include the new group
+/// 8. Sends the welcome messages via Nostr, several at a time
+/// 9. Emits group_added event
+///
+/// A member whose welcome can't be wrapped or sent doesn't stop the others from being welcomed;
+/// they're listed in the failures instead, and their welcomes are kept so they can be sent again
+/// with `retry_pending_welcomes`. If no member could be welcomed, or a step between 4 and 8
+/// fails otherwise, the group is removed from the database and the MLS state is put back as it
+/// was, so a failed creation leaves nothing behind locally. Once members are welcomed the group
+/// is kept, and what's left only logs its errors.
 ///
 /// # Errors
 /// Returns error if:
@@ -39,43 +109,65 @@ use tauri::Emitter;
 /// - Member/admin validation fails
 /// - Key package fetching fails
 /// - MLS group creation fails
-/// - Welcome message sending fails
+/// - No welcome message could be sent
 /// - Database operations fail
 #[tauri::command]
 pub async fn create_group(
+    creator_pubkey: PubkeyParam,
+    member_pubkeys: Vec<PubkeyParam>,
+    admin_pubkeys: Vec<PubkeyParam>,
+    group_name: String,
+    description: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<GroupWithFailures, WhitenoiseError> {
+    // TODO: Add ability to specify relays for the group
+    let group_relays = wn.nostr.relays().await?;
+
+    create_group_with_relays(
+        creator_pubkey.to_hex(),
+        member_pubkeys.iter().map(PubkeyParam::to_hex).collect(),
+        admin_pubkeys.iter().map(PubkeyParam::to_hex).collect(),
+        group_name,
+        description,
+        group_relays,
+        wn,
+        app_handle,
+    )
+    .await
+    .map_err(WhitenoiseError::from)
+}
+
+/// Creates a group like [`create_group`] that uses the given relays, e.g. the ones of a group
+/// template
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn create_group_with_relays(
     creator_pubkey: String,
     member_pubkeys: Vec<String>,
     admin_pubkeys: Vec<String>,
     group_name: String,
     description: String,
+    group_relays: Vec<String>,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<Group, String> {
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
-    let signer = wn.nostr.client.signer().await.map_err(|e| e.to_string())?;
+) -> Result<GroupWithFailures, WhitenoiseError> {
+    let active_account = Account::get_active(wn.clone()).await?;
+    let signer = wn.nostr.client.signer().await?;
 
     // Check that active account is the creator and signer
     if active_account.pubkey.to_hex() != creator_pubkey
-        || active_account.pubkey.to_hex()
-            != signer
-                .get_public_key()
-                .await
-                .map_err(|e| e.to_string())?
-                .to_hex()
+        || active_account.pubkey.to_hex() != signer.get_public_key().await?.to_hex()
     {
-        return Err("You cannot create a group for another account".to_string());
+        return Err(WhitenoiseError::Unauthorized(
+            "You cannot create a group for another account".to_string(),
+        ));
     }
 
     // Run various checks on the group members
-    Group::validate_group_members(&creator_pubkey, &member_pubkeys, &admin_pubkeys)
-        .map_err(|e| e.to_string())?;
+    Group::validate_group_members(&creator_pubkey, &member_pubkeys, &admin_pubkeys)?;
 
     // Fetch key packages for all members
-    let member_key_packages = fetch_key_packages_for_members(&member_pubkeys, wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let member_key_packages = fetch_key_packages_for_members(&member_pubkeys, wn.clone()).await?;
 
     tracing::debug!(
         target: "whitenoise::groups::create_group",
@@ -83,15 +175,11 @@ pub async fn create_group(
         member_key_packages
     );
 
-    // TODO: Add ability to specify relays for the group
-    let group_relays = wn.nostr.relays().await.unwrap();
-
     let create_group_result;
     {
         let nostr_mls = wn.nostr_mls.lock().await;
-
-        create_group_result = nostr_mls
-            .create_group(
+        create_group_result = profiling::time("mls.create_group", OperationKind::Mls, || {
+            nostr_mls.create_group(
                 group_name,
                 description,
                 member_key_packages
@@ -102,199 +190,432 @@ pub async fn create_group(
                 creator_pubkey,
                 group_relays,
             )
-            .map_err(|e| e.to_string())?;
+        })
+        .map_err(|e| WhitenoiseError::Mls(format!("Error creating group: {}", e)))?;
     }
 
     let mls_group = create_group_result.mls_group;
     let serialized_welcome_message = create_group_result.serialized_welcome_message;
     let group_data = create_group_result.nostr_group_data;
+    let group_id = mls_group.group_id().to_vec();
 
-    // Fan out the welcome message to all members
-    for member in member_key_packages {
-        let member_pubkey = PublicKey::from_hex(&member.pubkey).map_err(|e| e.to_string())?;
-        let contact =
-            fetch_enriched_contact(member.pubkey.clone(), false, wn.clone(), app_handle.clone())
-                .await?;
-
-        // We only want to connect to user relays in release mode
-        let relay_urls: Vec<String> = if cfg!(dev) {
-            vec![
-                "ws://localhost:8080".to_string(),
-                "ws://localhost:7777".to_string(),
-            ]
-        } else if !contact.inbox_relays.is_empty() {
-            contact.inbox_relays
-        } else if !contact.nostr_relays.is_empty() {
-            contact.nostr_relays
-        } else {
-            // Get default relays from the client
-            wn.nostr
-                .client
-                .relays()
-                .await
-                .keys()
-                .map(|url| url.to_string())
-                .collect()
-        };
+    // Wrap the welcome messages for all members before anything is stored or published
+    let targets = member_key_packages
+        .iter()
+        .map(|member| WelcomeTarget {
+            member_pubkey: member.pubkey.clone(),
+            key_package_event_id: member.event_id,
+            serialized_welcome_message: serialized_welcome_message.clone(),
+        })
+        .collect();
+    let (welcomes, mut failures) =
+        wrap_welcomes(targets, &active_account, &signer, wn.clone(), &app_handle).await;
+    if welcomes.is_empty() && !failures.is_empty() {
+        let error = no_welcomes_sent(&failures);
+        return Err(roll_back(error, &active_account, &group_id, None, wn.clone()).await);
+    }
 
-        let welcome_rumor =
-            EventBuilder::new(Kind::MlsWelcome, hex::encode(&serialized_welcome_message))
-                .tags(vec![
-                    Tag::from_standardized(TagStandard::Relays(
-                        relay_urls
-                            .iter()
-                            .filter_map(|r| RelayUrl::parse(r).ok())
-                            .collect(),
-                    )),
-                    Tag::event(member.event_id),
-                ])
-                .build(active_account.pubkey);
-
-        tracing::debug!(
-            target: "whitenoise::groups::create_group",
-            "Welcome rumor: {:?}",
-            welcome_rumor
-        );
+    let group_type = if mls_group.members().count() == 2 {
+        GroupType::DirectMessage
+    } else {
+        GroupType::Group
+    };
+
+    // Create the group and save it to the database
+    let nostr_group = match Group::new(
+        group_id.clone(),
+        mls_group.epoch().as_u64(),
+        group_type,
+        group_data,
+        wn.clone(),
+        &app_handle,
+    )
+    .await
+    {
+        Ok(nostr_group) => nostr_group,
+        Err(e) => {
+            return Err(roll_back(
+                WhitenoiseError::from(e),
+                &active_account,
+                &group_id,
+                None,
+                wn.clone(),
+            )
+            .await)
+        }
+    };
 
-        // Create a timestamp 1 month in the future
-        let one_month_future = Timestamp::now().add(30 * 24 * 60 * 60);
+    tracing::debug!(
+        target: "whitenoise::groups::create_group",
+        "Added group to database: {:?}",
+        nostr_group
+    );
 
-        let wrapped_event = EventBuilder::gift_wrap(
-            &signer,
-            &member_pubkey,
-            welcome_rumor,
-            vec![Tag::expiration(one_month_future)],
+    if let Err(e) = subscribe_to_groups(&active_account, wn.clone()).await {
+        return Err(roll_back(
+            e,
+            &active_account,
+            &group_id,
+            Some(&nostr_group),
+            wn.clone(),
         )
-        .await
-        .map_err(|e| e.to_string())?;
-
-        let max_retries = 5;
-        let mut retry_count = 0;
-        let mut last_error = None;
-
-        let mut relays_to_remove: Vec<String> = Vec::new();
-
-        for url in relay_urls.clone() {
-            let to_remove = wn
-                .nostr
-                .client
-                .add_relay(url.clone())
-                .await
-                .map_err(|e| e.to_string())?;
-            if to_remove {
-                relays_to_remove.push(url);
+        .await);
+    }
+
+    // Fan out the welcome messages
+    let (welcomed, publish_failures) = publish_welcomes(welcomes, wn.clone()).await;
+    failures.extend(publish_failures);
+    if welcomed.is_empty() && !failures.is_empty() {
+        let error = no_welcomes_sent(&failures);
+        return Err(roll_back(
+            error,
+            &active_account,
+            &group_id,
+            Some(&nostr_group),
+            wn.clone(),
+        )
+        .await);
+    }
+    if !failures.is_empty() {
+        tracing::warn!(
+            target: "whitenoise::commands::groups::create_group",
+            "Created group without welcoming {} of its members: {:?}",
+            failures.len(),
+            failures
+                .iter()
+                .map(|(target, e)| (&target.member_pubkey, e))
+                .collect::<Vec<_>>()
+        );
+        keep_pending(
+            &active_account,
+            &group_id,
+            nostr_group.epoch,
+            &failures,
+            wn.clone(),
+        )
+        .await;
+    }
+
+    // Members were welcomed, so the group exists for them whatever happens from here on
+    if let Err(e) = app_handle.emit("group_added", nostr_group.clone()) {
+        tracing::warn!(
+            target: "whitenoise::commands::groups::create_group",
+            "Failed to emit group_added for group {}: {}",
+            hex::encode(&group_id),
+            e
+        );
+    }
+
+    device_sync::share_group(&nostr_group, wn.clone()).await;
+
+    Ok(GroupWithFailures {
+        group: nostr_group,
+        delivery: WelcomeDelivery::new(&welcomed, &failures),
+    })
+}
+
+/// Wraps the welcome messages for the given members, several at a time
+///
+/// Returns the welcomes ready to be published, and the members whose welcome couldn't be wrapped
+/// with the reason.
+pub(crate) async fn wrap_welcomes(
+    targets: Vec<WelcomeTarget>,
+    active_account: &Account,
+    signer: &Arc<dyn NostrSigner>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> (Vec<Welcome>, Vec<(WelcomeTarget, String)>) {
+    let wrapped: Vec<(WelcomeTarget, Result<Welcome, WhitenoiseError>)> = stream::iter(targets)
+        .map(|target| {
+            let (wn, app_handle) = (wn.clone(), app_handle.clone());
+            async move {
+                let welcome =
+                    wrap_welcome(target.clone(), active_account, signer, wn, app_handle).await;
+                (target, welcome)
             }
+        })
+        .buffer_unordered(WELCOME_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut welcomes = Vec::new();
+    let mut failures = Vec::new();
+    for (target, welcome) in wrapped {
+        match welcome {
+            Ok(welcome) => welcomes.push(welcome),
+            Err(e) => failures.push((target, e.to_string())),
         }
+    }
+    (welcomes, failures)
+}
 
-        while retry_count < max_retries {
-            match wn
-                .nostr
-                .client
-                .send_event_to(relay_urls.clone(), &wrapped_event)
-                .await
-            {
-                Ok(result) => {
-                    // Successfully sent, break the loop
-                    // TODO: Remove the identifying info from the log
-                    tracing::info!(
-                        target: "whitenoise::groups::create_group",
-                        "Sent welcome message RESULT: {:?}",
-                        result
-                    );
-                    tracing::info!(
-                        target: "whitenoise::groups::create_group",
-                        "Successfully sent welcome message {:?} to {:?} on {:?}",
-                        wrapped_event,
-                        &member_pubkey,
-                        &relay_urls
-                    );
-                    break;
-                }
-                Err(e) => {
-                    tracing::error!(
-                        target: "whitenoise::groups::create_group",
-                        "Failed to send welcome message to {:?} on {:?}: {:?}",
-                        &member_pubkey,
-                        &relay_urls,
-                        e
-                    );
-                    last_error = Some(e);
-                    retry_count += 1;
-                    if retry_count < max_retries {
-                        // Wait for a short time before retrying
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                    }
-                }
+/// Publishes wrapped welcome messages, several at a time
+///
+/// Returns the members who were welcomed, and the ones whose welcome couldn't be sent with the
+/// reason.
+pub(crate) async fn publish_welcomes(
+    welcomes: Vec<Welcome>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> (Vec<WelcomeTarget>, Vec<(WelcomeTarget, String)>) {
+    let published: Vec<(WelcomeTarget, Result<(), WhitenoiseError>)> = stream::iter(welcomes)
+        .map(|welcome| {
+            let wn = wn.clone();
+            async move {
+                let target = welcome.target.clone();
+                (target, publish_welcome(welcome, wn).await)
             }
-        }
+        })
+        .buffer_unordered(WELCOME_CONCURRENCY)
+        .collect()
+        .await;
 
-        if retry_count == max_retries {
-            return Err(format!(
-                "Failed to send welcome message to {:?} on {:?} after {} attempts. Last error: {:?}",
-                &member_pubkey, &relay_urls, max_retries, last_error
-            ));
+    let mut welcomed = Vec::new();
+    let mut failures = Vec::new();
+    for (target, result) in published {
+        match result {
+            Ok(()) => welcomed.push(target),
+            Err(e) => failures.push((target, e.to_string())),
         }
+    }
+    (welcomed, failures)
+}
 
-        tracing::debug!(
-            target: "whitenoise::groups::create_group",
-            "Published welcome message to {:?} on {:?}: ID: {:?}",
-            &member_pubkey,
-            &relay_urls,
-            wrapped_event.id
-        );
+/// The epoch of a group's MLS state, which welcomes made now let new members join at
+pub(crate) async fn current_epoch(
+    mls_group_id: &[u8],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<u64, WhitenoiseError> {
+    let nostr_mls = wn.nostr_mls.lock().await;
+    groups::load_mls_group(&nostr_mls, mls_group_id)
+        .map(|mls_group| mls_group.epoch().as_u64())
+        .map_err(WhitenoiseError::from)
+}
 
-        for url in relays_to_remove {
-            wn.nostr
-                .client
-                .remove_relay(url)
-                .await
-                .map_err(|e| e.to_string())?;
+/// Keeps the welcomes made at `epoch` that couldn't be sent so they can be retried later
+pub(crate) async fn keep_pending(
+    active_account: &Account,
+    mls_group_id: &[u8],
+    epoch: u64,
+    failures: &[(WelcomeTarget, String)],
+    wn: tauri::State<'_, Whitenoise>,
+) {
+    for (target, error) in failures {
+        let result = match PublicKey::from_hex(&target.member_pubkey) {
+            Ok(member_pubkey) => PendingWelcome::record(
+                active_account,
+                mls_group_id,
+                &member_pubkey,
+                &target.key_package_event_id,
+                &target.serialized_welcome_message,
+                epoch,
+                error,
+                wn.clone(),
+            )
+            .await
+            .map_err(WhitenoiseError::from),
+            // A member that isn't a valid public key can never be welcomed
+            Err(e) => Err(WhitenoiseError::from(e)),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                target: "whitenoise::commands::groups::create_group",
+                "Failed to keep the welcome for {} to retry later: {}",
+                target.member_pubkey,
+                e
+            );
         }
     }
+}
 
-    let group_type = if mls_group.members().count() == 2 {
-        GroupType::DirectMessage
+/// Wraps the welcome message for a member, addressed to the relays they read from
+async fn wrap_welcome(
+    target: WelcomeTarget,
+    active_account: &Account,
+    signer: &Arc<dyn NostrSigner>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Welcome, WhitenoiseError> {
+    let member_pubkey = PublicKey::from_hex(&target.member_pubkey)?;
+    let contact =
+        fetch_enriched_contact(member_pubkey.into(), false, wn.clone(), app_handle).await?;
+    // Keeps the member picker's copy of the contact fresh
+    if let Err(e) =
+        contacts::cache_enriched(active_account, &target.member_pubkey, &contact, wn.clone()).await
+    {
+        tracing::warn!(
+            target: "whitenoise::commands::groups::create_group",
+            "Failed to cache contact {}: {}",
+            target.member_pubkey,
+            e
+        );
+    }
+
+    // We only want to connect to user relays in release mode
+    let relay_urls: Vec<String> = if cfg!(dev) {
+        vec![
+            "ws://localhost:8080".to_string(),
+            "ws://localhost:7777".to_string(),
+        ]
+    } else if !contact.inbox_relays.is_empty() {
+        contact.inbox_relays
+    } else if !contact.nostr_read_relays.is_empty() {
+        // Welcomes go where the member reads, not where they write
+        contact.nostr_read_relays
     } else {
-        GroupType::Group
+        // Get default relays from the client
+        wn.nostr
+            .client
+            .relays()
+            .await
+            .keys()
+            .map(|url| url.to_string())
+            .collect()
     };
 
-    let group_id = mls_group.group_id().to_vec();
+    let welcome_rumor = EventBuilder::new(
+        Kind::MlsWelcome,
+        hex::encode(&target.serialized_welcome_message),
+    )
+    .tags(vec![
+        Tag::from_standardized(TagStandard::Relays(
+            relay_urls
+                .iter()
+                .filter_map(|r| RelayUrl::parse(r).ok())
+                .collect(),
+        )),
+        Tag::event(target.key_package_event_id),
+    ])
+    .build(active_account.pubkey);
 
-    // Create the group and save it to the database
-    let nostr_group = Group::new(
-        group_id.clone(),
-        mls_group.epoch().as_u64(),
-        group_type,
-        group_data,
-        wn.clone(),
-        &app_handle,
+    tracing::debug!(
+        target: "whitenoise::groups::create_group",
+        "Welcome rumor: {:?}",
+        welcome_rumor
+    );
+
+    // Create a timestamp 1 month in the future
+    let one_month_future = Timestamp::now().add(30 * 24 * 60 * 60);
+
+    let wrapped_event = EventBuilder::gift_wrap(
+        signer,
+        &member_pubkey,
+        welcome_rumor,
+        vec![Tag::expiration(one_month_future)],
+    )
+    .await?;
+
+    Ok(Welcome {
+        target,
+        member_pubkey,
+        relay_urls,
+        wrapped_event,
+    })
+}
+
+/// Publishes a wrapped welcome message to the member's relays
+async fn publish_welcome(
+    welcome: Welcome,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    // Retries the member's relays, then fails over to our fallback relays, and records where
+    // the welcome ended up
+    let artifact = publish_with_failover(
+        &welcome.wrapped_event,
+        welcome.relay_urls.clone(),
+        ArtifactKind::Welcome,
+        Some(welcome.member_pubkey),
+        wn,
     )
     .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| {
+        WhitenoiseError::from(e).with_context(&format!(
+            "Failed to send welcome message to {:?} on {:?}",
+            &welcome.member_pubkey, &welcome.relay_urls
+        ))
+    })?;
 
     tracing::debug!(
         target: "whitenoise::groups::create_group",
-        "Added group to database: {:?}",
-        nostr_group
+        "Published welcome message to {:?} on {:?}: ID: {:?}",
+        &welcome.member_pubkey,
+        &artifact.relays,
+        welcome.wrapped_event.id
     );
+    Ok(())
+}
+
+/// The error for a group none of whose members could be welcomed
+fn no_welcomes_sent(failures: &[(WelcomeTarget, String)]) -> WhitenoiseError {
+    let reasons: Vec<&str> = failures.iter().map(|(_, e)| e.as_str()).collect();
+    WhitenoiseError::RelayUnreachable(format!(
+        "No welcome message could be sent: {}",
+        reasons.join("; ")
+    ))
+}
 
-    // Update the subscription for MLS group messages to include the new group
+/// Subscribes to the messages of every group of the account
+pub(crate) async fn subscribe_to_groups(
+    active_account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
     let group_ids = active_account
         .groups(wn.clone())
         .await
-        .map_err(|e| format!("Failed to get groups: {}", e))?
+        .context("Failed to get groups")?
         .into_iter()
         .map(|group| group.nostr_group_id)
         .collect::<Vec<_>>();
 
     wn.nostr
-        .subscribe_mls_group_messages(group_ids.clone())
+        .subscribe_mls_group_messages(group_ids)
         .await
-        .map_err(|e| format!("Failed to update MLS group subscription: {}", e))?;
+        .context("Failed to update MLS group subscription")
+}
 
-    app_handle
-        .emit("group_added", nostr_group.clone())
-        .map_err(|e| e.to_string())?;
+/// Undoes a group creation that failed partway: removes the group from the database if it was
+/// saved, deletes the group's MLS state and subscribes to the remaining groups again. The MLS
+/// state of the account's other groups is left alone, so messages they processed in the
+/// meantime aren't lost.
+///
+/// Returns the error to give the caller, which mentions anything that couldn't be undone.
+async fn roll_back(
+    error: WhitenoiseError,
+    active_account: &Account,
+    mls_group_id: &[u8],
+    group: Option<&Group>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> WhitenoiseError {
+    tracing::warn!(
+        target: "whitenoise::commands::groups::create_group",
+        "Creating the group failed, rolling back: {}",
+        error
+    );
 
-    Ok(nostr_group)
+    let mut failures = Vec::new();
+    if let Some(group) = group {
+        if let Err(e) = group.remove(wn.clone()).await {
+            failures.push(format!("removing the group: {}", e));
+        }
+        if let Err(e) = subscribe_to_groups(active_account, wn.clone()).await {
+            failures.push(e.to_string());
+        }
+    }
+    {
+        let nostr_mls = wn.nostr_mls.lock().await;
+        if let Err(e) = groups::delete_mls_state(&nostr_mls, mls_group_id) {
+            failures.push(format!("deleting the MLS state: {}", e));
+        }
+    }
+
+    if failures.is_empty() {
+        error
+    } else {
+        tracing::error!(
+            target: "whitenoise::commands::groups::create_group",
+            "Rolling back the group creation failed: {}",
+            failures.join("; ")
+        );
+        let message = format!("{} (rolling back failed: {})", error, failures.join("; "));
+        error.wrapped_in(message)
+    }
 }
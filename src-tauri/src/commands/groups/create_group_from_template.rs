@@ -0,0 +1,126 @@
+use super::create_group::{create_group_with_relays, GroupWithFailures};
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::PubkeyParam;
+use crate::set_group_sensitive;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Creates a group set up from one of the active account's group templates
+///
+/// The group gets the template's relays (or the client's relays if it has none) and name,
+/// description and admins; template admins who aren't among the members are left out. The
+/// template's message expiry, avatar and notification defaults are then applied to the group.
+/// Those are best effort: the group is returned even if one of them can't be applied.
+///
+/// # Arguments
+/// * `template_id` - ID of the template in the account settings
+/// * `member_pubkeys` - Hex or npub public keys of the members, not including the creator
+/// * `group_name` - Overrides the template's group name
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(GroupWithFailures)` - The new group with the template applied, and which of its members
+///   were welcomed
+/// * `Err(WhitenoiseError)` - Error message if the template doesn't exist or group creation fails
+#[tauri::command]
+pub async fn create_group_from_template(
+    template_id: String,
+    member_pubkeys: Vec<PubkeyParam>,
+    group_name: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<GroupWithFailures, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    let template = account
+        .settings
+        .group_templates
+        .iter()
+        .find(|template| template.id == template_id)
+        .cloned()
+        .ok_or_else(|| {
+            WhitenoiseError::NotFound(format!("Group template not found: {}", template_id))
+        })?;
+
+    let creator_pubkey = account.pubkey.to_hex();
+    let member_pubkeys: Vec<String> = member_pubkeys.iter().map(PubkeyParam::to_hex).collect();
+    let admin_pubkeys = template.admins_for(&creator_pubkey, &member_pubkeys);
+    let relays = if template.relays.is_empty() {
+        wn.nostr.relays().await?
+    } else {
+        template.relays.clone()
+    };
+
+    let GroupWithFailures {
+        group: mut group,
+        delivery,
+    } = create_group_with_relays(
+        creator_pubkey,
+        member_pubkeys,
+        admin_pubkeys,
+        group_name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| template.group_name()),
+        template.description.clone(),
+        relays,
+        wn.clone(),
+        app_handle.clone(),
+    )
+    .await?;
+
+    // The group exists and its members were welcomed at this point, so a setting that can't be
+    // applied is logged instead of failing the creation
+    if template.message_ttl.is_some() {
+        if let Err(e) = group
+            .set_message_ttl(template.message_ttl, wn.clone())
+            .await
+        {
+            log_unapplied("message expiry", &group, e);
+        }
+    }
+    if template.avatar_url.is_some() {
+        if let Err(e) = group
+            .set_avatar_url(template.avatar_url.clone(), wn.clone())
+            .await
+        {
+            log_unapplied("avatar", &group, e);
+        }
+    }
+    if let Some(snooze_secs) = template.notifications.snooze_secs {
+        let until = Timestamp::now() + snooze_secs;
+        match group.set_snoozed_until(Some(until), wn.clone()).await {
+            Ok(()) => group.snoozed_until = Some(until),
+            Err(e) => log_unapplied("snooze", &group, e),
+        }
+    }
+    if template.notifications.sensitive {
+        // Sent to the group as a settings update so the other members apply it too
+        match set_group_sensitive(
+            group.mls_group_id.clone().into(),
+            true,
+            wn.clone(),
+            app_handle,
+        )
+        .await
+        {
+            Ok(updated) => group = updated,
+            Err(e) => log_unapplied("sensitive flag", &group, e),
+        }
+    }
+
+    Ok(GroupWithFailures { group, delivery })
+}
+
+fn log_unapplied(setting: &str, group: &Group, error: impl std::fmt::Display) {
+    tracing::warn!(
+        target: "whitenoise::commands::groups::create_group_from_template",
+        "Failed to apply the template's {} to group {}: {}",
+        setting,
+        hex::encode(&group.mls_group_id),
+        error
+    );
+}
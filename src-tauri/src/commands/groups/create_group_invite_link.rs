@@ -0,0 +1,33 @@
+use crate::error::WhitenoiseError;
+use crate::invite_messages::{self, InviteLink};
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Creates an invite link for a group
+///
+/// The signed invite is published to the active account's relays under a random code, and the
+/// link points to it, so sharing the link is enough for someone to ask to join, see
+/// `accept_invite_link`. Their requests show up in `pending_join_requests`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `max_uses` - How many join requests through the link can be approved, unlimited if `None`
+/// * `expiry` - Seconds the link stays valid, a week if `None` and at most 90 days
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(InviteLink)` - The link, with its code and limits
+/// * `Err(WhitenoiseError)` - Error message if the settings are invalid, the group can't be found,
+///   the active account isn't one of its admins or the invite can't be published
+#[tauri::command]
+pub async fn create_group_invite_link(
+    group_id: GroupIdParam,
+    max_uses: Option<u32>,
+    expiry: Option<u64>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InviteLink, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    invite_messages::create_link(&mls_group_id, max_uses, expiry, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error creating invite link: {}", e)))
+}
@@ -0,0 +1,92 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_tasks::{self, GroupTask, TaskAction};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Creates a task in a group, optionally assigned to some of its members
+///
+/// Assignees are mentioned in the task message, so they're notified. Anyone who can see the
+/// task can check who it's assigned to, and only they, its creator and the group's admins can
+/// mark it done (see `set_group_task_completed`).
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `title` - What needs to be done
+/// * `assignees` - Hex encoded pubkeys of the members responsible for it
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(GroupTask)` - The new task
+/// * `Err(WhitenoiseError)` - Error message if the task is invalid, an assignee isn't a member, or
+///   sending fails
+#[tauri::command]
+pub async fn create_group_task(
+    group_id: GroupIdParam,
+    title: String,
+    assignees: Vec<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<GroupTask, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let action = TaskAction::Create {
+        task_id: uuid::Uuid::new_v4().to_string(),
+        title: title.trim().to_string(),
+        assignees,
+    };
+    group_tasks::validate_action(&action)?;
+    if let TaskAction::Create { assignees, .. } = &action {
+        let members = group
+            .members(wn.clone())
+            .await
+            .context("Error fetching group members")?;
+        if let Some(outsider) = assignees
+            .iter()
+            .find(|assignee| !members.iter().any(|member| member.to_hex() == **assignee))
+        {
+            return Err(WhitenoiseError::InvalidInput(format!(
+                "{} isn't a member of this group",
+                outsider
+            )));
+        }
+    }
+
+    send_task_action(group, &action, wn, app_handle).await
+}
+
+/// Sends a task action to the group and returns the task's resulting state
+pub(crate) async fn send_task_action(
+    group: Group,
+    action: &TaskAction,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<GroupTask, WhitenoiseError> {
+    let payload = serde_json::to_string(action).context("Error serializing task")?;
+    send_mls_message(
+        group.clone(),
+        payload,
+        protocol::kind_for(PayloadType::GroupTask),
+        Some(group_tasks::action_tags(action)),
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await?;
+
+    group_tasks::for_group(&group, wn.clone())
+        .await
+        .context("Error fetching tasks")?
+        .into_iter()
+        .find(|task| task.task_id == action.task_id())
+        .ok_or_else(|| WhitenoiseError::Internal("Task wasn't saved".to_string()))
+}
@@ -0,0 +1,28 @@
+use crate::error::WhitenoiseError;
+use crate::invite_messages;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Creates an invite message for a group that can be shared over other channels
+///
+/// The message says who invites to which group and until when, and ends with a line White Noise
+/// reads the signed invite from, see `parse_invite_message`. It stays valid for a week.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(String)` - The invite message
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or the active account isn't
+///   one of its admins
+#[tauri::command]
+pub async fn create_invite_message(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    invite_messages::create(&mls_group_id, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error creating invite message: {}", e)))
+}
@@ -1,6 +1,8 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::Group;
-use crate::messages::Message;
+use crate::messages::{Message, MessageError};
+use crate::protocol::{self, PayloadType};
 use crate::send_mls_message;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -18,7 +20,7 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 /// * `Ok(Message)` - The deletion event if successful
-/// * `Err(String)` - Error message if deletion fails
+/// * `Err(WhitenoiseError)` - Error message if deletion fails
 ///
 /// # Errors
 /// Returns error if:
@@ -33,7 +35,7 @@ pub async fn delete_message(
     message_id: String,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<Message, String> {
+) -> Result<Message, WhitenoiseError> {
     tracing::debug!(
         target: "whitenoise::commands::groups::delete_message",
         "Attempting to delete message with ID: {} from group: {}",
@@ -43,7 +45,7 @@ pub async fn delete_message(
 
     let active_account = Account::get_active(wn.clone())
         .await
-        .map_err(|e| format!("Failed to get active account: {}", e))?;
+        .context("Failed to get active account")?;
 
     tracing::debug!(
         target: "whitenoise::commands::groups::validate_deletion_request",
@@ -52,10 +54,15 @@ pub async fn delete_message(
         message_id
     );
 
-    let group_messages = group
-        .messages(wn.clone())
-        .await
-        .map_err(|e| format!("Failed to fetch messages: {}", e))?;
+    // Only the target message is needed, not the whole transcript
+    let group_messages: Vec<Message> = match EventId::from_hex(&message_id) {
+        Ok(event_id) => match Message::find_by_event_id(event_id, wn.clone()).await {
+            Ok(message) if message.mls_group_id == group.mls_group_id => vec![message],
+            Ok(_) | Err(MessageError::NotFound) => Vec::new(),
+            Err(e) => return Err(e).context("Failed to fetch message"),
+        },
+        Err(_) => Vec::new(),
+    };
 
     // Validate inputs and permissions
     let message_event_id =
@@ -76,14 +83,17 @@ pub async fn delete_message(
     let result = send_mls_message(
         group,
         deletion_reason.to_string(),
-        5, // Kind 5 for deletion events as per NIP-09
+        protocol::kind_for(PayloadType::Deletion),
         Some(deletion_tags),
         None,
+        None,
+        None,
+        None,
         wn,
         app_handle,
     )
     .await
-    .map_err(|e| format!("Failed to send deletion event: {}", e));
+    .context("Failed to send deletion event");
 
     match &result {
         Ok(message) => {
@@ -116,21 +126,33 @@ pub async fn delete_message(
 ///
 /// # Returns
 /// * `Ok((EventId, Account))` - Validated message ID and active account
-/// * `Err(String)` - Error message if validation fails
+/// * `Err(WhitenoiseError)` - Error message if validation fails
 async fn validate_deletion_request(
     message_id: &str,
     group_messages: &[Message],
     active_account: &Account,
-) -> Result<EventId, String> {
+) -> Result<EventId, WhitenoiseError> {
     // Parse and validate message ID
-    let message_event_id =
-        EventId::from_hex(message_id).map_err(|e| format!("Invalid message ID format: {}", e))?;
+    let message_event_id = EventId::from_hex(message_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid message ID format: {}", e)))?;
 
     // Find the target message
     let message = group_messages
         .iter()
         .find(|m| m.event_id == message_event_id)
-        .ok_or_else(|| format!("Message with ID {} not found in this group", message_id))?;
+        .ok_or_else(|| {
+            WhitenoiseError::NotFound(format!(
+                "Message with ID {} not found in this group",
+                message_id
+            ))
+        })?;
+
+    if message.deleted_at.is_some() {
+        return Err(WhitenoiseError::InvalidInput(format!(
+            "Message {} has already been deleted",
+            message_id
+        )));
+    }
 
     // Verify ownership
     if message.author_pubkey != active_account.pubkey {
@@ -141,10 +163,10 @@ async fn validate_deletion_request(
             message_id,
             message.author_pubkey.to_hex()
         );
-        return Err(format!(
+        return Err(WhitenoiseError::Unauthorized(format!(
             "Permission denied: Cannot delete message {}. Only the message creator can delete it.",
             message_id
-        ));
+        )));
     }
 
     tracing::debug!(
@@ -169,6 +191,7 @@ mod tests {
             last_used: Timestamp::now(),
             last_synced: Timestamp::zero(),
             active: true,
+            key_package_ids: Vec::new(),
         }
     }
 
@@ -197,6 +220,17 @@ mod tests {
             )
             .unwrap(),
             tokens: vec![],
+            semantics: Default::default(),
+            reply_to: None,
+            thread_root: None,
+            deleted_at: None,
+            edited_at: None,
+            author_migrated_to: None,
+            origin_group_id: None,
+            expires_at: None,
+            pending: false,
+            display_content: None,
+            payment_request: None,
         }
     }
 
@@ -0,0 +1,42 @@
+use crate::delete_message;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Retracts one of the active account's messages from an MLS group
+///
+/// Sends a kind 5 deletion rumor referencing the target through the group. Every member
+/// (including us, once the deletion is stored) tombstones the target and emits
+/// `mls_message_deleted`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `target_event_id` - Hex encoded ID of the message to delete
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The deletion message if successful
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex or the group can't be found
+/// - The target message isn't in the group or wasn't written by the active account
+/// - Sending the deletion fails
+#[tauri::command]
+pub async fn delete_mls_message(
+    group_id: GroupIdParam,
+    target_event_id: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    delete_message(group, target_event_id, wn, app_handle).await
+}
@@ -0,0 +1,80 @@
+use crate::accounts::Account;
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::media::attachments::{self, AttachmentMeta};
+use crate::media::servers;
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Downloads the attachments of a message into the local cache
+///
+/// Each file is checked against the hashes in the message before and after it's decrypted.
+/// Files that are already cached aren't downloaded again.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `event_id` - Hex encoded event ID of the message
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - Local paths of the decrypted files, in the order they were attached
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID or event ID are invalid
+/// - The message isn't in the group, was deleted or has no attachments
+/// - A file is too large, can't be downloaded, fails verification or can't be decrypted
+#[tauri::command]
+pub async fn download_attachment(
+    group_id: GroupIdParam,
+    event_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<String>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let event_id = EventId::from_hex(event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event id: {}", e)))?;
+
+    let message = Message::find_by_event_id(event_id, wn.clone())
+        .await
+        .context("Error fetching message")?;
+    if message.mls_group_id != mls_group_id {
+        return Err(WhitenoiseError::InvalidInput(
+            "Message is not in this group".to_string(),
+        ));
+    }
+    if message.deleted_at.is_some() {
+        // Drop any copy cached before the message was deleted
+        attachments::purge_cached(&message.tags, &mls_group_id, &wn.data_dir).await;
+        return Err(WhitenoiseError::NotFound("Message was deleted".to_string()));
+    }
+
+    let attachments = AttachmentMeta::from_tags(&message.tags);
+    if attachments.is_empty() {
+        return Err(WhitenoiseError::InvalidInput(
+            "Message has no attachments".to_string(),
+        ));
+    }
+
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    let servers = servers::servers_for(&active_account.settings.media_server, &wn.nostr.blossom);
+
+    let mut paths = Vec::with_capacity(attachments.len());
+    for attachment in &attachments {
+        let path =
+            attachments::download_attachment(attachment, &mls_group_id, &wn.data_dir, &servers)
+                .await
+                .map_err(|e| {
+                    WhitenoiseError::from(e)
+                        .with_context(&format!("Error downloading {}", attachment.filename))
+                })?;
+        paths.push(path.to_string_lossy().to_string());
+    }
+
+    Ok(paths)
+}
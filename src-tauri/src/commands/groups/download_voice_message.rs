@@ -0,0 +1,78 @@
+use crate::accounts::Account;
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::media::attachments::{self, AttachmentMeta};
+use crate::media::servers;
+use crate::media::voice::{is_voice_message, VoiceMessage};
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Downloads a voice message into the local cache for playback
+///
+/// The recording is verified and decrypted like any other attachment. Once cached, the
+/// frontend can stream it from the returned path without downloading it again.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `event_id` - Hex encoded event ID of the message
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(VoiceMessage)` - Path of the decrypted recording with its duration and waveform
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID or event ID are invalid
+/// - The message isn't in the group, was deleted or isn't a voice message
+/// - The recording is too large, can't be downloaded, fails verification or can't be decrypted
+#[tauri::command]
+pub async fn download_voice_message(
+    group_id: GroupIdParam,
+    event_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<VoiceMessage, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let event_id = EventId::from_hex(event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event id: {}", e)))?;
+
+    let message = Message::find_by_event_id(event_id, wn.clone())
+        .await
+        .context("Error fetching message")?;
+    if message.mls_group_id != mls_group_id {
+        return Err(WhitenoiseError::InvalidInput(
+            "Message is not in this group".to_string(),
+        ));
+    }
+    if message.deleted_at.is_some() {
+        // Drop any copy cached before the message was deleted
+        attachments::purge_cached(&message.tags, &mls_group_id, &wn.data_dir).await;
+        return Err(WhitenoiseError::NotFound("Message was deleted".to_string()));
+    }
+
+    let voice = AttachmentMeta::from_tags(&message.tags)
+        .into_iter()
+        .find(is_voice_message)
+        .ok_or_else(|| {
+            WhitenoiseError::InvalidInput("Message is not a voice message".to_string())
+        })?;
+
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    let servers = servers::servers_for(&active_account.settings.media_server, &wn.nostr.blossom);
+
+    let path = attachments::download_attachment(&voice, &mls_group_id, &wn.data_dir, &servers)
+        .await
+        .context("Error downloading voice message")?;
+
+    Ok(VoiceMessage {
+        path: path.to_string_lossy().to_string(),
+        mime_type: voice.mime_type,
+        duration_ms: voice.duration_ms.unwrap_or_default(),
+        waveform: voice.waveform.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,103 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::{Message, EDIT_KIND};
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Edits one of the active account's messages in an MLS group
+///
+/// Sends an edit rumor with an `e` tag referencing the original message and the full new
+/// content. When the edit is stored, the original transcript entry is updated to the new
+/// content and `mls_message_edited` is emitted; earlier versions stay available through
+/// `get_message_edit_history`. Edits of an ephemeral message expire with it.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `target_event_id` - Hex encoded ID of the message to edit
+/// * `new_content` - The replacement content
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The edit message if successful
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex or the group can't be found
+/// - The new content is empty
+/// - The target message isn't in the group, was deleted or wasn't written by the active account
+/// - Sending the edit fails
+#[tauri::command]
+pub async fn edit_mls_message(
+    group_id: GroupIdParam,
+    target_event_id: &str,
+    new_content: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    if new_content.trim().is_empty() {
+        return Err(WhitenoiseError::InvalidInput(
+            "Edited message can't be empty".to_string(),
+        ));
+    }
+
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let target_event_id = EventId::from_hex(target_event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event ID: {}", e)))?;
+    let target = Message::find_by_event_id(target_event_id, wn.clone())
+        .await
+        .context("Error fetching target message")?;
+    if target.mls_group_id != group.mls_group_id {
+        return Err(WhitenoiseError::InvalidInput(
+            "Target message is not in this group".to_string(),
+        ));
+    }
+    if target.deleted_at.is_some() {
+        return Err(WhitenoiseError::InvalidInput(
+            "Can't edit a deleted message".to_string(),
+        ));
+    }
+    if target.event_kind == EDIT_KIND {
+        return Err(WhitenoiseError::InvalidInput(
+            "Edit the original message instead of an edit".to_string(),
+        ));
+    }
+
+    let active_pubkey = Account::get_active_pubkey(wn.clone())
+        .await
+        .context("Failed to get active account")?;
+    if target.author_pubkey != active_pubkey {
+        return Err(WhitenoiseError::Unauthorized(
+            "Permission denied: Only the message creator can edit it".to_string(),
+        ));
+    }
+
+    // The edit holds the message's new content, so it has to expire along with it
+    let mut tags = vec![Tag::event(target.event_id)];
+    if let Some(expires_at) = target.expires_at {
+        tags.push(Tag::expiration(expires_at));
+    }
+
+    send_mls_message(
+        group,
+        new_content,
+        protocol::kind_for(PayloadType::Edit),
+        Some(tags),
+        None,
+        None,
+        None,
+        None,
+        wn,
+        app_handle,
+    )
+    .await
+}
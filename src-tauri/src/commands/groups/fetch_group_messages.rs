@@ -0,0 +1,42 @@
+use crate::app_lock;
+use crate::background_refresh;
+use crate::commands::nostr::ensure_nostr_initialized;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Syncs the messages of a single group, for when the user opens its conversation. The group is
+/// fetched from its own checkpoint, without waiting for the scheduled sync of every group, and
+/// its new messages are queued for processing.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+/// * `Ok(usize)` - How many new group events were queued
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or synced
+#[tauri::command]
+pub async fn fetch_group_messages(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    ensure_nostr_initialized(wn.clone(), app_handle.clone()).await?;
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    let timeout = wn
+        .nostr
+        .timeout()
+        .await
+        .context("Error getting relay timeout")?;
+    let (new_messages, _) = background_refresh::sync_group(&group, timeout, &wn)
+        .await
+        .context("Error syncing group messages")?;
+    Ok(new_messages)
+}
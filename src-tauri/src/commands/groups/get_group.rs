@@ -1,4 +1,6 @@
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::{Group, GroupWithRelays};
+use crate::params::GroupIdParam;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -10,7 +12,7 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 /// * `Ok(Group)` - The requested group if found
-/// * `Err(String)` - Error message if group not found or other error occurs
+/// * `Err(WhitenoiseError)` - Error message if group not found or other error occurs
 ///
 /// # Errors
 /// Returns error if:
@@ -19,15 +21,16 @@ use nostr_sdk::prelude::*;
 /// - Database error occurs
 #[tauri::command]
 pub async fn get_group(
-    group_id: &str,
+    group_id: GroupIdParam,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<GroupWithRelays, String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
+) -> Result<GroupWithRelays, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
     let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
-    let relays = group.relays(wn.clone()).await.map_err(|e| e.to_string())?;
+        .context("Error fetching group")?
+        .with_dm_display(wn.clone())
+        .await;
+    let relays = group.relays(wn.clone()).await?;
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group",
         "Group Relays: {:?}",
@@ -1,4 +1,6 @@
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::Group;
+use crate::params::GroupIdParam;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
@@ -10,7 +12,7 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 /// * `Ok(Vec<String>)` - List of admin public keys if successful
-/// * `Err(String)` - Error message if operation fails
+/// * `Err(WhitenoiseError)` - Error message if operation fails
 ///
 /// # Errors
 /// * If no active account is found
@@ -19,14 +21,13 @@ use nostr_sdk::prelude::*;
 /// * If admin list cannot be retrieved
 #[tauri::command]
 pub async fn get_group_admins(
-    group_id: &str,
+    group_id: GroupIdParam,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<Vec<PublicKey>, String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
+) -> Result<Vec<PublicKey>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
     let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
-    let admins = group.admins().map_err(|e| e.to_string())?;
+        .context("Error fetching group")?;
+    let admins = group.admins()?;
     Ok(admins)
 }
@@ -1,7 +1,9 @@
-use crate::groups::Group;
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, MAX_MESSAGE_PAGE_SIZE};
 use crate::messages::Message;
+use crate::params::GroupIdParam;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 use serde::Serialize;
 
 #[derive(Debug, Clone, Serialize)]
@@ -10,7 +12,10 @@ pub struct GroupAndMessages {
     messages: Vec<Message>,
 }
 
-/// Gets a single MLS group and its messages by group ID
+/// Gets a single MLS group and its newest messages by group ID
+///
+/// Up to `MAX_MESSAGE_PAGE_SIZE` messages are returned; older ones are fetched page by page with
+/// `get_group_messages`.
 ///
 /// # Arguments
 /// * `group_id` - Hex encoded MLS group ID
@@ -19,8 +24,8 @@ pub struct GroupAndMessages {
 /// # Returns
 /// * `Ok(GroupAndMessages)` - Struct containing:
 ///   - The requested group if found
-///   - Vector of messages for the group
-/// * `Err(String)` - Error message if operation fails
+///   - The newest messages of the group, ordered oldest to newest
+/// * `Err(WhitenoiseError)` - Error message if operation fails
 ///
 /// # Errors
 /// Returns error if:
@@ -29,11 +34,11 @@ pub struct GroupAndMessages {
 /// - Error fetching messages
 #[tauri::command]
 pub async fn get_group_and_messages(
-    group_id: &str,
+    group_id: GroupIdParam,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<GroupAndMessages, String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
+) -> Result<GroupAndMessages, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group_and_messages",
         "Getting group and messages for group ID: {:?}",
@@ -41,16 +46,18 @@ pub async fn get_group_and_messages(
     );
     let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
+        .context("Error fetching group")?
+        .with_dm_display(wn.clone())
+        .await;
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group_and_messages",
         "Group: {:?}",
         group
     );
     let messages = group
-        .messages(wn.clone())
+        .messages_page(None, MAX_MESSAGE_PAGE_SIZE, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching messages: {}", e))?;
+        .context("Error fetching messages")?;
 
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group_and_messages",
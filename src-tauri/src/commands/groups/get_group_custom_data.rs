@@ -0,0 +1,36 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_custom_data::{self, GroupCustomData};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the shared state frontend features stored in a group
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `namespace` - Optional namespace to get; all of the group's namespaces if omitted
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<GroupCustomData>)` - The namespaces that have a value, sorted by name
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or the query fails
+#[tauri::command]
+pub async fn get_group_custom_data(
+    group_id: GroupIdParam,
+    namespace: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<GroupCustomData>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    group_custom_data::for_group(
+        &group.account_pubkey,
+        &mls_group_id,
+        namespace.as_deref(),
+        wn.clone(),
+    )
+    .await
+    .map_err(|e| WhitenoiseError::Internal(format!("Error fetching custom data: {}", e)))
+}
@@ -0,0 +1,42 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::media_library::{self, GroupMediaPage, MediaCategory, MediaCursor};
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets a page of the attachments and links shared in a group, newest first, for the shared
+/// media view
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `category` - `image`, `video`, `file` or `link`; all of them if not set
+/// * `after` - The `next` cursor of the page already loaded. `None` returns the newest items.
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(GroupMediaPage)` - Up to 50 items, and where the next page starts if there's one
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or the query fails
+#[tauri::command]
+pub async fn get_group_media(
+    group_id: GroupIdParam,
+    category: Option<MediaCategory>,
+    after: Option<MediaCursor>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<GroupMediaPage, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    media_library::page(
+        &group.account_pubkey,
+        &group.mls_group_id,
+        category,
+        after,
+        wn.clone(),
+    )
+    .await
+    .map_err(|e| WhitenoiseError::Internal(format!("Error fetching group media: {}", e)))
+}
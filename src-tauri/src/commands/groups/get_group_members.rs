@@ -1,16 +1,18 @@
-use crate::groups::Group;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, GroupMember};
+use crate::params::GroupIdParam;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
-/// Gets the list of members in an MLS group
+/// Gets the members of an MLS group with their role, first seen epoch, last activity and
+/// contact metadata, so the frontend doesn't have to piece membership together itself
 ///
 /// # Arguments
 /// * `group_id` - Hex-encoded MLS group ID
 /// * `wn` - Whitenoise state handle
 ///
 /// # Returns
-/// * `Ok(Vec<String>)` - List of member public keys if successful
-/// * `Err(String)` - Error message if operation fails
+/// * `Ok(Vec<GroupMember>)` - The group's members if successful
+/// * `Err(WhitenoiseError)` - Error message if operation fails
 ///
 /// # Errors
 /// * If no active account is found
@@ -19,14 +21,15 @@ use nostr_sdk::prelude::*;
 /// * If members cannot be retrieved
 #[tauri::command]
 pub async fn get_group_members(
-    group_id: &str,
+    group_id: GroupIdParam,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<Vec<PublicKey>, String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
+) -> Result<Vec<GroupMember>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
     let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
-    let members = group.members(wn.clone()).await.map_err(|e| e.to_string())?;
-    Ok(members)
+        .context("Error fetching group")?;
+    group
+        .member_details(wn.clone())
+        .await
+        .map_err(WhitenoiseError::from)
 }
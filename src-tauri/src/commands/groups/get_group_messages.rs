@@ -0,0 +1,42 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, MessageCursor};
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets a page of messages for an MLS group
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `before` - The `created_at` and `event_id` of the oldest message already loaded. Only older
+///   messages are returned. `None` returns the newest messages.
+/// * `limit` - Maximum number of messages to return
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<Message>)` - Up to `limit` messages ordered oldest to newest
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Group not found in database
+/// - Error fetching messages
+#[tauri::command]
+pub async fn get_group_messages(
+    group_id: GroupIdParam,
+    before: Option<MessageCursor>,
+    limit: usize,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Message>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    group
+        .messages_page(before, limit, wn.clone())
+        .await
+        .context("Error fetching messages")
+}
@@ -0,0 +1,31 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_notes::{self, GroupNote};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the shared notes of a group, most recently updated first
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<GroupNote>)` - The notes, with any unresolved conflicting edit
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or the query fails
+#[tauri::command]
+pub async fn get_group_notes(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<GroupNote>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    group_notes::for_group(&group.account_pubkey, &mls_group_id, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching notes: {}", e)))
+}
@@ -0,0 +1,32 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the admin notices of a group, newest first
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<Message>)` - The group's notices that haven't been deleted
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or the query fails
+#[tauri::command]
+pub async fn get_group_notices(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Message>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    group
+        .notices(wn.clone())
+        .await
+        .context("Error fetching notices")
+}
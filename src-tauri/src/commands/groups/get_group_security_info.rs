@@ -0,0 +1,30 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_security::{self, GroupSecurityInfo};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the ciphersuite, epoch, key freshness and privacy options of a group, so users can audit
+/// how their conversation is protected
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(GroupSecurityInfo)` - The group's security report
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or its MLS state can't be
+///   read
+#[tauri::command]
+pub async fn get_group_security_info(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<GroupSecurityInfo, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    group_security::security_info(&group, wn.clone())
+        .await
+        .context("Error fetching group security info")
+}
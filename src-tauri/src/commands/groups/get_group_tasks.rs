@@ -0,0 +1,31 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_tasks::{self, GroupTask};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the current state of a group's tasks, oldest first
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<GroupTask>)` - The tasks with their assignees and completion state
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found or the query fails
+#[tauri::command]
+pub async fn get_group_tasks(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<GroupTask>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    group_tasks::for_group(&group, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching tasks: {}", e)))
+}
@@ -1,3 +1,5 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::Group;
 use crate::whitenoise::Whitenoise;
 
@@ -8,16 +10,24 @@ use crate::whitenoise::Whitenoise;
 /// * `wn` - Whitenoise state containing account and group managers
 ///
 /// # Returns
-/// * `Ok(Vec<Group>)` - List of groups the active account belongs to
-/// * `Err(String)` - Error message if retrieval fails
+/// * `Ok(Vec<Group>)` - List of groups the active account belongs to. Direct messages carry
+///   the peer's name and picture as their display name and picture.
+/// * `Err(WhitenoiseError)` - Error message if retrieval fails
 ///
 /// # Errors
 /// Returns error if:
 /// - No active account found
 /// - Database error occurs retrieving groups
 #[tauri::command]
-pub async fn get_groups(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Group>, String> {
-    Group::get_all_groups(wn.clone())
+pub async fn get_groups(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Group>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let groups = Group::get_all_groups(wn.clone())
         .await
-        .map_err(|e| format!("Error fetching groups for account: {}", e))
+        .context("Error fetching groups for account")?;
+
+    let mut resolved = Vec::with_capacity(groups.len());
+    for group in groups {
+        resolved.push(group.with_dm_display(wn.clone()).await);
+    }
+    Ok(resolved)
 }
@@ -0,0 +1,32 @@
+use crate::error::WhitenoiseError;
+use crate::outbox::DeliveryStatus;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Gets the delivery status of a message the active account sent to a group
+///
+/// Status changes are also emitted as `message_status_changed` events.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `event_id` - Hex encoded inner event ID of the message
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(DeliveryStatus)` - The delivery state and the relays that acknowledged the message
+/// * `Err(WhitenoiseError)` - Error message if the message isn't in the outbox or the lookup fails
+#[tauri::command]
+pub async fn get_message_delivery_status(
+    group_id: GroupIdParam,
+    event_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<DeliveryStatus, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let event_id = EventId::from_hex(event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event ID: {}", e)))?;
+
+    DeliveryStatus::find(&mls_group_id, &event_id, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching delivery status: {}", e)))
+}
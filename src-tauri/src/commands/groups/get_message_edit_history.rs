@@ -0,0 +1,38 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::messages::{Message, MessageEdit};
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Gets every version of a message: the original content followed by each edit
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `event_id` - Hex encoded event ID of the original message
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<MessageEdit>)` - The versions, oldest first. Empty if the message was deleted.
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Event ID is not a valid event ID
+/// - The message isn't in the group
+/// - Database error occurs
+#[tauri::command]
+pub async fn get_message_edit_history(
+    group_id: GroupIdParam,
+    event_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<MessageEdit>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let event_id = EventId::from_hex(event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event id: {}", e)))?;
+    Message::edit_history(&mls_group_id, &event_id, wn.clone())
+        .await
+        .context("Error fetching edit history")
+}
@@ -0,0 +1,36 @@
+use crate::app_lock;
+use crate::error::WhitenoiseError;
+use crate::params::GroupIdParam;
+use crate::reactions::{self, Reactor};
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+use std::collections::HashMap;
+
+/// Gets who reacted to a message, grouped by emoji
+///
+/// Retracted reactions aren't included. Each member is listed once per emoji, with the time
+/// of their reaction and the reaction's event ID (needed to retract it).
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `event_id` - Hex encoded ID of the message
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(HashMap<String, Vec<Reactor>>)` - Reactors keyed by emoji, in the order they reacted
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_message_reactions(
+    group_id: GroupIdParam,
+    event_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<HashMap<String, Vec<Reactor>>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let event_id = EventId::from_hex(event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event ID: {}", e)))?;
+
+    reactions::message_reactors(&mls_group_id, &event_id, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching reactions: {}", e)))
+}
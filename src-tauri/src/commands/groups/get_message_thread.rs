@@ -0,0 +1,37 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Gets a message thread: the root message followed by all of its replies
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `root_id` - Hex encoded event ID of the thread's root message
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<Message>)` - The root and its replies, ordered oldest to newest
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Root ID is not a valid event ID
+/// - Database error occurs
+#[tauri::command]
+pub async fn get_message_thread(
+    group_id: GroupIdParam,
+    root_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Message>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.into_bytes();
+    let root_id = EventId::from_hex(root_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid root id: {}", e)))?;
+    Message::thread(&mls_group_id, &root_id, wn.clone())
+        .await
+        .context("Error fetching thread")
+}
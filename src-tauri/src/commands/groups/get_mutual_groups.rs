@@ -0,0 +1,24 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the active account's groups that a contact is also a member of
+///
+/// # Arguments
+/// * `pubkey` - Public key of the contact, hex or npub
+/// * `wn` - Whitenoise state handle
+///
+/// # Returns
+/// * `Ok(Vec<Group>)` - Shared groups, most recently active first
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_mutual_groups(
+    pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Group>, WhitenoiseError> {
+    let pubkey = pubkey.public_key();
+    Group::mutual_groups(&pubkey, wn.clone())
+        .await
+        .context("Error fetching mutual groups")
+}
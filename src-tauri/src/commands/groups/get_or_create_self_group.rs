@@ -0,0 +1,80 @@
+use super::create_group::subscribe_to_groups;
+use crate::accounts::Account;
+use crate::device_sync;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, GroupType};
+use crate::profiling::{self, OperationKind};
+use crate::whitenoise::Whitenoise;
+use tauri::Emitter;
+
+/// Gets the active account's "Note to Self" group, creating it the first time.
+///
+/// The group has the account as its only member. It works like any other group, so it can hold
+/// personal notes and drafts. It's never treated as a direct message.
+///
+/// Only this device can read it: device sync announces the group to the account's other
+/// devices, but they have no MLS state for it and skip it (see `device_sync`). Moving files
+/// between devices needs them added to the group with their own key packages, which isn't done
+/// yet.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+/// * `Ok(Group)` - The group, with its display name and picture set
+/// * `Err(WhitenoiseError)` - Error message if the group couldn't be found or created
+#[tauri::command]
+pub async fn get_or_create_self_group(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Group, WhitenoiseError> {
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    let group_relays = wn.nostr.relays().await?;
+
+    // Held until the group is saved, so two calls can't both create one
+    let nostr_mls = wn.nostr_mls.lock().await;
+    if let Some(group) = Group::find_note_to_self(wn.clone())
+        .await
+        .context("Error fetching Note to Self group")?
+    {
+        drop(nostr_mls);
+        return Ok(group.with_dm_display(wn.clone()).await);
+    }
+
+    let pubkey = active_account.pubkey.to_hex();
+    let create_group_result = profiling::time("mls.create_group", OperationKind::Mls, || {
+        nostr_mls.create_group(
+            "Note to Self".to_string(),
+            String::new(),
+            Vec::new(),
+            vec![pubkey.clone()],
+            pubkey,
+            group_relays,
+        )
+    })
+    .map_err(|e| WhitenoiseError::Mls(format!("Error creating Note to Self group: {}", e)))?;
+
+    let mls_group = create_group_result.mls_group;
+    let group = Group::new(
+        mls_group.group_id().to_vec(),
+        mls_group.epoch().as_u64(),
+        GroupType::NoteToSelf,
+        create_group_result.nostr_group_data,
+        wn.clone(),
+        &app_handle,
+    )
+    .await
+    .context("Error saving Note to Self group")?;
+    drop(nostr_mls);
+
+    subscribe_to_groups(&active_account, wn.clone()).await?;
+
+    app_handle.emit("group_added", group.clone())?;
+
+    device_sync::share_group(&group, wn.clone()).await;
+
+    Ok(group.with_dm_display(wn.clone()).await)
+}
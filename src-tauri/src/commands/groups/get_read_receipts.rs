@@ -0,0 +1,29 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::read_receipts::ReadReceipt;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the latest read receipt from each member of a group
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<ReadReceipt>)` - The latest receipt per member, most recent first
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_read_receipts(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<ReadReceipt>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    ReadReceipt::for_group(&group, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching read receipts: {}", e)))
+}
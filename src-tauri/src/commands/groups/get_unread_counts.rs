@@ -0,0 +1,18 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::whitenoise::Whitenoise;
+use std::collections::HashMap;
+
+/// Gets the number of unread messages in each group of the active account
+///
+/// # Returns
+/// * `Ok(HashMap<String, u64>)` - Unread counts keyed by hex encoded MLS group ID
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_unread_counts(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<HashMap<String, u64>, WhitenoiseError> {
+    Group::unread_counts(wn.clone())
+        .await
+        .context("Error fetching unread counts")
+}
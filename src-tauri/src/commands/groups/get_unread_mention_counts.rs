@@ -0,0 +1,19 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::whitenoise::Whitenoise;
+use std::collections::HashMap;
+
+/// Gets the number of unread messages mentioning the active account in each of its groups
+///
+/// # Returns
+/// * `Ok(HashMap<String, u64>)` - Unread mention counts keyed by hex encoded MLS group ID; groups
+///   without unread mentions are left out
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_unread_mention_counts(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<HashMap<String, u64>, WhitenoiseError> {
+    Group::unread_mention_counts(wn.clone())
+        .await
+        .context("Error fetching unread mention counts")
+}
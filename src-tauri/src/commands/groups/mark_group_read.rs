@@ -0,0 +1,84 @@
+use super::send_mls_message::{create_unsigned_nostr_event, group_export_secret, publish_to_group};
+use crate::accounts::Account;
+use crate::device_sync;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::read_receipts::receipt_tags;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Marks a group as read up to (and including) a message
+///
+/// The read cursor only moves forward. If the cursor moved, it's shared with the account's other
+/// devices when device sync is enabled, and a read receipt is sent to the group when read
+/// receipts are.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `up_to_event_id` - Hex encoded event ID of the latest message read
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Group)` - The group with its updated read cursor
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID or event ID is invalid
+/// - Group not found, or the message isn't part of the group
+/// - Sending the read receipt fails
+#[tauri::command]
+pub async fn mark_group_read(
+    group_id: GroupIdParam,
+    up_to_event_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Group, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let event_id = EventId::from_hex(up_to_event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event ID: {}", e)))?;
+    let mut group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let moved = group
+        .mark_read(&event_id, wn.clone())
+        .await
+        .context("Error marking group as read")?;
+
+    if moved {
+        device_sync::share_read_marker(&group, wn.clone()).await;
+    }
+
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    if moved && account.settings.send_read_receipts {
+        send_read_receipt(&group, event_id, &wn).await?;
+    }
+
+    Ok(group)
+}
+
+/// Tells the group's members that the user has read up to a message
+pub(crate) async fn send_read_receipt(
+    group: &Group,
+    event_id: EventId,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let export_secret_hex = group_export_secret(group, wn).await?;
+    let export_nostr_keys = Keys::parse(&export_secret_hex)?;
+
+    let signer = wn.nostr.client.signer().await?;
+    let receipt = create_unsigned_nostr_event(
+        &signer,
+        String::new(),
+        protocol::kind_for(PayloadType::ReadReceipt),
+        Some(receipt_tags(event_id)),
+    )
+    .await?;
+
+    publish_to_group(group, &receipt, &export_nostr_keys, wn).await?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::localization::{self, StringKey};
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Merges a duplicate group into another group with the same people
+///
+/// The source group's local transcript is moved into the target group (each moved message keeps
+/// its original group in `origin_group_id`) and the source group is archived. The merge only
+/// changes the local copies of the groups.
+///
+/// # Arguments
+/// * `source_group_id` - Hex encoded MLS group ID of the group to merge and archive
+/// * `target_group_id` - Hex encoded MLS group ID of the group to keep
+/// * `notify_members` - Send a system message to the source group asking members to use the
+///   target group from now on
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Group)` - The target group
+/// * `Err(WhitenoiseError)` - Error message if the merge fails
+///
+/// # Errors
+/// Returns error if:
+/// - Either group ID is not valid hex or the group can't be found
+/// - Both IDs refer to the same group, or either group is already archived
+/// - Sending the notice or updating the database fails
+#[tauri::command]
+pub async fn merge_groups(
+    source_group_id: GroupIdParam,
+    target_group_id: GroupIdParam,
+    notify_members: bool,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Group, WhitenoiseError> {
+    let source_mls_group_id = source_group_id.into_bytes();
+    let target_mls_group_id = target_group_id.into_bytes();
+
+    let mut source = Group::find_by_mls_group_id(&source_mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    let target = Group::find_by_mls_group_id(&target_mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    source.validate_merge(&target)?;
+
+    // Sent before merging so the notice ends up in the merged transcript too
+    if notify_members {
+        let content = localization::format_string(
+            source.resolved_locale(),
+            StringKey::GroupMoved,
+            &[&target.name],
+        );
+        let tags = vec![Tag::custom(
+            TagKind::custom("moved_to"),
+            [target.nostr_group_id.clone()],
+        )];
+        send_mls_message(
+            source.clone(),
+            content,
+            protocol::kind_for(PayloadType::GroupMoved),
+            Some(tags),
+            None,
+            None,
+            None,
+            None,
+            wn.clone(),
+            app_handle,
+        )
+        .await?;
+    }
+
+    source
+        .merge_into(&target, wn.clone())
+        .await
+        .context("Error merging groups")
+}
@@ -1,19 +1,115 @@
+mod approve_join_request;
+mod bulk_group_action;
 mod create_group;
+mod create_group_from_template;
+mod create_group_invite_link;
+mod create_group_task;
+mod create_invite_message;
 mod delete_message;
+mod delete_mls_message;
+mod download_attachment;
+mod download_voice_message;
+mod edit_mls_message;
+mod fetch_group_messages;
 mod get_group;
 mod get_group_admins;
 mod get_group_and_messages;
+mod get_group_custom_data;
+mod get_group_media;
 mod get_group_members;
+mod get_group_messages;
+mod get_group_notes;
+mod get_group_notices;
+mod get_group_security_info;
+mod get_group_tasks;
 mod get_groups;
+mod get_message_delivery_status;
+mod get_message_edit_history;
+mod get_message_reactions;
+mod get_message_thread;
+mod get_mutual_groups;
+mod get_or_create_self_group;
+mod get_read_receipts;
+mod get_unread_counts;
+mod get_unread_mention_counts;
+mod mark_group_read;
+mod merge_groups;
+mod pending_join_requests;
+mod reject_join_request;
+mod remove_mls_reaction;
+mod replay_group_events;
+mod retry_pending_welcomes;
 mod rotate_key_in_group;
+mod send_group_notice;
+mod send_mls_attachment;
 mod send_mls_message;
+mod send_mls_reaction;
+mod send_quick_reply;
+mod send_typing_indicator;
+mod send_voice_message;
+mod set_group_content_filter;
+mod set_group_custom_data;
+mod set_group_locale;
+mod set_group_relays;
+mod set_group_sensitive;
+mod set_group_task_completed;
+mod snooze_group;
+mod update_group_note;
 
+pub use approve_join_request::approve_join_request;
+pub use bulk_group_action::bulk_group_action;
 pub use create_group::create_group;
+pub use create_group_from_template::create_group_from_template;
+pub use create_group_invite_link::create_group_invite_link;
+pub use create_group_task::create_group_task;
+pub use create_invite_message::create_invite_message;
 pub use delete_message::delete_message;
+pub use delete_mls_message::delete_mls_message;
+pub use download_attachment::download_attachment;
+pub use download_voice_message::download_voice_message;
+pub use edit_mls_message::edit_mls_message;
+pub use fetch_group_messages::fetch_group_messages;
 pub use get_group::get_group;
 pub use get_group_admins::get_group_admins;
 pub use get_group_and_messages::get_group_and_messages;
+pub use get_group_custom_data::get_group_custom_data;
+pub use get_group_media::get_group_media;
 pub use get_group_members::get_group_members;
+pub use get_group_messages::get_group_messages;
+pub use get_group_notes::get_group_notes;
+pub use get_group_notices::get_group_notices;
+pub use get_group_security_info::get_group_security_info;
+pub use get_group_tasks::get_group_tasks;
 pub use get_groups::get_groups;
+pub use get_message_delivery_status::get_message_delivery_status;
+pub use get_message_edit_history::get_message_edit_history;
+pub use get_message_reactions::get_message_reactions;
+pub use get_message_thread::get_message_thread;
+pub use get_mutual_groups::get_mutual_groups;
+pub use get_or_create_self_group::get_or_create_self_group;
+pub use get_read_receipts::get_read_receipts;
+pub use get_unread_counts::get_unread_counts;
+pub use get_unread_mention_counts::get_unread_mention_counts;
+pub use mark_group_read::mark_group_read;
+pub use merge_groups::merge_groups;
+pub use pending_join_requests::pending_join_requests;
+pub use reject_join_request::reject_join_request;
+pub use remove_mls_reaction::remove_mls_reaction;
+pub use replay_group_events::replay_group_events;
+pub use retry_pending_welcomes::retry_pending_welcomes;
 pub use rotate_key_in_group::rotate_key_in_group;
+pub use send_group_notice::send_group_notice;
+pub use send_mls_attachment::send_mls_attachment;
 pub use send_mls_message::send_mls_message;
+pub use send_mls_reaction::send_mls_reaction;
+pub use send_quick_reply::send_quick_reply;
+pub use send_typing_indicator::send_typing_indicator;
+pub use send_voice_message::send_voice_message;
+pub use set_group_content_filter::set_group_content_filter;
+pub use set_group_custom_data::set_group_custom_data;
+pub use set_group_locale::set_group_locale;
+pub use set_group_relays::set_group_relays;
+pub use set_group_sensitive::set_group_sensitive;
+pub use set_group_task_completed::set_group_task_completed;
+pub use snooze_group::{snooze_group, unsnooze_group};
+pub use update_group_note::update_group_note;
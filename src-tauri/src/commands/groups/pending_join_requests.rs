@@ -0,0 +1,29 @@
+use crate::error::WhitenoiseError;
+use crate::invite_messages::{self, JoinRequest};
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the pending requests to join a group through the invite messages and invite links the
+/// active account created
+///
+/// Requests leave the list once they're approved or rejected, see `approve_join_request` and
+/// `reject_join_request`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<JoinRequest>)` - The requests, newest first, each with the key package to add the
+///   requester with
+/// * `Err(WhitenoiseError)` - Error message if the requests can't be fetched
+#[tauri::command]
+pub async fn pending_join_requests(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<JoinRequest>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    invite_messages::join_requests(&mls_group_id, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error fetching join requests: {}", e)))
+}
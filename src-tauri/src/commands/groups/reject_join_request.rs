@@ -0,0 +1,47 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::invite_messages;
+use crate::params::{GroupIdParam, PubkeyParam};
+use crate::whitenoise::Whitenoise;
+
+/// Rejects a request to join a group
+///
+/// The request is forgotten without telling the requester. It doesn't count as a use of the
+/// invite link it came through, and the requester can ask again while the invite is valid.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `requester_pubkey` - Public key of the requester, hex or npub
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(())` - If the request was rejected
+/// * `Err(WhitenoiseError)` - Error message if the active account isn't an admin of the group or
+///   there's no pending request from the requester
+#[tauri::command]
+pub async fn reject_join_request(
+    group_id: GroupIdParam,
+    requester_pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let requester = requester_pubkey.public_key();
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let group = Group::find_by_mls_group_id_for_account(&mls_group_id, &active_pubkey, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    if !group.admin_pubkeys.contains(&active_pubkey.to_hex()) {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only group admins can reject join requests".to_string(),
+        ));
+    }
+
+    let request =
+        invite_messages::join_request(&mls_group_id, &requester, &active_pubkey, wn.clone())
+            .await
+            .context("Error fetching join request")?;
+    invite_messages::reject_request(&request, &active_pubkey, wn.clone())
+        .await
+        .context("Error rejecting join request")
+}
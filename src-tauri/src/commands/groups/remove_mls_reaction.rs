@@ -0,0 +1,91 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::localization::{self, StringKey};
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::reactions;
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Retracts the active account's reactions to a message
+///
+/// Sends a single kind 5 deletion referencing the reactions through the group. Every member
+/// tombstones them and emits `mls_reactions_updated` with the new aggregate.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `target_event_id` - Hex encoded ID of the message that was reacted to
+/// * `emoji` - Only retract reactions with this emoji. All of our reactions if not set.
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The deletion message if successful
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID or event ID are invalid, or the group can't be found
+/// - The active account has no matching reaction on the message
+/// - Sending the deletion fails
+#[tauri::command]
+pub async fn remove_mls_reaction(
+    group_id: GroupIdParam,
+    target_event_id: &str,
+    emoji: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    let target_event_id = EventId::from_hex(target_event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event ID: {}", e)))?;
+
+    let own_reactions = reactions::own_reactions(
+        &mls_group_id,
+        &target_event_id,
+        emoji.as_deref(),
+        wn.clone(),
+    )
+    .await
+    .context("Error fetching reactions")?;
+    if own_reactions.is_empty() {
+        return Err(WhitenoiseError::NotFound(
+            "No reaction to remove".to_string(),
+        ));
+    }
+
+    retract_reactions(group, &own_reactions, wn, app_handle).await
+}
+
+/// Sends a deletion for a set of the active account's reactions
+pub(crate) async fn retract_reactions(
+    group: Group,
+    own_reactions: &[Message],
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let tags = own_reactions
+        .iter()
+        .map(|reaction| Tag::event(reaction.event_id))
+        .collect();
+
+    let content = localization::template(group.resolved_locale(), StringKey::ReactionRemoved);
+    send_mls_message(
+        group,
+        content.to_string(),
+        protocol::kind_for(PayloadType::Deletion),
+        Some(tags),
+        None,
+        None,
+        None,
+        None,
+        wn,
+        app_handle,
+    )
+    .await
+}
@@ -0,0 +1,30 @@
+use crate::error::WhitenoiseError;
+use crate::group_event_log::{self, ReplayReport};
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Replays the logged events of a group into scratch MLS state and compares the outcomes with
+/// the logged ones. Only available in dev builds.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(ReplayReport)` - Every replayed event with its logged and replayed outcome
+/// * `Err(WhitenoiseError)` - Error message outside dev builds or if the replay fails
+#[tauri::command]
+pub async fn replay_group_events(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<ReplayReport, WhitenoiseError> {
+    if !group_event_log::is_enabled() {
+        return Err(WhitenoiseError::Unauthorized(
+            "Replaying group events is only available in dev builds".to_string(),
+        ));
+    }
+    let mls_group_id = group_id.into_bytes();
+    group_event_log::replay(&mls_group_id, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::Internal(format!("Error replaying group events: {}", e)))
+}
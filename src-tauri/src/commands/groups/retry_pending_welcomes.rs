@@ -0,0 +1,109 @@
+use super::create_group::{
+    current_epoch, keep_pending, publish_welcomes, wrap_welcomes, WelcomeDelivery, WelcomeTarget,
+};
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::pending_welcomes::PendingWelcome;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Sends again the welcomes to members of a group that couldn't be sent when it was created
+///
+/// Only the account that owns the group can send its welcomes, and only the ones made at the
+/// group's current epoch still let members join. Older welcomes are dropped and reported as
+/// failures; those members have to be invited again.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(WelcomeDelivery)` - The members who are now welcomed, and the ones whose welcome still
+///   couldn't be sent. Those stay pending for another retry, unless their welcome was stale.
+/// * `Err(WhitenoiseError)` - Error message if the group or its pending welcomes can't be fetched
+#[tauri::command]
+pub async fn retry_pending_welcomes(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<WelcomeDelivery, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    let group =
+        Group::find_by_mls_group_id_for_account(&mls_group_id, &active_account.pubkey, wn.clone())
+            .await
+            .context("Error fetching group")?;
+    let signer = wn.nostr.client.signer().await?;
+    // The signer follows account switches, so make sure it still belongs to the group's account
+    if signer.get_public_key().await? != group.account_pubkey {
+        return Err(WhitenoiseError::Unauthorized(
+            "Welcomes can only be sent by the account that owns the group".to_string(),
+        ));
+    }
+
+    let epoch = current_epoch(&mls_group_id, wn.clone())
+        .await
+        .context("Error loading group state")?;
+    let stale = PendingWelcome::remove_stale(&active_account, &mls_group_id, epoch, wn.clone())
+        .await
+        .context("Error dropping stale welcomes")?;
+    let stale_failures: Vec<(WelcomeTarget, String)> = stale
+        .into_iter()
+        .map(|welcome| {
+            (
+                WelcomeTarget {
+                    member_pubkey: welcome.member_pubkey.to_hex(),
+                    key_package_event_id: welcome.key_package_event_id,
+                    serialized_welcome_message: Vec::new(),
+                },
+                "The group changed since this welcome was made, invite the member again"
+                    .to_string(),
+            )
+        })
+        .collect();
+
+    let pending = PendingWelcome::for_group(&active_account, &mls_group_id, wn.clone())
+        .await
+        .context("Error fetching pending welcomes")?;
+    if pending.is_empty() {
+        return Ok(WelcomeDelivery::new(&[], &stale_failures));
+    }
+
+    let targets = pending
+        .into_iter()
+        .map(|welcome| WelcomeTarget {
+            member_pubkey: welcome.member_pubkey.to_hex(),
+            key_package_event_id: welcome.key_package_event_id,
+            serialized_welcome_message: welcome.welcome,
+        })
+        .collect();
+    let (welcomes, mut failures) =
+        wrap_welcomes(targets, &active_account, &signer, wn.clone(), &app_handle).await;
+    let (welcomed, publish_failures) = publish_welcomes(welcomes, wn.clone()).await;
+    failures.extend(publish_failures);
+
+    for target in &welcomed {
+        let Ok(member_pubkey) = PublicKey::from_hex(&target.member_pubkey) else {
+            continue;
+        };
+        if let Err(e) =
+            PendingWelcome::remove(&active_account, &mls_group_id, &member_pubkey, wn.clone()).await
+        {
+            tracing::warn!(
+                target: "whitenoise::commands::groups::retry_pending_welcomes",
+                "Failed to forget the welcome sent to {}: {}",
+                target.member_pubkey,
+                e
+            );
+        }
+    }
+    keep_pending(&active_account, &mls_group_id, epoch, &failures, wn.clone()).await;
+    failures.extend(stale_failures);
+
+    Ok(WelcomeDelivery::new(&welcomed, &failures))
+}
@@ -1,20 +1,18 @@
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::Group;
+use crate::params::GroupIdParam;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
 #[tauri::command]
 pub async fn rotate_key_in_group(
-    group_id: &str,
+    group_id: GroupIdParam,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
+) -> Result<(), WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
     let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
         .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
-    group
-        .self_update_keys(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+        .context("Error fetching group")?;
+    group.self_update_keys(wn.clone()).await?;
     Ok(())
 }
@@ -0,0 +1,69 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Sends an announcement to a group as an admin notice. Only group admins can send notices.
+///
+/// Notices are rendered apart from regular chat messages and notify every member, even those
+/// who snoozed the group.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `content` - The text of the notice
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The sent notice
+/// * `Err(WhitenoiseError)` - Error message if sending fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Group not found
+/// - The active account is not an admin of the group
+/// - The notice is empty
+/// - Sending the message fails
+#[tauri::command]
+pub async fn send_group_notice(
+    group_id: GroupIdParam,
+    content: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    if !group.admin_pubkeys.contains(&active_pubkey.to_hex()) {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only group admins can send notices".to_string(),
+        ));
+    }
+    if content.trim().is_empty() {
+        return Err(WhitenoiseError::InvalidInput(
+            "Notice can't be empty".to_string(),
+        ));
+    }
+
+    send_mls_message(
+        group,
+        content,
+        protocol::kind_for(PayloadType::GroupNotice),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await
+}
@@ -0,0 +1,93 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::media::attachments::{encrypt_attachment, mime_type_for_path};
+use crate::media::{sanitize_media, FileUpload};
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+use std::path::Path;
+
+/// Sends a file to a group as an encrypted attachment
+///
+/// The file is stripped of identifying metadata, encrypted with a fresh key and uploaded to
+/// the account's media server (see `set_media_server`), and to a mirror if the account mirrors
+/// attachments. The URLs, key and hashes are sent to the group in an `imeta` tag; members fetch
+/// the file with `download_attachment`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `file_path` - Path of the file to send
+/// * `caption` - Optional text sent with the file
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The sent message
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex or the group can't be found
+/// - The file can't be read, encrypted or uploaded
+/// - Sending the message fails
+#[tauri::command]
+pub async fn send_mls_attachment(
+    group_id: GroupIdParam,
+    file_path: String,
+    caption: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let path = Path::new(&file_path);
+    let filename = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| WhitenoiseError::InvalidInput("Invalid file path".to_string()))?
+        .to_string();
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Error reading file: {}", e)))?;
+
+    let file = FileUpload {
+        filename,
+        mime_type: mime_type_for_path(path).to_string(),
+        data,
+    };
+    let sanitized = sanitize_media(&file)?;
+
+    let encrypted = encrypt_attachment(&sanitized.data, &file.filename, &file.mime_type)?;
+
+    let active_account = Account::get_active(wn.clone()).await?;
+    let meta = encrypted
+        .upload(&active_account.settings.media_server, &wn.nostr.blossom)
+        .await?;
+
+    tracing::debug!(
+        target: "whitenoise::commands::groups::send_mls_attachment",
+        "Uploaded attachment {} to {}",
+        meta.filename,
+        meta.url
+    );
+
+    send_mls_message(
+        group,
+        caption.unwrap_or_default(),
+        protocol::kind_for(PayloadType::ChatMessage),
+        Some(vec![meta.to_tag()]),
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await
+}
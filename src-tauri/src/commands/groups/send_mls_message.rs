@@ -1,7 +1,15 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::Group;
 use crate::media::{add_media_file, FileUpload};
-use crate::messages::Message;
+use crate::messages::{
+    self, reply_tags, Message, EDIT_KIND, GROUP_NOTICE_KIND, SYSTEM_MESSAGE_KINDS,
+};
+use crate::outbox::{DeliveryState, DeliveryStatus};
+use crate::params::PubkeyParam;
+use crate::payments;
+use crate::profiling::{self, OperationKind};
+use crate::relay_blacklist::RelayBlacklist;
 use crate::secrets_store;
 use crate::whitenoise::Whitenoise;
 use lightning_invoice::SignedRawBolt11Invoice;
@@ -10,6 +18,7 @@ use nostr_sdk::NostrSigner;
 use std::str::FromStr;
 use std::sync::Arc;
 use tauri::Emitter;
+use zeroize::Zeroizing;
 
 #[tauri::command]
 pub async fn send_mls_message(
@@ -18,37 +27,83 @@ pub async fn send_mls_message(
     kind: u16,
     tags: Option<Vec<Tag>>,
     uploaded_files: Option<Vec<FileUpload>>,
+    reply_to_event_id: Option<String>,
+    expires_in: Option<u64>,
+    mentions: Option<Vec<PubkeyParam>>,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<Message, String> {
-    let nostr_keys = wn.nostr.client.signer().await.map_err(|e| e.to_string())?;
+) -> Result<Message, WhitenoiseError> {
+    let nostr_keys = wn.nostr.client.signer().await?;
     let mut final_tags = tags.unwrap_or_default();
     let mut final_content = message;
 
-    // Get export secret early as we need it for file encryption
-    let export_secret_hex;
-    let epoch;
-    {
-        let nostr_mls = wn.nostr_mls.lock().await;
-        (export_secret_hex, epoch) = nostr_mls
-            .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())
-            .map_err(|e| e.to_string())?;
+    // Reference the parent message (and its thread root) when replying
+    if let Some(reply_to_event_id) = reply_to_event_id {
+        let parent_id = EventId::from_hex(&reply_to_event_id).map_err(|e| {
+            WhitenoiseError::InvalidInput(format!("Invalid reply_to_event_id: {}", e))
+        })?;
+        let parent = Message::find_by_event_id(parent_id, wn.clone())
+            .await
+            .context("Error fetching parent message")?;
+        if parent.mls_group_id != group.mls_group_id {
+            return Err(WhitenoiseError::InvalidInput(
+                "Parent message is not in this group".to_string(),
+            ));
+        }
+        final_tags.extend(reply_tags(&parent));
     }
 
-    // Store the export secret key in the secrets store
-    secrets_store::store_mls_export_secret(
-        group.mls_group_id.clone(),
-        epoch,
-        export_secret_hex.clone(),
-        wn.data_dir.as_path(),
-    )
-    .map_err(|e| e.to_string())?;
+    // Mentioned pubkeys (hex or npub) are tagged so their clients can flag the message
+    for mention in mentions.unwrap_or_default() {
+        let tag = Tag::public_key(mention.public_key());
+        if !final_tags.contains(&tag) {
+            final_tags.push(tag);
+        }
+    }
 
-    let export_nostr_keys = Keys::parse(&export_secret_hex).map_err(|e| e.to_string())?;
+    // Don't share invoices that can no longer be paid
+    if let Some(request) = payments::payment_request_from_tags(
+        &Tags::from_list(final_tags.clone()),
+        Timestamp::now().as_u64(),
+    ) {
+        if request.expired {
+            return Err(WhitenoiseError::InvalidInput(
+                "The invoice in this message has expired".to_string(),
+            ));
+        }
+    }
 
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    // Ephemeral messages carry a NIP-40 expiration; every member deletes them locally once it
+    // passes. Chat messages fall back to the group's default expiry.
+    let expires_in = if SYSTEM_MESSAGE_KINDS.contains(&kind) {
+        expires_in
+    } else {
+        expires_in.or(group.message_ttl)
+    };
+    if let Some(expires_in) = expires_in {
+        if expires_in == 0 {
+            return Err(WhitenoiseError::InvalidInput(
+                "expires_in must be greater than zero".to_string(),
+            ));
+        }
+        final_tags.push(Tag::expiration(Timestamp::now() + expires_in));
+    }
+
+    // Get export secret early as we need it for file encryption
+    let export_secret_hex = group_export_secret(&group, &wn).await?;
+    let export_nostr_keys =
+        Keys::parse(&export_secret_hex).map_err(|e| WhitenoiseError::InvalidKey(e.to_string()))?;
+
+    let active_account = Account::get_active(wn.clone()).await?;
+
+    // Mask the text of chat messages, edits and notices before it's encrypted; media URLs are
+    // added later
+    if !SYSTEM_MESSAGE_KINDS.contains(&kind) || kind == EDIT_KIND || kind == GROUP_NOTICE_KIND {
+        final_content = active_account
+            .settings
+            .content_filter
+            .filter_outbound(&group.content_filter, &final_content)?;
+    }
 
     // Process media files if present
     if let Some(uploaded_files) = uploaded_files {
@@ -82,7 +137,9 @@ pub async fn send_mls_message(
 
         // If no files were processed successfully, return an error
         if uploaded_media.is_empty() && files_count > 0 {
-            return Err("Failed to process any media files".to_string());
+            return Err(WhitenoiseError::Internal(
+                "Failed to process any media files".to_string(),
+            ));
         }
 
         // Add media content and tags
@@ -102,9 +159,7 @@ pub async fn send_mls_message(
     }
 
     let inner_event =
-        create_unsigned_nostr_event(&nostr_keys, final_content, kind, Some(final_tags))
-            .await
-            .map_err(|e| e.to_string())?;
+        create_unsigned_nostr_event(&nostr_keys, final_content, kind, Some(final_tags)).await?;
 
     tracing::debug!(
         target: "whitenoise::commands::groups::send_mls_message",
@@ -112,68 +167,186 @@ pub async fn send_mls_message(
         inner_event.clone()
     );
 
-    let json_event_string = serde_json::to_string(&inner_event).map_err(|e| e.to_string())?;
+    let outer_event = build_group_event(&group, &inner_event, &export_nostr_keys, &wn).await?;
+    let inner_event_id = inner_event
+        .id
+        .ok_or_else(|| WhitenoiseError::Internal("Inner event has no id".to_string()))?;
+    let mut status =
+        DeliveryStatus::enqueue(&group, &inner_event_id, &outer_event, wn.clone()).await?;
 
-    let serialized_message;
+    // Resolved before the message is stored, since storing a settings update can change the
+    // group's relays and the update has to reach members on the current ones
+    let relays = group.publish_relays(wn.clone()).await?;
+
+    let mut message = group
+        .add_message(
+            outer_event.id.to_string(),
+            inner_event.clone(),
+            wn.clone(),
+            app_handle.clone(),
+            None,
+        )
+        .await?;
+
+    // The message shows up in the transcript right away and stays pending until a relay
+    // accepts it; the outbox keeps retrying if this first attempt fails
+    message.pending = true;
+    app_handle
+        .emit("mls_message_sent", (group.clone(), message.clone()))
+        .expect("Couldn't emit event");
+
+    // Relays that rate limited us are skipped until we're done backing off from them. If that's
+    // all of them, the outbox publishes the message once one is available again.
+    let relays = wn.nostr.relay_monitor.without_backed_off(relays);
+    if relays.is_empty() {
+        return Ok(message);
+    }
+    wn.nostr
+        .relay_monitor
+        .track_delivery(outer_event.id, &relays);
+    match profiling::time_async(
+        "relay.publish",
+        OperationKind::Relay,
+        wn.nostr.client.send_event_to(relays, &outer_event),
+    )
+    .await
+    {
+        Ok(output) => {
+            RelayBlacklist::record_rejections(&output, wn.clone()).await;
+            status
+                .record_publish(&output, wn.clone(), &app_handle)
+                .await
+        }
+        Err(e) => {
+            status
+                .record_failure(&e.to_string(), wn.clone(), &app_handle)
+                .await
+        }
+    }?;
+
+    message.pending = status.state == DeliveryState::Pending;
+    Ok(message)
+}
+
+/// Returns the group's current export secret (hex encoded), storing it in the secrets store so
+/// that messages from this epoch can still be decrypted later. The returned copy is zeroized when
+/// it's dropped.
+pub(crate) async fn group_export_secret(
+    group: &Group,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<Zeroizing<String>, WhitenoiseError> {
+    let export_secret_hex;
+    let epoch;
     {
         let nostr_mls = wn.nostr_mls.lock().await;
-        serialized_message = nostr_mls
-            .create_message_for_group(group.mls_group_id.clone(), json_event_string)
-            .map_err(|e| e.to_string())?;
+        (export_secret_hex, epoch) = nostr_mls
+            .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())
+            .map_err(|e| WhitenoiseError::Mls(format!("Error exporting secret: {}", e)))?;
     }
 
-    let encrypted_content = nip44::encrypt(
-        export_nostr_keys.secret_key(),
-        &export_nostr_keys.public_key(),
-        &serialized_message,
-        nip44::Version::V2,
-    )
-    .map_err(|e| e.to_string())?;
+    let export_secret_hex = Zeroizing::new(export_secret_hex);
+
+    // Store the export secret key in the secrets store, unless it's already there
+    let stored = secrets_store::get_secret_for_epoch(&group.mls_group_id, epoch, &wn.data_dir)?;
+    if stored.as_deref() != Some(&*export_secret_hex) {
+        secrets_store::store_mls_export_secret(
+            group.mls_group_id.clone(),
+            epoch,
+            export_secret_hex.to_string(),
+            wn.data_dir.as_path(),
+        )?;
+    }
 
-    let ephemeral_nostr_keys = Keys::generate();
+    Ok(export_secret_hex)
+}
 
-    let published_message_event = EventBuilder::new(Kind::MlsGroupMessage, encrypted_content)
-        .tags(vec![Tag::custom(
-            TagKind::h(),
-            vec![group.nostr_group_id.clone()],
-        )])
-        .sign(&ephemeral_nostr_keys)
-        .await
-        .map_err(|e| e.to_string())?;
+/// Wraps an inner event in an MLS application message, encrypts it with the group's export
+/// secret and publishes it to the group relays from a throwaway key
+///
+/// Unlike `send_mls_message`, nothing is stored and delivery isn't tracked.
+///
+/// # Returns
+/// * `Ok(EventId)` - The ID of the published outer event
+pub(crate) async fn publish_to_group(
+    group: &Group,
+    inner_event: &UnsignedEvent,
+    export_nostr_keys: &Keys,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<EventId, WhitenoiseError> {
+    let published_message_event =
+        build_group_event(group, inner_event, export_nostr_keys, wn).await?;
 
     tracing::debug!(
         target: "whitenoise::commands::groups::send_mls_message",
         "Publishing MLSMessage event to group relays"
     );
 
-    let relays = group.relays(wn.clone()).await.map_err(|e| e.to_string())?;
-    let outer_event_id = wn
-        .nostr
-        .client
-        .send_event_to(relays, &published_message_event)
-        .await
-        .map_err(|e| e.to_string())?;
+    let relays = group.publish_relays(wn.clone()).await?;
+    let output = profiling::time_async(
+        "relay.publish",
+        OperationKind::Relay,
+        wn.nostr
+            .client
+            .send_event_to(relays, &published_message_event),
+    )
+    .await?;
+    RelayBlacklist::record_rejections(&output, wn.clone()).await;
 
-    let message = group
-        .add_message(
-            outer_event_id.id().to_string(),
-            inner_event.clone(),
-            wn.clone(),
-            app_handle.clone(),
-            None,
+    Ok(*output.id())
+}
+
+/// Wraps an inner event in an MLS application message and encrypts it with the group's export
+/// secret into a kind 445 event signed by a throwaway key
+///
+/// The NIP-40 expiration of the inner event, if any, is copied to the outer event so that
+/// relays can drop it too.
+pub(crate) async fn build_group_event(
+    group: &Group,
+    inner_event: &UnsignedEvent,
+    export_nostr_keys: &Keys,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<Event, WhitenoiseError> {
+    // The plaintext is handed over to nostr_openmls, which doesn't zeroize it
+    let json_event_string = serde_json::to_string(inner_event)?;
+
+    let serialized_message;
+    {
+        let nostr_mls = wn.nostr_mls.lock().await;
+        serialized_message = profiling::time("mls.create_message", OperationKind::Mls, || {
+            nostr_mls.create_message_for_group(group.mls_group_id.clone(), json_event_string)
+        })
+        .map_err(|e| WhitenoiseError::Mls(format!("Error creating message: {}", e)))?;
+    }
+
+    let encrypted_content = profiling::time("nip44.encrypt", OperationKind::Crypto, || {
+        nip44::encrypt(
+            export_nostr_keys.secret_key(),
+            &export_nostr_keys.public_key(),
+            &serialized_message,
+            nip44::Version::V2,
         )
-        .await
-        .map_err(|e| e.to_string())?;
+    })
+    .map_err(|e| WhitenoiseError::Internal(format!("Error encrypting message: {}", e)))?;
 
-    app_handle
-        .emit("mls_message_sent", (group.clone(), message.clone()))
-        .expect("Couldn't emit event");
+    let ephemeral_nostr_keys = Keys::generate();
 
-    Ok(message)
+    let mut outer_tags = vec![Tag::custom(
+        TagKind::h(),
+        vec![group.nostr_group_id.clone()],
+    )];
+    if let Some(expires_at) = messages::expiration(&inner_event.tags) {
+        outer_tags.push(Tag::expiration(expires_at));
+    }
+
+    EventBuilder::new(Kind::MlsGroupMessage, encrypted_content)
+        .tags(outer_tags)
+        .sign(&ephemeral_nostr_keys)
+        .await
+        .map_err(WhitenoiseError::from)
 }
 
 /// Creates an unsigned nostr event with the given parameters
-async fn create_unsigned_nostr_event(
+pub(crate) async fn create_unsigned_nostr_event(
     nostr_keys: &Arc<dyn NostrSigner>,
     message: String,
     kind: u16,
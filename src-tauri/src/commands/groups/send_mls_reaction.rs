@@ -0,0 +1,104 @@
+use super::remove_mls_reaction::retract_reactions;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::reactions;
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Sends a NIP-25 style reaction to a message in an MLS group
+///
+/// The reaction is a kind 7 rumor with `e`, `p` and `k` tags referencing the target message,
+/// sent through the group exactly like a regular message.
+///
+/// With `replace`, the active account's other reactions to the message are retracted first, so
+/// the new reaction takes their place.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `target_event_id` - Hex encoded ID of the message being reacted to
+/// * `emoji` - The reaction content (an emoji, `+`, `-` or a `:shortcode:`)
+/// * `replace` - Whether to retract our other reactions to the message
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The reaction message if successful
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex or the group can't be found
+/// - The reaction content is invalid
+/// - The target message isn't in the group
+/// - Retracting the previous reactions or sending the reaction fails
+#[tauri::command]
+pub async fn send_mls_reaction(
+    group_id: GroupIdParam,
+    target_event_id: &str,
+    emoji: String,
+    replace: Option<bool>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    reactions::validate_reaction_content(&emoji)?;
+
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let target_event_id = EventId::from_hex(target_event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Invalid event ID: {}", e)))?;
+    let target = Message::find_by_event_id(target_event_id, wn.clone())
+        .await
+        .context("Error fetching target message")?;
+    if target.mls_group_id != group.mls_group_id {
+        return Err(WhitenoiseError::InvalidInput(
+            "Target message is not in this group".to_string(),
+        ));
+    }
+
+    if replace.unwrap_or(false) {
+        let previous: Vec<Message> =
+            reactions::own_reactions(&group.mls_group_id, &target.event_id, None, wn.clone())
+                .await
+                .context("Error fetching reactions")?
+                .into_iter()
+                .filter(|reaction| reaction.content.trim() != emoji.trim())
+                .collect();
+        if !previous.is_empty() {
+            retract_reactions(group.clone(), &previous, wn.clone(), app_handle.clone()).await?;
+        }
+    }
+
+    let mut tags = vec![
+        Tag::event(target.event_id),
+        Tag::public_key(target.author_pubkey),
+        Tag::custom(
+            TagKind::single_letter(Alphabet::K, false),
+            vec![target.event_kind.to_string()],
+        ),
+    ];
+    // Reactions to an ephemeral message expire with it
+    if let Some(expires_at) = target.expires_at {
+        tags.push(Tag::expiration(expires_at));
+    }
+
+    send_mls_message(
+        group,
+        emoji,
+        protocol::kind_for(PayloadType::Reaction),
+        Some(tags),
+        None,
+        None,
+        None,
+        None,
+        wn,
+        app_handle,
+    )
+    .await
+}
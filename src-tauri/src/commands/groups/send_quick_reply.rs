@@ -0,0 +1,85 @@
+use super::mark_group_read::mark_group_read;
+use super::send_mls_message::send_mls_message;
+use crate::commands::nostr::ensure_nostr_initialized;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+#[cfg(mobile)]
+use tauri_plugin_notification::NotificationExt;
+
+/// Sends a reply typed into a message notification
+///
+/// On mobile a notification action can wake the backend without the frontend ever loading, so
+/// the Nostr identity and MLS state of the active account are set up here if they aren't ready.
+/// The reply goes through the outbox like any other message: when no relay is reachable it's
+/// kept pending and republished once a relay connection is back. Replying marks the group as
+/// read and dismisses the notification.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID, from the notification's `group_id` extra
+/// * `content` - The reply text
+/// * `notification_id` - ID of the notification to dismiss
+/// * `wn` - Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The sent message, `pending` if no relay accepted it yet
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn send_quick_reply(
+    group_id: GroupIdParam,
+    content: String,
+    notification_id: Option<i32>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    if content.trim().is_empty() {
+        return Err(WhitenoiseError::InvalidInput(
+            "Reply can't be empty".to_string(),
+        ));
+    }
+
+    ensure_nostr_initialized(wn.clone(), app_handle.clone()).await?;
+
+    let mls_group_id = group_id.clone().into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let message = send_mls_message(
+        group,
+        content,
+        protocol::kind_for(PayloadType::ChatMessage),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle.clone(),
+    )
+    .await?;
+
+    if let Err(e) = mark_group_read(group_id, &message.event_id.to_hex(), wn.clone()).await {
+        tracing::warn!(
+            target: "whitenoise::commands::groups::send_quick_reply",
+            "Failed to mark group as read after replying: {}",
+            e
+        );
+    }
+
+    #[cfg(mobile)]
+    if let Some(notification_id) = notification_id {
+        let _ = app_handle
+            .notification()
+            .remove_active(vec![notification_id]);
+    }
+    #[cfg(desktop)]
+    let _ = notification_id;
+
+    Ok(message)
+}
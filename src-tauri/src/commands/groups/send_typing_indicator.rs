@@ -0,0 +1,54 @@
+use super::send_mls_message::{create_unsigned_nostr_event, group_export_secret, publish_to_group};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::typing::TYPING_INDICATOR_TTL_SECS;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Tells the other members of a group that the active account is typing
+///
+/// Sends an ephemeral rumor through the group that expires after a few seconds. Nothing is
+/// stored locally; receivers emit `peer_typing`. Frontends should call this at most every few
+/// seconds while the user keeps typing.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(())` - If the indicator was published
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex or the group can't be found
+/// - Creating or publishing the MLS message fails
+#[tauri::command]
+pub async fn send_typing_indicator(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let export_secret_hex = group_export_secret(&group, &wn).await?;
+    let export_nostr_keys =
+        Keys::parse(&export_secret_hex).map_err(|e| WhitenoiseError::InvalidKey(e.to_string()))?;
+
+    let signer = wn.nostr.client.signer().await?;
+    let expires_at = Timestamp::now() + TYPING_INDICATOR_TTL_SECS;
+    let inner_event = create_unsigned_nostr_event(
+        &signer,
+        String::new(),
+        protocol::kind_for(PayloadType::TypingIndicator),
+        Some(vec![Tag::expiration(expires_at)]),
+    )
+    .await?;
+
+    publish_to_group(&group, &inner_event, &export_nostr_keys, &wn).await?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::media::attachments::encrypt_attachment;
+use crate::media::voice::{normalize_waveform, validate_voice_recording, VOICE_MIME_TYPE};
+use crate::messages::Message;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Sends a voice message to a group
+///
+/// The recording is encrypted and uploaded like any other attachment (see
+/// `send_mls_attachment`), with its duration and waveform added to the `imeta` tag so members
+/// can draw the player before downloading it with `download_voice_message`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `audio_bytes` - The Ogg Opus recording
+/// * `duration_ms` - Length of the recording in milliseconds
+/// * `waveform` - Optional amplitudes (0-100) reported by the recorder, downsampled before sending
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Message)` - The sent message
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex or the group can't be found
+/// - The recording isn't Ogg Opus or is too long
+/// - The recording can't be encrypted or uploaded
+/// - Sending the message fails
+#[tauri::command]
+pub async fn send_voice_message(
+    group_id: GroupIdParam,
+    audio_bytes: Vec<u8>,
+    duration_ms: u64,
+    waveform: Option<Vec<u8>>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    validate_voice_recording(&audio_bytes, duration_ms)?;
+
+    let mut encrypted = encrypt_attachment(&audio_bytes, "voice-message.ogg", VOICE_MIME_TYPE)?;
+    encrypted.meta.duration_ms = Some(duration_ms);
+    encrypted.meta.waveform = Some(normalize_waveform(&waveform.unwrap_or_default()));
+
+    let active_account = Account::get_active(wn.clone()).await?;
+    let meta = encrypted
+        .upload(&active_account.settings.media_server, &wn.nostr.blossom)
+        .await?;
+
+    tracing::debug!(
+        target: "whitenoise::commands::groups::send_voice_message",
+        "Uploaded {}ms voice message to {}",
+        duration_ms,
+        meta.url
+    );
+
+    send_mls_message(
+        group,
+        String::new(),
+        protocol::kind_for(PayloadType::ChatMessage),
+        Some(vec![meta.to_tag()]),
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await
+}
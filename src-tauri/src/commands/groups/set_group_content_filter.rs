@@ -0,0 +1,36 @@
+use crate::content_filters::GroupContentFilter;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Overrides the account's content filter settings for a group
+///
+/// This is a local preference; it isn't shared with the other members.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `content_filter` - Whether the outbound and inbound filters apply in this group. Unset
+///   values follow the account settings.
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Group)` - The updated group
+/// * `Err(WhitenoiseError)` - Error message if the update fails
+#[tauri::command]
+pub async fn set_group_content_filter(
+    group_id: GroupIdParam,
+    content_filter: GroupContentFilter,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Group, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let mut group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    group
+        .set_content_filter(content_filter, wn.clone())
+        .await
+        .context("Error updating content filter")?;
+    Ok(group)
+}
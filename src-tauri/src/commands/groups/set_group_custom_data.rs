@@ -0,0 +1,76 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_custom_data::{self, CustomDataUpdate, GroupCustomData};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Sets a frontend feature's shared state in a group
+///
+/// The value replaces the namespace's current one for every member; concurrent changes are
+/// resolved last-writer-wins. Values are limited to a few KiB and groups to a fixed number of
+/// namespaces, so this is meant for small state like settings or poll results, not files.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `namespace` - Name of the feature's state, e.g. `polls`
+/// * `value` - Any JSON value; `null` clears the namespace
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Option<GroupCustomData>)` - The namespace's new state, or `None` if it was cleared
+/// * `Err(WhitenoiseError)` - Error message if the namespace or value is invalid, the group has no
+///   namespaces left, or sending fails
+#[tauri::command]
+pub async fn set_group_custom_data(
+    group_id: GroupIdParam,
+    namespace: String,
+    value: serde_json::Value,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<GroupCustomData>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let update = CustomDataUpdate { namespace, value };
+    group_custom_data::validate_update(&update)?;
+    if !update.value.is_null() {
+        group_custom_data::check_namespace_limit(
+            &group.account_pubkey,
+            &mls_group_id,
+            &update.namespace,
+            wn.clone(),
+        )
+        .await?;
+    }
+
+    let account_pubkey = group.account_pubkey;
+    let payload = serde_json::to_string(&update).context("Error serializing custom data")?;
+    send_mls_message(
+        group,
+        payload,
+        protocol::kind_for(PayloadType::GroupCustomData),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await?;
+
+    Ok(group_custom_data::for_group(
+        &account_pubkey,
+        &mls_group_id,
+        Some(&update.namespace),
+        wn.clone(),
+    )
+    .await
+    .context("Error fetching custom data")?
+    .pop())
+}
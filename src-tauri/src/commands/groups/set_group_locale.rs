@@ -0,0 +1,79 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, GroupSettingsUpdate};
+use crate::localization::Locale;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Sets the locale hint for a group. Only group admins can change it.
+///
+/// The locale is sent to the group as a settings update so every member's client formats the
+/// text it generates for the group, like system messages and exported timestamps, the same way.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `locale` - A supported locale tag (e.g. "es", "pt-BR"), or `None` to clear it
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Group)` - The updated group
+/// * `Err(WhitenoiseError)` - Error message if the update fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Group not found
+/// - The active account is not an admin of the group
+/// - The locale is not supported
+/// - Sending the settings update fails
+#[tauri::command]
+pub async fn set_group_locale(
+    group_id: GroupIdParam,
+    locale: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Group, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    if !group.admin_pubkeys.contains(&active_pubkey.to_hex()) {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only group admins can change the group locale".to_string(),
+        ));
+    }
+
+    if let Some(tag) = locale.as_deref() {
+        Locale::parse(tag).map_err(|e| WhitenoiseError::InvalidInput(e.to_string()))?;
+    }
+    let update = GroupSettingsUpdate {
+        // An empty tag clears the locale
+        locale: Some(locale.unwrap_or_default()),
+        ..Default::default()
+    };
+    let content = serde_json::to_string(&update)?;
+
+    // Our own copy is updated when the settings message is stored
+    send_mls_message(
+        group,
+        content,
+        protocol::kind_for(PayloadType::GroupSettings),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await?;
+
+    Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")
+}
@@ -0,0 +1,83 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, GroupSettingsUpdate};
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::relays;
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Replaces the relays a group's messages are sent to and fetched from. Only group admins can
+/// change them.
+///
+/// The new relays are sent to the group's current relays as a settings update, so every
+/// member's client stores them, connects to them and moves its group message subscription over.
+///
+/// The update is an MLS application message, not a commit changing the group context
+/// extensions: the MLS library doesn't support those commits yet. It's signed by the admin's
+/// leaf and members only apply it from admins, but it isn't part of the group state. The relays
+/// in the group data extension stay the ones the group was created with, so members who join
+/// later, or who miss the message, keep using those until an admin sends the relays again.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `relay_urls` - The group's new relays
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Group)` - The updated group
+/// * `Err(WhitenoiseError)` - Error message if the update fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Group not found
+/// - The active account is not an admin of the group
+/// - No relays are given, or a relay URL is invalid
+/// - Sending the settings update fails
+#[tauri::command]
+pub async fn set_group_relays(
+    group_id: GroupIdParam,
+    relay_urls: Vec<String>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Group, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    if !group.admin_pubkeys.contains(&active_pubkey.to_hex()) {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only group admins can change the group's relays".to_string(),
+        ));
+    }
+
+    let relay_urls = relays::normalize_group_relays(&relay_urls)?;
+    let update = GroupSettingsUpdate {
+        relays: Some(relay_urls),
+        ..Default::default()
+    };
+    let content = serde_json::to_string(&update)?;
+
+    // Our own copy is updated when the settings message is stored
+    send_mls_message(
+        group,
+        content,
+        protocol::kind_for(PayloadType::GroupSettings),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await?;
+
+    Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")
+}
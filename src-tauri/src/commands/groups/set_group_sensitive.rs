@@ -0,0 +1,75 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::{Group, GroupSettingsUpdate};
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Flags a group as sensitive (or clears the flag). Only group admins can change it.
+///
+/// The flag is sent to the group as a settings update so every member's client applies it.
+/// While any group is sensitive the app asks the OS to exclude its window from screenshots and
+/// screen recordings where supported (see the `capture_protection` capability), and frontends
+/// should blur previews of the group.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `sensitive` - Whether the group is sensitive
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Group)` - The updated group
+/// * `Err(WhitenoiseError)` - Error message if the update fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Group not found
+/// - The active account is not an admin of the group
+/// - Sending the settings update fails
+#[tauri::command]
+pub async fn set_group_sensitive(
+    group_id: GroupIdParam,
+    sensitive: bool,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Group, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    if !group.admin_pubkeys.contains(&active_pubkey.to_hex()) {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only group admins can change whether a group is sensitive".to_string(),
+        ));
+    }
+
+    let update = GroupSettingsUpdate {
+        sensitive: Some(sensitive),
+        ..Default::default()
+    };
+    let content = serde_json::to_string(&update)?;
+
+    // Our own copy is updated when the settings message is stored
+    send_mls_message(
+        group,
+        content,
+        protocol::kind_for(PayloadType::GroupSettings),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await?;
+
+    Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")
+}
@@ -0,0 +1,53 @@
+use super::create_group_task::send_task_action;
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_tasks::{self, GroupTask, TaskAction};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Marks a group task as done or not done
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `task_id` - ID of the task
+/// * `completed` - Whether the task is done
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(GroupTask)` - The task after the change
+/// * `Err(WhitenoiseError)` - Error message if the task doesn't exist, the active account isn't
+///   allowed to complete it, or sending fails
+#[tauri::command]
+pub async fn set_group_task_completed(
+    group_id: GroupIdParam,
+    task_id: String,
+    completed: bool,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<GroupTask, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let task = group_tasks::for_group(&group, wn.clone())
+        .await
+        .context("Error fetching tasks")?
+        .into_iter()
+        .find(|task| task.task_id == task_id)
+        .ok_or_else(|| WhitenoiseError::NotFound(format!("Task {} not found", task_id)))?;
+    let active_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    if !task.can_complete(&active_pubkey, &group.admin_pubkeys) {
+        return Err(WhitenoiseError::Unauthorized(
+            "Only the task's creator, its assignees and admins can complete it".to_string(),
+        ));
+    }
+    if task.completed == completed {
+        return Ok(task);
+    }
+
+    let action = TaskAction::SetCompleted { task_id, completed };
+    send_task_action(group, &action, wn, app_handle).await
+}
@@ -0,0 +1,75 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Snoozes notifications for a group for the given duration
+///
+/// Unlike muting, a snooze is temporary and is lifted early by the backend if the user
+/// is directly mentioned or replied to in the group.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `duration` - Snooze duration in seconds
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Timestamp)` - The time at which the snooze expires
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Duration is zero
+/// - Group not found in database
+/// - Database error occurs
+#[tauri::command]
+pub async fn snooze_group(
+    group_id: GroupIdParam,
+    duration: u64,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Timestamp, WhitenoiseError> {
+    if duration == 0 {
+        return Err(WhitenoiseError::InvalidInput(
+            "Snooze duration must be greater than zero".to_string(),
+        ));
+    }
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let until = Timestamp::now() + duration;
+    group
+        .set_snoozed_until(Some(until), wn.clone())
+        .await
+        .context("Error snoozing group")?;
+    Ok(until)
+}
+
+/// Lifts a snooze on a group before it expires
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Group not found in database
+/// - Database error occurs
+#[tauri::command]
+pub async fn unsnooze_group(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    group
+        .set_snoozed_until(None, wn.clone())
+        .await
+        .context("Error unsnoozing group")
+}
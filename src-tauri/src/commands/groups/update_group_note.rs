@@ -0,0 +1,88 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::group_notes::{self, GroupNote, GroupNoteError, NoteUpdate};
+use crate::groups::Group;
+use crate::params::GroupIdParam;
+use crate::protocol::{self, PayloadType};
+use crate::send_mls_message;
+use crate::whitenoise::Whitenoise;
+
+/// Creates or edits one of a group's shared notes
+///
+/// The edit is sent to the group with the version it was written on top of. If the note changed
+/// locally since that version, nothing is sent and the error says so, so the user can merge
+/// first. Edits that cross on the way are resolved last-writer-wins, and the losing text is kept
+/// as the note's `conflict`.
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `note_id` - ID of the note, chosen by the client for new notes
+/// * `content` - The full new text of the note
+/// * `based_on_version` - The version that was edited; 0 for a new note
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(GroupNote)` - The note after the edit
+/// * `Err(WhitenoiseError)` - Error message if the note is invalid, out of date, or sending fails
+#[tauri::command]
+pub async fn update_group_note(
+    group_id: GroupIdParam,
+    note_id: String,
+    content: String,
+    based_on_version: u64,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<GroupNote, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let update = NoteUpdate {
+        note_id: note_id.trim().to_string(),
+        content,
+        based_on_version,
+    };
+    group_notes::validate_update(&update)?;
+
+    let current_version = group_notes::find(
+        &group.account_pubkey,
+        &mls_group_id,
+        &update.note_id,
+        wn.clone(),
+    )
+    .await
+    .context("Error fetching note")?
+    .map_or(0, |note| note.version);
+    if current_version != based_on_version {
+        return Err(WhitenoiseError::InvalidInput(
+            GroupNoteError::Conflict {
+                note_id: update.note_id,
+                current_version,
+                based_on_version,
+            }
+            .to_string(),
+        ));
+    }
+
+    let account_pubkey = group.account_pubkey;
+    let payload = serde_json::to_string(&update).context("Error serializing note")?;
+    send_mls_message(
+        group,
+        payload,
+        protocol::kind_for(PayloadType::GroupNote),
+        None,
+        None,
+        None,
+        None,
+        None,
+        wn.clone(),
+        app_handle,
+    )
+    .await?;
+
+    group_notes::find(&account_pubkey, &mls_group_id, &update.note_id, wn.clone())
+        .await
+        .context("Error fetching note")?
+        .ok_or_else(|| WhitenoiseError::Internal("Note wasn't saved".to_string()))
+}
@@ -1,6 +1,9 @@
 use crate::accounts::Account;
+use crate::device_sync;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::groups::{Group, GroupType};
 use crate::invites::{Invite, InviteState};
+use crate::profiling::{self, OperationKind};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use tauri::Emitter;
@@ -14,7 +17,7 @@ use tauri::Emitter;
 ///
 /// # Returns
 /// * `Ok(())` if the invite was successfully accepted and the group was joined
-/// * `Err(String)` if there was an error accepting the invite or joining the group
+/// * `Err(WhitenoiseError)` if there was an error accepting the invite or joining the group
 ///
 /// # Events Emitted
 /// * `group_added` - Emitted with the newly joined group after successful join
@@ -24,22 +27,21 @@ pub async fn accept_invite(
     mut invite: Invite,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), WhitenoiseError> {
     tracing::debug!(target: "whitenoise::invites::accept_invite", "Accepting invite {:?}", invite.event.id.unwrap());
 
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let active_account = Account::get_active(wn.clone()).await?;
 
     // Scope the MutexGuard to drop it before the .await points
     let (mls_group, nostr_group_data) = {
         let nostr_mls = wn.nostr_mls.lock().await;
-        let joined_group_result = nostr_mls
-            .join_group_from_welcome(
-                hex::decode(&invite.event.content)
-                    .map_err(|e| format!("Error decoding welcome event: {}", e))?,
-            )
-            .map_err(|e| format!("Error joining group from welcome: {}", e))?;
+        let welcome_message = hex::decode(&invite.event.content).map_err(|e| {
+            WhitenoiseError::InvalidInput(format!("Error decoding welcome event: {}", e))
+        })?;
+        let joined_group_result = profiling::time("mls.join_group", OperationKind::Mls, || {
+            nostr_mls.join_group_from_welcome(welcome_message)
+        })
+        .map_err(|e| WhitenoiseError::Mls(format!("Error joining group from welcome: {}", e)))?;
 
         (
             joined_group_result.mls_group,
@@ -61,13 +63,13 @@ pub async fn accept_invite(
         &app_handle,
     )
     .await
-    .map_err(|e| format!("Failed to add group: {}", e))?;
+    .context("Failed to add group")?;
 
     // Update the subscription for MLS group messages to include the new group
     let group_ids = active_account
         .groups(wn.clone())
         .await
-        .map_err(|e| format!("Failed to get groups: {}", e))?
+        .context("Failed to get groups")?
         .into_iter()
         .map(|group| group.nostr_group_id)
         .collect::<Vec<_>>();
@@ -75,25 +77,23 @@ pub async fn accept_invite(
     wn.nostr
         .subscribe_mls_group_messages(group_ids.clone())
         .await
-        .map_err(|e| format!("Failed to update MLS group subscription: {}", e))?;
+        .context("Failed to update MLS group subscription")?;
 
     // Manually fetch for MLS messages for the new group
     wn.nostr
         .fetch_group_messages(Timestamp::zero(), group_ids.clone())
         .await
-        .map_err(|e| format!("Failed to fetch group messages: {}", e))?;
+        .context("Failed to fetch group messages")?;
+
+    app_handle.emit("group_added", group.clone())?;
 
-    app_handle
-        .emit("group_added", group.clone())
-        .map_err(|e| e.to_string())?;
+    device_sync::share_group(&group, wn.clone()).await;
 
     // Update the invite state to accepted
     invite.state = InviteState::Accepted;
-    invite.save(wn.clone()).await.map_err(|e| e.to_string())?;
+    invite.save(wn.clone()).await?;
 
-    app_handle
-        .emit("invite_accepted", invite)
-        .map_err(|e| e.to_string())?;
+    app_handle.emit("invite_accepted", invite)?;
 
     tracing::debug!(target: "whitenoise::invites::accept_invite", "Accepted invite - Added group: {:?}", group);
 
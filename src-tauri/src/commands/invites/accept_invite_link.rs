@@ -0,0 +1,27 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::invite_messages::{self, InviteMessage};
+use crate::whitenoise::Whitenoise;
+
+/// Looks up the invite behind an invite link and asks the inviter to let the active account join
+/// the group
+///
+/// The invite is checked like one from an invite message, see `parse_invite_message`, and the
+/// join request carries one of the account's key packages, published first if it has none.
+///
+/// # Arguments
+/// * `link` - The invite link
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(InviteMessage)` - The invite, once the join request is sent
+/// * `Err(WhitenoiseError)` - Error message if the link is malformed, its invite can't be found or
+///   isn't valid, or the request can't be sent
+#[tauri::command]
+pub async fn accept_invite_link(
+    link: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InviteMessage, WhitenoiseError> {
+    invite_messages::accept_link(&link, wn.clone())
+        .await
+        .context("Error accepting invite link")
+}
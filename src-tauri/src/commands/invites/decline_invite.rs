@@ -1,3 +1,4 @@
+use crate::error::WhitenoiseError;
 use crate::invites::{Invite, InviteState};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -12,7 +13,7 @@ use tauri::Emitter;
 ///
 /// # Returns
 /// * `Ok(())` if the invite was successfully declined
-/// * `Err(String)` if there was an error declining the invite
+/// * `Err(WhitenoiseError)` if there was an error declining the invite
 ///
 /// # Events Emitted
 /// * `invite_declined` - Emitted with the updated invite after it is declined
@@ -21,15 +22,13 @@ pub async fn decline_invite(
     mut invite: Invite,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), WhitenoiseError> {
     tracing::debug!(target: "whitenoise::invites::decline_invite", "Declining invite {:?}", invite.event.id.unwrap());
 
     invite.state = InviteState::Declined;
-    invite.save(wn.clone()).await.map_err(|e| e.to_string())?;
+    invite.save(wn.clone()).await?;
 
-    app_handle
-        .emit("invite_declined", invite)
-        .map_err(|e| e.to_string())?;
+    app_handle.emit("invite_declined", invite)?;
 
     Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::error::WhitenoiseError;
 use crate::invites::Invite;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -10,14 +11,14 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 /// * `Ok(Invite)` if the invite was found
-/// * `Err(String)` if there was an error retrieving the invite or it wasn't found
+/// * `Err(WhitenoiseError)` if there was an error retrieving the invite or it wasn't found
 #[tauri::command]
 pub async fn get_invite(
     active_account: String,
     invite_id: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<Invite, String> {
+) -> Result<Invite, WhitenoiseError> {
     Invite::find_by_id(&active_account, &invite_id, wn.clone())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(WhitenoiseError::from)
 }
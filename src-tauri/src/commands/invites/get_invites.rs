@@ -1,5 +1,8 @@
+use crate::error::WhitenoiseError;
 use crate::invites::{Invite, ProcessedInvite};
+use crate::relay_blacklist::RelayBlacklist;
 use crate::whitenoise::Whitenoise;
+use futures::future::join_all;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -10,15 +13,25 @@ pub struct InvitesWithFailures {
 }
 
 /// Fetches invites from the database for the active user
+///
+/// Each pending invite comes with the recent activity of its group, counted from the encrypted
+/// messages on the group's relays, to help decide whether to accept it. The groups are probed at
+/// the same time, so a slow relay holds the list up for at most a few seconds.
 #[tauri::command]
-pub async fn get_invites(wn: tauri::State<'_, Whitenoise>) -> Result<InvitesWithFailures, String> {
-    let pending_invites = Invite::pending(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+pub async fn get_invites(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InvitesWithFailures, WhitenoiseError> {
+    let mut pending_invites = Invite::pending(wn.clone()).await?;
+    let blacklist = RelayBlacklist::load(wn.clone()).await?;
+    join_all(
+        pending_invites
+            .iter_mut()
+            .map(|invite| invite.probe_activity(&blacklist)),
+    )
+    .await;
 
-    let failed_invites: Vec<(EventId, String)> = ProcessedInvite::failed_with_reason(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let failed_invites: Vec<(EventId, String)> =
+        ProcessedInvite::failed_with_reason(wn.clone()).await?;
 
     Ok(InvitesWithFailures {
         invites: pending_invites,
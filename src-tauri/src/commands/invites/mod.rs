@@ -1,9 +1,13 @@
 mod accept_invite;
+mod accept_invite_link;
 mod decline_invite;
 mod get_invite;
 mod get_invites;
+mod parse_invite_message;
 
 pub use accept_invite::accept_invite;
+pub use accept_invite_link::accept_invite_link;
 pub use decline_invite::decline_invite;
 pub use get_invite::get_invite;
 pub use get_invites::get_invites;
+pub use parse_invite_message::parse_invite_message;
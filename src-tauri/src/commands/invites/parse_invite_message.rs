@@ -0,0 +1,27 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::invite_messages::{self, InviteMessage};
+use crate::whitenoise::Whitenoise;
+
+/// Reads an invite message shared over another channel and asks the inviter to let the active
+/// account join the group
+///
+/// The invite has to be signed by the inviter, unexpired and for a group the account isn't in
+/// yet. The join request carries one of the account's key packages, published first if it has
+/// none.
+///
+/// # Arguments
+/// * `message` - The invite message, or any text containing it
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(InviteMessage)` - The invite, once the join request is sent
+/// * `Err(WhitenoiseError)` - Error message if the invite isn't valid or the request can't be sent
+#[tauri::command]
+pub async fn parse_invite_message(
+    message: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InviteMessage, WhitenoiseError> {
+    invite_messages::accept(&message, wn.clone())
+        .await
+        .context("Error accepting invite message")
+}
@@ -0,0 +1,23 @@
+use crate::error::WhitenoiseError;
+use crate::key_packages;
+use crate::Whitenoise;
+
+/// Deletes all of the active account's key packages with a NIP-09 deletion request
+///
+/// Covers the key packages recorded on the account as well as any other key package events of
+/// the account in the local cache, e.g. ones published by other clients.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state containing account and Nostr clients
+///
+/// # Returns
+/// * `Ok(usize)` - Number of key package events deletion was requested for
+/// * `Err(WhitenoiseError)` - Error message if the deletion couldn't be sent
+#[tauri::command]
+pub async fn delete_key_packages(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<usize, WhitenoiseError> {
+    key_packages::delete_key_packages(wn.clone())
+        .await
+        .map_err(WhitenoiseError::from)
+}
@@ -0,0 +1,25 @@
+use crate::error::WhitenoiseError;
+use crate::key_packages::{self, KeyPackageConsumption};
+use crate::Whitenoise;
+
+/// Returns the active account's key packages that were used up, for the account health view
+///
+/// A key package is consumed when a welcome for it arrives, or when another of the account's
+/// devices deletes it from the relays after using it. `key_package_consumed` is emitted for each
+/// one as it's found.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state containing the database
+///
+/// # Returns
+/// * `Ok(Vec<KeyPackageConsumption>)` - The consumed key packages, most recent first, with the
+///   group and inviter when known
+/// * `Err(WhitenoiseError)` - Error message if there's no active account or the query fails
+#[tauri::command]
+pub async fn get_key_package_consumptions(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<KeyPackageConsumption>, WhitenoiseError> {
+    key_packages::consumptions(wn.clone())
+        .await
+        .map_err(WhitenoiseError::from)
+}
@@ -0,0 +1,21 @@
+use crate::error::WhitenoiseError;
+use crate::key_packages::{self, KeyPackagePoolStatus};
+use crate::Whitenoise;
+
+/// Returns the active account's published key packages and how many are missing to reach the
+/// configured pool size
+///
+/// # Arguments
+/// * `wn` - Whitenoise state containing the database
+///
+/// # Returns
+/// * `Ok(KeyPackagePoolStatus)` - The published key package IDs, the minimum and the shortfall
+/// * `Err(WhitenoiseError)` - Error message if there's no active account
+#[tauri::command]
+pub async fn key_package_pool_status(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<KeyPackagePoolStatus, WhitenoiseError> {
+    key_packages::pool_status(wn.clone())
+        .await
+        .map_err(WhitenoiseError::from)
+}
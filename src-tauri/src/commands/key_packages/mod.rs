@@ -1,7 +1,17 @@
 mod delete_all_key_packages;
+mod delete_key_packages;
+mod get_key_package_consumptions;
+mod key_package_pool_status;
+mod publish_key_package;
 mod publish_new_key_package;
+mod rotate_key_package;
 mod valid_key_package_exists_for_user;
 
 pub use delete_all_key_packages::delete_all_key_packages;
+pub use delete_key_packages::delete_key_packages;
+pub use get_key_package_consumptions::get_key_package_consumptions;
+pub use key_package_pool_status::key_package_pool_status;
+pub use publish_key_package::publish_key_package;
 pub use publish_new_key_package::publish_new_key_package;
+pub use rotate_key_package::rotate_key_package;
 pub use valid_key_package_exists_for_user::valid_key_package_exists_for_user;
@@ -0,0 +1,21 @@
+use crate::error::WhitenoiseError;
+use crate::key_packages;
+use crate::Whitenoise;
+
+/// Publishes a fresh MLS key package for the active account and records it on the account
+///
+/// # Arguments
+/// * `wn` - Whitenoise state containing account and Nostr clients
+///
+/// # Returns
+/// * `Ok(String)` - Hex ID of the published key package event
+/// * `Err(WhitenoiseError)` - Error message if creating or publishing the key package fails
+#[tauri::command]
+pub async fn publish_key_package(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    key_packages::publish_key_package(wn.clone())
+        .await
+        .map(|event_id| event_id.to_hex())
+        .map_err(WhitenoiseError::from)
+}
@@ -0,0 +1,24 @@
+use crate::error::WhitenoiseError;
+use crate::key_packages;
+use crate::Whitenoise;
+
+/// Replaces the active account's published key packages with a fresh one
+///
+/// The new key package is published first; the previous ones are then deleted from relays with a
+/// NIP-09 deletion request.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state containing account and Nostr clients
+///
+/// # Returns
+/// * `Ok(String)` - Hex ID of the new key package event
+/// * `Err(WhitenoiseError)` - Error message if publishing or deleting fails
+#[tauri::command]
+pub async fn rotate_key_package(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    key_packages::rotate_key_package(wn.clone())
+        .await
+        .map(|event_id| event_id.to_hex())
+        .map_err(WhitenoiseError::from)
+}
@@ -0,0 +1,25 @@
+use crate::localization::{self, Locale};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize)]
+pub struct LocalizedStrings {
+    pub locale: Locale,
+    pub strings: HashMap<&'static str, &'static str>,
+}
+
+/// Returns the backend string table for a locale so frontends render the same text
+///
+/// # Arguments
+/// * `locale` - Optional locale tag (e.g. "es", "pt-BR"). Unknown or missing tags fall back to English.
+///
+/// # Returns
+/// * `LocalizedStrings` - The resolved locale and its string templates keyed by string key
+#[tauri::command]
+pub fn get_localized_strings(locale: Option<String>) -> LocalizedStrings {
+    let locale = Locale::from_hint(locale.as_deref());
+    LocalizedStrings {
+        locale,
+        strings: localization::strings(locale),
+    }
+}
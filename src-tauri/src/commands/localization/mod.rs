@@ -0,0 +1,3 @@
+mod get_localized_strings;
+
+pub use get_localized_strings::get_localized_strings;
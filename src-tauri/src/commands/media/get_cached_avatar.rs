@@ -0,0 +1,38 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::media::avatars;
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the local copy of a user's profile picture
+///
+/// Avatars that aren't cached yet are queued for the background fetcher; call again once the
+/// frontend needs it next, or fall back to the picture URL in the meantime.
+///
+/// # Arguments
+/// * `pubkey` - Hex or npub encoded pubkey of the user
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Some(String))` - Path of the cached picture
+/// * `Ok(None)` - The user has no picture, or it isn't cached yet
+/// * `Err(WhitenoiseError)` - Error message if the metadata can't be read
+#[tauri::command]
+pub async fn get_cached_avatar(
+    pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<String>, WhitenoiseError> {
+    let pubkey = pubkey.public_key();
+    let metadata = wn
+        .nostr
+        .query_user_metadata(pubkey)
+        .await
+        .context("Error fetching metadata")?;
+    let Some(picture) = metadata.and_then(|metadata| metadata.picture) else {
+        return Ok(None);
+    };
+
+    Ok(
+        avatars::cached_or_queue(&wn.data_dir, &pubkey.to_hex(), &picture)
+            .map(|path| path.to_string_lossy().to_string()),
+    )
+}
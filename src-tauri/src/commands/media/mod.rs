@@ -1,4 +1,8 @@
+mod get_cached_avatar;
 mod upload_file;
 mod upload_media;
+mod upload_profile_picture;
+pub use get_cached_avatar::get_cached_avatar;
 pub use upload_file::upload_file;
 pub use upload_media::upload_media;
+pub use upload_profile_picture::upload_profile_picture;
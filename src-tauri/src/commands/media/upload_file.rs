@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::WhitenoiseError;
 use crate::media::{add_media_file, FileUpload, UploadedMedia};
 use crate::secrets_store;
 use crate::whitenoise::Whitenoise;
@@ -29,7 +30,7 @@ const MAX_RETRIES: u32 = 3;
 /// # Returns
 ///
 /// * `Ok(UploadedMedia)` - The uploaded media details if successful
-/// * `Err(String)` - An error message if the upload fails after all retries
+/// * `Err(WhitenoiseError)` - An error message if the upload fails after all retries
 ///
 /// # Events
 ///
@@ -43,7 +44,7 @@ pub async fn upload_file(
     file: FileUpload,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<UploadedMedia, String> {
+) -> Result<UploadedMedia, WhitenoiseError> {
     let export_secret_hex;
     let epoch;
 
@@ -51,7 +52,7 @@ pub async fn upload_file(
         let nostr_mls = wn.nostr_mls.lock().await;
         (export_secret_hex, epoch) = nostr_mls
             .export_secret_as_hex_secret_key_and_epoch(group_id.clone())
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| WhitenoiseError::Mls(format!("Error exporting secret: {}", e)))?;
     }
 
     // Store the export secret key in the secrets store
@@ -60,15 +61,12 @@ pub async fn upload_file(
         epoch,
         export_secret_hex.clone(),
         wn.data_dir.as_path(),
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     let mut retries = 0;
     let mut last_error = None;
 
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let active_account = Account::get_active(wn.clone()).await?;
 
     while retries < MAX_RETRIES {
         match add_media_file(
@@ -93,7 +91,7 @@ pub async fn upload_file(
                 return Ok(media);
             }
             Err(e) => {
-                last_error = Some(e.to_string());
+                last_error = Some(WhitenoiseError::from(e));
                 retries += 1;
                 if retries < MAX_RETRIES {
                     // Emit retry event
@@ -109,11 +107,12 @@ pub async fn upload_file(
     }
 
     // If we get here, all retries failed
-    let error = last_error.unwrap_or_else(|| "Unknown error".to_string());
+    let error =
+        last_error.unwrap_or_else(|| WhitenoiseError::Internal("Unknown error".to_string()));
 
     // Emit error event
     app_handle
-        .emit("file_upload_error", (group_id.clone(), error.clone()))
+        .emit("file_upload_error", (group_id.clone(), error.to_string()))
         .expect("Couldn't emit event");
 
     Err(error)
@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::error::WhitenoiseError;
 use crate::media::FileUpload;
 use crate::Whitenoise;
 use tauri::State;
@@ -19,18 +20,19 @@ use tauri::State;
 ///
 /// Returns a `Result` containing:
 /// * `Ok(String)` - The URL of the uploaded media on success
-/// * `Err(String)` - An error message if:
+/// * `Err(WhitenoiseError)` - An error message if:
 ///   - No active account is found
 ///   - Account keys cannot be retrieved
 ///   - The upload to Blossom fails
 #[tauri::command]
-pub async fn upload_media(file: FileUpload, wn: State<'_, Whitenoise>) -> Result<String, String> {
+pub async fn upload_media(
+    file: FileUpload,
+    wn: State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
     // Get the active account
-    let account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let account = Account::get_active(wn.clone()).await?;
 
-    let keys = account.keys(wn.clone()).map_err(|e| e.to_string())?;
+    let keys = account.keys(wn.clone())?;
 
     // Upload the file to Blossom
     let blob_descriptor = wn
@@ -38,7 +40,9 @@ pub async fn upload_media(file: FileUpload, wn: State<'_, Whitenoise>) -> Result
         .blossom
         .upload_media(file.data, &file.mime_type, &keys)
         .await
-        .map_err(|e| format!("Failed to upload file to Blossom: {}", e))?;
+        .map_err(|e| {
+            WhitenoiseError::RelayUnreachable(format!("Failed to upload file to Blossom: {}", e))
+        })?;
 
     Ok(blob_descriptor.url)
 }
@@ -0,0 +1,98 @@
+use crate::accounts::Account;
+use crate::commands::accounts::{update_profile, ProfileUpdate};
+use crate::error::WhitenoiseError;
+use crate::media::attachments::{mime_type_for_path, upload_attachment};
+use crate::media::avatars::{self, MAX_AVATAR_BYTES};
+use crate::media::{sanitize_media, FileUpload};
+use crate::whitenoise::Whitenoise;
+use std::path::Path;
+
+/// Uploads a new profile picture and publishes it in the account's profile
+///
+/// The image is stripped of identifying metadata and uploaded, unencrypted since profiles are
+/// public, to the account's media server (see `set_media_server`). The profile is then
+/// republished with the new picture URL and the picture is put in the avatar cache.
+///
+/// # Arguments
+/// * `file_path` - Path of the image to use
+/// * `wn` - Whitenoise state
+/// * `app_handle` - Tauri app handle
+///
+/// # Returns
+/// * `Ok(Account)` - The account with its updated metadata
+/// * `Err(WhitenoiseError)` - Error message if the file isn't an image, is too large, or uploading
+///   or publishing fails
+#[tauri::command]
+pub async fn upload_profile_picture(
+    file_path: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Account, WhitenoiseError> {
+    let path = Path::new(&file_path);
+    let mime_type = mime_type_for_path(path);
+    if !mime_type.starts_with("image/") {
+        return Err(WhitenoiseError::InvalidInput(
+            "Profile pictures must be images".to_string(),
+        ));
+    }
+    let filename = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| WhitenoiseError::InvalidInput("Invalid file path".to_string()))?
+        .to_string();
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Error reading file: {}", e)))?;
+
+    let file = FileUpload {
+        filename,
+        mime_type: mime_type.to_string(),
+        data,
+    };
+    let sanitized = sanitize_media(&file)?;
+    if sanitized.data.len() > MAX_AVATAR_BYTES {
+        return Err(WhitenoiseError::InvalidInput(format!(
+            "Profile pictures can't be larger than {} MB",
+            MAX_AVATAR_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let active_account = Account::get_active(wn.clone()).await?;
+    let url = upload_attachment(
+        sanitized.data.clone(),
+        &active_account.settings.media_server,
+        &wn.nostr.blossom,
+    )
+    .await?;
+
+    tracing::debug!(
+        target: "whitenoise::commands::media::upload_profile_picture",
+        "Uploaded profile picture to {}",
+        url
+    );
+
+    if let Err(e) = avatars::store(
+        &wn.data_dir,
+        &active_account.pubkey.to_hex(),
+        &url,
+        &sanitized.data,
+    )
+    .await
+    {
+        tracing::warn!(
+            target: "whitenoise::commands::media::upload_profile_picture",
+            "Failed to cache profile picture: {}",
+            e
+        );
+    }
+
+    update_profile(
+        ProfileUpdate {
+            picture: Some(url),
+            ..Default::default()
+        },
+        wn,
+        app_handle,
+    )
+    .await
+}
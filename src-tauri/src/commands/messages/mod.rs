@@ -1,3 +1,5 @@
 mod query_message;
+mod search_messages;
 
 pub use query_message::query_message;
+pub use search_messages::search_messages;
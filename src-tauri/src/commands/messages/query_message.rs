@@ -1,3 +1,5 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::messages::Message;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,13 +8,14 @@ use nostr_sdk::prelude::*;
 pub async fn query_message(
     message_id: &str,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<UnsignedEvent, String> {
+) -> Result<UnsignedEvent, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
     let message = Message::find_by_event_id(
-        EventId::parse(message_id).map_err(|e| e.to_string())?,
+        EventId::parse(message_id).map_err(|e| WhitenoiseError::InvalidInput(e.to_string()))?,
         wn.clone(),
     )
     .await
-    .map_err(|e| format!("Error fetching message: {}", e))?;
+    .context("Error fetching message")?;
 
     Ok(message.event)
 }
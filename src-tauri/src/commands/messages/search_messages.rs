@@ -0,0 +1,37 @@
+use crate::app_lock;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::messages::{Message, MessageSearchResult};
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Maximum number of search results returned
+const SEARCH_RESULTS_LIMIT: usize = 100;
+
+/// Full-text search across the active account's group transcripts
+///
+/// # Arguments
+/// * `query` - The text to search for
+/// * `group_id` - Optional hex encoded MLS group ID to restrict the search to one group
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<MessageSearchResult>)` - Matches with group id, message id, snippet and timestamp
+/// * `Err(WhitenoiseError)` - Error message if the search fails
+///
+/// # Errors
+/// Returns error if:
+/// - Group ID is not valid hex
+/// - Database error occurs
+#[tauri::command]
+pub async fn search_messages(
+    query: String,
+    group_id: Option<GroupIdParam>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<MessageSearchResult>, WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let mls_group_id = group_id.map(GroupIdParam::into_bytes);
+
+    Message::search(&query, mls_group_id, SEARCH_RESULTS_LIMIT, wn.clone())
+        .await
+        .context("Error searching messages")
+}
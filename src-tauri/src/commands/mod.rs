@@ -1,20 +1,96 @@
+use crate::accounts::Account;
+use crate::capabilities::{self, Capabilities};
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::integrity::{self, IntegrityReport};
+use crate::profiling::{self, PerformanceReport};
+use crate::runtime_state::{self, RuntimeState};
+use crate::sensitive_actions::{self, SensitiveAction};
 use crate::whitenoise::Whitenoise;
 
 pub mod accounts;
+pub mod app_lock;
+pub mod contacts;
 pub mod groups;
 pub mod invites;
 pub mod key_packages;
+pub mod localization;
 pub mod media;
 pub mod messages;
 pub mod nostr;
 pub mod payments;
+pub mod quick_switcher;
+pub mod relays;
+pub mod secrets;
 
+/// Deletes all accounts, groups and messages from the device.
+///
+/// Requires a token from `request_sensitive_action` for `wipe_all_data`.
 #[tauri::command]
-pub async fn delete_all_data(wn: tauri::State<'_, Whitenoise>) -> Result<(), String> {
-    wn.delete_all_data().await.map_err(|e| e.to_string())?;
+pub async fn delete_all_data(
+    confirmation_token: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    sensitive_actions::authorize(SensitiveAction::WipeAllData, &confirmation_token, &wn).await?;
+    wn.delete_all_data()
+        .await
+        .map_err(|e| WhitenoiseError::Storage(format!("Error deleting data: {}", e)))?;
     Ok(())
 }
 
+/// Scans the stored accounts, groups and secrets for corruption.
+///
+/// Records that don't deserialize or no longer match their checksum are quarantined, so they're
+/// skipped when loading instead of breaking it.
+///
+/// # Returns
+///
+/// * `Ok(IntegrityReport)` - How many records were checked and which ones were quarantined
+/// * `Err(WhitenoiseError)` - An error message if the stores couldn't be scanned
+#[tauri::command]
+pub async fn verify_data_integrity(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<IntegrityReport, WhitenoiseError> {
+    integrity::verify(wn)
+        .await
+        .context("Error verifying data integrity")
+}
+
+/// Summarizes how long MLS operations, NIP-44 encryption, database writes and relay round trips
+/// have taken since launch.
+///
+/// # Returns
+///
+/// * `PerformanceReport` - Count, approximate p50/p95 and maximum duration per operation
+#[tauri::command]
+pub fn get_performance_report() -> PerformanceReport {
+    profiling::report()
+}
+
+/// Reports what the app is doing right now to diagnose hangs: who holds the `nostr_mls` and event
+/// processor locks and for how long, how many tasks wait for them, the event queue, the sync
+/// tasks, the async runtime and the relay subscriptions. Nothing here waits on those locks.
+///
+/// Only available in development builds or with the active account's dev mode on.
+///
+/// # Returns
+///
+/// * `Ok(RuntimeState)` - The state at the time of the call
+/// * `Err(WhitenoiseError)` - An error message if dev mode is off
+#[tauri::command]
+pub async fn dump_runtime_state(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<RuntimeState, WhitenoiseError> {
+    let dev_mode = Account::get_active(wn.clone())
+        .await
+        .is_ok_and(|account| account.settings.dev_mode);
+    if !cfg!(dev) && !dev_mode {
+        return Err(WhitenoiseError::Unauthorized(
+            "Runtime state is only available in dev mode".to_string(),
+        ));
+    }
+    Ok(runtime_state::capture(wn).await)
+}
+
 /// Determines if the current platform is a mobile device.
 ///
 /// This function checks if the application is running on either Android or iOS.
@@ -56,3 +132,17 @@ pub fn is_platform() -> String {
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     return "desktop".to_string();
 }
+
+/// Returns the security capabilities of the current platform.
+///
+/// Frontends should use this to decide which key storage options to offer. Hardware backed
+/// keys are reported together with the reason they're unavailable when they can't be used.
+///
+/// # Returns
+///
+/// A `Capabilities` struct describing the platform, the secure hardware present and the
+/// key storage backends that can be used.
+#[tauri::command]
+pub fn get_capabilities() -> Capabilities {
+    capabilities::detect()
+}
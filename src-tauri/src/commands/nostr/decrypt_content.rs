@@ -1,3 +1,5 @@
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
 use crate::types::NostrEncryptionMethod;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -5,12 +7,12 @@ use nostr_sdk::prelude::*;
 #[tauri::command]
 pub async fn decrypt_content(
     content: String,
-    pubkey: String,
+    pubkey: PubkeyParam,
     method: NostrEncryptionMethod,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<String, String> {
+) -> Result<String, WhitenoiseError> {
     wn.nostr
-        .decrypt_content(content, pubkey, method)
+        .decrypt_content(content, &pubkey.public_key(), method)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(WhitenoiseError::from)
 }
@@ -0,0 +1,23 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::key_migrations::KeyMigration;
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Dismisses the re-invite prompt for a contact's key migration
+///
+/// # Arguments
+/// * `old_pubkey` - Hex or npub encoded old key of the contact
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(())` - If the prompt was dismissed
+/// * `Err(WhitenoiseError)` - Error message if the update fails
+#[tauri::command]
+pub async fn dismiss_contact_key_migration(
+    old_pubkey: PubkeyParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    KeyMigration::dismiss(&old_pubkey.public_key(), wn.clone())
+        .await
+        .context("Error dismissing key migration")
+}
@@ -1,14 +1,23 @@
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
 use crate::secrets_store;
+use crate::sensitive_actions::{self, SensitiveAction};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
+/// Returns an account's private key as an nsec.
+///
+/// Requires a token from `request_sensitive_action` for `export_nsec`.
 #[tauri::command]
 pub async fn export_nsec(
-    pubkey: String,
+    pubkey: PubkeyParam,
+    confirmation_token: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<String, String> {
-    let keys = secrets_store::get_nostr_keys_for_pubkey(&pubkey, &wn.data_dir)
-        .map_err(|e| e.to_string())?;
+) -> Result<String, WhitenoiseError> {
+    sensitive_actions::authorize(SensitiveAction::ExportNsec, &confirmation_token, &wn).await?;
+    let keys = secrets_store::get_nostr_keys_for_pubkey(&pubkey.to_hex(), &wn.data_dir)?;
 
-    keys.secret_key().to_bech32().map_err(|e| e.to_string())
+    keys.secret_key()
+        .to_bech32()
+        .map_err(|e| WhitenoiseError::InvalidKey(e.to_string()))
 }
@@ -1,5 +1,9 @@
 use crate::accounts::Account;
-use crate::relays::RelayType;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::nip05;
+use crate::nostr_manager::NostrManager;
+use crate::params::PubkeyParam;
+use crate::relays::{self, RelayType};
 use crate::types::EnrichedContact;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -7,62 +11,66 @@ use tauri::Emitter;
 
 #[tauri::command]
 pub async fn fetch_enriched_contact(
-    pubkey: String,
+    pubkey: PubkeyParam,
     update_account: bool,
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<EnrichedContact, String> {
-    let pubkey = PublicKey::from_hex(&pubkey).map_err(|_| "Invalid pubkey".to_string())?;
+) -> Result<EnrichedContact, WhitenoiseError> {
+    let pubkey = pubkey.public_key();
 
     let metadata = wn
         .nostr
         .fetch_user_metadata(pubkey)
         .await
-        .map_err(|_| "Failed to get metadata".to_string())?;
-    let nostr_relays = wn
+        .context("Failed to get metadata")?;
+    let relay_list = wn
         .nostr
-        .fetch_user_relays(pubkey)
+        .fetch_user_relay_list(pubkey)
         .await
-        .map_err(|_| "Failed to get user relays".to_string())?;
+        .context("Failed to get user relays")?;
     let inbox_relays = wn
         .nostr
         .fetch_user_inbox_relays(pubkey)
         .await
-        .map_err(|_| "Failed to get inbox relays".to_string())?;
+        .context("Failed to get inbox relays")?;
     let key_package_relays = wn
         .nostr
         .fetch_user_key_package_relays(pubkey)
         .await
-        .map_err(|_| "Failed to get key package relays".to_string())?;
+        .context("Failed to get key package relays")?;
     let key_packages = wn
         .nostr
         .fetch_user_key_packages(pubkey)
         .await
-        .map_err(|_| "Failed to get key packages".to_string())?;
+        .context("Failed to get key packages")?;
 
-    let enriched_contact = EnrichedContact {
+    let mut enriched_contact = EnrichedContact {
         metadata: metadata.unwrap_or_default(),
         nip17: !inbox_relays.is_empty(),
         nip104: !key_packages.is_empty(),
-        nostr_relays,
+        nostr_relays: relay_list.iter().map(|(url, _)| url.clone()).collect(),
+        nostr_read_relays: NostrManager::read_relays(&relay_list),
         inbox_relays,
         key_package_relays,
+        nip05_verified: false,
     };
+    nip05::annotate([(&pubkey.to_hex(), &mut enriched_contact)], wn.clone())
+        .await
+        .context("Failed to check NIP-05 verification")?;
 
     if update_account {
         let mut account = Account::find_by_pubkey(&pubkey, wn.clone())
             .await
-            .map_err(|e| format!("Failed to find account: {}", e))?;
+            .context("Failed to find account")?;
 
         account.metadata = enriched_contact.metadata.clone();
-        account
-            .update_relays(RelayType::Nostr, &enriched_contact.nostr_relays, wn.clone())
+        relays::save_relay_list(&account, &relay_list, wn.clone())
             .await
-            .map_err(|e| format!("Failed to update relays: {}", e))?;
+            .context("Failed to update relays")?;
         account
             .update_relays(RelayType::Inbox, &enriched_contact.inbox_relays, wn.clone())
             .await
-            .map_err(|e| format!("Failed to update relays: {}", e))?;
+            .context("Failed to update relays")?;
         account
             .update_relays(
                 RelayType::KeyPackage,
@@ -70,15 +78,13 @@ pub async fn fetch_enriched_contact(
                 wn.clone(),
             )
             .await
-            .map_err(|e| format!("Failed to update relays: {}", e))?;
+            .context("Failed to update relays")?;
         account
             .save(wn.clone())
             .await
-            .map_err(|e| format!("Failed to save account: {}", e))?;
+            .context("Failed to save account")?;
 
-        app_handle
-            .emit("account_changed", ())
-            .map_err(|e| e.to_string())?;
+        app_handle.emit("account_changed", ())?;
     }
 
     Ok(enriched_contact)
@@ -1,3 +1,4 @@
+use crate::error::WhitenoiseError;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use std::collections::HashMap;
@@ -5,7 +6,7 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn fetch_relays(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, String>, String> {
+) -> Result<HashMap<String, String>, WhitenoiseError> {
     Ok(wn
         .nostr
         .client
@@ -0,0 +1,35 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::key_migrations::{KeyMigration, KeyMigrationPrompt};
+use crate::whitenoise::Whitenoise;
+
+/// Gets the key migrations announced by our contacts, newest first
+///
+/// Each entry links a contact's old key to their new one and lists the groups the new key
+/// should be re-invited to. Dismissed migrations are included with `dismissed` set so that
+/// frontends can still show the link between the two identities.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<KeyMigrationPrompt>)` - The known migrations
+/// * `Err(WhitenoiseError)` - Error message if the migrations or group memberships can't be loaded
+#[tauri::command]
+pub async fn get_contact_key_migrations(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<KeyMigrationPrompt>, WhitenoiseError> {
+    let migrations = KeyMigration::all(wn.clone())
+        .await
+        .context("Error fetching key migrations")?;
+
+    let mut prompts = Vec::with_capacity(migrations.len());
+    for migration in migrations {
+        prompts.push(
+            migration
+                .prompt(wn.clone())
+                .await
+                .context("Error fetching groups to re-invite")?,
+        );
+    }
+    Ok(prompts)
+}
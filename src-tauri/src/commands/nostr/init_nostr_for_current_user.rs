@@ -1,4 +1,7 @@
 use crate::accounts::Account;
+use crate::app_lock;
+use crate::capture_protection;
+use crate::error::{ErrorContext, WhitenoiseError};
 use crate::whitenoise::Whitenoise;
 use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
@@ -7,16 +10,15 @@ use nostr_sdk::prelude::*;
 pub async fn init_nostr_for_current_user(
     wn: tauri::State<'_, Whitenoise>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let current_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<(), WhitenoiseError> {
+    app_lock::ensure_unlocked(&wn).await?;
+
+    let current_account = Account::get_active(wn.clone()).await?;
 
     // Update Nostr identity and connect relays
     wn.nostr
         .set_nostr_identity(&current_account, wn.clone(), &app_handle)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     // Then update Nostr MLS instance
     {
@@ -24,9 +26,37 @@ pub async fn init_nostr_for_current_user(
         *nostr_mls = NostrMls::new(wn.data_dir.clone(), Some(current_account.pubkey.to_hex()));
     }
 
+    capture_protection::refresh(&current_account.pubkey, wn.clone(), &app_handle).await;
+
     tracing::debug!(
         target: "whitenoise::commands::nostr::init_nostr_for_current_user",
         "Nostr initialized for current user"
     );
     Ok(())
 }
+
+/// Initializes Nostr and MLS for the active account unless the frontend already did
+///
+/// Entry points the OS can call without the frontend loading (notification actions, background
+/// tasks) use this before touching the signer or MLS state.
+pub(crate) async fn ensure_nostr_initialized(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+
+    let ready = match wn.nostr.client.signer().await {
+        Ok(signer) => signer.get_public_key().await.ok() == Some(account.pubkey),
+        Err(_) => false,
+    };
+    if !ready {
+        tracing::debug!(
+            target: "whitenoise::commands::nostr::ensure_nostr_initialized",
+            "Backend woken without the frontend, initializing Nostr for the active account"
+        );
+        init_nostr_for_current_user(wn, app_handle).await?;
+    }
+    Ok(())
+}
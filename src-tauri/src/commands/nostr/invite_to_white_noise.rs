@@ -1,33 +1,37 @@
+use crate::error::WhitenoiseError;
+use crate::params::PubkeyParam;
+use crate::relay_blacklist;
 use crate::types::NostrEncryptionMethod;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 
 #[tauri::command]
 pub async fn invite_to_white_noise(
-    pubkey: String,
+    pubkey: PubkeyParam,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
-    let public_key = PublicKey::from_hex(&pubkey).map_err(|e| e.to_string())?;
+) -> Result<(), WhitenoiseError> {
+    let public_key = pubkey.public_key();
     let content = "Hi, I'm using White Noise to chat securely on Nostr. Join me! https://github.com/erskingardner/whitenoise/releases".to_string();
     let encrypted_content = wn
         .nostr
-        .encrypt_content(content, pubkey, NostrEncryptionMethod::Nip04)
-        .await
-        .map_err(|e| e.to_string())?;
+        .encrypt_content(content, &public_key, NostrEncryptionMethod::Nip04)
+        .await?;
 
-    let event = EventBuilder::new(Kind::EncryptedDirectMessage, encrypted_content)
-        .tag(Tag::public_key(public_key));
+    let event = wn
+        .nostr
+        .client
+        .sign_event_builder(
+            EventBuilder::new(Kind::EncryptedDirectMessage, encrypted_content)
+                .tag(Tag::public_key(public_key)),
+        )
+        .await?;
 
     tracing::debug!(
         target: "whitenoise::commands::nostr::invite_to_white_noise",
         "Sending event: {:?}",
         event
     );
-    wn.nostr
-        .client
-        .send_event_builder(event)
-        .await
-        .map_err(|e| e.to_string())?;
+    relay_blacklist::send_event(&event, vec![], wn.clone()).await?;
 
     Ok(())
 }
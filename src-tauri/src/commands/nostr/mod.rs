@@ -1,29 +1,48 @@
 mod decrypt_content;
+mod dismiss_contact_key_migration;
 mod encrypt_content;
 mod export_nsec;
 mod fetch_contacts_with_metadata;
 mod fetch_enriched_contact;
 mod fetch_enriched_contacts;
 mod fetch_relays;
+mod get_contact_key_migrations;
 mod init_nostr_for_current_user;
 mod invite_to_white_noise;
+mod pause_sync;
 mod publish_relay_list;
 mod query_contacts_with_metadata;
 mod query_enriched_contact;
 mod query_enriched_contacts;
+mod resolve_nip05;
+mod resume_sync;
+mod run_background_refresh;
 mod search_for_enriched_contacts;
+mod set_power_state;
+mod sync_now;
+mod verify_nip05;
 
 pub use decrypt_content::decrypt_content;
+pub use dismiss_contact_key_migration::dismiss_contact_key_migration;
 pub use encrypt_content::encrypt_content;
 pub use export_nsec::export_nsec;
 pub use fetch_contacts_with_metadata::fetch_contacts_with_metadata;
 pub use fetch_enriched_contact::fetch_enriched_contact;
 pub use fetch_enriched_contacts::fetch_enriched_contacts;
 pub use fetch_relays::fetch_relays;
+pub use get_contact_key_migrations::get_contact_key_migrations;
+pub(crate) use init_nostr_for_current_user::ensure_nostr_initialized;
 pub use init_nostr_for_current_user::init_nostr_for_current_user;
 pub use invite_to_white_noise::invite_to_white_noise;
+pub use pause_sync::pause_sync;
 pub use publish_relay_list::publish_relay_list;
 pub use query_contacts_with_metadata::query_contacts_with_metadata;
 pub use query_enriched_contact::query_enriched_contact;
 pub use query_enriched_contacts::query_enriched_contacts;
+pub use resolve_nip05::resolve_nip05;
+pub use resume_sync::resume_sync;
+pub use run_background_refresh::run_background_refresh;
 pub use search_for_enriched_contacts::search_for_enriched_contacts;
+pub use set_power_state::set_power_state;
+pub use sync_now::sync_now;
+pub use verify_nip05::verify_nip05;
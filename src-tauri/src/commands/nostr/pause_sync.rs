@@ -0,0 +1,17 @@
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Pauses scheduled background syncing until `resume_sync`. Subscriptions keep delivering new
+/// events and `sync_now` still runs.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+#[tauri::command]
+pub async fn pause_sync(wn: tauri::State<'_, Whitenoise>) -> Result<(), WhitenoiseError> {
+    wn.sync_scheduler.pause();
+    tracing::debug!(
+        target: "whitenoise::commands::nostr::pause_sync",
+        "Scheduled syncing paused"
+    );
+    Ok(())
+}
@@ -1,4 +1,6 @@
 use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relay_blacklist;
 use crate::relays::RelayType;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -8,9 +10,7 @@ pub async fn publish_relay_list(
     relays: Vec<String>,
     kind: u64,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
-    let signer = wn.nostr.client.signer().await.map_err(|e| e.to_string())?;
-
+) -> Result<(), WhitenoiseError> {
     let mut tags: Vec<Tag> = Vec::new();
     for relay in relays.clone() {
         tags.push(Tag::custom(TagKind::Relay, [relay]));
@@ -19,39 +19,41 @@ pub async fn publish_relay_list(
     let event_kind = match kind {
         10050 => Kind::InboxRelays,
         10051 => Kind::MlsKeyPackageRelays,
-        _ => return Err("Invalid relay list kind".to_string()),
+        _ => {
+            return Err(WhitenoiseError::InvalidInput(
+                "Invalid relay list kind".to_string(),
+            ))
+        }
     };
 
-    let event = EventBuilder::new(event_kind, "")
-        .tags(tags)
-        .sign(&signer)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    wn.nostr
+    let event = wn
+        .nostr
         .client
-        .send_event(&event)
-        .await
-        .map_err(|e| e.to_string())?;
+        .sign_event_builder(EventBuilder::new(event_kind, "").tags(tags))
+        .await?;
 
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    relay_blacklist::send_event(&event, vec![], wn.clone()).await?;
+
+    let active_account = Account::get_active(wn.clone()).await?;
 
     match kind {
         10050 => {
             active_account
                 .update_relays(RelayType::Inbox, &relays, wn.clone())
                 .await
-                .map_err(|e| format!("Failed to update relays: {}", e))?;
+                .context("Failed to update relays")?;
         }
         10051 => {
             active_account
                 .update_relays(RelayType::KeyPackage, &relays, wn.clone())
                 .await
-                .map_err(|e| format!("Failed to update relays: {}", e))?;
+                .context("Failed to update relays")?;
+        }
+        _ => {
+            return Err(WhitenoiseError::InvalidInput(
+                "Invalid relay list kind".to_string(),
+            ))
         }
-        _ => return Err("Invalid relay list kind".to_string()),
     }
     Ok(())
 }
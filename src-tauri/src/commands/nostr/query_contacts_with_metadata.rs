@@ -1,3 +1,4 @@
+use crate::error::WhitenoiseError;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use std::collections::HashMap;
@@ -5,8 +6,8 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn query_contacts_with_metadata(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, Metadata>, String> {
-    let events = wn.nostr.query_contacts().await.map_err(|e| e.to_string())?;
+) -> Result<HashMap<String, Metadata>, WhitenoiseError> {
+    let events = wn.nostr.query_contacts().await?;
 
     let mut metadata_map = HashMap::new();
 
@@ -1,3 +1,5 @@
+use crate::error::WhitenoiseError;
+use crate::nip05;
 use crate::types::EnrichedContact;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,13 +8,9 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn query_enriched_contacts(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, EnrichedContact>, String> {
+) -> Result<HashMap<String, EnrichedContact>, WhitenoiseError> {
     // Query contact list public keys from local database
-    let contact_list_pubkeys = wn
-        .nostr
-        .query_contact_list_pubkeys()
-        .await
-        .map_err(|e| e.to_string())?;
+    let contact_list_pubkeys = wn.nostr.query_contact_list_pubkeys().await?;
 
     tracing::debug!(
         "query_enriched_contacts contact_list_pubkeys length: {:?}",
@@ -35,8 +33,10 @@ pub async fn query_enriched_contacts(
                 nip17: false,
                 nip104: false,
                 nostr_relays: Vec::new(),
+                nostr_read_relays: Vec::new(),
                 inbox_relays: Vec::new(),
                 key_package_relays: Vec::new(),
+                nip05_verified: false,
             },
         );
     }
@@ -51,13 +51,7 @@ pub async fn query_enriched_contacts(
         ])
         .authors(contact_list_pubkeys.clone());
 
-    let stored_events = wn
-        .nostr
-        .client
-        .database()
-        .query(filter.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let stored_events = wn.nostr.client.database().query(filter.clone()).await?;
 
     // Process all events
     for event in stored_events {
@@ -70,14 +64,12 @@ pub async fn query_enriched_contacts(
                     }
                 }
                 Kind::RelayList => {
-                    contact.nostr_relays.extend(
-                        event
-                            .tags
-                            .iter()
-                            .filter(|tag| tag.kind() == TagKind::Relay)
-                            .filter_map(|tag| tag.content())
-                            .map(|s| s.to_string()),
-                    );
+                    for (url, metadata) in nip65::extract_relay_list(&event) {
+                        if *metadata != Some(RelayMetadata::Write) {
+                            contact.nostr_read_relays.push(url.to_string());
+                        }
+                        contact.nostr_relays.push(url.to_string());
+                    }
                 }
                 Kind::InboxRelays => {
                     contact.nip17 = true;
@@ -110,5 +102,7 @@ pub async fn query_enriched_contacts(
         }
     }
 
+    nip05::annotate(contacts_map.iter_mut(), wn).await?;
+
     Ok(contacts_map)
 }
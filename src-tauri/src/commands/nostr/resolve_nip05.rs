@@ -0,0 +1,18 @@
+use crate::error::WhitenoiseError;
+use crate::nip05::{self, Nip05Profile};
+
+/// Resolves a NIP-05 identifier to the pubkey and relays its domain publishes
+///
+/// # Arguments
+/// * `identifier` - A `name@domain` identifier, or a bare domain for `_@domain`
+///
+/// # Returns
+/// * `Ok(Nip05Profile)` - The hex encoded pubkey and the relays listed for it
+/// * `Err(WhitenoiseError)` - Error message if the identifier is invalid, unknown or can't be
+///   fetched
+#[tauri::command]
+pub async fn resolve_nip05(identifier: String) -> Result<Nip05Profile, WhitenoiseError> {
+    nip05::resolve(&identifier).await.map_err(|e| {
+        WhitenoiseError::from(e).with_context(&format!("Error resolving {}", identifier))
+    })
+}
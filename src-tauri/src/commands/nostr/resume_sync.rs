@@ -0,0 +1,17 @@
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Resumes scheduled background syncing after `pause_sync`. Tasks that became due in the
+/// meantime run on the scheduler's next check.
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+#[tauri::command]
+pub async fn resume_sync(wn: tauri::State<'_, Whitenoise>) -> Result<(), WhitenoiseError> {
+    wn.sync_scheduler.resume();
+    tracing::debug!(
+        target: "whitenoise::commands::nostr::resume_sync",
+        "Scheduled syncing resumed"
+    );
+    Ok(())
+}
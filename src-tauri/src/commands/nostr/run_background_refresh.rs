@@ -0,0 +1,28 @@
+use crate::background_refresh::{self, BackgroundRefreshReport};
+use crate::commands::nostr::ensure_nostr_initialized;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+use std::time::Duration;
+
+/// Entry point for iOS/Android background tasks: fetches new messages for the highest priority
+/// groups within the time budget the OS granted, posting notifications and updating the badge.
+///
+/// # Arguments
+/// * `budget_seconds` - How long the OS lets the task run
+/// * `wn` - Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+/// * `Ok(BackgroundRefreshReport)` - What got synced and the unread total for the badge
+/// * `Err(WhitenoiseError)` - Error message if the budget is too short or the refresh failed
+#[tauri::command]
+pub async fn run_background_refresh(
+    budget_seconds: u64,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<BackgroundRefreshReport, WhitenoiseError> {
+    ensure_nostr_initialized(wn.clone(), app_handle.clone()).await?;
+    background_refresh::run(Duration::from_secs(budget_seconds), wn, &app_handle)
+        .await
+        .context("Error running background refresh")
+}
@@ -1,3 +1,5 @@
+use crate::error::WhitenoiseError;
+use crate::nip05;
 use crate::types::EnrichedContact;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -7,12 +9,9 @@ use std::collections::HashMap;
 pub async fn search_for_enriched_contacts(
     query: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, EnrichedContact>, String> {
-    let enriched_users = wn
-        .nostr
-        .search_users(query, wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<HashMap<String, EnrichedContact>, WhitenoiseError> {
+    let mut enriched_users = wn.nostr.search_users(query, wn.clone()).await?;
+    nip05::annotate(enriched_users.iter_mut(), wn).await?;
 
     Ok(enriched_users)
 }
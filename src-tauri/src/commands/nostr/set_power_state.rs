@@ -0,0 +1,26 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::sync_throttle::{self, PowerState, SyncMode};
+use crate::whitenoise::Whitenoise;
+
+/// Reports the device's power and network conditions so syncing can be throttled accordingly.
+///
+/// Platforms call this whenever the battery or connection state changes.
+///
+/// # Arguments
+/// * `state` - Whether the device is on battery, low on battery and on a metered connection
+/// * `wn` - Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+/// * `Ok(SyncMode)` - The sync mode now in effect
+/// * `Err(WhitenoiseError)` - Error message if the new mode couldn't be applied
+#[tauri::command]
+pub async fn set_power_state(
+    state: PowerState,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<SyncMode, WhitenoiseError> {
+    sync_throttle::set_power_state(state, wn, &app_handle)
+        .await
+        .context("Error applying power state")
+}
@@ -0,0 +1,33 @@
+use crate::commands::nostr::ensure_nostr_initialized;
+use crate::error::WhitenoiseError;
+use crate::sync_scheduler::{self, SyncReport, SyncTask};
+use crate::whitenoise::Whitenoise;
+
+/// Runs background sync tasks right away, even while syncing is paused. Each run emits
+/// `sync_started` and `sync_finished`.
+///
+/// # Arguments
+/// * `tasks` - The tasks to run, or all of them if omitted
+/// * `wn` - Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+/// * `Ok(Vec<SyncReport>)` - How each run went, including the ones that failed
+/// * `Err(WhitenoiseError)` - Error message if the tasks couldn't be run
+#[tauri::command]
+pub async fn sync_now(
+    tasks: Option<Vec<SyncTask>>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<SyncReport>, WhitenoiseError> {
+    ensure_nostr_initialized(wn.clone(), app_handle.clone()).await?;
+
+    let mut reports = Vec::new();
+    for task in tasks.unwrap_or_else(|| SyncTask::ALL.to_vec()) {
+        let report = sync_scheduler::run(task, &app_handle).await.map_err(|e| {
+            WhitenoiseError::from(e).with_context(&format!("Error syncing {:?}", task))
+        })?;
+        reports.push(report);
+    }
+    Ok(reports)
+}
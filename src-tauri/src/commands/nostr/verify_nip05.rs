@@ -0,0 +1,30 @@
+use crate::error::WhitenoiseError;
+use crate::nip05::{self, Nip05Verification};
+use crate::params::PubkeyParam;
+use crate::whitenoise::Whitenoise;
+
+/// Checks whether a user's NIP-05 identifier points to their pubkey
+///
+/// The result is cached, so enriched contacts report the name as verified from then on. Call it
+/// when the user looks at a contact, not for every contact on a list: each check makes a request
+/// to the identifier's domain.
+///
+/// # Arguments
+/// * `pubkey` - The user's public key, hex or npub
+/// * `nip05` - The `nip05` field of the user's metadata
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Nip05Verification)` - Whether the identifier points to the pubkey
+/// * `Err(WhitenoiseError)` - Error message if the identifier is invalid or its domain can't be
+///   reached
+#[tauri::command]
+pub async fn verify_nip05(
+    pubkey: PubkeyParam,
+    nip05: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Nip05Verification, WhitenoiseError> {
+    nip05::verify(&pubkey.to_hex(), &nip05, wn.clone())
+        .await
+        .map_err(|e| WhitenoiseError::from(e).with_context(&format!("Error verifying {}", nip05)))
+}
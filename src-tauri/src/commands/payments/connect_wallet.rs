@@ -0,0 +1,36 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::payments;
+use crate::whitenoise::Whitenoise;
+
+/// Connects a Nostr Wallet Connect (NIP-47) wallet to the active account.
+///
+/// The wallet must answer an info request before the connection is kept. The URI holds the
+/// wallet's secret, so it's stored encrypted in the secrets store, never in the database.
+///
+/// # Arguments
+///
+/// * `nwc_uri` - The `nostr+walletconnect://` URI given by the wallet
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the wallet answered and the connection was stored
+/// * `Err(WhitenoiseError)` - An error message if the URI is invalid or the wallet can't be reached
+#[tauri::command]
+pub async fn connect_wallet(
+    nwc_uri: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error getting active account")?;
+
+    payments::check_wallet_connection(&nwc_uri)
+        .await
+        .context("Error connecting wallet")?;
+
+    active_account
+        .store_nostr_wallet_connect_uri(&nwc_uri, wn.clone())
+        .context("Error storing NWC URI")
+}
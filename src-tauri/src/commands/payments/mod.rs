@@ -1,3 +1,7 @@
+mod connect_wallet;
 mod pay_invoice;
+mod pay_invoice_from_chat;
 
+pub use connect_wallet::connect_wallet;
 pub use pay_invoice::pay_invoice;
+pub use pay_invoice_from_chat::pay_invoice_from_chat;
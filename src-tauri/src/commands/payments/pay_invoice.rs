@@ -3,6 +3,7 @@ use crate::commands::groups::send_mls_message;
 use crate::groups::Group;
 use crate::messages::Message;
 use crate::payments::{self, PaymentError};
+use crate::protocol::{self, PayloadType};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use serde::Serialize;
@@ -53,6 +54,9 @@ pub async fn pay_invoice(
         message_params.kind,
         message_params.tags,
         None,
+        None,
+        None,
+        None,
         wn,
         app_handle,
     )
@@ -95,7 +99,7 @@ async fn pay_invoice_and_get_msg_params(
         .map_err(CommandError::from)?;
     Ok(MlsMessageParams {
         message: "".to_string(),
-        kind: 9,
+        kind: protocol::kind_for(PayloadType::ChatMessage),
         tags: Some(create_payment_tags(tags, &preimage)),
     })
 }
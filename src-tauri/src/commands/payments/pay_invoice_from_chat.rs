@@ -0,0 +1,228 @@
+use crate::accounts::Account;
+use crate::commands::groups::send_mls_message;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::messages::Message;
+use crate::payments::{self, PaymentError, PaymentRequestKind};
+use crate::protocol::{self, PayloadType};
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Pays an invoice that was shared in a chat with the connected wallet.
+///
+/// On success the payment is posted back into the message's group as a reply to the invoice,
+/// carrying the `preimage` as proof of payment, so every member sees it was paid.
+///
+/// The invoice is the message's payment request, from its `bolt11` tag, i.e. what its payment card
+/// showed; invoices pasted in the text aren't paid. It's recorded by payment hash before it's
+/// paid, so the same invoice isn't paid twice, even by two calls at once. Only an invoice whose payment failed can be tried again.
+///
+/// # Arguments
+///
+/// * `event_id` - Hex encoded ID of the message holding the invoice
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The app handle, used to emit the new message
+///
+/// # Returns
+///
+/// * `Ok(Message)` - The payment message posted in the group
+/// * `Err(WhitenoiseError)` - An error message if there's no invoice, no wallet, or the payment
+///   failed
+#[tauri::command]
+pub async fn pay_invoice_from_chat(
+    event_id: String,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<Message, WhitenoiseError> {
+    let event_id = EventId::parse(&event_id)
+        .map_err(|e| WhitenoiseError::InvalidInput(format!("Error parsing event ID: {}", e)))?;
+    let active_account = Account::get_active(wn.clone())
+        .await
+        .context("Error getting active account")?;
+
+    let invoice_message = Message::find_by_event_id(event_id, wn.clone())
+        .await
+        .context("Error fetching message")?;
+    let request = invoice_message
+        .payment_request
+        .clone()
+        .filter(|request| request.kind == PaymentRequestKind::Bolt11Invoice)
+        .ok_or_else(|| {
+            WhitenoiseError::InvalidInput("Message doesn't contain an invoice".to_string())
+        })?;
+    let payment_hash = request.payment_hash.clone().ok_or_else(|| {
+        WhitenoiseError::InvalidInput("Message doesn't contain an invoice".to_string())
+    })?;
+
+    if already_paid(&invoice_message, &active_account, wn.clone())
+        .await
+        .context("Error checking previous payments")?
+    {
+        return Err(WhitenoiseError::InvalidInput(
+            "This invoice was already paid".to_string(),
+        ));
+    }
+
+    let group = Group::find_by_mls_group_id(&invoice_message.mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+
+    let nwc_uri = active_account
+        .get_nostr_wallet_connect_uri(wn.clone())
+        .context("Error getting NWC URI")?
+        .ok_or_else(|| WhitenoiseError::NotFound("No wallet connected".to_string()))?;
+
+    if !reserve_payment(&payment_hash, &event_id, &active_account, wn.clone())
+        .await
+        .context("Error recording payment")?
+    {
+        return Err(WhitenoiseError::InvalidInput(
+            "This invoice was already paid".to_string(),
+        ));
+    }
+    let preimage = match payments::pay_bolt11_invoice(&request.request, &nwc_uri).await {
+        Ok(preimage) => preimage,
+        Err(e) => {
+            // Only a payment the wallet may have attempted stays recorded as failed; an invoice
+            // that was never sent to the wallet is forgotten
+            let outcome = match e {
+                PaymentError::PaymentFailure(_) => PaymentOutcome::Failed,
+                _ => PaymentOutcome::NotAttempted,
+            };
+            if let Err(e) =
+                finish_payment(&payment_hash, &active_account, outcome, wn.clone()).await
+            {
+                tracing::error!(
+                    target: "whitenoise::commands::payments::pay_invoice_from_chat",
+                    "Failed to record failed payment: {}",
+                    e
+                );
+            }
+            return Err(WhitenoiseError::from(e).with_context("Error paying invoice"));
+        }
+    };
+    if let Err(e) = finish_payment(
+        &payment_hash,
+        &active_account,
+        PaymentOutcome::Paid(&preimage),
+        wn.clone(),
+    )
+    .await
+    {
+        tracing::error!(
+            target: "whitenoise::commands::payments::pay_invoice_from_chat",
+            "Failed to record payment: {}",
+            e
+        );
+    }
+
+    tracing::debug!(
+        target: "whitenoise::commands::payments::pay_invoice_from_chat",
+        "Paid invoice from message {}",
+        event_id.to_hex()
+    );
+
+    send_mls_message(
+        group,
+        String::new(),
+        protocol::kind_for(PayloadType::ChatMessage),
+        Some(vec![Tag::custom(
+            TagKind::Custom("preimage".into()),
+            vec![preimage],
+        )]),
+        None,
+        Some(event_id.to_hex()),
+        None,
+        None,
+        wn,
+        app_handle,
+    )
+    .await
+    .context("Error posting payment")
+}
+
+/// Whether the account already posted a payment replying to this invoice message
+async fn already_paid(
+    invoice_message: &Message,
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<bool> {
+    let payments: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM messages
+         WHERE account_pubkey = ? AND author_pubkey = ? AND mls_group_id = ? AND event_kind = ?
+         AND tags LIKE '%\"preimage\"%' AND tags LIKE ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(account.pubkey.to_hex())
+    .bind(&invoice_message.mls_group_id)
+    .bind(i64::from(protocol::kind_for(PayloadType::ChatMessage)))
+    .bind(format!("%\"{}\"%", invoice_message.event_id.to_hex()))
+    .fetch_one(&wn.database.pool)
+    .await?;
+    Ok(payments > 0)
+}
+
+/// Records that the account is about to pay an invoice, unless it already paid it or is paying it
+///
+/// # Returns
+/// * `Ok(true)` - If the invoice can be paid now
+async fn reserve_payment(
+    payment_hash: &str,
+    invoice_event_id: &EventId,
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query(
+        "INSERT INTO invoice_payments (account_pubkey, payment_hash, invoice_event_id, state, created_at)
+         VALUES (?, ?, ?, 'pending', ?)
+         ON CONFLICT(account_pubkey, payment_hash) DO UPDATE SET
+             state = 'pending',
+             invoice_event_id = excluded.invoice_event_id,
+             created_at = excluded.created_at
+         WHERE invoice_payments.state = 'failed'",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(payment_hash)
+    .bind(invoice_event_id.to_hex())
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// How paying a reserved invoice went
+enum PaymentOutcome<'a> {
+    Paid(&'a str),
+    Failed,
+    /// The invoice never reached the wallet, so it's as if it was never reserved
+    NotAttempted,
+}
+
+/// Records how paying a reserved invoice went
+async fn finish_payment(
+    payment_hash: &str,
+    account: &Account,
+    outcome: PaymentOutcome<'_>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<()> {
+    let query = match outcome {
+        PaymentOutcome::Paid(preimage) => sqlx::query(
+            "UPDATE invoice_payments SET state = 'paid', preimage = ?
+             WHERE account_pubkey = ? AND payment_hash = ?",
+        )
+        .bind(preimage),
+        PaymentOutcome::Failed => sqlx::query(
+            "UPDATE invoice_payments SET state = 'failed'
+             WHERE account_pubkey = ? AND payment_hash = ?",
+        ),
+        PaymentOutcome::NotAttempted => sqlx::query(
+            "DELETE FROM invoice_payments WHERE account_pubkey = ? AND payment_hash = ?",
+        ),
+    };
+    query
+        .bind(account.pubkey.to_hex())
+        .bind(payment_hash)
+        .execute(&wn.database.pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::quick_switcher::{self, QuickSwitcherEntry};
+use crate::whitenoise::Whitenoise;
+
+/// Maximum number of entries returned
+const QUICK_SWITCHER_LIMIT: usize = 50;
+
+/// Fuzzy matches groups and contacts across all accounts for the quick switcher
+///
+/// # Arguments
+/// * `query` - The text typed into the switcher. An empty query returns everything, busiest groups first.
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<QuickSwitcherEntry>)` - Ranked entries with ids, owning account and unread counts
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_quick_switcher_entries(
+    query: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<QuickSwitcherEntry>, WhitenoiseError> {
+    quick_switcher::entries(&query, QUICK_SWITCHER_LIMIT, wn.clone())
+        .await
+        .context("Error fetching quick switcher entries")
+}
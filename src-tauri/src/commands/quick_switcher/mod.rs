@@ -0,0 +1,3 @@
+mod get_quick_switcher_entries;
+
+pub use get_quick_switcher_entries::get_quick_switcher_entries;
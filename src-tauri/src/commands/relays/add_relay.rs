@@ -0,0 +1,28 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relays::{self, AccountRelay};
+use crate::whitenoise::Whitenoise;
+
+/// Adds a relay to the active account, or changes whether it's read from and written to, and
+/// connects to it
+///
+/// # Arguments
+/// * `url` - The relay URL
+/// * `read` - Whether events are fetched from the relay
+/// * `write` - Whether events are published to the relay
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(AccountRelay)` - The saved relay and its connection status
+/// * `Err(WhitenoiseError)` - Error message if the URL is invalid, the relay is blacklisted or it
+///   can't be saved
+#[tauri::command]
+pub async fn add_relay(
+    url: String,
+    read: bool,
+    write: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<AccountRelay, WhitenoiseError> {
+    relays::add(&url, read, write, wn.clone())
+        .await
+        .context("Error adding relay")
+}
@@ -0,0 +1,27 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relay_blacklist::{BlacklistSource, BlacklistedRelay, RelayBlacklist};
+use crate::whitenoise::Whitenoise;
+
+/// Blacklists a relay for the active account
+///
+/// Welcomes, key packages and group messages are never published to blacklisted relays, even
+/// when they're listed as a group or contact relay.
+///
+/// # Arguments
+/// * `url` - The relay URL
+/// * `reason` - Optional note on why the relay was blacklisted
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(BlacklistedRelay)` - The blacklist entry
+/// * `Err(WhitenoiseError)` - Error message if the URL is invalid or the entry can't be saved
+#[tauri::command]
+pub async fn blacklist_relay(
+    url: String,
+    reason: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<BlacklistedRelay, WhitenoiseError> {
+    RelayBlacklist::add(&url, BlacklistSource::User, reason, wn.clone())
+        .await
+        .context("Error blacklisting relay")
+}
@@ -0,0 +1,17 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relay_blacklist::{BlacklistedRelay, RelayBlacklist};
+use crate::whitenoise::Whitenoise;
+
+/// Gets the active account's blacklisted relays
+///
+/// # Returns
+/// * `Ok(Vec<BlacklistedRelay>)` - Blacklisted relays with how and why they were added, newest first
+/// * `Err(WhitenoiseError)` - Error message if operation fails
+#[tauri::command]
+pub async fn get_relay_blacklist(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<BlacklistedRelay>, WhitenoiseError> {
+    RelayBlacklist::list(wn.clone())
+        .await
+        .context("Error fetching relay blacklist")
+}
@@ -0,0 +1,34 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::groups::Group;
+use crate::nostr_manager::relay_monitor::RelayRecommendation;
+use crate::params::GroupIdParam;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the relays of a group that consistently miss the delivery SLA, i.e. echo back too few of
+/// the messages sent to them or take too long to, each with a better performing relay to use
+/// instead if there is one
+///
+/// # Arguments
+/// * `group_id` - Hex encoded MLS group ID
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<RelayRecommendation>)` - The underperforming relays; empty if they all meet the SLA
+///   or haven't been sent enough messages to tell
+/// * `Err(WhitenoiseError)` - Error message if the group can't be found
+#[tauri::command]
+pub async fn get_relay_recommendations(
+    group_id: GroupIdParam,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<RelayRecommendation>, WhitenoiseError> {
+    let mls_group_id = group_id.into_bytes();
+    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
+        .await
+        .context("Error fetching group")?;
+    let relays = group
+        .publish_relays(wn.clone())
+        .await
+        .context("Error fetching group relays")?;
+
+    Ok(wn.nostr.relay_monitor.recommendations(&relays))
+}
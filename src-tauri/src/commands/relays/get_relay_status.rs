@@ -0,0 +1,19 @@
+use crate::error::WhitenoiseError;
+use crate::nostr_manager::relay_monitor::RelayHealth;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the connection health of the client's relays
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<RelayHealth>)` - Connection state, last message time, error count, message
+///   delivery rate and latency, and the last refusal, rate limit backoff and block of each relay,
+///   sorted by URL
+#[tauri::command]
+pub async fn get_relay_status(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<RelayHealth>, WhitenoiseError> {
+    Ok(wn.nostr.relay_monitor.statuses())
+}
@@ -0,0 +1,25 @@
+use crate::accounts::Account;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relays::{self, AccountRelay};
+use crate::whitenoise::Whitenoise;
+
+/// Lists the active account's relays with their read/write setting and connection status
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(Vec<AccountRelay>)` - The account's relays, sorted by URL
+/// * `Err(WhitenoiseError)` - Error message if there's no active account or the relays can't be
+///   loaded
+#[tauri::command]
+pub async fn get_relays(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<AccountRelay>, WhitenoiseError> {
+    let account = Account::get_active(wn.clone())
+        .await
+        .context("Error fetching active account")?;
+    relays::list(&account, wn.clone())
+        .await
+        .context("Error fetching relays")
+}
@@ -0,0 +1,23 @@
+mod add_relay;
+mod blacklist_relay;
+mod get_relay_blacklist;
+mod get_relay_recommendations;
+mod get_relay_status;
+mod get_relays;
+mod publish_nip65_relay_list;
+mod remove_relay;
+mod start_dev_relay;
+mod test_relay;
+mod unblacklist_relay;
+
+pub use add_relay::add_relay;
+pub use blacklist_relay::blacklist_relay;
+pub use get_relay_blacklist::get_relay_blacklist;
+pub use get_relay_recommendations::get_relay_recommendations;
+pub use get_relay_status::get_relay_status;
+pub use get_relays::get_relays;
+pub use publish_nip65_relay_list::publish_nip65_relay_list;
+pub use remove_relay::remove_relay;
+pub use start_dev_relay::start_dev_relay;
+pub use test_relay::test_relay;
+pub use unblacklist_relay::unblacklist_relay;
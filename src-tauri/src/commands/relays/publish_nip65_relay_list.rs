@@ -0,0 +1,21 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relays;
+use crate::whitenoise::Whitenoise;
+
+/// Publishes the active account's relays as its NIP-65 relay list (kind 10002)
+///
+/// # Arguments
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(String)` - The hex encoded ID of the published relay list event
+/// * `Err(WhitenoiseError)` - Error message if the account has no relays or publishing fails
+#[tauri::command]
+pub async fn publish_nip65_relay_list(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    relays::publish_relay_list(wn)
+        .await
+        .map(|event_id| event_id.to_hex())
+        .context("Error publishing relay list")
+}
@@ -0,0 +1,22 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relays;
+use crate::whitenoise::Whitenoise;
+
+/// Removes a relay from the active account and disconnects from it
+///
+/// # Arguments
+/// * `url` - The relay URL
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(())` - If the relay was removed
+/// * `Err(WhitenoiseError)` - Error message if the URL is invalid or the relay can't be removed
+#[tauri::command]
+pub async fn remove_relay(
+    url: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    relays::remove(&url, wn.clone())
+        .await
+        .context("Error removing relay")
+}
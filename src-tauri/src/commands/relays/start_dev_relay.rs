@@ -0,0 +1,50 @@
+#[cfg(feature = "dev-relay")]
+use crate::dev_relay::{self, DEFAULT_DEV_RELAY_PORT};
+#[cfg(feature = "dev-relay")]
+use crate::error::ErrorContext;
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Starts the embedded dev relay and connects to it, so group flows can be exercised without
+/// running an external relay. Only available in builds with the `dev-relay` feature.
+///
+/// # Arguments
+/// * `port` - Port to listen on, 8080 if not given
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(String)` - The relay's URL
+/// * `Err(WhitenoiseError)` - Error message if the app was built without the feature or the relay
+///   couldn't be started
+#[tauri::command]
+pub async fn start_dev_relay(
+    port: Option<u16>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, WhitenoiseError> {
+    #[cfg(feature = "dev-relay")]
+    {
+        let url = dev_relay::start(port.unwrap_or(DEFAULT_DEV_RELAY_PORT))
+            .await
+            .map_err(|e| WhitenoiseError::Internal(format!("Error starting dev relay: {}", e)))?;
+        wn.nostr
+            .client
+            .add_relay(&url)
+            .await
+            .context("Error adding dev relay")?;
+        wn.nostr
+            .client
+            .connect_relay(&url)
+            .await
+            .context("Error connecting to dev relay")?;
+        Ok(url)
+    }
+
+    #[cfg(not(feature = "dev-relay"))]
+    {
+        let _ = (port, wn);
+        Err(WhitenoiseError::InvalidInput(
+            "This build doesn't include the dev relay, build with the dev-relay feature"
+                .to_string(),
+        ))
+    }
+}
@@ -0,0 +1,17 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relays::{self, RelayTestResult};
+
+/// Connects to a relay and reports whether it's reachable and how long connecting took
+///
+/// The connection is made outside the client, so relays can be tested before they're added.
+///
+/// # Arguments
+/// * `url` - The relay URL
+///
+/// # Returns
+/// * `Ok(RelayTestResult)` - Whether the relay is reachable, with the latency or the error
+/// * `Err(WhitenoiseError)` - Error message if the URL is invalid
+#[tauri::command]
+pub async fn test_relay(url: String) -> Result<RelayTestResult, WhitenoiseError> {
+    relays::test(&url).await.context("Error testing relay")
+}
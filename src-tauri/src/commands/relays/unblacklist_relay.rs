@@ -0,0 +1,25 @@
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::relay_blacklist::RelayBlacklist;
+use crate::whitenoise::Whitenoise;
+
+/// Removes a relay from the active account's blacklist
+///
+/// Its recorded protocol violations are cleared too, so an automatically blacklisted relay
+/// starts over.
+///
+/// # Arguments
+/// * `url` - The relay URL
+/// * `wn` - Whitenoise state
+///
+/// # Returns
+/// * `Ok(())` - If the relay was removed, or wasn't blacklisted
+/// * `Err(WhitenoiseError)` - Error message if the URL is invalid or the database update fails
+#[tauri::command]
+pub async fn unblacklist_relay(
+    url: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), WhitenoiseError> {
+    RelayBlacklist::remove(&url, wn.clone())
+        .await
+        .context("Error removing relay from blacklist")
+}
@@ -0,0 +1,37 @@
+use crate::db_encryption;
+use crate::error::WhitenoiseError;
+use crate::whitenoise::Whitenoise;
+
+/// Encrypts the existing plaintext database at rest and restarts the app to reopen it.
+///
+/// The database is closed while it's encrypted, so the app also restarts if encryption fails
+/// after that, reopening the plaintext database.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+/// * `app_handle` - The Tauri app handle
+///
+/// # Returns
+///
+/// * `Ok(())` - The app restarts before this is returned
+/// * `Err(WhitenoiseError)` - An error message if the database is already encrypted or no key could
+///   be stored, in which case the plaintext database stays in use
+#[tauri::command]
+pub async fn encrypt_database(
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), WhitenoiseError> {
+    if let Err(e) = db_encryption::encrypt(wn.clone()).await {
+        // Failures after the database was closed can only be recovered from by reopening it
+        if !wn.database.pool.is_closed() {
+            return Err(WhitenoiseError::from(e).with_context("Error encrypting database"));
+        }
+        tracing::error!(
+            target: "whitenoise::commands::secrets::encrypt_database",
+            "Error encrypting database: {}",
+            e
+        );
+    }
+    app_handle.restart();
+}
@@ -0,0 +1,21 @@
+use crate::capabilities::KeyStorageBackend;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::secrets_store;
+use crate::whitenoise::Whitenoise;
+
+/// Returns the backend secrets are currently stored with.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(KeyStorageBackend)` - `file` or `os_keychain`
+/// * `Err(WhitenoiseError)` - An error message if the secrets store couldn't be read
+#[tauri::command]
+pub fn get_secrets_backend(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<KeyStorageBackend, WhitenoiseError> {
+    secrets_store::get_backend(&wn.data_dir).context("Error reading secrets backend")
+}
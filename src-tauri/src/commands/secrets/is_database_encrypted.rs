@@ -0,0 +1,20 @@
+use crate::db_encryption;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::whitenoise::Whitenoise;
+
+/// Returns whether the local database is encrypted at rest.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(bool)` - `true` if the database is encrypted
+/// * `Err(WhitenoiseError)` - An error message if the database file couldn't be read
+#[tauri::command]
+pub fn is_database_encrypted(wn: tauri::State<'_, Whitenoise>) -> Result<bool, WhitenoiseError> {
+    db_encryption::is_plaintext(&wn.database.path)
+        .map(|plaintext| !plaintext)
+        .context("Error reading database")
+}
@@ -0,0 +1,9 @@
+mod encrypt_database;
+mod get_secrets_backend;
+mod is_database_encrypted;
+mod set_secrets_backend;
+
+pub use encrypt_database::encrypt_database;
+pub use get_secrets_backend::get_secrets_backend;
+pub use is_database_encrypted::is_database_encrypted;
+pub use set_secrets_backend::set_secrets_backend;
@@ -0,0 +1,28 @@
+use crate::capabilities::KeyStorageBackend;
+use crate::error::{ErrorContext, WhitenoiseError};
+use crate::secrets_store;
+use crate::whitenoise::Whitenoise;
+
+/// Selects where secrets are stored and moves the existing private keys, MLS export secrets and
+/// other secrets there.
+///
+/// On platforms without a usable OS keychain, selecting it keeps file storage; the returned
+/// backend is the one actually in use.
+///
+/// # Arguments
+///
+/// * `backend` - The backend to use, `file` or `os_keychain`
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(KeyStorageBackend)` - The backend in use afterwards
+/// * `Err(WhitenoiseError)` - An error message if the backend isn't supported or secrets couldn't
+///   be moved
+#[tauri::command]
+pub fn set_secrets_backend(
+    backend: KeyStorageBackend,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<KeyStorageBackend, WhitenoiseError> {
+    secrets_store::set_backend(backend, &wn.data_dir).context("Error setting secrets backend")
+}
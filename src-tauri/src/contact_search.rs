@@ -0,0 +1,279 @@
+//! Finding people to talk to, e.g. for the new-group member picker.
+//!
+//! [`search_local`] matches the query against the names, display names and NIP-05 identifiers in
+//! the local event cache and the contact list, without touching relays. Results are ranked by how
+//! well they match (exact, prefix, word prefix, then substring), with contacts, verified names and
+//! people who can join groups ranked higher. Relays supporting NIP-50 search can fill in when the
+//! cache doesn't know anyone by that name.
+
+use crate::accounts::Account;
+use crate::contacts::{self, Result};
+use crate::types::EnrichedContact;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const EXACT_MATCH: u32 = 100;
+const PREFIX_MATCH: u32 = 60;
+const WORD_PREFIX_MATCH: u32 = 40;
+const SUBSTRING_MATCH: u32 = 20;
+
+const CONTACT_BONUS: u32 = 30;
+const NIP05_VERIFIED_BONUS: u32 = 10;
+const CAN_JOIN_GROUPS_BONUS: u32 = 5;
+
+/// A person matching a search
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContactSearchResult {
+    /// Hex encoded pubkey
+    pub pubkey: String,
+    pub contact: EnrichedContact,
+    /// Whether they're on the active account's contact list
+    pub is_contact: bool,
+    /// Higher is a better match
+    pub score: u32,
+}
+
+/// How well one field matches a lowercased query, 0 if it doesn't
+fn field_score(query: &str, field: &str) -> u32 {
+    let field = field.trim().to_lowercase();
+    if field.is_empty() {
+        0
+    } else if field == query {
+        EXACT_MATCH
+    } else if field.starts_with(query) {
+        PREFIX_MATCH
+    } else if field
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(query))
+    {
+        WORD_PREFIX_MATCH
+    } else if field.contains(query) {
+        SUBSTRING_MATCH
+    } else {
+        0
+    }
+}
+
+/// How well a person matches a query, 0 if they don't
+///
+/// Only the name, display name, NIP-05 identifier and the petname the user gave them are matched,
+/// so a query that only appears in someone's bio doesn't find them.
+pub fn match_score(query: &str, contact: &EnrichedContact, petname: Option<&str>) -> u32 {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return 0;
+    }
+    let metadata = &contact.metadata;
+    [
+        metadata.name.as_deref(),
+        metadata.display_name.as_deref(),
+        metadata.nip05.as_deref(),
+        petname,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|field| field_score(&query, field))
+    .max()
+    .unwrap_or(0)
+}
+
+/// The score of a match, adjusted for who it is
+fn ranked_score(match_score: u32, contact: &EnrichedContact, is_contact: bool) -> u32 {
+    let mut score = match_score;
+    if is_contact {
+        score += CONTACT_BONUS;
+    }
+    if contact.nip05_verified {
+        score += NIP05_VERIFIED_BONUS;
+    }
+    if contact.nip104 {
+        score += CAN_JOIN_GROUPS_BONUS;
+    }
+    score
+}
+
+/// Sorts results best first, by display name on ties, and keeps the first `limit`
+pub fn rank(mut results: Vec<ContactSearchResult>, limit: usize) -> Vec<ContactSearchResult> {
+    for result in results.iter_mut() {
+        result.score = ranked_score(result.score, &result.contact, result.is_contact);
+    }
+    let name = |result: &ContactSearchResult| {
+        let metadata = &result.contact.metadata;
+        metadata
+            .display_name
+            .clone()
+            .or_else(|| metadata.name.clone())
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| name(a).cmp(&name(b)))
+            .then_with(|| a.pubkey.cmp(&b.pubkey))
+    });
+    results.truncate(limit);
+    results
+}
+
+/// Searches the contact list and the cached metadata of everyone else
+///
+/// Results are unranked: their score is only the match score, see [`rank`].
+pub async fn search_local(
+    query: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<HashMap<String, ContactSearchResult>> {
+    let mut results: HashMap<String, ContactSearchResult> = HashMap::new();
+
+    let account = Account::get_active(wn.clone()).await?;
+    let contact_list = contacts::list(&account, wn.clone()).await?;
+    for contact in contact_list {
+        let Some(enriched) = contact.enriched else {
+            continue;
+        };
+        let score = match_score(query, &enriched, contact.petname.as_deref());
+        if score > 0 {
+            results.insert(
+                contact.pubkey.clone(),
+                ContactSearchResult {
+                    pubkey: contact.pubkey,
+                    contact: enriched,
+                    is_contact: true,
+                    score,
+                },
+            );
+        }
+    }
+
+    let metadata_events = wn
+        .nostr
+        .client
+        .database()
+        .query(Filter::new().kind(Kind::Metadata).search(query))
+        .await?;
+    let mut matches: Vec<(PublicKey, EnrichedContact, u32)> = Vec::new();
+    for event in metadata_events {
+        if results.contains_key(&event.pubkey.to_hex()) {
+            continue;
+        }
+        let contact = EnrichedContact {
+            metadata: Metadata::from_json(&event.content).unwrap_or_default(),
+            nip17: false,
+            nip104: false,
+            nostr_relays: Vec::new(),
+            nostr_read_relays: Vec::new(),
+            inbox_relays: Vec::new(),
+            key_package_relays: Vec::new(),
+            nip05_verified: false,
+        };
+        let score = match_score(query, &contact, None);
+        if score > 0 {
+            matches.push((event.pubkey, contact, score));
+        }
+    }
+    if matches.is_empty() {
+        return Ok(results);
+    }
+
+    // Whether they can be messaged and added to groups, as far as the cache knows
+    let enriching_events = wn
+        .nostr
+        .client
+        .database()
+        .query(
+            Filter::new()
+                .authors(matches.iter().map(|(pubkey, _, _)| *pubkey))
+                .kinds(vec![Kind::InboxRelays, Kind::MlsKeyPackage]),
+        )
+        .await?;
+    for (pubkey, mut contact, score) in matches {
+        contact.nip17 = enriching_events
+            .iter()
+            .any(|event| event.kind == Kind::InboxRelays && event.pubkey == pubkey);
+        contact.nip104 = enriching_events
+            .iter()
+            .any(|event| event.kind == Kind::MlsKeyPackage && event.pubkey == pubkey);
+        results.insert(
+            pubkey.to_hex(),
+            ContactSearchResult {
+                pubkey: pubkey.to_hex(),
+                contact,
+                is_contact: false,
+                score,
+            },
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(name: &str, nip05: Option<&str>) -> EnrichedContact {
+        let mut metadata = Metadata::new().name(name);
+        if let Some(nip05) = nip05 {
+            metadata = metadata.nip05(nip05);
+        }
+        EnrichedContact {
+            metadata,
+            nip17: false,
+            nip104: false,
+            nostr_relays: Vec::new(),
+            nostr_read_relays: Vec::new(),
+            inbox_relays: Vec::new(),
+            key_package_relays: Vec::new(),
+            nip05_verified: false,
+        }
+    }
+
+    fn result(pubkey: &str, contact: EnrichedContact, is_contact: bool) -> ContactSearchResult {
+        ContactSearchResult {
+            pubkey: pubkey.to_string(),
+            score: match_score("ali", &contact, None),
+            contact,
+            is_contact,
+        }
+    }
+
+    #[test]
+    fn test_match_score() {
+        assert_eq!(
+            match_score("Alice", &contact("alice", None), None),
+            EXACT_MATCH
+        );
+        assert_eq!(
+            match_score("ali", &contact("alice", None), None),
+            PREFIX_MATCH
+        );
+        assert_eq!(
+            match_score("ali", &contact("bob", Some("bob@alice.com")), None),
+            WORD_PREFIX_MATCH
+        );
+        assert_eq!(
+            match_score("ali", &contact("bob", None), Some("Big Alice")),
+            WORD_PREFIX_MATCH
+        );
+        assert_eq!(
+            match_score("lic", &contact("alice", None), None),
+            SUBSTRING_MATCH
+        );
+        assert_eq!(match_score("carol", &contact("alice", None), None), 0);
+        assert_eq!(match_score("  ", &contact("alice", None), None), 0);
+    }
+
+    #[test]
+    fn test_rank_prefers_contacts_and_better_matches() {
+        let results = vec![
+            result("a", contact("malice", None), true),
+            result("b", contact("alicia", None), false),
+            result("c", contact("alice", None), true),
+        ];
+        let ranked = rank(results, 2);
+        let pubkeys: Vec<&str> = ranked.iter().map(|r| r.pubkey.as_str()).collect();
+        assert_eq!(pubkeys, vec!["c", "b"]);
+        assert_eq!(ranked[0].score, PREFIX_MATCH + CONTACT_BONUS);
+    }
+}
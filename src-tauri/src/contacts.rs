@@ -0,0 +1,572 @@
+//! The account's contact list (NIP-02).
+//!
+//! Contacts are kept in the `contacts` table, which mirrors the account's latest kind 3 event.
+//! Adding and removing contacts only changes the table and marks the list as having unpublished
+//! changes; [`publish`] sends the list as a new kind 3 event. Until then a newer contact list
+//! from another client isn't imported, so local edits aren't lost. Publishing fetches the
+//! account's latest kind 3 and merges the local edits into it, so contacts added elsewhere in the
+//! meantime and the event's other tags are kept.
+//!
+//! Each contact also caches its enriched data (metadata, relays, MLS support) from the last
+//! lookup, so the contact list and the group member picker can be shown without waiting on
+//! relays.
+
+use crate::accounts::{Account, AccountError};
+use crate::nostr_manager::NostrManagerError;
+use crate::relay_blacklist::{self, RelayBlacklistError};
+use crate::types::EnrichedContact;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContactError {
+    #[error("Invalid pubkey: {0}")]
+    InvalidPubkey(String),
+
+    #[error("Not a contact: {0}")]
+    NotFound(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("Nostr database error: {0}")]
+    NostrDatabaseError(#[from] DatabaseError),
+
+    #[error("Nostr error: {0}")]
+    NostrError(#[from] NostrManagerError),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+}
+
+pub type Result<T> = std::result::Result<T, ContactError>;
+
+/// A contact of the active account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Contact {
+    /// Hex encoded pubkey
+    pub pubkey: String,
+    /// The name the user gave the contact
+    pub petname: Option<String>,
+    /// A relay where the contact can be found
+    pub relay_url: Option<String>,
+    pub added_at: Timestamp,
+    /// Metadata, relays and MLS support from the last lookup, if there was one
+    pub enriched: Option<EnrichedContact>,
+    /// Whether the contact has published a key package, so they can be added to groups
+    pub can_join_groups: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ContactRow {
+    pubkey: String,
+    petname: Option<String>,
+    relay_url: Option<String>,
+    enriched: Option<String>,
+    added_at: i64,
+}
+
+impl TryFrom<ContactRow> for Contact {
+    type Error = ContactError;
+
+    fn try_from(row: ContactRow) -> Result<Self> {
+        let enriched: Option<EnrichedContact> = row
+            .enriched
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()?;
+        Ok(Self {
+            pubkey: row.pubkey,
+            petname: row.petname,
+            relay_url: row.relay_url,
+            added_at: Timestamp::from(row.added_at as u64),
+            can_join_groups: enriched.as_ref().is_some_and(|contact| contact.nip104),
+            enriched,
+        })
+    }
+}
+
+/// An entry of a kind 3 event: pubkey, relay URL and petname
+pub(crate) type ListEntry = (String, Option<String>, Option<String>);
+
+/// Reads the `p` tags of a contact list, skipping invalid pubkeys and duplicates
+pub(crate) fn entries_from_event(event: &Event) -> Vec<ListEntry> {
+    let mut entries: Vec<ListEntry> = Vec::new();
+    for tag in event.tags.iter().filter(|tag| tag.kind() == TagKind::p()) {
+        let values = tag.as_slice();
+        let Some(pubkey) = values
+            .get(1)
+            .and_then(|pubkey| PublicKey::from_hex(pubkey).ok())
+        else {
+            continue;
+        };
+        let pubkey = pubkey.to_hex();
+        if entries.iter().any(|(existing, _, _)| *existing == pubkey) {
+            continue;
+        }
+        let non_empty = |index: usize| values.get(index).filter(|v| !v.is_empty()).cloned();
+        entries.push((pubkey, non_empty(2), non_empty(3)));
+    }
+    entries
+}
+
+/// The `p` tag of a contact. The relay URL is left empty when there's only a petname.
+fn contact_tag(pubkey: &str, relay_url: Option<&str>, petname: Option<&str>) -> Tag {
+    let mut values = vec![pubkey.to_string()];
+    if relay_url.is_some() || petname.is_some() {
+        values.push(relay_url.unwrap_or_default().to_string());
+    }
+    if let Some(petname) = petname {
+        values.push(petname.to_string());
+    }
+    Tag::custom(TagKind::p(), values)
+}
+
+/// Merges the local contacts into the account's latest contact list, which changed since the
+/// list the local contacts were last synced with. Contacts removed locally stay removed, contacts
+/// added elsewhere are kept, and for contacts on both the local relay URL and petname win.
+fn merge_entries(
+    local: &[ListEntry],
+    synced: &[ListEntry],
+    latest: &[ListEntry],
+) -> Vec<ListEntry> {
+    let mut merged = local.to_vec();
+    for entry in latest {
+        let on_list =
+            |entries: &[ListEntry]| entries.iter().any(|(pubkey, _, _)| *pubkey == entry.0);
+        if !on_list(local) && !on_list(synced) {
+            merged.push(entry.clone());
+        }
+    }
+    merged
+}
+
+/// The account's latest contact list, from its relays or the local cache, whichever is newer
+async fn latest_contact_list(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<Event>> {
+    let filter = Filter::new()
+        .kind(Kind::ContactList)
+        .author(account.pubkey)
+        .limit(1);
+    let mut events: Vec<Event> = wn
+        .nostr
+        .client
+        .database()
+        .query(filter.clone())
+        .await?
+        .into_iter()
+        .collect();
+    match wn
+        .nostr
+        .client
+        .fetch_events(filter, wn.nostr.timeout().await?)
+        .await
+    {
+        Ok(fetched) => events.extend(fetched),
+        Err(e) => tracing::warn!(
+            target: "whitenoise::contacts::latest_contact_list",
+            "Couldn't fetch the contact list, using the cached one: {}",
+            e
+        ),
+    }
+    Ok(events.into_iter().max_by_key(|event| event.created_at))
+}
+
+/// Imports the account's latest cached kind 3 event, unless the table already mirrors it or has
+/// unpublished changes
+pub async fn sync(account: &Account, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    let filter = Filter::new()
+        .kind(Kind::ContactList)
+        .author(account.pubkey)
+        .limit(1);
+    let events = wn.nostr.client.database().query(filter).await?;
+    let Some(event) = events.first() else {
+        return Ok(());
+    };
+
+    let account_pubkey = account.pubkey.to_hex();
+    let state = sqlx::query_as::<_, (i64, bool)>(
+        "SELECT synced_at, dirty FROM contact_lists WHERE account_pubkey = ?",
+    )
+    .bind(&account_pubkey)
+    .fetch_optional(&wn.database.pool)
+    .await?;
+    if let Some((synced_at, dirty)) = state {
+        if dirty || event.created_at.as_u64() as i64 <= synced_at {
+            return Ok(());
+        }
+    }
+
+    let entries = entries_from_event(event);
+    let mut txn = wn.database.pool.begin().await?;
+    let existing: Vec<String> =
+        sqlx::query_scalar("SELECT pubkey FROM contacts WHERE account_pubkey = ?")
+            .bind(&account_pubkey)
+            .fetch_all(&mut *txn)
+            .await?;
+    for pubkey in existing
+        .iter()
+        .filter(|pubkey| !entries.iter().any(|(entry, _, _)| entry == *pubkey))
+    {
+        sqlx::query("DELETE FROM contacts WHERE account_pubkey = ? AND pubkey = ?")
+            .bind(&account_pubkey)
+            .bind(pubkey)
+            .execute(&mut *txn)
+            .await?;
+    }
+    for (pubkey, relay_url, petname) in &entries {
+        sqlx::query(
+            "INSERT INTO contacts (account_pubkey, pubkey, petname, relay_url, added_at) VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (account_pubkey, pubkey) DO UPDATE SET petname = excluded.petname, relay_url = excluded.relay_url",
+        )
+        .bind(&account_pubkey)
+        .bind(pubkey)
+        .bind(petname)
+        .bind(relay_url)
+        .bind(event.created_at.as_u64() as i64)
+        .execute(&mut *txn)
+        .await?;
+    }
+    sqlx::query(
+        "INSERT OR REPLACE INTO contact_lists (account_pubkey, synced_at, dirty) VALUES (?, ?, FALSE)",
+    )
+    .bind(&account_pubkey)
+    .bind(event.created_at.as_u64() as i64)
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+
+    tracing::debug!(
+        target: "whitenoise::contacts::sync",
+        "Imported {} contacts from contact list {}",
+        entries.len(),
+        event.id
+    );
+    Ok(())
+}
+
+/// The account's contacts, in the order they were added
+pub async fn list(account: &Account, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Contact>> {
+    sqlx::query_as::<_, ContactRow>(
+        "SELECT pubkey, petname, relay_url, enriched, added_at FROM contacts
+         WHERE account_pubkey = ? ORDER BY added_at, pubkey",
+    )
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(Contact::try_from)
+    .collect()
+}
+
+async fn mark_dirty(
+    conn: &mut sqlx::SqliteConnection,
+    account: &Account,
+) -> std::result::Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO contact_lists (account_pubkey, synced_at, dirty) VALUES (?, 0, TRUE)
+         ON CONFLICT (account_pubkey) DO UPDATE SET dirty = TRUE",
+    )
+    .bind(account.pubkey.to_hex())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Adds a contact, or renames one that's already on the list. A blank petname clears it.
+pub async fn add(
+    account: &Account,
+    pubkey: &str,
+    petname: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let pubkey = PublicKey::parse(pubkey)
+        .map_err(|_| ContactError::InvalidPubkey(pubkey.to_string()))?
+        .to_hex();
+    let petname = petname
+        .map(|petname| petname.trim().to_string())
+        .filter(|petname| !petname.is_empty());
+    sync(account, wn.clone()).await?;
+
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO contacts (account_pubkey, pubkey, petname, added_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT (account_pubkey, pubkey) DO UPDATE SET petname = excluded.petname",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(&pubkey)
+    .bind(&petname)
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&mut *txn)
+    .await?;
+    mark_dirty(&mut *txn, account).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Adds contacts imported from elsewhere, keeping the contacts already on the list as they are
+///
+/// # Returns
+/// * `Ok(usize)` - How many contacts were new
+pub async fn import(
+    account: &Account,
+    entries: &[ListEntry],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<usize> {
+    sync(account, wn.clone()).await?;
+
+    let added_at = Timestamp::now().as_u64() as i64;
+    let mut added = 0;
+    let mut txn = wn.database.pool.begin().await?;
+    for (pubkey, relay_url, petname) in entries {
+        added += sqlx::query(
+            "INSERT OR IGNORE INTO contacts (account_pubkey, pubkey, petname, relay_url, added_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(pubkey)
+        .bind(petname)
+        .bind(relay_url)
+        .bind(added_at)
+        .execute(&mut *txn)
+        .await?
+        .rows_affected() as usize;
+    }
+    if added > 0 {
+        mark_dirty(&mut *txn, account).await?;
+    }
+    txn.commit().await?;
+    Ok(added)
+}
+
+/// Removes a contact
+pub async fn remove(
+    account: &Account,
+    pubkey: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let pubkey = PublicKey::parse(pubkey)
+        .map_err(|_| ContactError::InvalidPubkey(pubkey.to_string()))?
+        .to_hex();
+    sync(account, wn.clone()).await?;
+
+    let mut txn = wn.database.pool.begin().await?;
+    let removed = sqlx::query("DELETE FROM contacts WHERE account_pubkey = ? AND pubkey = ?")
+        .bind(account.pubkey.to_hex())
+        .bind(&pubkey)
+        .execute(&mut *txn)
+        .await?;
+    if removed.rows_affected() == 0 {
+        return Err(ContactError::NotFound(pubkey));
+    }
+    mark_dirty(&mut *txn, account).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Publishes the contacts as the account's kind 3 contact list
+///
+/// The account's latest contact list is fetched first. If it's newer than the one the contacts
+/// were last synced with, the local edits are merged into it and the contacts added elsewhere are
+/// saved locally too. Its content and its tags other than `p` are kept either way.
+pub async fn publish(account: &Account, wn: tauri::State<'_, Whitenoise>) -> Result<EventId> {
+    let account_pubkey = account.pubkey.to_hex();
+    let local: Vec<ListEntry> = list(account, wn.clone())
+        .await?
+        .into_iter()
+        .map(|contact| (contact.pubkey, contact.relay_url, contact.petname))
+        .collect();
+    let synced_at = sqlx::query_scalar::<_, i64>(
+        "SELECT synced_at FROM contact_lists WHERE account_pubkey = ?",
+    )
+    .bind(&account_pubkey)
+    .fetch_optional(&wn.database.pool)
+    .await?
+    .unwrap_or_default();
+
+    let latest = latest_contact_list(account, wn.clone()).await?;
+    let entries = match &latest {
+        Some(latest) if latest.created_at.as_u64() as i64 > synced_at => {
+            // The list the local contacts were last synced with tells local removals apart from
+            // contacts added elsewhere
+            let synced: Vec<ListEntry> = if synced_at > 0 {
+                let filter = Filter::new()
+                    .kind(Kind::ContactList)
+                    .author(account.pubkey)
+                    .until(Timestamp::from(synced_at as u64))
+                    .limit(1);
+                wn.nostr
+                    .client
+                    .database()
+                    .query(filter)
+                    .await?
+                    .first()
+                    .map(entries_from_event)
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            merge_entries(&local, &synced, &entries_from_event(latest))
+        }
+        _ => local.clone(),
+    };
+
+    let mut tags: Vec<Tag> = latest
+        .iter()
+        .flat_map(|latest| latest.tags.iter())
+        .filter(|tag| tag.kind() != TagKind::p())
+        .cloned()
+        .collect();
+    tags.extend(entries.iter().map(|(pubkey, relay_url, petname)| {
+        contact_tag(pubkey, relay_url.as_deref(), petname.as_deref())
+    }));
+    let content = latest
+        .as_ref()
+        .map(|latest| latest.content.clone())
+        .unwrap_or_default();
+    let event = wn
+        .nostr
+        .client
+        .sign_event_builder(EventBuilder::new(Kind::ContactList, content).tags(tags))
+        .await?;
+    relay_blacklist::send_event(&event, Vec::new(), wn.clone()).await?;
+
+    let mut txn = wn.database.pool.begin().await?;
+    for (pubkey, relay_url, petname) in entries
+        .iter()
+        .filter(|(pubkey, _, _)| !local.iter().any(|(local, _, _)| local == pubkey))
+    {
+        sqlx::query(
+            "INSERT OR IGNORE INTO contacts (account_pubkey, pubkey, petname, relay_url, added_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&account_pubkey)
+        .bind(pubkey)
+        .bind(petname)
+        .bind(relay_url)
+        .bind(event.created_at.as_u64() as i64)
+        .execute(&mut *txn)
+        .await?;
+    }
+    sqlx::query(
+        "INSERT OR REPLACE INTO contact_lists (account_pubkey, synced_at, dirty) VALUES (?, ?, FALSE)",
+    )
+    .bind(&account_pubkey)
+    .bind(event.created_at.as_u64() as i64)
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+
+    tracing::debug!(
+        target: "whitenoise::contacts::publish",
+        "Published contact list with {} contacts: {}",
+        entries.len(),
+        event.id
+    );
+    Ok(event.id)
+}
+
+/// Caches the enriched data of a contact. Does nothing for pubkeys that aren't contacts.
+pub async fn cache_enriched(
+    account: &Account,
+    pubkey: &str,
+    enriched: &EnrichedContact,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    sqlx::query("UPDATE contacts SET enriched = ? WHERE account_pubkey = ? AND pubkey = ?")
+        .bind(serde_json::to_string(enriched)?)
+        .bind(account.pubkey.to_hex())
+        .bind(pubkey)
+        .execute(&wn.database.pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contact_list_entries() {
+        let keys = Keys::generate();
+        let alice = Keys::generate().public_key().to_hex();
+        let bob = Keys::generate().public_key().to_hex();
+        let event = EventBuilder::new(Kind::ContactList, "")
+            .tags([
+                contact_tag(&alice, Some("wss://relay.example.com"), Some("alice")),
+                contact_tag(&bob, None, Some("bob")),
+                contact_tag(&alice, None, None),
+                Tag::custom(TagKind::p(), ["not a pubkey"]),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        assert_eq!(
+            entries_from_event(&event),
+            vec![
+                (
+                    alice,
+                    Some("wss://relay.example.com".to_string()),
+                    Some("alice".to_string())
+                ),
+                (bob, None, Some("bob".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_contacts_added_elsewhere_and_local_removals() {
+        let entry = |name: &str, petname: Option<&str>| {
+            (
+                name.to_string(),
+                None,
+                petname.map(|petname| petname.to_string()),
+            )
+        };
+        let synced = vec![entry("alice", None), entry("bob", None)];
+        // Locally bob was removed and alice renamed, elsewhere carol was added
+        let local = vec![entry("alice", Some("Alice"))];
+        let latest = vec![
+            entry("alice", None),
+            entry("bob", None),
+            entry("carol", None),
+        ];
+
+        assert_eq!(
+            merge_entries(&local, &synced, &latest),
+            vec![entry("alice", Some("Alice")), entry("carol", None)]
+        );
+        // Without the synced list nothing is known to be removed
+        assert_eq!(
+            merge_entries(&local, &[], &latest),
+            vec![
+                entry("alice", Some("Alice")),
+                entry("bob", None),
+                entry("carol", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contact_tag_without_petname() {
+        let pubkey = Keys::generate().public_key().to_hex();
+        assert_eq!(contact_tag(&pubkey, None, None).as_slice().len(), 2);
+        assert_eq!(
+            contact_tag(&pubkey, None, Some("carol")).as_slice(),
+            &["p".to_string(), pubkey, String::new(), "carol".to_string()]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Content filters.
+//!
+//! Users can configure a list of words and regular expressions to mask. The outbound filter
+//! runs on the content of outgoing chat messages before they're encrypted, so the masked text is
+//! what the group receives. The inbound filter only affects display: received messages keep
+//! their content and get a masked `display_content` instead. Both filters can be toggled per
+//! account and overridden per group.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Character that replaces each character of a masked match
+const MASK_CHAR: char = '*';
+
+#[derive(Error, Debug)]
+pub enum ContentFilterError {
+    #[error("Invalid filter pattern: {0}")]
+    InvalidPattern(#[from] regex::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ContentFilterError>;
+
+/// The words and patterns to mask
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContentFilter {
+    /// Whole words, matched case-insensitively
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Regular expressions
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl ContentFilter {
+    fn compile(&self) -> Result<Vec<Regex>> {
+        let words = self
+            .words
+            .iter()
+            .map(|word| word.trim())
+            .filter(|word| !word.is_empty())
+            .map(|word| Regex::new(&format!(r"(?i)\b{}\b", regex::escape(word))));
+        let patterns = self
+            .patterns
+            .iter()
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| Regex::new(pattern));
+        Ok(words
+            .chain(patterns)
+            .collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Checks that every pattern is a valid regular expression
+    pub fn validate(&self) -> Result<()> {
+        self.compile().map(|_| ())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Replaces every match in `content` with mask characters of the same length
+    pub fn mask(&self, content: &str) -> Result<String> {
+        let mut masked = content.to_string();
+        for regex in self.compile()? {
+            masked = regex
+                .replace_all(&masked, |caps: &regex::Captures| {
+                    MASK_CHAR.to_string().repeat(caps[0].chars().count())
+                })
+                .into_owned();
+        }
+        Ok(masked)
+    }
+}
+
+/// Account-wide content filter settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContentFilterSettings {
+    /// Mask outgoing messages before they're sent
+    #[serde(default)]
+    pub outbound: bool,
+    /// Mask received messages when displaying them
+    #[serde(default)]
+    pub inbound: bool,
+    #[serde(default)]
+    pub filter: ContentFilter,
+}
+
+/// Per-group overrides of the account's content filter settings. `None` uses the account setting.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct GroupContentFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbound: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inbound: Option<bool>,
+}
+
+impl ContentFilterSettings {
+    /// Masks outgoing content if the outbound filter is enabled for the group
+    pub fn filter_outbound(&self, group: &GroupContentFilter, content: &str) -> Result<String> {
+        if group.outbound.unwrap_or(self.outbound) && !self.filter.is_empty() {
+            self.filter.mask(content)
+        } else {
+            Ok(content.to_string())
+        }
+    }
+
+    /// Returns the masked display content of a received message, or `None` if the inbound
+    /// filter is disabled for the group or didn't change anything
+    pub fn filter_inbound(&self, group: &GroupContentFilter, content: &str) -> Option<String> {
+        if !group.inbound.unwrap_or(self.inbound) || self.filter.is_empty() {
+            return None;
+        }
+        match self.filter.mask(content) {
+            Ok(masked) if masked != content => Some(masked),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::content_filters::filter_inbound",
+                    "Skipping invalid content filter: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(words: &[&str], patterns: &[&str]) -> ContentFilterSettings {
+        ContentFilterSettings {
+            outbound: true,
+            inbound: true,
+            filter: ContentFilter {
+                words: words.iter().map(|w| w.to_string()).collect(),
+                patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_mask_words() {
+        let filter = settings(&["darn"], &[]).filter;
+        assert_eq!(filter.mask("Darn it, darn!").unwrap(), "**** it, ****!");
+        // Only whole words are masked
+        assert_eq!(filter.mask("darnation").unwrap(), "darnation");
+    }
+
+    #[test]
+    fn test_mask_patterns() {
+        let filter = settings(&[], &[r"\d{4}-\d{4}"]).filter;
+        assert_eq!(filter.mask("call 5555-1234").unwrap(), "call *********");
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let filter = settings(&[], &["(unclosed"]).filter;
+        assert!(filter.validate().is_err());
+        assert!(filter.mask("anything").is_err());
+    }
+
+    #[test]
+    fn test_group_overrides() {
+        let mut settings = settings(&["darn"], &[]);
+        settings.inbound = false;
+
+        let default = GroupContentFilter::default();
+        assert_eq!(settings.filter_outbound(&default, "darn").unwrap(), "****");
+        assert_eq!(settings.filter_inbound(&default, "darn"), None);
+
+        let overridden = GroupContentFilter {
+            outbound: Some(false),
+            inbound: Some(true),
+        };
+        assert_eq!(
+            settings.filter_outbound(&overridden, "darn").unwrap(),
+            "darn"
+        );
+        assert_eq!(
+            settings.filter_inbound(&overridden, "darn"),
+            Some("****".to_string())
+        );
+        assert_eq!(settings.filter_inbound(&overridden, "clean"), None);
+    }
+}
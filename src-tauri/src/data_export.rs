@@ -0,0 +1,324 @@
+//! Machine-readable exports of an account's data, for data portability.
+//!
+//! Unlike encrypted backups (see `account_backup`), an export is plain JSON meant to be read by
+//! people and other programs, and it can't be used to restore the account: it leaves out the
+//! private key, the groups' export secrets and the MLS state. It contains:
+//!
+//! - `format` and `version`: always `"whitenoise-account-data"` and [`DATA_EXPORT_VERSION`]
+//! - `exported_at`: Unix timestamp of the export
+//! - `pubkey` and `npub`: the account's public key, hex and bech32 encoded
+//! - `profile`: the account's Nostr metadata (kind 0)
+//! - `settings`: the account's app settings
+//! - `relays`: the account's relays by type (`nostr`, `inbox`, `key_package`)
+//! - `contacts`: [`ExportedContact`]s, with the metadata last seen for each
+//! - `blocked_users`: the users the account blocked and when
+//! - `groups`: [`ExportedGroup`]s with their relays, members and full transcripts
+//!
+//! Timestamps are Unix timestamps in seconds, pubkeys and IDs are hex encoded. Messages also
+//! carry `sent_at`, their timestamp formatted in the group's locale. Transcripts include
+//! non-chat messages like reactions and edits; their `kind` tells them apart.
+
+use crate::accounts::{Account, AccountError, AccountSettings};
+use crate::atomic_file;
+use crate::blocklist::{self, BlockedUser, BlocklistError};
+use crate::contacts::{self, ContactError};
+use crate::groups::{Group, GroupError, GroupState, GroupType};
+use crate::localization::{self, Locale};
+use crate::messages::{Message, MessageRow};
+use crate::relays::RelayType;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Identifies export files
+pub const DATA_EXPORT_FORMAT: &str = "whitenoise-account-data";
+
+/// Version of the export format
+pub const DATA_EXPORT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum DataExportError {
+    #[error("Invalid export path: {0}")]
+    InvalidPath(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Contact error: {0}")]
+    ContactError(#[from] ContactError),
+
+    #[error("Blocklist error: {0}")]
+    BlocklistError(#[from] BlocklistError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, DataExportError>;
+
+/// A contact of the account
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportedContact {
+    pub pubkey: String,
+    /// The name the user gave the contact
+    pub petname: Option<String>,
+    pub relay_url: Option<String>,
+    pub added_at: Timestamp,
+    /// The contact's metadata when it was last looked up
+    pub metadata: Option<Metadata>,
+}
+
+/// A message of a group's transcript
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ExportedMessage {
+    pub event_id: String,
+    pub author: String,
+    /// Nostr event kind: 9 for chat messages, 7 for reactions, and so on
+    pub kind: u16,
+    pub created_at: Timestamp,
+    /// `created_at` formatted in the group's locale, e.g. "Nov 14, 2023 22:13 UTC"
+    pub sent_at: String,
+    /// The latest version of the content; empty for deleted messages
+    pub content: String,
+    pub tags: Vec<Vec<String>>,
+    pub reply_to: Option<String>,
+    pub thread_root: Option<String>,
+    pub edited_at: Option<Timestamp>,
+    pub deleted_at: Option<Timestamp>,
+    pub expires_at: Option<Timestamp>,
+}
+
+impl ExportedMessage {
+    /// Exports a message of a group whose text is formatted in `locale`
+    fn new(message: &Message, locale: Locale) -> Self {
+        Self {
+            event_id: message.event_id.to_hex(),
+            author: message.author_pubkey.to_hex(),
+            kind: message.event_kind,
+            created_at: message.created_at,
+            sent_at: localization::format_timestamp(locale, message.created_at),
+            content: message.content.clone(),
+            tags: message
+                .tags
+                .iter()
+                .map(|tag| tag.as_slice().to_vec())
+                .collect(),
+            reply_to: message.reply_to.map(|event_id| event_id.to_hex()),
+            thread_root: message.thread_root.map(|event_id| event_id.to_hex()),
+            edited_at: message.edited_at,
+            deleted_at: message.deleted_at,
+            expires_at: message.expires_at,
+        }
+    }
+}
+
+/// A group the account is or was in
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportedGroup {
+    /// Hex encoded MLS group ID
+    pub group_id: String,
+    pub nostr_group_id: String,
+    pub name: String,
+    pub description: String,
+    pub group_type: GroupType,
+    pub state: GroupState,
+    pub admin_pubkeys: Vec<String>,
+    pub relays: Vec<String>,
+    /// Current members; only known for the active account
+    pub members: Option<Vec<String>>,
+    pub archived_at: Option<Timestamp>,
+    /// Oldest first
+    pub messages: Vec<ExportedMessage>,
+}
+
+/// The contents of an export file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountDataExport {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: Timestamp,
+    pub pubkey: String,
+    pub npub: String,
+    pub profile: Metadata,
+    pub settings: AccountSettings,
+    pub relays: BTreeMap<String, Vec<String>>,
+    pub contacts: Vec<ExportedContact>,
+    pub blocked_users: Vec<BlockedUser>,
+    pub groups: Vec<ExportedGroup>,
+}
+
+/// Checks that an export can be written to `path`: an absolute file path in an existing
+/// directory
+pub fn validate_path(path: &Path) -> Result<()> {
+    if !path.is_absolute() {
+        return Err(DataExportError::InvalidPath(
+            "The path must be absolute".to_string(),
+        ));
+    }
+    if path.is_dir() {
+        return Err(DataExportError::InvalidPath(
+            "The path is a directory".to_string(),
+        ));
+    }
+    if !path.parent().is_some_and(Path::is_dir) {
+        return Err(DataExportError::InvalidPath(
+            "The directory doesn't exist".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn export_group(
+    account: &Account,
+    group: Group,
+    is_active: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<ExportedGroup> {
+    let relays = sqlx::query_scalar::<_, String>(
+        "SELECT url FROM group_relays WHERE group_id = ? AND account_pubkey = ?",
+    )
+    .bind(&group.mls_group_id)
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+    // Only the active account's MLS state is loaded
+    let members = if is_active {
+        group
+            .members(wn.clone())
+            .await
+            .ok()
+            .map(|members| members.iter().map(|pubkey| pubkey.to_hex()).collect())
+    } else {
+        None
+    };
+    let locale = group.resolved_locale();
+    let messages: Vec<ExportedMessage> = sqlx::query_as::<_, MessageRow>(
+        "SELECT * FROM messages WHERE mls_group_id = ? AND account_pubkey = ?
+         ORDER BY created_at, event_id",
+    )
+    .bind(&group.mls_group_id)
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(|row| ExportedMessage::new(&Message::from(row), locale))
+    .collect();
+
+    Ok(ExportedGroup {
+        group_id: hex::encode(&group.mls_group_id),
+        nostr_group_id: group.nostr_group_id,
+        name: group.name,
+        description: group.description,
+        group_type: group.group_type,
+        state: group.state,
+        admin_pubkeys: group.admin_pubkeys,
+        relays,
+        members,
+        archived_at: group.archived_at,
+        messages,
+    })
+}
+
+/// Collects an account's data into an export
+pub async fn create(
+    pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<AccountDataExport> {
+    let account = Account::find_by_pubkey(pubkey, wn.clone()).await?;
+    let is_active = Account::get_active_pubkey(wn.clone())
+        .await
+        .is_ok_and(|active| active == account.pubkey);
+
+    let mut relays = BTreeMap::new();
+    for relay_type in [RelayType::Nostr, RelayType::Inbox, RelayType::KeyPackage] {
+        relays.insert(
+            String::from(relay_type),
+            account.relays(relay_type, wn.clone()).await?,
+        );
+    }
+
+    let contacts = contacts::list(&account, wn.clone())
+        .await?
+        .into_iter()
+        .map(|contact| ExportedContact {
+            pubkey: contact.pubkey,
+            petname: contact.petname,
+            relay_url: contact.relay_url,
+            added_at: contact.added_at,
+            metadata: contact.enriched.map(|enriched| enriched.metadata),
+        })
+        .collect();
+
+    let mut groups = Vec::new();
+    for group in account.groups(wn.clone()).await? {
+        groups.push(export_group(&account, group, is_active, wn.clone()).await?);
+    }
+
+    Ok(AccountDataExport {
+        format: DATA_EXPORT_FORMAT.to_string(),
+        version: DATA_EXPORT_VERSION,
+        exported_at: Timestamp::now(),
+        pubkey: account.pubkey.to_hex(),
+        npub: account.pubkey.to_bech32().unwrap_or_default(),
+        profile: account.metadata.clone(),
+        settings: account.settings.clone(),
+        relays,
+        contacts,
+        blocked_users: blocklist::list(&account, wn.clone()).await?,
+        groups,
+    })
+}
+
+/// Writes an export of an account's data to `path`
+///
+/// # Returns
+/// * `Ok(PathBuf)` - The path of the export file
+pub async fn export(
+    pubkey: &PublicKey,
+    path: &Path,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<PathBuf> {
+    validate_path(path)?;
+    if wn.app_lock.lock().await.locked {
+        return Err(AccountError::AppLocked.into());
+    }
+    let data = create(pubkey, wn.clone()).await?;
+    // The export isn't encrypted: the write's temporary file is renamed over `path`, or removed
+    // if the write fails, so no other copy is left next to it
+    atomic_file::write(path, serde_json::to_vec_pretty(&data)?)?;
+
+    tracing::info!(
+        target: "whitenoise::data_export::export",
+        "Exported data of {} with {} groups to {:?}",
+        pubkey.to_hex(),
+        data.groups.len(),
+        path
+    );
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_path() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(validate_path(&dir.path().join("export.json")).is_ok());
+        assert!(validate_path(dir.path()).is_err());
+        assert!(validate_path(&dir.path().join("missing").join("export.json")).is_err());
+        assert!(validate_path(Path::new("export.json")).is_err());
+    }
+}
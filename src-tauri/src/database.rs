@@ -1,10 +1,13 @@
-use sqlx::sqlite::SqlitePoolOptions;
+use crate::db_encryption;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
 use std::fs;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 use tauri::{path::BaseDirectory, AppHandle, Manager};
 use thiserror::Error;
+use zeroize::Zeroizing;
 
 const MIGRATION_FILES: &[(&str, &[u8])] = &[
     (
@@ -15,6 +18,230 @@ const MIGRATION_FILES: &[(&str, &[u8])] = &[
         "0002_add_media_files.sql",
         include_bytes!("../db_migrations/0002_add_media_files.sql"),
     ),
+    (
+        "0003_add_tokens_to_messages.sql",
+        include_bytes!("../db_migrations/0003_add_tokens_to_messages.sql"),
+    ),
+    (
+        "0004_add_event_kind_to_messages.sql",
+        include_bytes!("../db_migrations/0004_add_event_kind_to_messages.sql"),
+    ),
+    (
+        "0005_add_locale_to_groups.sql",
+        include_bytes!("../db_migrations/0005_add_locale_to_groups.sql"),
+    ),
+    (
+        "0006_add_snoozed_until_to_groups.sql",
+        include_bytes!("../db_migrations/0006_add_snoozed_until_to_groups.sql"),
+    ),
+    (
+        "0007_rebuild_messages_fts.sql",
+        include_bytes!("../db_migrations/0007_rebuild_messages_fts.sql"),
+    ),
+    (
+        "0008_add_deleted_at_to_messages.sql",
+        include_bytes!("../db_migrations/0008_add_deleted_at_to_messages.sql"),
+    ),
+    (
+        "0009_add_edited_at_to_messages.sql",
+        include_bytes!("../db_migrations/0009_add_edited_at_to_messages.sql"),
+    ),
+    (
+        "0010_add_sensitive_to_groups.sql",
+        include_bytes!("../db_migrations/0010_add_sensitive_to_groups.sql"),
+    ),
+    (
+        "0011_add_contact_key_migrations.sql",
+        include_bytes!("../db_migrations/0011_add_contact_key_migrations.sql"),
+    ),
+    (
+        "0012_add_group_merges.sql",
+        include_bytes!("../db_migrations/0012_add_group_merges.sql"),
+    ),
+    (
+        "0013_add_read_state.sql",
+        include_bytes!("../db_migrations/0013_add_read_state.sql"),
+    ),
+    (
+        "0014_add_message_outbox.sql",
+        include_bytes!("../db_migrations/0014_add_message_outbox.sql"),
+    ),
+    (
+        "0015_add_expires_at_to_messages.sql",
+        include_bytes!("../db_migrations/0015_add_expires_at_to_messages.sql"),
+    ),
+    (
+        "0016_add_outbox_retries.sql",
+        include_bytes!("../db_migrations/0016_add_outbox_retries.sql"),
+    ),
+    (
+        "0017_add_content_filter_to_groups.sql",
+        include_bytes!("../db_migrations/0017_add_content_filter_to_groups.sql"),
+    ),
+    (
+        "0018_add_published_artifacts.sql",
+        include_bytes!("../db_migrations/0018_add_published_artifacts.sql"),
+    ),
+    (
+        "0019_add_relay_blacklist.sql",
+        include_bytes!("../db_migrations/0019_add_relay_blacklist.sql"),
+    ),
+    (
+        "0020_add_group_members.sql",
+        include_bytes!("../db_migrations/0020_add_group_members.sql"),
+    ),
+    (
+        "0021_add_background_sync_checkpoints.sql",
+        include_bytes!("../db_migrations/0021_add_background_sync_checkpoints.sql"),
+    ),
+    (
+        "0022_add_key_package_ids_to_accounts.sql",
+        include_bytes!("../db_migrations/0022_add_key_package_ids_to_accounts.sql"),
+    ),
+    (
+        "0023_add_record_integrity.sql",
+        include_bytes!("../db_migrations/0023_add_record_integrity.sql"),
+    ),
+    (
+        "0024_add_read_write_to_account_relays.sql",
+        include_bytes!("../db_migrations/0024_add_read_write_to_account_relays.sql"),
+    ),
+    (
+        "0025_add_usage_stats.sql",
+        include_bytes!("../db_migrations/0025_add_usage_stats.sql"),
+    ),
+    (
+        "0026_add_message_ttl_and_avatar_to_groups.sql",
+        include_bytes!("../db_migrations/0026_add_message_ttl_and_avatar_to_groups.sql"),
+    ),
+    (
+        "0027_add_muted_to_groups.sql",
+        include_bytes!("../db_migrations/0027_add_muted_to_groups.sql"),
+    ),
+    (
+        "0028_add_contacts.sql",
+        include_bytes!("../db_migrations/0028_add_contacts.sql"),
+    ),
+    (
+        "0029_add_nip05_verifications.sql",
+        include_bytes!("../db_migrations/0029_add_nip05_verifications.sql"),
+    ),
+    (
+        "0030_add_group_notes.sql",
+        include_bytes!("../db_migrations/0030_add_group_notes.sql"),
+    ),
+    (
+        "0031_add_group_custom_data.sql",
+        include_bytes!("../db_migrations/0031_add_group_custom_data.sql"),
+    ),
+    (
+        "0032_add_blocked_users.sql",
+        include_bytes!("../db_migrations/0032_add_blocked_users.sql"),
+    ),
+    (
+        "0033_add_key_package_consumptions.sql",
+        include_bytes!("../db_migrations/0033_add_key_package_consumptions.sql"),
+    ),
+    (
+        "0034_add_group_media.sql",
+        include_bytes!("../db_migrations/0034_add_group_media.sql"),
+    ),
+    (
+        "0035_add_recovery_contacts.sql",
+        include_bytes!("../db_migrations/0035_add_recovery_contacts.sql"),
+    ),
+    (
+        "0036_add_pending_welcomes.sql",
+        include_bytes!("../db_migrations/0036_add_pending_welcomes.sql"),
+    ),
+    (
+        "0037_add_join_requests.sql",
+        include_bytes!("../db_migrations/0037_add_join_requests.sql"),
+    ),
+    (
+        "0038_add_group_event_log.sql",
+        include_bytes!("../db_migrations/0038_add_group_event_log.sql"),
+    ),
+    (
+        "0039_add_mentions_only_to_groups.sql",
+        include_bytes!("../db_migrations/0039_add_mentions_only_to_groups.sql"),
+    ),
+    (
+        "0040_add_note_to_self_to_groups.sql",
+        include_bytes!("../db_migrations/0040_add_note_to_self_to_groups.sql"),
+    ),
+    (
+        "0041_add_mentions_me_to_messages.sql",
+        include_bytes!("../db_migrations/0041_add_mentions_me_to_messages.sql"),
+    ),
+    (
+        "0042_add_invite_links.sql",
+        include_bytes!("../db_migrations/0042_add_invite_links.sql"),
+    ),
+    (
+        "0043_add_synced_settings.sql",
+        include_bytes!("../db_migrations/0043_add_synced_settings.sql"),
+    ),
+    (
+        "0044_add_joined_epoch_to_group_members.sql",
+        include_bytes!("../db_migrations/0044_add_joined_epoch_to_group_members.sql"),
+    ),
+    (
+        "0045_add_leaf_updated_at_to_group_members.sql",
+        include_bytes!("../db_migrations/0045_add_leaf_updated_at_to_group_members.sql"),
+    ),
+    (
+        "0046_add_quarantined_events.sql",
+        include_bytes!("../db_migrations/0046_add_quarantined_events.sql"),
+    ),
+    (
+        "0047_add_stale_flagged_at_to_group_members.sql",
+        include_bytes!("../db_migrations/0047_add_stale_flagged_at_to_group_members.sql"),
+    ),
+    (
+        "0048_add_issued_invites.sql",
+        include_bytes!("../db_migrations/0048_add_issued_invites.sql"),
+    ),
+    (
+        "0049_add_leaf_key_to_group_members.sql",
+        include_bytes!("../db_migrations/0049_add_leaf_key_to_group_members.sql"),
+    ),
+    (
+        "0050_add_invoice_payments.sql",
+        include_bytes!("../db_migrations/0050_add_invoice_payments.sql"),
+    ),
+    (
+        "0051_clear_plaintext_group_event_log.sql",
+        include_bytes!("../db_migrations/0051_clear_plaintext_group_event_log.sql"),
+    ),
+    (
+        "0052_quarantine_event_ids_only.sql",
+        include_bytes!("../db_migrations/0052_quarantine_event_ids_only.sql"),
+    ),
+    (
+        "0053_add_pending_welcome_epoch.sql",
+        include_bytes!("../db_migrations/0053_add_pending_welcome_epoch.sql"),
+    ),
+    (
+        "0054_clear_edits_of_deleted_messages.sql",
+        include_bytes!("../db_migrations/0054_clear_edits_of_deleted_messages.sql"),
+    ),
+    (
+        "0055_add_rotated_key_packages.sql",
+        include_bytes!("../db_migrations/0055_add_rotated_key_packages.sql"),
+    ),
+    (
+        "0056_add_unblocked_users.sql",
+        include_bytes!("../db_migrations/0056_add_unblocked_users.sql"),
+    ),
+    (
+        "0057_uncount_group_notices.sql",
+        include_bytes!("../db_migrations/0057_uncount_group_notices.sql"),
+    ),
+    (
+        "0058_rename_joined_epoch.sql",
+        include_bytes!("../db_migrations/0058_rename_joined_epoch.sql"),
+    ),
     // Add new migrations here in order, for example:
     // ("0002_something.sql", include_bytes!("../db_migrations/0002_something.sql")),
     // ("0003_another.sql", include_bytes!("../db_migrations/0003_another.sql")),
@@ -35,14 +262,20 @@ pub enum DatabaseError {
 #[derive(Clone)]
 pub struct Database {
     pub pool: SqlitePool,
-    #[allow(unused)]
     pub path: PathBuf,
     #[allow(unused)]
     pub last_connected: std::time::SystemTime,
 }
 
 impl Database {
-    pub async fn new(db_path: PathBuf, app_handle: AppHandle) -> Result<Self, DatabaseError> {
+    /// Opens the database at `db_path`, creating it if needed, and runs migrations.
+    ///
+    /// `key` is the hex encoded SQLCipher key of an encrypted database, see [`db_encryption`].
+    pub async fn new(
+        db_path: PathBuf,
+        key: Option<Zeroizing<String>>,
+        app_handle: AppHandle,
+    ) -> Result<Self, DatabaseError> {
         // Create parent directories if they don't exist
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -66,6 +299,12 @@ impl Database {
             }
         }
 
+        let mut connect_options = SqliteConnectOptions::from_str(&format!("{}?mode=rwc", db_url))?;
+        if let Some(key) = key {
+            // sqlx applies the key pragma before any other statement, as SQLCipher requires
+            connect_options = connect_options.pragma("key", db_encryption::key_pragma(&key));
+        }
+
         // Create connection pool with refined settings
         tracing::info!("Creating connection pool...");
         let pool = SqlitePoolOptions::new()
@@ -92,7 +331,7 @@ impl Database {
                     Ok(())
                 })
             })
-            .connect(&format!("{}?mode=rwc", db_url))
+            .connect_with(connect_options)
             .await?;
 
         // Run migrations
@@ -179,7 +418,106 @@ impl Database {
         sqlx::query("DELETE FROM group_relays")
             .execute(&mut *txn)
             .await?;
+        sqlx::query("DELETE FROM group_members")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM background_sync_checkpoints")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM contact_key_migrations")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM read_receipts")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM message_outbox")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM published_artifacts")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM relay_violations")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM relay_blacklist")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM record_checksums")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM quarantined_records")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM usage_stats")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM contacts")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM contact_lists")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM nip05_verifications")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM group_notes")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM group_custom_data")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM blocked_users")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM unblocked_users")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM key_package_consumptions")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM rotated_key_packages")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM group_media")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM group_media_backfills")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM recovery_shares")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM recovery_requests")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM account_recoveries")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM released_recovery_shares")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM pending_welcomes")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM quarantined_events")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM join_requests")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM invite_links")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM group_event_log")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM group_event_log_baselines")
+            .execute(&mut *txn)
+            .await?;
         sqlx::query("DELETE FROM groups").execute(&mut *txn).await?;
+        sqlx::query("DELETE FROM synced_settings")
+            .execute(&mut *txn)
+            .await?;
         sqlx::query("DELETE FROM account_relays")
             .execute(&mut *txn)
             .await?;
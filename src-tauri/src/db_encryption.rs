@@ -0,0 +1,224 @@
+//! At-rest encryption of the local database.
+//!
+//! The database holding accounts, groups and message transcripts can be encrypted with
+//! SQLCipher. The key is a random 256-bit secret kept in the OS keychain (see
+//! `secrets_store::store_database_key`), never in the secrets file next to the database, so
+//! encryption is only offered where a keychain is available.
+//!
+//! Encryption is opt-in: [`encrypt`] converts an existing plaintext database, after which the app
+//! restarts to reopen it with the key. A user passphrase isn't supported since the database is
+//! opened at startup, before the frontend could ask for one.
+//!
+//! MLS group state isn't covered: nostr-openmls keeps it in its own files under `mls_storage`
+//! and has no way to open them with a key.
+
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use rand::RngCore;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// The first bytes of every plaintext SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+#[derive(Error, Debug)]
+pub enum DbEncryptionError {
+    #[error("Database is already encrypted")]
+    AlreadyEncrypted,
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("File system error: {0}")]
+    FileSystem(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, DbEncryptionError>;
+
+/// Whether the database file at `db_path` is a plaintext SQLite database. Missing files aren't.
+pub fn is_plaintext(db_path: &Path) -> Result<bool> {
+    let mut file = match fs::File::open(db_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(header == SQLITE_HEADER),
+        // Empty databases haven't been written yet, with or without a key
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The key to open the database at `db_path` with, or `None` if it isn't encrypted.
+///
+/// A plaintext file always opens without a key, so a key stored by an [`encrypt`] that was
+/// interrupted before the encrypted copy replaced the database is ignored.
+pub fn key_for(db_path: &Path, data_dir: &Path) -> Result<Option<Zeroizing<String>>> {
+    if is_plaintext(db_path)? {
+        return Ok(None);
+    }
+    Ok(secrets_store::get_database_key(data_dir)?)
+}
+
+/// The value of SQLCipher's `key` pragma for a hex encoded raw key
+pub fn key_pragma(key: &str) -> String {
+    format!("\"x'{}'\"", key)
+}
+
+/// Path of a file next to the database, named after it
+fn sibling(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = db_path.to_path_buf().into_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Overwrites a file with zeros before removing it, so the plaintext it held doesn't linger in
+/// its freed blocks. Storage that remaps writes (SSDs, copy-on-write file systems) may still
+/// keep old blocks around, which only full-disk encryption rules out.
+fn scrub(path: &Path) -> Result<()> {
+    let mut file = match fs::OpenOptions::new().write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let zeros = [0u8; 64 * 1024];
+    let mut remaining = file.metadata()?.len();
+    while remaining > 0 {
+        let chunk = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Scrubs the plaintext copy an interrupted [`encrypt`] left behind. Called at startup, before
+/// the database is opened.
+pub fn scrub_leftovers(db_path: &Path) -> Result<()> {
+    scrub(&sibling(db_path, ".plaintext"))
+}
+
+/// Encrypts the plaintext database into a copy and swaps it in.
+///
+/// The connection pool is closed first, so nothing can be written to the plaintext database after
+/// it's copied; the app has to restart to reopen the database, whether or not this succeeds. The
+/// key is stored before the copy is made and the copy only replaces the database once it's
+/// complete, so an interruption leaves the plaintext database in use. The plaintext file is then
+/// overwritten before it's removed.
+pub async fn encrypt(wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    let db_path = wn.database.path.clone();
+    if !is_plaintext(&db_path)? {
+        return Err(DbEncryptionError::AlreadyEncrypted);
+    }
+
+    let key = match secrets_store::get_database_key(&wn.data_dir)? {
+        Some(key) => key,
+        None => {
+            let mut key = [0u8; 32];
+            rand::rng().fill_bytes(&mut key);
+            let key = Zeroizing::new(hex::encode(key));
+            secrets_store::store_database_key(&key, &wn.data_dir)?;
+            key
+        }
+    };
+
+    // Closing the last connection also checkpoints the WAL into the plaintext file
+    wn.database.pool.close().await;
+
+    let encrypted_path = sibling(&db_path, ".encrypting");
+    if encrypted_path.exists() {
+        fs::remove_file(&encrypted_path)?;
+    }
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(&db_path)
+        .connect()
+        .await?;
+    sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+        .bind(encrypted_path.to_string_lossy().to_string())
+        .bind(format!("x'{}'", key.as_str()))
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("DETACH DATABASE encrypted")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+    fs::File::open(&encrypted_path)?.sync_all()?;
+
+    // Keep a link to the plaintext file so it can be scrubbed once the encrypted copy replaced it
+    let plaintext_path = sibling(&db_path, ".plaintext");
+    scrub(&plaintext_path)?;
+    fs::hard_link(&db_path, &plaintext_path)?;
+    fs::rename(&encrypted_path, &db_path)?;
+    scrub(&plaintext_path)?;
+    for suffix in ["-wal", "-shm"] {
+        scrub(&sibling(&db_path, suffix))?;
+    }
+
+    tracing::info!(
+        target: "whitenoise::db_encryption::encrypt",
+        "Encrypted database at {:?}",
+        db_path
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_plaintext() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test.sqlite");
+        assert!(!is_plaintext(&path)?);
+
+        fs::write(&path, b"")?;
+        assert!(!is_plaintext(&path)?);
+
+        fs::write(&path, [SQLITE_HEADER, &[0u8; 84]].concat())?;
+        assert!(is_plaintext(&path)?);
+
+        fs::write(&path, [0xa5u8; 100])?;
+        assert!(!is_plaintext(&path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_for_plaintext_database() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test.sqlite");
+        fs::write(&path, [SQLITE_HEADER, &[0u8; 84]].concat())?;
+        assert_eq!(key_for(&path, temp_dir.path())?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrub_leftovers() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("test.sqlite");
+        scrub_leftovers(&path)?;
+
+        let leftover = sibling(&path, ".plaintext");
+        fs::write(&leftover, [SQLITE_HEADER, &[0u8; 84]].concat())?;
+        scrub_leftovers(&path)?;
+        assert!(!leftover.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+//! In-process Nostr relay for development and tests.
+//!
+//! Only built with the `dev-relay` feature. The relay keeps its events in memory and listens on
+//! localhost, by default on the port of the nostr-rs-relay from `docker-compose.yml`, so it can
+//! stand in for it: the dev relays the app connects to already include it. It runs until the app
+//! exits.
+
+use nostr_relay_builder::{Error, LocalRelay, RelayBuilder};
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+/// Port the relay listens on unless another one is given, that of the Docker dev relay
+pub const DEFAULT_DEV_RELAY_PORT: u16 = 8080;
+
+static RELAY: Lazy<Mutex<Option<LocalRelay>>> = Lazy::new(|| Mutex::new(None));
+
+/// Starts the relay, unless it's already running
+///
+/// # Returns
+/// * `Ok(String)` - The relay's URL. If the relay was already running, that's the URL it was
+///   started with, whatever `port` is.
+pub async fn start(port: u16) -> Result<String, Error> {
+    let mut relay = RELAY.lock().await;
+    if let Some(relay) = relay.as_ref() {
+        return Ok(relay.url().to_string());
+    }
+
+    let started = LocalRelay::run(RelayBuilder::default().port(port)).await?;
+    let url = started.url().to_string();
+    tracing::info!(
+        target: "whitenoise::dev_relay::start",
+        "Started embedded dev relay at {}",
+        url
+    );
+    *relay = Some(started);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr_sdk::prelude::*;
+    use std::time::Duration;
+
+    /// Port the tests run the relay on, away from the Docker dev relay's
+    const TEST_PORT: u16 = 18_765;
+
+    #[tokio::test]
+    async fn test_relay_stores_published_events() {
+        let url = start(TEST_PORT).await.unwrap();
+        assert_eq!(start(DEFAULT_DEV_RELAY_PORT).await.unwrap(), url);
+
+        let keys = Keys::generate();
+        let client = Client::new(keys.clone());
+        client.add_relay(&url).await.unwrap();
+        client.connect().await;
+
+        let output = client
+            .send_event_builder(EventBuilder::text_note("hello"))
+            .await
+            .unwrap();
+        assert!(!output.success.is_empty());
+
+        let events = client
+            .fetch_events(
+                Filter::new().author(keys.public_key()),
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        assert!(events.iter().any(|event| event.id == *output.id()));
+    }
+}
@@ -0,0 +1,445 @@
+//! Multi-device sync of group state.
+//!
+//! Every device keeps its own MLS state, so running an account on two devices forks it: each
+//! device only knows the groups it joined itself. With device sync enabled (see
+//! `set_device_sync`) a device shares the changes the others need as deltas, gift-wrapped to the
+//! account's own pubkey: the groups it joins, the export secrets of new epochs, how far the
+//! user has read each group and the settings they change (see `settings_sync`). The other devices
+//! unwrap them in the giftwrap path and reconcile them with [`apply`], which is enough for them
+//! to clear the unread badge of chats read elsewhere and follow the user's settings.
+//!
+//! MLS state isn't shared: a group can only be read on a device that has its own MLS state for
+//! it. Groups a device has no MLS state for are skipped rather than listed unreadable.
+//!
+//! Deltas carry the random ID of the device that sent them so a device skips its own.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{self, Group, GroupError};
+use crate::nostr_manager::NostrManagerError;
+use crate::relay_blacklist::{self, RelayBlacklistError};
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::settings_sync::{self, SettingChange, SettingsSyncError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// The rumor kind of device sync messages
+pub const DEVICE_SYNC_KIND: u16 = 1777;
+
+#[derive(Error, Debug)]
+pub enum DeviceSyncError {
+    #[error("Invalid sync message: {0}")]
+    InvalidMessage(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("Settings sync error: {0}")]
+    SettingsSyncError(#[from] SettingsSyncError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+
+    #[error("Nostr event error: {0}")]
+    NostrEventError(#[from] nostr_sdk::event::builder::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, DeviceSyncError>;
+
+/// A change to the account's group state that other devices should apply
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncDelta {
+    /// The device joined or created a group
+    GroupJoined { group: Group, relays: Vec<String> },
+    /// The export secret of a group epoch
+    EpochSecret {
+        /// Hex encoded MLS group ID
+        mls_group_id: String,
+        epoch: u64,
+        /// Hex encoded secret
+        secret: String,
+    },
+    /// The user read a group up to a message
+    ReadMarker {
+        /// Hex encoded MLS group ID
+        mls_group_id: String,
+        /// Hex encoded event ID of the latest message read
+        event_id: String,
+        /// When that message was created, so the marker applies before the message arrives
+        created_at: Timestamp,
+    },
+    /// The user changed synced settings
+    Settings { changes: Vec<SettingChange> },
+}
+
+/// The content of a device sync rumor
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncMessage {
+    /// The device that sent the deltas
+    pub device_id: String,
+    pub deltas: Vec<SyncDelta>,
+}
+
+/// The deltas that bring another device up to date on a group: the group itself and every
+/// export secret stored for it, or the current one if none are stored yet
+pub async fn group_deltas(
+    group: &Group,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<SyncDelta>> {
+    let mut deltas = vec![SyncDelta::GroupJoined {
+        group: group.clone(),
+        relays: group.relays(wn.clone()).await?,
+    }];
+
+    let mut export_secrets =
+        secrets_store::get_export_secrets_for_group(&group.mls_group_id, &wn.data_dir)?;
+    if export_secrets.is_empty() {
+        // Freshly created and joined groups don't have their secret stored until it's first used
+        let (secret, epoch) = wn
+            .nostr_mls
+            .lock()
+            .await
+            .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())
+            .map_err(GroupError::MlsError)?;
+        secrets_store::store_mls_export_secret(
+            group.mls_group_id.clone(),
+            epoch,
+            secret.clone(),
+            &wn.data_dir,
+        )?;
+        export_secrets.push((epoch, Zeroizing::new(secret)));
+    }
+
+    for (epoch, secret) in export_secrets {
+        deltas.push(SyncDelta::EpochSecret {
+            mls_group_id: hex::encode(&group.mls_group_id),
+            epoch,
+            secret: secret.to_string(),
+        });
+    }
+    Ok(deltas)
+}
+
+/// Gift-wraps deltas to the active account's own pubkey and publishes them, leaving out
+/// blacklisted relays. Does nothing unless the account has device sync enabled.
+pub async fn publish(deltas: Vec<SyncDelta>, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    let account = Account::get_active(wn.clone()).await?;
+    if !account.settings.device_sync || deltas.is_empty() {
+        return Ok(());
+    }
+
+    let message = SyncMessage {
+        device_id: secrets_store::get_device_id(&wn.data_dir),
+        deltas,
+    };
+    let rumor = EventBuilder::new(
+        Kind::Custom(DEVICE_SYNC_KIND),
+        serde_json::to_string(&message)?,
+    )
+    .build(account.pubkey);
+
+    let signer = wn.nostr.client.signer().await?;
+    let wrapped = EventBuilder::gift_wrap(&signer, &account.pubkey, rumor, vec![]).await?;
+    relay_blacklist::send_event(&wrapped, vec![], wn.clone()).await?;
+
+    tracing::debug!(
+        target: "whitenoise::device_sync::publish",
+        "Published {} deltas to other devices",
+        message.deltas.len()
+    );
+    Ok(())
+}
+
+/// Publishes deltas, logging failures instead of returning them so that syncing can never fail
+/// the operation that produced the deltas
+pub async fn share(deltas: Vec<SyncDelta>, wn: tauri::State<'_, Whitenoise>) {
+    if let Err(e) = publish(deltas, wn).await {
+        tracing::error!(
+            target: "whitenoise::device_sync::share",
+            "Failed to share state with other devices: {}",
+            e
+        );
+    }
+}
+
+/// Shares a group and its export secrets with the account's other devices
+pub async fn share_group(group: &Group, wn: tauri::State<'_, Whitenoise>) {
+    match group_deltas(group, wn.clone()).await {
+        Ok(deltas) => share(deltas, wn).await,
+        Err(e) => tracing::error!(
+            target: "whitenoise::device_sync::share_group",
+            "Failed to collect group state to share: {}",
+            e
+        ),
+    }
+}
+
+/// Shares how far the user has read a group with the account's other devices
+pub async fn share_read_marker(group: &Group, wn: tauri::State<'_, Whitenoise>) {
+    let (Some(event_id), Some(created_at)) = (
+        group.last_read_message_id.clone(),
+        group.last_read_message_at,
+    ) else {
+        return;
+    };
+    share(
+        vec![SyncDelta::ReadMarker {
+            mls_group_id: hex::encode(&group.mls_group_id),
+            event_id,
+            created_at,
+        }],
+        wn,
+    )
+    .await;
+}
+
+/// Parses a device sync rumor. Returns `None` for messages sent by this device.
+pub fn parse(rumor: &UnsignedEvent, device_id: &str) -> Result<Option<SyncMessage>> {
+    if rumor.kind != Kind::Custom(DEVICE_SYNC_KIND) {
+        return Err(DeviceSyncError::InvalidMessage(format!(
+            "Unexpected kind {}",
+            rumor.kind
+        )));
+    }
+    let message: SyncMessage = serde_json::from_str(&rumor.content)
+        .map_err(|e| DeviceSyncError::InvalidMessage(e.to_string()))?;
+    if message.device_id == device_id {
+        return Ok(None);
+    }
+    Ok(Some(message))
+}
+
+/// Reconciles a device sync rumor from another device with this device's state.
+///
+/// Export secrets that aren't stored yet are added. Groups that are missing are added and
+/// subscribed to if this device has MLS state for them, and `group_added` is emitted for them;
+/// groups this device already knows or can't read are left out. Read markers only move the read
+/// cursor of the account's own copy of a group forward; `group_updated` is emitted for groups
+/// whose cursor moved, followed by the account's `unread_total_changed`. Settings are merged with
+/// [`settings_sync::merge`]. Deltas for other accounts are ignored.
+pub async fn apply(
+    rumor: &UnsignedEvent,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let account = Account::get_active(wn.clone()).await?;
+    if rumor.pubkey != account.pubkey {
+        return Err(DeviceSyncError::InvalidMessage(
+            "Sync message from another account".to_string(),
+        ));
+    }
+    let Some(message) = parse(rumor, &secrets_store::get_device_id(&wn.data_dir))? else {
+        return Ok(());
+    };
+
+    let mut joined_groups = false;
+    let mut read_groups = false;
+    for delta in message.deltas {
+        match delta {
+            SyncDelta::EpochSecret {
+                mls_group_id,
+                epoch,
+                secret,
+            } => {
+                let Ok(mls_group_id) = hex::decode(&mls_group_id) else {
+                    continue;
+                };
+                if secrets_store::get_export_secret_keys_for_group(
+                    mls_group_id.clone(),
+                    epoch,
+                    &wn.data_dir,
+                )
+                .is_err()
+                {
+                    secrets_store::store_mls_export_secret(
+                        mls_group_id,
+                        epoch,
+                        secret,
+                        &wn.data_dir,
+                    )?;
+                }
+            }
+            SyncDelta::GroupJoined { group, relays } => {
+                if group.account_pubkey != account.pubkey
+                    || Group::find_by_mls_group_id_for_account(
+                        &group.mls_group_id,
+                        &account.pubkey,
+                        wn.clone(),
+                    )
+                    .await
+                    .is_ok()
+                {
+                    continue;
+                }
+                let has_mls_state = {
+                    let nostr_mls = wn.nostr_mls.lock().await;
+                    groups::load_mls_group(&nostr_mls, &group.mls_group_id).is_ok()
+                };
+                if !has_mls_state {
+                    tracing::info!(
+                        target: "whitenoise::device_sync::apply",
+                        "Skipping group {} from device {}: no MLS state on this device",
+                        hex::encode(&group.mls_group_id),
+                        message.device_id
+                    );
+                    continue;
+                }
+                let group = group.save(wn.clone()).await?;
+                for relay in relays {
+                    sqlx::query("INSERT OR REPLACE INTO group_relays (url, relay_type, account_pubkey, group_id) VALUES (?, ?, ?, ?)")
+                        .bind(relay)
+                        .bind("group")
+                        .bind(account.pubkey.to_hex())
+                        .bind(&group.mls_group_id)
+                        .execute(&wn.database.pool)
+                        .await?;
+                }
+                tracing::info!(
+                    target: "whitenoise::device_sync::apply",
+                    "Added group {} from device {}",
+                    hex::encode(&group.mls_group_id),
+                    message.device_id
+                );
+                app_handle.emit("group_added", group)?;
+                joined_groups = true;
+            }
+            SyncDelta::ReadMarker {
+                mls_group_id,
+                event_id,
+                created_at,
+            } => {
+                let (Ok(mls_group_id), Ok(event_id)) =
+                    (hex::decode(&mls_group_id), EventId::from_hex(&event_id))
+                else {
+                    continue;
+                };
+                // Other accounts on this device can be in the same group
+                let Ok(mut group) = Group::find_by_mls_group_id_for_account(
+                    &mls_group_id,
+                    &account.pubkey,
+                    wn.clone(),
+                )
+                .await
+                else {
+                    continue;
+                };
+                if group
+                    .move_read_cursor(&event_id, created_at, wn.clone())
+                    .await?
+                {
+                    app_handle.emit("group_updated", group)?;
+                    read_groups = true;
+                }
+            }
+            SyncDelta::Settings { changes } => {
+                settings_sync::merge(&account, changes, wn.clone(), app_handle).await?;
+            }
+        }
+    }
+
+    if read_groups {
+        let unread_total: u64 = Group::unread_counts_for_account(&account.pubkey, wn.clone())
+            .await?
+            .values()
+            .sum();
+        app_handle.emit("unread_total_changed", unread_total)?;
+    }
+
+    if joined_groups {
+        wn.nostr
+            .subscribe_mls_group_messages(account.nostr_group_ids(wn.clone()).await?)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rumor(content: &str) -> UnsignedEvent {
+        EventBuilder::new(Kind::Custom(DEVICE_SYNC_KIND), content)
+            .build(Keys::generate().public_key())
+    }
+
+    fn message(device_id: &str) -> String {
+        serde_json::to_string(&SyncMessage {
+            device_id: device_id.to_string(),
+            deltas: vec![SyncDelta::EpochSecret {
+                mls_group_id: "00ff".to_string(),
+                epoch: 3,
+                secret: "ab".repeat(32),
+            }],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_message_from_other_device() {
+        let parsed = parse(&rumor(&message("laptop")), "phone").unwrap().unwrap();
+        assert_eq!(parsed.device_id, "laptop");
+        assert!(matches!(
+            parsed.deltas.as_slice(),
+            [SyncDelta::EpochSecret { epoch: 3, .. }]
+        ));
+    }
+
+    #[test]
+    fn test_parse_skips_own_messages() {
+        assert!(parse(&rumor(&message("phone")), "phone").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_messages() {
+        assert!(parse(&rumor("not json"), "phone").is_err());
+
+        let wrong_kind = EventBuilder::new(Kind::TextNote, message("laptop"))
+            .build(Keys::generate().public_key());
+        assert!(parse(&wrong_kind, "phone").is_err());
+    }
+
+    #[test]
+    fn test_delta_serialization_is_tagged() {
+        let json = serde_json::to_value(SyncDelta::EpochSecret {
+            mls_group_id: "00ff".to_string(),
+            epoch: 1,
+            secret: String::new(),
+        })
+        .unwrap();
+        assert_eq!(json["type"], "epoch_secret");
+
+        let json = serde_json::to_value(SyncDelta::ReadMarker {
+            mls_group_id: "00ff".to_string(),
+            event_id: EventId::all_zeros().to_hex(),
+            created_at: Timestamp::from(1_700_000_000),
+        })
+        .unwrap();
+        assert_eq!(json["type"], "read_marker");
+        assert_eq!(json["created_at"], 1_700_000_000);
+    }
+}
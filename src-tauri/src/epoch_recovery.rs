@@ -0,0 +1,396 @@
+//! Recovery from group messages that don't decrypt with the expected epoch's export secret.
+//!
+//! Group message events are NIP-44 encrypted with the export secret of the epoch they were sent
+//! in. When our view of the group's epoch is off, e.g. because another of the account's devices
+//! moved ahead or a commit arrived out of order, the expected secret doesn't decrypt them.
+//! [`decrypt`] tries the current epoch's secret, re-deriving it from nostr_openmls if it isn't
+//! stored yet, then the stored secrets of the epochs around it, as far back as the account's
+//! retention window. The event doesn't say which epoch it was sent in.
+//!
+//! Secrets of epochs older than each account's retention window are pruned with every group
+//! message sync, see [`prune_all_secrets`].
+//!
+//! Events none of those decrypt are quarantined rather than dropped, and so are events that
+//! decrypt but belong to an epoch MLS hasn't reached, e.g. because the commit that leads to it
+//! hasn't arrived yet. Only their IDs are kept; the events themselves stay in the nostr database.
+//! [`retry`] runs them through the event processor again with every group message sync and when
+//! their account becomes active, since a secret or commit that arrived since may let them
+//! through. Events that still fail after [`MAX_RETRY_ATTEMPTS`] are marked as failed and
+//! released. The quarantine holds at most [`MAX_QUARANTINED_PER_GROUP`] events per group and
+//! [`MAX_QUARANTINED_PER_ACCOUNT`] per account; events beyond that are marked as failed right
+//! away.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError, GroupState};
+use crate::messages::{MessageError, ProcessedMessage, ProcessedMessageState};
+use crate::nostr_manager::event_processor::EventProcessor;
+use crate::profiling::{self, OperationKind};
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// How many epochs before the current one export secrets are kept for by default
+pub const DEFAULT_EXPORT_SECRET_RETENTION_EPOCHS: u32 = 20;
+
+/// Bounds of the export secret retention setting
+pub const MIN_EXPORT_SECRET_RETENTION_EPOCHS: u32 = 5;
+pub const MAX_EXPORT_SECRET_RETENTION_EPOCHS: u32 = 1000;
+
+/// How many times a quarantined event is retried before it's given up on
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// Most events quarantined for a single group
+pub const MAX_QUARANTINED_PER_GROUP: u32 = 100;
+
+/// Most events quarantined for a single account, across its groups
+pub const MAX_QUARANTINED_PER_ACCOUNT: u32 = 500;
+
+#[derive(Error, Debug)]
+pub enum EpochRecoveryError {
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Message error: {0}")]
+    MessageError(#[from] MessageError),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Nostr database error: {0}")]
+    NostrDatabaseError(#[from] nostr_sdk::database::DatabaseError),
+}
+
+pub type Result<T> = std::result::Result<T, EpochRecoveryError>;
+
+#[derive(Debug, sqlx::FromRow)]
+struct QuarantinedEventRow {
+    event_id: String,
+    reason: String,
+    attempts: i64,
+}
+
+/// The stored epochs whose secrets to try on a message the current epoch's secret doesn't decrypt:
+/// the ones from `retention_epochs` before the current epoch on, closest first and later before
+/// earlier at the same distance. Later epochs are tried however far ahead they are, since another
+/// of the account's devices may have moved the group ahead.
+fn candidate_epochs(
+    current_epoch: u64,
+    retention_epochs: u32,
+    stored_epochs: impl IntoIterator<Item = u64>,
+) -> Vec<u64> {
+    let oldest_epoch = current_epoch.saturating_sub(retention_epochs as u64);
+    let mut epochs: Vec<u64> = stored_epochs
+        .into_iter()
+        .filter(|epoch| *epoch != current_epoch && *epoch >= oldest_epoch)
+        .collect();
+    epochs.sort_by_key(|epoch| (epoch.abs_diff(current_epoch), Reverse(*epoch)));
+    epochs
+}
+
+fn decrypt_with_secret(secret: &str, event: &Event) -> Option<Zeroizing<Vec<u8>>> {
+    let keys = Keys::parse(secret).ok()?;
+    profiling::time("nip44.decrypt", OperationKind::Crypto, || {
+        nip44::decrypt_to_bytes(keys.secret_key(), &keys.public_key(), &event.content)
+    })
+    .ok()
+    .map(Zeroizing::new)
+}
+
+/// Decrypts a group message event with the export secret of whichever epoch it was sent in
+///
+/// # Returns
+/// * `Ok(Some(Zeroizing<Vec<u8>>))` - The serialized MLS message
+/// * `Ok(None)` - If none of the secrets decrypt the event
+pub async fn decrypt(
+    group: &Group,
+    event: &Event,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    let (current_secret, current_epoch) = wn
+        .nostr_mls
+        .lock()
+        .await
+        .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())
+        .map_err(GroupError::MlsError)?;
+    let current_secret = Zeroizing::new(current_secret);
+
+    if secrets_store::get_secret_for_epoch(&group.mls_group_id, current_epoch, &wn.data_dir)?
+        .is_none()
+    {
+        tracing::debug!(
+            target: "whitenoise::epoch_recovery::decrypt",
+            "No export secret stored for epoch {}, storing the one from nostr_openmls",
+            current_epoch
+        );
+        group
+            .store_epoch_secret(current_epoch, current_secret.to_string(), wn.clone())
+            .await?;
+    }
+
+    if let Some(decrypted) = decrypt_with_secret(&current_secret, event) {
+        return Ok(Some(decrypted));
+    }
+
+    let retention_epochs = Account::find_by_pubkey(&group.account_pubkey, wn.clone())
+        .await?
+        .settings
+        .export_secret_retention_epochs;
+    let stored: HashMap<u64, Zeroizing<String>> =
+        secrets_store::get_export_secrets_for_group(&group.mls_group_id, &wn.data_dir)?
+            .into_iter()
+            .collect();
+    for epoch in candidate_epochs(current_epoch, retention_epochs, stored.keys().copied()) {
+        if let Some(decrypted) = decrypt_with_secret(&stored[&epoch], event) {
+            tracing::debug!(
+                target: "whitenoise::epoch_recovery::decrypt",
+                "Decrypted message {} with the secret of epoch {} (current epoch is {})",
+                event.id,
+                epoch,
+                current_epoch
+            );
+            return Ok(Some(decrypted));
+        }
+    }
+    Ok(None)
+}
+
+/// Prunes the export secrets of every account's active groups, keeping as many epochs before the
+/// latest stored one as the group's account's retention setting. Secrets of inactive groups are
+/// left alone.
+///
+/// # Returns
+/// * `Ok(usize)` - How many secrets were removed
+pub async fn prune_all_secrets(wn: tauri::State<'_, Whitenoise>) -> Result<usize> {
+    let mut retention_epochs = HashMap::new();
+    for account in Account::all_including_hidden(wn.clone()).await? {
+        let epochs = account.settings.export_secret_retention_epochs as u64;
+        for group in account.groups(wn.clone()).await? {
+            if matches!(group.state, GroupState::Active) {
+                retention_epochs.insert(group.mls_group_id, epochs);
+            }
+        }
+    }
+    let pruned = secrets_store::prune_export_secrets(&retention_epochs, &wn.data_dir)?;
+    if pruned > 0 {
+        tracing::info!(
+            target: "whitenoise::epoch_recovery::prune_all_secrets",
+            "Pruned {} expired export secrets",
+            pruned
+        );
+    }
+    Ok(pruned)
+}
+
+/// Whether an error from processing a decrypted group message means it belongs to an epoch the
+/// group's MLS state isn't at, so it may process once the missing commits arrive
+pub fn is_epoch_mismatch(error: &str) -> bool {
+    error.to_lowercase().contains("epoch")
+}
+
+/// Keeps an event that couldn't be processed for a later retry, or counts another failed attempt
+///
+/// # Returns
+/// * `Ok(true)` - If the event is quarantined
+/// * `Ok(false)` - If the quarantine of the group or its account is full, in which case the
+///   event is marked as failed
+pub async fn quarantine(
+    group: &Group,
+    event: &Event,
+    reason: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<bool> {
+    let now = Timestamp::now().as_u64() as i64;
+    let retried = sqlx::query(
+        "UPDATE quarantined_events SET reason = ?, attempts = attempts + 1, updated_at = ?
+         WHERE account_pubkey = ? AND event_id = ?",
+    )
+    .bind(reason)
+    .bind(now)
+    .bind(group.account_pubkey.to_hex())
+    .bind(event.id.to_hex())
+    .execute(&wn.database.pool)
+    .await?;
+    if retried.rows_affected() > 0 {
+        return Ok(true);
+    }
+
+    let quarantined = sqlx::query(
+        "INSERT INTO quarantined_events
+             (account_pubkey, event_id, mls_group_id, reason, attempts, quarantined_at, updated_at)
+         SELECT ?1, ?2, ?3, ?4, 1, ?5, ?5
+         WHERE (SELECT COUNT(*) FROM quarantined_events
+                WHERE account_pubkey = ?1 AND mls_group_id = ?3) < ?6
+           AND (SELECT COUNT(*) FROM quarantined_events WHERE account_pubkey = ?1) < ?7",
+    )
+    .bind(group.account_pubkey.to_hex())
+    .bind(event.id.to_hex())
+    .bind(&group.mls_group_id)
+    .bind(reason)
+    .bind(now)
+    .bind(MAX_QUARANTINED_PER_GROUP)
+    .bind(MAX_QUARANTINED_PER_ACCOUNT)
+    .execute(&wn.database.pool)
+    .await?;
+    if quarantined.rows_affected() > 0 {
+        return Ok(true);
+    }
+
+    tracing::warn!(
+        target: "whitenoise::epoch_recovery::quarantine",
+        "Quarantine of group {} is full, giving up on message {}",
+        hex::encode(&group.mls_group_id),
+        event.id
+    );
+    ProcessedMessage::create_with_state_and_reason(
+        event.id,
+        None,
+        ProcessedMessageState::Failed,
+        reason.to_string(),
+        wn.clone(),
+    )
+    .await?;
+    Ok(false)
+}
+
+async fn release(
+    account: &Account,
+    event_id: EventId,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    sqlx::query("DELETE FROM quarantined_events WHERE account_pubkey = ? AND event_id = ?")
+        .bind(account.pubkey.to_hex())
+        .bind(event_id.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+    Ok(())
+}
+
+/// Runs the active account's quarantined events through the event processor again, oldest first
+///
+/// Processing needs the account's MLS state, which is only loaded for the active account, so
+/// other accounts' events wait until their account becomes active; retrying stops early if the
+/// active account changes meanwhile. An event is released once it's been processed, successfully
+/// or not. Events still quarantined after [`MAX_RETRY_ATTEMPTS`] attempts, or no longer in the
+/// nostr database, are marked as failed and released too.
+///
+/// # Returns
+/// * `Ok(usize)` - How many events were released
+pub async fn retry(app_handle: &AppHandle) -> Result<usize> {
+    let wn = app_handle.state::<Whitenoise>();
+    let account = Account::get_active(wn.clone()).await?;
+    let rows = sqlx::query_as::<_, QuarantinedEventRow>(
+        "SELECT event_id, reason, attempts FROM quarantined_events WHERE account_pubkey = ?
+         ORDER BY quarantined_at",
+    )
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let mut released = 0;
+    for row in rows {
+        if Account::get_active_pubkey(wn.clone()).await? != account.pubkey {
+            break;
+        }
+        let Ok(event_id) = EventId::from_hex(&row.event_id) else {
+            sqlx::query("DELETE FROM quarantined_events WHERE account_pubkey = ? AND event_id = ?")
+                .bind(account.pubkey.to_hex())
+                .bind(&row.event_id)
+                .execute(&wn.database.pool)
+                .await?;
+            continue;
+        };
+        let Some(event) = wn.nostr.client.database().event_by_id(&event_id).await? else {
+            ProcessedMessage::create_with_state_and_reason(
+                event_id,
+                None,
+                ProcessedMessageState::Failed,
+                format!("{} (no longer stored for a retry)", row.reason),
+                wn.clone(),
+            )
+            .await?;
+            release(&account, event_id, wn.clone()).await?;
+            released += 1;
+            continue;
+        };
+        if let Err(e) = EventProcessor::process_mls_message(app_handle, event).await {
+            tracing::warn!(
+                target: "whitenoise::epoch_recovery::retry",
+                "Failed to reprocess quarantined message {}: {}",
+                event_id,
+                e
+            );
+        }
+
+        if ProcessedMessage::find_by_event_id(event_id, wn.clone())
+            .await?
+            .is_some()
+        {
+            release(&account, event_id, wn.clone()).await?;
+            released += 1;
+        } else if row.attempts as u32 >= MAX_RETRY_ATTEMPTS {
+            ProcessedMessage::create_with_state_and_reason(
+                event_id,
+                None,
+                ProcessedMessageState::Failed,
+                row.reason,
+                wn.clone(),
+            )
+            .await?;
+            release(&account, event_id, wn.clone()).await?;
+            released += 1;
+        }
+    }
+
+    if released > 0 {
+        tracing::info!(
+            target: "whitenoise::epoch_recovery::retry",
+            "Released {} quarantined messages",
+            released
+        );
+    }
+    Ok(released)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_epoch_mismatch() {
+        assert!(is_epoch_mismatch("Wrong Epoch."));
+        assert!(is_epoch_mismatch(
+            "The message epoch is too far in the future"
+        ));
+        assert!(!is_epoch_mismatch("Cannot decrypt own messages"));
+    }
+
+    #[test]
+    fn test_current_epoch_is_not_tried_again() {
+        assert_eq!(candidate_epochs(10, 20, [10]), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_stored_epochs_are_tried_closest_first() {
+        assert_eq!(
+            candidate_epochs(10, 20, [2, 5, 9, 11, 12, 30]),
+            vec![11, 9, 12, 5, 2, 30]
+        );
+    }
+
+    #[test]
+    fn test_epochs_before_the_retention_window_are_skipped() {
+        assert_eq!(candidate_epochs(30, 5, [20, 25, 26, 31]), vec![31, 26, 25]);
+        assert_eq!(candidate_epochs(1, 5, [0, 2]), vec![2, 0]);
+    }
+}
@@ -0,0 +1,805 @@
+//! Errors returned by Tauri commands.
+//!
+//! Commands used to return plain strings, leaving the frontend no way to tell a relay that can't
+//! be reached from an invalid key but matching on messages. [`WhitenoiseError`] serializes as
+//! `{ code, message, retryable }`: `code` names the kind of failure, `message` keeps the human
+//! readable context and `retryable` hints whether trying again later could succeed.
+//!
+//! Module errors convert by what went wrong (see the `From` impls), and [`ErrorContext::context`]
+//! prefixes the message with what the command was doing. Strings convert to
+//! [`WhitenoiseError::Internal`], so `map_err(|e| format!(...))?` keeps working for failures that
+//! don't need a code of their own.
+
+use crate::account_backup::AccountBackupError;
+use crate::accounts::AccountError;
+use crate::app_data::AppDataError;
+use crate::app_lock::AppLockError;
+use crate::background_refresh::BackgroundRefreshError;
+use crate::blocklist::BlocklistError;
+use crate::contacts::ContactError;
+use crate::content_filters::ContentFilterError;
+use crate::database::DatabaseError;
+use crate::db_encryption::DbEncryptionError;
+use crate::epoch_recovery::EpochRecoveryError;
+use crate::group_custom_data::GroupCustomDataError;
+use crate::group_notes::GroupNoteError;
+use crate::group_tasks::GroupTaskError;
+use crate::group_templates::GroupTemplateError;
+use crate::groups::GroupError;
+use crate::integrity::IntegrityError;
+use crate::invite_messages::InviteMessageError;
+use crate::invites::InviteError;
+use crate::key_migrations::KeyMigrationError;
+use crate::key_packages::KeyPackageError;
+use crate::media::MediaError;
+use crate::messages::MessageError;
+use crate::nip05::Nip05Error;
+use crate::nostr_manager::NostrManagerError;
+use crate::outbox::OutboxError;
+use crate::payments::PaymentError;
+use crate::pending_welcomes::PendingWelcomeError;
+use crate::quick_switcher::QuickSwitcherError;
+use crate::reactions::ReactionError;
+use crate::relay_blacklist::RelayBlacklistError;
+use crate::relay_failover::RelayFailoverError;
+use crate::relays::RelayError;
+use crate::secrets_store::SecretsStoreError;
+use crate::sensitive_actions::SensitiveActionError;
+use crate::sync_scheduler::SyncError;
+use crate::sync_throttle::SyncThrottleError;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WhitenoiseError {
+    /// An argument is malformed or not allowed, e.g. a group ID that isn't hex
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// A public or private key couldn't be parsed or doesn't match
+    #[error("{0}")]
+    InvalidKey(String),
+
+    /// The group, account, message or other record doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    NoActiveAccount(String),
+
+    /// The app is locked, see `unlock_app`
+    #[error("{0}")]
+    AppLocked(String),
+
+    /// A passphrase or confirmation token was missing or wrong
+    #[error("{0}")]
+    Unauthorized(String),
+
+    /// Relays couldn't be reached or didn't accept an event
+    #[error("{0}")]
+    RelayUnreachable(String),
+
+    /// The MLS group state rejected the operation
+    #[error("{0}")]
+    Mls(String),
+
+    /// The database or the secrets store failed
+    #[error("{0}")]
+    Storage(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+pub type Result<T> = std::result::Result<T, WhitenoiseError>;
+
+impl WhitenoiseError {
+    /// Stable identifier of the kind of failure, for the frontend to match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidInput(_) => "invalid_input",
+            Self::InvalidKey(_) => "invalid_key",
+            Self::NotFound(_) => "not_found",
+            Self::NoActiveAccount(_) => "no_active_account",
+            Self::AppLocked(_) => "app_locked",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::RelayUnreachable(_) => "relay_unreachable",
+            Self::Mls(_) => "mls",
+            Self::Storage(_) => "storage",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// Whether the same call could succeed if tried again later without changes
+    pub fn retryable(&self) -> bool {
+        matches!(self, Self::RelayUnreachable(_) | Self::AppLocked(_))
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::InvalidInput(message)
+            | Self::InvalidKey(message)
+            | Self::NotFound(message)
+            | Self::NoActiveAccount(message)
+            | Self::AppLocked(message)
+            | Self::Unauthorized(message)
+            | Self::RelayUnreachable(message)
+            | Self::Mls(message)
+            | Self::Storage(message)
+            | Self::Internal(message) => message,
+        }
+    }
+
+    /// Prefixes the message with what was being done, keeping the code
+    pub fn with_context(self, context: &str) -> Self {
+        self.map_message(|message| format!("{}: {}", context, message))
+    }
+
+    /// Takes the code of a wrapped error while keeping the message of the error wrapping it
+    pub(crate) fn wrapped_in(self, message: String) -> Self {
+        self.map_message(|_| message)
+    }
+
+    fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Self::InvalidInput(message) => Self::InvalidInput(f(message)),
+            Self::InvalidKey(message) => Self::InvalidKey(f(message)),
+            Self::NotFound(message) => Self::NotFound(f(message)),
+            Self::NoActiveAccount(message) => Self::NoActiveAccount(f(message)),
+            Self::AppLocked(message) => Self::AppLocked(f(message)),
+            Self::Unauthorized(message) => Self::Unauthorized(f(message)),
+            Self::RelayUnreachable(message) => Self::RelayUnreachable(f(message)),
+            Self::Mls(message) => Self::Mls(f(message)),
+            Self::Storage(message) => Self::Storage(f(message)),
+            Self::Internal(message) => Self::Internal(f(message)),
+        }
+    }
+}
+
+impl Serialize for WhitenoiseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("WhitenoiseError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.end()
+    }
+}
+
+/// Adds what a command was doing to the errors it returns
+pub trait ErrorContext<T> {
+    fn context(self, context: &str) -> Result<T>;
+}
+
+impl<T, E: Into<WhitenoiseError>> ErrorContext<T> for std::result::Result<T, E> {
+    fn context(self, context: &str) -> Result<T> {
+        self.map_err(|e| e.into().with_context(context))
+    }
+}
+
+impl From<String> for WhitenoiseError {
+    fn from(message: String) -> Self {
+        Self::Internal(message)
+    }
+}
+
+impl From<&str> for WhitenoiseError {
+    fn from(message: &str) -> Self {
+        Self::Internal(message.to_string())
+    }
+}
+
+impl From<nostr_sdk::key::Error> for WhitenoiseError {
+    fn from(e: nostr_sdk::key::Error) -> Self {
+        Self::InvalidKey(e.to_string())
+    }
+}
+
+impl From<nostr_sdk::SignerError> for WhitenoiseError {
+    fn from(e: nostr_sdk::SignerError) -> Self {
+        Self::Unauthorized(e.to_string())
+    }
+}
+
+impl From<nostr_sdk::client::Error> for WhitenoiseError {
+    fn from(e: nostr_sdk::client::Error) -> Self {
+        let message = e.to_string();
+        match e {
+            // Signing fails the same way however often it's tried
+            nostr_sdk::client::Error::Signer(_) | nostr_sdk::client::Error::SignerNotConfigured => {
+                Self::Unauthorized(message)
+            }
+            nostr_sdk::client::Error::Database(_) => Self::Storage(message),
+            nostr_sdk::client::Error::EventBuilder(_) | nostr_sdk::client::Error::Json(_) => {
+                Self::Internal(message)
+            }
+            _ => Self::RelayUnreachable(message),
+        }
+    }
+}
+
+impl From<tauri::Error> for WhitenoiseError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for WhitenoiseError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<nostr_sdk::database::DatabaseError> for WhitenoiseError {
+    fn from(e: nostr_sdk::database::DatabaseError) -> Self {
+        Self::Storage(e.to_string())
+    }
+}
+
+impl From<nostr_sdk::event::builder::Error> for WhitenoiseError {
+    fn from(e: nostr_sdk::event::builder::Error) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for WhitenoiseError {
+    fn from(e: sqlx::Error) -> Self {
+        let message = e.to_string();
+        match e {
+            sqlx::Error::RowNotFound => Self::NotFound(message),
+            _ => Self::Storage(message),
+        }
+    }
+}
+
+impl From<DatabaseError> for WhitenoiseError {
+    fn from(e: DatabaseError) -> Self {
+        let message = e.to_string();
+        match e {
+            DatabaseError::Sqlx(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Storage(message),
+        }
+    }
+}
+
+impl From<SecretsStoreError> for WhitenoiseError {
+    fn from(e: SecretsStoreError) -> Self {
+        let message = e.to_string();
+        match e {
+            SecretsStoreError::KeyError(e) => Self::from(e).wrapped_in(message),
+            SecretsStoreError::KeyNotFound => Self::NotFound(message),
+            _ => Self::Storage(message),
+        }
+    }
+}
+
+impl From<NostrManagerError> for WhitenoiseError {
+    fn from(e: NostrManagerError) -> Self {
+        let message = e.to_string();
+        match e {
+            NostrManagerError::Client(e) => Self::from(e).wrapped_in(message),
+            NostrManagerError::Database(e) => Self::from(e).wrapped_in(message),
+            NostrManagerError::SecretsStoreError(_) => Self::Storage(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<AccountError> for WhitenoiseError {
+    fn from(e: AccountError) -> Self {
+        let message = e.to_string();
+        match e {
+            AccountError::NoActiveAccount => Self::NoActiveAccount(message),
+            AccountError::AppLocked => Self::AppLocked(message),
+            AccountError::PublicKeyError(e) => Self::from(e).wrapped_in(message),
+            AccountError::DatabaseError(e) => Self::from(e).wrapped_in(message),
+            AccountError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            AccountError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            AccountError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<RelayError> for WhitenoiseError {
+    fn from(e: RelayError) -> Self {
+        let message = e.to_string();
+        match e {
+            RelayError::Blacklisted(_) | RelayError::InvalidUrl(_) | RelayError::NoGroupRelays => {
+                Self::InvalidInput(message)
+            }
+            RelayError::NoRelays => Self::RelayUnreachable(message),
+            RelayError::AccountError(e) => Self::from(e).wrapped_in(message),
+            RelayError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            RelayError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<RelayBlacklistError> for WhitenoiseError {
+    fn from(e: RelayBlacklistError) -> Self {
+        let message = e.to_string();
+        match e {
+            RelayBlacklistError::InvalidUrl(_) => Self::InvalidInput(message),
+            RelayBlacklistError::NoRelays => Self::RelayUnreachable(message),
+            RelayBlacklistError::AccountError(e) => Self::from(e).wrapped_in(message),
+            RelayBlacklistError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            RelayBlacklistError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<GroupError> for WhitenoiseError {
+    fn from(e: GroupError) -> Self {
+        let message = e.to_string();
+        match e {
+            GroupError::GroupNotFound => Self::NotFound(message),
+            GroupError::InvalidParameters(_) => Self::InvalidInput(message),
+            GroupError::MlsError(_)
+            | GroupError::MlsStorageError(_)
+            | GroupError::MemberAdditionsUnsupported => Self::Mls(message),
+            GroupError::KeyError(e) => Self::from(e).wrapped_in(message),
+            GroupError::AccountError(e) => Self::from(e).wrapped_in(message),
+            GroupError::DatabaseError(e) => Self::from(e).wrapped_in(message),
+            GroupError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            GroupError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            GroupError::NostrError(e) => Self::from(e).wrapped_in(message),
+            GroupError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+            GroupError::RelayError(e) => Self::from(e).wrapped_in(message),
+            GroupError::RelayBlacklistError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<InviteMessageError> for WhitenoiseError {
+    fn from(e: InviteMessageError) -> Self {
+        let message = e.to_string();
+        match e {
+            InviteMessageError::InvalidInvite(_)
+            | InviteMessageError::Expired
+            | InviteMessageError::InvalidLinkSettings(_)
+            | InviteMessageError::LinkUsedUp => Self::InvalidInput(message),
+            InviteMessageError::LinkNotFound | InviteMessageError::RequestNotFound => {
+                Self::NotFound(message)
+            }
+            InviteMessageError::NotAdmin => Self::Unauthorized(message),
+            InviteMessageError::AccountError(e) => Self::from(e).wrapped_in(message),
+            InviteMessageError::GroupError(e) => Self::from(e).wrapped_in(message),
+            InviteMessageError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+            InviteMessageError::RelayBlacklistError(e) => Self::from(e).wrapped_in(message),
+            InviteMessageError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            InviteMessageError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<MessageError> for WhitenoiseError {
+    fn from(e: MessageError) -> Self {
+        let message = e.to_string();
+        match e {
+            MessageError::NotFound => Self::NotFound(message),
+            MessageError::Sqlx(e) => Self::from(e).wrapped_in(message),
+            MessageError::Account(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<AppLockError> for WhitenoiseError {
+    fn from(e: AppLockError) -> Self {
+        let message = e.to_string();
+        match e {
+            AppLockError::Locked => Self::AppLocked(message),
+            AppLockError::InvalidPassphrase(_) | AppLockError::TooManyAttempts(_) => {
+                Self::Unauthorized(message)
+            }
+            AppLockError::NotConfigured => Self::InvalidInput(message),
+            AppLockError::AccountError(e) => Self::from(e).wrapped_in(message),
+            AppLockError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            AppLockError::PublicKeyError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<ContactError> for WhitenoiseError {
+    fn from(e: ContactError) -> Self {
+        let message = e.to_string();
+        match e {
+            ContactError::InvalidPubkey(_) => Self::InvalidKey(message),
+            ContactError::NotFound(_) => Self::NotFound(message),
+            ContactError::AccountError(e) => Self::from(e).wrapped_in(message),
+            ContactError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            ContactError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            ContactError::NostrDatabaseError(_) => Self::Storage(message),
+            ContactError::NostrError(e) => Self::from(e).wrapped_in(message),
+            ContactError::RelayBlacklistError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<BlocklistError> for WhitenoiseError {
+    fn from(e: BlocklistError) -> Self {
+        let message = e.to_string();
+        match e {
+            BlocklistError::InvalidPubkey(_) => Self::InvalidKey(message),
+            BlocklistError::BlockingSelf => Self::InvalidInput(message),
+            BlocklistError::AccountError(e) => Self::from(e).wrapped_in(message),
+            BlocklistError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            BlocklistError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            BlocklistError::NostrDatabaseError(_) => Self::Storage(message),
+            BlocklistError::NostrError(e) => Self::from(e).wrapped_in(message),
+            BlocklistError::RelayBlacklistError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<KeyPackageError> for WhitenoiseError {
+    fn from(e: KeyPackageError) -> Self {
+        let message = e.to_string();
+        match e {
+            KeyPackageError::NoValidKeyPackage(_) => Self::NotFound(message),
+            KeyPackageError::FetchingKeyPackage(_) => Self::RelayUnreachable(message),
+            KeyPackageError::NostrMlsError(_) => Self::Mls(message),
+            KeyPackageError::AccountError(e) => Self::from(e).wrapped_in(message),
+            KeyPackageError::NostrError(e) => Self::from(e).wrapped_in(message),
+            KeyPackageError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            KeyPackageError::NostrSignerError(e) => Self::from(e).wrapped_in(message),
+            KeyPackageError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            KeyPackageError::RelayBlacklistError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<InviteError> for WhitenoiseError {
+    fn from(e: InviteError) -> Self {
+        let message = e.to_string();
+        match e {
+            InviteError::Event(_) => Self::InvalidInput(message),
+            InviteError::Database(_) => Self::Storage(message),
+            InviteError::Sqlx(e) => Self::from(e).wrapped_in(message),
+            InviteError::Account(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<PaymentError> for WhitenoiseError {
+    fn from(e: PaymentError) -> Self {
+        let message = e.to_string();
+        match e {
+            PaymentError::InvalidInvoice(_)
+            | PaymentError::ExpiredInvoice
+            | PaymentError::InvalidNwcUri(_) => Self::InvalidInput(message),
+            PaymentError::WalletUnreachable(_) => Self::RelayUnreachable(message),
+            PaymentError::PaymentFailure(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<MediaError> for WhitenoiseError {
+    fn from(e: MediaError) -> Self {
+        let message = e.to_string();
+        match e {
+            MediaError::Upload(_) | MediaError::Download(_) | MediaError::Delete(_) => {
+                Self::RelayUnreachable(message)
+            }
+            MediaError::Integrity(_) | MediaError::Decryption(_) => Self::InvalidInput(message),
+            MediaError::ExportSecret(_) => Self::Mls(message),
+            MediaError::Database(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<AccountBackupError> for WhitenoiseError {
+    fn from(e: AccountBackupError) -> Self {
+        let message = e.to_string();
+        match e {
+            AccountBackupError::InvalidPassphrase(_) | AccountBackupError::DecryptionFailed => {
+                Self::Unauthorized(message)
+            }
+            AccountBackupError::InvalidBackup(_) | AccountBackupError::UnsupportedVersion(_) => {
+                Self::InvalidInput(message)
+            }
+            AccountBackupError::AccountError(e) => Self::from(e).wrapped_in(message),
+            AccountBackupError::GroupError(e) => Self::from(e).wrapped_in(message),
+            AccountBackupError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            AccountBackupError::KeyError(e) => Self::from(e).wrapped_in(message),
+            AccountBackupError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            AccountBackupError::FileError(_) => Self::Storage(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<AppDataError> for WhitenoiseError {
+    fn from(e: AppDataError) -> Self {
+        let message = e.to_string();
+        match e {
+            AppDataError::InvalidPath(_)
+            | AppDataError::InvalidArchive(_)
+            | AppDataError::UnsupportedVersion(_) => Self::InvalidInput(message),
+            AppDataError::AccountBackupError(e) => Self::from(e).wrapped_in(message),
+            AppDataError::AccountError(e) => Self::from(e).wrapped_in(message),
+            AppDataError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            AppDataError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            AppDataError::FileError(_) => Self::Storage(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<KeyMigrationError> for WhitenoiseError {
+    fn from(e: KeyMigrationError) -> Self {
+        let message = e.to_string();
+        match e {
+            KeyMigrationError::InvalidMigration(_) | KeyMigrationError::EventIdError(_) => {
+                Self::InvalidInput(message)
+            }
+            KeyMigrationError::AccountError(e) => Self::from(e).wrapped_in(message),
+            KeyMigrationError::GroupError(e) => Self::from(e).wrapped_in(message),
+            KeyMigrationError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            KeyMigrationError::PublicKeyError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<SyncError> for WhitenoiseError {
+    fn from(e: SyncError) -> Self {
+        let message = e.to_string();
+        match e {
+            SyncError::InvalidSchedule(_) => Self::InvalidInput(message),
+            SyncError::AccountError(e) => Self::from(e).wrapped_in(message),
+            SyncError::KeyPackageError(e) => Self::from(e).wrapped_in(message),
+            SyncError::BackgroundRefreshError(e) => Self::from(e).wrapped_in(message),
+            SyncError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+            SyncError::TauriError(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<SensitiveActionError> for WhitenoiseError {
+    fn from(e: SensitiveActionError) -> Self {
+        let message = e.to_string();
+        match e {
+            SensitiveActionError::AppLockError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Unauthorized(message),
+        }
+    }
+}
+
+impl From<BackgroundRefreshError> for WhitenoiseError {
+    fn from(e: BackgroundRefreshError) -> Self {
+        let message = e.to_string();
+        match e {
+            BackgroundRefreshError::BudgetTooShort => Self::InvalidInput(message),
+            BackgroundRefreshError::GroupError(e) => Self::from(e).wrapped_in(message),
+            BackgroundRefreshError::MessageError(e) => Self::from(e).wrapped_in(message),
+            BackgroundRefreshError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+            BackgroundRefreshError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            BackgroundRefreshError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            BackgroundRefreshError::TauriError(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<Nip05Error> for WhitenoiseError {
+    fn from(e: Nip05Error) -> Self {
+        let message = e.to_string();
+        match e {
+            Nip05Error::InvalidIdentifier(_) => Self::InvalidInput(message),
+            Nip05Error::NameNotFound(_) => Self::NotFound(message),
+            Nip05Error::InvalidPubkey(_) => Self::InvalidKey(message),
+            Nip05Error::DocumentTooLarge(_) | Nip05Error::InvalidDocument(_) => {
+                Self::RelayUnreachable(message)
+            }
+            Nip05Error::RequestError(_) => Self::RelayUnreachable(message),
+            Nip05Error::SqlxError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<PendingWelcomeError> for WhitenoiseError {
+    fn from(e: PendingWelcomeError) -> Self {
+        let message = e.to_string();
+        match e {
+            PendingWelcomeError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Storage(message),
+        }
+    }
+}
+
+impl From<DbEncryptionError> for WhitenoiseError {
+    fn from(e: DbEncryptionError) -> Self {
+        let message = e.to_string();
+        match e {
+            DbEncryptionError::AlreadyEncrypted => Self::InvalidInput(message),
+            DbEncryptionError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            DbEncryptionError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            DbEncryptionError::FileSystem(_) => Self::Storage(message),
+        }
+    }
+}
+
+impl From<IntegrityError> for WhitenoiseError {
+    fn from(e: IntegrityError) -> Self {
+        let message = e.to_string();
+        match e {
+            IntegrityError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            IntegrityError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            IntegrityError::SerializationError(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<QuickSwitcherError> for WhitenoiseError {
+    fn from(e: QuickSwitcherError) -> Self {
+        let message = e.to_string();
+        match e {
+            QuickSwitcherError::AccountError(e) => Self::from(e).wrapped_in(message),
+            QuickSwitcherError::GroupError(e) => Self::from(e).wrapped_in(message),
+            QuickSwitcherError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<GroupTemplateError> for WhitenoiseError {
+    fn from(e: GroupTemplateError) -> Self {
+        let message = e.to_string();
+        match e {
+            GroupTemplateError::NotFound(_) => Self::NotFound(message),
+            _ => Self::InvalidInput(message),
+        }
+    }
+}
+
+impl From<ContentFilterError> for WhitenoiseError {
+    fn from(e: ContentFilterError) -> Self {
+        Self::InvalidInput(e.to_string())
+    }
+}
+
+impl From<EpochRecoveryError> for WhitenoiseError {
+    fn from(e: EpochRecoveryError) -> Self {
+        let message = e.to_string();
+        match e {
+            EpochRecoveryError::AccountError(e) => Self::from(e).wrapped_in(message),
+            EpochRecoveryError::GroupError(e) => Self::from(e).wrapped_in(message),
+            EpochRecoveryError::MessageError(e) => Self::from(e).wrapped_in(message),
+            EpochRecoveryError::SecretsStoreError(e) => Self::from(e).wrapped_in(message),
+            EpochRecoveryError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            EpochRecoveryError::NostrDatabaseError(_) => Self::Storage(message),
+        }
+    }
+}
+
+impl From<SyncThrottleError> for WhitenoiseError {
+    fn from(e: SyncThrottleError) -> Self {
+        let message = e.to_string();
+        match e {
+            SyncThrottleError::AccountError(e) => Self::from(e).wrapped_in(message),
+            SyncThrottleError::NostrManagerError(e) => Self::from(e).wrapped_in(message),
+            SyncThrottleError::TauriError(_) => Self::Internal(message),
+        }
+    }
+}
+
+impl From<RelayFailoverError> for WhitenoiseError {
+    fn from(e: RelayFailoverError) -> Self {
+        let message = e.to_string();
+        match e {
+            RelayFailoverError::NotPublished(_) => Self::RelayUnreachable(message),
+            RelayFailoverError::AccountError(e) => Self::from(e).wrapped_in(message),
+            RelayFailoverError::NostrClientError(e) => Self::from(e).wrapped_in(message),
+            RelayFailoverError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            RelayFailoverError::RelayBlacklistError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<OutboxError> for WhitenoiseError {
+    fn from(e: OutboxError) -> Self {
+        let message = e.to_string();
+        match e {
+            OutboxError::NotFound => Self::NotFound(message),
+            OutboxError::AccountError(e) => Self::from(e).wrapped_in(message),
+            OutboxError::GroupError(e) => Self::from(e).wrapped_in(message),
+            OutboxError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            _ => Self::Internal(message),
+        }
+    }
+}
+
+impl From<ReactionError> for WhitenoiseError {
+    fn from(e: ReactionError) -> Self {
+        let message = e.to_string();
+        match e {
+            ReactionError::InvalidReaction(_) => Self::InvalidInput(message),
+            ReactionError::AccountError(e) => Self::from(e).wrapped_in(message),
+            ReactionError::SqlxError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<GroupTaskError> for WhitenoiseError {
+    fn from(e: GroupTaskError) -> Self {
+        let message = e.to_string();
+        match e {
+            GroupTaskError::InvalidTask(_) => Self::InvalidInput(message),
+            GroupTaskError::SqlxError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<GroupNoteError> for WhitenoiseError {
+    fn from(e: GroupNoteError) -> Self {
+        let message = e.to_string();
+        match e {
+            GroupNoteError::InvalidNote(_) | GroupNoteError::Conflict { .. } => {
+                Self::InvalidInput(message)
+            }
+            GroupNoteError::SqlxError(e) => Self::from(e).wrapped_in(message),
+        }
+    }
+}
+
+impl From<GroupCustomDataError> for WhitenoiseError {
+    fn from(e: GroupCustomDataError) -> Self {
+        let message = e.to_string();
+        match e {
+            GroupCustomDataError::SqlxError(e) => Self::from(e).wrapped_in(message),
+            GroupCustomDataError::SerializationError(_) => Self::Internal(message),
+            _ => Self::InvalidInput(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_message_and_retryable() {
+        let error = WhitenoiseError::from(RelayError::NoRelays).with_context("Error publishing");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "relay_unreachable",
+                "message": "Error publishing: The account has no relays to publish",
+                "retryable": true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nested_errors_keep_their_kind() {
+        let error: WhitenoiseError = GroupError::AccountError(AccountError::NoActiveAccount).into();
+        assert_eq!(error.code(), "no_active_account");
+        assert_eq!(error.message(), "Account error: No active account found");
+
+        let error: WhitenoiseError = GroupError::SqlxError(sqlx::Error::RowNotFound).into();
+        assert_eq!(error.code(), "not_found");
+        assert!(!error.retryable());
+    }
+
+    #[test]
+    fn test_context_keeps_the_message() {
+        let result: std::result::Result<(), GroupError> = Err(GroupError::GroupNotFound);
+        let error = result.context("Error fetching group").unwrap_err();
+        assert_eq!(error.code(), "not_found");
+        assert_eq!(error.to_string(), "Error fetching group: Group not found");
+    }
+
+    #[test]
+    fn test_strings_are_internal() {
+        let error: WhitenoiseError = format!("Error doing {}", "something").into();
+        assert_eq!(error.code(), "internal");
+        assert_eq!(error.message(), "Error doing something");
+    }
+}
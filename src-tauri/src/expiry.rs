@@ -0,0 +1,50 @@
+//! Local deletion of ephemeral messages.
+//!
+//! Messages sent with `expires_in`, or to a group with a `message_ttl`, carry a NIP-40
+//! expiration tag. Every member's backend deletes its copy once the expiration passes,
+//! independently of any group-wide retention, and emits `mls_message_expired` so frontends can
+//! drop the message from the transcript.
+
+use crate::messages::Message;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often expired messages are swept
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Starts the background task that deletes expired messages
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = sweep(&app_handle).await {
+                tracing::error!(
+                    target: "whitenoise::expiry::sweep",
+                    "Failed to delete expired messages: {}",
+                    e
+                );
+            }
+        }
+    });
+}
+
+async fn sweep(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let wn = app_handle.state::<Whitenoise>();
+    let expired = Message::delete_expired(Timestamp::now(), wn).await?;
+
+    if !expired.is_empty() {
+        tracing::debug!(
+            target: "whitenoise::expiry::sweep",
+            "Deleted {} expired messages",
+            expired.len()
+        );
+    }
+
+    for event in expired {
+        app_handle.emit("mls_message_expired", event)?;
+    }
+    Ok(())
+}
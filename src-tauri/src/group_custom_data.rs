@@ -0,0 +1,294 @@
+//! Small namespaced blobs of state that frontend features share within a group.
+//!
+//! A feature picks a namespace (e.g. `polls` or `com.example.board`) and stores any JSON value
+//! under it, so it can sync per-group state without a new payload type and backend changes each
+//! time. A change is sent as a `GROUP_CUSTOM_DATA_KIND` message whose content is a JSON
+//! [`CustomDataUpdate`], and the latest value of each namespace is kept in `group_custom_data`.
+//!
+//! Values replace each other whole, last-writer-wins by creation time (ties broken by event ID),
+//! so every member ends up with the same value whatever order the messages arrived in. Setting a
+//! namespace to `null` clears it. To keep the transcript small, values are limited to
+//! [`MAX_VALUE_BYTES`] and a group can use at most [`MAX_NAMESPACES`] namespaces. Members who set
+//! a new namespace at the same time can go over the limit; every update is stored, and the
+//! namespaces whose values were set first (ties broken by event ID) hold the slots, so every
+//! member sees the same ones.
+
+use crate::messages;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Maximum size of a value, serialized as JSON
+pub const MAX_VALUE_BYTES: usize = 4 * 1024;
+
+/// Maximum number of namespaces with a value in a group
+pub const MAX_NAMESPACES: usize = 32;
+
+/// Maximum length of a namespace
+const MAX_NAMESPACE_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum GroupCustomDataError {
+    #[error("Invalid namespace: {0}")]
+    InvalidNamespace(String),
+
+    #[error("Value is larger than {MAX_VALUE_BYTES} bytes")]
+    ValueTooLarge,
+
+    #[error("Groups can't have more than {MAX_NAMESPACES} namespaces")]
+    TooManyNamespaces,
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, GroupCustomDataError>;
+
+/// The content of a `GROUP_CUSTOM_DATA_KIND` message
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CustomDataUpdate {
+    pub namespace: String,
+    /// The new value; `null` clears the namespace
+    pub value: serde_json::Value,
+}
+
+/// The current value of a namespace. Payload of the `group_custom_data_updated` event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GroupCustomData {
+    /// Hex encoded MLS group ID
+    pub group_id: String,
+    pub namespace: String,
+    pub value: serde_json::Value,
+    /// Who set the current value
+    pub author: PublicKey,
+    /// The message that set the current value
+    pub event_id: EventId,
+    pub updated_at: Timestamp,
+}
+
+/// Checks a namespace: lowercase letters, digits, `.`, `_` and `-`, starting with a letter or digit
+pub fn validate_namespace(namespace: &str) -> Result<()> {
+    let valid = !namespace.is_empty()
+        && namespace.len() <= MAX_NAMESPACE_LENGTH
+        && namespace
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-'));
+    if !valid {
+        return Err(GroupCustomDataError::InvalidNamespace(
+            namespace.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks an update before it's sent or applied
+pub fn validate_update(update: &CustomDataUpdate) -> Result<()> {
+    validate_namespace(&update.namespace)?;
+    if serde_json::to_vec(&update.value)?.len() > MAX_VALUE_BYTES {
+        return Err(GroupCustomDataError::ValueTooLarge);
+    }
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GroupCustomDataRow {
+    mls_group_id: Vec<u8>,
+    namespace: String,
+    value: String,
+    author_pubkey: String,
+    event_id: String,
+    updated_at: i64,
+}
+
+impl From<GroupCustomDataRow> for GroupCustomData {
+    fn from(row: GroupCustomDataRow) -> Self {
+        Self {
+            group_id: hex::encode(&row.mls_group_id),
+            namespace: row.namespace,
+            value: serde_json::from_str(&row.value).unwrap_or_default(),
+            author: PublicKey::from_hex(&row.author_pubkey).unwrap(),
+            event_id: EventId::from_hex(&row.event_id).unwrap(),
+            updated_at: Timestamp::from(row.updated_at as u64),
+        }
+    }
+}
+
+/// Finds the stored value of a namespace, including a cleared one
+async fn find_stored(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    namespace: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<GroupCustomData>> {
+    Ok(sqlx::query_as::<_, GroupCustomDataRow>(
+        "SELECT * FROM group_custom_data
+         WHERE account_pubkey = ? AND mls_group_id = ? AND namespace = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(namespace)
+    .fetch_optional(&wn.database.pool)
+    .await?
+    .map(GroupCustomData::from))
+}
+
+/// Lists the namespaces of a group that have a value, optionally just one of them
+///
+/// Only the [`MAX_NAMESPACES`] namespaces whose values were set first are listed.
+pub async fn for_group(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    namespace: Option<&str>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<GroupCustomData>> {
+    Ok(sqlx::query_as::<_, GroupCustomDataRow>(
+        "SELECT * FROM (
+             SELECT * FROM group_custom_data
+             WHERE account_pubkey = ? AND mls_group_id = ? AND value != 'null'
+             ORDER BY updated_at, event_id
+             LIMIT ?
+         )
+         WHERE ? IS NULL OR namespace = ?
+         ORDER BY namespace",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(MAX_NAMESPACES as i64)
+    .bind(namespace)
+    .bind(namespace)
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(GroupCustomData::from)
+    .collect())
+}
+
+/// Checks that setting `namespace` wouldn't take the group over [`MAX_NAMESPACES`]
+pub async fn check_namespace_limit(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    namespace: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let used = for_group(account_pubkey, mls_group_id, None, wn).await?;
+    if used.len() >= MAX_NAMESPACES && !used.iter().any(|data| data.namespace == namespace) {
+        return Err(GroupCustomDataError::TooManyNamespaces);
+    }
+    Ok(())
+}
+
+/// Applies a received `GROUP_CUSTOM_DATA_KIND` message, returning the namespace's new state if
+/// it changed and the namespace holds one of the group's slots
+///
+/// Malformed and outdated updates are ignored.
+pub async fn apply(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    message: &UnsignedEvent,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<GroupCustomData>> {
+    let update: CustomDataUpdate = match serde_json::from_str(&message.content) {
+        Ok(update) => update,
+        Err(e) => {
+            tracing::warn!(
+                target: "whitenoise::group_custom_data::apply",
+                "Ignoring malformed custom data update: {}",
+                e
+            );
+            return Ok(None);
+        }
+    };
+    if let Err(e) = validate_update(&update) {
+        tracing::warn!(
+            target: "whitenoise::group_custom_data::apply",
+            "Ignoring invalid custom data update: {}",
+            e
+        );
+        return Ok(None);
+    }
+    let Some(event_id) = message.id else {
+        return Ok(None);
+    };
+
+    let current = find_stored(account_pubkey, mls_group_id, &update.namespace, wn.clone()).await?;
+    if current.is_some_and(|current| {
+        !messages::lww_wins(
+            (message.created_at, event_id),
+            (current.updated_at, current.event_id),
+        )
+    }) {
+        return Ok(None);
+    }
+
+    let data = GroupCustomData {
+        group_id: hex::encode(mls_group_id),
+        namespace: update.namespace,
+        value: update.value,
+        author: message.pubkey,
+        event_id,
+        updated_at: message.created_at,
+    };
+    sqlx::query(
+        "INSERT OR REPLACE INTO group_custom_data
+         (account_pubkey, mls_group_id, namespace, value, author_pubkey, event_id, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(&data.namespace)
+    .bind(serde_json::to_string(&data.value)?)
+    .bind(data.author.to_hex())
+    .bind(data.event_id.to_hex())
+    .bind(data.updated_at.as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+
+    if data.value.is_null() {
+        return Ok(Some(data));
+    }
+    // Namespaces over the limit are kept in case a slot frees up, but not shown
+    Ok(
+        for_group(account_pubkey, mls_group_id, Some(&data.namespace), wn)
+            .await?
+            .pop(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_namespace() {
+        for namespace in ["polls", "com.example.board", "v2_drafts-1"] {
+            assert!(validate_namespace(namespace).is_ok(), "{}", namespace);
+        }
+        for namespace in ["", "Polls", ".hidden", "a b", "emoji🙂"] {
+            assert!(validate_namespace(namespace).is_err(), "{}", namespace);
+        }
+        assert!(validate_namespace(&"a".repeat(MAX_NAMESPACE_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_update_limits_value_size() {
+        let mut update = CustomDataUpdate {
+            namespace: "polls".to_string(),
+            value: json!({"question": "Lunch?", "options": ["pizza", "sushi"]}),
+        };
+        assert!(validate_update(&update).is_ok());
+        update.value = json!("x".repeat(MAX_VALUE_BYTES));
+        assert!(matches!(
+            validate_update(&update),
+            Err(GroupCustomDataError::ValueTooLarge)
+        ));
+    }
+}
@@ -0,0 +1,475 @@
+//! A log of raw group message events, for replaying them while debugging.
+//!
+//! In dev builds every encrypted group message event is kept together with what processing it
+//! gave: the ID of the decrypted message, or why it failed. Before the first event of a group is
+//! logged the account's MLS state is copied as the group's baseline. `replay_group_events` opens
+//! that copy in a scratch `nostr_mls`, runs the logged events through it in the order they arrived
+//! and compares the outcomes with the logged ones, so a transcript that differs between members
+//! can be reproduced without touching the real state.
+//!
+//! Baselines hold the account's group secrets, so they're encrypted with a key derived from the
+//! account's private key. At most [`MAX_LOGGED_GROUPS`] groups are logged per account, each for
+//! at most [`MAX_LOGGED_EVENTS`] events, and a group's log is dropped after [`LOG_RETENTION_SECS`]
+//! so logging starts over from a fresh baseline.
+
+use crate::account_backup::{self, AccountBackupError, BackupStateFile};
+use crate::accounts::{Account, AccountError};
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use nostr_openmls::NostrMls;
+use nostr_sdk::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Directory in the data dir replays keep their scratch MLS state in
+const REPLAY_DIR: &str = "replays";
+
+/// Most groups of an account logged at a time
+const MAX_LOGGED_GROUPS: i64 = 10;
+
+/// Most events logged per group; later ones aren't logged
+const MAX_LOGGED_EVENTS: i64 = 2_000;
+
+/// How long a group's baseline and events are kept before its log starts over
+const LOG_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Error, Debug)]
+pub enum GroupEventLogError {
+    #[error("No events logged for this group")]
+    NothingLogged,
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("MLS state error: {0}")]
+    MlsStateError(#[from] AccountBackupError),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("Baseline encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Base64 error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+}
+
+pub type Result<T> = std::result::Result<T, GroupEventLogError>;
+
+/// What processing a group message event gave
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventOutcome {
+    /// The event decrypted to this message
+    Message { message_event_id: EventId },
+    /// The event couldn't be processed
+    Failed { error: String },
+}
+
+impl EventOutcome {
+    /// The outcome of passing an event through `process_message_for_group`
+    pub(crate) fn from_processed<E: Display>(result: &std::result::Result<Vec<u8>, E>) -> Self {
+        let message = match result {
+            Ok(message) => message,
+            Err(e) => {
+                return Self::Failed {
+                    error: e.to_string(),
+                }
+            }
+        };
+        match serde_json::from_slice::<UnsignedEvent>(message) {
+            Ok(UnsignedEvent { id: Some(id), .. }) => Self::Message {
+                message_event_id: id,
+            },
+            Ok(_) => Self::Failed {
+                error: "Message without an ID".to_string(),
+            },
+            Err(e) => Self::Failed {
+                error: format!("Failed to deserialize message into JSON: {}", e),
+            },
+        }
+    }
+
+    /// Whether two outcomes agree. Errors only have to agree on failing, their wording depends
+    /// on where in the pipeline they were caught.
+    fn agrees_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Message {
+                    message_event_id: a,
+                },
+                Self::Message {
+                    message_event_id: b,
+                },
+            ) => a == b,
+            (Self::Failed { .. }, Self::Failed { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct LoggedEventRow {
+    event: String,
+    message_event_id: Option<String>,
+    error: Option<String>,
+}
+
+/// A replayed event, with what it gave then and now
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReplayedEvent {
+    pub event_id: EventId,
+    pub created_at: Timestamp,
+    pub logged: EventOutcome,
+    pub replayed: EventOutcome,
+    pub matches: bool,
+}
+
+/// The result of replaying a group's logged events
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub mls_group_id: Vec<u8>,
+    /// When the baseline the replay started from was taken
+    pub baseline_at: Timestamp,
+    pub events: Vec<ReplayedEvent>,
+    pub mismatches: usize,
+}
+
+/// Whether group message events are logged. Only dev builds log them, since baselines copy the
+/// account's group secrets.
+pub(crate) fn is_enabled() -> bool {
+    cfg!(dev)
+}
+
+/// The key baselines of an account are encrypted with
+fn baseline_key(account_pubkey: &PublicKey, data_dir: &Path) -> Result<Zeroizing<[u8; 32]>> {
+    let keys = secrets_store::get_nostr_keys_for_pubkey(&account_pubkey.to_hex(), data_dir)?;
+    let mut hasher = Sha256::new();
+    hasher.update(b"whitenoise-group-event-log:");
+    hasher.update(keys.secret_key().to_secret_hex().as_bytes());
+    Ok(Zeroizing::new(<[u8; 32]>::from(hasher.finalize())))
+}
+
+/// Encrypts a baseline's MLS state as `<hex nonce>:<base64 ciphertext>`
+fn seal_state(state: &[BackupStateFile], key: &[u8; 32]) -> Result<String> {
+    let plaintext = Zeroizing::new(serde_json::to_vec(state)?);
+    let mut nonce = [0u8; 12];
+    rand::rng().fill_bytes(&mut nonce);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|e| GroupEventLogError::Encryption(e.to_string()))?;
+    Ok(format!(
+        "{}:{}",
+        hex::encode(nonce),
+        general_purpose::STANDARD.encode(ciphertext)
+    ))
+}
+
+/// Decrypts a baseline's MLS state sealed by [`seal_state`]
+fn open_state(sealed: &str, key: &[u8; 32]) -> Result<Vec<BackupStateFile>> {
+    let invalid = || GroupEventLogError::Encryption("Malformed baseline".to_string());
+    let (nonce, ciphertext) = sealed.split_once(':').ok_or_else(invalid)?;
+    let nonce = hex::decode(nonce).map_err(|_| invalid())?;
+    if nonce.len() != 12 {
+        return Err(invalid());
+    }
+    let ciphertext = general_purpose::STANDARD.decode(ciphertext)?;
+    let plaintext = Zeroizing::new(
+        ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| {
+                GroupEventLogError::Encryption("Failed to decrypt baseline".to_string())
+            })?,
+    );
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Makes sure the group has a baseline to log its events against, copying the account's MLS state
+/// if it has none yet. Logs past [`LOG_RETENTION_SECS`] are dropped first, and no new baseline is
+/// taken once [`MAX_LOGGED_GROUPS`] groups are logged. The caller holds the `nostr_mls` lock so
+/// the state doesn't change while it's copied.
+///
+/// # Returns
+/// * `Ok(true)` - If the group's events are logged
+pub(crate) async fn save_baseline(
+    account: &Account,
+    mls_group_id: &[u8],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<bool> {
+    let expired_before = Timestamp::now().as_u64().saturating_sub(LOG_RETENTION_SECS) as i64;
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM group_event_log WHERE account_pubkey = ? AND mls_group_id IN (
+             SELECT mls_group_id FROM group_event_log_baselines
+             WHERE account_pubkey = ? AND created_at < ?
+         )",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(account.pubkey.to_hex())
+    .bind(expired_before)
+    .execute(&mut *txn)
+    .await?;
+    sqlx::query(
+        "DELETE FROM group_event_log_baselines WHERE account_pubkey = ? AND created_at < ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(expired_before)
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+
+    let (has_baseline, logged_groups): (bool, i64) = sqlx::query_as(
+        "SELECT COALESCE(SUM(mls_group_id = ?), 0) > 0, COUNT(*) FROM group_event_log_baselines
+         WHERE account_pubkey = ?",
+    )
+    .bind(mls_group_id)
+    .bind(account.pubkey.to_hex())
+    .fetch_one(&wn.database.pool)
+    .await?;
+    if has_baseline {
+        return Ok(true);
+    }
+    if logged_groups >= MAX_LOGGED_GROUPS {
+        return Ok(false);
+    }
+
+    let state = account_backup::snapshot_mls_state(&wn.data_dir, &account.pubkey)?;
+    let key = baseline_key(&account.pubkey, &wn.data_dir)?;
+    sqlx::query(
+        "INSERT INTO group_event_log_baselines (account_pubkey, mls_group_id, state, created_at)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(seal_state(&state, &key)?)
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(true)
+}
+
+/// Logs a group message event with what processing it gave, unless the group already has
+/// [`MAX_LOGGED_EVENTS`] logged
+pub(crate) async fn record(
+    account: &Account,
+    mls_group_id: &[u8],
+    event: &Event,
+    outcome: &EventOutcome,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let (message_event_id, error) = match outcome {
+        EventOutcome::Message { message_event_id } => (Some(message_event_id.to_hex()), None),
+        EventOutcome::Failed { error } => (None, Some(error.clone())),
+    };
+    sqlx::query(
+        "INSERT INTO group_event_log
+             (account_pubkey, mls_group_id, event_id, event, message_event_id, error, received_at)
+         SELECT ?, ?, ?, ?, ?, ?, ?
+         WHERE (SELECT COUNT(*) FROM group_event_log WHERE account_pubkey = ? AND mls_group_id = ?) < ?
+         ON CONFLICT(account_pubkey, event_id) DO NOTHING",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(event.id.to_hex())
+    .bind(event.as_json())
+    .bind(message_event_id)
+    .bind(error)
+    .bind(Timestamp::now().as_u64() as i64)
+    .bind(account.pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(MAX_LOGGED_EVENTS)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(())
+}
+
+/// Decrypts an event with the group's current export secret and processes it
+fn process(nostr_mls: &NostrMls, mls_group_id: &[u8], event: &Event) -> EventOutcome {
+    let decrypted = nostr_mls
+        .export_secret_as_hex_secret_key_and_epoch(mls_group_id.to_vec())
+        .map_err(|e| e.to_string())
+        .and_then(|(secret, _)| Keys::parse(&secret).map_err(|e| e.to_string()))
+        .and_then(|keys| {
+            nip44::decrypt_to_bytes(keys.secret_key(), &keys.public_key(), &event.content)
+                .map_err(|e| e.to_string())
+        });
+    match decrypted {
+        Ok(decrypted) => EventOutcome::from_processed(
+            &nostr_mls.process_message_for_group(mls_group_id.to_vec(), decrypted),
+        ),
+        Err(error) => EventOutcome::Failed { error },
+    }
+}
+
+/// Replays the active account's logged events of a group from the group's baseline
+pub async fn replay(mls_group_id: &[u8], wn: tauri::State<'_, Whitenoise>) -> Result<ReplayReport> {
+    let account = Account::get_active(wn.clone()).await?;
+
+    let (state, baseline_at): (String, i64) = sqlx::query_as(
+        "SELECT state, created_at FROM group_event_log_baselines
+         WHERE account_pubkey = ? AND mls_group_id = ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(mls_group_id)
+    .fetch_optional(&wn.database.pool)
+    .await?
+    .ok_or(GroupEventLogError::NothingLogged)?;
+    let state = open_state(&state, &*baseline_key(&account.pubkey, &wn.data_dir)?)?;
+
+    // Rows are replayed in the order they were logged, which is the order they were processed in
+    let rows = sqlx::query_as::<_, LoggedEventRow>(
+        "SELECT event, message_event_id, error FROM group_event_log
+         WHERE account_pubkey = ? AND mls_group_id = ?
+         ORDER BY rowid",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(mls_group_id)
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let mut logged = Vec::with_capacity(rows.len());
+    for row in rows {
+        let outcome = match (row.message_event_id, row.error) {
+            (Some(id), _) => EventOutcome::Message {
+                message_event_id: EventId::from_hex(&id)?,
+            },
+            (None, error) => EventOutcome::Failed {
+                error: error.unwrap_or_default(),
+            },
+        };
+        logged.push((serde_json::from_str::<Event>(&row.event)?, outcome));
+    }
+
+    let mut suffix = [0u8; 8];
+    rand::rng().fill_bytes(&mut suffix);
+    let scratch_dir = wn.data_dir.join(REPLAY_DIR).join(hex::encode(suffix));
+    account_backup::write_mls_state(&scratch_dir, &account.pubkey, &state)?;
+
+    let scratch = NostrMls::new(scratch_dir.clone(), Some(account.pubkey.to_hex()));
+    let events: Vec<ReplayedEvent> = logged
+        .into_iter()
+        .map(|(event, logged)| {
+            let replayed = process(&scratch, mls_group_id, &event);
+            ReplayedEvent {
+                event_id: event.id,
+                created_at: event.created_at,
+                matches: logged.agrees_with(&replayed),
+                logged,
+                replayed,
+            }
+        })
+        .collect();
+    drop(scratch);
+
+    if let Err(e) = fs::remove_dir_all(&scratch_dir) {
+        tracing::warn!(
+            target: "whitenoise::group_event_log::replay",
+            "Failed to remove scratch MLS state {}: {}",
+            scratch_dir.display(),
+            e
+        );
+    }
+
+    Ok(ReplayReport {
+        mls_group_id: mls_group_id.to_vec(),
+        baseline_at: Timestamp::from(baseline_at as u64),
+        mismatches: events.iter().filter(|event| !event.matches).count(),
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(keys: &Keys) -> Vec<u8> {
+        let mut event = EventBuilder::text_note("hi").build(keys.public_key());
+        event.ensure_id();
+        event.as_json().into_bytes()
+    }
+
+    #[test]
+    fn test_outcome_from_processed() {
+        let keys = Keys::generate();
+        let bytes = message(&keys);
+        let id = UnsignedEvent::from_json(&bytes).unwrap().id.unwrap();
+
+        let ok: std::result::Result<Vec<u8>, String> = Ok(bytes);
+        assert_eq!(
+            EventOutcome::from_processed(&ok),
+            EventOutcome::Message {
+                message_event_id: id
+            }
+        );
+
+        let failed: std::result::Result<Vec<u8>, String> = Err("wrong epoch".to_string());
+        assert_eq!(
+            EventOutcome::from_processed(&failed),
+            EventOutcome::Failed {
+                error: "wrong epoch".to_string()
+            }
+        );
+
+        let garbage: std::result::Result<Vec<u8>, String> = Ok(b"not json".to_vec());
+        assert!(matches!(
+            EventOutcome::from_processed(&garbage),
+            EventOutcome::Failed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_baseline_state_round_trips_sealed() {
+        let key = [7u8; 32];
+        let state = vec![BackupStateFile {
+            path: "mls.db".to_string(),
+            data: "c2VjcmV0".to_string(),
+        }];
+        let sealed = seal_state(&state, &key).unwrap();
+
+        assert!(!sealed.contains("c2VjcmV0"));
+        assert_eq!(open_state(&sealed, &key).unwrap(), state);
+        assert!(open_state(&sealed, &[8u8; 32]).is_err());
+        assert!(open_state("not sealed", &key).is_err());
+    }
+
+    #[test]
+    fn test_outcomes_agree() {
+        let id = EventId::all_zeros();
+        let message = EventOutcome::Message {
+            message_event_id: id,
+        };
+        let failed = |error: &str| EventOutcome::Failed {
+            error: error.to_string(),
+        };
+
+        assert!(message.agrees_with(&message.clone()));
+        assert!(failed("a").agrees_with(&failed("b")));
+        assert!(!message.agrees_with(&failed("a")));
+        assert!(!message.agrees_with(&EventOutcome::Message {
+            message_event_id: EventId::from_slice(&[1; 32]).unwrap()
+        }));
+    }
+}
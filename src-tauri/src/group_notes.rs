@@ -0,0 +1,373 @@
+//! Collaborative notes shared by the members of a group.
+//!
+//! Every change to a note is sent through the group as a `GROUP_NOTE_KIND` message whose content
+//! is a JSON [`NoteUpdate`] with the full new text and the version it was written on top of. The
+//! latest state of each note is kept in `group_notes`.
+//!
+//! Concurrent edits are resolved last-writer-wins: the update created last (ties broken by event
+//! ID) becomes the note, whatever order members receive the updates in. The losing text isn't
+//! dropped silently; it's kept as the note's `conflict` until someone writes a new version on top
+//! of the winner, so members can merge it by hand.
+
+use crate::messages;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Maximum length (in chars) of a note
+pub const MAX_NOTE_LENGTH: usize = 32_000;
+
+/// Maximum length (in chars) of a note ID
+const MAX_NOTE_ID_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum GroupNoteError {
+    #[error("Invalid note: {0}")]
+    InvalidNote(String),
+
+    #[error("Note {note_id} is at version {current_version}, not {based_on_version}")]
+    Conflict {
+        note_id: String,
+        current_version: u64,
+        based_on_version: u64,
+    },
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+}
+
+pub type Result<T> = std::result::Result<T, GroupNoteError>;
+
+/// The content of a `GROUP_NOTE_KIND` message
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NoteUpdate {
+    pub note_id: String,
+    pub content: String,
+    /// The version the author edited; 0 for a new note
+    pub based_on_version: u64,
+}
+
+/// An edit that lost to a concurrent one
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NoteConflict {
+    pub content: String,
+    pub author: PublicKey,
+    pub event_id: EventId,
+    pub created_at: Timestamp,
+}
+
+/// The current state of a group note. Payload of the `group_note_updated` event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GroupNote {
+    /// Hex encoded MLS group ID
+    pub group_id: String,
+    pub note_id: String,
+    pub content: String,
+    /// Increases with every update; edits should be based on it
+    pub version: u64,
+    /// Who wrote the current content
+    pub author: PublicKey,
+    /// The update that set the current content
+    pub event_id: EventId,
+    pub updated_at: Timestamp,
+    /// A concurrent edit that lost, until a newer version supersedes it
+    pub conflict: Option<NoteConflict>,
+}
+
+/// An update as received, with the metadata of the message that carried it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedUpdate {
+    pub update: NoteUpdate,
+    pub author: PublicKey,
+    pub event_id: EventId,
+    pub created_at: Timestamp,
+}
+
+impl ReceivedUpdate {
+    fn as_conflict(&self) -> NoteConflict {
+        NoteConflict {
+            content: self.update.content.clone(),
+            author: self.author,
+            event_id: self.event_id,
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// Checks a note before it's sent
+pub fn validate_update(update: &NoteUpdate) -> Result<()> {
+    let note_id = update.note_id.trim();
+    if note_id.is_empty() || note_id.chars().count() > MAX_NOTE_ID_LENGTH {
+        return Err(GroupNoteError::InvalidNote(format!(
+            "Note ID must be between 1 and {} characters",
+            MAX_NOTE_ID_LENGTH
+        )));
+    }
+    if update.content.chars().count() > MAX_NOTE_LENGTH {
+        return Err(GroupNoteError::InvalidNote(format!(
+            "Notes can't be longer than {} characters",
+            MAX_NOTE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Computes the state of a note after an update
+pub fn merge(group_id: &str, current: Option<GroupNote>, received: &ReceivedUpdate) -> GroupNote {
+    let update = &received.update;
+    let applied = |conflict: Option<NoteConflict>, version: u64| GroupNote {
+        group_id: group_id.to_string(),
+        note_id: update.note_id.clone(),
+        content: update.content.clone(),
+        version,
+        author: received.author,
+        event_id: received.event_id,
+        updated_at: received.created_at,
+        conflict,
+    };
+
+    let Some(current) = current else {
+        return applied(None, update.based_on_version + 1);
+    };
+    // The same update delivered twice
+    if current.event_id == received.event_id {
+        return current;
+    }
+    // A clean edit of the current version supersedes any earlier conflict
+    if update.based_on_version == current.version {
+        return applied(None, current.version + 1);
+    }
+
+    let version = current.version.max(update.based_on_version + 1);
+    if messages::lww_wins(
+        (received.created_at, received.event_id),
+        (current.updated_at, current.event_id),
+    ) {
+        let conflict = NoteConflict {
+            content: current.content,
+            author: current.author,
+            event_id: current.event_id,
+            created_at: current.updated_at,
+        };
+        applied(Some(conflict), version)
+    } else {
+        GroupNote {
+            version,
+            conflict: Some(received.as_conflict()),
+            ..current
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GroupNoteRow {
+    mls_group_id: Vec<u8>,
+    note_id: String,
+    content: String,
+    version: i64,
+    author_pubkey: String,
+    event_id: String,
+    updated_at: i64,
+    conflict_content: Option<String>,
+    conflict_author_pubkey: Option<String>,
+    conflict_event_id: Option<String>,
+    conflict_at: Option<i64>,
+}
+
+impl From<GroupNoteRow> for GroupNote {
+    fn from(row: GroupNoteRow) -> Self {
+        let conflict = match (
+            row.conflict_content,
+            row.conflict_author_pubkey
+                .and_then(|pubkey| PublicKey::from_hex(&pubkey).ok()),
+            row.conflict_event_id
+                .and_then(|event_id| EventId::from_hex(&event_id).ok()),
+            row.conflict_at,
+        ) {
+            (Some(content), Some(author), Some(event_id), Some(created_at)) => Some(NoteConflict {
+                content,
+                author,
+                event_id,
+                created_at: Timestamp::from(created_at as u64),
+            }),
+            _ => None,
+        };
+        Self {
+            group_id: hex::encode(&row.mls_group_id),
+            note_id: row.note_id,
+            content: row.content,
+            version: row.version as u64,
+            author: PublicKey::from_hex(&row.author_pubkey).unwrap(),
+            event_id: EventId::from_hex(&row.event_id).unwrap(),
+            updated_at: Timestamp::from(row.updated_at as u64),
+            conflict,
+        }
+    }
+}
+
+/// Finds a note of a group, as stored for the account that's a member of it
+pub async fn find(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    note_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<GroupNote>> {
+    Ok(sqlx::query_as::<_, GroupNoteRow>(
+        "SELECT * FROM group_notes WHERE account_pubkey = ? AND mls_group_id = ? AND note_id = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(note_id)
+    .fetch_optional(&wn.database.pool)
+    .await?
+    .map(GroupNote::from))
+}
+
+/// Lists the notes of a group, most recently updated first
+pub async fn for_group(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<GroupNote>> {
+    Ok(sqlx::query_as::<_, GroupNoteRow>(
+        "SELECT * FROM group_notes WHERE account_pubkey = ? AND mls_group_id = ?
+         ORDER BY updated_at DESC, note_id",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(GroupNote::from)
+    .collect())
+}
+
+/// Applies a received `GROUP_NOTE_KIND` message, returning the note's new state
+///
+/// Malformed updates are ignored.
+pub async fn apply(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    message: &UnsignedEvent,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<GroupNote>> {
+    let update: NoteUpdate = match serde_json::from_str(&message.content) {
+        Ok(update) => update,
+        Err(e) => {
+            tracing::warn!(
+                target: "whitenoise::group_notes::apply",
+                "Ignoring malformed note update: {}",
+                e
+            );
+            return Ok(None);
+        }
+    };
+    if let Err(e) = validate_update(&update) {
+        tracing::warn!(
+            target: "whitenoise::group_notes::apply",
+            "Ignoring invalid note update: {}",
+            e
+        );
+        return Ok(None);
+    }
+    let Some(event_id) = message.id else {
+        return Ok(None);
+    };
+
+    let received = ReceivedUpdate {
+        update,
+        author: message.pubkey,
+        event_id,
+        created_at: message.created_at,
+    };
+    let current = find(
+        account_pubkey,
+        mls_group_id,
+        &received.update.note_id,
+        wn.clone(),
+    )
+    .await?;
+    let note = merge(&hex::encode(mls_group_id), current, &received);
+
+    let conflict = note.conflict.as_ref();
+    sqlx::query(
+        "INSERT OR REPLACE INTO group_notes
+         (account_pubkey, mls_group_id, note_id, content, version, author_pubkey, event_id, updated_at,
+          conflict_content, conflict_author_pubkey, conflict_event_id, conflict_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(&note.note_id)
+    .bind(&note.content)
+    .bind(note.version as i64)
+    .bind(note.author.to_hex())
+    .bind(note.event_id.to_hex())
+    .bind(note.updated_at.as_u64() as i64)
+    .bind(conflict.map(|conflict| conflict.content.clone()))
+    .bind(conflict.map(|conflict| conflict.author.to_hex()))
+    .bind(conflict.map(|conflict| conflict.event_id.to_hex()))
+    .bind(conflict.map(|conflict| conflict.created_at.as_u64() as i64))
+    .execute(&wn.database.pool)
+    .await?;
+
+    Ok(Some(note))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(content: &str, based_on_version: u64, created_at: u64, id: u8) -> ReceivedUpdate {
+        ReceivedUpdate {
+            update: NoteUpdate {
+                note_id: "agenda".to_string(),
+                content: content.to_string(),
+                based_on_version,
+            },
+            author: Keys::generate().public_key(),
+            event_id: EventId::from_slice(&[id; 32]).unwrap(),
+            created_at: Timestamp::from(created_at),
+        }
+    }
+
+    #[test]
+    fn test_sequential_edits_bump_the_version() {
+        let note = merge("ab", None, &received("first", 0, 10, 1));
+        assert_eq!(note.version, 1);
+        let note = merge("ab", Some(note), &received("second", 1, 20, 2));
+        assert_eq!(note.version, 2);
+        assert_eq!(note.content, "second");
+        assert_eq!(note.conflict, None);
+    }
+
+    #[test]
+    fn test_concurrent_edits_converge_and_keep_the_loser() {
+        let base = merge("ab", None, &received("first", 0, 10, 1));
+        let early = received("early", 1, 20, 2);
+        let late = received("late", 1, 30, 3);
+
+        let one_order = merge("ab", Some(merge("ab", Some(base.clone()), &early)), &late);
+        let other_order = merge("ab", Some(merge("ab", Some(base), &late)), &early);
+
+        for note in [&one_order, &other_order] {
+            assert_eq!(note.content, "late");
+            assert_eq!(note.version, 2);
+            assert_eq!(note.conflict.as_ref().unwrap().content, "early");
+        }
+
+        // Writing on top of the winner resolves the conflict
+        let resolved = merge("ab", Some(one_order), &received("merged", 2, 40, 4));
+        assert_eq!(resolved.version, 3);
+        assert_eq!(resolved.conflict, None);
+    }
+
+    #[test]
+    fn test_validate_update() {
+        let mut update = received("text", 0, 10, 1).update;
+        assert!(validate_update(&update).is_ok());
+        update.note_id = " ".to_string();
+        assert!(validate_update(&update).is_err());
+    }
+}
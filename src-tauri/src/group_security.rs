@@ -0,0 +1,304 @@
+//! Security report of a group, for users who want to audit their conversations.
+//!
+//! The cryptographic parameters come from the MLS group. Key freshness is tracked per member in
+//! `group_members.leaf_updated_at`, which is set when a member joins a group we're already in and
+//! whenever their leaf encryption key in the ratchet tree changes. A leaf that hasn't been seen
+//! getting fresh keys has an unknown age and isn't counted as stale.
+//!
+//! Members whose keys are stale or who have been inactive for too long are flagged in
+//! `get_group_members`. For groups the user administers, [`flag_stale_members`] also emits
+//! `stale_members_detected` once per member going stale, suggesting to remove them. Rotating the
+//! admin's own keys isn't suggested: it doesn't refresh anyone else's leaf.
+
+use crate::accounts::Account;
+use crate::groups::{self, Group, Result};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+/// How long a leaf can go without fresh keys before it's considered stale
+pub const STALE_LEAF_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How long a member can go without posting before they're considered inactive
+pub const INACTIVE_MEMBER_SECS: u64 = 90 * 24 * 60 * 60;
+
+/// Privacy measures in effect for a group's messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupPrivacyOptions {
+    /// Message events are encrypted with NIP-44 v2, which pads the content to hide its length
+    pub padded_content: bool,
+    /// Message events are signed with a fresh key each, so relays can't tell who sent them
+    pub ephemeral_sender_keys: bool,
+    /// Whether the user lets the other members know when they've read the group
+    pub read_receipts: bool,
+    /// Seconds after which the user's messages expire by default
+    pub message_ttl: Option<u64>,
+    /// Whether the group is flagged as sensitive, blurring previews and deterring screenshots
+    pub sensitive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupSecurityInfo {
+    /// Name of the MLS ciphersuite, e.g. `MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519`
+    pub ciphersuite: String,
+    /// The ciphersuite's value as published in key packages
+    pub ciphersuite_value: String,
+    /// The group's current MLS epoch
+    pub epoch: u64,
+    /// When the user's own keys in the group were last replaced, by joining or rotating
+    pub last_key_rotation_at: Option<Timestamp>,
+    /// How many members the group has
+    pub members: usize,
+    /// How many members were last seen getting fresh keys more than [`STALE_LEAF_AGE_SECS`] ago
+    pub stale_leaves: usize,
+    pub privacy: GroupPrivacyOptions,
+}
+
+/// Whether a leaf last updated at the given time is stale. Leaves that haven't been seen getting
+/// fresh keys aren't.
+pub fn is_stale(leaf_updated_at: Option<i64>, now: Timestamp) -> bool {
+    leaf_updated_at.is_some_and(|updated_at| {
+        now.as_u64().saturating_sub(updated_at.max(0) as u64) > STALE_LEAF_AGE_SECS
+    })
+}
+
+/// Whether a member who last posted, and whose leaf was last updated, at the given times is
+/// inactive. Members only count as inactive once we know them for long enough; members we know
+/// nothing about don't.
+pub fn is_inactive(
+    last_message_at: Option<i64>,
+    leaf_updated_at: Option<i64>,
+    now: Timestamp,
+) -> bool {
+    match last_message_at.max(leaf_updated_at) {
+        Some(active_at) => {
+            now.as_u64().saturating_sub(active_at.max(0) as u64) > INACTIVE_MEMBER_SECS
+        }
+        None => false,
+    }
+}
+
+/// What admins could do about stale members
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleMemberSuggestion {
+    /// Remove members whose keys are stale, so the group's secrets move on from their old key
+    /// material. Only the member can refresh their own leaf.
+    RemoveStaleMembers,
+    /// Remove members who are no longer around
+    RemoveInactiveMembers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleMember {
+    pub pubkey: PublicKey,
+    pub stale_keys: bool,
+    pub inactive: bool,
+}
+
+/// Payload of `stale_members_detected`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleMembersEvent {
+    /// Hex encoded MLS group ID
+    pub mls_group_id: String,
+    /// Members that went stale since admins were last told
+    pub members: Vec<StaleMember>,
+    pub suggestions: Vec<StaleMemberSuggestion>,
+}
+
+fn suggestions(members: &[StaleMember]) -> Vec<StaleMemberSuggestion> {
+    let mut suggestions = Vec::new();
+    if members.iter().any(|member| member.stale_keys) {
+        suggestions.push(StaleMemberSuggestion::RemoveStaleMembers);
+    }
+    if members.iter().any(|member| member.inactive) {
+        suggestions.push(StaleMemberSuggestion::RemoveInactiveMembers);
+    }
+    suggestions
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct MemberActivityRow {
+    member_pubkey: String,
+    leaf_updated_at: Option<i64>,
+    last_message_at: Option<i64>,
+    stale_flagged_at: Option<i64>,
+}
+
+/// Indexes a group's members, then flags the members that went stale if the user administers
+/// the group, emitting `stale_members_detected` if there are any. Members that were already
+/// flagged aren't reported again until they've been fresh in between.
+///
+/// # Returns
+/// * `Ok(Vec<StaleMember>)` - The newly flagged members
+pub async fn flag_stale_members(
+    group: &Group,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<StaleMember>> {
+    group.index_members(wn.clone()).await?;
+    if !group.admin_pubkeys.contains(&group.account_pubkey.to_hex()) {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query_as::<_, MemberActivityRow>(
+        "SELECT m.member_pubkey, m.leaf_updated_at, m.stale_flagged_at,
+             (SELECT MAX(msg.created_at) FROM messages msg
+              WHERE msg.mls_group_id = m.group_id AND msg.account_pubkey = m.account_pubkey
+                AND msg.author_pubkey = m.member_pubkey) AS last_message_at
+         FROM group_members m
+         WHERE m.group_id = ? AND m.account_pubkey = ? AND m.member_pubkey != m.account_pubkey",
+    )
+    .bind(&group.mls_group_id)
+    .bind(group.account_pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let now = Timestamp::now();
+    let mut newly_stale = Vec::new();
+    let mut txn = wn.database.pool.begin().await?;
+    for row in rows {
+        let stale_keys = is_stale(row.leaf_updated_at, now);
+        let inactive = is_inactive(row.last_message_at, row.leaf_updated_at, now);
+        let stale = stale_keys || inactive;
+        if stale == row.stale_flagged_at.is_some() {
+            continue;
+        }
+        sqlx::query(
+            "UPDATE group_members SET stale_flagged_at = ?
+             WHERE group_id = ? AND account_pubkey = ? AND member_pubkey = ?",
+        )
+        .bind(stale.then_some(now.as_u64() as i64))
+        .bind(&group.mls_group_id)
+        .bind(group.account_pubkey.to_hex())
+        .bind(&row.member_pubkey)
+        .execute(&mut *txn)
+        .await?;
+        if stale {
+            newly_stale.push(StaleMember {
+                pubkey: PublicKey::from_hex(&row.member_pubkey)?,
+                stale_keys,
+                inactive,
+            });
+        }
+    }
+    txn.commit().await?;
+
+    if !newly_stale.is_empty() {
+        app_handle.emit(
+            "stale_members_detected",
+            StaleMembersEvent {
+                mls_group_id: hex::encode(&group.mls_group_id),
+                suggestions: suggestions(&newly_stale),
+                members: newly_stale.clone(),
+            },
+        )?;
+    }
+    Ok(newly_stale)
+}
+
+/// Reports the ciphersuite, epoch, key freshness and privacy options of a group
+pub async fn security_info(
+    group: &Group,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<GroupSecurityInfo> {
+    let account = Account::find_by_pubkey(&group.account_pubkey, wn.clone()).await?;
+    let members = group.index_members(wn.clone()).await?;
+
+    let (ciphersuite, epoch) = {
+        let nostr_mls = wn.nostr_mls.lock().await;
+        let mls_group = groups::load_mls_group(&nostr_mls, &group.mls_group_id)?;
+        (mls_group.ciphersuite(), mls_group.epoch().as_u64())
+    };
+
+    let leaves = sqlx::query_as::<_, (String, Option<i64>)>(
+        "SELECT member_pubkey, leaf_updated_at FROM group_members
+         WHERE group_id = ? AND account_pubkey = ?",
+    )
+    .bind(&group.mls_group_id)
+    .bind(group.account_pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let now = Timestamp::now();
+    let own_pubkey = group.account_pubkey.to_hex();
+    let last_key_rotation_at = leaves
+        .iter()
+        .find(|(member, _)| *member == own_pubkey)
+        .and_then(|(_, updated_at)| *updated_at)
+        .map(|updated_at| Timestamp::from(updated_at as u64));
+    let stale_leaves = leaves
+        .iter()
+        .filter(|(_, updated_at)| is_stale(*updated_at, now))
+        .count();
+
+    Ok(GroupSecurityInfo {
+        ciphersuite: format!("{:?}", ciphersuite),
+        ciphersuite_value: u16::from(ciphersuite).to_string(),
+        epoch,
+        last_key_rotation_at,
+        members: members.len(),
+        stale_leaves,
+        privacy: GroupPrivacyOptions {
+            padded_content: true,
+            ephemeral_sender_keys: true,
+            read_receipts: account.settings.send_read_receipts,
+            message_ttl: group.message_ttl,
+            sensitive: group.sensitive,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_leaves() {
+        let now = Timestamp::from(100 * 24 * 60 * 60);
+        let fresh = (now.as_u64() - 60) as i64;
+        let old = (now.as_u64() - STALE_LEAF_AGE_SECS - 1) as i64;
+
+        assert!(!is_stale(Some(fresh), now));
+        assert!(is_stale(Some(old), now));
+        // Leaves of unknown age aren't flagged
+        assert!(!is_stale(None, now));
+        // A clock that ran ahead doesn't make a leaf stale
+        assert!(!is_stale(Some((now.as_u64() + 60) as i64), now));
+    }
+
+    #[test]
+    fn test_inactive_members() {
+        let now = Timestamp::from(200 * 24 * 60 * 60);
+        let recent = (now.as_u64() - 60) as i64;
+        let long_ago = (now.as_u64() - INACTIVE_MEMBER_SECS - 1) as i64;
+
+        assert!(!is_inactive(Some(recent), Some(long_ago), now));
+        assert!(is_inactive(Some(long_ago), Some(long_ago), now));
+        // Members who never posted are inactive once we've known them for long enough
+        assert!(!is_inactive(None, Some(recent), now));
+        assert!(is_inactive(None, Some(long_ago), now));
+        assert!(!is_inactive(None, None, now));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let member = |stale_keys, inactive| StaleMember {
+            pubkey: Keys::generate().public_key(),
+            stale_keys,
+            inactive,
+        };
+        assert_eq!(
+            suggestions(&[member(true, false)]),
+            vec![StaleMemberSuggestion::RemoveStaleMembers]
+        );
+        assert_eq!(
+            suggestions(&[member(false, true), member(true, true)]),
+            vec![
+                StaleMemberSuggestion::RemoveStaleMembers,
+                StaleMemberSuggestion::RemoveInactiveMembers
+            ]
+        );
+    }
+}
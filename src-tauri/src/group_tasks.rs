@@ -0,0 +1,324 @@
+//! Tasks and checklists shared in a group.
+//!
+//! Tasks are `GROUP_TASK_KIND` messages whose content is a JSON [`TaskAction`]: one creates a
+//! task with its assignees, later ones mark it done or not done. Like reactions, the messages are
+//! stored in the transcript and the current state of each task is aggregated from them, so every
+//! member ends up with the same state whatever order the messages arrived in.
+//!
+//! A task can be completed by its creator, its assignees and the group's admins, or by anyone if
+//! it has no assignees. Actions from other members are ignored.
+
+use crate::groups::Group;
+use crate::messages::{self, Message, MessageRow, GROUP_TASK_KIND};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Maximum length (in chars) of a task's title
+const MAX_TITLE_LENGTH: usize = 500;
+
+/// Maximum length (in chars) of a task ID
+const MAX_TASK_ID_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum GroupTaskError {
+    #[error("Invalid task: {0}")]
+    InvalidTask(String),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+}
+
+pub type Result<T> = std::result::Result<T, GroupTaskError>;
+
+/// The content of a `GROUP_TASK_KIND` message
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TaskAction {
+    Create {
+        task_id: String,
+        title: String,
+        /// Hex encoded pubkeys
+        #[serde(default)]
+        assignees: Vec<String>,
+    },
+    SetCompleted {
+        task_id: String,
+        completed: bool,
+    },
+}
+
+impl TaskAction {
+    pub fn task_id(&self) -> &str {
+        match self {
+            TaskAction::Create { task_id, .. } | TaskAction::SetCompleted { task_id, .. } => {
+                task_id
+            }
+        }
+    }
+}
+
+/// A task action as received, with the metadata of the message that carried it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedAction {
+    pub action: TaskAction,
+    pub author: PublicKey,
+    pub event_id: EventId,
+    pub created_at: Timestamp,
+}
+
+/// The current state of a task
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GroupTask {
+    pub task_id: String,
+    pub title: String,
+    pub assignees: Vec<PublicKey>,
+    pub created_by: PublicKey,
+    pub created_at: Timestamp,
+    /// The message that created the task
+    pub event_id: EventId,
+    pub completed: bool,
+    /// Who last marked the task done or not done
+    pub completed_by: Option<PublicKey>,
+    pub completed_at: Option<Timestamp>,
+}
+
+impl GroupTask {
+    /// Whether `member` may mark the task done or not done
+    pub fn can_complete(&self, member: &PublicKey, admins: &[String]) -> bool {
+        self.assignees.is_empty()
+            || self.created_by == *member
+            || self.assignees.contains(member)
+            || admins.contains(&member.to_hex())
+    }
+}
+
+/// Checks an action before it's sent
+pub fn validate_action(action: &TaskAction) -> Result<()> {
+    let task_id = action.task_id();
+    if task_id.trim().is_empty() || task_id.chars().count() > MAX_TASK_ID_LENGTH {
+        return Err(GroupTaskError::InvalidTask(format!(
+            "Task ID must be between 1 and {} characters",
+            MAX_TASK_ID_LENGTH
+        )));
+    }
+    if let TaskAction::Create {
+        title, assignees, ..
+    } = action
+    {
+        if title.trim().is_empty() || title.chars().count() > MAX_TITLE_LENGTH {
+            return Err(GroupTaskError::InvalidTask(format!(
+                "Title must be between 1 and {} characters",
+                MAX_TITLE_LENGTH
+            )));
+        }
+        if let Some(invalid) = assignees
+            .iter()
+            .find(|assignee| PublicKey::from_hex(assignee).is_err())
+        {
+            return Err(GroupTaskError::InvalidTask(format!(
+                "Invalid assignee: {}",
+                invalid
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Builds the current state of the tasks from their actions, oldest task first
+///
+/// The first task created with an ID wins, and the completions of a task are resolved
+/// last-writer-wins.
+///
+/// # Arguments
+/// * `actions` - The group's task actions, in any order
+/// * `admins` - Hex encoded pubkeys of the group's admins
+pub fn aggregate(actions: &[ReceivedAction], admins: &[String]) -> Vec<GroupTask> {
+    let mut actions: Vec<&ReceivedAction> = actions
+        .iter()
+        .filter(|received| validate_action(&received.action).is_ok())
+        .collect();
+    actions.sort_by_key(|received| (received.created_at, received.event_id));
+
+    let mut tasks: Vec<GroupTask> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for received in &actions {
+        let TaskAction::Create {
+            task_id,
+            title,
+            assignees,
+        } = &received.action
+        else {
+            continue;
+        };
+        if index.contains_key(task_id.as_str()) {
+            continue;
+        }
+        index.insert(task_id, tasks.len());
+        tasks.push(GroupTask {
+            task_id: task_id.clone(),
+            title: title.clone(),
+            assignees: assignees
+                .iter()
+                .filter_map(|assignee| PublicKey::from_hex(assignee).ok())
+                .collect(),
+            created_by: received.author,
+            created_at: received.created_at,
+            event_id: received.event_id,
+            completed: false,
+            completed_by: None,
+            completed_at: None,
+        });
+    }
+
+    // The completion that currently holds each task
+    let mut completions: HashMap<&str, (Timestamp, EventId)> = HashMap::new();
+    for received in &actions {
+        let TaskAction::SetCompleted { task_id, completed } = &received.action else {
+            continue;
+        };
+        let Some(task) = index.get(task_id.as_str()).map(|&i| &mut tasks[i]) else {
+            continue;
+        };
+        if !task.can_complete(&received.author, admins) {
+            continue;
+        }
+        let update = (received.created_at, received.event_id);
+        if let Some(&current) = completions.get(task_id.as_str()) {
+            if !messages::lww_wins(update, current) {
+                continue;
+            }
+        }
+        completions.insert(task_id, update);
+        task.completed = *completed;
+        task.completed_by = Some(received.author);
+        task.completed_at = Some(received.created_at);
+    }
+    tasks
+}
+
+/// Retrieves the current state of a group's tasks, oldest first
+pub async fn for_group(group: &Group, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<GroupTask>> {
+    let actions: Vec<ReceivedAction> = sqlx::query_as::<_, MessageRow>(
+        "SELECT * FROM messages
+         WHERE mls_group_id = ? AND account_pubkey = ? AND event_kind = ? AND deleted_at IS NULL",
+    )
+    .bind(&group.mls_group_id)
+    .bind(group.account_pubkey.to_hex())
+    .bind(i64::from(GROUP_TASK_KIND))
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(Message::from)
+    .filter_map(|message| {
+        Some(ReceivedAction {
+            action: serde_json::from_str(&message.content).ok()?,
+            author: message.author_pubkey,
+            event_id: message.event_id,
+            created_at: message.created_at,
+        })
+    })
+    .collect();
+
+    Ok(aggregate(&actions, &group.admin_pubkeys))
+}
+
+/// Tags of a task action: new tasks mention their assignees so they're notified
+pub fn action_tags(action: &TaskAction) -> Vec<Tag> {
+    match action {
+        TaskAction::Create { assignees, .. } => assignees
+            .iter()
+            .filter_map(|assignee| PublicKey::from_hex(assignee).ok())
+            .map(Tag::public_key)
+            .collect(),
+        TaskAction::SetCompleted { .. } => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(author: &Keys, action: TaskAction, created_at: u64, id: u8) -> ReceivedAction {
+        ReceivedAction {
+            action,
+            author: author.public_key(),
+            event_id: EventId::from_slice(&[id; 32]).unwrap(),
+            created_at: Timestamp::from(created_at),
+        }
+    }
+
+    fn create(assignees: Vec<String>) -> TaskAction {
+        TaskAction::Create {
+            task_id: "groceries".to_string(),
+            title: "Buy groceries".to_string(),
+            assignees,
+        }
+    }
+
+    fn set_completed(completed: bool) -> TaskAction {
+        TaskAction::SetCompleted {
+            task_id: "groceries".to_string(),
+            completed,
+        }
+    }
+
+    #[test]
+    fn test_latest_completion_wins_in_any_order() {
+        let alice = Keys::generate();
+        let actions = vec![
+            received(&alice, set_completed(false), 30, 3),
+            received(&alice, create(vec![]), 10, 1),
+            received(&alice, set_completed(true), 20, 2),
+        ];
+        let tasks = aggregate(&actions, &[]);
+        assert_eq!(tasks.len(), 1);
+        assert!(!tasks[0].completed);
+        assert_eq!(tasks[0].completed_at, Some(Timestamp::from(30)));
+    }
+
+    #[test]
+    fn test_only_allowed_members_complete_assigned_tasks() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let mallory = Keys::generate();
+        let mut actions = vec![
+            received(&alice, create(vec![bob.public_key().to_hex()]), 10, 1),
+            received(&mallory, set_completed(true), 20, 2),
+        ];
+        assert!(!aggregate(&actions, &[])[0].completed);
+        assert!(aggregate(&actions, &[mallory.public_key().to_hex()])[0].completed);
+
+        actions.push(received(&bob, set_completed(true), 30, 3));
+        let task = &aggregate(&actions, &[])[0];
+        assert!(task.completed);
+        assert_eq!(task.completed_by, Some(bob.public_key()));
+    }
+
+    #[test]
+    fn test_simultaneous_completions_converge() {
+        let alice = Keys::generate();
+        let created = received(&alice, create(vec![]), 10, 1);
+        let done = received(&alice, set_completed(true), 20, 2);
+        let undone = received(&alice, set_completed(false), 20, 3);
+
+        let one_order = aggregate(&[created.clone(), done.clone(), undone.clone()], &[]);
+        let other_order = aggregate(&[undone, done, created], &[]);
+        assert_eq!(one_order, other_order);
+        assert!(!one_order[0].completed);
+    }
+
+    #[test]
+    fn test_validate_action() {
+        assert!(validate_action(&create(vec![])).is_ok());
+        assert!(validate_action(&create(vec!["not a pubkey".to_string()])).is_err());
+        let untitled = TaskAction::Create {
+            task_id: "a".to_string(),
+            title: " ".to_string(),
+            assignees: vec![],
+        };
+        assert!(validate_action(&untitled).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Group templates.
+//!
+//! A template is a preset for groups that are set up the same way again and again, e.g. a
+//! "project team". Templates are stored in the account settings. Creating a group from one uses
+//! the template's relays and admins, then applies its retention (the default expiry of messages
+//! sent to the group), its notification defaults and its avatar to the new group.
+
+use crate::utils::is_valid_hex_pubkey;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// How many templates an account can keep
+pub const MAX_GROUP_TEMPLATES: usize = 50;
+
+#[derive(Error, Debug)]
+pub enum GroupTemplateError {
+    #[error("Invalid group template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Group template not found: {0}")]
+    NotFound(String),
+
+    #[error(
+        "An account can't have more than {} group templates",
+        MAX_GROUP_TEMPLATES
+    )]
+    TooManyTemplates,
+}
+
+pub type Result<T> = std::result::Result<T, GroupTemplateError>;
+
+/// Notification settings new groups start with
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct TemplateNotifications {
+    /// Flag new groups as sensitive, so previews and notifications are blurred
+    #[serde(default)]
+    pub sensitive: bool,
+    /// Snooze notifications of new groups for this many seconds
+    #[serde(default)]
+    pub snooze_secs: Option<u64>,
+}
+
+/// A preset for creating groups
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GroupTemplate {
+    /// Chosen by the frontend, unique per account
+    pub id: String,
+    /// Shown when picking a template
+    pub name: String,
+    /// The name new groups get unless another one is given. The template name if empty.
+    #[serde(default)]
+    pub group_name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Relays of new groups. The client's relays are used if empty.
+    #[serde(default)]
+    pub relays: Vec<String>,
+    /// Hex pubkeys that become admins when they're among the members. The creator always is one.
+    #[serde(default)]
+    pub admin_pubkeys: Vec<String>,
+    /// Seconds after which messages sent to new groups expire
+    #[serde(default)]
+    pub message_ttl: Option<u64>,
+    #[serde(default)]
+    pub notifications: TemplateNotifications,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+impl GroupTemplate {
+    /// Checks the template and normalizes its relay URLs
+    pub fn validated(mut self) -> Result<Self> {
+        self.id = self.id.trim().to_string();
+        self.name = self.name.trim().to_string();
+        if self.id.is_empty() || self.name.is_empty() {
+            return Err(GroupTemplateError::InvalidTemplate(
+                "Templates need an ID and a name".to_string(),
+            ));
+        }
+
+        let mut relays: Vec<String> = Vec::new();
+        for relay in &self.relays {
+            let url = RelayUrl::parse(relay.trim())
+                .map_err(|e| {
+                    GroupTemplateError::InvalidTemplate(format!(
+                        "Invalid relay URL {}: {}",
+                        relay, e
+                    ))
+                })?
+                .to_string();
+            if !relays.contains(&url) {
+                relays.push(url);
+            }
+        }
+        self.relays = relays;
+
+        if let Some(pubkey) = self
+            .admin_pubkeys
+            .iter()
+            .find(|pubkey| !is_valid_hex_pubkey(pubkey))
+        {
+            return Err(GroupTemplateError::InvalidTemplate(format!(
+                "Invalid admin pubkey: {}",
+                pubkey
+            )));
+        }
+        if self.message_ttl == Some(0) || self.notifications.snooze_secs == Some(0) {
+            return Err(GroupTemplateError::InvalidTemplate(
+                "Durations must be greater than zero".to_string(),
+            ));
+        }
+        if let Some(avatar_url) = &self.avatar_url {
+            Url::parse(avatar_url).map_err(|e| {
+                GroupTemplateError::InvalidTemplate(format!(
+                    "Invalid avatar URL {}: {}",
+                    avatar_url, e
+                ))
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// The admins of a group created from this template: the creator and the template's admins
+    /// that are members
+    pub fn admins_for(&self, creator_pubkey: &str, member_pubkeys: &[String]) -> Vec<String> {
+        let mut admins = vec![creator_pubkey.to_string()];
+        for pubkey in &self.admin_pubkeys {
+            if member_pubkeys.contains(pubkey) && !admins.contains(pubkey) {
+                admins.push(pubkey.clone());
+            }
+        }
+        admins
+    }
+
+    /// The name of a group created from this template
+    pub fn group_name(&self) -> String {
+        if self.group_name.trim().is_empty() {
+            self.name.clone()
+        } else {
+            self.group_name.clone()
+        }
+    }
+}
+
+/// Adds a template to the list, replacing the one with the same ID
+pub fn upsert(templates: &mut Vec<GroupTemplate>, template: GroupTemplate) -> Result<()> {
+    let template = template.validated()?;
+    match templates.iter_mut().find(|t| t.id == template.id) {
+        Some(existing) => *existing = template,
+        None if templates.len() >= MAX_GROUP_TEMPLATES => {
+            return Err(GroupTemplateError::TooManyTemplates)
+        }
+        None => templates.push(template),
+    }
+    Ok(())
+}
+
+/// Removes the template with the given ID from the list
+pub fn remove(templates: &mut Vec<GroupTemplate>, template_id: &str) -> Result<()> {
+    let count = templates.len();
+    templates.retain(|t| t.id != template_id);
+    if templates.len() == count {
+        return Err(GroupTemplateError::NotFound(template_id.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(id: &str) -> GroupTemplate {
+        GroupTemplate {
+            id: id.to_string(),
+            name: "Project team".to_string(),
+            group_name: String::new(),
+            description: String::new(),
+            relays: vec![
+                "wss://relay.example.com".to_string(),
+                " wss://relay.example.com ".to_string(),
+            ],
+            admin_pubkeys: Vec::new(),
+            message_ttl: Some(86_400),
+            notifications: TemplateNotifications::default(),
+            avatar_url: None,
+        }
+    }
+
+    #[test]
+    fn test_validation_normalizes_relays() {
+        let template = template("team").validated().unwrap();
+        assert_eq!(template.relays.len(), 1);
+        assert_eq!(template.group_name(), "Project team");
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_values() {
+        let mut bad_relay = template("team");
+        bad_relay.relays = vec!["not a url".to_string()];
+        assert!(bad_relay.validated().is_err());
+
+        let mut zero_ttl = template("team");
+        zero_ttl.message_ttl = Some(0);
+        assert!(zero_ttl.validated().is_err());
+
+        let mut bad_admin = template("team");
+        bad_admin.admin_pubkeys = vec!["npub".to_string()];
+        assert!(bad_admin.validated().is_err());
+
+        assert!(template(" ").validated().is_err());
+    }
+
+    #[test]
+    fn test_admins_are_limited_to_members() {
+        let creator = Keys::generate().public_key().to_hex();
+        let member = Keys::generate().public_key().to_hex();
+        let outsider = Keys::generate().public_key().to_hex();
+        let mut template = template("team");
+        template.admin_pubkeys = vec![member.clone(), outsider, creator.clone()];
+
+        assert_eq!(
+            template.admins_for(&creator, &[member.clone()]),
+            vec![creator, member]
+        );
+    }
+
+    #[test]
+    fn test_upsert_replaces_by_id() {
+        let mut templates = Vec::new();
+        upsert(&mut templates, template("team")).unwrap();
+        let mut renamed = template("team");
+        renamed.name = "Team".to_string();
+        upsert(&mut templates, renamed).unwrap();
+        upsert(&mut templates, template("family")).unwrap();
+
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].name, "Team");
+        assert!(remove(&mut templates, "team").is_ok());
+        assert!(remove(&mut templates, "team").is_err());
+    }
+}
@@ -1,14 +1,49 @@
 use crate::accounts::{Account, AccountError};
+use crate::capture_protection;
+use crate::content_filters::{ContentFilterSettings, GroupContentFilter};
 use crate::database::DatabaseError;
-use crate::messages::{Message, MessageRow};
+use crate::device_sync::{self, SyncDelta};
+use crate::group_custom_data::{self, GroupCustomDataError};
+use crate::group_notes::{self, GroupNoteError};
+use crate::group_security;
+use crate::group_tasks::{self, GroupTaskError, TaskAction};
+use crate::integrity;
+use crate::localization::{self, Locale, StringKey};
+use crate::media::attachments;
+use crate::media_library;
+use crate::messages::{
+    expiration, thread_refs, Message, MessageRow, MessageSemantics, MlsMessageDeletedEvent,
+    MlsMessageEditedEvent, SystemMessageKind, DELETION_KIND, EDIT_KIND, GROUP_CUSTOM_DATA_KIND,
+    GROUP_NOTE_KIND, GROUP_NOTICE_KIND, GROUP_SETTINGS_KIND, GROUP_TASK_KIND, SYSTEM_MESSAGE_KINDS,
+};
 use crate::nostr_manager::parser::{parse, SerializableToken};
+use crate::nostr_manager::NostrManagerError;
+use crate::notifications::{self, NotificationDecision, NotificationLevel};
+use crate::payments;
+use crate::profiling::{self, OperationKind};
+use crate::reactions::{
+    reaction_target, summarize_message_reactions, MlsReactionsUpdatedEvent, ReactionError,
+    REACTION_KIND,
+};
+use crate::relay_blacklist::{self, RelayBlacklist, RelayBlacklistError};
+use crate::relays::{self, RelayError};
 use crate::secrets_store;
+use crate::types::EnrichedContact;
+use crate::usage_stats;
 use crate::utils::is_valid_hex_pubkey;
 use crate::Whitenoise;
 use nostr_openmls::groups::GroupError as NostrMlsError;
+use nostr_openmls::key_packages::KeyPackage;
 use nostr_openmls::nostr_group_data_extension::NostrGroupDataExtension;
+use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
+use openmls::credentials::BasicCredential;
+use openmls::group::{GroupId, MlsGroup};
+use openmls_traits::OpenMlsProvider;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use tauri::Emitter;
 use tauri_plugin_notification::NotificationExt;
 use thiserror::Error;
 
@@ -26,6 +61,53 @@ pub struct GroupRow {
     pub group_type: String,
     pub epoch: u64,
     pub state: String,
+    pub locale: Option<String>,
+    pub snoozed_until: Option<u64>,
+    pub sensitive: bool,
+    pub archived_at: Option<u64>,
+    pub merged_into: Option<Vec<u8>>,
+    pub last_read_message_id: Option<String>,
+    pub last_read_message_at: Option<u64>,
+    pub content_filter: String, // JSON string
+    pub message_ttl: Option<u64>,
+    pub avatar_url: Option<String>,
+    pub muted: bool,
+    pub mentions_only: bool,
+    pub note_to_self: bool,
+}
+
+/// A member of a group, as returned by [`Group::member_details`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupMember {
+    pub pubkey: PublicKey,
+    pub is_admin: bool,
+    /// The epoch the member was first indexed in, if known. Members can have joined in an earlier
+    /// one, e.g. when they were already in the group the first time it was indexed.
+    pub first_seen_epoch: Option<u64>,
+    /// When the member last sent a message to the group
+    pub last_message_at: Option<Timestamp>,
+    /// When the member's leaf was last seen getting fresh keys, if it has been
+    pub leaf_updated_at: Option<Timestamp>,
+    /// Whether the member's keys are stale, see [`group_security::is_stale`]
+    pub stale_keys: bool,
+    /// Whether the member has been inactive for too long, see [`group_security::is_inactive`]
+    pub inactive: bool,
+    /// The user's petname for the member, if they're a contact
+    pub petname: Option<String>,
+    /// The cached contact, if the member is one
+    pub enriched: Option<EnrichedContact>,
+    /// The member's profile, from the contact or else the local event cache
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GroupMemberRow {
+    member_pubkey: String,
+    first_seen_epoch: Option<i64>,
+    leaf_updated_at: Option<i64>,
+    petname: Option<String>,
+    enriched: Option<String>,
+    last_message_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,6 +140,68 @@ pub struct Group {
     pub epoch: u64,
     /// The state of the group
     pub state: GroupState,
+    /// Optional locale hint (e.g. "es" or "pt-BR") sent by admins, used for backend formatting
+    pub locale: Option<String>,
+    /// Notifications for the group are suppressed until this time, unless the user is mentioned or replied to
+    pub snoozed_until: Option<Timestamp>,
+    /// Set by admins for groups whose content shouldn't be captured. Frontends should blur
+    /// previews and notifications for sensitive groups.
+    #[serde(default)]
+    pub sensitive: bool,
+    /// Set when the group was archived, e.g. after being merged into another group
+    #[serde(default)]
+    pub archived_at: Option<Timestamp>,
+    /// The MLS group ID of the group this one was merged into
+    #[serde(default)]
+    pub merged_into: Option<Vec<u8>>,
+    /// Hex encoded Nostr event ID of the latest message the user has read
+    #[serde(default)]
+    pub last_read_message_id: Option<String>,
+    /// Timestamp of the latest message the user has read
+    #[serde(default)]
+    pub last_read_message_at: Option<Timestamp>,
+    /// Overrides of the account's content filter settings for this group
+    #[serde(default)]
+    pub content_filter: GroupContentFilter,
+    /// Seconds after which messages the user sends to the group expire, unless they pick an
+    /// expiry themselves
+    #[serde(default)]
+    pub message_ttl: Option<u64>,
+    /// Picture shown for the group. Only stored on this device.
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    /// Muted groups never notify, not even for mentions. Only stored on this device.
+    #[serde(default)]
+    pub muted: bool,
+    /// Groups that only notify for mentions, replies and notices. Only stored on this device.
+    #[serde(default)]
+    pub mentions_only: bool,
+    /// For direct messages, the other member
+    #[serde(default)]
+    pub dm_peer: Option<PublicKey>,
+    /// For direct messages, the name to show for the group, taken from the peer's metadata.
+    /// Frontends should prefer it over `name`, which is whatever the creator typed.
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// For direct messages, the peer's profile picture URL
+    #[serde(default)]
+    pub display_picture: Option<String>,
+}
+
+/// Group settings distributed by admins through the group as `GROUP_SETTINGS_KIND` messages.
+/// Only the settings that are present are changed.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct GroupSettingsUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensitive: Option<bool>,
+    /// Replaces the group's relays. This is an application message rather than a group context
+    /// extensions commit, which the MLS library can't make yet, so the MLS group data extension
+    /// keeps the relays the group was created with and members welcomed later start from those.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relays: Option<Vec<String>>,
+    /// The group's locale tag, or an empty string to clear it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,6 +210,9 @@ pub enum GroupType {
     DirectMessage,
     /// A group with more than two members
     Group,
+    /// The account's own single-member group, for notes, drafts and moving files between its
+    /// devices
+    NoteToSelf,
 }
 
 impl From<String> for GroupType {
@@ -73,6 +220,7 @@ impl From<String> for GroupType {
         match s.as_str() {
             "DirectMessage" => Self::DirectMessage,
             "Group" => Self::Group,
+            "NoteToSelf" => Self::NoteToSelf,
             _ => panic!("Invalid group type: {}", s),
         }
     }
@@ -83,6 +231,18 @@ impl From<GroupType> for String {
         match group_type {
             GroupType::DirectMessage => "DirectMessage".to_string(),
             GroupType::Group => "Group".to_string(),
+            GroupType::NoteToSelf => "NoteToSelf".to_string(),
+        }
+    }
+}
+
+impl GroupType {
+    /// The value stored in the `group_type` column, whose CHECK constraint predates
+    /// `NoteToSelf`. Note to Self groups are stored as `Group` and flagged with `note_to_self`.
+    fn stored(&self) -> String {
+        match self {
+            Self::NoteToSelf => String::from(Self::Group),
+            group_type => String::from(group_type.clone()),
         }
     }
 }
@@ -120,6 +280,9 @@ pub enum GroupError {
     #[error("Invalid parameters: {0}")]
     InvalidParameters(String),
 
+    #[error("Members can't be added to existing groups until incoming commits are processed")]
+    MemberAdditionsUnsupported,
+
     #[error("Account error: {0}")]
     AccountError(#[from] AccountError),
 
@@ -129,6 +292,9 @@ pub enum GroupError {
     #[error("MLS error: {0}")]
     MlsError(#[from] NostrMlsError),
 
+    #[error("MLS storage error: {0}")]
+    MlsStorageError(String),
+
     #[error("Key error: {0}")]
     KeyError(#[from] nostr_sdk::key::Error),
 
@@ -155,11 +321,247 @@ pub enum GroupError {
 
     #[error("Notification error: {0}")]
     NotificationError(#[from] tauri_plugin_notification::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Relay error: {0}")]
+    RelayError(#[from] RelayError),
+
+    #[error("Reaction error: {0}")]
+    ReactionError(#[from] ReactionError),
+
+    #[error("Group note error: {0}")]
+    GroupNoteError(#[from] GroupNoteError),
+
+    #[error("Group task error: {0}")]
+    GroupTaskError(#[from] GroupTaskError),
+
+    #[error("Group custom data error: {0}")]
+    GroupCustomDataError(#[from] GroupCustomDataError),
 }
 
 pub type Result<T> = std::result::Result<T, GroupError>;
 
+/// The largest page of messages that can be requested at once
+pub const MAX_MESSAGE_PAGE_SIZE: usize = 500;
+
+/// Where a page of messages ends: the creation time and event ID of its oldest message
+///
+/// Messages created in the same second are ordered by event ID, so no message is skipped or
+/// repeated across pages.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct MessageCursor {
+    pub created_at: Timestamp,
+    pub event_id: EventId,
+}
+
+/// Matches the messages `m` after the read cursor of their group `g`, in the same
+/// (created_at, event_id) order as [`MessageCursor`]. Groups read before the cursor kept an event
+/// ID count everything from the cursor's second as unread.
+const UNREAD_CONDITION: &str = "(g.last_read_message_at IS NULL
+         OR m.created_at > g.last_read_message_at
+         OR (m.created_at = g.last_read_message_at
+             AND m.event_id > COALESCE(g.last_read_message_id, '')))";
+
+/// Action type of message notifications, which frontends register with an inline reply action
+/// that calls `send_quick_reply`
+pub const QUICK_REPLY_ACTION_TYPE: &str = "quick_reply";
+
+/// Whether members can be added to groups that already exist. An Add commit moves the group to
+/// a new epoch, but commits from other members aren't processed yet (see `EventProcessor`), so
+/// they would stay behind and the group would split. Until they are, join requests can't be
+/// approved and invite links and messages can't be created.
+pub const MEMBER_ADDITIONS_ENABLED: bool = false;
+
+/// Deletes a group's MLS state from the storage of the identity `nostr_mls` is opened for,
+/// leaving its other groups as they are. The caller holds the `nostr_mls` lock.
+pub(crate) fn delete_mls_state(nostr_mls: &NostrMls, mls_group_id: &[u8]) -> Result<()> {
+    let storage = nostr_mls.provider.storage();
+    let loaded = MlsGroup::load(storage, &GroupId::from_slice(mls_group_id))
+        .map_err(|e| GroupError::MlsStorageError(e.to_string()))?;
+    if let Some(mut mls_group) = loaded {
+        mls_group
+            .delete(storage)
+            .map_err(|e| GroupError::MlsStorageError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Loads a group's MLS state from the storage of the identity `nostr_mls` is opened for. The
+/// caller holds the `nostr_mls` lock.
+pub(crate) fn load_mls_group(nostr_mls: &NostrMls, mls_group_id: &[u8]) -> Result<MlsGroup> {
+    MlsGroup::load(
+        nostr_mls.provider.storage(),
+        &GroupId::from_slice(mls_group_id),
+    )
+    .map_err(|e| GroupError::MlsStorageError(e.to_string()))?
+    .ok_or_else(|| {
+        GroupError::MlsStorageError(format!(
+            "No MLS state for group {}",
+            hex::encode(mls_group_id)
+        ))
+    })
+}
+
+/// The member pubkey in a basic credential's identity, which holds it raw or hex encoded
+fn credential_pubkey(identity: &[u8]) -> Option<PublicKey> {
+    PublicKey::from_slice(identity)
+        .ok()
+        .or_else(|| PublicKey::from_hex(std::str::from_utf8(identity).ok()?).ok())
+}
+
+/// The hex encoded leaf encryption key of each member, keyed by hex pubkey. A leaf's encryption
+/// key changes whenever the leaf gets fresh keys, through a self update or a commit updating its
+/// path, so a changed key is how a leaf update shows.
+pub(crate) fn leaf_keys(mls_group: &MlsGroup) -> HashMap<String, String> {
+    mls_group
+        .members()
+        .filter_map(|member| {
+            let credential = BasicCredential::try_from(member.credential).ok()?;
+            let pubkey = credential_pubkey(credential.identity())?;
+            Some((pubkey.to_hex(), hex::encode(&member.encryption_key)))
+        })
+        .collect()
+}
+
 impl Group {
+    /// Builds a group from its database row
+    ///
+    /// # Arguments
+    /// * `row` - The group row loaded from the database
+    /// * `account_pubkey` - The account that owns the group
+    ///
+    /// # Errors
+    /// Returns a `serde_json::Error` if the stored admin pubkeys can't be deserialized
+    pub fn from_row(
+        row: GroupRow,
+        account_pubkey: PublicKey,
+    ) -> std::result::Result<Self, serde_json::Error> {
+        Ok(Self {
+            mls_group_id: row.mls_group_id,
+            account_pubkey,
+            nostr_group_id: row.nostr_group_id,
+            name: row.name,
+            description: row.description,
+            admin_pubkeys: serde_json::from_str(&row.admin_pubkeys)?,
+            last_message_id: row.last_message_id,
+            last_message_at: row.last_message_at.map(Timestamp::from),
+            group_type: if row.note_to_self {
+                GroupType::NoteToSelf
+            } else {
+                row.group_type.into()
+            },
+            epoch: row.epoch,
+            state: row.state.into(),
+            locale: row.locale,
+            snoozed_until: row.snoozed_until.map(Timestamp::from),
+            sensitive: row.sensitive,
+            archived_at: row.archived_at.map(Timestamp::from),
+            merged_into: row.merged_into,
+            last_read_message_id: row.last_read_message_id,
+            last_read_message_at: row.last_read_message_at.map(Timestamp::from),
+            content_filter: serde_json::from_str(&row.content_filter)?,
+            message_ttl: row.message_ttl,
+            avatar_url: row.avatar_url,
+            muted: row.muted,
+            mentions_only: row.mentions_only,
+            dm_peer: None,
+            display_name: None,
+            display_picture: None,
+        })
+    }
+
+    /// Sets the display name and picture of a direct message group from the peer's metadata.
+    /// The name falls back to the peer's npub when their profile has none.
+    pub fn apply_peer_metadata(&mut self, peer: PublicKey, metadata: &Metadata) {
+        let display_name = metadata
+            .display_name
+            .clone()
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| metadata.name.clone().filter(|name| !name.trim().is_empty()))
+            .unwrap_or_else(|| peer.to_bech32().unwrap_or_else(|_| peer.to_hex()));
+
+        self.dm_peer = Some(peer);
+        self.display_name = Some(display_name);
+        self.display_picture = metadata
+            .picture
+            .clone()
+            .filter(|picture| !picture.is_empty());
+    }
+
+    /// For direct messages, finds the peer and derives the group's display name and picture from
+    /// their cached metadata, so the group follows their profile. Other groups are returned as
+    /// they are.
+    ///
+    /// Errors are logged rather than returned: a group without a display name is still usable.
+    pub async fn with_dm_display(mut self, wn: tauri::State<'_, Whitenoise>) -> Self {
+        if matches!(self.group_type, GroupType::NoteToSelf) {
+            return self.with_note_to_self_display(wn).await;
+        }
+        if !matches!(self.group_type, GroupType::DirectMessage) {
+            return self;
+        }
+        let peer = match self.members(wn.clone()).await {
+            Ok(members) => members
+                .into_iter()
+                .find(|member| member != &self.account_pubkey),
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::groups::with_dm_display",
+                    "Failed to load members of direct message group: {}",
+                    e
+                );
+                None
+            }
+        };
+        let Some(peer) = peer else {
+            return self;
+        };
+
+        let metadata = match wn.nostr.query_user_metadata(peer).await {
+            Ok(metadata) => metadata.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::groups::with_dm_display",
+                    "Failed to query metadata of {}: {}",
+                    peer,
+                    e
+                );
+                Metadata::default()
+            }
+        };
+        self.apply_peer_metadata(peer, &metadata);
+        self
+    }
+
+    /// Names a Note to Self group in the group's language and shows the account's own picture,
+    /// whatever name it was created with
+    async fn with_note_to_self_display(mut self, wn: tauri::State<'_, Whitenoise>) -> Self {
+        self.display_name =
+            Some(localization::template(self.resolved_locale(), StringKey::NoteToSelf).to_string());
+        self.display_picture = match wn.nostr.query_user_metadata(self.account_pubkey).await {
+            Ok(metadata) => metadata
+                .and_then(|metadata| metadata.picture)
+                .filter(|picture| !picture.is_empty()),
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::groups::with_dm_display",
+                    "Failed to query own metadata: {}",
+                    e
+                );
+                None
+            }
+        };
+        self
+    }
+
     /// Validates the members and admins of a group during creation
     ///
     /// # Arguments
@@ -271,12 +673,27 @@ impl Group {
             group_type,
             epoch: mls_group_epoch,
             state: GroupState::Active,
+            locale: None,
+            snoozed_until: None,
+            sensitive: false,
+            archived_at: None,
+            merged_into: None,
+            last_read_message_id: None,
+            last_read_message_at: None,
+            content_filter: GroupContentFilter::default(),
+            message_ttl: None,
+            avatar_url: None,
+            muted: false,
+            mentions_only: false,
+            dm_peer: None,
+            display_name: None,
+            display_picture: None,
         };
 
         let mut txn = wn.database.pool.begin().await?;
 
         // Save the group - not using the save method because we want relay creation in the same transaction
-        sqlx::query("INSERT INTO groups (mls_group_id, account_pubkey, nostr_group_id, name, description, admin_pubkeys, last_message_id, last_message_at, group_type, epoch, state) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        sqlx::query("INSERT INTO groups (mls_group_id, account_pubkey, nostr_group_id, name, description, admin_pubkeys, last_message_id, last_message_at, group_type, epoch, state, note_to_self) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
             .bind(group.mls_group_id.clone())
             .bind(account.pubkey.to_hex().as_str())
             .bind(group.nostr_group_id.clone())
@@ -285,9 +702,10 @@ impl Group {
             .bind(serde_json::to_string(&group.admin_pubkeys)?)
             .bind(group.last_message_id.clone())
             .bind(group.last_message_at.map(|t| t.as_u64() as i64))
-            .bind(String::from(group.group_type.clone()))
+            .bind(group.group_type.stored())
             .bind(group.epoch as i64)
             .bind(String::from(group.state.clone()))
+            .bind(matches!(group.group_type, GroupType::NoteToSelf))
             .execute(&mut *txn)
             .await?;
 
@@ -308,7 +726,7 @@ impl Group {
         Ok(group)
     }
 
-    /// Find a group by their mls_group_id and the account it belongs to
+    /// Find a group by their mls_group_id among the active account's groups
     pub async fn find_by_mls_group_id(
         mls_group_id: &Vec<u8>,
         wn: tauri::State<'_, Whitenoise>,
@@ -316,12 +734,20 @@ impl Group {
         let account = Account::get_active(wn.clone())
             .await
             .map_err(GroupError::AccountError)?;
+        Self::find_by_mls_group_id_for_account(mls_group_id, &account.pubkey, wn).await
+    }
 
+    /// Find a group by their mls_group_id among the groups of `account_pubkey`
+    pub async fn find_by_mls_group_id_for_account(
+        mls_group_id: &Vec<u8>,
+        account_pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Self> {
         let group_row = sqlx::query_as::<_, GroupRow>(
             "SELECT * FROM groups WHERE mls_group_id = ? AND account_pubkey = ?",
         )
         .bind(mls_group_id)
-        .bind(account.pubkey.to_hex())
+        .bind(account_pubkey.to_hex())
         .fetch_optional(&wn.database.pool)
         .await?
         .ok_or_else(|| GroupError::GroupNotFound)?;
@@ -332,19 +758,7 @@ impl Group {
             group_row
         );
 
-        Ok(Self {
-            mls_group_id: group_row.mls_group_id,
-            account_pubkey: account.pubkey,
-            nostr_group_id: group_row.nostr_group_id,
-            name: group_row.name,
-            description: group_row.description,
-            admin_pubkeys: serde_json::from_str(&group_row.admin_pubkeys)?,
-            last_message_id: group_row.last_message_id,
-            last_message_at: group_row.last_message_at.map(Timestamp::from),
-            group_type: group_row.group_type.into(),
-            epoch: group_row.epoch,
-            state: group_row.state.into(),
-        })
+        Ok(Self::from_row(group_row, *account_pubkey)?)
     }
 
     pub async fn get_by_nostr_group_id(
@@ -364,19 +778,25 @@ impl Group {
         .await?
         .ok_or_else(|| GroupError::GroupNotFound)?;
 
-        Ok(Self {
-            mls_group_id: group_row.mls_group_id,
-            account_pubkey: account.pubkey,
-            nostr_group_id: group_row.nostr_group_id,
-            name: group_row.name,
-            description: group_row.description,
-            admin_pubkeys: serde_json::from_str(&group_row.admin_pubkeys)?,
-            last_message_id: group_row.last_message_id,
-            last_message_at: group_row.last_message_at.map(Timestamp::from),
-            group_type: group_row.group_type.into(),
-            epoch: group_row.epoch,
-            state: group_row.state.into(),
-        })
+        Ok(Self::from_row(group_row, account.pubkey)?)
+    }
+
+    /// The active account's Note to Self group, if it has one
+    pub async fn find_note_to_self(wn: tauri::State<'_, Whitenoise>) -> Result<Option<Self>> {
+        let account = Account::get_active(wn.clone())
+            .await
+            .map_err(GroupError::AccountError)?;
+
+        let group_row = sqlx::query_as::<_, GroupRow>(
+            "SELECT * FROM groups WHERE account_pubkey = ? AND note_to_self AND state = 'Active'
+             LIMIT 1",
+        )
+        .bind(account.pubkey.to_hex())
+        .fetch_optional(&wn.database.pool)
+        .await?;
+        Ok(group_row
+            .map(|row| Self::from_row(row, account.pubkey))
+            .transpose()?)
     }
 
     /// Gets all groups for a given account
@@ -412,32 +832,31 @@ impl Group {
             group_rows
         );
 
-        group_rows
+        // A corrupt group shouldn't hide the others, verify_data_integrity quarantines it
+        Ok(group_rows
             .into_iter()
-            .map(|row| {
-                Ok(Self {
-                    mls_group_id: row.mls_group_id,
-                    account_pubkey: account.pubkey,
-                    nostr_group_id: row.nostr_group_id,
-                    name: row.name,
-                    description: row.description,
-                    admin_pubkeys: serde_json::from_str(&row.admin_pubkeys)?,
-                    last_message_id: row.last_message_id,
-                    last_message_at: row.last_message_at.map(Timestamp::from),
-                    group_type: row.group_type.into(),
-                    epoch: row.epoch,
-                    state: row.state.into(),
-                })
+            .filter_map(|row| {
+                let mls_group_id = hex::encode(&row.mls_group_id);
+                Self::from_row(row, account.pubkey)
+                    .map_err(|e| {
+                        tracing::error!(
+                            target: "whitenoise::groups::get_all_groups",
+                            "Skipping corrupt group {}: {}",
+                            mls_group_id,
+                            e
+                        )
+                    })
+                    .ok()
             })
-            .collect::<Result<Vec<_>>>()
+            .collect())
     }
 
     // Save the group to the database
-    #[allow(dead_code)]
     pub async fn save(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Self> {
+        let started = Instant::now();
         let mut txn = wn.database.pool.begin().await?;
 
-        sqlx::query("INSERT INTO groups (mls_group_id, account_pubkey, nostr_group_id, name, description, admin_pubkeys, last_message_id, last_message_at, group_type, epoch, state) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        sqlx::query("INSERT INTO groups (mls_group_id, account_pubkey, nostr_group_id, name, description, admin_pubkeys, last_message_id, last_message_at, group_type, epoch, state, locale, snoozed_until, sensitive, archived_at, merged_into, last_read_message_id, last_read_message_at, content_filter, message_ttl, avatar_url, muted, mentions_only, note_to_self) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
             .bind(self.mls_group_id.clone())
             .bind(self.account_pubkey.to_hex().as_str())
             .bind(self.nostr_group_id.clone())
@@ -446,13 +865,42 @@ impl Group {
             .bind(serde_json::to_string(&self.admin_pubkeys)?)
             .bind(self.last_message_id.clone())
             .bind(self.last_message_at.map(|t| t.as_u64() as i64))
-            .bind(String::from(self.group_type.clone()))
+            .bind(self.group_type.stored())
             .bind(self.epoch as i64)
             .bind(String::from(self.state.clone()))
+            .bind(self.locale.clone())
+            .bind(self.snoozed_until.map(|t| t.as_u64() as i64))
+            .bind(self.sensitive)
+            .bind(self.archived_at.map(|t| t.as_u64() as i64))
+            .bind(self.merged_into.clone())
+            .bind(self.last_read_message_id.clone())
+            .bind(self.last_read_message_at.map(|t| t.as_u64() as i64))
+            .bind(serde_json::to_string(&self.content_filter)?)
+            .bind(self.message_ttl.map(|ttl| ttl as i64))
+            .bind(self.avatar_url.clone())
+            .bind(self.muted)
+            .bind(self.mentions_only)
+            .bind(matches!(self.group_type, GroupType::NoteToSelf))
             .execute(&mut *txn)
             .await?;
 
+        let account_pubkey = self.account_pubkey.to_hex();
+        integrity::record_checksum(
+            &mut *txn,
+            integrity::GROUPS_TABLE,
+            &integrity::group_record_id(&self.mls_group_id, &account_pubkey),
+            &integrity::group_checksum(
+                &self.nostr_group_id,
+                &self.name,
+                &self.description,
+                &serde_json::to_string(&self.admin_pubkeys)?,
+                &self.group_type.stored(),
+            ),
+        )
+        .await?;
+
         txn.commit().await?;
+        profiling::record("db.save_group", OperationKind::Database, started.elapsed());
         Ok(self.clone())
     }
 
@@ -494,11 +942,37 @@ impl Group {
             .await
             .map_err(GroupError::AccountError)?;
 
+        if message.kind.as_u16() == GROUP_NOTICE_KIND
+            && !self.admin_pubkeys.contains(&message.pubkey.to_hex())
+        {
+            return Err(GroupError::InvalidParameters(format!(
+                "Notice from non-admin {}",
+                message.pubkey
+            )));
+        }
+
+        let started = Instant::now();
         let mut txn = wn.database.pool.begin().await?;
 
         let event_json = serde_json::to_string(&message)?;
         let tags_json = serde_json::to_string(&message.tags)?;
         let tokens = pre_parsed_tokens.unwrap_or_else(|| parse(&message.content));
+        let expires_at = expiration(&message.tags);
+        let semantics = MessageSemantics::compute(
+            message.kind.as_u16(),
+            &message.content,
+            &message.tags,
+            &tokens,
+            &account.pubkey,
+        );
+        let display_content = if message.pubkey != account.pubkey {
+            account
+                .settings
+                .content_filter
+                .filter_inbound(&self.content_filter, &message.content)
+        } else {
+            None
+        };
 
         tracing::debug!(
             target: "whitenoise::groups::add_message",
@@ -521,9 +995,13 @@ impl Group {
             r#"
             INSERT INTO messages (
                 event_id, account_pubkey, author_pubkey, mls_group_id,
-                created_at, content, tags, event, outer_event_id, tokens, event_kind
+                created_at, content, tags, event, outer_event_id, tokens, event_kind,
+                expires_at, mentions_me, author_migrated_to
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (
+                SELECT new_pubkey FROM contact_key_migrations
+                WHERE account_pubkey = ? AND old_pubkey = ?
+            ))
             RETURNING id
             "#,
         )
@@ -538,6 +1016,10 @@ impl Group {
         .bind(&outer_event_id)
         .bind(serde_json::to_value(&tokens)?)
         .bind(i64::from(message.kind.as_u16()))
+        .bind(expires_at.map(|t| t.as_u64() as i64))
+        .bind(message.pubkey != account.pubkey && semantics.mentions_me)
+        .bind(account.pubkey.to_hex())
+        .bind(message.pubkey.to_hex())
         .execute(&mut *txn)
         .await?;
 
@@ -547,13 +1029,19 @@ impl Group {
             query
         );
 
-        // Update the last message id and last message at
+        // Update the last message id and last message at, in the same transaction so they can't
+        // point past the stored messages. Only this account's row is touched, and older messages
+        // arriving late don't move it back.
         sqlx::query(
-            "UPDATE groups SET last_message_id = ?, last_message_at = ? WHERE mls_group_id = ?",
+            "UPDATE groups SET last_message_id = ?, last_message_at = ?
+             WHERE mls_group_id = ? AND account_pubkey = ?
+               AND (last_message_at IS NULL OR last_message_at <= ?)",
         )
         .bind(message.id.unwrap().to_string())
         .bind(message.created_at.as_u64() as i64)
         .bind(&self.mls_group_id)
+        .bind(account.pubkey.to_hex())
+        .bind(message.created_at.as_u64() as i64)
         .execute(&mut *txn)
         .await?;
 
@@ -566,30 +1054,122 @@ impl Group {
         .fetch_one(&mut *txn)
         .await?;
 
+        usage_stats::record_message(&mut *txn, &account.pubkey, &self.mls_group_id, &message)
+            .await?;
+        media_library::index_message(
+            &mut *txn,
+            &account.pubkey,
+            &self.mls_group_id,
+            &message,
+            &tokens,
+        )
+        .await?;
+
         txn.commit().await?;
+        profiling::record("db.add_message", OperationKind::Database, started.elapsed());
 
-        // Send notification
-        if account.pubkey.to_hex() != message.pubkey.to_hex() {
-            let message_author = wn
-                .nostr
-                .client
-                .database()
-                .metadata(message.pubkey)
-                .await
-                .map_err(|e| GroupError::NostrError(nostr_sdk::client::Error::Database(e)))?;
-
-            if let Some(author) = message_author {
-                app_handle
-                    .notification()
-                    .builder()
-                    .title(
-                        author
-                            .display_name
-                            .unwrap_or(author.name.unwrap_or("Unknown".to_string())),
-                    )
-                    .body(message.content.clone())
-                    .show()
-                    .map_err(GroupError::NotificationError)?;
+        let tokens: Vec<SerializableToken> = serde_json::from_value(message_row.tokens).unwrap();
+        let (thread_root, reply_to) = thread_refs(&message.tags);
+
+        match message.kind.as_u16() {
+            DELETION_KIND => {
+                self.apply_deletion(&message, &account.pubkey, wn.clone(), &app_handle)
+                    .await?
+            }
+            EDIT_KIND => {
+                self.apply_edit(&message, &tokens, &account.pubkey, wn.clone(), &app_handle)
+                    .await?
+            }
+            GROUP_SETTINGS_KIND => {
+                self.apply_settings_update(&message, wn.clone(), &app_handle)
+                    .await?
+            }
+            GROUP_NOTE_KIND => {
+                if let Some(note) = group_notes::apply(
+                    &self.account_pubkey,
+                    &self.mls_group_id,
+                    &message,
+                    wn.clone(),
+                )
+                .await?
+                {
+                    app_handle
+                        .emit("group_note_updated", note)
+                        .map_err(GroupError::TauriError)?;
+                }
+            }
+            GROUP_TASK_KIND => {
+                let task_id = serde_json::from_str::<TaskAction>(&message.content)
+                    .map(|action| action.task_id().to_string());
+                if let Ok(task_id) = task_id {
+                    let tasks = group_tasks::for_group(self, wn.clone()).await?;
+                    if let Some(task) = tasks.into_iter().find(|task| task.task_id == task_id) {
+                        app_handle
+                            .emit("group_task_updated", task)
+                            .map_err(GroupError::TauriError)?;
+                    }
+                }
+            }
+            GROUP_CUSTOM_DATA_KIND => {
+                if let Some(data) = group_custom_data::apply(
+                    &self.account_pubkey,
+                    &self.mls_group_id,
+                    &message,
+                    wn.clone(),
+                )
+                .await?
+                {
+                    app_handle
+                        .emit("group_custom_data_updated", data)
+                        .map_err(GroupError::TauriError)?;
+                }
+            }
+            _ => {}
+        }
+
+        // Run the message through the notification filter, which also applies the group's
+        // notification level. Task updates only notify the assignees of new tasks.
+        if account.pubkey != message.pubkey
+            && !matches!(
+                semantics.system_message,
+                Some(
+                    SystemMessageKind::GroupSettings
+                        | SystemMessageKind::GroupNote
+                        | SystemMessageKind::GroupCustomData
+                )
+            )
+            && (semantics.system_message != Some(SystemMessageKind::GroupTask)
+                || semantics.mentions_me)
+        {
+            let level = self.notification_level();
+            let replies_to_me = (self.snoozed_until.is_some()
+                || level == NotificationLevel::MentionsOnly)
+                && Self::replies_to_account(&message, &account.pubkey, wn.clone()).await?;
+
+            match notifications::evaluate(
+                self.snoozed_until,
+                level,
+                account.settings.notifications.is_quiet_now(),
+                &semantics,
+                replies_to_me,
+                Timestamp::now(),
+            ) {
+                NotificationDecision::Suppress => {
+                    tracing::debug!(
+                        target: "whitenoise::groups::add_message",
+                        "Notification suppressed by the group's notification settings"
+                    );
+                }
+                decision => {
+                    if decision == NotificationDecision::NotifyAndUnsnooze {
+                        self.set_snoozed_until(None, wn.clone()).await?;
+                        app_handle
+                            .emit("group_unsnoozed", hex::encode(&self.mls_group_id))
+                            .map_err(GroupError::TauriError)?;
+                    }
+                    self.show_notification(&message, wn.clone(), &app_handle)
+                        .await?;
+                }
             }
         }
 
@@ -602,71 +1182,553 @@ impl Group {
             created_at: message.created_at,
             content: message.content.clone(),
             tags: message.tags.clone(),
+            payment_request: payments::payment_request_from_tags(
+                &message.tags,
+                Timestamp::now().as_u64(),
+            ),
             event: message,
             outer_event_id: EventId::from_hex(&message_row.outer_event_id)?,
-            tokens: serde_json::from_value(message_row.tokens).unwrap(),
+            tokens,
+            semantics,
+            reply_to,
+            thread_root,
+            deleted_at: None,
+            edited_at: None,
+            author_migrated_to: message_row
+                .author_migrated_to
+                .as_deref()
+                .and_then(|pubkey| PublicKey::from_hex(pubkey).ok()),
+            origin_group_id: None,
+            expires_at,
+            pending: false,
+            display_content,
         })
     }
 
-    /// Retrieves all messages for this group
+    /// Applies a group settings update sent by an admin and emits `group_updated`
+    ///
+    /// Updates from non-admins and malformed payloads are ignored.
+    async fn apply_settings_update(
+        &self,
+        message: &UnsignedEvent,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        if !self.admin_pubkeys.contains(&message.pubkey.to_hex()) {
+            tracing::warn!(
+                target: "whitenoise::groups::apply_settings_update",
+                "Ignoring group settings update from non-admin {}",
+                message.pubkey
+            );
+            return Ok(());
+        }
+        let update: GroupSettingsUpdate = match serde_json::from_str(&message.content) {
+            Ok(update) => update,
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::groups::apply_settings_update",
+                    "Ignoring malformed group settings update: {}",
+                    e
+                );
+                return Ok(());
+            }
+        };
+
+        let mut group = self.clone();
+        if let Some(sensitive) = update.sensitive {
+            group.set_sensitive(sensitive, wn.clone()).await?;
+            capture_protection::refresh(&self.account_pubkey, wn.clone(), app_handle).await;
+        }
+        if let Some(group_relays) = update.relays {
+            match relays::normalize_group_relays(&group_relays) {
+                Ok(group_relays) => {
+                    relays::set_group_relays(
+                        &self.mls_group_id,
+                        &self.account_pubkey,
+                        &group_relays,
+                        wn.clone(),
+                    )
+                    .await?;
+                    if let Err(e) = self.follow_relays(wn.clone()).await {
+                        tracing::warn!(
+                            target: "whitenoise::groups::apply_settings_update",
+                            "Failed to subscribe on the group's new relays: {}",
+                            e
+                        );
+                    }
+                }
+                Err(e) => tracing::warn!(
+                    target: "whitenoise::groups::apply_settings_update",
+                    "Ignoring invalid group relays: {}",
+                    e
+                ),
+            }
+        }
+        if let Some(locale) = update.locale {
+            let locale = Some(locale).filter(|tag| !tag.is_empty());
+            match locale.as_deref().map(Locale::parse).transpose() {
+                Ok(_) => group.update_locale(locale, wn.clone()).await?,
+                Err(e) => tracing::warn!(
+                    target: "whitenoise::groups::apply_settings_update",
+                    "Ignoring unsupported group locale: {}",
+                    e
+                ),
+            }
+        }
+
+        app_handle
+            .emit("group_updated", group)
+            .map_err(GroupError::TauriError)?;
+        Ok(())
+    }
+
+    /// Connects to the relays of the account's groups and renews the group message subscription,
+    /// so that messages on relays a group just moved to arrive
+    async fn follow_relays(&self, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+        let account = Account::find_by_pubkey(&self.account_pubkey, wn.clone()).await?;
+        relays::connect_group_relays(&account, wn.clone()).await?;
+        let group_ids = account.nostr_group_ids(wn.clone()).await?;
+        wn.nostr.subscribe_mls_group_messages(group_ids).await?;
+        Ok(())
+    }
+
+    /// Replaces the content of the message targeted by an edit and emits `mls_message_edited`
+    ///
+    /// Only the author of a message can edit it, deleted messages stay deleted, and an edit
+    /// older than the version we already show (edits can arrive out of order) is kept in the
+    /// history without superseding the newer one.
+    async fn apply_edit(
+        &self,
+        edit: &UnsignedEvent,
+        tokens: &[SerializableToken],
+        account_pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        let Some(target_id) = edit.tags.event_ids().next().copied() else {
+            return Ok(());
+        };
+
+        let mut txn = wn.database.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE messages SET content = ?, tokens = ?, edited_at = ?
+             WHERE event_id = ? AND mls_group_id = ? AND account_pubkey = ? AND author_pubkey = ?
+               AND event_kind != ? AND deleted_at IS NULL
+               AND (edited_at IS NULL OR edited_at <= ?)",
+        )
+        .bind(&edit.content)
+        .bind(serde_json::to_value(tokens)?)
+        .bind(edit.created_at.as_u64() as i64)
+        .bind(target_id.to_hex())
+        .bind(&self.mls_group_id)
+        .bind(account_pubkey.to_hex())
+        .bind(edit.pubkey.to_hex())
+        .bind(EDIT_KIND as i64)
+        .bind(edit.created_at.as_u64() as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::debug!(
+                target: "whitenoise::groups::apply_edit",
+                "Edit of {} not applied: not found, deleted, superseded or not authored by {}",
+                target_id,
+                edit.pubkey
+            );
+            return Ok(());
+        }
+
+        let row = sqlx::query_as::<_, MessageRow>(
+            "SELECT * FROM messages WHERE event_id = ? AND account_pubkey = ?",
+        )
+        .bind(target_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .fetch_one(&mut *txn)
+        .await?;
+        let message = Message::from(row);
+        media_library::reindex_links(
+            &mut *txn,
+            account_pubkey,
+            &self.mls_group_id,
+            &message.event,
+            tokens,
+        )
+        .await?;
+        txn.commit().await?;
+
+        app_handle
+            .emit(
+                "mls_message_edited",
+                MlsMessageEditedEvent {
+                    group_id: self.mls_group_id.clone(),
+                    message,
+                },
+            )
+            .map_err(GroupError::TauriError)?;
+        Ok(())
+    }
+
+    /// Tombstones the messages targeted by a kind 5 deletion and emits `mls_message_deleted`
+    /// for each of them
+    ///
+    /// As in NIP-09, only messages written by the author of the deletion are affected; the
+    /// rest of the `e` tags are ignored. Tombstoned messages keep their row (so replies and
+    /// reactions still resolve) but their content is cleared, along with the content and tags of
+    /// their edits, and their downloaded attachments are removed from the cache.
+    async fn apply_deletion(
+        &self,
+        deletion: &UnsignedEvent,
+        account_pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        let Some(deletion_event_id) = deletion.id else {
+            return Ok(());
+        };
+
+        for target_id in deletion.tags.event_ids() {
+            let mut txn = wn.database.pool.begin().await?;
+            let deleted: Option<(String,)> = sqlx::query_as(
+                "UPDATE messages
+                 SET deleted_at = ?, content = '', tokens = '[]',
+                     event = json_set(event, '$.content', '')
+                 WHERE event_id = ? AND mls_group_id = ? AND account_pubkey = ?
+                   AND author_pubkey = ? AND deleted_at IS NULL
+                 RETURNING tags",
+            )
+            .bind(deletion.created_at.as_u64() as i64)
+            .bind(target_id.to_hex())
+            .bind(&self.mls_group_id)
+            .bind(account_pubkey.to_hex())
+            .bind(deletion.pubkey.to_hex())
+            .fetch_optional(&mut *txn)
+            .await?;
+
+            // The edits of a message hold its later versions, so they go with it. Their tags are
+            // cleared too since they carry the new mentions and links.
+            if deleted.is_some() {
+                sqlx::query(
+                    "UPDATE messages
+                     SET deleted_at = ?, content = '', tokens = '[]', tags = '[]',
+                         event = json_set(event, '$.content', '', '$.tags', json('[]'))
+                     WHERE mls_group_id = ? AND account_pubkey = ? AND author_pubkey = ?
+                       AND event_kind = ? AND deleted_at IS NULL
+                       AND EXISTS (
+                           SELECT 1 FROM json_each(messages.tags) AS t
+                           WHERE json_extract(t.value, '$[0]') = 'e'
+                             AND json_extract(t.value, '$[1]') = ?
+                       )",
+                )
+                .bind(deletion.created_at.as_u64() as i64)
+                .bind(&self.mls_group_id)
+                .bind(account_pubkey.to_hex())
+                .bind(deletion.pubkey.to_hex())
+                .bind(EDIT_KIND as i64)
+                .bind(target_id.to_hex())
+                .execute(&mut *txn)
+                .await?;
+            }
+            txn.commit().await?;
+
+            let Some((tags,)) = deleted else {
+                tracing::debug!(
+                    target: "whitenoise::groups::apply_deletion",
+                    "Ignoring deletion of {}: not found, already deleted or not authored by {}",
+                    target_id,
+                    deletion.pubkey
+                );
+                continue;
+            };
+            if let Ok(tags) = serde_json::from_str::<Tags>(&tags) {
+                attachments::purge_cached(&tags, &self.mls_group_id, &wn.data_dir).await;
+            }
+
+            app_handle
+                .emit(
+                    "mls_message_deleted",
+                    MlsMessageDeletedEvent {
+                        group_id: self.mls_group_id.clone(),
+                        message_id: *target_id,
+                        deletion_event_id,
+                        deleted_at: deletion.created_at,
+                    },
+                )
+                .map_err(GroupError::TauriError)?;
+
+            self.emit_reactions_updated_if_reaction(
+                target_id,
+                account_pubkey,
+                wn.clone(),
+                app_handle,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// When a retracted message was a reaction, emits `mls_reactions_updated` with the new
+    /// aggregate for the message it reacted to
+    async fn emit_reactions_updated_if_reaction(
+        &self,
+        deleted_event_id: &EventId,
+        account_pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        let row = sqlx::query_as::<_, MessageRow>(
+            "SELECT * FROM messages WHERE event_id = ? AND account_pubkey = ? AND event_kind = ?",
+        )
+        .bind(deleted_event_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .bind(REACTION_KIND as i64)
+        .fetch_optional(&wn.database.pool)
+        .await?;
+        let Some(target_event_id) = row.and_then(|row| reaction_target(&Message::from(row).tags))
+        else {
+            return Ok(());
+        };
+
+        let reactions =
+            summarize_message_reactions(&self.mls_group_id, &target_event_id, wn).await?;
+        app_handle
+            .emit(
+                "mls_reactions_updated",
+                MlsReactionsUpdatedEvent {
+                    group_id: self.mls_group_id.clone(),
+                    target_event_id,
+                    reactions,
+                },
+            )
+            .map_err(GroupError::TauriError)?;
+        Ok(())
+    }
+
+    /// The ID of this group's OS notifications. Notifications of a group replace each other, and
+    /// the ID is handed back to `send_quick_reply` to dismiss the notification after replying.
+    pub fn notification_id(&self) -> i32 {
+        let mut bytes = [0u8; 4];
+        for (byte, id_byte) in bytes.iter_mut().zip(&self.mls_group_id) {
+            *byte = *id_byte;
+        }
+        (u32::from_be_bytes(bytes) & 0x7fff_ffff) as i32
+    }
+
+    /// Shows an OS notification for a message from another user
+    async fn show_notification(
+        &self,
+        message: &UnsignedEvent,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        let message_author = wn
+            .nostr
+            .client
+            .database()
+            .metadata(message.pubkey)
+            .await
+            .map_err(|e| GroupError::NostrError(nostr_sdk::client::Error::Database(e)))?;
+
+        if let Some(author) = message_author {
+            // Don't leak the content of sensitive groups onto the lock screen
+            let body = if self.sensitive {
+                "New message".to_string()
+            } else {
+                message.content.clone()
+            };
+            app_handle
+                .notification()
+                .builder()
+                .title(
+                    author
+                        .display_name
+                        .unwrap_or(author.name.unwrap_or("Unknown".to_string())),
+                )
+                .body(body)
+                .id(self.notification_id())
+                .action_type_id(QUICK_REPLY_ACTION_TYPE)
+                .extra("group_id", hex::encode(&self.mls_group_id))
+                .show()
+                .map_err(GroupError::NotificationError)?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the message references (via an `e` tag) a message authored by `pubkey`
+    async fn replies_to_account(
+        message: &UnsignedEvent,
+        pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<bool> {
+        for event_id in message.tags.event_ids() {
+            let authored = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM messages WHERE event_id = ? AND account_pubkey = ? AND author_pubkey = ?",
+            )
+            .bind(event_id.to_hex())
+            .bind(pubkey.to_hex())
+            .bind(pubkey.to_hex())
+            .fetch_one(&wn.database.pool)
+            .await?;
+            if authored > 0 {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Snoozes (or, with `None`, unsnoozes) notifications for this group
     ///
     /// # Arguments
+    /// * `until` - The time until which notifications are suppressed
     /// * `wn` - The Whitenoise application state
     ///
-    /// # Returns
-    /// * `Ok(Vec<Message>)` - A vector of all messages in the group if successful
-    /// * `Err(GroupError)` - If there's an error retrieving messages
+    /// # Errors
+    /// Returns `GroupError` if the database update fails
+    pub async fn set_snoozed_until(
+        &self,
+        until: Option<Timestamp>,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE groups SET snoozed_until = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(until.map(|t| t.as_u64() as i64))
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// How much of this group's activity notifies
+    pub fn notification_level(&self) -> NotificationLevel {
+        if self.muted {
+            NotificationLevel::Muted
+        } else if self.mentions_only {
+            NotificationLevel::MentionsOnly
+        } else {
+            NotificationLevel::All
+        }
+    }
+
+    /// Sets how much of this group's activity notifies
     ///
-    /// # Details
-    /// This method fetches all messages associated with this group from the database,
-    /// ordered by their creation timestamp.
+    /// # Arguments
+    /// * `level` - The new notification level
+    /// * `wn` - The Whitenoise application state
     ///
     /// # Errors
-    /// Returns `GroupError` if:
-    /// - Database query fails
-    /// - Message parsing fails
-    /// - Any other operation during message retrieval fails
-    pub async fn messages(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Message>> {
-        let pubkey = Account::get_active_pubkey(wn.clone())
-            .await
-            .map_err(GroupError::AccountError)?;
-
-        let message_rows = sqlx::query_as::<_, MessageRow>(
-            "SELECT * FROM messages WHERE mls_group_id = ? AND account_pubkey = ?",
+    /// Returns `GroupError` if the database update fails
+    pub async fn set_notification_level(
+        &self,
+        level: NotificationLevel,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE groups SET muted = ?, mentions_only = ? WHERE mls_group_id = ? AND account_pubkey = ?",
         )
+        .bind(level == NotificationLevel::Muted)
+        .bind(level == NotificationLevel::MentionsOnly)
         .bind(&self.mls_group_id)
-        .bind(pubkey.to_hex())
-        .fetch_all(&wn.database.pool)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
         .await?;
+        Ok(())
+    }
 
-        tracing::debug!(
-            target: "whitenoise::groups::messages",
-            "Messages: {:?}",
-            message_rows
-        );
-
-        message_rows
-            .into_iter()
-            .map(|row| Ok(Message::from(row)))
-            .collect::<Result<Vec<_>>>()
+    /// Sets the display content of a received message according to the inbound content filter
+    fn display_filtered(&self, mut message: Message, settings: &ContentFilterSettings) -> Message {
+        if message.author_pubkey != message.account_pubkey {
+            message.display_content =
+                settings.filter_inbound(&self.content_filter, &message.content);
+        }
+        message
     }
 
-    /// Retrieves all members of this group
+    /// Retrieves a page of messages for this group without loading the whole transcript
     ///
     /// # Arguments
+    /// * `before` - Only return messages ordered before this cursor. `None` starts from the newest message.
+    /// * `limit` - Maximum number of messages to return (clamped to `MAX_MESSAGE_PAGE_SIZE`)
     /// * `wn` - The Whitenoise application state
     ///
     /// # Returns
-    /// * `Ok(Vec<PublicKey>)` - A vector of all member public keys if successful
-    /// * `Err(GroupError)` - If there's an error retrieving members
+    /// * `Ok(Vec<Message>)` - Up to `limit` messages, ordered oldest to newest
+    /// * `Err(GroupError)` - If there's an error retrieving messages
     ///
     /// # Details
-    /// This method fetches all member public keys from the MLS group and converts them
-    /// to Nostr public keys.
-    ///
-    /// # Errors
-    /// Returns `GroupError` if:
-    /// - MLS group operations fail
+    /// Pages are read newest-first from the database (backed by the `(mls_group_id, created_at)` index)
+    /// and returned in chronological order so they can be prepended to an existing transcript.
+    /// Pass the cursor of the oldest message in a page as `before` to fetch the next one.
+    pub async fn messages_page(
+        &self,
+        before: Option<MessageCursor>,
+        limit: usize,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<Message>> {
+        let account = Account::get_active(wn.clone())
+            .await
+            .map_err(GroupError::AccountError)?;
+
+        let message_rows = message_page_rows(
+            &wn.database.pool,
+            &self.mls_group_id,
+            &account.pubkey,
+            before,
+            limit,
+        )
+        .await?;
+
+        tracing::debug!(
+            target: "whitenoise::groups::messages_page",
+            "Fetched {} messages",
+            message_rows.len()
+        );
+
+        Ok(message_rows
+            .into_iter()
+            .map(|row| self.display_filtered(Message::from(row), &account.settings.content_filter))
+            .collect())
+    }
+
+    /// Retrieves the group's admin notices that haven't been deleted, newest first
+    pub async fn notices(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Message>> {
+        let account = Account::get_active(wn.clone())
+            .await
+            .map_err(GroupError::AccountError)?;
+
+        let message_rows = sqlx::query_as::<_, MessageRow>(
+            "SELECT * FROM messages
+             WHERE mls_group_id = ? AND account_pubkey = ? AND event_kind = ? AND deleted_at IS NULL
+             ORDER BY created_at DESC, id DESC",
+        )
+        .bind(&self.mls_group_id)
+        .bind(account.pubkey.to_hex())
+        .bind(i64::from(GROUP_NOTICE_KIND))
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        Ok(message_rows
+            .into_iter()
+            .map(|row| self.display_filtered(Message::from(row), &account.settings.content_filter))
+            .collect())
+    }
+
+    /// Retrieves all members of this group
+    ///
+    /// # Arguments
+    /// * `wn` - The Whitenoise application state
+    ///
+    /// # Returns
+    /// * `Ok(Vec<PublicKey>)` - A vector of all member public keys if successful
+    /// * `Err(GroupError)` - If there's an error retrieving members
+    ///
+    /// # Details
+    /// This method fetches all member public keys from the MLS group and converts them
+    /// to Nostr public keys.
+    ///
+    /// # Errors
+    /// Returns `GroupError` if:
+    /// - MLS group operations fail
     /// - Public key parsing fails
     /// - Any other operation during member retrieval fails
     pub async fn members(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<PublicKey>> {
@@ -682,6 +1744,217 @@ impl Group {
             })
     }
 
+    /// Brings the indexed members of this group in line with its MLS members, writing only what
+    /// changed. Members added after the group was first indexed are recorded as having joined,
+    /// with fresh keys, in the current epoch. A member's leaf counts as updated when its
+    /// encryption key changes; until it's seen changing, when it was last updated is unknown.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<PublicKey>)` - The current members
+    pub async fn index_members(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<PublicKey>> {
+        let members = self.members(wn.clone()).await?;
+        let (epoch, leaf_keys) = {
+            let nostr_mls = wn.nostr_mls.lock().await;
+            let mls_group = load_mls_group(&nostr_mls, &self.mls_group_id)?;
+            (mls_group.epoch().as_u64(), leaf_keys(&mls_group))
+        };
+        let member_hexes: Vec<String> = members.iter().map(|member| member.to_hex()).collect();
+
+        let indexed: HashMap<String, Option<String>> = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT member_pubkey, leaf_key FROM group_members WHERE group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let first_indexing = indexed.is_empty();
+        let former: Vec<&String> = indexed
+            .keys()
+            .filter(|pubkey| !member_hexes.contains(pubkey))
+            .collect();
+        let changed: Vec<(&String, Option<&String>)> = member_hexes
+            .iter()
+            .map(|pubkey| (pubkey, leaf_keys.get(pubkey)))
+            .filter(|(pubkey, leaf_key)| match indexed.get(*pubkey) {
+                Some(indexed_key) => indexed_key.as_ref() != *leaf_key,
+                None => true,
+            })
+            .collect();
+        if former.is_empty() && changed.is_empty() {
+            return Ok(members);
+        }
+
+        let now = Timestamp::now().as_u64() as i64;
+        let mut txn = wn.database.pool.begin().await?;
+        for former in former {
+            sqlx::query(
+                "DELETE FROM group_members WHERE group_id = ? AND account_pubkey = ? AND member_pubkey = ?",
+            )
+            .bind(&self.mls_group_id)
+            .bind(self.account_pubkey.to_hex())
+            .bind(former)
+            .execute(&mut *txn)
+            .await?;
+        }
+        for (member, leaf_key) in changed {
+            match indexed.get(member) {
+                None => {
+                    let fresh = (!first_indexing).then_some(now);
+                    sqlx::query(
+                        "INSERT INTO group_members (group_id, account_pubkey, member_pubkey, first_seen_epoch, leaf_key, leaf_updated_at)
+                         VALUES (?, ?, ?, ?, ?, ?)",
+                    )
+                    .bind(&self.mls_group_id)
+                    .bind(self.account_pubkey.to_hex())
+                    .bind(member)
+                    .bind(epoch as i64)
+                    .bind(leaf_key)
+                    .bind(fresh)
+                    .execute(&mut *txn)
+                    .await?;
+                }
+                Some(indexed_key) => {
+                    // A key we had never seen before isn't an update we saw
+                    let updated_at = indexed_key.is_some().then_some(now);
+                    sqlx::query(
+                        "UPDATE group_members SET leaf_key = ?, leaf_updated_at = COALESCE(?, leaf_updated_at)
+                         WHERE group_id = ? AND account_pubkey = ? AND member_pubkey = ?",
+                    )
+                    .bind(leaf_key)
+                    .bind(updated_at)
+                    .bind(&self.mls_group_id)
+                    .bind(self.account_pubkey.to_hex())
+                    .bind(member)
+                    .execute(&mut *txn)
+                    .await?;
+                }
+            }
+        }
+        txn.commit().await?;
+
+        Ok(members)
+    }
+
+    /// The group's current members with their role, when they were first seen and last active,
+    /// whether they're stale, and what the contact cache knows about them, in the order of the MLS
+    /// group
+    ///
+    /// Nothing is written: what's known about members is whatever the last [`Group::index_members`]
+    /// recorded. Profiles of members that aren't cached contacts come from the local event cache;
+    /// nothing is fetched from relays.
+    pub async fn member_details(
+        &self,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<GroupMember>> {
+        let members = self.members(wn.clone()).await?;
+        let admins = self.admins()?;
+
+        let rows = sqlx::query_as::<_, GroupMemberRow>(
+            "SELECT m.member_pubkey, m.first_seen_epoch, m.leaf_updated_at, c.petname, c.enriched,
+                 (SELECT MAX(msg.created_at) FROM messages msg
+                  WHERE msg.mls_group_id = m.group_id AND msg.account_pubkey = m.account_pubkey
+                    AND msg.author_pubkey = m.member_pubkey) AS last_message_at
+             FROM group_members m
+             LEFT JOIN contacts c ON c.account_pubkey = m.account_pubkey AND c.pubkey = m.member_pubkey
+             WHERE m.group_id = ? AND m.account_pubkey = ?",
+        )
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+        let mut rows: HashMap<String, GroupMemberRow> = rows
+            .into_iter()
+            .map(|row| (row.member_pubkey.clone(), row))
+            .collect();
+
+        let now = Timestamp::now();
+        let mut details = Vec::with_capacity(members.len());
+        for pubkey in members {
+            let row = rows.remove(&pubkey.to_hex());
+            let leaf_updated_at = row.as_ref().and_then(|row| row.leaf_updated_at);
+            let last_message_at = row.as_ref().and_then(|row| row.last_message_at);
+            let enriched: Option<EnrichedContact> = row
+                .as_ref()
+                .and_then(|row| row.enriched.as_deref())
+                .map(serde_json::from_str)
+                .transpose()?;
+            let metadata = match &enriched {
+                Some(enriched) => enriched.metadata.clone(),
+                None => wn
+                    .nostr
+                    .query_user_metadata(pubkey)
+                    .await?
+                    .unwrap_or_default(),
+            };
+            details.push(GroupMember {
+                is_admin: admins.contains(&pubkey),
+                first_seen_epoch: row
+                    .as_ref()
+                    .and_then(|row| row.first_seen_epoch)
+                    .map(|epoch| epoch as u64),
+                last_message_at: last_message_at.map(|at| Timestamp::from(at as u64)),
+                leaf_updated_at: leaf_updated_at.map(|at| Timestamp::from(at as u64)),
+                stale_keys: group_security::is_stale(leaf_updated_at, now),
+                inactive: group_security::is_inactive(last_message_at, leaf_updated_at, now),
+                petname: row.and_then(|row| row.petname),
+                enriched,
+                metadata,
+                pubkey,
+            });
+        }
+        Ok(details)
+    }
+
+    /// Returns the active account's groups that `pubkey` is a member of, most recently active
+    /// first. Archived groups are left out.
+    ///
+    /// The member index of every group is brought up to date with its MLS members first, so
+    /// members who joined or left since it was last indexed are accounted for. Only what changed
+    /// is written.
+    pub async fn mutual_groups(
+        pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<Self>> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone())
+            .await
+            .map_err(GroupError::AccountError)?;
+
+        let active = sqlx::query_as::<_, GroupRow>(
+            "SELECT * FROM groups WHERE account_pubkey = ? AND archived_at IS NULL",
+        )
+        .bind(account_pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+        for row in active {
+            let group = Self::from_row(row, account_pubkey)?;
+            if let Err(e) = group.index_members(wn.clone()).await {
+                tracing::warn!(
+                    target: "whitenoise::groups::mutual_groups",
+                    "Failed to index members of group {}: {}",
+                    hex::encode(&group.mls_group_id),
+                    e
+                );
+            }
+        }
+
+        let rows = sqlx::query_as::<_, GroupRow>(
+            "SELECT g.* FROM groups g
+             JOIN group_members m ON m.group_id = g.mls_group_id AND m.account_pubkey = g.account_pubkey
+             WHERE g.account_pubkey = ? AND m.member_pubkey = ? AND g.archived_at IS NULL
+             ORDER BY COALESCE(g.last_message_at, 0) DESC",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| Ok(Self::from_row(row, account_pubkey)?))
+            .collect()
+    }
+
     /// Retrieves all admin members of this group
     ///
     /// # Returns
@@ -734,6 +2007,24 @@ impl Group {
         .await?)
     }
 
+    /// The group's relays that events can be published to, leaving out the ones the active
+    /// account has blacklisted. Groups without relays of their own publish to the relays their
+    /// members read from, according to the members' cached NIP-65 relay lists.
+    pub async fn publish_relays(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<String>> {
+        let blacklist = RelayBlacklist::load(wn.clone()).await?;
+        let mut relays = self.relays(wn.clone()).await?;
+        if relays.is_empty() {
+            for member in self.members(wn.clone()).await? {
+                for relay in wn.nostr.query_user_read_relays(member).await? {
+                    if !relays.contains(&relay) {
+                        relays.push(relay);
+                    }
+                }
+            }
+        }
+        Ok(blacklist.filter(relays))
+    }
+
     /// Updates the group's keys for the current user
     ///
     /// # Arguments
@@ -750,6 +2041,7 @@ impl Group {
     /// 3. Encrypts the commit message with the previous epoch's key
     /// 4. Publishes the commit message to group relays
     /// 5. Stores the new epoch secret in the secrets store
+    /// 6. Records when the user's leaf was last updated, for [`crate::group_security`]
     ///
     /// # Errors
     /// Returns `GroupError` if:
@@ -760,32 +2052,106 @@ impl Group {
     /// - Secret storage fails
     /// - Any other operation during key update fails
     pub async fn self_update_keys(&self, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
-        let serialized_commit_message: Vec<u8>;
-        let current_exporter_secret_hex: String;
-        let new_exporter_secret_hex: String;
-        let new_epoch: u64;
-        {
+        let self_update_result = {
             let nostr_mls = wn.nostr_mls.lock().await;
-            let self_update_result = nostr_mls
-                .self_update(self.mls_group_id.clone())
-                .map_err(GroupError::MlsError)?;
-            serialized_commit_message = self_update_result.serialized_message;
-            current_exporter_secret_hex = self_update_result.current_exporter_secret_hex;
-            new_exporter_secret_hex = self_update_result.new_exporter_secret_hex;
-            new_epoch = self_update_result.new_epoch;
-        }
+            profiling::time("mls.self_update", OperationKind::Mls, || {
+                nostr_mls.self_update(self.mls_group_id.clone())
+            })
+            .map_err(GroupError::MlsError)?
+        };
 
-        // Send 445 event with commit_message - needs to be encrypted to the last epoch's exporter secret key
+        // TODO: This is assuming we don't have any welcome messages in this commit we probably need to handle that case in the future
+        self.publish_commit(
+            &self_update_result.serialized_message,
+            &self_update_result.current_exporter_secret_hex,
+            wn.clone(),
+        )
+        .await?;
+        self.store_epoch_secret(
+            self_update_result.new_epoch,
+            self_update_result.new_exporter_secret_hex,
+            wn.clone(),
+        )
+        .await?;
 
-        let last_epoch_export_nostr_keys =
-            Keys::parse(current_exporter_secret_hex.as_str()).map_err(GroupError::KeyError)?;
+        // Our own leaf got fresh keys even if its previous key was never seen
+        self.index_members(wn.clone()).await?;
+        sqlx::query(
+            "UPDATE group_members SET leaf_updated_at = ?
+             WHERE group_id = ? AND account_pubkey = ? AND member_pubkey = ?",
+        )
+        .bind(Timestamp::now().as_u64() as i64)
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Adds a member to the group with one of their key packages
+    ///
+    /// Publishes the Add commit to the group relays and stores the new epoch secret, like
+    /// [`Group::self_update_keys`]. The member's welcome isn't sent; it's returned for the caller
+    /// to wrap and publish to them.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The serialized welcome message for the new member
+    /// * `Err(GroupError)` - If the MLS operation, publishing the commit or storing the secret
+    ///   fails, or [`GroupError::MemberAdditionsUnsupported`] while
+    ///   [`MEMBER_ADDITIONS_ENABLED`] is off
+    pub async fn add_member(
+        &self,
+        key_package: KeyPackage,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<u8>> {
+        if !MEMBER_ADDITIONS_ENABLED {
+            return Err(GroupError::MemberAdditionsUnsupported);
+        }
+        let add_result = {
+            let nostr_mls = wn.nostr_mls.lock().await;
+            profiling::time("mls.add_members", OperationKind::Mls, || {
+                nostr_mls.add_members(self.mls_group_id.clone(), vec![key_package])
+            })
+            .map_err(GroupError::MlsError)?
+        };
 
-        let encrypted_content = nip44::encrypt(
-            last_epoch_export_nostr_keys.secret_key(),
-            &last_epoch_export_nostr_keys.public_key(),
-            &serialized_commit_message,
-            nip44::Version::V2,
+        self.publish_commit(
+            &add_result.serialized_commit_message,
+            &add_result.current_exporter_secret_hex,
+            wn.clone(),
+        )
+        .await?;
+        self.store_epoch_secret(
+            add_result.new_epoch,
+            add_result.new_exporter_secret_hex,
+            wn.clone(),
         )
+        .await?;
+        self.index_members(wn).await?;
+        Ok(add_result.serialized_welcome_message)
+    }
+
+    /// Publishes a commit to the group relays, encrypted with the export secret of the epoch it
+    /// was made in
+    async fn publish_commit(
+        &self,
+        serialized_commit_message: &[u8],
+        current_exporter_secret_hex: &str,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        // Send 445 event with commit_message - needs to be encrypted to the last epoch's exporter secret key
+        let last_epoch_export_nostr_keys =
+            Keys::parse(current_exporter_secret_hex).map_err(GroupError::KeyError)?;
+
+        let encrypted_content = profiling::time("nip44.encrypt", OperationKind::Crypto, || {
+            nip44::encrypt(
+                last_epoch_export_nostr_keys.secret_key(),
+                &last_epoch_export_nostr_keys.public_key(),
+                serialized_commit_message,
+                nip44::Version::V2,
+            )
+        })
         .map_err(GroupError::NostrEncryptionError)?;
 
         let ephemeral_nostr_keys = Keys::generate();
@@ -799,19 +2165,23 @@ impl Group {
             .map_err(GroupError::NostrEventError)?;
 
         tracing::debug!(
-            target: "whitenoise::groups::self_update_keys",
+            target: "whitenoise::groups::publish_commit",
             "Publishing MLS commit message event to group relays"
         );
 
-        wn.nostr
-            .client
-            .send_event_to(self.relays(wn.clone()).await?, &commit_message_event)
-            .await
-            .map_err(GroupError::NostrError)?;
-
-        // TODO: This is assuming we don't have any welcome messages in this commit we probably need to handle that case in the future
+        let relays = self.publish_relays(wn.clone()).await?;
+        relay_blacklist::send_event(&commit_message_event, relays, wn).await?;
+        Ok(())
+    }
 
-        // Add the new epoch secret to the secret store
+    /// Adds the secret of a new epoch to the secret store and shares it with the account's other
+    /// devices
+    pub(crate) async fn store_epoch_secret(
+        &self,
+        new_epoch: u64,
+        new_exporter_secret_hex: String,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
         secrets_store::store_mls_export_secret(
             self.mls_group_id.clone(),
             new_epoch,
@@ -820,8 +2190,612 @@ impl Group {
         )
         .map_err(GroupError::SecretsStoreError)?;
 
+        device_sync::share(
+            vec![SyncDelta::EpochSecret {
+                mls_group_id: hex::encode(&self.mls_group_id),
+                epoch: new_epoch,
+                secret: new_exporter_secret_hex,
+            }],
+            wn,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Stores (or clears) the locale hint for this group
+    ///
+    /// This only changes the local copy; admins distribute the locale to the rest of the group
+    /// with a `GroupSettingsUpdate` message.
+    ///
+    /// # Arguments
+    /// * `locale` - A supported locale tag (e.g. "es", "pt-BR"), or `None` to clear it
+    /// * `wn` - The Whitenoise application state
+    ///
+    /// # Errors
+    /// Returns `GroupError` if:
+    /// - The locale isn't supported
+    /// - Database update fails
+    pub async fn update_locale(
+        &mut self,
+        locale: Option<String>,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        if let Some(tag) = locale.as_deref() {
+            Locale::parse(tag).map_err(|e| GroupError::InvalidParameters(e.to_string()))?;
+        }
+
+        sqlx::query("UPDATE groups SET locale = ? WHERE mls_group_id = ? AND account_pubkey = ?")
+            .bind(&locale)
+            .bind(&self.mls_group_id)
+            .bind(self.account_pubkey.to_hex())
+            .execute(&wn.database.pool)
+            .await?;
+
+        self.locale = locale;
+        Ok(())
+    }
+
+    /// Stores this group's overrides of the account's content filter settings
+    pub async fn set_content_filter(
+        &mut self,
+        content_filter: GroupContentFilter,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE groups SET content_filter = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(serde_json::to_string(&content_filter)?)
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+        self.content_filter = content_filter;
+        Ok(())
+    }
+
+    /// Sets the default expiry of messages sent to the group. `None` keeps them until deleted.
+    pub async fn set_message_ttl(
+        &mut self,
+        message_ttl: Option<u64>,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        if message_ttl == Some(0) {
+            return Err(GroupError::InvalidParameters(
+                "Message TTL must be greater than zero".to_string(),
+            ));
+        }
+        sqlx::query(
+            "UPDATE groups SET message_ttl = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(message_ttl.map(|ttl| ttl as i64))
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+        self.message_ttl = message_ttl;
+        Ok(())
+    }
+
+    /// Sets the picture shown for the group on this device
+    pub async fn set_avatar_url(
+        &mut self,
+        avatar_url: Option<String>,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE groups SET avatar_url = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&avatar_url)
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+        self.avatar_url = avatar_url;
+        Ok(())
+    }
+
+    /// Stores whether the group is sensitive
+    ///
+    /// This only changes the local copy; admins distribute the flag to the rest of the group
+    /// with a `GroupSettingsUpdate` message.
+    pub async fn set_sensitive(
+        &mut self,
+        sensitive: bool,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE groups SET sensitive = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(sensitive)
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+        self.sensitive = sensitive;
+        Ok(())
+    }
+
+    /// Checks that this group can be merged into `target`
+    pub fn validate_merge(&self, target: &Group) -> Result<()> {
+        if self.mls_group_id == target.mls_group_id {
+            return Err(GroupError::InvalidParameters(
+                "Can't merge a group into itself".to_string(),
+            ));
+        }
+        if self.archived_at.is_some() || target.archived_at.is_some() {
+            return Err(GroupError::InvalidParameters(
+                "Archived groups can't be merged".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Merges this group's local transcript into `target` and archives this group
+    ///
+    /// Messages keep their original group in `origin_group_id`. Nothing is sent to the other
+    /// members; the merge only affects the local copy of both groups.
+    ///
+    /// # Returns
+    /// * `Ok(Group)` - The target group with its last message updated
+    /// * `Err(GroupError::InvalidParameters)` - If the groups are the same or either is archived
+    pub async fn merge_into(
+        &mut self,
+        target: &Group,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Group> {
+        self.validate_merge(target)?;
+
+        let account_pubkey = self.account_pubkey.to_hex();
+        let archived_at = Timestamp::now();
+        let mut txn = wn.database.pool.begin().await?;
+
+        let moved = sqlx::query(
+            "UPDATE messages SET origin_group_id = COALESCE(origin_group_id, mls_group_id), mls_group_id = ?
+             WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&target.mls_group_id)
+        .bind(&self.mls_group_id)
+        .bind(&account_pubkey)
+        .execute(&mut *txn)
+        .await?;
+
+        sqlx::query(
+            "UPDATE message_outbox SET mls_group_id = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&target.mls_group_id)
+        .bind(&self.mls_group_id)
+        .bind(&account_pubkey)
+        .execute(&mut *txn)
+        .await?;
+
+        sqlx::query(
+            "UPDATE group_media SET mls_group_id = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&target.mls_group_id)
+        .bind(&self.mls_group_id)
+        .bind(&account_pubkey)
+        .execute(&mut *txn)
+        .await?;
+
+        sqlx::query(
+            "UPDATE groups SET (last_message_id, last_message_at) = (
+                 SELECT event_id, created_at FROM messages
+                 WHERE mls_group_id = groups.mls_group_id AND account_pubkey = groups.account_pubkey
+                 ORDER BY created_at DESC LIMIT 1
+             )
+             WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(&target.mls_group_id)
+        .bind(&account_pubkey)
+        .execute(&mut *txn)
+        .await?;
+
+        sqlx::query(
+            "UPDATE groups SET archived_at = ?, merged_into = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(archived_at.as_u64() as i64)
+        .bind(&target.mls_group_id)
+        .bind(&self.mls_group_id)
+        .bind(&account_pubkey)
+        .execute(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        tracing::debug!(
+            target: "whitenoise::groups::merge_into",
+            "Moved {} messages from group {} into group {}",
+            moved.rows_affected(),
+            hex::encode(&self.mls_group_id),
+            hex::encode(&target.mls_group_id)
+        );
+
+        self.archived_at = Some(archived_at);
+        self.merged_into = Some(target.mls_group_id.clone());
+        Self::find_by_mls_group_id(&target.mls_group_id, wn).await
+    }
+
+    /// Moves the local read cursor forward to `up_to_event_id`, a message in this group
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the cursor moved
+    /// * `Ok(false)` - If the user had already read this or a later message
+    /// * `Err(GroupError::InvalidParameters)` - If the message isn't part of this group
+    pub async fn mark_read(
+        &mut self,
+        up_to_event_id: &EventId,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<bool> {
+        let account_pubkey = self.account_pubkey.to_hex();
+        let created_at: Option<i64> = sqlx::query_scalar(
+            "SELECT created_at FROM messages WHERE event_id = ? AND account_pubkey = ? AND mls_group_id = ?",
+        )
+        .bind(up_to_event_id.to_hex())
+        .bind(&account_pubkey)
+        .bind(&self.mls_group_id)
+        .fetch_optional(&wn.database.pool)
+        .await?;
+        let created_at = created_at
+            .map(|t| Timestamp::from(t as u64))
+            .ok_or_else(|| {
+                GroupError::InvalidParameters(format!(
+                    "Message {} not found in this group",
+                    up_to_event_id
+                ))
+            })?;
+
+        self.move_read_cursor(up_to_event_id, created_at, wn).await
+    }
+
+    /// Moves the local read cursor forward to a message created at `created_at`, which doesn't
+    /// have to be stored yet, e.g. when another device of the account read it first
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the cursor moved
+    /// * `Ok(false)` - If the user had already read this or a later message
+    pub async fn move_read_cursor(
+        &mut self,
+        up_to_event_id: &EventId,
+        created_at: Timestamp,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<bool> {
+        // Like message pages, the cursor is ordered by (created_at, event_id) so messages from
+        // the same second aren't skipped or read twice
+        let read_up_to = self.last_read_message_at.zip(
+            self.last_read_message_id
+                .as_deref()
+                .and_then(|id| EventId::from_hex(id).ok()),
+        );
+        if read_up_to.is_some_and(|read_up_to| read_up_to >= (created_at, *up_to_event_id)) {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "UPDATE groups SET last_read_message_id = ?, last_read_message_at = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+        )
+        .bind(up_to_event_id.to_hex())
+        .bind(created_at.as_u64() as i64)
+        .bind(&self.mls_group_id)
+        .bind(self.account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+        self.last_read_message_id = Some(up_to_event_id.to_hex());
+        self.last_read_message_at = Some(created_at);
+        Ok(true)
+    }
+
+    /// Returns the number of unread chat messages from other members in each of the active
+    /// account's groups, keyed by hex encoded MLS group ID
+    ///
+    /// Deleted messages, system messages and archived groups aren't counted.
+    pub async fn unread_counts(wn: tauri::State<'_, Whitenoise>) -> Result<HashMap<String, u64>> {
+        let account = Account::get_active(wn.clone())
+            .await
+            .map_err(GroupError::AccountError)?;
+        Self::unread_counts_for_account(&account.pubkey, wn).await
+    }
+
+    /// Same as [`Group::unread_counts`], for any account
+    pub async fn unread_counts_for_account(
+        account_pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<HashMap<String, u64>> {
+        let system_kinds = SYSTEM_MESSAGE_KINDS
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let query = format!(
+            "SELECT g.mls_group_id, COUNT(m.id) FROM groups g
+             LEFT JOIN messages m ON m.mls_group_id = g.mls_group_id
+                 AND m.account_pubkey = g.account_pubkey
+                 AND m.author_pubkey != g.account_pubkey
+                 AND m.deleted_at IS NULL
+                 AND m.event_kind NOT IN ({})
+                 AND {}
+             WHERE g.account_pubkey = ? AND g.archived_at IS NULL
+             GROUP BY g.mls_group_id",
+            system_kinds, UNREAD_CONDITION
+        );
+
+        let rows: Vec<(Vec<u8>, i64)> = sqlx::query_as(&query)
+            .bind(account_pubkey.to_hex())
+            .fetch_all(&wn.database.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(mls_group_id, unread)| (hex::encode(mls_group_id), unread as u64))
+            .collect())
+    }
+
+    /// Returns the number of unread messages mentioning the active account in each of its
+    /// groups that has any, keyed by hex encoded MLS group ID
+    ///
+    /// Deleted messages and archived groups aren't counted.
+    pub async fn unread_mention_counts(
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<HashMap<String, u64>> {
+        let account = Account::get_active(wn.clone())
+            .await
+            .map_err(GroupError::AccountError)?;
+
+        let query = format!(
+            "SELECT g.mls_group_id, COUNT(m.id) FROM groups g
+             JOIN messages m ON m.mls_group_id = g.mls_group_id
+                 AND m.account_pubkey = g.account_pubkey
+                 AND m.mentions_me
+                 AND m.deleted_at IS NULL
+                 AND {}
+             WHERE g.account_pubkey = ? AND g.archived_at IS NULL
+             GROUP BY g.mls_group_id",
+            UNREAD_CONDITION
+        );
+        let rows: Vec<(Vec<u8>, i64)> = sqlx::query_as(&query)
+            .bind(account.pubkey.to_hex())
+            .fetch_all(&wn.database.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(mls_group_id, mentions)| (hex::encode(mls_group_id), mentions as u64))
+            .collect())
+    }
+
+    /// The resolved locale used when formatting text for this group
+    pub fn resolved_locale(&self) -> Locale {
+        Locale::from_hint(self.locale.as_deref())
+    }
+
+    /// Removes the group and its relays from the database, e.g. when creating it failed
+    /// partway. Its messages aren't touched.
+    pub async fn remove(&self, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+        let account_pubkey = self.account_pubkey.to_hex();
+        let mut txn = wn.database.pool.begin().await?;
+
+        sqlx::query("DELETE FROM group_relays WHERE group_id = ? AND account_pubkey = ?")
+            .bind(&self.mls_group_id)
+            .bind(&account_pubkey)
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM groups WHERE mls_group_id = ? AND account_pubkey = ?")
+            .bind(&self.mls_group_id)
+            .bind(&account_pubkey)
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM record_checksums WHERE table_name = ? AND record_id = ?")
+            .bind(integrity::GROUPS_TABLE)
+            .bind(integrity::group_record_id(
+                &self.mls_group_id,
+                &account_pubkey,
+            ))
+            .execute(&mut *txn)
+            .await?;
+
+        txn.commit().await?;
         Ok(())
     }
+}
 
-    // pub fn remove(&self, wn: &tauri::State<'_, Whitenoise>) -> Result<()> {}
+/// Reads a page of a group's transcript ordered by `(created_at, event_id)`, oldest to newest
+async fn message_page_rows(
+    pool: &sqlx::SqlitePool,
+    mls_group_id: &[u8],
+    account_pubkey: &PublicKey,
+    before: Option<MessageCursor>,
+    limit: usize,
+) -> Result<Vec<MessageRow>> {
+    let limit = limit.clamp(1, MAX_MESSAGE_PAGE_SIZE);
+    let (before_at, before_id) = match before {
+        Some(cursor) => (cursor.created_at.as_u64() as i64, cursor.event_id.to_hex()),
+        None => (i64::MAX, String::new()),
+    };
+
+    let mut message_rows = sqlx::query_as::<_, MessageRow>(
+        "SELECT m.*, EXISTS(
+             SELECT 1 FROM message_outbox o
+             WHERE o.event_id = m.event_id AND o.account_pubkey = m.account_pubkey AND o.state = 'Pending'
+         ) AS pending
+         FROM messages m
+         WHERE m.mls_group_id = ? AND m.account_pubkey = ?
+           AND (m.created_at < ? OR (m.created_at = ? AND m.event_id < ?))
+         ORDER BY m.created_at DESC, m.event_id DESC
+         LIMIT ?",
+    )
+    .bind(mls_group_id)
+    .bind(account_pubkey.to_hex())
+    .bind(before_at)
+    .bind(before_at)
+    .bind(before_id)
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await?;
+
+    message_rows.reverse();
+    Ok(message_rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tempfile::tempdir;
+
+    async fn setup_test_pool() -> (sqlx::SqlitePool, tempfile::TempDir) {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        std::fs::File::create(&db_path).expect("Failed to create database file");
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&format!("sqlite:{}", db_path.display()))
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "CREATE TABLE messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                event_id TEXT NOT NULL,
+                account_pubkey TEXT NOT NULL,
+                author_pubkey TEXT NOT NULL,
+                event_kind INTEGER NOT NULL,
+                mls_group_id BLOB NOT NULL,
+                created_at INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                tags TEXT,
+                event TEXT NOT NULL,
+                outer_event_id TEXT NOT NULL,
+                tokens TEXT NOT NULL,
+                deleted_at INTEGER,
+                edited_at INTEGER,
+                author_migrated_to TEXT,
+                origin_group_id BLOB,
+                expires_at INTEGER
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE message_outbox (
+                event_id TEXT NOT NULL,
+                account_pubkey TEXT NOT NULL,
+                state TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        (pool, temp_dir)
+    }
+
+    async fn insert_message(pool: &sqlx::SqlitePool, account: &PublicKey, id: u8, created_at: u64) {
+        sqlx::query(
+            "INSERT INTO messages
+             (event_id, account_pubkey, author_pubkey, event_kind, mls_group_id, created_at, content,
+              tags, event, outer_event_id, tokens)
+             VALUES (?, ?, ?, 9, ?, ?, '', '[]', '{}', ?, '[]')",
+        )
+        .bind(EventId::from_slice(&[id; 32]).unwrap().to_hex())
+        .bind(account.to_hex())
+        .bind(account.to_hex())
+        .bind(vec![1u8])
+        .bind(created_at as i64)
+        .bind(EventId::all_zeros().to_hex())
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_message_pages_split_messages_created_in_the_same_second() {
+        let (pool, _temp_dir) = setup_test_pool().await;
+        let account = Keys::generate().public_key();
+        // Inserted out of order, three of them in the same second
+        for (id, created_at) in [(3, 20), (1, 10), (4, 20), (2, 20), (5, 30)] {
+            insert_message(&pool, &account, id, created_at).await;
+        }
+
+        let mut seen = Vec::new();
+        let mut before = None;
+        loop {
+            let page = message_page_rows(&pool, &[1], &account, before, 2)
+                .await
+                .unwrap();
+            let Some(oldest) = page.first() else {
+                break;
+            };
+            before = Some(MessageCursor {
+                created_at: Timestamp::from(oldest.created_at),
+                event_id: EventId::from_hex(&oldest.event_id).unwrap(),
+            });
+            let mut ids: Vec<String> = page.into_iter().map(|row| row.event_id).collect();
+            ids.extend(seen);
+            seen = ids;
+        }
+
+        let expected: Vec<String> = [1u8, 2, 3, 4, 5]
+            .iter()
+            .map(|id| EventId::from_slice(&[*id; 32]).unwrap().to_hex())
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[tokio::test]
+    async fn test_message_pages_are_scoped_to_the_account() {
+        let (pool, _temp_dir) = setup_test_pool().await;
+        let account = Keys::generate().public_key();
+        let other_account = Keys::generate().public_key();
+        insert_message(&pool, &account, 1, 10).await;
+        insert_message(&pool, &other_account, 2, 20).await;
+
+        let page = message_page_rows(&pool, &[1], &account, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].account_pubkey, account.to_hex());
+    }
+
+    #[tokio::test]
+    async fn test_read_cursor_splits_messages_created_in_the_same_second() {
+        let (pool, _temp_dir) = setup_test_pool().await;
+        let account = Keys::generate().public_key();
+        for (id, created_at) in [(1, 10), (2, 20), (3, 20), (4, 20)] {
+            insert_message(&pool, &account, id, created_at).await;
+        }
+        sqlx::query(
+            "CREATE TABLE groups (
+                mls_group_id BLOB NOT NULL,
+                last_read_message_id TEXT,
+                last_read_message_at INTEGER
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO groups VALUES (?, ?, 20)")
+            .bind(vec![1u8])
+            .bind(EventId::from_slice(&[2; 32]).unwrap().to_hex())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let unread: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM groups g JOIN messages m ON m.mls_group_id = g.mls_group_id
+             WHERE {}",
+            UNREAD_CONDITION
+        ))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        // Read up to the second message, so the other two from the same second are unread
+        assert_eq!(unread, 2);
+    }
 }
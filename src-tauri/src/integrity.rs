@@ -0,0 +1,372 @@
+//! Integrity checks for stored accounts, groups and secrets.
+//!
+//! Saving an account or group records a SHA-256 hash of the fields only `save` writes in
+//! `record_checksums`. [`verify`] rereads every record, checks that it deserializes and still
+//! matches its hash, and quarantines the records that don't: database rows are moved to
+//! `quarantined_records`, secrets to the quarantine entry of the secrets file. Loading then skips
+//! over the damage instead of failing on it.
+//!
+//! Records saved before checksums existed get their hash recorded on the first verification.
+
+use crate::accounts::{AccountOnboarding, AccountRow, AccountSettings};
+use crate::content_filters::GroupContentFilter;
+use crate::groups::GroupRow;
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub const ACCOUNTS_TABLE: &str = "accounts";
+pub const GROUPS_TABLE: &str = "groups";
+
+#[derive(Error, Debug)]
+pub enum IntegrityError {
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, IntegrityError>;
+
+/// A corrupt record that was quarantined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityFinding {
+    /// `accounts`, `groups` or `secrets`
+    pub store: String,
+    pub record_id: String,
+    pub reason: String,
+}
+
+/// The outcome of [`verify`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Records checked across all stores
+    pub checked: usize,
+    /// Records that had no checksum yet and got one
+    pub backfilled: usize,
+    pub findings: Vec<IntegrityFinding>,
+}
+
+/// Hashes the given fields, length-prefixed so that shifting content between fields changes the
+/// hash
+pub fn checksum(fields: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+pub fn account_checksum(metadata: &str, settings: &str, onboarding: &str) -> String {
+    checksum(&[metadata, settings, onboarding])
+}
+
+pub fn group_checksum(
+    nostr_group_id: &str,
+    name: &str,
+    description: &str,
+    admin_pubkeys: &str,
+    group_type: &str,
+) -> String {
+    checksum(&[nostr_group_id, name, description, admin_pubkeys, group_type])
+}
+
+/// The ID a group's checksum and quarantine entry are stored under
+pub fn group_record_id(mls_group_id: &[u8], account_pubkey: &str) -> String {
+    format!("{}:{}", hex::encode(mls_group_id), account_pubkey)
+}
+
+/// Records the checksum of a record, as part of the transaction that saves it
+pub async fn record_checksum(
+    conn: &mut sqlx::SqliteConnection,
+    table_name: &str,
+    record_id: &str,
+    checksum: &str,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO record_checksums (table_name, record_id, checksum) VALUES (?, ?, ?)",
+    )
+    .bind(table_name)
+    .bind(record_id)
+    .bind(checksum)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Why an account row is corrupt, or `None` if it's intact
+fn check_account_row(row: &AccountRow, stored_checksum: Option<&String>) -> Option<String> {
+    if let Err(e) = PublicKey::parse(&row.pubkey) {
+        return Some(format!("Invalid pubkey: {}", e));
+    }
+    if let Err(e) = serde_json::from_str::<Metadata>(&row.metadata) {
+        return Some(format!("Invalid metadata: {}", e));
+    }
+    if let Err(e) = serde_json::from_str::<AccountSettings>(&row.settings) {
+        return Some(format!("Invalid settings: {}", e));
+    }
+    if let Err(e) = serde_json::from_str::<AccountOnboarding>(&row.onboarding) {
+        return Some(format!("Invalid onboarding: {}", e));
+    }
+    if let Err(e) = serde_json::from_str::<Vec<EventId>>(&row.key_package_ids) {
+        return Some(format!("Invalid key package IDs: {}", e));
+    }
+    let actual = account_checksum(&row.metadata, &row.settings, &row.onboarding);
+    match stored_checksum {
+        Some(expected) if *expected != actual => Some("Checksum mismatch".to_string()),
+        _ => None,
+    }
+}
+
+/// Why a group row is corrupt, or `None` if it's intact
+fn check_group_row(row: &GroupRow, stored_checksum: Option<&String>) -> Option<String> {
+    if let Err(e) = PublicKey::parse(&row.account_pubkey) {
+        return Some(format!("Invalid account pubkey: {}", e));
+    }
+    if let Err(e) = serde_json::from_str::<Vec<String>>(&row.admin_pubkeys) {
+        return Some(format!("Invalid admin pubkeys: {}", e));
+    }
+    if let Err(e) = serde_json::from_str::<GroupContentFilter>(&row.content_filter) {
+        return Some(format!("Invalid content filter: {}", e));
+    }
+    let actual = group_checksum(
+        &row.nostr_group_id,
+        &row.name,
+        &row.description,
+        &row.admin_pubkeys,
+        &row.group_type,
+    );
+    match stored_checksum {
+        Some(expected) if *expected != actual => Some("Checksum mismatch".to_string()),
+        _ => None,
+    }
+}
+
+/// Moves a row into `quarantined_records`. Foreign keys are off while the row is removed so that
+/// the records depending on it (e.g. a group's messages) are kept rather than cascade-deleted.
+async fn quarantine(
+    table_name: &str,
+    record_id: &str,
+    data: String,
+    reason: &str,
+    delete_query: sqlx::query::Query<'_, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'_>>,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut conn = wn.database.pool.acquire().await?;
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await?;
+
+    let result = async {
+        let mut txn = sqlx::Connection::begin(&mut *conn).await?;
+        sqlx::query(
+            "INSERT INTO quarantined_records (table_name, record_id, data, reason, quarantined_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(table_name)
+        .bind(record_id)
+        .bind(data)
+        .bind(reason)
+        .bind(Timestamp::now().as_u64() as i64)
+        .execute(&mut *txn)
+        .await?;
+        delete_query.execute(&mut *txn).await?;
+        sqlx::query("DELETE FROM record_checksums WHERE table_name = ? AND record_id = ?")
+            .bind(table_name)
+            .bind(record_id)
+            .execute(&mut *txn)
+            .await?;
+        txn.commit().await
+    }
+    .await;
+
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await?;
+    result?;
+
+    tracing::warn!(
+        target: "whitenoise::integrity::quarantine",
+        "Quarantined {} record {}: {}",
+        table_name,
+        record_id,
+        reason
+    );
+    Ok(())
+}
+
+/// Checks every account, group and secret, quarantining the corrupt ones
+pub async fn verify(wn: tauri::State<'_, Whitenoise>) -> Result<IntegrityReport> {
+    let mut report = IntegrityReport::default();
+
+    let stored: HashMap<(String, String), String> = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT table_name, record_id, checksum FROM record_checksums",
+    )
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(|(table_name, record_id, checksum)| ((table_name, record_id), checksum))
+    .collect();
+
+    let accounts = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts")
+        .fetch_all(&wn.database.pool)
+        .await?;
+    for row in accounts {
+        report.checked += 1;
+        let key = (ACCOUNTS_TABLE.to_string(), row.pubkey.clone());
+        let stored_checksum = stored.get(&key);
+        match check_account_row(&row, stored_checksum) {
+            Some(reason) => {
+                let delete = sqlx::query("DELETE FROM accounts WHERE pubkey = ?").bind(&row.pubkey);
+                quarantine(
+                    ACCOUNTS_TABLE,
+                    &row.pubkey,
+                    serde_json::to_string(&row)?,
+                    &reason,
+                    delete,
+                    &wn,
+                )
+                .await?;
+                report.findings.push(IntegrityFinding {
+                    store: ACCOUNTS_TABLE.to_string(),
+                    record_id: row.pubkey,
+                    reason,
+                });
+            }
+            None if stored_checksum.is_none() => {
+                let checksum = account_checksum(&row.metadata, &row.settings, &row.onboarding);
+                let mut conn = wn.database.pool.acquire().await?;
+                record_checksum(&mut conn, ACCOUNTS_TABLE, &row.pubkey, &checksum).await?;
+                report.backfilled += 1;
+            }
+            None => {}
+        }
+    }
+
+    let groups = sqlx::query_as::<_, GroupRow>("SELECT * FROM groups")
+        .fetch_all(&wn.database.pool)
+        .await?;
+    for row in groups {
+        report.checked += 1;
+        let record_id = group_record_id(&row.mls_group_id, &row.account_pubkey);
+        let stored_checksum = stored.get(&(GROUPS_TABLE.to_string(), record_id.clone()));
+        match check_group_row(&row, stored_checksum) {
+            Some(reason) => {
+                let delete =
+                    sqlx::query("DELETE FROM groups WHERE mls_group_id = ? AND account_pubkey = ?")
+                        .bind(&row.mls_group_id)
+                        .bind(&row.account_pubkey);
+                quarantine(
+                    GROUPS_TABLE,
+                    &record_id,
+                    serde_json::to_string(&row)?,
+                    &reason,
+                    delete,
+                    &wn,
+                )
+                .await?;
+                report.findings.push(IntegrityFinding {
+                    store: GROUPS_TABLE.to_string(),
+                    record_id,
+                    reason,
+                });
+            }
+            None if stored_checksum.is_none() => {
+                let checksum = group_checksum(
+                    &row.nostr_group_id,
+                    &row.name,
+                    &row.description,
+                    &row.admin_pubkeys,
+                    &row.group_type,
+                );
+                let mut conn = wn.database.pool.acquire().await?;
+                record_checksum(&mut conn, GROUPS_TABLE, &record_id, &checksum).await?;
+                report.backfilled += 1;
+            }
+            None => {}
+        }
+    }
+
+    let (checked, corrupt) = secrets_store::quarantine_corrupt_entries(&wn.data_dir)?;
+    report.checked += checked;
+    for (key, reason) in corrupt {
+        tracing::warn!(
+            target: "whitenoise::integrity::verify",
+            "Quarantined secret {}: {}",
+            key,
+            reason
+        );
+        report.findings.push(IntegrityFinding {
+            store: "secrets".to_string(),
+            record_id: key,
+            reason,
+        });
+    }
+
+    tracing::info!(
+        target: "whitenoise::integrity::verify",
+        "Checked {} records, {} corrupt, {} checksums backfilled",
+        report.checked,
+        report.findings.len(),
+        report.backfilled
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_row() -> AccountRow {
+        AccountRow {
+            pubkey: Keys::generate().public_key().to_hex(),
+            metadata: serde_json::to_string(&Metadata::default()).unwrap(),
+            settings: serde_json::to_string(&AccountSettings::default()).unwrap(),
+            onboarding: serde_json::to_string(&AccountOnboarding::default()).unwrap(),
+            last_used: 0,
+            last_synced: 0,
+            active: true,
+            key_package_ids: "[]".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_checksum_is_field_aligned() {
+        assert_ne!(checksum(&["ab", "c"]), checksum(&["a", "bc"]));
+        assert_eq!(checksum(&["ab", "c"]), checksum(&["ab", "c"]));
+    }
+
+    #[test]
+    fn test_intact_account_row_passes() {
+        let row = account_row();
+        let expected = account_checksum(&row.metadata, &row.settings, &row.onboarding);
+        assert_eq!(check_account_row(&row, Some(&expected)), None);
+        // Rows saved before checksums existed have nothing to compare against
+        assert_eq!(check_account_row(&row, None), None);
+    }
+
+    #[test]
+    fn test_corrupt_account_rows_are_reported() {
+        let mut row = account_row();
+        row.settings = "{\"dark_theme\":tr".to_string();
+        assert!(check_account_row(&row, None)
+            .unwrap()
+            .starts_with("Invalid settings"));
+
+        let row = account_row();
+        let stale = account_checksum("{}", &row.settings, &row.onboarding);
+        assert_eq!(
+            check_account_row(&row, Some(&stale)).as_deref(),
+            Some("Checksum mismatch")
+        );
+    }
+}
@@ -0,0 +1,938 @@
+//! Group invitations shared outside of Nostr.
+//!
+//! A group admin creates an invite message (see `create_invite_message`): a few lines saying who
+//! invites whom to which group and until when, followed by a `whitenoise-invite:` line holding
+//! an invite signed by the admin, base64 encoded. It can be pasted anywhere, and whoever pastes
+//! it back into White Noise (`parse_invite_message`) gets the invite checked: it has to be signed
+//! by the inviter and unexpired. The signed invite only names where to send join requests; the
+//! inviter keeps which group it's for (see `issued_invites`), so an invite that leaks doesn't
+//! give the group's ID away.
+//!
+//! An invite link (see `create_group_invite_link`) points to a signed invite published to the
+//! inviter's relays under a random code. That invite is an opaque token: it says nothing about
+//! the group, so anyone who finds it on a relay learns only that the inviter made a link. The
+//! link names the inviter, the code and the relays to look on and to send join requests to.
+//! Links can expire sooner or later than a week and be limited to a number of uses.
+//!
+//! Accepting the invite sends the inviter a gift-wrapped join request carrying the invite and
+//! one of the invitee's key packages, published first if they have none. The inviter keeps the
+//! join requests for invites they actually signed, so the key package is at hand to add the
+//! member with (see `pending_join_requests`). Approving a request adds the member and counts as a
+//! use of the link it came through; approved and rejected requests are forgotten.
+//!
+//! Adding a member to an existing group needs incoming commits to be processed, which they
+//! aren't yet, so invites can't be created while [`MEMBER_ADDITIONS_ENABLED`] is off.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError, MEMBER_ADDITIONS_ENABLED};
+use crate::key_packages::{self, KeyPackageError};
+use crate::nostr_manager::{NostrManager, NostrManagerError};
+use crate::relay_blacklist::{self, RelayBlacklist, RelayBlacklistError};
+use crate::relays::RelayType;
+use crate::Whitenoise;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use nostr_sdk::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use thiserror::Error;
+
+/// The kind of the signed invite inside an invite message
+pub const INVITE_MESSAGE_KIND: u16 = 1779;
+
+/// The rumor kind of join requests
+pub const JOIN_REQUEST_KIND: u16 = 1780;
+
+/// How long an invite message can be used
+const INVITE_VALIDITY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// How long an invite link can be made to last
+const MAX_INVITE_LINK_VALIDITY_SECS: u64 = 90 * 24 * 60 * 60;
+
+/// Starts the machine readable line of an invite message
+const INVITE_PREFIX: &str = "whitenoise-invite:";
+
+/// Starts an invite link, which goes on with the inviter's npub and the code
+const INVITE_LINK_PREFIX: &str = "whitenoise://join/";
+
+#[derive(Error, Debug)]
+pub enum InviteMessageError {
+    #[error("Invalid invite: {0}")]
+    InvalidInvite(String),
+
+    #[error("This invite expired")]
+    Expired,
+
+    #[error("Only admins can invite to this group")]
+    NotAdmin,
+
+    #[error("Invalid invite link settings: {0}")]
+    InvalidLinkSettings(String),
+
+    #[error("Invite not found for this link")]
+    LinkNotFound,
+
+    #[error("This invite link has been used up")]
+    LinkUsedUp,
+
+    #[error("No pending join request from this user")]
+    RequestNotFound,
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Key package error: {0}")]
+    KeyPackageError(#[from] KeyPackageError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("Nostr event error: {0}")]
+    NostrEventError(#[from] nostr_sdk::event::builder::Error),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+
+    #[error("Failed to parse public key: {0}")]
+    PublicKeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, InviteMessageError>;
+
+/// The content of a signed invite message. The invites published for links have no content.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+struct InviteContent {
+    /// Where the inviter reads join requests
+    relays: Vec<String>,
+}
+
+/// A checked invite from an invite message
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct InviteMessage {
+    /// ID of the signed invite
+    pub id: EventId,
+    pub inviter: PublicKey,
+    /// Where join requests are sent
+    pub relays: Vec<String>,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+impl InviteMessage {
+    /// Checks a signed invite and reads it, as of `now`
+    fn from_event(event: &Event, now: Timestamp) -> Result<Self> {
+        if event.kind != Kind::Custom(INVITE_MESSAGE_KIND) {
+            return Err(InviteMessageError::InvalidInvite(format!(
+                "Unexpected kind {}",
+                event.kind
+            )));
+        }
+        event
+            .verify()
+            .map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))?;
+        let expires_at = *event.tags.expiration().ok_or_else(|| {
+            InviteMessageError::InvalidInvite("The invite has no expiration".to_string())
+        })?;
+        if expires_at <= now {
+            return Err(InviteMessageError::Expired);
+        }
+        let content: InviteContent = if event.content.is_empty() {
+            InviteContent::default()
+        } else {
+            serde_json::from_str(&event.content)
+                .map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))?
+        };
+
+        Ok(Self {
+            id: event.id,
+            inviter: event.pubkey,
+            relays: content.relays,
+            created_at: event.created_at,
+            expires_at,
+        })
+    }
+}
+
+/// The content of a join request rumor
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct JoinRequestContent {
+    /// The signed invite being accepted
+    invite: Event,
+    /// A key package of the requester to add them with
+    key_package_event_id: EventId,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct JoinRequestRow {
+    mls_group_id: Vec<u8>,
+    requester_pubkey: String,
+    invite_id: String,
+    key_package_event_id: String,
+    requested_at: u64,
+}
+
+/// A request to join one of our groups through an invite message we created
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct JoinRequest {
+    pub mls_group_id: Vec<u8>,
+    pub requester: PublicKey,
+    pub invite_id: EventId,
+    /// The requester's key package to add them with
+    pub key_package_event_id: EventId,
+    pub requested_at: Timestamp,
+}
+
+impl TryFrom<JoinRequestRow> for JoinRequest {
+    type Error = InviteMessageError;
+
+    fn try_from(row: JoinRequestRow) -> Result<Self> {
+        Ok(Self {
+            mls_group_id: row.mls_group_id,
+            requester: PublicKey::from_hex(&row.requester_pubkey)?,
+            invite_id: EventId::from_hex(&row.invite_id)?,
+            key_package_event_id: EventId::from_hex(&row.key_package_event_id)?,
+            requested_at: Timestamp::from(row.requested_at),
+        })
+    }
+}
+
+/// An invite link to one of our groups
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct InviteLink {
+    pub mls_group_id: Vec<u8>,
+    pub code: String,
+    /// The link to share
+    pub link: String,
+    /// ID of the signed invite published under the code
+    pub invite_id: EventId,
+    /// How many requests through the link can be approved, `None` for no limit
+    pub max_uses: Option<u32>,
+    /// How many requests through the link were approved
+    pub uses: u32,
+    pub expires_at: Timestamp,
+    pub created_at: Timestamp,
+}
+
+/// Writes an invite link for an invite published under `code` on `relays`
+fn format_link(inviter: &PublicKey, code: &str, relays: &[String]) -> Result<String> {
+    let npub = inviter
+        .to_bech32()
+        .map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))?;
+    let mut link = Url::parse(&format!("{}{}/{}", INVITE_LINK_PREFIX, npub, code))
+        .map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))?;
+    for relay in relays {
+        link.query_pairs_mut().append_pair("relay", relay);
+    }
+    Ok(link.to_string())
+}
+
+/// Reads an invite link: the inviter, the code and the relays the invite is published on
+fn parse_link(link: &str) -> Result<(PublicKey, String, Vec<String>)> {
+    let invalid = || InviteMessageError::InvalidInvite("Not a White Noise invite link".to_string());
+    let link = link.trim();
+    if !link.starts_with(INVITE_LINK_PREFIX) {
+        return Err(invalid());
+    }
+    let url = Url::parse(link).map_err(|_| invalid())?;
+    let mut segments = url.path_segments().ok_or_else(invalid)?;
+    let (Some(inviter), Some(code), None) = (segments.next(), segments.next(), segments.next())
+    else {
+        return Err(invalid());
+    };
+    if code.is_empty() {
+        return Err(invalid());
+    }
+    let relays = url
+        .query_pairs()
+        .filter(|(key, _)| key == "relay")
+        .map(|(_, relay)| relay.into_owned())
+        .collect();
+    Ok((PublicKey::parse(inviter)?, code.to_string(), relays))
+}
+
+/// Writes an invite message: the human readable summary, then the encoded invite
+fn format_message(
+    invite: &Event,
+    group_name: &str,
+    group_description: &str,
+    inviter: &str,
+    expires_at: Timestamp,
+) -> String {
+    let valid_until = chrono::DateTime::from_timestamp(expires_at.as_u64() as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| expires_at.to_string());
+
+    let mut message = format!(
+        "{} invited you to join \"{}\" on White Noise\n",
+        inviter, group_name
+    );
+    if !group_description.is_empty() {
+        message.push_str(&format!("{}\n", group_description));
+    }
+    message.push_str(&format!(
+        "Invite valid until {}\n\nTo ask to join, paste this whole message into White Noise:\n{}{}",
+        valid_until,
+        INVITE_PREFIX,
+        URL_SAFE_NO_PAD.encode(invite.as_json())
+    ));
+    message
+}
+
+/// Finds and decodes the signed invite in an invite message
+fn decode(message: &str) -> Result<Event> {
+    let encoded = message
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(INVITE_PREFIX))
+        .ok_or_else(|| {
+            InviteMessageError::InvalidInvite("No White Noise invite found".to_string())
+        })?;
+    let json = URL_SAFE_NO_PAD
+        .decode(encoded.trim())
+        .map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))?;
+    Event::from_json(json).map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))
+}
+
+/// The active account and one of its groups, which it must be an admin of to invite to it
+async fn admin_group(
+    mls_group_id: &Vec<u8>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(Account, Group)> {
+    if !MEMBER_ADDITIONS_ENABLED {
+        return Err(GroupError::MemberAdditionsUnsupported.into());
+    }
+    let account = Account::get_active(wn.clone()).await?;
+    let group = Group::find_by_mls_group_id_for_account(mls_group_id, &account.pubkey, wn).await?;
+    if !group.admin_pubkeys.contains(&account.pubkey.to_hex()) {
+        return Err(InviteMessageError::NotAdmin);
+    }
+    Ok((account, group))
+}
+
+/// The relays an account reads join requests on: its inbox relays, or its relays if it has none
+async fn request_relays(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<String>> {
+    let relays = account.relays(RelayType::Inbox, wn.clone()).await?;
+    if !relays.is_empty() {
+        return Ok(relays);
+    }
+    Ok(account.relays(RelayType::Nostr, wn).await?)
+}
+
+/// Signs an invite with `content`, valid until `expires_at`
+async fn sign_invite(
+    content: String,
+    expires_at: Timestamp,
+    extra_tags: Vec<Tag>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Event> {
+    let mut tags = vec![Tag::expiration(expires_at)];
+    tags.extend(extra_tags);
+    let builder = EventBuilder::new(Kind::Custom(INVITE_MESSAGE_KIND), content).tags(tags);
+    Ok(wn.nostr.client.sign_event_builder(builder).await?)
+}
+
+/// Creates an invite message for one of the active account's groups, valid for a week
+pub async fn create(mls_group_id: &Vec<u8>, wn: tauri::State<'_, Whitenoise>) -> Result<String> {
+    let (account, group) = admin_group(mls_group_id, wn.clone()).await?;
+    let content = InviteContent {
+        relays: RelayBlacklist::load(wn.clone())
+            .await?
+            .filter(request_relays(&account, wn.clone()).await?),
+    };
+    let created_at = Timestamp::now();
+    let expires_at = created_at + INVITE_VALIDITY_SECS;
+    let invite = sign_invite(
+        serde_json::to_string(&content)?,
+        expires_at,
+        vec![],
+        wn.clone(),
+    )
+    .await?;
+    sqlx::query("DELETE FROM issued_invites WHERE account_pubkey = ? AND expires_at <= ?")
+        .bind(account.pubkey.to_hex())
+        .bind(created_at.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+    sqlx::query(
+        "INSERT INTO issued_invites (account_pubkey, mls_group_id, invite_id, expires_at, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(invite.id.to_hex())
+    .bind(expires_at.as_u64() as i64)
+    .bind(created_at.as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+
+    let inviter = account
+        .metadata
+        .display_name
+        .clone()
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            account
+                .metadata
+                .name
+                .clone()
+                .filter(|name| !name.is_empty())
+        })
+        .unwrap_or_else(|| {
+            account
+                .pubkey
+                .to_bech32()
+                .unwrap_or_else(|_| account.pubkey.to_hex())
+        });
+    Ok(format_message(
+        &invite,
+        &group.name,
+        &group.description,
+        &inviter,
+        expires_at,
+    ))
+}
+
+/// Creates an invite link for one of the active account's groups and publishes its invite
+///
+/// # Arguments
+/// * `max_uses` - How many join requests through the link can be approved, `None` for no limit
+/// * `expires_in` - Seconds the link stays valid, a week if `None` and at most 90 days
+pub async fn create_link(
+    mls_group_id: &Vec<u8>,
+    max_uses: Option<u32>,
+    expires_in: Option<u64>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InviteLink> {
+    if max_uses == Some(0) {
+        return Err(InviteMessageError::InvalidLinkSettings(
+            "max_uses must be greater than zero".to_string(),
+        ));
+    }
+    let expires_in = expires_in.unwrap_or(INVITE_VALIDITY_SECS);
+    if expires_in == 0 || expires_in > MAX_INVITE_LINK_VALIDITY_SECS {
+        return Err(InviteMessageError::InvalidLinkSettings(format!(
+            "expires_in must be between 1 and {} seconds",
+            MAX_INVITE_LINK_VALIDITY_SECS
+        )));
+    }
+    let (account, _) = admin_group(mls_group_id, wn.clone()).await?;
+    let relays = RelayBlacklist::load(wn.clone())
+        .await?
+        .filter(request_relays(&account, wn.clone()).await?);
+
+    let mut code = [0u8; 8];
+    rand::rng().fill_bytes(&mut code);
+    let code = hex::encode(code);
+    let created_at = Timestamp::now();
+    let expires_at = created_at + expires_in;
+    let invite = sign_invite(
+        String::new(),
+        expires_at,
+        vec![Tag::identifier(code.clone())],
+        wn.clone(),
+    )
+    .await?;
+    relay_blacklist::send_event(&invite, relays.clone(), wn.clone()).await?;
+
+    let link = InviteLink {
+        mls_group_id: mls_group_id.clone(),
+        link: format_link(&account.pubkey, &code, &relays)?,
+        code,
+        invite_id: invite.id,
+        max_uses,
+        uses: 0,
+        expires_at,
+        created_at,
+    };
+    sqlx::query(
+        "INSERT INTO invite_links
+             (account_pubkey, mls_group_id, code, invite_id, max_uses, uses, expires_at, created_at)
+         VALUES (?, ?, ?, ?, ?, 0, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(&link.mls_group_id)
+    .bind(&link.code)
+    .bind(link.invite_id.to_hex())
+    .bind(link.max_uses)
+    .bind(link.expires_at.as_u64() as i64)
+    .bind(link.created_at.as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(link)
+}
+
+/// Checks an invite message and asks the inviter to let the active account join the group
+///
+/// Publishes a key package first if the account has none, so the inviter can add it.
+pub async fn accept(message: &str, wn: tauri::State<'_, Whitenoise>) -> Result<InviteMessage> {
+    request_to_join(decode(message)?, vec![], wn).await
+}
+
+/// Looks up the invite an invite link points to and asks the inviter to let the active account
+/// join the group, like [`accept`]
+///
+/// The invite is fetched from the relays named in the link with a client of its own, so they
+/// don't join the pool, or from the account's relays if the link names none. The join request is
+/// sent to the link's relays.
+pub async fn accept_link(link: &str, wn: tauri::State<'_, Whitenoise>) -> Result<InviteMessage> {
+    let (inviter, code, relays) = parse_link(link)?;
+
+    let filter = Filter::new()
+        .kind(Kind::Custom(INVITE_MESSAGE_KIND))
+        .author(inviter)
+        .identifier(code.clone());
+    let timeout = wn.nostr.timeout().await?;
+    let events: Vec<Event> = if relays.is_empty() {
+        wn.nostr
+            .client
+            .fetch_events(filter, timeout)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        let blacklist = RelayBlacklist::load(wn.clone()).await?;
+        NostrManager::fetch_from_foreign_relays(&relays, filter, &blacklist, timeout).await?
+    };
+    let event = events
+        .into_iter()
+        .filter(|event| event.pubkey == inviter && event.tags.identifier() == Some(code.as_str()))
+        .max_by_key(|event| event.created_at)
+        .ok_or(InviteMessageError::LinkNotFound)?;
+    request_to_join(event, relays, wn).await
+}
+
+/// Checks a signed invite and sends its inviter a join request, to the relays the invite names,
+/// else `fallback_relays`, else the inviter's inbox relays
+async fn request_to_join(
+    event: Event,
+    fallback_relays: Vec<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InviteMessage> {
+    let invite = InviteMessage::from_event(&event, Timestamp::now())?;
+
+    let account = Account::get_active(wn.clone()).await?;
+    if invite.inviter == account.pubkey {
+        return Err(InviteMessageError::InvalidInvite(
+            "This is your own invite".to_string(),
+        ));
+    }
+
+    let pool = key_packages::replenish(wn.clone()).await?;
+    let key_package_event_id = pool
+        .key_package_ids
+        .last()
+        .map(EventId::from_hex)
+        .transpose()?
+        .ok_or_else(|| {
+            KeyPackageError::NoValidKeyPackage("No key package published".to_string())
+        })?;
+
+    let content = JoinRequestContent {
+        invite: event,
+        key_package_event_id,
+    };
+    let rumor = EventBuilder::new(
+        Kind::Custom(JOIN_REQUEST_KIND),
+        serde_json::to_string(&content)?,
+    )
+    .build(account.pubkey);
+    let signer = wn.nostr.client.signer().await?;
+    let wrapped = EventBuilder::gift_wrap(&signer, &invite.inviter, rumor, vec![]).await?;
+
+    let mut relays = invite.relays.clone();
+    if relays.is_empty() {
+        relays = fallback_relays;
+    }
+    if relays.is_empty() {
+        relays = wn.nostr.fetch_user_inbox_relays(invite.inviter).await?;
+    }
+    relay_blacklist::send_event(&wrapped, relays, wn).await?;
+
+    tracing::debug!(
+        target: "whitenoise::invite_messages::accept",
+        "Asked {} to join through invite {}",
+        invite.inviter.to_hex(),
+        invite.id
+    );
+    Ok(invite)
+}
+
+/// The join requests for one of the active account's groups, newest first
+pub async fn join_requests(
+    mls_group_id: &[u8],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<JoinRequest>> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let rows = sqlx::query_as::<_, JoinRequestRow>(
+        "SELECT mls_group_id, requester_pubkey, invite_id, key_package_event_id, requested_at
+         FROM join_requests WHERE account_pubkey = ? AND mls_group_id = ?
+         ORDER BY requested_at DESC",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .fetch_all(&wn.database.pool)
+    .await?;
+    rows.into_iter().map(JoinRequest::try_from).collect()
+}
+
+/// A pending join request from `requester` for one of the groups of `account_pubkey`
+pub async fn join_request(
+    mls_group_id: &[u8],
+    requester: &PublicKey,
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<JoinRequest> {
+    let row = sqlx::query_as::<_, JoinRequestRow>(
+        "SELECT mls_group_id, requester_pubkey, invite_id, key_package_event_id, requested_at
+         FROM join_requests
+         WHERE account_pubkey = ? AND mls_group_id = ? AND requester_pubkey = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(requester.to_hex())
+    .fetch_optional(&wn.database.pool)
+    .await?
+    .ok_or(InviteMessageError::RequestNotFound)?;
+    row.try_into()
+}
+
+/// Fails with [`InviteMessageError::LinkUsedUp`] if the request came through an invite link whose
+/// uses have all been approved. Invite messages have no limit.
+async fn ensure_uses_left(
+    request: &JoinRequest,
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let link: Option<(Option<u32>, u32)> = sqlx::query_as(
+        "SELECT max_uses, uses FROM invite_links WHERE account_pubkey = ? AND invite_id = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(request.invite_id.to_hex())
+    .fetch_optional(&wn.database.pool)
+    .await?;
+    match link {
+        Some((Some(max_uses), uses)) if uses >= max_uses => Err(InviteMessageError::LinkUsedUp),
+        _ => Ok(()),
+    }
+}
+
+/// Takes a join request for approval: forgets it and counts a use of the invite link it came
+/// through, in one transaction, so two approvals can't both take a link's last use or approve
+/// the same request. See [`release_use`] to undo it if adding the member fails.
+///
+/// # Errors
+/// Returns [`InviteMessageError::RequestNotFound`] if the request was already taken, or
+/// [`InviteMessageError::LinkUsedUp`] if its link has no uses left.
+pub async fn reserve_use(
+    request: &JoinRequest,
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut txn = wn.database.pool.begin().await?;
+    let taken = sqlx::query(
+        "DELETE FROM join_requests
+         WHERE account_pubkey = ? AND mls_group_id = ? AND requester_pubkey = ? AND invite_id = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(&request.mls_group_id)
+    .bind(request.requester.to_hex())
+    .bind(request.invite_id.to_hex())
+    .execute(&mut *txn)
+    .await?
+    .rows_affected();
+    if taken == 0 {
+        return Err(InviteMessageError::RequestNotFound);
+    }
+
+    let is_link: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM invite_links WHERE account_pubkey = ? AND invite_id = ?)",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(request.invite_id.to_hex())
+    .fetch_one(&mut *txn)
+    .await?;
+    if is_link {
+        let counted = sqlx::query(
+            "UPDATE invite_links SET uses = uses + 1
+             WHERE account_pubkey = ? AND invite_id = ? AND (max_uses IS NULL OR uses < max_uses)",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(request.invite_id.to_hex())
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+        if counted == 0 {
+            return Err(InviteMessageError::LinkUsedUp);
+        }
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Undoes [`reserve_use`] after adding the member failed: the request is pending again and the
+/// link's use is given back
+pub async fn release_use(
+    request: &JoinRequest,
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO join_requests
+             (account_pubkey, mls_group_id, requester_pubkey, invite_id, key_package_event_id, requested_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(&request.mls_group_id)
+    .bind(request.requester.to_hex())
+    .bind(request.invite_id.to_hex())
+    .bind(request.key_package_event_id.to_hex())
+    .bind(request.requested_at.as_u64() as i64)
+    .execute(&mut *txn)
+    .await?;
+    sqlx::query(
+        "UPDATE invite_links SET uses = uses - 1
+         WHERE account_pubkey = ? AND invite_id = ? AND uses > 0",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(request.invite_id.to_hex())
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Forgets a rejected join request
+pub async fn reject_request(
+    request: &JoinRequest,
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM join_requests
+         WHERE account_pubkey = ? AND mls_group_id = ? AND requester_pubkey = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(&request.mls_group_id)
+    .bind(request.requester.to_hex())
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(())
+}
+
+/// The group an invite we signed is for, from the invite links and invite messages we created
+async fn invited_group(
+    invite: &InviteMessage,
+    account_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<Group>> {
+    let mls_group_id: Option<Vec<u8>> = sqlx::query_scalar(
+        "SELECT mls_group_id FROM invite_links WHERE account_pubkey = ?1 AND invite_id = ?2
+         UNION ALL
+         SELECT mls_group_id FROM issued_invites WHERE account_pubkey = ?1 AND invite_id = ?2
+         LIMIT 1",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(invite.id.to_hex())
+    .fetch_optional(&wn.database.pool)
+    .await?;
+    let Some(mls_group_id) = mls_group_id else {
+        return Ok(None);
+    };
+    match Group::find_by_mls_group_id_for_account(&mls_group_id, account_pubkey, wn).await {
+        Ok(group) => Ok(Some(group)),
+        Err(GroupError::GroupNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Handles a join request rumor sent to the active account
+///
+/// Requests for invites we didn't sign, that expired by now, whose invite link is used up or that
+/// come from members are dropped. Expiry is checked against the current time, not the rumor's
+/// `created_at`, which the requester picks.
+pub async fn handle(
+    rumor: &UnsignedEvent,
+    sender: PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let content: JoinRequestContent = serde_json::from_str(&rumor.content)
+        .map_err(|e| InviteMessageError::InvalidInvite(e.to_string()))?;
+    let invite = InviteMessage::from_event(&content.invite, Timestamp::now())?;
+    if invite.inviter != account_pubkey || sender == account_pubkey {
+        return Ok(());
+    }
+    let Some(group) = invited_group(&invite, &account_pubkey, wn.clone()).await? else {
+        return Ok(());
+    };
+    if group.members(wn.clone()).await?.contains(&sender) {
+        return Ok(());
+    }
+
+    let request = JoinRequest {
+        mls_group_id: group.mls_group_id.clone(),
+        requester: sender,
+        invite_id: invite.id,
+        key_package_event_id: content.key_package_event_id,
+        requested_at: rumor.created_at,
+    };
+    match ensure_uses_left(&request, &account_pubkey, wn.clone()).await {
+        Err(InviteMessageError::LinkUsedUp) => return Ok(()),
+        result => result?,
+    }
+    sqlx::query(
+        "INSERT INTO join_requests
+             (account_pubkey, mls_group_id, requester_pubkey, invite_id, key_package_event_id, requested_at)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT(account_pubkey, mls_group_id, requester_pubkey) DO UPDATE SET
+             invite_id = excluded.invite_id,
+             key_package_event_id = excluded.key_package_event_id,
+             requested_at = excluded.requested_at
+         WHERE excluded.requested_at > join_requests.requested_at",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(&request.mls_group_id)
+    .bind(request.requester.to_hex())
+    .bind(request.invite_id.to_hex())
+    .bind(request.key_package_event_id.to_hex())
+    .bind(request.requested_at.as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+
+    app_handle.emit("join_requested", request)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content() -> InviteContent {
+        InviteContent {
+            relays: vec!["wss://relay.example.com".to_string()],
+        }
+    }
+
+    fn invite(keys: &Keys, kind: u16, expires_at: Timestamp) -> Event {
+        EventBuilder::new(
+            Kind::Custom(kind),
+            serde_json::to_string(&content()).unwrap(),
+        )
+        .tags(vec![Tag::expiration(expires_at)])
+        .sign_with_keys(keys)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let keys = Keys::generate();
+        let expires_at = Timestamp::from(1_800_000_000);
+        let event = invite(&keys, INVITE_MESSAGE_KIND, expires_at);
+        let message = format_message(&event, "Book club", "Monthly picks", "Alice", expires_at);
+
+        assert!(message.starts_with("Alice invited you to join \"Book club\" on White Noise\n"));
+        assert!(message.contains("Monthly picks\nInvite valid until 2027-01-15 08:00 UTC"));
+
+        let pasted = format!("Hey, join us!\n\n{}\n", message);
+        let decoded = decode(&pasted).unwrap();
+        assert_eq!(decoded, event);
+
+        let parsed = InviteMessage::from_event(&decoded, Timestamp::from(1_700_000_000)).unwrap();
+        assert_eq!(parsed.inviter, keys.public_key());
+        assert_eq!(parsed.relays, content().relays);
+        assert_eq!(parsed.expires_at, expires_at);
+    }
+
+    #[test]
+    fn test_rejects_invalid_invites() {
+        let keys = Keys::generate();
+        let expires_at = Timestamp::from(1_800_000_000);
+
+        let expired = invite(&keys, INVITE_MESSAGE_KIND, expires_at);
+        assert!(matches!(
+            InviteMessage::from_event(&expired, expires_at),
+            Err(InviteMessageError::Expired)
+        ));
+
+        let wrong_kind = invite(&keys, 1, expires_at);
+        assert!(InviteMessage::from_event(&wrong_kind, Timestamp::from(0)).is_err());
+
+        let mut forged = invite(&keys, INVITE_MESSAGE_KIND, expires_at);
+        forged.pubkey = Keys::generate().public_key();
+        assert!(InviteMessage::from_event(&forged, Timestamp::from(0)).is_err());
+
+        assert!(decode("no invite here").is_err());
+        assert!(decode(&format!("{}not base64!", INVITE_PREFIX)).is_err());
+    }
+
+    #[test]
+    fn test_link_invites_are_opaque() {
+        let keys = Keys::generate();
+        let event = EventBuilder::new(Kind::Custom(INVITE_MESSAGE_KIND), "")
+            .tags(vec![
+                Tag::expiration(Timestamp::from(1_800_000_000)),
+                Tag::identifier("0123abcd"),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let parsed = InviteMessage::from_event(&event, Timestamp::from(1_700_000_000)).unwrap();
+        assert_eq!(parsed.inviter, keys.public_key());
+        assert!(parsed.relays.is_empty());
+    }
+
+    #[test]
+    fn test_link_round_trip() {
+        let inviter = Keys::generate().public_key();
+        let relays = vec![
+            "wss://relay.example.com".to_string(),
+            "wss://other.example.com/path?x=1".to_string(),
+        ];
+        let link = format_link(&inviter, "0123abcd", &relays).unwrap();
+        assert!(link.starts_with(&format!(
+            "{}{}/0123abcd?relay=",
+            INVITE_LINK_PREFIX,
+            inviter.to_bech32().unwrap()
+        )));
+
+        let (parsed_inviter, code, parsed_relays) = parse_link(&format!(" {} ", link)).unwrap();
+        assert_eq!(parsed_inviter, inviter);
+        assert_eq!(code, "0123abcd");
+        assert_eq!(parsed_relays, relays);
+
+        let bare = format_link(&inviter, "0123abcd", &[]).unwrap();
+        assert_eq!(parse_link(&bare).unwrap().2, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_rejects_invalid_links() {
+        let npub = Keys::generate().public_key().to_bech32().unwrap();
+        assert!(parse_link("https://example.com/join/abc").is_err());
+        assert!(parse_link(&format!("{}{}", INVITE_LINK_PREFIX, npub)).is_err());
+        assert!(parse_link(&format!("{}{}/", INVITE_LINK_PREFIX, npub)).is_err());
+        assert!(parse_link(&format!("{}{}/abc/def", INVITE_LINK_PREFIX, npub)).is_err());
+        assert!(parse_link(&format!("{}not-a-key/abc", INVITE_LINK_PREFIX)).is_err());
+    }
+}
@@ -1,10 +1,32 @@
 use crate::accounts::Account;
 use crate::database::DatabaseError;
+use crate::nostr_manager::NostrManager;
+use crate::relay_blacklist::RelayBlacklist;
 use crate::Whitenoise;
 use nostr_sdk::prelude::*;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
 use thiserror::Error;
 
+/// How far back the activity of an invite's group is measured
+const ACTIVITY_WINDOW_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Most group messages fetched per probe; busier groups are reported as capped
+const MAX_ACTIVITY_EVENTS: usize = 500;
+
+/// How long a probe's result is reused
+const ACTIVITY_TTL_SECS: u64 = 10 * 60;
+
+/// How long a probe waits for the group's relays
+const ACTIVITY_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Latest activity probes, by Nostr group ID
+static ACTIVITY_CACHE: Lazy<Mutex<HashMap<String, GroupActivity>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 #[derive(Error, Debug)]
 pub enum InviteError {
     #[error("Database error: {0}")]
@@ -70,6 +92,46 @@ pub struct Invite {
     pub state: InviteState,
     /// The event id of the 1059 event that contained the invite
     pub outer_event_id: String,
+    /// Recent activity of the group, probed from its relays for pending invites
+    #[serde(default)]
+    pub activity: Option<GroupActivity>,
+}
+
+/// How busy an invite's group is, from the encrypted messages on its relays. Counting them
+/// doesn't need the group's keys, so it works before the invite is accepted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GroupActivity {
+    /// Group messages (including commits) sent within the window
+    pub message_count: usize,
+    /// Whether the probe stopped counting at its limit, so the group is at least this busy
+    pub capped: bool,
+    /// When the latest group message was sent, if any was within the window
+    pub last_message_at: Option<Timestamp>,
+    /// Length of the window, in seconds before `checked_at`
+    pub window_secs: u64,
+    pub checked_at: Timestamp,
+}
+
+impl GroupActivity {
+    /// Summarizes the creation times of the group messages fetched by a probe
+    fn summarize(created_at: impl IntoIterator<Item = Timestamp>, now: Timestamp) -> Self {
+        let since = Timestamp::from(now.as_u64().saturating_sub(ACTIVITY_WINDOW_SECS));
+        let in_window: Vec<Timestamp> = created_at
+            .into_iter()
+            .filter(|created_at| *created_at >= since && *created_at <= now)
+            .collect();
+        Self {
+            message_count: in_window.len(),
+            capped: in_window.len() >= MAX_ACTIVITY_EVENTS,
+            last_message_at: in_window.iter().max().copied(),
+            window_secs: ACTIVITY_WINDOW_SECS,
+            checked_at: now,
+        }
+    }
+
+    fn is_fresh(&self, now: Timestamp) -> bool {
+        now.as_u64() < self.checked_at.as_u64() + ACTIVITY_TTL_SECS
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
@@ -190,6 +252,7 @@ impl From<InviteRow> for Invite {
             member_count: row.member_count,
             state: InviteState::from(row.state),
             outer_event_id: row.outer_event_id,
+            activity: None,
         }
     }
 }
@@ -222,6 +285,7 @@ impl Invite {
             member_count: invite_row.member_count,
             state: InviteState::from(invite_row.state),
             outer_event_id: invite_row.outer_event_id,
+            activity: None,
         })
     }
 
@@ -258,6 +322,64 @@ impl Invite {
         Ok(self.clone())
     }
 
+    /// Fills in the group's recent activity, probing its relays unless a recent probe is cached
+    ///
+    /// The group's relays are queried at the same time by a short-lived client of their own,
+    /// within [`ACTIVITY_PROBE_TIMEOUT`], leaving out blacklisted relays. They learn that someone
+    /// is looking at the group but not who, since the fetch isn't authenticated. Unreachable
+    /// relays can't be told apart from quiet ones, so if no messages come back `activity` stays
+    /// empty.
+    pub async fn probe_activity(&mut self, blacklist: &RelayBlacklist) {
+        let now = Timestamp::now();
+        let cached = ACTIVITY_CACHE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&self.nostr_group_id)
+            .filter(|activity| activity.is_fresh(now))
+            .cloned();
+        if cached.is_some() {
+            self.activity = cached;
+            return;
+        }
+
+        let filter = Filter::new()
+            .kind(Kind::MlsGroupMessage)
+            .custom_tag(
+                SingleLetterTag::lowercase(Alphabet::H),
+                &self.nostr_group_id,
+            )
+            .since(Timestamp::from(
+                now.as_u64().saturating_sub(ACTIVITY_WINDOW_SECS),
+            ))
+            .until(now)
+            .limit(MAX_ACTIVITY_EVENTS);
+        match NostrManager::fetch_from_foreign_relays(
+            &self.group_relays,
+            filter,
+            blacklist,
+            ACTIVITY_PROBE_TIMEOUT,
+        )
+        .await
+        {
+            Ok(events) if events.is_empty() => {}
+            Ok(events) => {
+                let activity =
+                    GroupActivity::summarize(events.into_iter().map(|event| event.created_at), now);
+                ACTIVITY_CACHE
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(self.nostr_group_id.clone(), activity.clone());
+                self.activity = Some(activity);
+            }
+            Err(e) => tracing::debug!(
+                target: "whitenoise::invites::probe_activity",
+                "Couldn't probe activity of group {}: {}",
+                self.nostr_group_id,
+                e
+            ),
+        }
+    }
+
     // pub fn new(event: UnsignedEvent, database: &Database) -> Result<Invite> {}
     // pub fn find_by_event_id(event_id: &str, database: &Database) -> Result<Option<Invite>> {}
     // pub fn fetch_invites_from_relays(database: &Database) -> Result<()> {}
@@ -336,3 +458,33 @@ impl ProcessedInvite {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_counts_messages_within_the_window() {
+        let now = Timestamp::from(ACTIVITY_WINDOW_SECS * 2);
+        let activity = GroupActivity::summarize(
+            [
+                Timestamp::from(now.as_u64() - ACTIVITY_WINDOW_SECS - 1),
+                Timestamp::from(now.as_u64() - 60),
+                Timestamp::from(now.as_u64() - 3600),
+            ],
+            now,
+        );
+        assert_eq!(activity.message_count, 2);
+        assert!(!activity.capped);
+        assert_eq!(
+            activity.last_message_at,
+            Some(Timestamp::from(now.as_u64() - 60))
+        );
+        assert!(activity.is_fresh(now));
+        assert!(!activity.is_fresh(now + ACTIVITY_TTL_SECS));
+
+        let quiet = GroupActivity::summarize([], now);
+        assert_eq!(quiet.message_count, 0);
+        assert_eq!(quiet.last_message_at, None);
+    }
+}
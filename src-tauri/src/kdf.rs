@@ -0,0 +1,155 @@
+//! Key derivation from user passphrases.
+//!
+//! Keys and hashes derived from passphrases use Argon2id, which makes every guess cost memory as
+//! well as time. The parameters are stored alongside whatever was derived, so they can be raised
+//! later without breaking existing files, and [`KdfParams::validate`] bounds them when they come
+//! from a file that may have been crafted to make opening it exhaust the device.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Length of derived keys in bytes
+pub const KEY_LENGTH: usize = 32;
+
+/// Length of the random salts passphrases are derived with
+pub const SALT_LENGTH: usize = 16;
+
+/// Most memory a stored parameter set may ask for, in KiB
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+
+/// Most passes a stored parameter set may ask for
+const MAX_ITERATIONS: u32 = 16;
+
+/// Most lanes a stored parameter set may ask for
+const MAX_PARALLELISM: u32 = 8;
+
+#[derive(Error, Debug)]
+pub enum KdfError {
+    #[error("Invalid key derivation parameters: {0}")]
+    InvalidParams(String),
+
+    #[error("Key derivation failed: {0}")]
+    Argon2(String),
+}
+
+pub type Result<T> = std::result::Result<T, KdfError>;
+
+/// Argon2id cost parameters
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB and 3 passes, which takes well under a second on current phones
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Rejects parameters outside the bounds this app ever writes with room to spare
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_PARALLELISM).contains(&self.parallelism) {
+            return Err(KdfError::InvalidParams(format!(
+                "parallelism must be between 1 and {}",
+                MAX_PARALLELISM
+            )));
+        }
+        if !(1..=MAX_ITERATIONS).contains(&self.iterations) {
+            return Err(KdfError::InvalidParams(format!(
+                "iterations must be between 1 and {}",
+                MAX_ITERATIONS
+            )));
+        }
+        if !(8 * self.parallelism..=MAX_MEMORY_KIB).contains(&self.memory_kib) {
+            return Err(KdfError::InvalidParams(format!(
+                "memory must be between {} and {} KiB",
+                8 * self.parallelism,
+                MAX_MEMORY_KIB
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Derives a [`KEY_LENGTH`] byte key from a passphrase with Argon2id
+pub fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: &KdfParams,
+) -> Result<Zeroizing<[u8; KEY_LENGTH]>> {
+    params.validate()?;
+    let argon2_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(KEY_LENGTH),
+    )
+    .map_err(|e| KdfError::InvalidParams(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| KdfError::Argon2(e.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so the tests stay fast
+    fn test_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_derive_key_is_deterministic_per_salt() {
+        let params = test_params();
+        let key = derive_key("correct horse", &[1; SALT_LENGTH], &params).unwrap();
+        assert_eq!(
+            *key,
+            *derive_key("correct horse", &[1; SALT_LENGTH], &params).unwrap()
+        );
+        assert_ne!(
+            *key,
+            *derive_key("correct horse", &[2; SALT_LENGTH], &params).unwrap()
+        );
+        assert_ne!(
+            *key,
+            *derive_key("battery staple", &[1; SALT_LENGTH], &params).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_bounds_params() {
+        assert!(KdfParams::default().validate().is_ok());
+        let too_much_memory = KdfParams {
+            memory_kib: u32::MAX,
+            ..KdfParams::default()
+        };
+        assert!(too_much_memory.validate().is_err());
+        let too_many_iterations = KdfParams {
+            iterations: 1_000_000,
+            ..KdfParams::default()
+        };
+        assert!(too_many_iterations.validate().is_err());
+        let no_iterations = KdfParams {
+            iterations: 0,
+            ..KdfParams::default()
+        };
+        assert!(no_iterations.validate().is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Contact key migrations.
+//!
+//! A contact who moves to a new keypair announces it with a statement signed by the old key
+//! that endorses the new one: a kind 1776 event authored by the old key with a single `p` tag
+//! for the new key. We link the two identities, flag the messages written with the old key,
+//! and prompt the user to re-invite the new key to the groups they share with the old one.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The event kind of a key migration statement
+pub const KEY_MIGRATION_KIND: u16 = 1776;
+
+#[derive(Error, Debug)]
+pub enum KeyMigrationError {
+    #[error("Invalid key migration: {0}")]
+    InvalidMigration(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Failed to parse public key: {0}")]
+    PublicKeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+}
+
+pub type Result<T> = std::result::Result<T, KeyMigrationError>;
+
+#[derive(Debug, sqlx::FromRow)]
+struct KeyMigrationRow {
+    old_pubkey: String,
+    new_pubkey: String,
+    event_id: String,
+    created_at: u64,
+    dismissed: bool,
+}
+
+/// A link between a contact's old and new keys
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct KeyMigration {
+    pub old_pubkey: PublicKey,
+    pub new_pubkey: PublicKey,
+    /// The migration statement signed by the old key
+    pub event_id: EventId,
+    pub created_at: Timestamp,
+    /// The user dismissed the re-invite prompt
+    pub dismissed: bool,
+}
+
+/// Payload of the `contact_key_migrated` event, also returned by `get_contact_key_migrations`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyMigrationPrompt {
+    pub migration: KeyMigration,
+    /// Groups the old key is a member of but the new key isn't
+    pub groups_to_reinvite: Vec<Group>,
+}
+
+impl TryFrom<KeyMigrationRow> for KeyMigration {
+    type Error = KeyMigrationError;
+
+    fn try_from(row: KeyMigrationRow) -> Result<Self> {
+        Ok(Self {
+            old_pubkey: PublicKey::from_hex(&row.old_pubkey)?,
+            new_pubkey: PublicKey::from_hex(&row.new_pubkey)?,
+            event_id: EventId::from_hex(&row.event_id)?,
+            created_at: Timestamp::from(row.created_at),
+            dismissed: row.dismissed,
+        })
+    }
+}
+
+/// Validates a key migration statement and returns the `(old, new)` keys it links
+pub fn parse_migration(event: &Event) -> Result<(PublicKey, PublicKey)> {
+    if event.kind.as_u16() != KEY_MIGRATION_KIND {
+        return Err(KeyMigrationError::InvalidMigration(format!(
+            "Unexpected kind {}",
+            event.kind
+        )));
+    }
+    event
+        .verify()
+        .map_err(|e| KeyMigrationError::InvalidMigration(e.to_string()))?;
+
+    let new_keys: Vec<&PublicKey> = event.tags.public_keys().collect();
+    match new_keys.as_slice() {
+        [new_pubkey] if **new_pubkey != event.pubkey => Ok((event.pubkey, **new_pubkey)),
+        [_] => Err(KeyMigrationError::InvalidMigration(
+            "A key can't migrate to itself".to_string(),
+        )),
+        _ => Err(KeyMigrationError::InvalidMigration(
+            "Expected exactly one new key".to_string(),
+        )),
+    }
+}
+
+impl KeyMigration {
+    /// Stores a contact's key migration statement for the active account and flags the
+    /// messages written with the old key
+    ///
+    /// # Returns
+    /// * `Ok(Some(KeyMigration))` - The migration, if it's new or supersedes an older statement
+    /// * `Ok(None)` - If we already know about this (or a newer) migration for the old key
+    pub async fn record(event: &Event, wn: tauri::State<'_, Whitenoise>) -> Result<Option<Self>> {
+        let (old_pubkey, new_pubkey) = parse_migration(event)?;
+        Self::link(old_pubkey, new_pubkey, event.id, event.created_at, wn).await
+    }
+
+    /// Links a contact's old key to a new one like [`KeyMigration::record`], for migrations
+    /// vouched for in another way than a statement signed by the old key, e.g. by recovery
+    /// contacts (see `recovery`)
+    pub async fn link(
+        old_pubkey: PublicKey,
+        new_pubkey: PublicKey,
+        event_id: EventId,
+        created_at: Timestamp,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Option<Self>> {
+        if old_pubkey == new_pubkey {
+            return Err(KeyMigrationError::InvalidMigration(
+                "A key can't migrate to itself".to_string(),
+            ));
+        }
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let mut txn = wn.database.pool.begin().await?;
+
+        let result = sqlx::query(
+            "INSERT INTO contact_key_migrations (account_pubkey, old_pubkey, new_pubkey, event_id, created_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(account_pubkey, old_pubkey) DO UPDATE SET
+                 new_pubkey = excluded.new_pubkey,
+                 event_id = excluded.event_id,
+                 created_at = excluded.created_at,
+                 dismissed = FALSE
+             WHERE excluded.created_at > contact_key_migrations.created_at",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(old_pubkey.to_hex())
+        .bind(new_pubkey.to_hex())
+        .bind(event_id.to_hex())
+        .bind(created_at.as_u64() as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        sqlx::query(
+            "UPDATE messages SET author_migrated_to = ? WHERE account_pubkey = ? AND author_pubkey = ?",
+        )
+        .bind(new_pubkey.to_hex())
+        .bind(account_pubkey.to_hex())
+        .bind(old_pubkey.to_hex())
+        .execute(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(Some(Self {
+            old_pubkey,
+            new_pubkey,
+            event_id,
+            created_at,
+            dismissed: false,
+        }))
+    }
+
+    /// Returns all key migrations known to the active account
+    pub async fn all(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Self>> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let rows = sqlx::query_as::<_, KeyMigrationRow>(
+            "SELECT old_pubkey, new_pubkey, event_id, created_at, dismissed
+             FROM contact_key_migrations WHERE account_pubkey = ? ORDER BY created_at DESC",
+        )
+        .bind(account_pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        rows.into_iter().map(Self::try_from).collect()
+    }
+
+    /// Hides the re-invite prompt for a migration
+    pub async fn dismiss(old_pubkey: &PublicKey, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        sqlx::query(
+            "UPDATE contact_key_migrations SET dismissed = TRUE WHERE account_pubkey = ? AND old_pubkey = ?",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(old_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the groups the old key is a member of that the new key hasn't joined yet
+    pub async fn groups_to_reinvite(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Group>> {
+        let mut groups = Vec::new();
+        for group in Group::get_all_groups(wn.clone()).await? {
+            let members = group.members(wn.clone()).await?;
+            if members.contains(&self.old_pubkey) && !members.contains(&self.new_pubkey) {
+                groups.push(group);
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Builds the re-invite prompt for this migration
+    pub async fn prompt(self, wn: tauri::State<'_, Whitenoise>) -> Result<KeyMigrationPrompt> {
+        let groups_to_reinvite = self.groups_to_reinvite(wn).await?;
+        Ok(KeyMigrationPrompt {
+            migration: self,
+            groups_to_reinvite,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migration_event(old: &Keys, tags: Vec<Tag>) -> Event {
+        EventBuilder::new(Kind::Custom(KEY_MIGRATION_KIND), "")
+            .tags(tags)
+            .sign_with_keys(old)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_migration() {
+        let old = Keys::generate();
+        let new = Keys::generate();
+
+        let event = migration_event(&old, vec![Tag::public_key(new.public_key())]);
+        assert_eq!(
+            parse_migration(&event).unwrap(),
+            (old.public_key(), new.public_key())
+        );
+    }
+
+    #[test]
+    fn test_parse_migration_rejects_invalid_statements() {
+        let old = Keys::generate();
+        let new = Keys::generate();
+        let other = Keys::generate();
+
+        // No new key
+        assert!(parse_migration(&migration_event(&old, vec![])).is_err());
+        // Ambiguous new key
+        let tags = vec![
+            Tag::public_key(new.public_key()),
+            Tag::public_key(other.public_key()),
+        ];
+        assert!(parse_migration(&migration_event(&old, tags)).is_err());
+        // Migrating to itself
+        let tags = vec![Tag::public_key(old.public_key())];
+        assert!(parse_migration(&migration_event(&old, tags)).is_err());
+        // Wrong kind
+        let event = EventBuilder::text_note("")
+            .tag(Tag::public_key(new.public_key()))
+            .sign_with_keys(&old)
+            .unwrap();
+        assert!(parse_migration(&event).is_err());
+    }
+}
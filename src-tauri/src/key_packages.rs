@@ -1,11 +1,38 @@
 use crate::accounts::{Account, AccountError};
 use crate::nostr_manager;
+use crate::relay_blacklist::{self, RelayBlacklistError};
+use crate::relay_failover::{self, ArtifactKind, PublishedArtifact, RelayFailoverError};
 use crate::relays::RelayType;
 use crate::whitenoise::Whitenoise;
 use nostr_openmls::key_packages::{create_key_package_for_event, KeyPackage};
+use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
 use thiserror::Error;
 
+/// Key packages kept published unless the account configures otherwise
+pub const DEFAULT_KEY_PACKAGE_POOL_SIZE: u32 = 1;
+
+/// Upper bound for the configurable key package pool size
+pub const MAX_KEY_PACKAGE_POOL_SIZE: u32 = 10;
+
+/// How often published key packages are checked for deletions by the account's other devices
+const CONSUMPTION_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// How long the private key material of rotated key packages is kept for welcomes in flight
+const ROTATED_KEY_PACKAGE_GRACE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How many consumptions are kept for the account health view
+const MAX_CONSUMPTIONS_LISTED: i64 = 100;
+
+/// Reason of the deletions sent when rotating key packages
+const ROTATED_REASON: &str = "Rotated key package";
+
+/// Reason of the deletions sent when the user deletes their key packages
+const DELETED_REASON: &str = "Delete own key package";
+
 #[derive(Error, Debug)]
 pub enum KeyPackageError {
     #[error("No valid key package found: {0}")]
@@ -22,6 +49,14 @@ pub enum KeyPackageError {
     NostrSignerError(#[from] nostr_sdk::SignerError),
     #[error("Nostr MLS Error: {0}")]
     NostrMlsError(#[from] nostr_openmls::key_packages::KeyPackageError),
+    #[error("Relay Blacklist Error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+    #[error("Relay Failover Error: {0}")]
+    RelayFailoverError(#[from] RelayFailoverError),
+    #[error("Database Error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+    #[error("Tauri Error: {0}")]
+    TauriError(#[from] tauri::Error),
 }
 
 #[derive(Debug)]
@@ -31,6 +66,94 @@ pub struct KeyPackageResponse {
     pub key_package: KeyPackage,
 }
 
+/// How the active account's published key packages compare to its configured pool size
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPackagePoolStatus {
+    /// Hex IDs of the key package events currently published
+    pub key_package_ids: Vec<String>,
+    /// The configured minimum pool size
+    pub minimum: u32,
+    /// How many key packages have to be published to reach the minimum
+    pub missing: u32,
+}
+
+impl KeyPackagePoolStatus {
+    fn new(account: &Account) -> Self {
+        let published = account.key_package_ids.len() as u32;
+        let minimum = account.settings.key_package_pool_size;
+        Self {
+            key_package_ids: account
+                .key_package_ids
+                .iter()
+                .map(|event_id| event_id.to_hex())
+                .collect(),
+            minimum,
+            missing: minimum.saturating_sub(published),
+        }
+    }
+}
+
+/// How a key package was found to be used up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsumptionSource {
+    /// A welcome for it arrived
+    Welcome,
+    /// One of the account's other devices deleted it from the relays, which it does once it
+    /// used the key package to join a group
+    RelayDeletion,
+}
+
+impl ConsumptionSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Welcome => "welcome",
+            Self::RelayDeletion => "relay_deletion",
+        }
+    }
+}
+
+/// A published key package that was used up. Payload of the `key_package_consumed` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPackageConsumption {
+    /// Hex ID of the key package event
+    pub key_package_id: String,
+    pub consumed_at: Timestamp,
+    pub source: ConsumptionSource,
+    /// Hex encoded MLS group ID of the group it was used to join, when known
+    pub group_id: Option<String>,
+    pub group_name: Option<String>,
+    /// Hex pubkey of the member who sent the welcome, when known
+    pub inviter: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct KeyPackageConsumptionRow {
+    key_package_id: String,
+    consumed_at: i64,
+    source: String,
+    mls_group_id: Option<Vec<u8>>,
+    group_name: Option<String>,
+    inviter: Option<String>,
+}
+
+impl From<KeyPackageConsumptionRow> for KeyPackageConsumption {
+    fn from(row: KeyPackageConsumptionRow) -> Self {
+        Self {
+            key_package_id: row.key_package_id,
+            consumed_at: Timestamp::from(row.consumed_at as u64),
+            source: if row.source == ConsumptionSource::Welcome.as_str() {
+                ConsumptionSource::Welcome
+            } else {
+                ConsumptionSource::RelayDeletion
+            },
+            group_id: row.mls_group_id.map(hex::encode),
+            group_name: row.group_name,
+            inviter: row.inviter,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, KeyPackageError>;
 
 /// Fetches key packages for a list of pubkeys
@@ -48,8 +171,11 @@ pub async fn fetch_key_packages_for_members(
 
     // Check that members are valid pubkeys & fetch key packages
     for pubkey in member_pubkeys.iter() {
+        let public_key = PublicKey::from_hex(pubkey).map_err(|_| {
+            KeyPackageError::FetchingKeyPackage(format!("Invalid member pubkey: {}", pubkey))
+        })?;
         // Fetch prekeys from the members
-        match fetch_key_package_for_pubkey(pubkey.clone(), wn.clone()).await {
+        match fetch_key_package_for_pubkey(public_key, wn.clone()).await {
             Ok(event_and_key_package) => match event_and_key_package {
                 Some((event_id, kp)) => member_key_packages.push(KeyPackageResponse {
                     pubkey: pubkey.clone(),
@@ -77,11 +203,10 @@ pub async fn fetch_key_packages_for_members(
 
 /// Fetches key packages for a single pubkey
 pub async fn fetch_key_package_for_pubkey(
-    pubkey: String,
+    public_key: PublicKey,
     wn: tauri::State<'_, Whitenoise>,
 ) -> Result<Option<(EventId, KeyPackage)>> {
-    tracing::debug!(target: "whitenoise::key_packages::fetch_key_package_for_pubkey", "Fetching key package for pubkey: {:?}", pubkey);
-    let public_key = PublicKey::from_hex(&pubkey).expect("Invalid pubkey");
+    tracing::debug!(target: "whitenoise::key_packages::fetch_key_package_for_pubkey", "Fetching key package for pubkey: {:?}", public_key);
     let key_package_filter = Filter::new().kind(Kind::MlsKeyPackage).author(public_key);
     let key_package_events = wn
         .nostr
@@ -91,26 +216,13 @@ pub async fn fetch_key_package_for_pubkey(
         .expect("Error fetching key_package events");
 
     let nostr_mls = wn.nostr_mls.lock().await;
-    let ciphersuite = nostr_mls.ciphersuite;
-    let extensions = nostr_mls.extensions.clone();
 
     let mut valid_key_packages: Vec<(EventId, KeyPackage)> = Vec::new();
     for event in key_package_events.iter() {
         let key_package =
             nostr_openmls::key_packages::parse_key_package(event.content.to_string(), &nostr_mls)
                 .map_err(KeyPackageError::NostrMlsError)?;
-        if key_package.ciphersuite() == ciphersuite
-            && key_package.last_resort()
-            && key_package.leaf_node().capabilities().extensions().len() == extensions.len()
-            && extensions.iter().all(|&ext_type| {
-                key_package
-                    .leaf_node()
-                    .capabilities()
-                    .extensions()
-                    .iter()
-                    .any(|ext| ext == &ext_type)
-            })
-        {
+        if is_usable(&key_package, &nostr_mls) {
             valid_key_packages.push((event.id, key_package));
         }
     }
@@ -135,17 +247,110 @@ pub async fn fetch_key_package_for_pubkey(
     }
 }
 
+/// Fetches a specific key package of a pubkey, e.g. the one a join request names
+///
+/// # Returns
+/// * `Ok(Some(KeyPackage))` - If the key package was found and can be used with our groups
+/// * `Ok(None)` - If it wasn't found, or uses a different ciphersuite or extensions
+pub async fn fetch_key_package_by_id(
+    pubkey: &PublicKey,
+    event_id: EventId,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<KeyPackage>> {
+    let filter = Filter::new()
+        .kind(Kind::MlsKeyPackage)
+        .author(*pubkey)
+        .id(event_id);
+    let events = wn
+        .nostr
+        .client
+        .fetch_events(filter, wn.nostr.timeout().await?)
+        .await?;
+    let Some(event) = events.into_iter().find(|event| event.id == event_id) else {
+        return Ok(None);
+    };
+
+    let nostr_mls = wn.nostr_mls.lock().await;
+    let key_package =
+        nostr_openmls::key_packages::parse_key_package(event.content.to_string(), &nostr_mls)?;
+    Ok(is_usable(&key_package, &nostr_mls).then_some(key_package))
+}
+
+/// Whether a key package can be added to groups made with our ciphersuite and extensions
+fn is_usable(key_package: &KeyPackage, nostr_mls: &NostrMls) -> bool {
+    key_package.ciphersuite() == nostr_mls.ciphersuite
+        && key_package.last_resort()
+        && key_package.leaf_node().capabilities().extensions().len() == nostr_mls.extensions.len()
+        && nostr_mls.extensions.iter().all(|&ext_type| {
+            key_package
+                .leaf_node()
+                .capabilities()
+                .extensions()
+                .iter()
+                .any(|ext| ext == &ext_type)
+        })
+}
+
+/// The relays the active account's key packages are published to
+pub async fn key_package_relays(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<String>> {
+    if cfg!(dev) {
+        Ok(vec![
+            "ws://localhost:8080".to_string(),
+            "ws://localhost:7777".to_string(),
+        ])
+    } else {
+        Ok(account.relays(RelayType::KeyPackage, wn.clone()).await?)
+    }
+}
+
+/// Adds the relays key package events were actually published to, e.g. fallback relays, to
+/// `relays`
+async fn with_published_relays(
+    event_ids: &[EventId],
+    mut relays: Vec<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<String>> {
+    for event_id in event_ids {
+        if let Some(artifact) = PublishedArtifact::find(event_id, wn.clone()).await? {
+            for url in artifact.relays {
+                if !relays.contains(&url) {
+                    relays.push(url);
+                }
+            }
+        }
+    }
+    Ok(relays)
+}
+
+/// Stops tracking key packages as published on the active account
+async fn forget_key_packages(
+    event_ids: &[EventId],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut account = Account::get_active(wn.clone()).await?;
+    account
+        .key_package_ids
+        .retain(|event_id| !event_ids.contains(event_id));
+    account.save(wn.clone()).await?;
+    Ok(())
+}
+
 /// Deletes a specific key package event from Nostr relays.
 ///
 /// This function performs the following steps:
-/// 1. Retrieves the relays associated with key packages for the current identity.
-/// 2. Fetches the specific key package event from the Nostr network.
-/// 3. Verifies that the event is a valid key package event and is authored by the current user.
-/// 4. Creates and sends a delete event for the specified key package event.
+/// 1. Fetches the specific key package event from the Nostr network.
+/// 2. Verifies that the event is a valid key package event and is authored by the current user.
+/// 3. Creates and sends a delete event for the specified key package event.
+/// 4. Removes the event from the account's published key packages.
 ///
 /// # Arguments
 ///
 /// * `event_id` - The `EventId` of the key package event to be deleted.
+/// * `key_package_relays` - The account's key package relays.
+/// * `delete_mls_stored_keys` - Whether to also delete the private key material from MLS storage.
 /// * `wn` - A Tauri State containing a Whitenoise instance, which provides access to Nostr functionality.
 ///
 /// # Returns
@@ -156,27 +361,17 @@ pub async fn fetch_key_package_for_pubkey(
 /// # Errors
 ///
 /// This function may return an error if:
-/// - There's an error retrieving the key package relays for the current identity.
 /// - There's an error fetching the specified event from the Nostr network.
 /// - The specified event is not a key package event (Kind::KeyPackage).
 /// - The specified event is not authored by the current user.
 /// - There's an error creating or sending the delete event.
-#[allow(unused)]
 pub async fn delete_key_package_from_relays(
     event_id: &EventId,
     key_package_relays: &[String],
     delete_mls_stored_keys: bool,
     wn: tauri::State<'_, Whitenoise>,
 ) -> Result<()> {
-    let current_pubkey = wn
-        .nostr
-        .client
-        .signer()
-        .await
-        .unwrap()
-        .get_public_key()
-        .await
-        .unwrap();
+    let current_pubkey = wn.nostr.client.signer().await?.get_public_key().await?;
 
     let key_package_filter = Filter::new()
         .id(*event_id)
@@ -186,7 +381,7 @@ pub async fn delete_key_package_from_relays(
     let key_package_events = wn
         .nostr
         .client
-        .fetch_events(key_package_filter, wn.nostr.timeout().await.unwrap())
+        .fetch_events(key_package_filter, wn.nostr.timeout().await?)
         .await?;
 
     if let Some(event) = key_package_events.first() {
@@ -202,30 +397,34 @@ pub async fn delete_key_package_from_relays(
             nostr_openmls::key_packages::delete_key_package_from_storage(key_package, &nostr_mls)
                 .map_err(KeyPackageError::NostrMlsError)?;
         }
-        let builder = EventBuilder::delete(EventDeletionRequest::new().id(event.id));
-        wn.nostr
+        let relays =
+            with_published_relays(&[event.id], key_package_relays.to_vec(), wn.clone()).await?;
+
+        let deletion = wn
+            .nostr
             .client
-            .send_event_builder_to(key_package_relays, builder)
+            .sign_event_builder(EventBuilder::delete(
+                EventDeletionRequest::new().id(event.id),
+            ))
             .await?;
+        relay_blacklist::send_event(&deletion, relays, wn.clone()).await?;
     }
-    Ok(())
+
+    forget_key_packages(&[*event_id], wn).await
 }
 
-pub async fn publish_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+/// Creates a fresh key package, publishes it to the active account's key package relays and
+/// records it on the account
+///
+/// # Returns
+///
+/// * `Ok(EventId)` - The ID of the published key package event
+pub async fn publish_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<EventId> {
     let active_account = Account::get_active(wn.clone()).await?;
     let pubkey = active_account.pubkey;
 
     let event: EventBuilder;
-    let key_package_relays = if cfg!(dev) {
-        vec![
-            "ws://localhost:8080".to_string(),
-            "ws://localhost:7777".to_string(),
-        ]
-    } else {
-        active_account
-            .relays(RelayType::KeyPackage, wn.clone())
-            .await?
-    };
+    let key_package_relays = key_package_relays(&active_account, wn.clone()).await?;
 
     {
         let nostr_mls = wn.nostr_mls.lock().await;
@@ -242,10 +441,498 @@ pub async fn publish_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<()>
             Tag::custom(TagKind::Relays, key_package_relays.clone()),
         ]);
     }
-    wn.nostr
+    let event = wn.nostr.client.sign_event_builder(event).await?;
+    let artifact = relay_failover::publish_with_failover(
+        &event,
+        key_package_relays,
+        ArtifactKind::KeyPackage,
+        None,
+        wn.clone(),
+    )
+    .await?;
+
+    tracing::debug!(
+        target: "whitenoise::key_packages::publish_key_package",
+        "Published key package {} to {:?}",
+        artifact.event_id,
+        artifact.relays
+    );
+
+    // Reload the account, publishing can take a while
+    let mut account = Account::get_active(wn.clone()).await?;
+    account.key_package_ids.push(event.id);
+    account.save(wn.clone()).await?;
+
+    Ok(event.id)
+}
+
+/// Publishes a fresh key package, deletes the ones published before it and refills the pool
+///
+/// The private key material of the old key packages is kept in MLS storage for
+/// `ROTATED_KEY_PACKAGE_GRACE` so that welcomes already in flight for them can still be
+/// processed, and purged by the background task after that.
+///
+/// # Returns
+///
+/// * `Ok(EventId)` - The ID of the new key package event
+pub async fn rotate_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<EventId> {
+    let account = Account::get_active(wn.clone()).await?;
+    let stale = account.key_package_ids.clone();
+    let event_id = publish_key_package(wn.clone()).await?;
+    record_rotated(&account, &stale, wn.clone()).await?;
+    delete_key_packages_by_id(&stale, ROTATED_REASON, wn.clone()).await?;
+    // Refill the rest of the pool
+    replenish(wn.clone()).await?;
+
+    tracing::debug!(
+        target: "whitenoise::key_packages::rotate_key_package",
+        "Rotated key package to {}, deleted {} stale key packages",
+        event_id,
+        stale.len()
+    );
+    Ok(event_id)
+}
+
+/// Remembers the serialized key packages of rotated key package events, so that their private key
+/// material can be purged once the grace period is over
+async fn record_rotated(
+    account: &Account,
+    event_ids: &[EventId],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let rotated_at = Timestamp::now();
+    for event in wn.nostr.query_user_key_packages(account.pubkey).await? {
+        if !event_ids.contains(&event.id) {
+            continue;
+        }
+        sqlx::query(
+            "INSERT OR IGNORE INTO rotated_key_packages
+             (account_pubkey, key_package_id, key_package, rotated_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(event.id.to_hex())
+        .bind(event.content.to_string())
+        .bind(rotated_at.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Deletes the private key material of the active account's key packages rotated more than
+/// `ROTATED_KEY_PACKAGE_GRACE` ago from MLS storage
+///
+/// # Returns
+///
+/// * `Ok(usize)` - How many rotated key packages were purged
+async fn purge_rotated(wn: tauri::State<'_, Whitenoise>) -> Result<usize> {
+    let account = Account::get_active(wn.clone()).await?;
+    let cutoff = Timestamp::now().as_u64() - ROTATED_KEY_PACKAGE_GRACE.as_secs();
+    let rotated = sqlx::query_as::<_, (String, String)>(
+        "SELECT key_package_id, key_package FROM rotated_key_packages
+         WHERE account_pubkey = ? AND rotated_at <= ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(cutoff as i64)
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    for (key_package_id, serialized) in &rotated {
+        let purged = {
+            let nostr_mls = wn.nostr_mls.lock().await;
+            nostr_openmls::key_packages::parse_key_package(serialized.clone(), &nostr_mls).and_then(
+                |key_package| {
+                    nostr_openmls::key_packages::delete_key_package_from_storage(
+                        key_package,
+                        &nostr_mls,
+                    )
+                },
+            )
+        };
+        // A key package that can't be parsed has nothing left to purge either
+        if let Err(e) = purged {
+            tracing::warn!(
+                target: "whitenoise::key_packages::purge_rotated",
+                "Couldn't purge rotated key package {}: {}",
+                key_package_id,
+                e
+            );
+        }
+        sqlx::query(
+            "DELETE FROM rotated_key_packages WHERE account_pubkey = ? AND key_package_id = ?",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(key_package_id)
+        .execute(&wn.database.pool)
+        .await?;
+    }
+    Ok(rotated.len())
+}
+
+/// Deletes every key package of the active account: the ones recorded on the account and any
+/// other key package events of the account in the local cache
+///
+/// # Returns
+///
+/// * `Ok(usize)` - How many key package events deletion was requested for
+pub async fn delete_key_packages(wn: tauri::State<'_, Whitenoise>) -> Result<usize> {
+    let account = Account::get_active(wn.clone()).await?;
+    let mut event_ids = account.key_package_ids.clone();
+    for event in wn.nostr.query_user_key_packages(account.pubkey).await? {
+        if !event_ids.contains(&event.id) {
+            event_ids.push(event.id);
+        }
+    }
+
+    delete_key_packages_by_id(&event_ids, DELETED_REASON, wn).await?;
+    Ok(event_ids.len())
+}
+
+/// Sends a single NIP-09 deletion for the given key package events and stops tracking them
+async fn delete_key_packages_by_id(
+    event_ids: &[EventId],
+    reason: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    if event_ids.is_empty() {
+        tracing::debug!(target: "whitenoise::key_packages::delete_key_packages_by_id", "No key packages to delete");
+        return Ok(());
+    }
+
+    let account = Account::get_active(wn.clone()).await?;
+    let relays = key_package_relays(&account, wn.clone()).await?;
+    let relays = with_published_relays(event_ids, relays, wn.clone()).await?;
+
+    let delete_event = wn
+        .nostr
         .client
-        .send_event_builder_to(key_package_relays.clone(), event)
+        .sign_event_builder(EventBuilder::delete(
+            EventDeletionRequest::new()
+                .ids(event_ids.iter().copied())
+                .reason(reason),
+        ))
         .await?;
+    tracing::debug!(target: "whitenoise::key_packages::delete_key_packages_by_id", "Deleting key packages: {:?}", event_ids);
+    relay_blacklist::send_event(&delete_event, relays, wn.clone()).await?;
+
+    forget_key_packages(event_ids, wn).await
+}
+
+/// Returns the active account with its published key packages. Accounts that published key
+/// packages before they were tracked start with none recorded, so those are picked up from the
+/// local cache, leaving out the ones the account deleted.
+async fn with_key_package_ids(wn: tauri::State<'_, Whitenoise>) -> Result<Account> {
+    let mut account = Account::get_active(wn.clone()).await?;
+    if !account.key_package_ids.is_empty() {
+        return Ok(account);
+    }
+
+    let cached: Vec<EventId> = wn
+        .nostr
+        .query_user_key_packages(account.pubkey)
+        .await?
+        .into_iter()
+        .map(|event| event.id)
+        .collect();
+    if cached.is_empty() {
+        return Ok(account);
+    }
+    let filter = Filter::new()
+        .kind(Kind::EventDeletion)
+        .author(account.pubkey)
+        .events(cached.iter().copied());
+    let deletions: Vec<Event> = wn
+        .nostr
+        .client
+        .database()
+        .query(filter)
+        .await
+        .map_err(nostr_manager::NostrManagerError::from)?
+        .into_iter()
+        .collect();
+    let deleted: Vec<EventId> = deleted_key_packages(&deletions, &cached)
+        .into_iter()
+        .map(|(event_id, _)| event_id)
+        .collect();
+
+    account.key_package_ids = cached
+        .into_iter()
+        .filter(|event_id| !deleted.contains(event_id))
+        .collect();
+    if !account.key_package_ids.is_empty() {
+        tracing::debug!(
+            target: "whitenoise::key_packages::with_key_package_ids",
+            "Found {} published key packages in the cache",
+            account.key_package_ids.len()
+        );
+        account.save(wn.clone()).await?;
+    }
+    Ok(account)
+}
+
+/// Returns the active account's key package pool status
+pub async fn pool_status(wn: tauri::State<'_, Whitenoise>) -> Result<KeyPackagePoolStatus> {
+    let account = with_key_package_ids(wn.clone()).await?;
+    Ok(KeyPackagePoolStatus::new(&account))
+}
+
+/// Publishes key packages until the active account has its configured minimum published
+///
+/// # Returns
+///
+/// * `Ok(KeyPackagePoolStatus)` - The pool status after replenishing
+pub async fn replenish(wn: tauri::State<'_, Whitenoise>) -> Result<KeyPackagePoolStatus> {
+    let missing = pool_status(wn.clone()).await?.missing;
+    for _ in 0..missing {
+        publish_key_package(wn.clone()).await?;
+    }
+    if missing > 0 {
+        tracing::debug!(
+            target: "whitenoise::key_packages::replenish",
+            "Published {} key packages to refill the pool",
+            missing
+        );
+    }
+    pool_status(wn).await
+}
+
+/// Records that a key package was used up and emits `key_package_consumed`, unless it was
+/// already recorded
+///
+/// # Returns
+///
+/// * `Ok(bool)` - Whether the consumption is new
+pub async fn record_consumption(
+    account: &Account,
+    consumption: &KeyPackageConsumption,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &AppHandle,
+) -> Result<bool> {
+    let mls_group_id = consumption
+        .group_id
+        .as_deref()
+        .and_then(|group_id| hex::decode(group_id).ok());
+    let inserted = sqlx::query(
+        "INSERT OR IGNORE INTO key_package_consumptions
+         (account_pubkey, key_package_id, consumed_at, source, mls_group_id, group_name, inviter)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(&consumption.key_package_id)
+    .bind(consumption.consumed_at.as_u64() as i64)
+    .bind(consumption.source.as_str())
+    .bind(mls_group_id)
+    .bind(&consumption.group_name)
+    .bind(&consumption.inviter)
+    .execute(&wn.database.pool)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if inserted {
+        tracing::debug!(
+            target: "whitenoise::key_packages::record_consumption",
+            "Key package {} was consumed ({:?})",
+            consumption.key_package_id,
+            consumption.source
+        );
+        app_handle.emit("key_package_consumed", consumption)?;
+    }
+    Ok(inserted)
+}
+
+/// The active account's consumed key packages, most recent first
+pub async fn consumptions(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<KeyPackageConsumption>> {
+    let account = Account::get_active(wn.clone()).await?;
+    Ok(sqlx::query_as::<_, KeyPackageConsumptionRow>(
+        "SELECT key_package_id, consumed_at, source, mls_group_id, group_name, inviter
+         FROM key_package_consumptions WHERE account_pubkey = ?
+         ORDER BY consumed_at DESC LIMIT ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(MAX_CONSUMPTIONS_LISTED)
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(KeyPackageConsumption::from)
+    .collect())
+}
+
+/// The published key packages that `deletions` delete, with when they were deleted
+fn deleted_key_packages(deletions: &[Event], published: &[EventId]) -> Vec<(EventId, Timestamp)> {
+    let mut deleted: Vec<(EventId, Timestamp)> = Vec::new();
+    for deletion in deletions {
+        for event_id in deletion.tags.event_ids() {
+            if published.contains(event_id) && !deleted.iter().any(|(id, _)| id == event_id) {
+                deleted.push((*event_id, deletion.created_at));
+            }
+        }
+    }
+    deleted
+}
 
+/// Whether a deletion of the account's key packages means they were used to join a group, as
+/// opposed to rotated or deleted by the user on this or another device
+fn is_consumption(deletion: &Event) -> bool {
+    deletion.content != ROTATED_REASON && deletion.content != DELETED_REASON
+}
+
+/// Looks for deletions of the active account's published key packages by its other devices,
+/// records them as consumed and refills the pool. Rotated and deleted key packages are only
+/// forgotten, they weren't consumed.
+async fn check_consumed_elsewhere(app_handle: &AppHandle) -> Result<()> {
+    let wn = app_handle.state::<Whitenoise>();
+    let account = with_key_package_ids(wn.clone()).await?;
+    if account.key_package_ids.is_empty() {
+        return Ok(());
+    }
+
+    let filter = Filter::new()
+        .kind(Kind::EventDeletion)
+        .author(account.pubkey)
+        .events(account.key_package_ids.iter().copied());
+    let deletions: Vec<Event> = wn
+        .nostr
+        .client
+        .fetch_events(filter, wn.nostr.timeout().await?)
+        .await?
+        .into_iter()
+        .collect();
+    let deleted = deleted_key_packages(&deletions, &account.key_package_ids);
+    if deleted.is_empty() {
+        return Ok(());
+    }
+
+    let consumption_deletions: Vec<Event> = deletions.into_iter().filter(is_consumption).collect();
+    for (event_id, deleted_at) in
+        deleted_key_packages(&consumption_deletions, &account.key_package_ids)
+    {
+        let consumption = KeyPackageConsumption {
+            key_package_id: event_id.to_hex(),
+            consumed_at: deleted_at,
+            source: ConsumptionSource::RelayDeletion,
+            group_id: None,
+            group_name: None,
+            inviter: None,
+        };
+        record_consumption(&account, &consumption, wn.clone(), app_handle).await?;
+    }
+    let event_ids: Vec<EventId> = deleted.into_iter().map(|(event_id, _)| event_id).collect();
+    forget_key_packages(&event_ids, wn.clone()).await?;
+    replenish(wn).await?;
     Ok(())
 }
+
+/// Starts the background task that notices key packages consumed on the account's other devices
+/// and purges rotated key packages once their grace period is over
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CONSUMPTION_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_consumed_elsewhere(&app_handle).await {
+                tracing::debug!(
+                    target: "whitenoise::key_packages::check_consumed_elsewhere",
+                    "Couldn't check for consumed key packages: {}",
+                    e
+                );
+            }
+            if let Err(e) = purge_rotated(app_handle.state::<Whitenoise>()).await {
+                tracing::debug!(
+                    target: "whitenoise::key_packages::purge_rotated",
+                    "Couldn't purge rotated key packages: {}",
+                    e
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::{AccountOnboarding, AccountSettings};
+
+    fn create_test_account() -> Account {
+        Account {
+            pubkey: Keys::generate().public_key(),
+            metadata: Metadata::default(),
+            settings: AccountSettings::default(),
+            onboarding: AccountOnboarding::default(),
+            last_used: Timestamp::now(),
+            last_synced: Timestamp::zero(),
+            active: true,
+            key_package_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_pool_status_counts_missing_key_packages() {
+        let mut account = create_test_account();
+        account.settings.key_package_pool_size = 3;
+        account.key_package_ids = vec![EventId::all_zeros()];
+
+        let status = KeyPackagePoolStatus::new(&account);
+        assert_eq!(status.minimum, 3);
+        assert_eq!(status.missing, 2);
+        assert_eq!(status.key_package_ids, vec![EventId::all_zeros().to_hex()]);
+    }
+
+    #[test]
+    fn test_pool_status_full_pool_is_not_missing_anything() {
+        let mut account = create_test_account();
+        account.settings.key_package_pool_size = 1;
+        account.key_package_ids = vec![EventId::all_zeros()];
+        assert_eq!(KeyPackagePoolStatus::new(&account).missing, 0);
+
+        account.settings.key_package_pool_size = 0;
+        account.key_package_ids = Vec::new();
+        assert_eq!(KeyPackagePoolStatus::new(&account).missing, 0);
+    }
+
+    #[test]
+    fn test_pool_size_defaults_for_existing_settings() {
+        let settings: AccountSettings =
+            serde_json::from_str(r#"{"dark_theme":true,"dev_mode":false,"lockdown_mode":false}"#)
+                .unwrap();
+        assert_eq!(
+            settings.key_package_pool_size,
+            DEFAULT_KEY_PACKAGE_POOL_SIZE
+        );
+    }
+
+    #[test]
+    fn test_deleted_key_packages_only_matches_published_ones() {
+        let keys = Keys::generate();
+        let published = [EventId::from_slice(&[1; 32]).unwrap()];
+        let unrelated = EventId::from_slice(&[2; 32]).unwrap();
+        let deletion =
+            EventBuilder::delete(EventDeletionRequest::new().ids([published[0], unrelated]))
+                .custom_created_at(Timestamp::from(42))
+                .sign_with_keys(&keys)
+                .unwrap();
+
+        assert_eq!(
+            deleted_key_packages(&[deletion.clone(), deletion], &published),
+            vec![(published[0], Timestamp::from(42))]
+        );
+        assert!(deleted_key_packages(&[], &published).is_empty());
+    }
+
+    #[test]
+    fn test_is_consumption_leaves_out_rotations_and_deletions() {
+        let keys = Keys::generate();
+        let key_package_id = EventId::from_slice(&[1; 32]).unwrap();
+        let deletion = |reason: Option<&str>| {
+            let mut request = EventDeletionRequest::new().id(key_package_id);
+            if let Some(reason) = reason {
+                request = request.reason(reason);
+            }
+            EventBuilder::delete(request).sign_with_keys(&keys).unwrap()
+        };
+
+        assert!(is_consumption(&deletion(None)));
+        assert!(!is_consumption(&deletion(Some(ROTATED_REASON))));
+        assert!(!is_consumption(&deletion(Some(DELETED_REASON))));
+    }
+}
@@ -1,28 +1,90 @@
+mod account_backup;
 mod accounts;
+mod app_data;
+mod app_lock;
+mod atomic_file;
+mod background_refresh;
+mod blocklist;
+mod bulk_group_actions;
+mod capabilities;
+mod capture_protection;
+mod client_import;
 mod commands;
+mod contact_search;
+mod contacts;
+mod content_filters;
+mod data_export;
 mod database;
+mod db_encryption;
+#[cfg(feature = "dev-relay")]
+mod dev_relay;
+mod device_sync;
+mod epoch_recovery;
+mod error;
+mod expiry;
+mod group_custom_data;
+mod group_event_log;
+mod group_notes;
+mod group_security;
+mod group_tasks;
+mod group_templates;
 mod groups;
+mod integrity;
+mod invite_messages;
 mod invites;
+mod kdf;
+mod key_migrations;
 mod key_packages;
+mod localization;
 mod media;
+mod media_library;
 mod messages;
+mod nip05;
 mod nostr_manager;
+mod notifications;
+mod outbox;
+mod params;
 mod payments;
+mod pending_welcomes;
+mod profiling;
+mod protocol;
+mod quick_switcher;
+mod reactions;
+mod read_receipts;
+mod recovery;
+mod relay_blacklist;
+mod relay_failover;
 mod relays;
+mod runtime_state;
 mod secrets_store;
+mod sensitive_actions;
+mod settings_sync;
+mod sync_scheduler;
+mod sync_throttle;
 mod types;
+mod typing;
+mod usage_stats;
 mod utils;
 mod whitenoise;
 
 use crate::commands::accounts::*;
+use crate::commands::app_lock::*;
+use crate::commands::contacts::*;
 use crate::commands::groups::*;
 use crate::commands::invites::*;
 use crate::commands::key_packages::*;
+use crate::commands::localization::*;
 use crate::commands::media::*;
 use crate::commands::messages::*;
 use crate::commands::nostr::*;
 use crate::commands::payments::*;
-use crate::commands::{delete_all_data, is_mobile, is_platform};
+use crate::commands::quick_switcher::*;
+use crate::commands::relays::*;
+use crate::commands::secrets::*;
+use crate::commands::{
+    delete_all_data, dump_runtime_state, get_capabilities, get_performance_report, is_mobile,
+    is_platform, verify_data_integrity,
+};
 use crate::whitenoise::Whitenoise;
 use once_cell::sync::Lazy;
 use std::path::PathBuf;
@@ -76,63 +138,210 @@ pub fn run() {
                 window.close_devtools();
             }
 
+            let app_handle = app.handle().clone();
+            profiling::init(app_handle.clone());
             tauri::async_runtime::block_on(async move {
                 let whitenoise =
                     Whitenoise::new(formatted_data_dir, formatted_logs_dir, app.handle().clone())
                         .await;
                 app.manage(whitenoise);
             });
+
+            expiry::start(app_handle.clone());
+            nostr_manager::relay_monitor::start(app_handle.clone());
+            outbox::start(app_handle.clone());
+            key_packages::start(app_handle.clone());
+            sync_scheduler::start(app_handle.clone());
+            media::avatars::start(app_handle.clone());
+            secrets_store::start_cache_sweep();
+            app_lock::start(app_handle);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             create_identity,
             get_accounts,
+            get_usage_stats,
             set_active_account,
             login,
             logout,
             init_nostr_for_current_user,
+            run_background_refresh,
+            set_power_state,
+            pause_sync,
+            resume_sync,
+            sync_now,
             fetch_contacts_with_metadata,
             query_contacts_with_metadata,
             fetch_enriched_contact,
             query_enriched_contact,
             fetch_enriched_contacts,
             query_enriched_contacts,
+            resolve_nip05,
+            verify_nip05,
+            get_contacts,
+            add_contact,
+            remove_contact,
+            search_contacts,
+            block_user,
+            unblock_user,
+            get_blocked_users,
+            publish_contact_list,
+            get_contact_key_migrations,
+            dismiss_contact_key_migration,
             fetch_relays,
+            get_relay_blacklist,
+            get_relay_recommendations,
+            get_relay_status,
+            blacklist_relay,
+            unblacklist_relay,
+            get_relays,
+            publish_nip65_relay_list,
+            add_relay,
+            remove_relay,
+            start_dev_relay,
+            test_relay,
             encrypt_content,
             decrypt_content,
             create_group,
+            create_group_from_template,
+            retry_pending_welcomes,
+            create_invite_message,
+            parse_invite_message,
+            create_group_invite_link,
+            accept_invite_link,
+            pending_join_requests,
+            approve_join_request,
+            reject_join_request,
+            fetch_group_messages,
             get_groups,
+            get_or_create_self_group,
             get_invites,
             publish_new_key_package,
             delete_all_key_packages,
+            publish_key_package,
+            rotate_key_package,
+            delete_key_packages,
+            key_package_pool_status,
+            get_key_package_consumptions,
             valid_key_package_exists_for_user,
             publish_relay_list,
             update_account_onboarding,
+            set_whitelist_only_mode,
+            set_send_read_receipts,
+            set_sync_policy,
+            set_sync_schedule,
+            get_notification_settings,
+            set_notification_settings,
+            set_key_package_pool_size,
+            set_export_secret_retention,
+            set_content_filter,
+            save_group_template,
+            delete_group_template,
+            set_device_local_settings,
+            set_device_sync,
+            set_auto_lock,
+            set_media_server,
+            set_fallback_relays,
+            export_account,
+            export_account_data,
+            import_account_backup,
+            export_app_data,
+            import_app_data,
+            import_from_client_export,
+            designate_recovery_contacts,
+            request_account_recovery,
+            get_recovery_requests,
+            approve_recovery_request,
+            set_duress_passphrase,
+            clear_duress_passphrase,
+            set_app_passphrase,
+            lock_app,
+            unlock_app,
+            is_app_locked,
+            is_app_passphrase_set,
+            report_app_activity,
+            request_sensitive_action,
+            get_secrets_backend,
+            set_secrets_backend,
+            encrypt_database,
+            is_database_encrypted,
             has_nostr_wallet_connect_uri,
             set_nostr_wallet_connect_uri,
             remove_nostr_wallet_connect_uri,
             get_nostr_wallet_connect_balance,
             get_group,
             get_group_and_messages,
+            get_group_messages,
+            get_message_thread,
+            merge_groups,
+            bulk_group_action,
+            mark_group_read,
+            get_unread_counts,
+            get_unread_mention_counts,
+            get_read_receipts,
+            get_message_edit_history,
+            get_message_delivery_status,
             get_group_members,
             get_group_admins,
+            get_group_security_info,
+            get_mutual_groups,
+            get_group_notices,
+            send_group_notice,
+            get_group_notes,
+            update_group_note,
+            get_group_tasks,
+            get_group_media,
+            create_group_task,
+            set_group_task_completed,
+            get_group_custom_data,
+            set_group_custom_data,
+            set_group_locale,
+            set_group_relays,
+            set_group_sensitive,
+            set_group_content_filter,
+            snooze_group,
+            unsnooze_group,
+            get_localized_strings,
             rotate_key_in_group,
             get_invite,
             accept_invite,
             decline_invite,
             pay_invoice,
+            pay_invoice_from_chat,
+            connect_wallet,
             send_mls_message,
+            send_quick_reply,
+            send_mls_attachment,
+            download_attachment,
+            send_voice_message,
+            download_voice_message,
+            send_mls_reaction,
+            remove_mls_reaction,
+            get_message_reactions,
+            send_typing_indicator,
             delete_message,
+            delete_mls_message,
+            edit_mls_message,
             delete_all_data,
+            verify_data_integrity,
+            get_performance_report,
+            dump_runtime_state,
+            replay_group_events,
             search_for_enriched_contacts,
             invite_to_white_noise,
             query_message,
+            search_messages,
+            get_quick_switcher_entries,
             export_nsec,
             upload_file,
             upload_media,
+            upload_profile_picture,
+            get_cached_avatar,
             publish_metadata_event,
+            update_profile,
             is_mobile,
             is_platform,
+            get_capabilities,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -150,7 +359,7 @@ fn setup_logging(logs_dir: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     let (non_blocking_stdout, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
 
     static GUARDS: Lazy<Mutex<Option<(WorkerGuard, WorkerGuard)>>> = Lazy::new(|| Mutex::new(None));
-    *GUARDS.lock().unwrap() = Some((file_guard, stdout_guard));
+    *GUARDS.lock().unwrap_or_else(|e| e.into_inner()) = Some((file_guard, stdout_guard));
 
     Registry::default()
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug")))
@@ -0,0 +1,228 @@
+//! Localization helpers used by the backend when it has to produce user facing text
+//! (system messages, notifications, exported timestamps) on behalf of a group.
+//!
+//! The same string tables are exposed to the frontends through the `get_localized_strings`
+//! command so that every client renders backend generated text consistently.
+
+use chrono::{DateTime, Utc};
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LocalizationError {
+    #[error("Unsupported locale: {0}")]
+    UnsupportedLocale(String),
+}
+
+pub type Result<T> = std::result::Result<T, LocalizationError>;
+
+/// The languages the backend can format text for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+    Pt,
+    Fr,
+    De,
+}
+
+impl Locale {
+    #[cfg(test)]
+    pub const ALL: [Locale; 5] = [Locale::En, Locale::Es, Locale::Pt, Locale::Fr, Locale::De];
+
+    /// Parses a BCP 47 style tag (e.g. `es`, `pt-BR`, `de_AT`) into a supported locale
+    ///
+    /// Only the primary language subtag is used; region subtags are accepted but ignored.
+    pub fn parse(tag: &str) -> Result<Self> {
+        let language = tag
+            .trim()
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" => Ok(Self::En),
+            "es" => Ok(Self::Es),
+            "pt" => Ok(Self::Pt),
+            "fr" => Ok(Self::Fr),
+            "de" => Ok(Self::De),
+            _ => Err(LocalizationError::UnsupportedLocale(tag.to_string())),
+        }
+    }
+
+    /// Resolves an optional stored locale hint, falling back to English when missing or unknown
+    pub fn from_hint(hint: Option<&str>) -> Self {
+        hint.and_then(|tag| Self::parse(tag).ok())
+            .unwrap_or_default()
+    }
+
+    /// The chrono format string used for timestamps in this locale
+    fn timestamp_format(&self) -> &'static str {
+        match self {
+            Self::En => "%b %-d, %Y %H:%M UTC",
+            Self::Es | Self::Pt | Self::Fr => "%d/%m/%Y %H:%M UTC",
+            Self::De => "%d.%m.%Y %H:%M UTC",
+        }
+    }
+}
+
+/// Keys for every backend generated string
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum StringKey {
+    NoteToSelf,
+    NewInvite,
+    InvitedToGroup,
+    GroupMoved,
+    ReactionRemoved,
+}
+
+impl StringKey {
+    pub const ALL: [StringKey; 5] = [
+        StringKey::NoteToSelf,
+        StringKey::NewInvite,
+        StringKey::InvitedToGroup,
+        StringKey::GroupMoved,
+        StringKey::ReactionRemoved,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoteToSelf => "note_to_self",
+            Self::NewInvite => "new_invite",
+            Self::InvitedToGroup => "invited_to_group",
+            Self::GroupMoved => "group_moved",
+            Self::ReactionRemoved => "reaction_removed",
+        }
+    }
+}
+
+/// Returns the template for a key in the given locale.
+///
+/// Templates use positional placeholders (`{0}`, `{1}`, ...) filled in by [`format_string`].
+pub fn template(locale: Locale, key: StringKey) -> &'static str {
+    use Locale::*;
+    use StringKey::*;
+    match (locale, key) {
+        (En, NoteToSelf) => "Note to Self",
+        (En, NewInvite) => "New invite",
+        (En, InvitedToGroup) => "You were invited to {0}",
+        (En, GroupMoved) => {
+            "This group has moved to \"{0}\". Please use the new group from now on."
+        }
+        (En, ReactionRemoved) => "Reaction removed",
+
+        (Es, NoteToSelf) => "Notas personales",
+        (Es, NewInvite) => "Nueva invitación",
+        (Es, InvitedToGroup) => "Te invitaron a {0}",
+        (Es, GroupMoved) => {
+            "Este grupo se trasladó a \"{0}\". Usa el nuevo grupo a partir de ahora."
+        }
+        (Es, ReactionRemoved) => "Reacción eliminada",
+
+        (Pt, NoteToSelf) => "Notas pessoais",
+        (Pt, NewInvite) => "Novo convite",
+        (Pt, InvitedToGroup) => "Você foi convidado para {0}",
+        (Pt, GroupMoved) => "Este grupo mudou para \"{0}\". Use o novo grupo a partir de agora.",
+        (Pt, ReactionRemoved) => "Reação removida",
+
+        (Fr, NoteToSelf) => "Notes personnelles",
+        (Fr, NewInvite) => "Nouvelle invitation",
+        (Fr, InvitedToGroup) => "Vous avez été invité à rejoindre {0}",
+        (Fr, GroupMoved) => {
+            "Ce groupe a été déplacé vers « {0} ». Veuillez désormais utiliser le nouveau groupe."
+        }
+        (Fr, ReactionRemoved) => "Réaction retirée",
+
+        (De, NoteToSelf) => "Notizen an mich",
+        (De, NewInvite) => "Neue Einladung",
+        (De, InvitedToGroup) => "Du wurdest zu {0} eingeladen",
+        (De, GroupMoved) => {
+            "Diese Gruppe ist nach „{0}“ umgezogen. Bitte nutze ab jetzt die neue Gruppe."
+        }
+        (De, ReactionRemoved) => "Reaktion entfernt",
+    }
+}
+
+/// Formats a localized string, replacing `{n}` placeholders with the given arguments
+pub fn format_string(locale: Locale, key: StringKey, args: &[&str]) -> String {
+    args.iter()
+        .enumerate()
+        .fold(template(locale, key).to_string(), |acc, (i, arg)| {
+            acc.replace(&format!("{{{}}}", i), arg)
+        })
+}
+
+/// Formats a Nostr timestamp for display in the given locale (always rendered in UTC)
+pub fn format_timestamp(locale: Locale, timestamp: Timestamp) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp.as_u64() as i64, 0)
+        .map(|dt| dt.format(locale.timestamp_format()).to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Returns the full string table for a locale, keyed by the snake_case string key
+pub fn strings(locale: Locale) -> HashMap<&'static str, &'static str> {
+    StringKey::ALL
+        .iter()
+        .map(|key| (key.as_str(), template(locale, *key)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("es").unwrap(), Locale::Es);
+        assert_eq!(Locale::parse("pt-BR").unwrap(), Locale::Pt);
+        assert_eq!(Locale::parse("de_AT").unwrap(), Locale::De);
+        assert_eq!(Locale::parse(" EN ").unwrap(), Locale::En);
+        assert!(Locale::parse("xx").is_err());
+        assert!(Locale::parse("").is_err());
+    }
+
+    #[test]
+    fn test_from_hint_falls_back_to_english() {
+        assert_eq!(Locale::from_hint(None), Locale::En);
+        assert_eq!(Locale::from_hint(Some("klingon")), Locale::En);
+        assert_eq!(Locale::from_hint(Some("fr-CA")), Locale::Fr);
+    }
+
+    #[test]
+    fn test_format_string() {
+        assert_eq!(
+            format_string(Locale::En, StringKey::InvitedToGroup, &["Team"]),
+            "You were invited to Team"
+        );
+        assert_eq!(
+            format_string(Locale::Es, StringKey::InvitedToGroup, &["Team"]),
+            "Te invitaron a Team"
+        );
+        assert_eq!(
+            format_string(Locale::De, StringKey::GroupMoved, &["Team"]),
+            "Diese Gruppe ist nach „Team“ umgezogen. Bitte nutze ab jetzt die neue Gruppe."
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let ts = Timestamp::from(1_700_000_000);
+        assert_eq!(format_timestamp(Locale::En, ts), "Nov 14, 2023 22:13 UTC");
+        assert_eq!(format_timestamp(Locale::Es, ts), "14/11/2023 22:13 UTC");
+        assert_eq!(format_timestamp(Locale::De, ts), "14.11.2023 22:13 UTC");
+    }
+
+    #[test]
+    fn test_every_locale_has_every_string() {
+        for locale in Locale::ALL {
+            let table = strings(locale);
+            assert_eq!(table.len(), StringKey::ALL.len());
+            assert!(table.values().all(|s| !s.is_empty()));
+        }
+    }
+}
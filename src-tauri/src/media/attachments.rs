@@ -0,0 +1,480 @@
+//! Encrypted file attachments.
+//!
+//! Unlike media uploaded with [`add_media_file`](super::add_media_file), which is encrypted with
+//! a key derived from the group's exporter secret, every attachment is encrypted with its own
+//! random key. The key, nonce and hashes travel inside the (already MLS encrypted) message in an
+//! `imeta` tag, so members can still open the file after the group moves to a new epoch and the
+//! media server only ever sees ciphertext.
+
+use crate::media::blossom::BlossomClient;
+use crate::media::encryption;
+use crate::media::errors::MediaError;
+use crate::media::servers::{self, MediaServer};
+use crate::media::types::MediaServerSettings;
+use nostr_sdk::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const ATTACHMENT_CACHE_DIR: &str = "attachments";
+const ENCRYPTION_ALGORITHM: &str = "chacha20-poly1305";
+
+/// Largest attachment that's downloaded, matching the upload limit of the default media server
+pub const MAX_ATTACHMENT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// What ChaCha20-Poly1305 adds to a file: its 16 byte authentication tag
+const ENCRYPTION_OVERHEAD: u64 = 16;
+
+/// Everything a member needs to fetch, verify and decrypt an attachment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentMeta {
+    /// Where the encrypted file is stored
+    pub url: String,
+    /// Other servers storing a copy of the encrypted file, tried in order when `url` fails
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// The MIME type of the original file
+    pub mime_type: String,
+    /// The original filename
+    pub filename: String,
+    /// Size of the original file in bytes
+    pub size: u64,
+    /// Hex encoded SHA256 of the encrypted file, as stored on the media server
+    pub encrypted_hash: String,
+    /// Hex encoded SHA256 of the original file
+    pub original_hash: String,
+    /// Hex encoded 32 byte ChaCha20-Poly1305 key
+    pub key: String,
+    /// Hex encoded 12 byte nonce
+    pub nonce: String,
+    /// Length of audio and video attachments in milliseconds
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// Amplitude samples (0-100) of voice messages, used to draw the waveform before the
+    /// file is downloaded
+    #[serde(default)]
+    pub waveform: Option<Vec<u8>>,
+}
+
+impl AttachmentMeta {
+    /// Builds the `imeta` tag carried in the message.
+    pub fn to_tag(&self) -> Tag {
+        let mut values = vec![format!("url {}", self.url)];
+        values.extend(
+            self.mirrors
+                .iter()
+                .map(|mirror| format!("fallback {}", mirror)),
+        );
+        values.extend([
+            format!("m {}", self.mime_type),
+            format!("filename {}", self.filename),
+            format!("size {}", self.size),
+            format!("x {}", self.encrypted_hash),
+            format!("ox {}", self.original_hash),
+            format!("decryption-key {}", self.key),
+            format!("decryption-nonce {}", self.nonce),
+            format!("encryption-algorithm {}", ENCRYPTION_ALGORITHM),
+        ]);
+        if let Some(duration_ms) = self.duration_ms {
+            values.push(format!("duration {}", duration_ms));
+        }
+        if let Some(waveform) = &self.waveform {
+            let samples: Vec<String> = waveform.iter().map(u8::to_string).collect();
+            values.push(format!("waveform {}", samples.join(" ")));
+        }
+        Tag::custom(TagKind::from("imeta"), values)
+    }
+
+    /// Parses an `imeta` tag. Returns `None` for tags that aren't attachments, e.g. media
+    /// encrypted with the group's exporter secret, which has no `decryption-key`.
+    pub fn from_tag(tag: &Tag) -> Option<Self> {
+        let values = tag.as_slice();
+        if values.first().map(|v| v.as_str()) != Some("imeta") {
+            return None;
+        }
+
+        let fields = |name: &str| {
+            values[1..]
+                .iter()
+                .filter_map(|value| {
+                    value
+                        .split_once(' ')
+                        .filter(|(key, _)| *key == name)
+                        .map(|(_, value)| value.to_string())
+                })
+                .collect::<Vec<_>>()
+        };
+        let field = |name: &str| fields(name).into_iter().next();
+
+        if field("encryption-algorithm")? != ENCRYPTION_ALGORITHM {
+            return None;
+        }
+
+        Some(Self {
+            url: field("url")?,
+            mirrors: fields("fallback"),
+            mime_type: field("m").unwrap_or_else(|| "application/octet-stream".to_string()),
+            filename: field("filename").unwrap_or_else(|| "attachment".to_string()),
+            size: field("size").and_then(|s| s.parse().ok()).unwrap_or(0),
+            encrypted_hash: field("x")?,
+            original_hash: field("ox")?,
+            key: field("decryption-key")?,
+            nonce: field("decryption-nonce")?,
+            duration_ms: field("duration").and_then(|d| d.parse().ok()),
+            waveform: field("waveform").and_then(|w| {
+                w.split_whitespace()
+                    .map(|sample| sample.parse().ok())
+                    .collect::<Option<Vec<u8>>>()
+            }),
+        })
+    }
+
+    /// Returns every attachment referenced in a message's tags.
+    pub fn from_tags(tags: &Tags) -> Vec<Self> {
+        tags.iter().filter_map(Self::from_tag).collect()
+    }
+}
+
+/// The result of encrypting a file for upload.
+pub struct EncryptedAttachment {
+    /// The ciphertext to upload
+    pub data: Vec<u8>,
+    /// The attachment metadata, with an empty `url` until the file is uploaded
+    pub meta: AttachmentMeta,
+}
+
+impl EncryptedAttachment {
+    /// Uploads the file to the account's media servers, mirroring it if the account asks for
+    /// it, and returns its metadata with the URLs filled in
+    pub async fn upload(
+        self,
+        settings: &MediaServerSettings,
+        default_blossom: &BlossomClient,
+    ) -> Result<AttachmentMeta, MediaError> {
+        let servers = servers::servers_for(settings, default_blossom);
+        let mut meta = self.meta;
+        if settings.mirror_attachments {
+            (meta.url, meta.mirrors) = servers::upload_mirrored(self.data, &servers).await?;
+        } else {
+            meta.url = servers::upload(self.data, &servers).await?;
+        }
+        Ok(meta)
+    }
+}
+
+/// Encrypts a file with a fresh random key.
+pub fn encrypt_attachment(
+    data: &[u8],
+    filename: &str,
+    mime_type: &str,
+) -> Result<EncryptedAttachment, MediaError> {
+    let mut key = [0u8; 32];
+    rand::rng().fill_bytes(&mut key);
+
+    let (encrypted, nonce) = encryption::encrypt_file(data, &key)?;
+
+    Ok(EncryptedAttachment {
+        meta: AttachmentMeta {
+            url: String::new(),
+            mirrors: Vec::new(),
+            mime_type: mime_type.to_string(),
+            filename: filename.to_string(),
+            size: data.len() as u64,
+            encrypted_hash: sha256_hex(&encrypted),
+            original_hash: sha256_hex(data),
+            key: hex::encode(key),
+            nonce: hex::encode(nonce),
+            duration_ms: None,
+            waveform: None,
+        },
+        data: encrypted,
+    })
+}
+
+/// Verifies the hashes of a downloaded attachment and decrypts it.
+///
+/// Both the ciphertext and the decrypted file are checked so a tampered or swapped upload is
+/// rejected before anything is written to disk.
+pub fn decrypt_attachment(meta: &AttachmentMeta, encrypted: &[u8]) -> Result<Vec<u8>, MediaError> {
+    if !sha256_hex(encrypted).eq_ignore_ascii_case(&meta.encrypted_hash) {
+        return Err(MediaError::Integrity(
+            "Encrypted file hash doesn't match".to_string(),
+        ));
+    }
+
+    let key = hex::decode(&meta.key).map_err(|e| MediaError::Decryption(e.to_string()))?;
+    let nonce = hex::decode(&meta.nonce).map_err(|e| MediaError::Decryption(e.to_string()))?;
+    if key.len() != 32 || nonce.len() != 12 {
+        return Err(MediaError::Decryption(
+            "Invalid key or nonce length".to_string(),
+        ));
+    }
+
+    let data = encryption::decrypt_file(encrypted, &key, &nonce)?;
+    if !sha256_hex(&data).eq_ignore_ascii_case(&meta.original_hash) {
+        return Err(MediaError::Integrity(
+            "Decrypted file hash doesn't match".to_string(),
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Uploads an encrypted attachment to the account's media servers, falling back to the next
+/// one when a server is down, and returns its URL.
+pub async fn upload_attachment(
+    data: Vec<u8>,
+    settings: &MediaServerSettings,
+    default_blossom: &BlossomClient,
+) -> Result<String, MediaError> {
+    servers::upload(data, &servers::servers_for(settings, default_blossom)).await
+}
+
+/// Downloads, verifies and decrypts an attachment into the local cache and returns the path
+/// of the decrypted file. Files already in the cache aren't downloaded again.
+///
+/// If neither the attachment's URL nor its mirrors work, it's looked up on `servers` by its
+/// encrypted hash. Attachments over [`MAX_ATTACHMENT_BYTES`], and servers returning more than
+/// the size in the message, are rejected.
+pub async fn download_attachment(
+    meta: &AttachmentMeta,
+    mls_group_id: &[u8],
+    data_dir: &Path,
+    servers: &[Box<dyn MediaServer>],
+) -> Result<PathBuf, MediaError> {
+    if meta.size > MAX_ATTACHMENT_BYTES {
+        return Err(MediaError::Download(format!(
+            "Attachment is larger than {} MB",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let path = cache_path(meta, mls_group_id, data_dir);
+    match tokio::fs::read(&path).await {
+        Ok(cached) if sha256_hex(&cached).eq_ignore_ascii_case(&meta.original_hash) => {
+            return Ok(path);
+        }
+        Ok(_) => tracing::warn!(
+            target: "whitenoise::media::attachments",
+            "Cached attachment {} is corrupt, downloading it again",
+            path.display()
+        ),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(MediaError::Cache(e.to_string())),
+    }
+
+    let urls: Vec<&str> = std::iter::once(&meta.url)
+        .chain(&meta.mirrors)
+        .map(String::as_str)
+        .collect();
+    let encrypted = servers::fetch(
+        &urls,
+        &meta.encrypted_hash,
+        meta.size + ENCRYPTION_OVERHEAD,
+        servers,
+    )
+    .await?;
+
+    let data = decrypt_attachment(meta, &encrypted)?;
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| MediaError::Cache(e.to_string()))?;
+    }
+    tokio::fs::write(&path, data)
+        .await
+        .map_err(|e| MediaError::Cache(e.to_string()))?;
+
+    Ok(path)
+}
+
+/// Removes the decrypted copies of the attachments in `tags` from the local cache, so nothing
+/// of a deleted or expired message stays on disk
+pub async fn purge_cached(tags: &Tags, mls_group_id: &[u8], data_dir: &Path) {
+    for meta in AttachmentMeta::from_tags(tags) {
+        let path = cache_path(&meta, mls_group_id, data_dir);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!(
+                target: "whitenoise::media::attachments::purge_cached",
+                "Failed to remove cached attachment {}: {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Guesses a MIME type from a file extension. Unknown files are sent as binary data.
+pub fn mime_type_for_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "ogg" | "opus" => "audio/ogg",
+        "wav" => "audio/wav",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Cached files are stored by group and original hash, keeping the extension so the OS can
+/// open them with the right application.
+fn cache_path(meta: &AttachmentMeta, mls_group_id: &[u8], data_dir: &Path) -> PathBuf {
+    let mut file_name = meta.original_hash.to_lowercase();
+    if let Some(extension) = Path::new(&meta.filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric()))
+    {
+        file_name.push('.');
+        file_name.push_str(extension);
+    }
+    data_dir
+        .join(ATTACHMENT_CACHE_DIR)
+        .join(hex::encode(mls_group_id))
+        .join(file_name)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_and_decrypt_attachment() {
+        let encrypted = encrypt_attachment(b"hello", "hello.txt", "text/plain").unwrap();
+        assert_ne!(encrypted.data, b"hello");
+        assert_eq!(encrypted.meta.size, 5);
+
+        let decrypted = decrypt_attachment(&encrypted.meta, &encrypted.data).unwrap();
+        assert_eq!(decrypted, b"hello");
+    }
+
+    #[test]
+    fn test_each_attachment_gets_its_own_key() {
+        let first = encrypt_attachment(b"hello", "a.txt", "text/plain").unwrap();
+        let second = encrypt_attachment(b"hello", "a.txt", "text/plain").unwrap();
+        assert_ne!(first.meta.key, second.meta.key);
+        assert_eq!(first.meta.original_hash, second.meta.original_hash);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampered_file() {
+        let encrypted = encrypt_attachment(b"hello", "hello.txt", "text/plain").unwrap();
+        let mut tampered = encrypted.data.clone();
+        tampered[0] ^= 1;
+
+        assert!(matches!(
+            decrypt_attachment(&encrypted.meta, &tampered),
+            Err(MediaError::Integrity(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_purge_cached_removes_decrypted_files() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut encrypted = encrypt_attachment(b"hello", "hello.txt", "text/plain").unwrap();
+        encrypted.meta.url = "https://example.com/abc".to_string();
+        let path = cache_path(&encrypted.meta, b"group", data_dir.path());
+        tokio::fs::create_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+        tokio::fs::write(&path, b"hello").await.unwrap();
+
+        let tags = Tags::from_list(vec![encrypted.meta.to_tag()]);
+        purge_cached(&tags, b"group", data_dir.path()).await;
+        assert!(!path.exists());
+
+        // Nothing left to remove isn't an error
+        purge_cached(&tags, b"group", data_dir.path()).await;
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_oversized_attachment() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mut encrypted = encrypt_attachment(b"hello", "hello.txt", "text/plain").unwrap();
+        encrypted.meta.size = MAX_ATTACHMENT_BYTES + 1;
+
+        assert!(matches!(
+            download_attachment(&encrypted.meta, b"group", data_dir.path(), &[]).await,
+            Err(MediaError::Download(_))
+        ));
+    }
+
+    #[test]
+    fn test_tag_round_trip() {
+        let mut encrypted = encrypt_attachment(b"hello", "hello.txt", "text/plain").unwrap();
+        encrypted.meta.url = "https://example.com/abc".to_string();
+
+        let tag = encrypted.meta.to_tag();
+        assert_eq!(AttachmentMeta::from_tag(&tag), Some(encrypted.meta));
+    }
+
+    #[test]
+    fn test_tag_round_trip_with_mirrors() {
+        let mut encrypted = encrypt_attachment(b"hello", "hello.txt", "text/plain").unwrap();
+        encrypted.meta.url = "https://a.example/abc".to_string();
+        encrypted.meta.mirrors = vec![
+            "https://b.example/abc".to_string(),
+            "https://c.example/abc".to_string(),
+        ];
+
+        let tag = encrypted.meta.to_tag();
+        assert_eq!(AttachmentMeta::from_tag(&tag), Some(encrypted.meta));
+    }
+
+    #[test]
+    fn test_tag_round_trip_with_voice_metadata() {
+        let mut encrypted = encrypt_attachment(b"hello", "voice.ogg", "audio/ogg").unwrap();
+        encrypted.meta.url = "https://example.com/abc".to_string();
+        encrypted.meta.duration_ms = Some(4_200);
+        encrypted.meta.waveform = Some(vec![0, 12, 100, 7]);
+
+        let tag = encrypted.meta.to_tag();
+        assert_eq!(AttachmentMeta::from_tag(&tag), Some(encrypted.meta));
+    }
+
+    #[test]
+    fn test_from_tag_ignores_group_media() {
+        let tag = Tag::custom(
+            TagKind::from("imeta"),
+            vec![
+                "url https://example.com/abc".to_string(),
+                "x abc".to_string(),
+                "decryption-nonce 00".to_string(),
+                "encryption-algorithm chacha20-poly1305".to_string(),
+            ],
+        );
+        assert_eq!(AttachmentMeta::from_tag(&tag), None);
+    }
+
+    #[test]
+    fn test_mime_type_for_path() {
+        assert_eq!(mime_type_for_path(Path::new("a/b/photo.JPG")), "image/jpeg");
+        assert_eq!(
+            mime_type_for_path(Path::new("notes")),
+            "application/octet-stream"
+        );
+    }
+}
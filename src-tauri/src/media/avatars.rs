@@ -0,0 +1,379 @@
+//! Local cache of profile pictures.
+//!
+//! Contacts' avatars are downloaded in the background into `<data_dir>/avatars`, so frontends
+//! can show them from disk instead of hitting every picture URL on each render (which also
+//! tells those servers when the user is online). A cached file is keyed by the pubkey and a
+//! hash of the picture URL, so changing a profile picture replaces it. Pictures larger than
+//! [`MAX_AVATAR_BYTES`] or that aren't images are skipped, and the least recently used files are
+//! evicted once the cache grows past [`MAX_CACHE_BYTES`].
+//!
+//! Profile pictures are set by anyone, so only HTTPS URLs on a domain name and the default port
+//! are downloaded, and redirects are held to the same rule. That keeps avatars from reaching
+//! into the user's local network.
+
+use crate::accounts::Account;
+use crate::contacts;
+use crate::media::MediaError;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Largest profile picture that's downloaded or uploaded
+pub const MAX_AVATAR_BYTES: usize = 2 * 1024 * 1024;
+
+/// Size the cache is pruned back to
+pub const MAX_CACHE_BYTES: u64 = 100 * 1024 * 1024;
+
+const AVATAR_CACHE_DIR: &str = "avatars";
+
+/// How often queued avatars are downloaded
+const FETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a picture server gets to answer
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How many redirects a picture server can send
+const MAX_REDIRECTS: usize = 3;
+
+/// How long a picture that failed to download isn't tried again
+const RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Avatars waiting for the background fetcher: picture URL by hex encoded pubkey
+static PENDING: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// When each picture URL last failed to download
+static FAILED: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Where the avatar of `pubkey` at `url` is cached
+pub fn cache_path(data_dir: &Path, pubkey: &str, url: &str) -> PathBuf {
+    let url_hash = hex::encode(Sha256::digest(url.as_bytes()));
+    data_dir
+        .join(AVATAR_CACHE_DIR)
+        .join(format!("{}-{}", pubkey, &url_hash[..16]))
+}
+
+/// Whether a picture URL may be downloaded: HTTPS on the default port of a host that's a domain
+/// name rather than an IP address or a local name
+fn is_allowed_url(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let local = host == "localhost"
+        || host.ends_with(".localhost")
+        || host.ends_with(".local")
+        || host.ends_with(".internal");
+    url.scheme() == "https"
+        && url.port().is_none()
+        && host.contains('.')
+        && !local
+        && host.trim_matches(['[', ']']).parse::<IpAddr>().is_err()
+}
+
+/// Returns the cached avatar of `pubkey` at `url`, queueing it for download if it's missing.
+/// Pictures at URLs that aren't allowed are never queued.
+pub fn cached_or_queue(data_dir: &Path, pubkey: &str, url: &str) -> Option<PathBuf> {
+    let path = cache_path(data_dir, pubkey, url);
+    if path.exists() {
+        return Some(path);
+    }
+    if !Url::parse(url).is_ok_and(|url| is_allowed_url(&url)) {
+        return None;
+    }
+    let recently_failed = FAILED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(url)
+        .is_some_and(|failed_at| failed_at.elapsed() < RETRY_AFTER);
+    if recently_failed {
+        return None;
+    }
+    PENDING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(pubkey.to_string(), url.to_string());
+    None
+}
+
+/// Stores an avatar, replacing the pubkey's earlier pictures
+pub async fn store(
+    data_dir: &Path,
+    pubkey: &str,
+    url: &str,
+    data: &[u8],
+) -> Result<PathBuf, MediaError> {
+    if data.len() > MAX_AVATAR_BYTES {
+        return Err(MediaError::Cache(format!(
+            "Avatar is larger than {} bytes",
+            MAX_AVATAR_BYTES
+        )));
+    }
+    if ::image::guess_format(data).is_err() {
+        return Err(MediaError::Cache("Avatar isn't an image".to_string()));
+    }
+
+    let path = cache_path(data_dir, pubkey, url);
+    let dir = data_dir.join(AVATAR_CACHE_DIR);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| MediaError::Cache(e.to_string()))?;
+    let prefix = format!("{}-", pubkey);
+    let mut entries = tokio::fs::read_dir(&dir)
+        .await
+        .map_err(|e| MediaError::Cache(e.to_string()))?;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let _ = tokio::fs::remove_file(entry.path()).await;
+        }
+    }
+    tokio::fs::write(&path, data)
+        .await
+        .map_err(|e| MediaError::Cache(e.to_string()))?;
+    Ok(path)
+}
+
+/// Downloads an avatar into the cache, giving up on pictures over [`MAX_AVATAR_BYTES`] and on
+/// URLs that aren't allowed
+pub async fn download(data_dir: &Path, pubkey: &str, url: &str) -> Result<PathBuf, MediaError> {
+    let parsed = Url::parse(url).map_err(|e| MediaError::Download(e.to_string()))?;
+    if !is_allowed_url(&parsed) {
+        return Err(MediaError::Download(format!(
+            "Avatar URL isn't allowed: {}",
+            url
+        )));
+    }
+    let redirects = reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("Too many redirects")
+        } else if is_allowed_url(attempt.url()) {
+            attempt.follow()
+        } else {
+            attempt.error("Redirected to a URL that isn't allowed")
+        }
+    });
+    let client = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .redirect(redirects)
+        .build()
+        .map_err(|e| MediaError::Download(e.to_string()))?;
+    let mut response = client
+        .get(parsed)
+        .send()
+        .await
+        .map_err(|e| MediaError::Download(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(MediaError::Download(format!(
+            "Download failed with status: {}",
+            response.status()
+        )));
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length as usize > MAX_AVATAR_BYTES)
+    {
+        return Err(MediaError::Download("Avatar is too large".to_string()));
+    }
+
+    // Servers can omit or lie about the length, so the limit is enforced while reading too
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| MediaError::Download(e.to_string()))?
+    {
+        if data.len() + chunk.len() > MAX_AVATAR_BYTES {
+            return Err(MediaError::Download("Avatar is too large".to_string()));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    store(data_dir, pubkey, url, &data).await
+}
+
+/// Deletes the least recently used avatars until the cache is at most `max_bytes`
+pub async fn prune(data_dir: &Path, max_bytes: u64) -> Result<(), MediaError> {
+    let Ok(mut entries) = tokio::fs::read_dir(data_dir.join(AVATAR_CACHE_DIR)).await else {
+        return Ok(());
+    };
+    let mut files: Vec<(PathBuf, u64, std::time::SystemTime)> = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        if let Ok(used) = metadata.accessed().or_else(|_| metadata.modified()) {
+            files.push((entry.path(), metadata.len(), used));
+        }
+    }
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort_by_key(|(_, _, used)| *used);
+    for (path, size, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|e| MediaError::Cache(e.to_string()))?;
+        total -= size;
+    }
+    Ok(())
+}
+
+/// Queues the avatars of the active account's contacts, from their cached metadata
+async fn queue_contacts(wn: tauri::State<'_, Whitenoise>) {
+    let Ok(account) = Account::get_active(wn.clone()).await else {
+        return;
+    };
+    let Ok(contact_list) = contacts::list(&account, wn.clone()).await else {
+        return;
+    };
+    for contact in contact_list {
+        if let Some(picture) = contact
+            .enriched
+            .and_then(|enriched| enriched.metadata.picture)
+        {
+            cached_or_queue(&wn.data_dir, &contact.pubkey, &picture);
+        }
+    }
+}
+
+async fn fetch_pending(app_handle: &AppHandle) {
+    let wn = app_handle.state::<Whitenoise>();
+    queue_contacts(wn.clone()).await;
+    let pending: Vec<(String, String)> = PENDING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .drain()
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+
+    for (pubkey, url) in pending {
+        if let Err(e) = download(&wn.data_dir, &pubkey, &url).await {
+            FAILED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(url.clone(), Instant::now());
+            tracing::debug!(
+                target: "whitenoise::media::avatars::fetch_pending",
+                "Couldn't cache avatar of {}: {}",
+                pubkey,
+                e
+            );
+        }
+    }
+    if let Err(e) = prune(&wn.data_dir, MAX_CACHE_BYTES).await {
+        tracing::error!(
+            target: "whitenoise::media::avatars::fetch_pending",
+            "Failed to prune avatar cache: {}",
+            e
+        );
+    }
+}
+
+/// Starts the background task that downloads queued avatars
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(FETCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            fetch_pending(&app_handle).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+    const PNG_HEADER: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+    #[tokio::test]
+    async fn test_store_replaces_earlier_pictures() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = store(
+            dir.path(),
+            PUBKEY,
+            "https://example.com/old.png",
+            &PNG_HEADER,
+        )
+        .await
+        .unwrap();
+        let new = store(
+            dir.path(),
+            PUBKEY,
+            "https://example.com/new.png",
+            &PNG_HEADER,
+        )
+        .await
+        .unwrap();
+        assert!(!old.exists());
+        assert!(new.exists());
+        assert_eq!(
+            cached_or_queue(dir.path(), PUBKEY, "https://example.com/new.png"),
+            Some(new)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_rejects_non_images_and_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://example.com/a.png";
+        assert!(store(dir.path(), PUBKEY, url, b"<html></html>")
+            .await
+            .is_err());
+        let mut large = PNG_HEADER.to_vec();
+        large.resize(MAX_AVATAR_BYTES + 1, 0);
+        assert!(store(dir.path(), PUBKEY, url, &large).await.is_err());
+    }
+
+    #[test]
+    fn test_only_public_https_urls_are_allowed() {
+        let allowed = |url: &str| is_allowed_url(&Url::parse(url).unwrap());
+        assert!(allowed("https://example.com/a.png"));
+        assert!(!allowed("http://example.com/a.png"));
+        assert!(!allowed("https://example.com:8443/a.png"));
+        assert!(!allowed("https://192.168.1.1/a.png"));
+        assert!(!allowed("https://[::1]/a.png"));
+        assert!(!allowed("https://localhost/a.png"));
+        assert!(!allowed("https://router.local/a.png"));
+        assert_eq!(
+            cached_or_queue(
+                tempfile::tempdir().unwrap().path(),
+                PUBKEY,
+                "http://10.0.0.1/a.png"
+            ),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_cache_under_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..3 {
+            let mut data = PNG_HEADER.to_vec();
+            data.resize(1_000, 0);
+            store(
+                dir.path(),
+                &format!("{:064}", i),
+                "https://example.com/a.png",
+                &data,
+            )
+            .await
+            .unwrap();
+        }
+        prune(dir.path(), 2_000).await.unwrap();
+        let remaining = std::fs::read_dir(dir.path().join(AVATAR_CACHE_DIR))
+            .unwrap()
+            .count();
+        assert_eq!(remaining, 2);
+    }
+}
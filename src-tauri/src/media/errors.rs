@@ -20,8 +20,12 @@ pub enum MediaError {
     #[error("Sanitization error: {0}")]
     Sanitize(String),
 
-    // #[error("Failed to download file: {0}")]
-    // Download(String),
+    #[error("Failed to download file: {0}")]
+    Download(String),
+
+    #[error("File integrity check failed: {0}")]
+    Integrity(String),
+
     #[error("Failed to generate IMETA tag: {0}")]
     Encryption(String),
 
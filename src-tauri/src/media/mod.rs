@@ -36,13 +36,31 @@
 //! - For images: dimensions and blurhash
 //! - SHA256 hash of the original file
 //! - Decryption information (nonce and algorithm)
+//!
+//! # Attachments
+//!
+//! Files sent with `send_mls_attachment` are handled by the [`attachments`] module instead. Each
+//! one is encrypted with its own random key, which is carried in the message together with the
+//! hashes of the encrypted and original file, and can go to a Blossom, NIP-96 or plain HTTP
+//! server chosen per account, with fallbacks (see [`servers`]). Voice messages are attachments
+//! too; the [`voice`] module adds their duration and waveform.
+//!
+//! # Avatars
+//!
+//! Profile pictures are public, so they're uploaded unencrypted. The [`avatars`] module keeps a
+//! size-limited local cache of contacts' pictures for frontends to show from disk.
 
+pub mod attachments;
+pub mod avatars;
 pub mod blossom;
 mod cache;
 mod encryption;
 mod errors;
+mod nip96;
 mod sanitizer;
+pub mod servers;
 mod types;
+pub mod voice;
 
 pub use errors::MediaError;
 pub use sanitizer::sanitize_media;
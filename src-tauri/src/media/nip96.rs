@@ -0,0 +1,140 @@
+//! Minimal NIP-96 (HTTP file storage) client, used when an account picks a NIP-96 server for
+//! attachments instead of Blossom.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use nostr_sdk::prelude::*;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// The parts of `/.well-known/nostr/nip96.json` we need
+#[derive(Debug, Deserialize)]
+struct ServerInfo {
+    api_url: String,
+}
+
+/// The parts of an upload response we need
+#[derive(Debug, Deserialize)]
+struct UploadResponse {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    nip94_event: Option<Nip94Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nip94Event {
+    tags: Vec<Vec<String>>,
+}
+
+/// Client for interacting with a NIP-96 server
+#[derive(Clone, Debug)]
+pub struct Nip96Client {
+    /// Base URL of the server
+    pub url: String,
+}
+
+impl Nip96Client {
+    /// Creates a new Nip96Client instance
+    ///
+    /// # Arguments
+    /// * `url` - The base URL of the NIP-96 server
+    pub fn new(url: &str) -> Self {
+        Nip96Client {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Looks up the URL files are uploaded to and downloaded from
+    pub async fn api_url(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let info: ServerInfo = reqwest::Client::new()
+            .get(format!("{}/.well-known/nostr/nip96.json", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(info.api_url.trim_end_matches('/').to_string())
+    }
+
+    /// Uploads a file and returns its download URL
+    ///
+    /// Uploads are authorized with a NIP-98 event signed by a throwaway key so the upload can't
+    /// be linked to the account.
+    ///
+    /// # Arguments
+    /// * `file` - The file contents as a byte vector
+    pub async fn upload(
+        &self,
+        file: Vec<u8>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let client = reqwest::Client::new();
+        let api_url = self.api_url().await?;
+
+        let payload = format!("{:x}", Sha256::digest(&file));
+        let auth_header = self
+            .create_auth_event(&api_url, "POST", &payload, &Keys::generate())
+            .await?;
+
+        let form = reqwest::multipart::Form::new().part(
+            "file",
+            reqwest::multipart::Part::bytes(file)
+                .file_name("blob")
+                .mime_str("application/octet-stream")?,
+        );
+
+        let response = client
+            .post(&api_url)
+            .header("Authorization", auth_header)
+            .multipart(form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Upload failed with status: {}", response.status()).into());
+        }
+
+        let body: UploadResponse = response.json().await?;
+        if body.status != "success" {
+            return Err(body
+                .message
+                .unwrap_or_else(|| "Upload failed".to_string())
+                .into());
+        }
+
+        body.nip94_event
+            .and_then(|event| {
+                event
+                    .tags
+                    .into_iter()
+                    .find(|tag| tag.first().map(|t| t.as_str()) == Some("url"))
+                    .and_then(|tag| tag.get(1).cloned())
+            })
+            .ok_or_else(|| "Upload response has no url".into())
+    }
+
+    /// Creates a NIP-98 HTTP auth header
+    async fn create_auth_event(
+        &self,
+        url: &str,
+        method: &str,
+        payload: &str,
+        keys: &Keys,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let tags = vec![
+            Tag::custom(TagKind::Custom("u".into()), vec![url.to_string()]),
+            Tag::custom(TagKind::Custom("method".into()), vec![method.to_string()]),
+            Tag::custom(TagKind::Custom("payload".into()), vec![payload.to_string()]),
+        ];
+
+        let event = EventBuilder::new(Kind::Custom(27235), "")
+            .tags(tags)
+            .sign(keys)
+            .await?;
+
+        Ok(format!(
+            "Nostr {}",
+            STANDARD.encode(serde_json::to_string(&event)?)
+        ))
+    }
+}
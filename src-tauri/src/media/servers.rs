@@ -0,0 +1,402 @@
+//! The servers attachments are stored on.
+//!
+//! Uploads go through the [`MediaServer`] trait, implemented for Blossom, NIP-96 and plain HTTP
+//! servers (e.g. a self-hosted S3 compatible bucket). An account picks a primary server and
+//! fallbacks in its [`MediaServerSettings`], and a file goes to the first one that accepts it.
+//! The app's default Blossom server is only used when the account didn't pick a primary server:
+//! files never go to, or are looked up on, a server the user didn't choose.
+//! Servers that failed recently are tried after the others for [`RETRY_AFTER`], so a server
+//! that's down doesn't slow down every upload.
+//!
+//! Attachments can also be mirrored to a second server, whose URL is sent along with the first
+//! one. All of these servers address files by their SHA256, so when none of the URLs in a message
+//! work, the file is looked up by its encrypted hash on the account's servers instead.
+
+use crate::media::blossom::BlossomClient;
+use crate::media::errors::MediaError;
+use crate::media::nip96::Nip96Client;
+use crate::media::types::{MediaServerEndpoint, MediaServerProtocol, MediaServerSettings};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a server that failed is tried after the others
+const RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// When each server, by URL, last failed
+static FAILED: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A server attachments can be uploaded to
+#[async_trait::async_trait]
+pub trait MediaServer: Send + Sync {
+    /// Base URL of the server
+    fn url(&self) -> &str;
+
+    /// Uploads a file and returns its download URL
+    async fn upload(&self, data: Vec<u8>) -> Result<String, MediaError>;
+
+    /// Where the server serves a file with the given hex encoded SHA256
+    async fn blob_url(&self, sha256: &str) -> Result<String, MediaError> {
+        Ok(format!("{}/{}", self.url(), sha256))
+    }
+}
+
+#[async_trait::async_trait]
+impl MediaServer for BlossomClient {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn upload(&self, data: Vec<u8>) -> Result<String, MediaError> {
+        BlossomClient::upload(self, data)
+            .await
+            .map(|(blob, _)| blob.url)
+            .map_err(|e| MediaError::Upload(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl MediaServer for Nip96Client {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn upload(&self, data: Vec<u8>) -> Result<String, MediaError> {
+        Nip96Client::upload(self, data)
+            .await
+            .map_err(|e| MediaError::Upload(e.to_string()))
+    }
+
+    async fn blob_url(&self, sha256: &str) -> Result<String, MediaError> {
+        let api_url = self
+            .api_url()
+            .await
+            .map_err(|e| MediaError::Download(e.to_string()))?;
+        Ok(format!("{}/{}", api_url, sha256))
+    }
+}
+
+/// A plain HTTP server that stores files `PUT` to `<url>/<sha256>`
+#[derive(Clone, Debug)]
+pub struct HttpMediaServer {
+    pub url: String,
+}
+
+impl HttpMediaServer {
+    pub fn new(url: &str) -> Self {
+        HttpMediaServer {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MediaServer for HttpMediaServer {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn upload(&self, data: Vec<u8>) -> Result<String, MediaError> {
+        let url = self
+            .blob_url(&format!("{:x}", Sha256::digest(&data)))
+            .await?;
+        let response = reqwest::Client::new()
+            .put(&url)
+            .header("Content-Type", "application/octet-stream")
+            .body(data)
+            .send()
+            .await
+            .map_err(|e| MediaError::Upload(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(MediaError::Upload(format!(
+                "Upload failed with status: {}",
+                response.status()
+            )));
+        }
+        Ok(url)
+    }
+}
+
+/// The client for a configured server
+pub fn server_for(endpoint: &MediaServerEndpoint) -> Box<dyn MediaServer> {
+    match endpoint.protocol {
+        MediaServerProtocol::Blossom => Box::new(BlossomClient::new(&endpoint.url)),
+        MediaServerProtocol::Nip96 => Box::new(Nip96Client::new(&endpoint.url)),
+        MediaServerProtocol::Http => Box::new(HttpMediaServer::new(&endpoint.url)),
+    }
+}
+
+/// The servers of an account in the order they're tried: the primary server, or the default
+/// Blossom server if there's none, then the fallbacks, with the ones that failed recently moved
+/// to the end
+pub fn servers_for(
+    settings: &MediaServerSettings,
+    default_blossom: &BlossomClient,
+) -> Vec<Box<dyn MediaServer>> {
+    let primary: Box<dyn MediaServer> = match &settings.url {
+        Some(url) => server_for(&MediaServerEndpoint {
+            url: url.clone(),
+            protocol: settings.protocol,
+        }),
+        None => Box::new(default_blossom.clone()),
+    };
+    let mut servers = vec![primary];
+    for fallback in settings.fallbacks.iter().map(server_for) {
+        if !servers.iter().any(|server| server.url() == fallback.url()) {
+            servers.push(fallback);
+        }
+    }
+
+    let failed = FAILED.lock().unwrap_or_else(|e| e.into_inner());
+    by_availability(servers, &failed)
+}
+
+/// Moves the servers that failed in the last [`RETRY_AFTER`] to the end, keeping the order
+/// otherwise
+fn by_availability(
+    mut servers: Vec<Box<dyn MediaServer>>,
+    failed: &HashMap<String, Instant>,
+) -> Vec<Box<dyn MediaServer>> {
+    servers.sort_by_key(|server| {
+        failed
+            .get(server.url())
+            .is_some_and(|failed_at| failed_at.elapsed() < RETRY_AFTER)
+    });
+    servers
+}
+
+fn record_result(server: &dyn MediaServer, ok: bool) {
+    let mut failed = FAILED.lock().unwrap_or_else(|e| e.into_inner());
+    if ok {
+        failed.remove(server.url());
+    } else {
+        failed.insert(server.url().to_string(), Instant::now());
+    }
+}
+
+/// Uploads a file to the first of `servers` that accepts it, returning the server's index and
+/// the file's URL
+async fn upload_first(
+    data: &[u8],
+    servers: &[Box<dyn MediaServer>],
+) -> Result<(usize, String), MediaError> {
+    let mut errors = Vec::new();
+    for (index, server) in servers.iter().enumerate() {
+        match server.upload(data.to_vec()).await {
+            Ok(url) => {
+                record_result(server.as_ref(), true);
+                return Ok((index, url));
+            }
+            Err(e) => {
+                record_result(server.as_ref(), false);
+                tracing::warn!(
+                    target: "whitenoise::media::servers::upload",
+                    "Upload to {} failed, trying the next server: {}",
+                    server.url(),
+                    e
+                );
+                errors.push(format!("{}: {}", server.url(), e));
+            }
+        }
+    }
+    Err(MediaError::Upload(errors.join("; ")))
+}
+
+/// Uploads a file to the first server that accepts it and returns its URL
+pub async fn upload(data: Vec<u8>, servers: &[Box<dyn MediaServer>]) -> Result<String, MediaError> {
+    upload_first(&data, servers).await.map(|(_, url)| url)
+}
+
+/// Uploads a file like [`upload`], then mirrors it to the next server that accepts it
+///
+/// # Returns
+/// * `Ok((String, Vec<String>))` - The file's URL, and its mirror's URL unless no other server
+///   accepted it
+pub async fn upload_mirrored(
+    data: Vec<u8>,
+    servers: &[Box<dyn MediaServer>],
+) -> Result<(String, Vec<String>), MediaError> {
+    let (index, url) = upload_first(&data, servers).await?;
+    let mirrors = match upload_first(&data, &servers[index + 1..]).await {
+        Ok((_, mirror)) if mirror != url => vec![mirror],
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            tracing::warn!(
+                target: "whitenoise::media::servers::upload_mirrored",
+                "Couldn't mirror {}: {}",
+                url,
+                e
+            );
+            Vec::new()
+        }
+    };
+    Ok((url, mirrors))
+}
+
+/// Downloads a file, giving up once it's over `max_bytes`
+async fn download(url: &str, max_bytes: u64) -> Result<Vec<u8>, MediaError> {
+    let mut response = reqwest::get(url)
+        .await
+        .map_err(|e| MediaError::Download(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(MediaError::Download(format!(
+            "Download failed with status: {}",
+            response.status()
+        )));
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes)
+    {
+        return Err(MediaError::Download("File is too large".to_string()));
+    }
+
+    // Servers can omit or lie about the length, so the limit is enforced while reading too
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| MediaError::Download(e.to_string()))?
+    {
+        if (data.len() + chunk.len()) as u64 > max_bytes {
+            return Err(MediaError::Download("File is too large".to_string()));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Downloads a file and checks it has the hex encoded SHA256 `sha256`, since a server may
+/// return anything
+async fn download_verified(url: &str, sha256: &str, max_bytes: u64) -> Result<Vec<u8>, MediaError> {
+    let data = download(url, max_bytes).await?;
+    let hash = format!("{:x}", Sha256::digest(&data));
+    if !hash.eq_ignore_ascii_case(sha256) {
+        return Err(MediaError::Integrity(format!(
+            "{} returned a file with hash {}, expected {}",
+            url, hash, sha256
+        )));
+    }
+    Ok(data)
+}
+
+/// Downloads a file from the first of `urls` that works, or by its hex encoded SHA256 from one
+/// of the servers if none does
+///
+/// Every download is checked against `sha256`; a mirror returning something else, or more than
+/// `max_bytes`, is skipped like one that's down.
+pub async fn fetch(
+    urls: &[&str],
+    sha256: &str,
+    max_bytes: u64,
+    servers: &[Box<dyn MediaServer>],
+) -> Result<Vec<u8>, MediaError> {
+    let mut error = MediaError::Download("No URL to download from".to_string());
+    for url in urls {
+        match download_verified(url, sha256, max_bytes).await {
+            Ok(data) => return Ok(data),
+            Err(e) => {
+                tracing::debug!(
+                    target: "whitenoise::media::servers::fetch",
+                    "Couldn't download {}, trying the next URL: {}",
+                    url,
+                    e
+                );
+                error = e;
+            }
+        }
+    }
+    for server in servers {
+        let Ok(blob_url) = server.blob_url(sha256).await else {
+            continue;
+        };
+        if urls.contains(&blob_url.as_str()) {
+            continue;
+        }
+        match download_verified(&blob_url, sha256, max_bytes).await {
+            Ok(data) => {
+                tracing::debug!(
+                    target: "whitenoise::media::servers::fetch",
+                    "Fetched {} from {}",
+                    sha256,
+                    server.url()
+                );
+                return Ok(data);
+            }
+            Err(e) => {
+                tracing::debug!(
+                    target: "whitenoise::media::servers::fetch",
+                    "Couldn't fetch {} from {}: {}",
+                    sha256,
+                    server.url(),
+                    e
+                );
+                error = e;
+            }
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(servers: &[Box<dyn MediaServer>]) -> Vec<&str> {
+        servers.iter().map(|server| server.url()).collect()
+    }
+
+    #[test]
+    fn test_servers_for_tries_primary_then_fallbacks() {
+        let settings = MediaServerSettings {
+            url: Some("https://primary.example".to_string()),
+            protocol: MediaServerProtocol::Nip96,
+            fallbacks: vec![MediaServerEndpoint {
+                url: "https://bucket.example/media/".to_string(),
+                protocol: MediaServerProtocol::Http,
+            }],
+            mirror_attachments: false,
+        };
+        let default_blossom = BlossomClient::new("https://blossom.example");
+        let servers = servers_for(&settings, &default_blossom);
+        // The default server isn't tried when the account picked its own
+        assert_eq!(
+            urls(&servers),
+            vec!["https://primary.example", "https://bucket.example/media"]
+        );
+
+        // Without a primary server, the default one takes its place
+        let settings = MediaServerSettings {
+            fallbacks: vec![MediaServerEndpoint {
+                url: "https://nip96.example".to_string(),
+                protocol: MediaServerProtocol::Nip96,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            urls(&servers_for(&settings, &default_blossom)),
+            vec!["https://blossom.example", "https://nip96.example"]
+        );
+    }
+
+    #[test]
+    fn test_recently_failed_servers_are_tried_last() {
+        let servers: Vec<Box<dyn MediaServer>> = vec![
+            Box::new(HttpMediaServer::new("https://a.example")),
+            Box::new(HttpMediaServer::new("https://b.example")),
+            Box::new(HttpMediaServer::new("https://c.example")),
+        ];
+        let mut failed = HashMap::new();
+        failed.insert("https://a.example".to_string(), Instant::now());
+        assert_eq!(
+            urls(&by_availability(servers, &failed)),
+            vec![
+                "https://b.example",
+                "https://c.example",
+                "https://a.example"
+            ]
+        );
+    }
+}
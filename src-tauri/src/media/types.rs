@@ -77,3 +77,41 @@ pub struct UploadedMedia {
     /// The IMETA tag containing metadata about the file for Nostr events
     pub imeta_tag: Tag,
 }
+
+/// The protocol spoken by a media server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaServerProtocol {
+    #[default]
+    Blossom,
+    Nip96,
+    /// A plain HTTP endpoint, e.g. a self-hosted S3 compatible bucket: files are `PUT` to and
+    /// served from `<url>/<sha256>`
+    Http,
+}
+
+/// A media server and the protocol it speaks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaServerEndpoint {
+    pub url: String,
+    #[serde(default)]
+    pub protocol: MediaServerProtocol,
+}
+
+/// The media servers an account uploads attachments to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaServerSettings {
+    /// Base URL of the primary server. When unset the app's default Blossom server is used.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub protocol: MediaServerProtocol,
+    /// Servers tried in order when the primary one is down. The default Blossom server is never
+    /// used as a fallback.
+    #[serde(default)]
+    pub fallbacks: Vec<MediaServerEndpoint>,
+    /// When enabled, attachments are also uploaded to the next server in line, so they can
+    /// still be downloaded when one of the servers is gone
+    #[serde(default)]
+    pub mirror_attachments: bool,
+}
@@ -0,0 +1,132 @@
+//! Voice messages.
+//!
+//! Voice messages are Ogg Opus recordings sent as regular [`attachments`](super::attachments),
+//! with their duration and a coarse waveform added to the `imeta` tag so clients can render the
+//! player before the audio is downloaded.
+
+use crate::media::attachments::AttachmentMeta;
+use crate::media::errors::MediaError;
+use serde::{Deserialize, Serialize};
+
+/// MIME type voice messages are sent with
+pub const VOICE_MIME_TYPE: &str = "audio/ogg";
+
+/// Longest voice message that can be sent
+pub const MAX_VOICE_DURATION_MS: u64 = 15 * 60 * 1000;
+
+/// Number of amplitude samples kept in the waveform
+pub const WAVEFORM_SAMPLES: usize = 64;
+
+/// Largest value of a waveform sample
+const MAX_WAVEFORM_AMPLITUDE: u8 = 100;
+
+/// A downloaded voice message, ready to be played from the local cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceMessage {
+    /// Path of the decrypted recording
+    pub path: String,
+    pub mime_type: String,
+    pub duration_ms: u64,
+    pub waveform: Vec<u8>,
+}
+
+/// Checks that a recording is an Ogg Opus file of an acceptable length.
+pub fn validate_voice_recording(data: &[u8], duration_ms: u64) -> Result<(), MediaError> {
+    if duration_ms == 0 || duration_ms > MAX_VOICE_DURATION_MS {
+        return Err(MediaError::Metadata(format!(
+            "Voice messages must be between 1ms and {} minutes long",
+            MAX_VOICE_DURATION_MS / 60_000
+        )));
+    }
+
+    // Every Ogg stream starts with a page header, and the first page of an Opus stream
+    // carries the `OpusHead` identification header.
+    let is_ogg = data.starts_with(b"OggS");
+    let is_opus = data
+        .get(..data.len().min(512))
+        .is_some_and(|head| head.windows(8).any(|window| window == b"OpusHead"));
+    if !is_ogg || !is_opus {
+        return Err(MediaError::Metadata(
+            "Voice messages must be Ogg Opus audio".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reduces the amplitudes reported by the recorder to [`WAVEFORM_SAMPLES`] samples, keeping
+/// the peak of each bucket, and clamps them to 0-100.
+pub fn normalize_waveform(amplitudes: &[u8]) -> Vec<u8> {
+    if amplitudes.len() <= WAVEFORM_SAMPLES {
+        return amplitudes
+            .iter()
+            .map(|a| (*a).min(MAX_WAVEFORM_AMPLITUDE))
+            .collect();
+    }
+
+    (0..WAVEFORM_SAMPLES)
+        .map(|bucket| {
+            let start = bucket * amplitudes.len() / WAVEFORM_SAMPLES;
+            let end = (bucket + 1) * amplitudes.len() / WAVEFORM_SAMPLES;
+            amplitudes[start..end]
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(0)
+                .min(MAX_WAVEFORM_AMPLITUDE)
+        })
+        .collect()
+}
+
+/// Returns true if the attachment is a voice message
+pub fn is_voice_message(meta: &AttachmentMeta) -> bool {
+    meta.mime_type == VOICE_MIME_TYPE && meta.duration_ms.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opus_header() -> Vec<u8> {
+        let mut data = b"OggS".to_vec();
+        data.extend_from_slice(&[0; 24]);
+        data.extend_from_slice(b"OpusHead");
+        data.extend_from_slice(&[1, 2, 3]);
+        data
+    }
+
+    #[test]
+    fn test_validate_voice_recording() {
+        assert!(validate_voice_recording(&opus_header(), 1_000).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_other_formats() {
+        assert!(validate_voice_recording(b"RIFF....WAVEfmt ", 1_000).is_err());
+
+        let mut vorbis = b"OggS".to_vec();
+        vorbis.extend_from_slice(b"\x01vorbis");
+        assert!(validate_voice_recording(&vorbis, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_duration() {
+        assert!(validate_voice_recording(&opus_header(), 0).is_err());
+        assert!(validate_voice_recording(&opus_header(), MAX_VOICE_DURATION_MS + 1).is_err());
+    }
+
+    #[test]
+    fn test_normalize_waveform_downsamples_to_peaks() {
+        let amplitudes: Vec<u8> = (0..WAVEFORM_SAMPLES * 2)
+            .map(|i| if i % 2 == 1 { 80 } else { 10 })
+            .collect();
+        let waveform = normalize_waveform(&amplitudes);
+        assert_eq!(waveform.len(), WAVEFORM_SAMPLES);
+        assert!(waveform.iter().all(|sample| *sample == 80));
+    }
+
+    #[test]
+    fn test_normalize_waveform_clamps_short_input() {
+        assert_eq!(normalize_waveform(&[5, 150, 255]), vec![5, 100, 100]);
+    }
+}
@@ -0,0 +1,571 @@
+//! The shared media of each group, for the "shared media" tab.
+//!
+//! Every attachment and link in a chat message is indexed in `group_media` in the same
+//! transaction that stores the message, so listing them never needs a scan of the transcript.
+//! Links are re-indexed when a message is edited, and deleted or expired messages drop out of
+//! the listing with their transcript entry. Groups with messages from before the index existed
+//! are indexed once, the first time their media is listed, and messages restored from an archive
+//! are indexed as they're restored.
+
+use crate::media::attachments::AttachmentMeta;
+use crate::messages::MessageRow;
+use crate::nostr_manager::parser::SerializableToken;
+use crate::protocol::CHAT_MESSAGE_KIND;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How many items a page holds
+pub const PAGE_SIZE: u32 = 50;
+
+/// What kind of media an item is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaCategory {
+    Image,
+    Video,
+    /// Any other attachment, including audio and voice messages
+    File,
+    Link,
+}
+
+impl MediaCategory {
+    fn for_mime_type(mime_type: &str) -> Self {
+        if mime_type.starts_with("image/") {
+            MediaCategory::Image
+        } else if mime_type.starts_with("video/") {
+            MediaCategory::Video
+        } else {
+            MediaCategory::File
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            MediaCategory::Image => "image",
+            MediaCategory::Video => "video",
+            MediaCategory::File => "file",
+            MediaCategory::Link => "link",
+        }
+    }
+
+    fn from_db(category: &str) -> Self {
+        match category {
+            "image" => MediaCategory::Image,
+            "video" => MediaCategory::Video,
+            "link" => MediaCategory::Link,
+            _ => MediaCategory::File,
+        }
+    }
+}
+
+/// An attachment or link shared in a group
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroupMediaItem {
+    /// The message that shared it; attachments are downloaded with `download_attachment`
+    pub event_id: EventId,
+    pub author: PublicKey,
+    pub created_at: Timestamp,
+    pub category: MediaCategory,
+    pub url: String,
+    /// Only known for attachments
+    pub mime_type: Option<String>,
+    pub filename: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Where a page of media ends: its last item's message and position within the message
+///
+/// Like [`crate::groups::MessageCursor`], items are ordered by their message's creation time and
+/// event ID, so items shared while the list is paged through don't shift the next pages.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MediaCursor {
+    pub created_at: Timestamp,
+    pub event_id: EventId,
+    pub position: u32,
+}
+
+/// A page of a group's media, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroupMediaPage {
+    pub items: Vec<GroupMediaItem>,
+    /// Where the next page starts, `None` on the last page
+    pub next: Option<MediaCursor>,
+}
+
+/// An entry of a message in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MediaEntry {
+    category: MediaCategory,
+    url: String,
+    mime_type: Option<String>,
+    filename: Option<String>,
+    size: Option<u64>,
+}
+
+fn attachment_entries(tags: &Tags) -> Vec<MediaEntry> {
+    AttachmentMeta::from_tags(tags)
+        .into_iter()
+        .map(|meta| MediaEntry {
+            category: MediaCategory::for_mime_type(&meta.mime_type),
+            url: meta.url,
+            mime_type: Some(meta.mime_type),
+            filename: Some(meta.filename),
+            size: Some(meta.size),
+        })
+        .collect()
+}
+
+/// The links in a message's content, leaving out duplicates and the URLs of its attachments
+fn link_entries(tokens: &[SerializableToken], attachments: &[MediaEntry]) -> Vec<MediaEntry> {
+    let mut links: Vec<MediaEntry> = Vec::new();
+    for token in tokens {
+        let SerializableToken::Url(url) = token else {
+            continue;
+        };
+        let known = attachments
+            .iter()
+            .chain(links.iter())
+            .any(|entry| entry.url == *url);
+        if !known {
+            links.push(MediaEntry {
+                category: MediaCategory::Link,
+                url: url.clone(),
+                mime_type: None,
+                filename: None,
+                size: None,
+            });
+        }
+    }
+    links
+}
+
+/// Everything a message shares, attachments first
+fn entries(message: &UnsignedEvent, tokens: &[SerializableToken]) -> Vec<MediaEntry> {
+    if message.kind.as_u16() != CHAT_MESSAGE_KIND {
+        return Vec::new();
+    }
+    let mut entries = attachment_entries(&message.tags);
+    let links = link_entries(tokens, &entries);
+    entries.extend(links);
+    entries
+}
+
+async fn insert_entries(
+    conn: &mut sqlx::SqliteConnection,
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    message: &UnsignedEvent,
+    entries: Vec<(usize, MediaEntry)>,
+) -> sqlx::Result<()> {
+    let Some(event_id) = message.id else {
+        return Ok(());
+    };
+    for (position, entry) in entries {
+        sqlx::query(
+            "INSERT OR REPLACE INTO group_media
+             (account_pubkey, mls_group_id, event_id, position, category, url, mime_type,
+              filename, size, author_pubkey, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(mls_group_id)
+        .bind(event_id.to_hex())
+        .bind(position as i64)
+        .bind(entry.category.as_str())
+        .bind(&entry.url)
+        .bind(&entry.mime_type)
+        .bind(&entry.filename)
+        .bind(entry.size.map(|size| size as i64))
+        .bind(message.pubkey.to_hex())
+        .bind(message.created_at.as_u64() as i64)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Indexes the attachments and links of a newly stored message
+pub async fn index_message(
+    conn: &mut sqlx::SqliteConnection,
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    message: &UnsignedEvent,
+    tokens: &[SerializableToken],
+) -> sqlx::Result<()> {
+    let entries = entries(message, tokens).into_iter().enumerate().collect();
+    insert_entries(conn, account_pubkey, mls_group_id, message, entries).await
+}
+
+/// Replaces the indexed links of an edited message with the ones in its new content
+pub async fn reindex_links(
+    conn: &mut sqlx::SqliteConnection,
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    message: &UnsignedEvent,
+    tokens: &[SerializableToken],
+) -> sqlx::Result<()> {
+    let Some(event_id) = message.id else {
+        return Ok(());
+    };
+    if message.kind.as_u16() != CHAT_MESSAGE_KIND {
+        return Ok(());
+    }
+    sqlx::query(
+        "DELETE FROM group_media WHERE account_pubkey = ? AND event_id = ? AND category = 'link'",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(event_id.to_hex())
+    .execute(&mut *conn)
+    .await?;
+
+    let attachments = attachment_entries(&message.tags);
+    let first_link = attachments.len();
+    let links = link_entries(tokens, &attachments)
+        .into_iter()
+        .enumerate()
+        .map(|(i, link)| (first_link + i, link))
+        .collect();
+    insert_entries(conn, account_pubkey, mls_group_id, message, links).await
+}
+
+/// Indexes a message that was stored without going through `add_message`, at its current
+/// content. Deleted messages and other kinds than chat messages are skipped.
+pub async fn index_stored_message(
+    conn: &mut sqlx::SqliteConnection,
+    account_pubkey: &PublicKey,
+    row: &MessageRow,
+) -> sqlx::Result<()> {
+    if row.event_kind != CHAT_MESSAGE_KIND || row.deleted_at.is_some() {
+        return Ok(());
+    }
+    let Ok(mut event) = serde_json::from_str::<UnsignedEvent>(&row.event) else {
+        return Ok(());
+    };
+    // Index the current content of edited messages
+    event.content = row.content.clone();
+    let tokens: Vec<SerializableToken> =
+        serde_json::from_value(row.tokens.clone()).unwrap_or_default();
+    index_message(conn, account_pubkey, &row.mls_group_id, &event, &tokens).await
+}
+
+/// Indexes the messages a group received before the index existed, once
+async fn backfill(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<()> {
+    let mut txn = wn.database.pool.begin().await?;
+    let inserted = sqlx::query(
+        "INSERT OR IGNORE INTO group_media_backfills (account_pubkey, mls_group_id) VALUES (?, ?)",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .execute(&mut *txn)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    let rows = sqlx::query_as::<_, MessageRow>(
+        "SELECT * FROM messages
+         WHERE account_pubkey = ? AND mls_group_id = ? AND event_kind = ? AND deleted_at IS NULL",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(i64::from(CHAT_MESSAGE_KIND))
+    .fetch_all(&mut *txn)
+    .await?;
+    for row in rows {
+        index_stored_message(&mut *txn, account_pubkey, &row).await?;
+    }
+    txn.commit().await
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GroupMediaRow {
+    event_id: String,
+    position: i64,
+    category: String,
+    url: String,
+    mime_type: Option<String>,
+    filename: Option<String>,
+    size: Option<i64>,
+    author_pubkey: String,
+    created_at: i64,
+}
+
+impl GroupMediaRow {
+    fn cursor(&self) -> Option<MediaCursor> {
+        Some(MediaCursor {
+            created_at: Timestamp::from(self.created_at as u64),
+            event_id: EventId::from_hex(&self.event_id).ok()?,
+            position: self.position as u32,
+        })
+    }
+
+    /// The item, unless the row doesn't hold valid IDs
+    fn into_item(self) -> Option<GroupMediaItem> {
+        Some(GroupMediaItem {
+            event_id: EventId::from_hex(&self.event_id).ok()?,
+            author: PublicKey::from_hex(&self.author_pubkey).ok()?,
+            created_at: Timestamp::from(self.created_at as u64),
+            category: MediaCategory::from_db(&self.category),
+            url: self.url,
+            mime_type: self.mime_type,
+            filename: self.filename,
+            size: self.size.map(|size| size as u64),
+        })
+    }
+}
+
+/// The rows of a page of a group's media, newest first, plus one more if there's another page
+async fn page_rows(
+    pool: &sqlx::SqlitePool,
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    category: Option<MediaCategory>,
+    after: Option<&MediaCursor>,
+) -> sqlx::Result<Vec<GroupMediaRow>> {
+    let category = category.map(MediaCategory::as_str);
+    let (after_at, after_id, after_position) = match after {
+        Some(cursor) => (
+            cursor.created_at.as_u64() as i64,
+            cursor.event_id.to_hex(),
+            i64::from(cursor.position),
+        ),
+        None => (i64::MAX, String::new(), 0),
+    };
+    sqlx::query_as::<_, GroupMediaRow>(
+        "SELECT gm.event_id, gm.position, gm.category, gm.url, gm.mime_type, gm.filename,
+                gm.size, gm.author_pubkey, gm.created_at
+         FROM group_media gm
+         JOIN messages m ON m.event_id = gm.event_id AND m.account_pubkey = gm.account_pubkey
+         WHERE gm.account_pubkey = ? AND gm.mls_group_id = ? AND (? IS NULL OR gm.category = ?)
+           AND m.deleted_at IS NULL
+           AND (gm.created_at < ?
+                OR (gm.created_at = ? AND gm.event_id < ?)
+                OR (gm.created_at = ? AND gm.event_id = ? AND gm.position > ?))
+         ORDER BY gm.created_at DESC, gm.event_id DESC, gm.position
+         LIMIT ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(category)
+    .bind(category)
+    .bind(after_at)
+    .bind(after_at)
+    .bind(&after_id)
+    .bind(after_at)
+    .bind(&after_id)
+    .bind(after_position)
+    .bind(i64::from(PAGE_SIZE) + 1)
+    .fetch_all(pool)
+    .await
+}
+
+/// Lists a page of a group's media for an account, newest first, optionally of one category
+///
+/// # Arguments
+/// * `after` - Where the previous page ended. `None` returns the newest items.
+pub async fn page(
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    category: Option<MediaCategory>,
+    after: Option<MediaCursor>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<GroupMediaPage> {
+    backfill(account_pubkey, mls_group_id, wn.clone()).await?;
+
+    let mut rows = page_rows(
+        &wn.database.pool,
+        account_pubkey,
+        mls_group_id,
+        category,
+        after.as_ref(),
+    )
+    .await?;
+    // The extra row only tells whether there's another page
+    let has_more = rows.len() > PAGE_SIZE as usize;
+    rows.truncate(PAGE_SIZE as usize);
+    let next = if has_more {
+        rows.last().and_then(GroupMediaRow::cursor)
+    } else {
+        None
+    };
+
+    let items = rows
+        .into_iter()
+        .filter_map(|row| {
+            let event_id = row.event_id.clone();
+            let item = row.into_item();
+            if item.is_none() {
+                tracing::warn!(
+                    target: "whitenoise::media_library::page",
+                    "Skipping malformed media item of message {}",
+                    event_id
+                );
+            }
+            item
+        })
+        .collect();
+    Ok(GroupMediaPage { items, next })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::attachments::encrypt_attachment;
+    use crate::nostr_manager::parser::parse;
+
+    fn message(kind: u16, content: &str, tags: Vec<Tag>) -> UnsignedEvent {
+        EventBuilder::new(Kind::from(kind), content)
+            .tags(tags)
+            .build(Keys::generate().public_key())
+    }
+
+    #[test]
+    fn test_entries_categorize_attachments_and_links() {
+        let mut photo = encrypt_attachment(b"photo", "cat.jpg", "image/jpeg").unwrap();
+        photo.meta.url = "https://media.example/abc".to_string();
+        let mut notes = encrypt_attachment(b"notes", "notes.pdf", "application/pdf").unwrap();
+        notes.meta.url = "https://media.example/def".to_string();
+        let content = "Look https://example.com/page and https://example.com/page";
+        let message = message(
+            CHAT_MESSAGE_KIND,
+            content,
+            vec![photo.meta.to_tag(), notes.meta.to_tag()],
+        );
+
+        let entries = entries(&message, &parse(content));
+        let categories: Vec<MediaCategory> = entries.iter().map(|e| e.category).collect();
+        assert_eq!(
+            categories,
+            vec![
+                MediaCategory::Image,
+                MediaCategory::File,
+                MediaCategory::Link
+            ]
+        );
+        assert_eq!(entries[2].url, "https://example.com/page");
+    }
+
+    async fn setup_test_pool() -> (sqlx::SqlitePool, tempfile::TempDir) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        std::fs::File::create(&db_path).unwrap();
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&format!("sqlite:{}", db_path.display()))
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE messages (
+                event_id TEXT NOT NULL,
+                account_pubkey TEXT NOT NULL,
+                deleted_at INTEGER
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE group_media (
+                account_pubkey TEXT NOT NULL,
+                mls_group_id BLOB NOT NULL,
+                event_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                category TEXT NOT NULL,
+                url TEXT NOT NULL,
+                mime_type TEXT,
+                filename TEXT,
+                size INTEGER,
+                author_pubkey TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        (pool, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_pages_follow_the_cursor() {
+        let (pool, _temp_dir) = setup_test_pool().await;
+        let account = Keys::generate().public_key();
+        // Two messages in the same second, sharing more links than fit on a page
+        for id in [1u8, 2] {
+            let event_id = EventId::from_slice(&[id; 32]).unwrap().to_hex();
+            sqlx::query("INSERT INTO messages (event_id, account_pubkey) VALUES (?, ?)")
+                .bind(&event_id)
+                .bind(account.to_hex())
+                .execute(&pool)
+                .await
+                .unwrap();
+            for position in 0..PAGE_SIZE {
+                sqlx::query(
+                    "INSERT INTO group_media (account_pubkey, mls_group_id, event_id, position,
+                     category, url, author_pubkey, created_at)
+                     VALUES (?, ?, ?, ?, 'link', ?, ?, 10)",
+                )
+                .bind(account.to_hex())
+                .bind(&[1u8][..])
+                .bind(&event_id)
+                .bind(i64::from(position))
+                .bind(format!("https://example.com/{}/{}", id, position))
+                .bind(account.to_hex())
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+        }
+
+        let mut seen = Vec::new();
+        let mut after = None;
+        loop {
+            let rows = page_rows(&pool, &account, &[1], None, after.as_ref())
+                .await
+                .unwrap();
+            let has_more = rows.len() > PAGE_SIZE as usize;
+            let page: Vec<GroupMediaRow> = rows.into_iter().take(PAGE_SIZE as usize).collect();
+            after = page.last().and_then(GroupMediaRow::cursor);
+            seen.extend(page.into_iter().map(|row| row.url));
+            if !has_more {
+                break;
+            }
+        }
+
+        let expected: Vec<String> = [2u8, 1]
+            .iter()
+            .flat_map(|id| {
+                (0..PAGE_SIZE)
+                    .map(move |position| format!("https://example.com/{}/{}", id, position))
+            })
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_malformed_rows_have_no_item() {
+        let row = GroupMediaRow {
+            event_id: "not an event id".to_string(),
+            position: 0,
+            category: "link".to_string(),
+            url: "https://example.com".to_string(),
+            mime_type: None,
+            filename: None,
+            size: None,
+            author_pubkey: Keys::generate().public_key().to_hex(),
+            created_at: 10,
+        };
+        assert!(row.into_item().is_none());
+    }
+
+    #[test]
+    fn test_only_chat_messages_are_indexed() {
+        let content = "https://example.com";
+        let reaction = message(7, content, vec![]);
+        assert!(entries(&reaction, &parse(content)).is_empty());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::accounts::Account;
+use crate::media::attachments;
 use crate::nostr_manager::parser::SerializableToken;
+use crate::payments::{self, PaymentRequest};
+use crate::reactions::REACTION_KIND;
 use crate::Whitenoise;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -32,6 +35,14 @@ pub struct MessageRow {
     pub event: String, // JSON string for UnsignedEvent
     pub outer_event_id: String,
     pub tokens: JsonValue, // Vec<SerializableToken>
+    pub deleted_at: Option<u64>,
+    pub edited_at: Option<u64>,
+    pub author_migrated_to: Option<String>,
+    pub origin_group_id: Option<Vec<u8>>,
+    pub expires_at: Option<u64>,
+    /// Only selected by transcript queries that join the outbox
+    #[sqlx(default)]
+    pub pending: bool,
 }
 
 /// This is the processed rumor message that represents a private chat message
@@ -50,6 +61,328 @@ pub struct Message {
     pub event: UnsignedEvent,
     pub outer_event_id: EventId,
     pub tokens: Vec<SerializableToken>,
+    /// Semantic hints for assistive frontends, computed by the backend
+    #[serde(default)]
+    pub semantics: MessageSemantics,
+    /// The message this one directly replies to (NIP-10 `reply` marker, or `root` for direct replies)
+    #[serde(default)]
+    pub reply_to: Option<EventId>,
+    /// The first message of the thread this message belongs to (NIP-10 `root` marker)
+    #[serde(default)]
+    pub thread_root: Option<EventId>,
+    /// Set when the author retracted the message. The content of a deleted message is cleared.
+    #[serde(default)]
+    pub deleted_at: Option<Timestamp>,
+    /// Set when the author edited the message. `content` is always the latest version.
+    #[serde(default)]
+    pub edited_at: Option<Timestamp>,
+    /// The author's new key, if they've since migrated away from the key that wrote this message
+    #[serde(default)]
+    pub author_migrated_to: Option<PublicKey>,
+    /// The group the message was received in, if it was moved here by a group merge
+    #[serde(default)]
+    pub origin_group_id: Option<Vec<u8>>,
+    /// Set for ephemeral messages; the message is deleted locally once this time passes
+    #[serde(default)]
+    pub expires_at: Option<Timestamp>,
+    /// Sent by the active account but not accepted by any relay yet; it's queued in the outbox
+    #[serde(default)]
+    pub pending: bool,
+    /// The content masked by the account's inbound content filter, if it changed anything.
+    /// Frontends should display this instead of `content` when it's set.
+    #[serde(default)]
+    pub display_content: Option<String>,
+    /// The invoice or payment request shared in the message, parsed for payment cards
+    #[serde(default)]
+    pub payment_request: Option<PaymentRequest>,
+}
+
+/// Payload of the `mls_message_deleted` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MlsMessageDeletedEvent {
+    pub group_id: Vec<u8>,
+    /// The message that was tombstoned
+    pub message_id: EventId,
+    /// The kind 5 message that deleted it
+    pub deletion_event_id: EventId,
+    pub deleted_at: Timestamp,
+}
+
+/// Payload of the `mls_message_edited` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MlsMessageEditedEvent {
+    pub group_id: Vec<u8>,
+    /// The edited message, with its content replaced by the latest version
+    pub message: Message,
+}
+
+/// Payload of the `mls_message_expired` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MlsMessageExpiredEvent {
+    pub group_id: Vec<u8>,
+    /// The message that was removed from the transcript
+    pub message_id: EventId,
+}
+
+/// One version of an edited message
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MessageEdit {
+    /// The original message for the first version, the edit rumor for later ones
+    pub event_id: EventId,
+    pub content: String,
+    pub created_at: Timestamp,
+}
+
+/// The inner event kind used for deletions (NIP-09)
+pub const DELETION_KIND: u16 = 5;
+
+/// The inner event kind used for edits. The edit references the original message with an `e`
+/// tag and carries the full replacement content.
+pub const EDIT_KIND: u16 = 1010;
+
+/// The inner event kind used by admins to distribute group settings that aren't part of the
+/// MLS group data extension. The content is a JSON encoded `GroupSettingsUpdate`.
+pub const GROUP_SETTINGS_KIND: u16 = 1011;
+
+/// The inner event kind sent to a group that was merged into another one, asking members to
+/// move to the group referenced by its `moved_to` tag (the new group's Nostr group ID)
+pub const GROUP_MOVED_KIND: u16 = 1012;
+
+/// The inner event kind of admin notices: announcements that are rendered apart from the chat
+/// and always notify, even in snoozed groups. Notices from members who aren't admins are dropped.
+pub const GROUP_NOTICE_KIND: u16 = 1014;
+
+/// The inner event kind of edits to a group's shared notes. The content is a JSON encoded
+/// `group_notes::NoteUpdate`.
+pub const GROUP_NOTE_KIND: u16 = 1015;
+
+/// The inner event kind of shared tasks. The content is a JSON encoded `group_tasks::TaskAction`
+/// that creates a task or changes whether it's done.
+pub const GROUP_TASK_KIND: u16 = 1016;
+
+/// The inner event kind of namespaced state that frontend features share within a group. The
+/// content is a JSON encoded `group_custom_data::CustomDataUpdate`.
+pub const GROUP_CUSTOM_DATA_KIND: u16 = 1017;
+
+/// Inner event kinds that are stored in the transcript but aren't chat messages
+pub const SYSTEM_MESSAGE_KINDS: [u16; 9] = [
+    DELETION_KIND,
+    REACTION_KIND,
+    EDIT_KIND,
+    GROUP_SETTINGS_KIND,
+    GROUP_MOVED_KIND,
+    GROUP_NOTICE_KIND,
+    GROUP_NOTE_KIND,
+    GROUP_TASK_KIND,
+    GROUP_CUSTOM_DATA_KIND,
+];
+
+/// Extracts the thread root and the direct parent from NIP-10 marked `e` tags.
+///
+/// Only marked tags are considered so that reactions and deletions, which reference their
+/// target with plain `e` tags, aren't treated as replies. A message with only a `root` marker
+/// is a direct reply to the root.
+pub fn thread_refs(tags: &Tags) -> (Option<EventId>, Option<EventId>) {
+    let mut root = None;
+    let mut reply = None;
+    for tag in tags.iter() {
+        let values = tag.as_slice();
+        if values.first().map(String::as_str) != Some("e") {
+            continue;
+        }
+        let Some(event_id) = values.get(1).and_then(|id| EventId::from_hex(id).ok()) else {
+            continue;
+        };
+        match values.get(3).map(String::as_str) {
+            Some("root") => root = Some(event_id),
+            Some("reply") => reply = Some(event_id),
+            _ => {}
+        }
+    }
+    (root, reply.or(root))
+}
+
+/// Builds NIP-10 marked `e` tags for a reply to `parent`, which itself may be part of a thread
+pub fn reply_tags(parent: &Message) -> Vec<Tag> {
+    let root = parent.thread_root.unwrap_or(parent.event_id);
+    let mut tags = vec![marked_event_tag(&root, "root")];
+    if root != parent.event_id {
+        tags.push(marked_event_tag(&parent.event_id, "reply"));
+    }
+    tags.push(Tag::public_key(parent.author_pubkey));
+    tags
+}
+
+fn marked_event_tag(event_id: &EventId, marker: &str) -> Tag {
+    Tag::custom(
+        TagKind::e(),
+        vec![event_id.to_hex(), String::new(), marker.to_string()],
+    )
+}
+
+/// Returns the NIP-40 expiration of an event, if it has one
+pub fn expiration(tags: &Tags) -> Option<Timestamp> {
+    tags.iter().find_map(|tag| match tag.as_slice() {
+        [name, value, ..] if name == "expiration" => value.parse::<u64>().ok().map(Timestamp::from),
+        _ => None,
+    })
+}
+
+/// Returns true if the event has an expiration at or before `now`
+pub fn is_expired(tags: &Tags, now: Timestamp) -> bool {
+    expiration(tags).is_some_and(|expires_at| expires_at <= now)
+}
+
+/// Whether an update replaces another under last-writer-wins
+///
+/// Updates are identified by their creation time and event ID. The later one wins and ties are
+/// broken by event ID, so every member settles on the same winner whatever order the updates
+/// arrived in.
+pub fn lww_wins(update: (Timestamp, EventId), current: (Timestamp, EventId)) -> bool {
+    update > current
+}
+
+/// The kind of non-chat message an inner event represents
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SystemMessageKind {
+    /// A kind 5 deletion request
+    Deletion,
+    /// A kind 7 reaction
+    Reaction,
+    /// An edit of an earlier message
+    Edit,
+    /// A group settings update from an admin
+    GroupSettings,
+    /// The group was merged into another one
+    GroupMoved,
+    /// An admin notice, rendered apart from the chat
+    GroupNotice,
+    /// An edit of one of the group's shared notes
+    GroupNote,
+    /// A shared task was created or marked done or not done
+    GroupTask,
+    /// Shared state of a frontend feature changed
+    GroupCustomData,
+}
+
+impl SystemMessageKind {
+    pub fn from_kind(kind: u16) -> Option<Self> {
+        match kind {
+            DELETION_KIND => Some(Self::Deletion),
+            REACTION_KIND => Some(Self::Reaction),
+            EDIT_KIND => Some(Self::Edit),
+            GROUP_SETTINGS_KIND => Some(Self::GroupSettings),
+            GROUP_MOVED_KIND => Some(Self::GroupMoved),
+            GROUP_NOTICE_KIND => Some(Self::GroupNotice),
+            GROUP_NOTE_KIND => Some(Self::GroupNote),
+            GROUP_TASK_KIND => Some(Self::GroupTask),
+            GROUP_CUSTOM_DATA_KIND => Some(Self::GroupCustomData),
+            _ => None,
+        }
+    }
+}
+
+/// Semantic metadata about a message so that screen readers and other assistive
+/// frontends can announce it meaningfully without re-parsing the content.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct MessageSemantics {
+    /// The content consists only of emoji (and whitespace)
+    pub is_emoji_only: bool,
+    /// The message carries at least one media attachment (`imeta` tag)
+    pub has_attachment: bool,
+    /// Set when the message isn't a regular chat message
+    pub system_message: Option<SystemMessageKind>,
+    /// The message mentions the account that owns it
+    pub mentions_me: bool,
+    /// The message is an admin notice and should be rendered distinctly
+    #[serde(default)]
+    pub is_notice: bool,
+}
+
+impl MessageSemantics {
+    /// Computes the semantics of a message from the point of view of `account_pubkey`
+    pub fn compute(
+        kind: u16,
+        content: &str,
+        tags: &Tags,
+        tokens: &[SerializableToken],
+        account_pubkey: &PublicKey,
+    ) -> Self {
+        let has_attachment = tags
+            .iter()
+            .any(|tag| tag.as_slice().first().map(String::as_str) == Some("imeta"));
+
+        let mentioned_in_tags = tags.public_keys().any(|pubkey| pubkey == account_pubkey);
+        let mentioned_in_content = tokens.iter().any(|token| match token {
+            SerializableToken::Nostr(uri) => PublicKey::parse(uri)
+                .map(|pubkey| &pubkey == account_pubkey)
+                .unwrap_or(false),
+            _ => false,
+        });
+
+        Self {
+            is_emoji_only: is_emoji_only(content),
+            has_attachment,
+            system_message: SystemMessageKind::from_kind(kind),
+            mentions_me: mentioned_in_tags || mentioned_in_content,
+            is_notice: kind == GROUP_NOTICE_KIND,
+        }
+    }
+}
+
+/// Returns true if the content is non-empty and made up solely of emoji and whitespace
+pub fn is_emoji_only(content: &str) -> bool {
+    let trimmed = content.trim();
+    !trimmed.is_empty()
+        && trimmed
+            .chars()
+            .all(|c| c.is_whitespace() || is_emoji_char(c))
+        && trimmed.chars().any(is_emoji_pictograph)
+}
+
+/// Characters that render an emoji on their own
+fn is_emoji_pictograph(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // Emoticons, pictographs, transport, flags, supplemental symbols
+            | 0x2600..=0x27BF // Miscellaneous symbols and dingbats
+            | 0x2300..=0x23FF // Miscellaneous technical (watch, hourglass, ...)
+            | 0x2B00..=0x2BFF // Arrows and stars
+            | 0x2190..=0x21FF // Arrows
+            | 0x3030 | 0x303D | 0x3297 | 0x3299 | 0x00A9 | 0x00AE | 0x2122
+    )
+}
+
+/// Characters allowed inside an emoji sequence
+fn is_emoji_char(c: char) -> bool {
+    is_emoji_pictograph(c)
+        || matches!(
+            c as u32,
+            0x200D // Zero width joiner
+                | 0xFE0E..=0xFE0F // Variation selectors
+                | 0x20E3 // Combining enclosing keycap
+                | 0xE0020..=0xE007F // Tag sequences (subdivision flags)
+        )
+}
+
+/// A single full-text search hit
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageSearchResult {
+    /// Hex encoded MLS group ID of the group the message belongs to
+    pub group_id: String,
+    /// The inner event ID of the matching message
+    pub message_id: EventId,
+    /// A short excerpt of the content around the match, with matches wrapped in `<mark>` tags
+    pub snippet: String,
+    pub created_at: Timestamp,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct MessageSearchRow {
+    event_id: String,
+    mls_group_id: Vec<u8>,
+    created_at: u64,
+    snippet: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -219,26 +552,496 @@ impl Message {
     }
 }
 
+impl Message {
+    /// Searches the account's messages using the FTS5 index
+    ///
+    /// # Arguments
+    /// * `query` - Free text typed by the user. Each word is matched as a prefix.
+    /// * `mls_group_id` - Restrict the search to a single group
+    /// * `limit` - Maximum number of results
+    /// * `wn` - The Whitenoise application state
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MessageSearchResult>)` - Matches ordered by relevance. Empty if the query has no searchable terms.
+    pub async fn search(
+        query: &str,
+        mls_group_id: Option<Vec<u8>>,
+        limit: usize,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<MessageSearchResult>> {
+        let Some(fts_query) = build_fts_query(query) else {
+            return Ok(vec![]);
+        };
+        let active_account = Account::get_active(wn.clone()).await?;
+
+        let rows = sqlx::query_as::<_, MessageSearchRow>(
+            "SELECT m.event_id, m.mls_group_id, m.created_at,
+                    snippet(messages_fts, 0, '<mark>', '</mark>', '…', 12) AS snippet
+             FROM messages_fts
+             JOIN messages m ON m.id = messages_fts.rowid
+             WHERE messages_fts MATCH ?
+               AND m.account_pubkey = ?
+               AND m.deleted_at IS NULL
+               AND (? IS NULL OR m.mls_group_id = ?)
+             ORDER BY rank
+             LIMIT ?",
+        )
+        .bind(fts_query)
+        .bind(active_account.pubkey.to_hex())
+        .bind(mls_group_id.as_deref())
+        .bind(mls_group_id.as_deref())
+        .bind(limit as i64)
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(MessageSearchResult {
+                    group_id: hex::encode(&row.mls_group_id),
+                    message_id: EventId::parse(&row.event_id).ok()?,
+                    snippet: row.snippet,
+                    created_at: Timestamp::from(row.created_at),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Turns free text into a safe FTS5 query: every word is quoted (so FTS5 operators and
+/// punctuation typed by the user can't cause syntax errors) and matched as a prefix.
+fn build_fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+impl Message {
+    /// Loads a thread: the root message and every message that references it as its root,
+    /// ordered oldest to newest
+    ///
+    /// # Arguments
+    /// * `mls_group_id` - The group the thread belongs to
+    /// * `root_id` - The event ID of the first message of the thread
+    /// * `wn` - The Whitenoise application state
+    pub async fn thread(
+        mls_group_id: &[u8],
+        root_id: &EventId,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<Self>> {
+        let active_account = Account::get_active(wn.clone()).await?;
+
+        let rows = sqlx::query_as::<_, MessageRow>(
+            "SELECT * FROM messages
+             WHERE mls_group_id = ? AND account_pubkey = ?
+               AND (event_id = ? OR EXISTS (
+                   SELECT 1 FROM json_each(messages.tags) AS t
+                   WHERE json_extract(t.value, '$[0]') = 'e' AND json_extract(t.value, '$[1]') = ?
+               ))
+             ORDER BY created_at ASC, id ASC",
+        )
+        .bind(mls_group_id)
+        .bind(active_account.pubkey.to_hex())
+        .bind(root_id.to_hex())
+        .bind(root_id.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(Message::from)
+            .filter(|m| m.event_id == *root_id || m.thread_root == Some(*root_id))
+            .collect())
+    }
+}
+
+impl Message {
+    /// Returns every version of a message, oldest first: the original content followed by
+    /// each edit made by its author
+    ///
+    /// # Arguments
+    /// * `mls_group_id` - The group the message belongs to
+    /// * `event_id` - The event ID of the original message
+    /// * `wn` - The Whitenoise application state
+    pub async fn edit_history(
+        mls_group_id: &[u8],
+        event_id: &EventId,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<MessageEdit>> {
+        let original = Self::find_by_event_id(*event_id, wn.clone()).await?;
+        if original.mls_group_id != mls_group_id {
+            return Err(MessageError::NotFound);
+        }
+        // Retracting a message retracts all of its versions
+        if original.deleted_at.is_some() {
+            return Ok(vec![]);
+        }
+
+        let rows = sqlx::query_as::<_, MessageRow>(
+            "SELECT * FROM messages
+             WHERE mls_group_id = ? AND account_pubkey = ? AND author_pubkey = ? AND event_kind = ?
+               AND EXISTS (
+                   SELECT 1 FROM json_each(messages.tags) AS t
+                   WHERE json_extract(t.value, '$[0]') = 'e' AND json_extract(t.value, '$[1]') = ?
+               )
+             ORDER BY created_at ASC, id ASC",
+        )
+        .bind(mls_group_id)
+        .bind(original.account_pubkey.to_hex())
+        .bind(original.author_pubkey.to_hex())
+        .bind(EDIT_KIND as i64)
+        .bind(event_id.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        // The stored event keeps the content as it was originally sent
+        let mut history = vec![MessageEdit {
+            event_id: original.event_id,
+            content: original.event.content.clone(),
+            created_at: original.created_at,
+        }];
+        history.extend(rows.into_iter().map(Message::from).map(|edit| MessageEdit {
+            event_id: edit.event_id,
+            content: edit.content,
+            created_at: edit.created_at,
+        }));
+        Ok(history)
+    }
+}
+
+impl Message {
+    /// Deletes every message whose expiration is at or before `now`, for all accounts, along
+    /// with the edits and reactions referencing them and their downloaded attachments
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MlsMessageExpiredEvent>)` - The messages that were deleted
+    pub async fn delete_expired(
+        now: Timestamp,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<MlsMessageExpiredEvent>> {
+        let rows = delete_expired_rows(&wn.database.pool, now).await?;
+
+        for (group_id, _, tags) in &rows {
+            if let Ok(tags) = serde_json::from_str::<Tags>(tags) {
+                attachments::purge_cached(&tags, group_id, &wn.data_dir).await;
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(group_id, event_id, _)| {
+                Some(MlsMessageExpiredEvent {
+                    group_id,
+                    message_id: EventId::from_hex(&event_id).ok()?,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Matches the expired messages, and the edits and reactions of the same account and group whose
+/// `e` tag references one of them. Those don't always carry an expiration of their own (e.g.
+/// reactions from clients that don't copy it), but they'd leak what the message was about.
+const EXPIRED_CONDITION: &str = "expires_at <= ?1 OR (
+         event_kind IN (?2, ?3) AND EXISTS (
+             SELECT 1 FROM messages AS expired, json_each(messages.tags) AS t
+             WHERE expired.expires_at <= ?1
+               AND expired.account_pubkey = messages.account_pubkey
+               AND expired.mls_group_id = messages.mls_group_id
+               AND json_extract(t.value, '$[0]') = 'e'
+               AND json_extract(t.value, '$[1]') = expired.event_id
+         )
+     )";
+
+/// Deletes the expired messages and what references them, returning the group ID, event ID and
+/// tags of each deleted row
+async fn delete_expired_rows(
+    pool: &sqlx::SqlitePool,
+    now: Timestamp,
+) -> Result<Vec<(Vec<u8>, String, String)>> {
+    let mut txn = pool.begin().await?;
+
+    sqlx::query(&format!(
+        "DELETE FROM message_outbox WHERE (event_id, account_pubkey) IN (
+             SELECT event_id, account_pubkey FROM messages WHERE {EXPIRED_CONDITION}
+         )"
+    ))
+    .bind(now.as_u64() as i64)
+    .bind(EDIT_KIND as i64)
+    .bind(REACTION_KIND as i64)
+    .execute(&mut *txn)
+    .await?;
+
+    let rows = sqlx::query_as(&format!(
+        "DELETE FROM messages WHERE {EXPIRED_CONDITION} RETURNING mls_group_id, event_id, tags"
+    ))
+    .bind(now.as_u64() as i64)
+    .bind(EDIT_KIND as i64)
+    .bind(REACTION_KIND as i64)
+    .fetch_all(&mut *txn)
+    .await?;
+
+    txn.commit().await?;
+    Ok(rows)
+}
+
 impl From<MessageRow> for Message {
     fn from(row: MessageRow) -> Self {
+        let account_pubkey = PublicKey::from_hex(&row.account_pubkey).unwrap();
+        let tags: Tags = serde_json::from_str(&row.tags).unwrap();
+        let tokens: Vec<SerializableToken> = match serde_json::from_value(row.tokens) {
+            Ok(val) => val,
+            Err(e) => {
+                tracing::error!("Failed to parse tokens: {}", e);
+                vec![] // or handle according to your error strategy
+            }
+        };
+        let semantics = MessageSemantics::compute(
+            row.event_kind,
+            &row.content,
+            &tags,
+            &tokens,
+            &account_pubkey,
+        );
+        let (thread_root, reply_to) = thread_refs(&tags);
+        let payment_request = payments::payment_request_from_tags(&tags, Timestamp::now().as_u64());
         Self {
             event_id: EventId::parse(&row.event_id).unwrap(),
-            account_pubkey: PublicKey::from_hex(&row.account_pubkey).unwrap(),
+            account_pubkey,
             author_pubkey: PublicKey::from_hex(&row.author_pubkey).unwrap(),
             event_kind: row.event_kind,
             mls_group_id: row.mls_group_id,
             created_at: Timestamp::from(row.created_at),
             content: row.content,
-            tags: serde_json::from_str(&row.tags).unwrap(),
+            tags,
             event: serde_json::from_str(&row.event).unwrap(),
             outer_event_id: EventId::parse(&row.outer_event_id).unwrap(),
-            tokens: match serde_json::from_value(row.tokens) {
-                Ok(val) => val,
-                Err(e) => {
-                    tracing::error!("Failed to parse tokens: {}", e);
-                    vec![] // or handle according to your error strategy
-                }
-            },
+            tokens,
+            semantics,
+            reply_to,
+            thread_root,
+            deleted_at: row.deleted_at.map(Timestamp::from),
+            edited_at: row.edited_at.map(Timestamp::from),
+            author_migrated_to: row
+                .author_migrated_to
+                .and_then(|pubkey| PublicKey::from_hex(&pubkey).ok()),
+            origin_group_id: row.origin_group_id,
+            expires_at: row.expires_at.map(Timestamp::from),
+            pending: row.pending,
+            display_content: None,
+            payment_request,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_fts_query() {
+        assert_eq!(build_fts_query(""), None);
+        assert_eq!(build_fts_query("   "), None);
+        assert_eq!(build_fts_query("hello"), Some("\"hello\"*".to_string()));
+        assert_eq!(
+            build_fts_query("hello  world"),
+            Some("\"hello\"* \"world\"*".to_string())
+        );
+        assert_eq!(
+            build_fts_query("say \"hi\" OR"),
+            Some("\"say\"* \"\"\"hi\"\"\"* \"OR\"*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_thread_refs() {
+        let root = EventId::all_zeros();
+        let parent =
+            EventId::from_hex("1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef")
+                .unwrap();
+
+        // Direct reply to the root
+        let tags = Tags::from_list(vec![marked_event_tag(&root, "root")]);
+        assert_eq!(thread_refs(&tags), (Some(root), Some(root)));
+
+        // Nested reply
+        let tags = Tags::from_list(vec![
+            marked_event_tag(&root, "root"),
+            marked_event_tag(&parent, "reply"),
+        ]);
+        assert_eq!(thread_refs(&tags), (Some(root), Some(parent)));
+
+        // Unmarked e tags (reactions, deletions) aren't replies
+        let tags = Tags::from_list(vec![Tag::event(parent)]);
+        assert_eq!(thread_refs(&tags), (None, None));
+    }
+
+    #[test]
+    fn test_expiration() {
+        let now = Timestamp::from(1_700_000_000);
+        let tags = Tags::from_list(vec![Tag::expiration(Timestamp::from(1_700_000_010))]);
+        assert_eq!(expiration(&tags), Some(Timestamp::from(1_700_000_010)));
+        assert!(!is_expired(&tags, now));
+        assert!(is_expired(&tags, Timestamp::from(1_700_000_010)));
+
+        // Events without an expiration never expire
+        assert_eq!(expiration(&Tags::new()), None);
+        assert!(!is_expired(&Tags::new(), now));
+    }
+
+    #[test]
+    fn test_lww_wins() {
+        let low = EventId::from_slice(&[1; 32]).unwrap();
+        let high = EventId::from_slice(&[2; 32]).unwrap();
+        let at = Timestamp::from;
+        assert!(lww_wins((at(20), low), (at(10), high)));
+        assert!(!lww_wins((at(10), high), (at(20), low)));
+        // Ties go to the higher event ID
+        assert!(lww_wins((at(10), high), (at(10), low)));
+        assert!(!lww_wins((at(10), low), (at(10), high)));
+        assert!(!lww_wins((at(10), low), (at(10), low)));
+    }
+
+    #[test]
+    fn test_is_emoji_only() {
+        assert!(is_emoji_only("😀"));
+        assert!(is_emoji_only(" 👍🏽  🎉 "));
+        assert!(is_emoji_only("👨‍👩‍👧"));
+        assert!(is_emoji_only("❤️"));
+        assert!(!is_emoji_only(""));
+        assert!(!is_emoji_only("   "));
+        assert!(!is_emoji_only("hi 😀"));
+        assert!(!is_emoji_only("\u{200D}"));
+    }
+
+    #[test]
+    fn test_semantics_attachment_and_system_kind() {
+        let me = Keys::generate().public_key();
+        let tags = Tags::from_list(vec![Tag::custom(
+            TagKind::Custom("imeta".into()),
+            vec!["url https://example.com/a.jpg".to_string()],
+        )]);
+        let semantics = MessageSemantics::compute(9, "look", &tags, &[], &me);
+        assert!(semantics.has_attachment);
+        assert!(!semantics.is_emoji_only);
+        assert_eq!(semantics.system_message, None);
+        assert!(!semantics.mentions_me);
+
+        let semantics = MessageSemantics::compute(5, "", &Tags::new(), &[], &me);
+        assert_eq!(semantics.system_message, Some(SystemMessageKind::Deletion));
+
+        let semantics = MessageSemantics::compute(EDIT_KIND, "fixed", &Tags::new(), &[], &me);
+        assert_eq!(semantics.system_message, Some(SystemMessageKind::Edit));
+        assert!(!semantics.is_notice);
+
+        let semantics =
+            MessageSemantics::compute(GROUP_NOTICE_KIND, "Maintenance", &Tags::new(), &[], &me);
+        assert!(semantics.is_notice);
+        assert_eq!(
+            semantics.system_message,
+            Some(SystemMessageKind::GroupNotice)
+        );
+    }
+
+    #[test]
+    fn test_semantics_mentions_me() {
+        let me = Keys::generate().public_key();
+        let someone_else = Keys::generate().public_key();
+
+        let tags = Tags::from_list(vec![Tag::public_key(me)]);
+        assert!(MessageSemantics::compute(9, "hey", &tags, &[], &me).mentions_me);
+
+        let tokens = vec![SerializableToken::Nostr(format!(
+            "nostr:{}",
+            me.to_bech32().unwrap()
+        ))];
+        let empty = Tags::new();
+        assert!(MessageSemantics::compute(9, "hey", &empty, &tokens, &me).mentions_me);
+        assert!(!MessageSemantics::compute(9, "hey", &empty, &tokens, &someone_else).mentions_me);
+    }
+
+    async fn insert_message(
+        pool: &sqlx::SqlitePool,
+        id: u8,
+        kind: u16,
+        tags: Vec<Tag>,
+        expires_at: Option<u64>,
+    ) {
+        sqlx::query(
+            "INSERT INTO messages (event_id, account_pubkey, event_kind, mls_group_id, tags, expires_at)
+             VALUES (?, 'account', ?, ?, ?, ?)",
+        )
+        .bind(EventId::from_slice(&[id; 32]).unwrap().to_hex())
+        .bind(kind as i64)
+        .bind(vec![1u8])
+        .bind(serde_json::to_string(&tags).unwrap())
+        .bind(expires_at.map(|t| t as i64))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired_takes_edits_and_reactions_along() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        std::fs::File::create(&db_path).unwrap();
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&format!("sqlite:{}", db_path.display()))
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE messages (
+                event_id TEXT NOT NULL,
+                account_pubkey TEXT NOT NULL,
+                event_kind INTEGER NOT NULL,
+                mls_group_id BLOB NOT NULL,
+                tags TEXT NOT NULL,
+                expires_at INTEGER
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TABLE message_outbox (event_id TEXT NOT NULL, account_pubkey TEXT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let ephemeral = EventId::from_slice(&[1; 32]).unwrap();
+        insert_message(&pool, 1, 9, vec![], Some(100)).await;
+        // An edit and a reaction that don't carry the expiration themselves
+        insert_message(&pool, 2, EDIT_KIND, vec![Tag::event(ephemeral)], None).await;
+        insert_message(&pool, 3, REACTION_KIND, vec![Tag::event(ephemeral)], None).await;
+        // A reply is a message of its own and stays
+        insert_message(&pool, 4, 9, vec![Tag::event(ephemeral)], None).await;
+        insert_message(&pool, 5, 9, vec![], Some(200)).await;
+
+        let deleted = delete_expired_rows(&pool, Timestamp::from(150))
+            .await
+            .unwrap();
+        let mut deleted: Vec<String> = deleted.into_iter().map(|(_, id, _)| id).collect();
+        deleted.sort();
+        assert_eq!(
+            deleted,
+            [1u8, 2, 3]
+                .map(|id| EventId::from_slice(&[id; 32]).unwrap().to_hex())
+                .to_vec()
+        );
+
+        let (left,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM messages")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 2);
+    }
+}
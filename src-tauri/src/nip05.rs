@@ -0,0 +1,303 @@
+//! NIP-05 identifiers (`name@domain`).
+//!
+//! [`resolve`] looks an identifier up in the domain's `/.well-known/nostr.json`. Only names on
+//! the default HTTPS port of a domain are looked up, never IP addresses, and the document is
+//! read up to `MAX_DOCUMENT_SIZE`.
+//!
+//! Requests are only made when the user asks: [`verify`] checks a contact's `nip05` against
+//! their pubkey and caches the result in `nip05_verifications`, so that the contact list isn't
+//! sent to every domain it mentions. [`annotate`] only reads that cache. A result is reused for a
+//! day if the name was verified and for an hour if it wasn't.
+
+use crate::types::EnrichedContact;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long a successful verification is trusted
+const VERIFIED_TTL: u64 = 24 * 60 * 60;
+
+/// How long a failed verification is cached before it's retried
+const FAILED_TTL: u64 = 60 * 60;
+
+/// How long a domain gets to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The largest `nostr.json` document that's read
+const MAX_DOCUMENT_SIZE: usize = 64 * 1024;
+
+#[derive(Error, Debug)]
+pub enum Nip05Error {
+    #[error("Invalid NIP-05 identifier: {0}")]
+    InvalidIdentifier(String),
+
+    #[error("No pubkey is published for {0}")]
+    NameNotFound(String),
+
+    #[error("Invalid pubkey: {0}")]
+    InvalidPubkey(String),
+
+    #[error("The nostr.json of {0} is too large")]
+    DocumentTooLarge(String),
+
+    #[error("Invalid nostr.json: {0}")]
+    InvalidDocument(#[from] serde_json::Error),
+
+    #[error("Request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Nip05Error>;
+
+/// What a NIP-05 identifier resolves to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Nip05Profile {
+    /// Hex encoded pubkey
+    pub pubkey: String,
+    /// Relays the domain lists for the pubkey
+    pub relays: Vec<String>,
+}
+
+/// Whether a NIP-05 identifier points to a pubkey
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Nip05Verification {
+    /// Hex encoded pubkey
+    pub pubkey: String,
+    pub nip05: String,
+    pub verified: bool,
+}
+
+/// The `/.well-known/nostr.json` document of a domain
+#[derive(Debug, Deserialize)]
+struct Nip05Document {
+    #[serde(default)]
+    names: HashMap<String, String>,
+    #[serde(default)]
+    relays: HashMap<String, Vec<String>>,
+}
+
+/// Splits an identifier into its local part and domain. A bare domain stands for `_@domain`.
+/// Domains with a port and IP addresses aren't accepted.
+pub fn parse_identifier(identifier: &str) -> Result<(String, String)> {
+    let identifier = identifier.trim().to_lowercase();
+    let (name, domain) = match identifier.split_once('@') {
+        Some((name, domain)) => (name.to_string(), domain.to_string()),
+        None => ("_".to_string(), identifier.clone()),
+    };
+
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    let valid_domain = domain.contains('.')
+        && !domain.starts_with('.')
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.'))
+        && domain.parse::<IpAddr>().is_err();
+    if !valid_name || !valid_domain {
+        return Err(Nip05Error::InvalidIdentifier(identifier));
+    }
+    Ok((name, domain))
+}
+
+fn profile_from_document(document: &Nip05Document, name: &str) -> Result<Nip05Profile> {
+    let pubkey = document
+        .names
+        .get(name)
+        .ok_or_else(|| Nip05Error::NameNotFound(name.to_string()))?;
+    let pubkey = PublicKey::from_hex(pubkey)
+        .map_err(|_| Nip05Error::InvalidPubkey(pubkey.to_string()))?
+        .to_hex();
+    let relays = document.relays.get(&pubkey).cloned().unwrap_or_default();
+    Ok(Nip05Profile { pubkey, relays })
+}
+
+/// Looks up the pubkey and relays a NIP-05 identifier points to
+pub async fn resolve(identifier: &str) -> Result<Nip05Profile> {
+    let (name, domain) = parse_identifier(identifier)?;
+
+    // NIP-05 forbids following redirects, so a domain can't vouch for another one's names
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let mut response = client
+        .get(format!("https://{}/.well-known/nostr.json", domain))
+        .query(&[("name", &name)])
+        .send()
+        .await?
+        .error_for_status()?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_DOCUMENT_SIZE as u64)
+    {
+        return Err(Nip05Error::DocumentTooLarge(domain));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_DOCUMENT_SIZE {
+            return Err(Nip05Error::DocumentTooLarge(domain));
+        }
+    }
+    let document: Nip05Document = serde_json::from_slice(&body)?;
+
+    profile_from_document(&document, &name)
+}
+
+/// Whether a cached result checked at `checked_at` can still be used at `now`
+fn is_fresh(verified: bool, checked_at: u64, now: u64) -> bool {
+    let ttl = if verified { VERIFIED_TTL } else { FAILED_TTL };
+    now < checked_at.saturating_add(ttl)
+}
+
+/// Sets `nip05_verified` on each contact from cached results
+///
+/// A name is verified only if the contact's `nip05` is still the one that was verified.
+pub async fn annotate<'a>(
+    contacts: impl IntoIterator<Item = (&'a String, &'a mut EnrichedContact)>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<()> {
+    let rows = sqlx::query_as::<_, (String, String, bool, i64)>(
+        "SELECT pubkey, nip05, verified, checked_at FROM nip05_verifications",
+    )
+    .fetch_all(&wn.database.pool)
+    .await?;
+    let cached: HashMap<String, (String, bool, u64)> = rows
+        .into_iter()
+        .map(|(pubkey, nip05, verified, checked_at)| (pubkey, (nip05, verified, checked_at as u64)))
+        .collect();
+
+    for (pubkey, contact) in contacts {
+        let Some(nip05) = contact.metadata.nip05.as_deref().map(str::to_lowercase) else {
+            contact.nip05_verified = false;
+            continue;
+        };
+        contact.nip05_verified = cached
+            .get(pubkey)
+            .is_some_and(|(cached_nip05, verified, _)| *cached_nip05 == nip05 && *verified);
+    }
+    Ok(())
+}
+
+async fn store(
+    verification: &Nip05Verification,
+    now: u64,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO nip05_verifications (pubkey, nip05, verified, checked_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT (pubkey) DO UPDATE
+         SET nip05 = excluded.nip05, verified = excluded.verified, checked_at = excluded.checked_at",
+    )
+    .bind(&verification.pubkey)
+    .bind(&verification.nip05)
+    .bind(verification.verified)
+    .bind(now as i64)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(())
+}
+
+/// Checks whether `nip05` points to the hex encoded `pubkey` and caches the result. A fresh
+/// cached result for the same name is returned without a request.
+///
+/// # Errors
+///
+/// Returns an error if the identifier is invalid or its domain couldn't be reached; nothing is
+/// cached then.
+pub async fn verify(
+    pubkey: &str,
+    nip05: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Nip05Verification> {
+    let nip05 = nip05.trim().to_lowercase();
+    parse_identifier(&nip05)?;
+
+    let now = Timestamp::now().as_u64();
+    let cached = sqlx::query_as::<_, (String, bool, i64)>(
+        "SELECT nip05, verified, checked_at FROM nip05_verifications WHERE pubkey = ?",
+    )
+    .bind(pubkey)
+    .fetch_optional(&wn.database.pool)
+    .await?;
+    if let Some((_, verified, _)) = cached.filter(|(cached_nip05, verified, checked_at)| {
+        *cached_nip05 == nip05 && is_fresh(*verified, *checked_at as u64, now)
+    }) {
+        return Ok(Nip05Verification {
+            pubkey: pubkey.to_string(),
+            nip05,
+            verified,
+        });
+    }
+
+    let verified = match resolve(&nip05).await {
+        Ok(profile) => profile.pubkey == pubkey,
+        Err(Nip05Error::NameNotFound(_))
+        | Err(Nip05Error::InvalidPubkey(_))
+        | Err(Nip05Error::InvalidDocument(_))
+        | Err(Nip05Error::DocumentTooLarge(_)) => false,
+        Err(e) => return Err(e),
+    };
+    let verification = Nip05Verification {
+        pubkey: pubkey.to_string(),
+        nip05,
+        verified,
+    };
+    store(&verification, now, wn).await?;
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+
+    #[test]
+    fn test_parse_identifier() {
+        assert_eq!(
+            parse_identifier(" Bob@Example.com ").unwrap(),
+            ("bob".to_string(), "example.com".to_string())
+        );
+        assert_eq!(
+            parse_identifier("example.com").unwrap(),
+            ("_".to_string(), "example.com".to_string())
+        );
+        assert!(parse_identifier("bob@localhost").is_err());
+        assert!(parse_identifier("bob@evil.com/path").is_err());
+        assert!(parse_identifier("b ob@example.com").is_err());
+        assert!(parse_identifier("bob@127.0.0.1").is_err());
+        assert!(parse_identifier("bob@example.com:8080").is_err());
+    }
+
+    #[test]
+    fn test_profile_from_document() {
+        let document: Nip05Document = serde_json::from_str(&format!(
+            r#"{{"names": {{"bob": "{PUBKEY}"}}, "relays": {{"{PUBKEY}": ["wss://relay.example.com"]}}}}"#
+        ))
+        .unwrap();
+        let profile = profile_from_document(&document, "bob").unwrap();
+        assert_eq!(profile.pubkey, PUBKEY);
+        assert_eq!(profile.relays, vec!["wss://relay.example.com".to_string()]);
+        assert!(matches!(
+            profile_from_document(&document, "alice"),
+            Err(Nip05Error::NameNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_failed_verifications_expire_sooner() {
+        assert!(is_fresh(true, 1_000, 1_000 + FAILED_TTL));
+        assert!(!is_fresh(false, 1_000, 1_000 + FAILED_TTL));
+        assert!(!is_fresh(true, 1_000, 1_000 + VERIFIED_TTL));
+    }
+}
@@ -1,12 +1,27 @@
 use crate::accounts::{Account, AccountError};
-use crate::groups::{Group, GroupError};
+use crate::blocklist::{self, BlocklistError};
+use crate::device_sync::{self, DeviceSyncError, DEVICE_SYNC_KIND};
+use crate::epoch_recovery::{self, EpochRecoveryError};
+use crate::group_event_log::{self, EventOutcome};
+use crate::groups::{Group, GroupError, GroupType};
+use crate::invite_messages::{self, InviteMessageError, JOIN_REQUEST_KIND};
 use crate::invites::{Invite, InviteError, InviteState, ProcessedInvite, ProcessedInviteState};
-use crate::key_packages;
-use crate::messages::{MessageError, ProcessedMessage, ProcessedMessageState};
+use crate::key_migrations::{KeyMigration, KeyMigrationError};
+use crate::key_packages::{self, ConsumptionSource, KeyPackageConsumption};
+use crate::messages::{
+    self, Message, MessageError, MessageSemantics, ProcessedMessage, ProcessedMessageState,
+};
 use crate::nostr_manager::parser::{parse, SerializableToken};
 use crate::nostr_manager::NostrManagerError;
+use crate::notifications::{self, NotificationError};
+use crate::profiling::{self, OperationKind};
+use crate::protocol;
+use crate::reactions::{self, MlsReactionReceivedEvent, ReactionError, REACTION_KIND};
+use crate::read_receipts::{ReadReceipt, ReadReceiptError, READ_RECEIPT_KIND};
+use crate::recovery::{self, RecoveryError, RECOVERY_KIND};
 use crate::relays::RelayType;
 use crate::secrets_store;
+use crate::typing::{PeerTypingEvent, TYPING_INDICATOR_KIND, TYPING_INDICATOR_TTL_SECS};
 use crate::Whitenoise;
 use nostr_openmls::groups::GroupError as NostrOpenmlsGroupError;
 use nostr_sdk::prelude::*;
@@ -15,6 +30,7 @@ use tauri::{AppHandle, Emitter, Manager};
 use thiserror::Error;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{self, Receiver, Sender};
+use zeroize::Zeroizing;
 
 #[derive(Error, Debug)]
 pub enum EventProcessorError {
@@ -44,6 +60,24 @@ pub enum EventProcessorError {
     UnparseableKey(#[from] nostr_sdk::key::Error),
     #[error("Message error: {0}")]
     MessageError(#[from] MessageError),
+    #[error("Reaction error: {0}")]
+    ReactionError(#[from] ReactionError),
+    #[error("Key migration error: {0}")]
+    KeyMigrationError(#[from] KeyMigrationError),
+    #[error("Read receipt error: {0}")]
+    ReadReceiptError(#[from] ReadReceiptError),
+    #[error("Device sync error: {0}")]
+    DeviceSyncError(#[from] DeviceSyncError),
+    #[error("Blocklist error: {0}")]
+    BlocklistError(#[from] BlocklistError),
+    #[error("Recovery error: {0}")]
+    RecoveryError(#[from] RecoveryError),
+    #[error("Invite message error: {0}")]
+    InviteMessageError(#[from] InviteMessageError),
+    #[error("Notification error: {0}")]
+    NotificationError(#[from] NotificationError),
+    #[error("Epoch recovery error: {0}")]
+    EpochRecoveryError(#[from] EpochRecoveryError),
 }
 
 pub type Result<T> = std::result::Result<T, EventProcessorError>;
@@ -52,6 +86,8 @@ pub type Result<T> = std::result::Result<T, EventProcessorError>;
 pub enum ProcessableEvent {
     GiftWrap(Event),
     MlsMessage(Event),
+    KeyMigration(Event),
+    Metadata(Event),
 }
 
 #[derive(Debug)]
@@ -64,6 +100,9 @@ pub struct EventProcessor {
 pub struct MlsMessageReceivedEvent {
     pub group_id: Vec<u8>,
     pub event: UnsignedEvent,
+    pub semantics: MessageSemantics,
+    /// The message this one replies to, resolved from the transcript if we have it
+    pub parent: Option<Message>,
 }
 
 impl EventProcessor {
@@ -99,6 +138,11 @@ impl EventProcessor {
         }
     }
 
+    /// How many queued events haven't been processed yet
+    pub fn queue_depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
     pub async fn queue_event(&self, event: ProcessableEvent) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_manager::event_processor",
@@ -159,6 +203,24 @@ impl EventProcessor {
                                 );
                             }
                         }
+                        ProcessableEvent::KeyMigration(event) => {
+                            if let Err(e) = Self::process_key_migration(&app_handle, event).await {
+                                tracing::error!(
+                                    target: "whitenoise::nostr_manager::event_processor",
+                                    "Error processing key migration: {}",
+                                    e
+                                );
+                            }
+                        }
+                        ProcessableEvent::Metadata(event) => {
+                            if let Err(e) = Self::process_metadata(&app_handle, event).await {
+                                tracing::error!(
+                                    target: "whitenoise::nostr_manager::event_processor",
+                                    "Error processing metadata: {}",
+                                    e
+                                );
+                            }
+                        }
                     }
                 }
                 Some(_) = shutdown.recv() => {
@@ -207,12 +269,49 @@ impl EventProcessor {
         let wn = app_handle.state::<Whitenoise>();
         let active_account = Account::get_active(wn.clone()).await?;
         let keys = active_account.keys(wn.clone())?;
+
+        // In whitelist-only mode we only unwrap the outer layer to learn the sender and drop
+        // anything that isn't from a contact before the rumor is ever decrypted.
+        if active_account.settings.whitelist_only_mode {
+            let contacts = wn.nostr.query_contact_list_pubkeys().await?;
+            match Self::giftwrap_sender(&keys, &event) {
+                Some(sender) if contacts.contains(&sender) || sender == active_account.pubkey => {}
+                sender => {
+                    tracing::debug!(
+                        target: "whitenoise::nostr_manager::event_processor",
+                        "Whitelist-only mode: dropping giftwrap {} from non-contact {:?}",
+                        event.id,
+                        sender.map(|pk| pk.to_hex())
+                    );
+                    return Ok(());
+                }
+            }
+        }
+
         if let Ok(unwrapped) = extract_rumor(&keys, &event).await {
             match unwrapped.rumor.kind {
                 Kind::MlsWelcome => {
                     Self::process_invite(app_handle, active_account, event, unwrapped.rumor)
                         .await?;
                 }
+                kind if kind.as_u16() == DEVICE_SYNC_KIND => {
+                    if unwrapped.sender == active_account.pubkey {
+                        device_sync::apply(&unwrapped.rumor, wn.clone(), app_handle).await?;
+                    }
+                }
+                kind if kind.as_u16() == RECOVERY_KIND => {
+                    recovery::handle(&unwrapped.rumor, unwrapped.sender, wn.clone(), app_handle)
+                        .await?;
+                }
+                kind if kind.as_u16() == JOIN_REQUEST_KIND => {
+                    invite_messages::handle(
+                        &unwrapped.rumor,
+                        unwrapped.sender,
+                        wn.clone(),
+                        app_handle,
+                    )
+                    .await?;
+                }
                 Kind::PrivateDirectMessage => {
                     tracing::debug!(
                         target: "whitenoise::nostr_manager::event_processor",
@@ -232,6 +331,16 @@ impl EventProcessor {
         Ok(())
     }
 
+    /// Decrypts only the gift wrap layer and returns the (verified) author of the seal inside it
+    fn giftwrap_sender(keys: &Keys, event: &Event) -> Option<PublicKey> {
+        let seal_json = nip44::decrypt(keys.secret_key(), &event.pubkey, &event.content).ok()?;
+        let seal = Event::from_json(seal_json).ok()?;
+        if seal.kind != Kind::Seal || seal.verify().is_err() {
+            return None;
+        }
+        Some(seal.pubkey)
+    }
+
     async fn process_invite(
         app_handle: &AppHandle,
         account: Account,
@@ -297,8 +406,10 @@ impl EventProcessor {
         }
 
         let unwrapped_welcome_preview = welcome_preview.unwrap();
+        let inviter_blocked =
+            blocklist::is_blocked(&account, &rumor_event.pubkey, wn.clone()).await?;
 
-        // Create and save invite
+        // Create and save invite. Invites from blocked users are declined right away.
         let invite = Invite {
             event_id: rumor_event.id.unwrap().to_string(),
             account_pubkey: account.pubkey.to_hex(),
@@ -315,8 +426,13 @@ impl EventProcessor {
             group_relays: unwrapped_welcome_preview.nostr_group_data.relays(),
             inviter: rumor_event.pubkey.to_hex(),
             member_count: unwrapped_welcome_preview.staged_welcome.members().count() as u32,
-            state: InviteState::Pending,
+            state: if inviter_blocked {
+                InviteState::Declined
+            } else {
+                InviteState::Pending
+            },
             outer_event_id: outer_event.id.to_string(),
+            activity: None,
         };
 
         invite.save(wn.clone()).await?;
@@ -338,9 +454,37 @@ impl EventProcessor {
             })
             .and_then(|tag| tag.content());
 
-        app_handle
-            .emit("invite_processed", invite)
-            .map_err(NostrManagerError::TauriError)?;
+        if let Some(key_package_id) = key_package_event_id {
+            let consumption = KeyPackageConsumption {
+                key_package_id: key_package_id.to_string(),
+                consumed_at: rumor_event.created_at,
+                source: ConsumptionSource::Welcome,
+                group_id: Some(hex::encode(&invite.mls_group_id)),
+                group_name: Some(invite.group_name.clone()),
+                inviter: Some(invite.inviter.clone()),
+            };
+            key_packages::record_consumption(&account, &consumption, wn.clone(), app_handle)
+                .await?;
+        }
+
+        if inviter_blocked {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::event_processor",
+                "Declined invite from blocked user: {}",
+                invite.inviter
+            );
+        } else {
+            if let Err(e) = notifications::show_invite(&account, &invite, app_handle) {
+                tracing::warn!(
+                    target: "whitenoise::nostr_manager::event_processor",
+                    "Failed to show invite notification: {}",
+                    e
+                );
+            }
+            app_handle
+                .emit("invite_processed", invite)
+                .map_err(NostrManagerError::TauriError)?;
+        }
 
         let key_package_relays: Vec<String> = if cfg!(dev) {
             vec![
@@ -361,8 +505,9 @@ impl EventProcessor {
             .await?;
             tracing::debug!(target: "whitenoise::nostr_manager::event_processor", "Deleted used key package from relays");
 
-            key_packages::publish_key_package(wn.clone()).await?;
-            tracing::debug!(target: "whitenoise::nostr_manager::event_processor", "Published new key package");
+            // Replace the consumed key package
+            key_packages::replenish(wn.clone()).await?;
+            tracing::debug!(target: "whitenoise::nostr_manager::event_processor", "Replenished key packages");
         }
 
         Ok(())
@@ -384,7 +529,130 @@ impl EventProcessor {
         Ok(())
     }
 
-    async fn process_mls_message(app_handle: &AppHandle, event: Event) -> Result<()> {
+    /// Records a key migration announced by one of our contacts and emits `contact_key_migrated`
+    async fn process_key_migration(app_handle: &AppHandle, event: Event) -> Result<()> {
+        let wn = app_handle.state::<Whitenoise>();
+
+        // Only contacts can prompt us to re-invite someone
+        if !wn
+            .nostr
+            .query_contact_list_pubkeys()
+            .await?
+            .contains(&event.pubkey)
+        {
+            return Ok(());
+        }
+
+        let Some(migration) = KeyMigration::record(&event, wn.clone()).await? else {
+            return Ok(());
+        };
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::event_processor",
+            "Contact {} migrated to {}",
+            migration.old_pubkey,
+            migration.new_pubkey
+        );
+
+        let prompt = migration.prompt(wn.clone()).await?;
+        app_handle
+            .emit("contact_key_migrated", prompt)
+            .map_err(NostrManagerError::TauriError)?;
+        Ok(())
+    }
+
+    /// Refreshes the display name and picture of the direct messages with the author of a
+    /// metadata event and emits `group_updated` for each of them
+    async fn process_metadata(app_handle: &AppHandle, event: Event) -> Result<()> {
+        let wn = app_handle.state::<Whitenoise>();
+        let Ok(metadata) = Metadata::from_json(&event.content) else {
+            return Ok(());
+        };
+
+        for mut group in Group::get_all_groups(wn.clone()).await? {
+            if !matches!(group.group_type, GroupType::DirectMessage)
+                || group.account_pubkey == event.pubkey
+                || !group.members(wn.clone()).await?.contains(&event.pubkey)
+            {
+                continue;
+            }
+            group.apply_peer_metadata(event.pubkey, &metadata);
+            app_handle
+                .emit("group_updated", group)
+                .map_err(NostrManagerError::TauriError)?;
+        }
+        Ok(())
+    }
+
+    /// Emits `peer_typing` for a typing indicator from another member, unless it already expired
+    fn emit_peer_typing(
+        app_handle: &AppHandle,
+        group: &Group,
+        indicator: &UnsignedEvent,
+        active_account: &Account,
+    ) -> Result<()> {
+        let now = Timestamp::now();
+        if indicator.pubkey == active_account.pubkey || messages::is_expired(&indicator.tags, now) {
+            return Ok(());
+        }
+
+        let expires_at = messages::expiration(&indicator.tags)
+            .unwrap_or(now + TYPING_INDICATOR_TTL_SECS)
+            .min(now + TYPING_INDICATOR_TTL_SECS);
+
+        app_handle
+            .emit(
+                "peer_typing",
+                PeerTypingEvent {
+                    group_id: hex::encode(&group.mls_group_id),
+                    pubkey: indicator.pubkey,
+                    expires_at,
+                },
+            )
+            .map_err(NostrManagerError::TauriError)?;
+        Ok(())
+    }
+
+    /// Aggregates the reactions on the message a reaction targets and emits `mls_reaction_received`
+    async fn emit_reaction_received(
+        app_handle: &AppHandle,
+        group: &Group,
+        reaction: &Message,
+    ) -> Result<()> {
+        let wn = app_handle.state::<Whitenoise>();
+        let Some(target_event_id) = reactions::reaction_target(&reaction.tags) else {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::event_processor",
+                "Reaction {} has no target event",
+                reaction.event_id
+            );
+            return Ok(());
+        };
+
+        let summaries = reactions::summarize_message_reactions(
+            &group.mls_group_id,
+            &target_event_id,
+            wn.clone(),
+        )
+        .await?;
+
+        app_handle
+            .emit(
+                "mls_reaction_received",
+                MlsReactionReceivedEvent {
+                    group_id: group.mls_group_id.clone(),
+                    target_event_id,
+                    reaction: reaction.clone(),
+                    reactions: summaries,
+                },
+            )
+            .map_err(NostrManagerError::TauriError)?;
+        Ok(())
+    }
+
+    /// Decrypts and stores a group message. Called directly, rather than through the queue, by
+    /// callers that need to know when processing is done.
+    pub(crate) async fn process_mls_message(app_handle: &AppHandle, event: Event) -> Result<()> {
         let wn = app_handle.state::<Whitenoise>();
 
         // Check to see if the event has already been processed
@@ -401,55 +669,95 @@ impl EventProcessor {
             .unwrap();
 
         let group = Group::get_by_nostr_group_id(group_id, wn.clone()).await?;
+        let active_account = Account::get_active(wn.clone()).await?;
 
-        // TODO: Need to figure out how to reprocess events that fail because a commit arrives out of order
-
-        let nostr_keys = match secrets_store::get_export_secret_keys_for_group(
-            group.mls_group_id.clone(),
-            group.epoch,
-            wn.data_dir.as_path(),
-        ) {
-            Ok(keys) => keys,
-            Err(_) => {
-                tracing::debug!(
-                    target: "whitenoise::commands::groups::fetch_mls_messages",
-                    "No export secret keys found, fetching from nostr_openmls",
-                );
-                // We need to get the export secret for the group from nostr_openmls
-                let nostr_mls = wn.nostr_mls.lock().await;
-                let (export_secret_hex, epoch) = nostr_mls
-                    .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())?;
-
-                // Store the export secret key in the secrets store
-                secrets_store::store_mls_export_secret(
-                    group.mls_group_id.clone(),
-                    epoch,
-                    export_secret_hex.clone(),
-                    wn.data_dir.as_path(),
-                )?;
-
-                Keys::parse(&export_secret_hex)?
-            }
+        // Decrypt events using the export secret of the epoch they were sent in
+        let Some(decrypted_content) = epoch_recovery::decrypt(&group, &event, wn.clone()).await?
+        else {
+            tracing::warn!(
+                target: "whitenoise::commands::groups::fetch_mls_messages",
+                "No export secret decrypts message {}, quarantining it",
+                event.id
+            );
+            epoch_recovery::quarantine(
+                &group,
+                &event,
+                "No known export secret decrypts the message",
+                wn.clone(),
+            )
+            .await?;
+            return Ok(());
         };
 
-        // Decrypt events using export secret key
-        let decrypted_content = nip44::decrypt_to_bytes(
-            nostr_keys.secret_key(),
-            &nostr_keys.public_key(),
-            &event.content,
-        )?;
-
         let message_vec;
         {
             let nostr_mls = wn.nostr_mls.lock().await;
+            let log_events = group_event_log::is_enabled()
+                && match group_event_log::save_baseline(
+                    &active_account,
+                    &group.mls_group_id,
+                    wn.clone(),
+                )
+                .await
+                {
+                    Ok(logged) => logged,
+                    Err(e) => {
+                        tracing::warn!(
+                            target: "whitenoise::nostr_manager::event_processor",
+                            "Failed to save group event log baseline: {}",
+                            e
+                        );
+                        false
+                    }
+                };
 
             // TODO: This only handles application messages for now. We need to handle commits and proposals
-            match nostr_mls
-                .process_message_for_group(group.mls_group_id.clone(), decrypted_content.clone())
-            {
-                Ok(message) => message_vec = message,
+            // External joins (external commits against a published GroupInfo) aren't supported
+            // and are dropped here like any other commit. `process_message_for_group` doesn't hand
+            // back the staged commit, so there's no joiner to check against the group's admins.
+            // Supporting them means publishing GroupInfo, validating and merging the staged
+            // commit, storing the new epoch's export secret and emitting `group_member_joined`.
+            let result = profiling::time("mls.process_message", OperationKind::Mls, || {
+                nostr_mls.process_message_for_group(
+                    group.mls_group_id.clone(),
+                    decrypted_content.to_vec(),
+                )
+            });
+            if log_events {
+                if let Err(e) = group_event_log::record(
+                    &active_account,
+                    &group.mls_group_id,
+                    &event,
+                    &EventOutcome::from_processed(&result),
+                    wn.clone(),
+                )
+                .await
+                {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::event_processor",
+                        "Failed to log group event {}: {}",
+                        event.id,
+                        e
+                    );
+                }
+            }
+            match result {
+                Ok(message) => message_vec = Zeroizing::new(message),
                 Err(e) => {
                     match e {
+                        NostrOpenmlsGroupError::ProcessMessageError(e)
+                            if epoch_recovery::is_epoch_mismatch(&e.to_string()) =>
+                        {
+                            // Likely sent after a commit that hasn't arrived yet
+                            tracing::debug!(
+                                target: "whitenoise::commands::groups::fetch_mls_messages",
+                                "Message {} isn't for the group's current epoch, quarantining it: {}",
+                                event.id,
+                                e
+                            );
+                            epoch_recovery::quarantine(&group, &event, &e.to_string(), wn.clone())
+                                .await?;
+                        }
                         NostrOpenmlsGroupError::ProcessMessageError(e) => {
                             if !e.to_string().contains("Cannot decrypt own messages") {
                                 tracing::error!(
@@ -461,7 +769,7 @@ impl EventProcessor {
                                     event.id,
                                     None,
                                     ProcessedMessageState::Failed,
-                                    "Cannot decrypt own messages".to_string(),
+                                    e.to_string(),
                                     wn.clone(),
                                 )
                                 .await?;
@@ -485,7 +793,6 @@ impl EventProcessor {
                             .await?;
                         }
                     }
-                    // TODO: Need to figure out how to reprocess events that fail because a commit arrives out of order
                     return Ok(());
                 }
             }
@@ -493,6 +800,8 @@ impl EventProcessor {
 
         // This processes an application message into JSON.
         let mut json_event;
+        let semantics;
+        let parent;
         match serde_json::from_slice::<serde_json::Value>(&message_vec) {
             Ok(json_value) => {
                 tracing::debug!(
@@ -503,6 +812,23 @@ impl EventProcessor {
                 let json_str = json_value.to_string();
                 json_event = UnsignedEvent::from_json(&json_str).unwrap();
 
+                if let Err(e) = protocol::normalize(&mut json_event) {
+                    tracing::debug!(
+                        target: "whitenoise::commands::groups::fetch_mls_messages",
+                        "Dropping malformed message: {}",
+                        e
+                    );
+                    ProcessedMessage::create_with_state_and_reason(
+                        event.id,
+                        json_event.id,
+                        ProcessedMessageState::Failed,
+                        e.to_string(),
+                        wn.clone(),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
                 if !group
                     .members(wn.clone())
                     .await?
@@ -524,6 +850,80 @@ impl EventProcessor {
                     return Ok(());
                 }
 
+                if json_event.pubkey != active_account.pubkey
+                    && blocklist::is_blocked(&active_account, &json_event.pubkey, wn.clone())
+                        .await?
+                {
+                    tracing::debug!(
+                        target: "whitenoise::commands::groups::fetch_mls_messages",
+                        "Dropping message from blocked user: {:?}",
+                        json_event.pubkey
+                    );
+                    ProcessedMessage::create_with_state_and_reason(
+                        event.id,
+                        Some(json_event.id.unwrap()),
+                        ProcessedMessageState::Failed,
+                        "Message from blocked user".to_string(),
+                        wn.clone(),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
+                if active_account.settings.whitelist_only_mode
+                    && json_event.pubkey != active_account.pubkey
+                    && !wn
+                        .nostr
+                        .query_contact_list_pubkeys()
+                        .await?
+                        .contains(&json_event.pubkey)
+                {
+                    tracing::debug!(
+                        target: "whitenoise::commands::groups::fetch_mls_messages",
+                        "Whitelist-only mode: dropping message from non-contact: {:?}",
+                        json_event.pubkey
+                    );
+                    ProcessedMessage::create_with_state_and_reason(
+                        event.id,
+                        Some(json_event.id.unwrap()),
+                        ProcessedMessageState::Failed,
+                        "Message from non-contact in whitelist-only mode".to_string(),
+                        wn.clone(),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
+                // Typing indicators are only relayed to the frontend, never stored
+                if json_event.kind.as_u16() == TYPING_INDICATOR_KIND {
+                    Self::emit_peer_typing(app_handle, &group, &json_event, &active_account)?;
+                    return Ok(());
+                }
+
+                // Ephemeral messages that expired while in transit are never stored
+                if messages::is_expired(&json_event.tags, Timestamp::now()) {
+                    tracing::debug!(
+                        target: "whitenoise::commands::groups::fetch_mls_messages",
+                        "Dropping expired message: {:?}",
+                        json_event.id
+                    );
+                    return Ok(());
+                }
+
+                // Read receipts update the read status of other members, not the transcript
+                if json_event.kind.as_u16() == READ_RECEIPT_KIND {
+                    if json_event.pubkey != active_account.pubkey {
+                        if let Some(receipt) =
+                            ReadReceipt::record(&group, &json_event, wn.clone()).await?
+                        {
+                            app_handle
+                                .emit("read_receipt", receipt)
+                                .map_err(NostrManagerError::TauriError)?;
+                        }
+                    }
+                    return Ok(());
+                }
+
                 // Parse the content into tokens and ensure it's properly formatted
                 let tokens = parse(&json_event.content);
                 tracing::debug!(
@@ -558,6 +958,16 @@ impl EventProcessor {
                     )
                     .await?;
 
+                semantics = message.semantics.clone();
+                parent = match message.reply_to {
+                    Some(parent_id) => Message::find_by_event_id(parent_id, wn.clone()).await.ok(),
+                    None => None,
+                };
+
+                if message.event_kind == REACTION_KIND {
+                    Self::emit_reaction_received(app_handle, &group, &message).await?;
+                }
+
                 app_handle
                     .emit("mls_message_processed", (group.clone(), message.clone()))
                     .expect("Couldn't emit event");
@@ -581,14 +991,22 @@ impl EventProcessor {
             }
         }
 
+        // Messages of muted groups are announced like any other, so open chats and mention badges
+        // stay up to date. Muting only silences notifications, see `notifications::evaluate`.
+        let received = MlsMessageReceivedEvent {
+            group_id: group.mls_group_id.clone(),
+            event: json_event.clone(),
+            semantics,
+            parent,
+        };
+        // Mentions of the active account are announced separately too, so they can be highlighted
+        if received.semantics.mentions_me && json_event.pubkey != active_account.pubkey {
+            app_handle
+                .emit("mls_mention_received", received.clone())
+                .map_err(NostrManagerError::TauriError)?;
+        }
         app_handle
-            .emit(
-                "mls_message_received",
-                MlsMessageReceivedEvent {
-                    group_id: group.mls_group_id.clone(),
-                    event: json_event.clone(),
-                },
-            )
+            .emit("mls_message_received", received)
             .map_err(NostrManagerError::TauriError)?;
         Ok(())
     }
@@ -3,11 +3,51 @@
 //! In almost all cases, we query for events already stored in our databsae
 //! and combine the results from our database with those from relays in the response.
 
+use crate::key_migrations::KEY_MIGRATION_KIND;
 use crate::nostr_manager::event_processor::ProcessableEvent;
 use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use crate::profiling::{self, OperationKind};
+use crate::relay_blacklist::RelayBlacklist;
 use nostr_sdk::prelude::*;
+use std::time::Duration;
 
 impl NostrManager {
+    /// Fetches events from relays someone else named, e.g. in an invite link, with a short-lived
+    /// client of its own so they never join the pool. Blacklisted relays are skipped and the rest
+    /// are queried at the same time, within `timeout` overall. Relays that can't be reached
+    /// contribute nothing.
+    pub async fn fetch_from_foreign_relays(
+        relays: &[String],
+        filter: Filter,
+        blacklist: &RelayBlacklist,
+        timeout: Duration,
+    ) -> Result<Vec<Event>> {
+        let client = Client::default();
+        let mut added = false;
+        for relay in blacklist.filter(relays.to_vec()) {
+            match client.add_read_relay(&relay).await {
+                Ok(_) => added = true,
+                Err(e) => tracing::debug!(
+                    target: "whitenoise::nostr_manager::fetch::fetch_from_foreign_relays",
+                    "Skipping relay {}: {}",
+                    relay,
+                    e
+                ),
+            }
+        }
+        if !added {
+            return Ok(Vec::new());
+        }
+
+        client.connect().await;
+        let result = tokio::time::timeout(timeout, client.fetch_events(filter, timeout)).await;
+        client.shutdown().await;
+        match result {
+            Ok(events) => Ok(events?.into_iter().collect()),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
     pub async fn fetch_for_user(
         &self,
         pubkey: PublicKey,
@@ -16,6 +56,7 @@ impl NostrManager {
     ) -> Result<()> {
         self.fetch_user_metadata(pubkey).await?;
         self.fetch_contacts().await?;
+        self.fetch_contacts_key_migrations().await?;
         self.fetch_user_relays(pubkey).await?;
         self.fetch_user_inbox_relays(pubkey).await?;
         self.fetch_user_key_package_relays(pubkey).await?;
@@ -37,6 +78,19 @@ impl NostrManager {
     }
 
     pub async fn fetch_user_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
+        Ok(self
+            .fetch_user_relay_list(pubkey)
+            .await?
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect())
+    }
+
+    /// Fetches the user's NIP-65 relay list with the read/write marker of each relay
+    pub async fn fetch_user_relay_list(
+        &self,
+        pubkey: PublicKey,
+    ) -> Result<Vec<(String, Option<RelayMetadata>)>> {
         let filter = Filter::new().author(pubkey).kind(Kind::RelayList).limit(1);
 
         let events = self
@@ -45,7 +99,7 @@ impl NostrManager {
             .await
             .map_err(NostrManagerError::from)?;
 
-        Ok(Self::relay_urls_from_events(events))
+        Ok(Self::relay_list_from_events(events))
     }
 
     pub async fn fetch_user_inbox_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
@@ -108,7 +162,35 @@ impl NostrManager {
         Ok(contacts.into_iter().collect())
     }
 
-    async fn fetch_user_giftwrapped_events(&self, pubkey: PublicKey) -> Result<Vec<Event>> {
+    /// Fetches key migration statements published by our contacts and queues them for processing
+    async fn fetch_contacts_key_migrations(&self) -> Result<()> {
+        let contacts_pubkeys = self
+            .client
+            .get_contact_list_public_keys(self.timeout().await?)
+            .await?;
+        if contacts_pubkeys.is_empty() {
+            return Ok(());
+        }
+
+        let filter = Filter::new()
+            .kind(Kind::Custom(KEY_MIGRATION_KIND))
+            .authors(contacts_pubkeys);
+        let events = self
+            .client
+            .fetch_events(filter, self.timeout().await?)
+            .await?;
+
+        for event in events.into_iter() {
+            let processor = self.event_processor.lock().await;
+            processor
+                .queue_event(ProcessableEvent::KeyMigration(event))
+                .await
+                .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    pub async fn fetch_user_giftwrapped_events(&self, pubkey: PublicKey) -> Result<Vec<Event>> {
         let filter = Filter::new().kind(Kind::GiftWrap).pubkey(pubkey);
         let stored_events = self.client.database().query(filter.clone()).await?;
         let fetched_events = self
@@ -139,21 +221,31 @@ impl NostrManager {
             .until(Timestamp::now());
 
         let stored_events = self.client.database().query(filter.clone()).await?;
-        let fetched_events = self
-            .client
-            .fetch_events(filter, self.timeout().await?)
-            .await?;
+        let fetched_events = profiling::time_async(
+            "relay.fetch_group_messages",
+            OperationKind::Relay,
+            self.client.fetch_events(filter, self.timeout().await?),
+        )
+        .await?;
 
         let events = stored_events.merge(fetched_events);
+        self.queue_group_messages(events.iter().cloned()).await?;
 
-        for event in events.iter() {
+        Ok(events.into_iter().collect())
+    }
+
+    /// Queues group messages for processing, behind the events already queued
+    pub async fn queue_group_messages(
+        &self,
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<()> {
+        for event in events {
             let processor = self.event_processor.lock().await;
             processor
-                .queue_event(ProcessableEvent::MlsMessage(event.clone()))
+                .queue_event(ProcessableEvent::MlsMessage(event))
                 .await
                 .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
         }
-
-        Ok(events.into_iter().collect())
+        Ok(())
     }
 }
@@ -1,6 +1,12 @@
 use crate::accounts::Account;
 use crate::media::blossom::BlossomClient;
 use crate::nostr_manager::event_processor::EventProcessor;
+use crate::nostr_manager::relay_monitor::RelayMonitor;
+use crate::profiling::{self, OperationKind};
+use crate::relay_blacklist::RelayBlacklist;
+use crate::relays;
+use crate::runtime_state::{LockSnapshot, TrackedMutex};
+use crate::sync_throttle;
 use crate::types::NostrEncryptionMethod;
 use crate::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -15,6 +21,8 @@ pub mod event_processor;
 pub mod fetch;
 pub mod parser;
 pub mod query;
+pub mod relay_monitor;
+pub mod relay_rejections;
 pub mod search;
 pub mod subscriptions;
 pub mod sync;
@@ -49,7 +57,8 @@ pub struct NostrManager {
     pub client: Client,
     pub blossom: BlossomClient,
     pub settings: Arc<Mutex<NostrManagerSettings>>,
-    event_processor: Arc<Mutex<EventProcessor>>,
+    pub relay_monitor: RelayMonitor,
+    event_processor: Arc<TrackedMutex<EventProcessor>>,
 }
 
 impl Default for NostrManagerSettings {
@@ -114,16 +123,36 @@ impl NostrManager {
         // Connect to the default relays
         client.connect().await;
 
-        let event_processor = Arc::new(Mutex::new(EventProcessor::new(app_handle)));
+        let event_processor = Arc::new(TrackedMutex::new(
+            "event_processor",
+            EventProcessor::new(app_handle),
+        ));
 
         Ok(Self {
             client,
             blossom,
             settings: Arc::new(Mutex::new(settings)),
+            relay_monitor: RelayMonitor::default(),
             event_processor,
         })
     }
 
+    /// The event processor's lock, and how many events wait to be processed unless the lock is
+    /// held
+    pub fn event_processor_state(&self) -> (LockSnapshot, Option<usize>) {
+        let lock = self.event_processor.snapshot();
+        let queue_depth = self
+            .event_processor
+            .try_lock()
+            .map(|processor| processor.queue_depth());
+        (lock, queue_depth)
+    }
+
+    /// How many events wait to be processed
+    pub async fn queue_depth(&self) -> usize {
+        self.event_processor.lock().await.queue_depth()
+    }
+
     pub async fn timeout(&self) -> Result<Duration> {
         let guard = self.settings.lock().await;
         Ok(guard.timeout)
@@ -209,21 +238,66 @@ impl NostrManager {
         );
         self.client.set_signer(keys.clone()).await;
 
-        // Add the default relays
-        tracing::debug!(
-            target: "whitenoise::nostr_manager::set_nostr_identity",
-            "Adding default relays"
-        );
-        for relay in self.relays().await? {
-            self.client.add_relay(relay).await?;
+        // The account's own relays replace the default ones, so that a relay removed from the
+        // account doesn't come back on the next login
+        let has_own_relays = match relays::list(account, wn.clone()).await {
+            Ok(relays) => !relays.is_empty(),
+            Err(e) => {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::set_nostr_identity",
+                    "Error loading account relays: {}",
+                    e
+                );
+                false
+            }
+        };
+        let blacklist = match RelayBlacklist::load(wn.clone()).await {
+            Ok(blacklist) => blacklist,
+            Err(e) => {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::set_nostr_identity",
+                    "Error loading relay blacklist: {}",
+                    e
+                );
+                RelayBlacklist::default()
+            }
+        };
+
+        if !has_own_relays {
+            // Add the default relays
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::set_nostr_identity",
+                "Adding default relays"
+            );
+            for relay in blacklist.filter(self.relays().await?) {
+                self.client.add_relay(relay).await?;
+            }
+
+            // Connect to the default relays
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::set_nostr_identity",
+                "Connecting to default relays"
+            );
+            self.client.connect().await;
         }
 
-        // Connect to the default relays
-        tracing::debug!(
-            target: "whitenoise::nostr_manager::set_nostr_identity",
-            "Connecting to default relays"
-        );
-        self.client.connect().await;
+        // Connect to the relays configured for the account
+        if let Err(e) = relays::connect_account_relays(account, wn.clone()).await {
+            tracing::error!(
+                target: "whitenoise::nostr_manager::set_nostr_identity",
+                "Error connecting to account relays: {}",
+                e
+            );
+        }
+
+        // Connect to the relays of the account's groups
+        if let Err(e) = relays::connect_group_relays(account, wn.clone()).await {
+            tracing::error!(
+                target: "whitenoise::nostr_manager::set_nostr_identity",
+                "Error connecting to group relays: {}",
+                e
+            );
+        }
 
         // We only want to connect to user relays in release mode
         if !cfg!(dev) {
@@ -231,10 +305,13 @@ impl NostrManager {
                 target: "whitenoise::nostr_manager::set_nostr_identity",
                 "Setting up user-specific relays"
             );
-            // Add the new user's relays
-            // TODO: We should query first and only fetch if we don't have them
-            let relays = self.fetch_user_relays(keys.public_key()).await?;
-            for relay in relays.iter() {
+            // Add the new user's relays, unless the account already has its own
+            let relays = if has_own_relays {
+                Vec::new()
+            } else {
+                self.fetch_user_relays(keys.public_key()).await?
+            };
+            for relay in blacklist.filter(relays).iter() {
                 self.client.add_relay(relay).await?;
                 self.client.connect_relay(relay).await?;
                 tracing::debug!(
@@ -247,7 +324,7 @@ impl NostrManager {
             // Add the new user's inbox relays
             // TODO: We should query first and only fetch if we don't have them
             let inbox_relays = self.fetch_user_inbox_relays(keys.public_key()).await?;
-            for relay in inbox_relays.iter() {
+            for relay in blacklist.filter(inbox_relays).iter() {
                 self.client.add_read_relay(relay).await?;
                 self.client.connect_relay(relay).await?;
                 tracing::debug!(
@@ -262,7 +339,7 @@ impl NostrManager {
             let key_package_relays = self
                 .fetch_user_key_package_relays(keys.public_key())
                 .await?;
-            for relay in key_package_relays.iter() {
+            for relay in blacklist.filter(key_package_relays).iter() {
                 self.client.add_relay(relay).await?;
                 self.client.connect_relay(relay).await?;
                 tracing::debug!(
@@ -309,9 +386,13 @@ impl NostrManager {
                 .await
                 .expect("Couldn't get nostr group ids");
 
+            let contact_subscriptions = sync_throttle::current_mode(&wn_state)
+                .await
+                .contact_subscriptions();
+
             match wn_state
                 .nostr
-                .setup_subscriptions(account_clone_subs.pubkey, group_ids)
+                .setup_subscriptions(account_clone_subs.pubkey, group_ids, contact_subscriptions)
                 .await
             {
                 Ok(_) => {
@@ -389,24 +470,26 @@ impl NostrManager {
     pub async fn encrypt_content(
         &self,
         content: String,
-        pubkey: String,
+        recipient_pubkey: &PublicKey,
         method: NostrEncryptionMethod,
     ) -> Result<String> {
-        let recipient_pubkey = PublicKey::from_hex(&pubkey).unwrap();
         let signer = self.client.signer().await.unwrap();
         match method {
             NostrEncryptionMethod::Nip04 => {
                 let encrypted = signer
-                    .nip04_encrypt(&recipient_pubkey, &content)
+                    .nip04_encrypt(recipient_pubkey, &content)
                     .await
                     .unwrap();
                 Ok(encrypted)
             }
             NostrEncryptionMethod::Nip44 => {
-                let encrypted = signer
-                    .nip44_encrypt(&recipient_pubkey, &content)
-                    .await
-                    .unwrap();
+                let encrypted = profiling::time_async(
+                    "nip44.encrypt",
+                    OperationKind::Crypto,
+                    signer.nip44_encrypt(recipient_pubkey, &content),
+                )
+                .await
+                .unwrap();
                 Ok(encrypted)
             }
         }
@@ -415,24 +498,23 @@ impl NostrManager {
     pub async fn decrypt_content(
         &self,
         content: String,
-        pubkey: String,
+        author_pubkey: &PublicKey,
         method: NostrEncryptionMethod,
     ) -> Result<String> {
-        let author_pubkey = PublicKey::from_hex(&pubkey).unwrap();
         let signer = self.client.signer().await.unwrap();
         match method {
             NostrEncryptionMethod::Nip04 => {
-                let decrypted = signer
-                    .nip04_decrypt(&author_pubkey, &content)
-                    .await
-                    .unwrap();
+                let decrypted = signer.nip04_decrypt(author_pubkey, &content).await.unwrap();
                 Ok(decrypted)
             }
             NostrEncryptionMethod::Nip44 => {
-                let decrypted = signer
-                    .nip44_decrypt(&author_pubkey, &content)
-                    .await
-                    .unwrap();
+                let decrypted = profiling::time_async(
+                    "nip44.decrypt",
+                    OperationKind::Crypto,
+                    signer.nip44_decrypt(author_pubkey, &content),
+                )
+                .await
+                .unwrap();
                 Ok(decrypted)
             }
         }
@@ -447,6 +529,29 @@ impl NostrManager {
             .collect()
     }
 
+    /// The relays of the newest NIP-65 relay list among `events`, with their read/write markers
+    fn relay_list_from_events(events: Events) -> Vec<(String, Option<RelayMetadata>)> {
+        events
+            .into_iter()
+            .next()
+            .map(|event| {
+                nip65::extract_relay_list(&event)
+                    .map(|(url, metadata)| (url.to_string(), *metadata))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The relays of a NIP-65 relay list that its author reads from. Unmarked relays are both read
+    /// from and written to.
+    pub fn read_relays(relay_list: &[(String, Option<RelayMetadata>)]) -> Vec<String> {
+        relay_list
+            .iter()
+            .filter(|(_, metadata)| *metadata != Some(RelayMetadata::Write))
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
     pub async fn delete_all_data(&self) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_manager::delete_all_data",
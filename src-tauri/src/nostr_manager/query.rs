@@ -11,9 +11,29 @@ impl NostrManager {
 
     #[allow(dead_code)]
     pub async fn query_user_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
+        Ok(self
+            .query_user_relay_list(pubkey)
+            .await?
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect())
+    }
+
+    /// The user's cached NIP-65 relay list with the read/write marker of each relay
+    pub async fn query_user_relay_list(
+        &self,
+        pubkey: PublicKey,
+    ) -> Result<Vec<(String, Option<RelayMetadata>)>> {
         let filter = Filter::new().author(pubkey).kind(Kind::RelayList).limit(1);
         let events = self.client.database().query(filter).await?;
-        Ok(Self::relay_urls_from_events(events))
+        Ok(Self::relay_list_from_events(events))
+    }
+
+    /// The relays the user reads from according to their cached NIP-65 relay list
+    pub async fn query_user_read_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
+        Ok(Self::read_relays(
+            &self.query_user_relay_list(pubkey).await?,
+        ))
     }
 
     pub async fn query_user_inbox_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
@@ -44,7 +64,14 @@ impl NostrManager {
 
     pub async fn query_contact_list_pubkeys(&self) -> Result<Vec<PublicKey>> {
         let pubkey = self.client.signer().await?.get_public_key().await.unwrap();
+        self.query_contact_list_pubkeys_for(pubkey).await
+    }
 
+    /// Returns the contacts of any user whose contact list is in the local cache
+    pub async fn query_contact_list_pubkeys_for(
+        &self,
+        pubkey: PublicKey,
+    ) -> Result<Vec<PublicKey>> {
         let filter = Filter::new()
             .kind(Kind::ContactList)
             .author(pubkey)
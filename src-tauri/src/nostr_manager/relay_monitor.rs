@@ -0,0 +1,712 @@
+//! Connection health of the client's relays.
+//!
+//! A background task polls the relay pool and keeps, for every relay, its connection state, when
+//! it last sent us a message and how often it failed (dropped connections and rejected events or
+//! subscriptions). When a relay connects or drops, `relay_connected` or `relay_disconnected` is
+//! emitted with its [`RelayHealth`], which is also how the outbox learns that it can flush.
+//!
+//! Group messages we send are also timed until each relay echoes them back on our subscription,
+//! which gives every relay a delivery rate and latency. Relays below [`SLA_ECHO_RATE`] or above
+//! [`SLA_LATENCY`] over at least [`MIN_DELIVERIES`] messages are reported by
+//! [`RelayMonitor::recommendations`], with a better performing relay to use instead.
+//!
+//! Requests a relay refuses are recorded with their [`RelayRejection`]. A relay that rate limits
+//! us is backed off from, for a delay that doubles with every rate limit in a row, and
+//! [`RelayMonitor::without_backed_off`] drops it from the relays messages are published to until
+//! the delay is over. A relay that blocks us is reported as blocked until it accepts an event
+//! again. Both are emitted as `relay_rejected` when they start.
+
+use crate::nostr_manager::relay_rejections::{RejectionReason, RelayRejection};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the relay pool is polled for connection changes
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to wait for a relay to echo a message back before counting it as not delivered
+const ECHO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Share of messages a relay has to echo back to meet the delivery SLA
+pub const SLA_ECHO_RATE: f64 = 0.9;
+
+/// Average echo latency a relay has to stay under to meet the delivery SLA
+pub const SLA_LATENCY: Duration = Duration::from_secs(3);
+
+/// Messages a relay has to have been sent before it's judged against the SLA
+pub const MIN_DELIVERIES: u64 = 10;
+
+/// How long to back off from a relay the first time it rate limits us; doubled every time it
+/// does so again right after a backoff
+const BASE_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Upper bound for backing off from a rate limiting relay
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// Connection state of a relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+impl From<RelayStatus> for ConnectionState {
+    fn from(status: RelayStatus) -> Self {
+        match status {
+            RelayStatus::Connected => Self::Connected,
+            RelayStatus::Pending | RelayStatus::Connecting => Self::Connecting,
+            _ => Self::Disconnected,
+        }
+    }
+}
+
+/// Health of one relay. Payload of `relay_connected` and `relay_disconnected`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayHealth {
+    pub url: String,
+    pub state: ConnectionState,
+    /// When the current connection was established
+    pub connected_since: Option<u64>,
+    /// When the relay last sent an event or message
+    pub last_message_at: Option<u64>,
+    /// Dropped connections and rejected events or subscriptions since launch
+    pub error_count: u64,
+    /// Echoes of the group messages sent to the relay since launch
+    pub delivery: DeliveryStats,
+    /// The last request the relay refused, other than with `duplicate`
+    pub last_rejection: Option<RelayRejection>,
+    /// Until when nothing is published to the relay because it rate limited us
+    pub rate_limited_until: Option<u64>,
+    /// Why the relay refuses our events, if it blocked us and hasn't accepted an event since
+    pub blocked: Option<RelayRejection>,
+    /// Rate limits in a row, each right after the backoff of the previous one
+    #[serde(skip)]
+    rate_limit_strikes: u32,
+}
+
+impl RelayHealth {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            state: ConnectionState::Disconnected,
+            connected_since: None,
+            last_message_at: None,
+            error_count: 0,
+            delivery: DeliveryStats::default(),
+            last_rejection: None,
+            rate_limited_until: None,
+            blocked: None,
+            rate_limit_strikes: 0,
+        }
+    }
+
+    fn is_rate_limited(&self, now: Timestamp) -> bool {
+        self.rate_limited_until
+            .is_some_and(|until| until > now.as_u64())
+    }
+}
+
+/// Backoff after a relay rate limited us `strikes` times in a row
+fn rate_limit_backoff(strikes: u32) -> Duration {
+    BASE_RATE_LIMIT_BACKOFF
+        .saturating_mul(2u32.saturating_pow(strikes.saturating_sub(1)))
+        .min(MAX_RATE_LIMIT_BACKOFF)
+}
+
+/// A relay that started rate limiting or blocking us. Payload of `relay_rejected`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayRejectionEvent {
+    pub url: String,
+    pub rejection: RelayRejection,
+    /// Until when the relay is backed off from, if it rate limited us
+    pub rate_limited_until: Option<u64>,
+}
+
+/// How reliably and quickly a relay echoes back the messages sent to it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeliveryStats {
+    /// Messages that were echoed back or timed out
+    pub sent: u64,
+    /// Messages that were echoed back within the timeout
+    pub echoed: u64,
+    /// Average time from sending a message to its echo
+    pub average_latency_ms: Option<u64>,
+}
+
+impl DeliveryStats {
+    fn record_echo(&mut self, latency: Duration) {
+        let latency_ms = latency.as_millis() as u64;
+        let total = self.average_latency_ms.unwrap_or(0) * self.echoed + latency_ms;
+        self.sent += 1;
+        self.echoed += 1;
+        self.average_latency_ms = Some(total / self.echoed);
+    }
+
+    fn record_timeout(&mut self) {
+        self.sent += 1;
+    }
+
+    /// Share of the sent messages that were echoed back
+    pub fn echo_rate(&self) -> Option<f64> {
+        (self.sent > 0).then(|| self.echoed as f64 / self.sent as f64)
+    }
+
+    /// Whether there are enough messages to judge the relay by
+    fn is_measured(&self) -> bool {
+        self.sent >= MIN_DELIVERIES
+    }
+
+    /// Whether the relay misses the delivery SLA
+    pub fn is_underperforming(&self) -> bool {
+        self.is_measured()
+            && (self.echo_rate().unwrap_or(0.0) < SLA_ECHO_RATE
+                || self
+                    .average_latency_ms
+                    .is_some_and(|ms| ms > SLA_LATENCY.as_millis() as u64))
+    }
+}
+
+/// A relay of a group that misses the delivery SLA
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RelayRecommendation {
+    pub url: String,
+    pub delivery: DeliveryStats,
+    /// The best performing relay that meets the SLA and isn't one of the group's relays yet
+    pub replacement: Option<String>,
+}
+
+/// A change of a relay's connection worth telling the frontend about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayTransition {
+    Connected,
+    Disconnected,
+}
+
+impl RelayTransition {
+    pub fn event_name(self) -> &'static str {
+        match self {
+            RelayTransition::Connected => "relay_connected",
+            RelayTransition::Disconnected => "relay_disconnected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RelayMonitor {
+    relays: Arc<Mutex<BTreeMap<String, RelayHealth>>>,
+    /// When each message waiting for an echo was sent, by relay
+    pending_echoes: Arc<Mutex<HashMap<EventId, HashMap<String, Instant>>>>,
+    /// Rejections worth telling the frontend about, until the background task emits them
+    rejections: Arc<Mutex<Vec<RelayRejectionEvent>>>,
+}
+
+impl RelayMonitor {
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, RelayHealth>> {
+        self.relays.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Notes that a relay sent us an event or message
+    pub fn record_message(&self, relay_url: &RelayUrl) {
+        let url = relay_url.to_string();
+        self.lock()
+            .entry(url.clone())
+            .or_insert_with(|| RelayHealth::new(url))
+            .last_message_at = Some(Timestamp::now().as_u64());
+    }
+
+    /// Starts timing a message until the relays it's sent to echo it back
+    pub fn track_delivery(&self, event_id: EventId, relay_urls: &[String]) {
+        self.track_delivery_at(event_id, relay_urls, Instant::now());
+    }
+
+    fn track_delivery_at(&self, event_id: EventId, relay_urls: &[String], now: Instant) {
+        let mut pending = self
+            .pending_echoes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let relays = pending.entry(event_id).or_default();
+        for url in relay_urls {
+            // Keyed like the pool, which normalizes URLs
+            let url = RelayUrl::parse(url)
+                .map(|url| url.to_string())
+                .unwrap_or_else(|_| url.clone());
+            // A resend doesn't restart the clock
+            relays.entry(url).or_insert(now);
+        }
+    }
+
+    /// Notes that a relay sent us an event, which may be the echo of a message we sent
+    pub fn record_echo(&self, relay_url: &RelayUrl, event_id: &EventId) {
+        self.record_echo_at(relay_url, event_id, Instant::now());
+    }
+
+    fn record_echo_at(&self, relay_url: &RelayUrl, event_id: &EventId, now: Instant) {
+        let url = relay_url.to_string();
+        let sent_at = {
+            let mut pending = self
+                .pending_echoes
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let Some(relays) = pending.get_mut(event_id) else {
+                return;
+            };
+            let sent_at = relays.remove(&url);
+            if relays.is_empty() {
+                pending.remove(event_id);
+            }
+            sent_at
+        };
+        if let Some(sent_at) = sent_at {
+            self.lock()
+                .entry(url.clone())
+                .or_insert_with(|| RelayHealth::new(url))
+                .delivery
+                .record_echo(now.saturating_duration_since(sent_at));
+        }
+    }
+
+    /// Counts the messages that weren't echoed back within [`ECHO_TIMEOUT`] as not delivered
+    pub fn expire_deliveries(&self) {
+        self.expire_deliveries_at(Instant::now());
+    }
+
+    fn expire_deliveries_at(&self, now: Instant) {
+        let mut expired = Vec::new();
+        self.pending_echoes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, relays| {
+                relays.retain(|url, sent_at| {
+                    let is_expired = now.saturating_duration_since(*sent_at) >= ECHO_TIMEOUT;
+                    if is_expired {
+                        expired.push(url.clone());
+                    }
+                    !is_expired
+                });
+                !relays.is_empty()
+            });
+
+        let mut relays = self.lock();
+        for url in expired {
+            relays
+                .entry(url.clone())
+                .or_insert_with(|| RelayHealth::new(url))
+                .delivery
+                .record_timeout();
+        }
+    }
+
+    /// The relays of a group that miss the delivery SLA, each with a relay to use instead
+    pub fn recommendations(&self, group_relays: &[String]) -> Vec<RelayRecommendation> {
+        let group_relays: Vec<String> = group_relays
+            .iter()
+            .map(|url| {
+                RelayUrl::parse(url)
+                    .map(|url| url.to_string())
+                    .unwrap_or_else(|_| url.clone())
+            })
+            .collect();
+        let relays = self.lock();
+
+        // Fewest missed echoes first, then the fastest
+        let mut candidates: Vec<&RelayHealth> = relays
+            .values()
+            .filter(|health| {
+                !group_relays.contains(&health.url)
+                    && health.delivery.is_measured()
+                    && !health.delivery.is_underperforming()
+            })
+            .collect();
+        candidates.sort_by_key(|health| {
+            (
+                health.delivery.sent - health.delivery.echoed,
+                health.delivery.average_latency_ms,
+            )
+        });
+        let replacement = candidates.first().map(|health| health.url.clone());
+
+        group_relays
+            .iter()
+            .filter_map(|url| relays.get(url))
+            .filter(|health| health.delivery.is_underperforming())
+            .map(|health| RelayRecommendation {
+                url: health.url.clone(),
+                delivery: health.delivery.clone(),
+                replacement: replacement.clone(),
+            })
+            .collect()
+    }
+
+    /// Notes that a relay rejected an event or closed a subscription
+    pub fn record_error(&self, relay_url: &RelayUrl) {
+        let url = relay_url.to_string();
+        self.lock()
+            .entry(url.clone())
+            .or_insert_with(|| RelayHealth::new(url))
+            .error_count += 1;
+    }
+
+    /// Notes that a relay refused a request, backing off from it if it rate limited us. Rate
+    /// limits and blocks that just started are queued for `relay_rejected`.
+    pub fn record_rejection(&self, relay_url: &RelayUrl, rejection: RelayRejection) {
+        self.record_rejection_at(relay_url, rejection, Timestamp::now());
+    }
+
+    fn record_rejection_at(&self, relay_url: &RelayUrl, rejection: RelayRejection, now: Timestamp) {
+        if rejection.reason == RejectionReason::Duplicate {
+            return;
+        }
+        let url = relay_url.to_string();
+        let event = {
+            let mut relays = self.lock();
+            let health = relays
+                .entry(url.clone())
+                .or_insert_with(|| RelayHealth::new(url.clone()));
+            health.last_rejection = Some(rejection.clone());
+
+            let started = if rejection.reason == RejectionReason::RateLimited {
+                // Rejections of requests sent before the backoff started don't extend it
+                let started = !health.is_rate_limited(now);
+                if started {
+                    health.rate_limit_strikes += 1;
+                    health.rate_limited_until = Some(
+                        now.as_u64() + rate_limit_backoff(health.rate_limit_strikes).as_secs(),
+                    );
+                }
+                started
+            } else if rejection.reason.is_block() {
+                health.blocked.replace(rejection.clone()).is_none()
+            } else {
+                false
+            };
+            started.then(|| RelayRejectionEvent {
+                url,
+                rejection,
+                rate_limited_until: health.rate_limited_until,
+            })
+        };
+
+        if let Some(event) = event {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::relay_monitor::record_rejection",
+                "{}: {}",
+                event.url,
+                event.rejection
+            );
+            self.rejections
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(event);
+        }
+    }
+
+    /// Notes that a relay accepted an event, which ends a block and the run of rate limits
+    pub fn record_accepted(&self, relay_url: &RelayUrl) {
+        if let Some(health) = self.lock().get_mut(&relay_url.to_string()) {
+            health.blocked = None;
+            health.rate_limit_strikes = 0;
+        }
+    }
+
+    /// Removes the relays we're backing off from after they rate limited us from a set of
+    /// candidate relays
+    pub fn without_backed_off(&self, relays: Vec<String>) -> Vec<String> {
+        self.without_backed_off_at(relays, Timestamp::now())
+    }
+
+    fn without_backed_off_at(&self, relays: Vec<String>, now: Timestamp) -> Vec<String> {
+        let health = self.lock();
+        relays
+            .into_iter()
+            .filter(|url| {
+                // Keyed like the pool, which normalizes URLs
+                let key = RelayUrl::parse(url)
+                    .map(|url| url.to_string())
+                    .unwrap_or_else(|_| url.clone());
+                !health
+                    .get(&key)
+                    .is_some_and(|health| health.is_rate_limited(now))
+            })
+            .collect()
+    }
+
+    /// Takes the rejections queued for `relay_rejected`
+    pub fn take_rejections(&self) -> Vec<RelayRejectionEvent> {
+        std::mem::take(&mut *self.rejections.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Updates the state of a relay, returning the transition if it connected or dropped
+    pub fn update(
+        &self,
+        url: &str,
+        state: ConnectionState,
+        now: Timestamp,
+    ) -> Option<(RelayTransition, RelayHealth)> {
+        let mut relays = self.lock();
+        let health = relays
+            .entry(url.to_string())
+            .or_insert_with(|| RelayHealth::new(url.to_string()));
+        let previous = health.state;
+        health.state = state;
+
+        let transition = match (previous, state) {
+            (ConnectionState::Connected, ConnectionState::Connected) => None,
+            (_, ConnectionState::Connected) => {
+                health.connected_since = Some(now.as_u64());
+                Some(RelayTransition::Connected)
+            }
+            (ConnectionState::Connected, _) => {
+                health.connected_since = None;
+                health.error_count += 1;
+                Some(RelayTransition::Disconnected)
+            }
+            _ => None,
+        };
+        transition.map(|transition| (transition, health.clone()))
+    }
+
+    /// Forgets relays that are no longer in the pool
+    pub fn retain(&self, urls: &[String]) {
+        self.lock().retain(|url, _| urls.contains(url));
+    }
+
+    /// The health of every known relay, sorted by URL
+    pub fn statuses(&self) -> Vec<RelayHealth> {
+        self.lock().values().cloned().collect()
+    }
+
+    /// Whether at least one relay is connected
+    pub fn is_online(&self) -> bool {
+        self.lock()
+            .values()
+            .any(|health| health.state == ConnectionState::Connected)
+    }
+
+    /// Polls the relay pool and returns the relays that connected or dropped since the last poll
+    pub async fn refresh(&self, client: &Client) -> Vec<(RelayTransition, RelayHealth)> {
+        let now = Timestamp::now();
+        let pool = client.relays().await;
+        let urls: Vec<String> = pool.keys().map(|url| url.to_string()).collect();
+        self.retain(&urls);
+
+        pool.iter()
+            .filter_map(|(url, relay)| self.update(&url.to_string(), relay.status().into(), now))
+            .collect()
+    }
+}
+
+/// Starts the background task that polls relay connections and emits their changes
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let wn = app_handle.state::<Whitenoise>();
+            wn.nostr.relay_monitor.expire_deliveries();
+
+            for (transition, health) in wn.nostr.relay_monitor.refresh(&wn.nostr.client).await {
+                tracing::debug!(
+                    target: "whitenoise::nostr_manager::relay_monitor::start",
+                    "Relay {}: {}",
+                    health.url,
+                    transition.event_name()
+                );
+                if let Err(e) = app_handle.emit(transition.event_name(), health) {
+                    tracing::error!(
+                        target: "whitenoise::nostr_manager::relay_monitor::start",
+                        "Failed to emit {}: {}",
+                        transition.event_name(),
+                        e
+                    );
+                }
+            }
+
+            for rejection in wn.nostr.relay_monitor.take_rejections() {
+                if let Err(e) = app_handle.emit("relay_rejected", rejection) {
+                    tracing::error!(
+                        target: "whitenoise::nostr_manager::relay_monitor::start",
+                        "Failed to emit relay_rejected: {}",
+                        e
+                    );
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "wss://relay.example.com";
+
+    #[test]
+    fn test_transitions_are_reported_once() {
+        let monitor = RelayMonitor::default();
+        let now = Timestamp::from(1_700_000_000);
+
+        assert!(monitor
+            .update(URL, ConnectionState::Connecting, now)
+            .is_none());
+        let (transition, health) = monitor
+            .update(URL, ConnectionState::Connected, now)
+            .unwrap();
+        assert_eq!(transition, RelayTransition::Connected);
+        assert_eq!(health.connected_since, Some(1_700_000_000));
+        assert!(monitor
+            .update(URL, ConnectionState::Connected, now)
+            .is_none());
+        assert!(monitor.is_online());
+
+        let (transition, health) = monitor
+            .update(URL, ConnectionState::Disconnected, now)
+            .unwrap();
+        assert_eq!(transition, RelayTransition::Disconnected);
+        assert_eq!(health.error_count, 1);
+        assert_eq!(health.connected_since, None);
+        assert!(!monitor.is_online());
+    }
+
+    #[test]
+    fn test_never_connected_relays_dont_disconnect() {
+        let monitor = RelayMonitor::default();
+        assert!(monitor
+            .update(URL, ConnectionState::Disconnected, Timestamp::now())
+            .is_none());
+        assert_eq!(monitor.statuses()[0].error_count, 0);
+    }
+
+    #[test]
+    fn test_removed_relays_are_forgotten() {
+        let monitor = RelayMonitor::default();
+        monitor.update(URL, ConnectionState::Connected, Timestamp::now());
+        monitor.update(
+            "wss://other.example.com",
+            ConnectionState::Connected,
+            Timestamp::now(),
+        );
+        monitor.retain(&[URL.to_string()]);
+        assert_eq!(monitor.statuses().len(), 1);
+    }
+
+    #[test]
+    fn test_echoes_are_timed_per_relay() {
+        let monitor = RelayMonitor::default();
+        let event_id = EventId::from_slice(&[0; 32]).unwrap();
+        let relay = RelayUrl::parse(URL).unwrap();
+        let sent_at = Instant::now();
+        monitor.track_delivery_at(
+            event_id,
+            &[URL.to_string(), "wss://other.example.com".to_string()],
+            sent_at,
+        );
+
+        monitor.record_echo_at(&relay, &event_id, sent_at + Duration::from_millis(400));
+        // A second echo of the same event isn't counted again
+        monitor.record_echo_at(&relay, &event_id, sent_at + Duration::from_millis(900));
+        monitor.expire_deliveries_at(sent_at + ECHO_TIMEOUT);
+
+        let statuses = monitor.statuses();
+        let echoed = statuses
+            .iter()
+            .find(|h| h.url == relay.to_string())
+            .unwrap();
+        assert_eq!(echoed.delivery.sent, 1);
+        assert_eq!(echoed.delivery.echoed, 1);
+        assert_eq!(echoed.delivery.average_latency_ms, Some(400));
+        let missed = statuses
+            .iter()
+            .find(|h| h.url == "wss://other.example.com")
+            .unwrap();
+        assert_eq!(missed.delivery.sent, 1);
+        assert_eq!(missed.delivery.echo_rate(), Some(0.0));
+    }
+
+    #[test]
+    fn test_rate_limited_relays_are_backed_off_from() {
+        let monitor = RelayMonitor::default();
+        let relay = RelayUrl::parse(URL).unwrap();
+        let now = Timestamp::from(1_700_000_000);
+        let rate_limited = || RelayRejection::parse("rate-limited: slow down");
+
+        monitor.record_rejection_at(&relay, rate_limited(), now);
+        let until = now.as_u64() + BASE_RATE_LIMIT_BACKOFF.as_secs();
+        assert_eq!(monitor.statuses()[0].rate_limited_until, Some(until));
+        assert!(monitor
+            .without_backed_off_at(vec![URL.to_string()], now)
+            .is_empty());
+        assert_eq!(monitor.take_rejections().len(), 1);
+
+        // Rejections during the backoff don't extend it
+        monitor.record_rejection_at(&relay, rate_limited(), now + 1);
+        assert_eq!(monitor.statuses()[0].rate_limited_until, Some(until));
+        assert!(monitor.take_rejections().is_empty());
+
+        // Once it's over the relay is published to again, and a new rate limit doubles it
+        let later = Timestamp::from(until);
+        assert_eq!(
+            monitor.without_backed_off_at(vec![URL.to_string()], later),
+            vec![URL.to_string()]
+        );
+        monitor.record_rejection_at(&relay, rate_limited(), later);
+        assert_eq!(
+            monitor.statuses()[0].rate_limited_until,
+            Some(until + 2 * BASE_RATE_LIMIT_BACKOFF.as_secs())
+        );
+        assert_eq!(rate_limit_backoff(20), MAX_RATE_LIMIT_BACKOFF);
+    }
+
+    #[test]
+    fn test_blocks_last_until_an_event_is_accepted() {
+        let monitor = RelayMonitor::default();
+        let relay = RelayUrl::parse(URL).unwrap();
+        let now = Timestamp::now();
+
+        monitor.record_rejection_at(&relay, RelayRejection::parse("duplicate: have it"), now);
+        assert!(monitor.statuses().is_empty());
+
+        monitor.record_rejection_at(&relay, RelayRejection::parse("blocked: no 445s"), now);
+        monitor.record_rejection_at(&relay, RelayRejection::parse("blocked: no 445s"), now);
+        assert_eq!(monitor.take_rejections().len(), 1);
+        assert_eq!(
+            monitor.statuses()[0].blocked.as_ref().map(|r| r.reason),
+            Some(RejectionReason::Blocked)
+        );
+
+        monitor.record_accepted(&relay);
+        assert!(monitor.statuses()[0].blocked.is_none());
+        assert!(monitor.statuses()[0].last_rejection.is_some());
+    }
+
+    #[test]
+    fn test_underperforming_relays_are_recommended_for_replacement() {
+        let monitor = RelayMonitor::default();
+        let slow = RelayUrl::parse("wss://slow.example.com").unwrap();
+        let fast = RelayUrl::parse("wss://fast.example.com").unwrap();
+        let sent_at = Instant::now();
+        for i in 0..MIN_DELIVERIES {
+            let event_id = EventId::from_slice(&[i as u8; 32]).unwrap();
+            monitor.track_delivery_at(event_id, &[slow.to_string(), fast.to_string()], sent_at);
+            monitor.record_echo_at(&slow, &event_id, sent_at + SLA_LATENCY * 2);
+            monitor.record_echo_at(&fast, &event_id, sent_at + Duration::from_millis(100));
+        }
+
+        let recommendations = monitor.recommendations(&["wss://slow.example.com".to_string()]);
+        assert_eq!(recommendations.len(), 1);
+        assert_eq!(recommendations[0].url, slow.to_string());
+        assert_eq!(recommendations[0].replacement, Some(fast.to_string()));
+
+        // Relays without enough messages aren't judged
+        let monitor = RelayMonitor::default();
+        let event_id = EventId::from_slice(&[0; 32]).unwrap();
+        monitor.track_delivery_at(event_id, &[slow.to_string()], sent_at);
+        monitor.expire_deliveries_at(sent_at + ECHO_TIMEOUT);
+        assert!(monitor.recommendations(&[slow.to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! Machine-readable reasons relays give for turning us down.
+//!
+//! NIP-01 relays prefix the message of a failed `OK` or a `CLOSED` with why they refused the
+//! request, e.g. `rate-limited: slow down`. `NOTICE` messages are free-form, but relays that
+//! complain through them tend to use the same prefixes. [`RelayRejection::parse`] maps the prefix
+//! to a [`RejectionReason`] so callers can tell a relay that wants us to slow down from one that
+//! will never take our events.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Why a relay refused a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    /// The relay already has the event
+    Duplicate,
+    /// The event doesn't have enough proof of work
+    Pow,
+    /// The relay refuses our pubkey, IP or the event kind
+    Blocked,
+    /// We send too much, too fast
+    RateLimited,
+    /// The event or filter is malformed
+    Invalid,
+    /// Writing needs permissions we don't have, e.g. a paid membership
+    Restricted,
+    /// The relay wants us to authenticate first (NIP-42)
+    AuthRequired,
+    /// The relay failed on its side
+    Error,
+    /// The message has no prefix we know
+    Other,
+}
+
+impl RejectionReason {
+    fn from_prefix(prefix: &str) -> Self {
+        match prefix {
+            "duplicate" => Self::Duplicate,
+            "pow" => Self::Pow,
+            "blocked" => Self::Blocked,
+            "rate-limited" => Self::RateLimited,
+            "invalid" => Self::Invalid,
+            "restricted" => Self::Restricted,
+            "auth-required" => Self::AuthRequired,
+            "error" => Self::Error,
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether the relay won't take our events no matter how often we retry
+    pub fn is_block(self) -> bool {
+        matches!(self, Self::Blocked | Self::Restricted)
+    }
+}
+
+/// A request a relay refused, with the message it gave
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[error("Relay refused the request ({reason:?}): {message}")]
+pub struct RelayRejection {
+    pub reason: RejectionReason,
+    pub message: String,
+}
+
+impl RelayRejection {
+    /// Parses the message of a failed `OK` or a `CLOSED`. Prefixes are matched case-insensitively.
+    pub fn parse(message: &str) -> Self {
+        let message = message.trim();
+        let reason = message
+            .split_once(':')
+            .map(|(prefix, _)| RejectionReason::from_prefix(&prefix.trim().to_lowercase()))
+            .unwrap_or(RejectionReason::Other);
+        Self {
+            reason,
+            message: message.to_string(),
+        }
+    }
+
+    /// Parses a `NOTICE`. Unlike `OK` and `CLOSED` messages most notices are just chatter, so only
+    /// the ones with a known prefix count as rejections.
+    pub fn parse_notice(message: &str) -> Option<Self> {
+        Some(Self::parse(message)).filter(|rejection| rejection.reason != RejectionReason::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefixes_are_parsed() {
+        let rejection = RelayRejection::parse("rate-limited: slow down there chief");
+        assert_eq!(rejection.reason, RejectionReason::RateLimited);
+        assert_eq!(rejection.message, "rate-limited: slow down there chief");
+
+        assert_eq!(
+            RelayRejection::parse(" BLOCKED: kind 445 not accepted").reason,
+            RejectionReason::Blocked
+        );
+        assert_eq!(
+            RelayRejection::parse("auth-required: we only accept events from members").reason,
+            RejectionReason::AuthRequired
+        );
+        assert_eq!(
+            RelayRejection::parse("could not connect").reason,
+            RejectionReason::Other
+        );
+        assert_eq!(
+            RelayRejection::parse("shrug: unknown prefix").reason,
+            RejectionReason::Other
+        );
+    }
+
+    #[test]
+    fn test_only_prefixed_notices_are_rejections() {
+        assert!(RelayRejection::parse_notice("welcome to the relay!").is_none());
+        assert_eq!(
+            RelayRejection::parse_notice("rate-limited: too many subscriptions")
+                .unwrap()
+                .reason,
+            RejectionReason::RateLimited
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert!(RejectionReason::Blocked.is_block());
+        assert!(RejectionReason::Restricted.is_block());
+        assert!(!RejectionReason::RateLimited.is_block());
+        assert!(!RejectionReason::Duplicate.is_block());
+    }
+}
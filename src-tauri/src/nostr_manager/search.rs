@@ -61,8 +61,10 @@ impl NostrManager {
                     .iter()
                     .any(|event| event.kind == Kind::MlsKeyPackage && event.pubkey == user.pubkey),
                 nostr_relays: Vec::new(), // For now, we don't care about these since we're only searching in the context of finding a person to start a conversation with. We'll fetch all their data later.
+                nostr_read_relays: Vec::new(), // For now, we don't care about these
                 inbox_relays: Vec::new(), // For now, we don't care about these
                 key_package_relays: Vec::new(), // For now, we don't care about these
+                nip05_verified: false,    // Annotated by the command
             };
             enriched_contacts.insert(user.pubkey.to_hex(), enriched_contact);
         }
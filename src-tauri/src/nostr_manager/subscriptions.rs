@@ -1,11 +1,15 @@
 //! Subscription functions for NostrManager
 //! This mostly handles subscribing and processing events as they come in while the user is active.
 
+use crate::key_migrations::KEY_MIGRATION_KIND;
 use crate::nostr_manager::event_processor::ProcessableEvent;
+use crate::nostr_manager::relay_rejections::RelayRejection;
 use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
 use nostr_sdk::prelude::*;
 
 const MLS_MESSAGES_SUB: &str = "mls_messages";
+const CONTACTS_METADATA_SUB: &str = "contacts_metadata";
+const CONTACTS_KEY_MIGRATIONS_SUB: &str = "contacts_key_migrations";
 
 impl NostrManager {
     async fn subscribe_contact_list(&self, pubkey: PublicKey) -> Result<Output<SubscriptionId>> {
@@ -17,7 +21,8 @@ impl NostrManager {
         Ok(self.client.subscribe(contacts_filter, None).await?)
     }
 
-    async fn subscribe_contacts_metadata(&self) -> Result<Output<SubscriptionId>> {
+    async fn subscribe_contacts_metadata(&self) -> Result<Output<()>> {
+        let sub_id = SubscriptionId::new(CONTACTS_METADATA_SUB);
         let contact_list_pubkeys = self
             .client
             .get_contact_list_public_keys(self.timeout().await?)
@@ -28,7 +33,32 @@ impl NostrManager {
             .authors(contact_list_pubkeys)
             .since(Timestamp::now());
 
-        Ok(self.client.subscribe(contact_metadata_filter, None).await?)
+        Ok(self
+            .client
+            .subscribe_with_id(sub_id, contact_metadata_filter, None)
+            .await?)
+    }
+
+    async fn subscribe_contacts_key_migrations(&self) -> Result<Option<Output<()>>> {
+        let sub_id = SubscriptionId::new(CONTACTS_KEY_MIGRATIONS_SUB);
+        let contact_list_pubkeys = self
+            .client
+            .get_contact_list_public_keys(self.timeout().await?)
+            .await?;
+        if contact_list_pubkeys.is_empty() {
+            return Ok(None);
+        }
+
+        let key_migration_filter = Filter::new()
+            .kind(Kind::Custom(KEY_MIGRATION_KIND))
+            .authors(contact_list_pubkeys)
+            .since(Timestamp::now());
+
+        Ok(Some(
+            self.client
+                .subscribe_with_id(sub_id, key_migration_filter, None)
+                .await?,
+        ))
     }
 
     async fn subscribe_metadata(&self, pubkey: PublicKey) -> Result<Output<SubscriptionId>> {
@@ -90,13 +120,40 @@ impl NostrManager {
             .await?)
     }
 
+    /// Closes the subscription to group messages, e.g. after the user left every group
+    pub async fn unsubscribe_mls_group_messages(&self) {
+        self.client
+            .unsubscribe(&SubscriptionId::new(MLS_MESSAGES_SUB))
+            .await;
+    }
+
+    /// Closes the subscriptions to contacts' metadata and key migrations
+    pub async fn pause_contact_subscriptions(&self) {
+        self.client
+            .unsubscribe(&SubscriptionId::new(CONTACTS_METADATA_SUB))
+            .await;
+        self.client
+            .unsubscribe(&SubscriptionId::new(CONTACTS_KEY_MIGRATIONS_SUB))
+            .await;
+    }
+
+    /// Reopens the subscriptions to contacts' metadata and key migrations
+    pub async fn resume_contact_subscriptions(&self) -> Result<()> {
+        self.subscribe_contacts_metadata().await?;
+        self.subscribe_contacts_key_migrations().await?;
+        Ok(())
+    }
+
     pub async fn setup_subscriptions(
         &self,
         pubkey: PublicKey,
         nostr_group_ids: Vec<String>,
+        contact_subscriptions: bool,
     ) -> Result<()> {
         self.subscribe_contact_list(pubkey).await?;
-        self.subscribe_contacts_metadata().await?;
+        if contact_subscriptions {
+            self.resume_contact_subscriptions().await?;
+        }
         self.subscribe_metadata(pubkey).await?;
         self.subscribe_relay_list(pubkey).await?;
         self.subscribe_inbox_relay_list(pubkey).await?;
@@ -110,7 +167,10 @@ impl NostrManager {
             .client
             .handle_notifications(|notification| async {
                 match notification {
-                    RelayPoolNotification::Event { event, .. } => {
+                    RelayPoolNotification::Event {
+                        relay_url, event, ..
+                    } => {
+                        self.relay_monitor.record_message(&relay_url);
                         self.handle_event(*event).await?;
                         Ok(false)
                     }
@@ -160,6 +220,22 @@ impl NostrManager {
                     .await
                     .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
             }
+            Kind::Metadata => {
+                self.event_processor
+                    .lock()
+                    .await
+                    .queue_event(ProcessableEvent::Metadata(event))
+                    .await
+                    .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
+            }
+            kind if kind.as_u16() == KEY_MIGRATION_KIND => {
+                self.event_processor
+                    .lock()
+                    .await
+                    .queue_event(ProcessableEvent::KeyMigration(event))
+                    .await
+                    .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
+            }
             _ => {}
         }
         Ok(())
@@ -167,6 +243,32 @@ impl NostrManager {
 
     // Handle other types of notifications
     fn handle_message(&self, relay_url: RelayUrl, message: RelayMessage) -> Result<()> {
+        self.relay_monitor.record_message(&relay_url);
+        // Every relay's copy comes through here, unlike `RelayPoolNotification::Event`
+        if let RelayMessage::Event { event, .. } = &message {
+            self.relay_monitor.record_echo(&relay_url, &event.id);
+        }
+        match &message {
+            RelayMessage::Ok { status: true, .. } => self.relay_monitor.record_accepted(&relay_url),
+            RelayMessage::Ok {
+                status: false,
+                message,
+                ..
+            }
+            | RelayMessage::Closed { message, .. } => {
+                self.relay_monitor.record_error(&relay_url);
+                self.relay_monitor
+                    .record_rejection(&relay_url, RelayRejection::parse(message));
+            }
+            RelayMessage::Notice(message) => {
+                if let Some(rejection) = RelayRejection::parse_notice(message) {
+                    self.relay_monitor.record_rejection(&relay_url, rejection);
+                }
+            }
+            RelayMessage::NegErr { .. } => self.relay_monitor.record_error(&relay_url),
+            _ => {}
+        }
+
         let variant_name = match message {
             RelayMessage::Event { .. } => "Event",
             RelayMessage::Ok { .. } => "Ok",
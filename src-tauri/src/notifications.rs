@@ -0,0 +1,498 @@
+//! Backend notification filter.
+//!
+//! Every incoming message passes through [`evaluate`] before an OS notification is shown,
+//! so that notification policy (snoozing, etc.) lives in one place rather than in each frontend.
+//!
+//! Each group has a [`NotificationLevel`]; the account has quiet hours, during which nothing but
+//! admin notices notifies, and a switch for invite notifications.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError};
+use crate::invites::Invite;
+use crate::localization::{self, Locale, StringKey};
+use crate::messages::MessageSemantics;
+use crate::settings_sync::{self, SyncedSetting};
+use crate::Whitenoise;
+use chrono::Timelike;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri_plugin_notification::NotificationExt;
+use thiserror::Error;
+
+/// Minutes in a day, the range of quiet hours bounds
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+#[derive(Error, Debug)]
+pub enum NotificationError {
+    #[error("Invalid quiet hours: {0}")]
+    InvalidQuietHours(String),
+
+    #[error("Invalid group ID: {0}")]
+    InvalidGroupId(#[from] hex::FromHexError),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Notification error: {0}")]
+    PluginError(#[from] tauri_plugin_notification::Error),
+}
+
+pub type Result<T> = std::result::Result<T, NotificationError>;
+
+/// How much of a group's activity notifies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationLevel {
+    /// Every message
+    #[default]
+    All,
+    /// Only messages that mention or reply to the user, and admin notices
+    MentionsOnly,
+    /// Only admin notices, not even mentions
+    Muted,
+}
+
+/// A daily window, in local time, during which nothing but admin notices notifies. It may wrap
+/// past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Minutes after local midnight the window starts at
+    pub start_minute: u16,
+    /// Minutes after local midnight the window ends at, exclusive
+    pub end_minute: u16,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<()> {
+        if self.start_minute >= MINUTES_PER_DAY || self.end_minute >= MINUTES_PER_DAY {
+            return Err(NotificationError::InvalidQuietHours(format!(
+                "Bounds must be below {} minutes",
+                MINUTES_PER_DAY
+            )));
+        }
+        if self.start_minute == self.end_minute {
+            return Err(NotificationError::InvalidQuietHours(
+                "Start and end must differ".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `minute` after local midnight falls in the window
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start_minute <= self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+}
+
+/// The account-wide notification settings, stored with the account settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub quiet_hours: Option<QuietHours>,
+    /// Whether new invites notify
+    pub invites: bool,
+    /// Locale tag (e.g. "es", "pt-BR") notification texts are written in, English if unset
+    pub locale: Option<String>,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            quiet_hours: None,
+            invites: true,
+            locale: None,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Whether it's currently quiet hours
+    pub fn is_quiet_now(&self) -> bool {
+        let now = chrono::Local::now();
+        let minute = (now.hour() * 60 + now.minute()) as u16;
+        self.quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.contains(minute))
+    }
+}
+
+/// The account's notification preferences together with the level of each of its groups
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    #[serde(flatten)]
+    pub preferences: NotificationPreferences,
+    /// Notification level by hex encoded MLS group ID. When setting, groups left out keep their
+    /// level.
+    #[serde(default)]
+    pub groups: HashMap<String, NotificationLevel>,
+}
+
+/// The active account's notification settings
+pub async fn settings(wn: tauri::State<'_, Whitenoise>) -> Result<NotificationSettings> {
+    let account = Account::get_active(wn.clone()).await?;
+    let groups = Group::get_all_groups(wn.clone())
+        .await?
+        .into_iter()
+        .map(|group| (hex::encode(&group.mls_group_id), group.notification_level()))
+        .collect();
+    Ok(NotificationSettings {
+        preferences: account.settings.notifications,
+        groups,
+    })
+}
+
+/// Updates the active account's notification settings, shares the change with the account's other
+/// devices and returns them
+pub async fn update(
+    settings: NotificationSettings,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<NotificationSettings> {
+    if let Some(quiet_hours) = &settings.preferences.quiet_hours {
+        quiet_hours.validate()?;
+    }
+    // Look every group up before changing anything
+    let mut groups = Vec::with_capacity(settings.groups.len());
+    for (group_id, level) in &settings.groups {
+        let group = Group::find_by_mls_group_id(&hex::decode(group_id)?, wn.clone()).await?;
+        groups.push((group, *level));
+    }
+
+    let account = Account::get_active(wn.clone()).await?;
+    let preferences = settings.preferences.clone();
+    account
+        .update_settings(
+            |account_settings| account_settings.notifications = preferences,
+            wn.clone(),
+        )
+        .await?;
+    let mut changed = vec![SyncedSetting::NotificationPreferences {
+        preferences: settings.preferences,
+    }];
+    for (group, level) in groups {
+        group.set_notification_level(level, wn.clone()).await?;
+        changed.push(SyncedSetting::GroupNotificationLevel {
+            mls_group_id: hex::encode(&group.mls_group_id),
+            level,
+        });
+    }
+    settings_sync::share(changed, wn.clone()).await;
+    self::settings(wn).await
+}
+
+/// Shows an OS notification for a new invite, unless invite notifications are off or it's quiet
+/// hours
+pub fn show_invite(
+    account: &Account,
+    invite: &Invite,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let preferences = &account.settings.notifications;
+    if !preferences.invites || preferences.is_quiet_now() {
+        return Ok(());
+    }
+    let locale = Locale::from_hint(preferences.locale.as_deref());
+    app_handle
+        .notification()
+        .builder()
+        .title(localization::template(locale, StringKey::NewInvite))
+        .body(localization::format_string(
+            locale,
+            StringKey::InvitedToGroup,
+            &[&invite.group_name],
+        ))
+        .extra("invite_id", invite.event_id.clone())
+        .show()?;
+    Ok(())
+}
+
+/// The outcome of running a message through the notification filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationDecision {
+    /// Show a notification
+    Notify,
+    /// Don't show a notification
+    Suppress,
+    /// The group is snoozed but the user was mentioned or replied to: show a notification
+    /// and end the snooze early
+    NotifyAndUnsnooze,
+}
+
+/// Decides whether an incoming message from another user should produce a notification
+///
+/// Admin notices always notify, even during quiet hours and in muted groups, and without ending
+/// the snooze. Nothing else notifies during quiet hours.
+///
+/// # Arguments
+/// * `snoozed_until` - The group's snooze expiry, if any
+/// * `level` - The group's notification level
+/// * `quiet` - Whether it's the account's quiet hours
+/// * `semantics` - The computed semantics of the message
+/// * `replies_to_me` - Whether the message replies to a message authored by the active account
+/// * `now` - The current time
+pub fn evaluate(
+    snoozed_until: Option<Timestamp>,
+    level: NotificationLevel,
+    quiet: bool,
+    semantics: &MessageSemantics,
+    replies_to_me: bool,
+    now: Timestamp,
+) -> NotificationDecision {
+    if semantics.is_notice {
+        return NotificationDecision::Notify;
+    }
+    if quiet {
+        return NotificationDecision::Suppress;
+    }
+    if level == NotificationLevel::Muted {
+        return NotificationDecision::Suppress;
+    }
+    let directed = semantics.mentions_me || replies_to_me;
+    match snoozed_until {
+        Some(until) if until > now => {
+            if directed {
+                NotificationDecision::NotifyAndUnsnooze
+            } else {
+                NotificationDecision::Suppress
+            }
+        }
+        _ if level == NotificationLevel::MentionsOnly && !directed => {
+            NotificationDecision::Suppress
+        }
+        _ => NotificationDecision::Notify,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_snoozed_notifies() {
+        let now = Timestamp::from(1_000);
+        let semantics = MessageSemantics::default();
+        assert_eq!(
+            evaluate(None, NotificationLevel::All, false, &semantics, false, now),
+            NotificationDecision::Notify
+        );
+        // An expired snooze behaves like no snooze
+        assert_eq!(
+            evaluate(
+                Some(Timestamp::from(999)),
+                NotificationLevel::All,
+                false,
+                &semantics,
+                false,
+                now
+            ),
+            NotificationDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_snoozed_suppresses() {
+        let now = Timestamp::from(1_000);
+        let semantics = MessageSemantics::default();
+        assert_eq!(
+            evaluate(
+                Some(Timestamp::from(2_000)),
+                NotificationLevel::All,
+                false,
+                &semantics,
+                false,
+                now
+            ),
+            NotificationDecision::Suppress
+        );
+    }
+
+    #[test]
+    fn test_snoozed_mention_or_reply_unsnoozes() {
+        let now = Timestamp::from(1_000);
+        let until = Some(Timestamp::from(2_000));
+        let mention = MessageSemantics {
+            mentions_me: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(until, NotificationLevel::All, false, &mention, false, now),
+            NotificationDecision::NotifyAndUnsnooze
+        );
+        assert_eq!(
+            evaluate(
+                until,
+                NotificationLevel::All,
+                false,
+                &MessageSemantics::default(),
+                true,
+                now
+            ),
+            NotificationDecision::NotifyAndUnsnooze
+        );
+    }
+
+    #[test]
+    fn test_notices_ignore_snooze() {
+        let now = Timestamp::from(1_000);
+        let notice = MessageSemantics {
+            is_notice: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(
+                Some(Timestamp::from(2_000)),
+                NotificationLevel::All,
+                false,
+                &notice,
+                false,
+                now
+            ),
+            NotificationDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_mentions_only() {
+        let now = Timestamp::from(1_000);
+        let plain = MessageSemantics::default();
+        let mention = MessageSemantics {
+            mentions_me: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(
+                None,
+                NotificationLevel::MentionsOnly,
+                false,
+                &plain,
+                false,
+                now
+            ),
+            NotificationDecision::Suppress
+        );
+        assert_eq!(
+            evaluate(
+                None,
+                NotificationLevel::MentionsOnly,
+                false,
+                &mention,
+                false,
+                now
+            ),
+            NotificationDecision::Notify
+        );
+        assert_eq!(
+            evaluate(
+                None,
+                NotificationLevel::MentionsOnly,
+                false,
+                &plain,
+                true,
+                now
+            ),
+            NotificationDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_muted_suppresses_everything_but_notices() {
+        let now = Timestamp::from(1_000);
+        let mention = MessageSemantics {
+            mentions_me: true,
+            ..Default::default()
+        };
+        let notice = MessageSemantics {
+            is_notice: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(None, NotificationLevel::Muted, false, &mention, true, now),
+            NotificationDecision::Suppress
+        );
+        assert_eq!(
+            evaluate(None, NotificationLevel::Muted, false, &notice, false, now),
+            NotificationDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_quiet_hours_suppress_everything_but_notices() {
+        let now = Timestamp::from(1_000);
+        let mention = MessageSemantics {
+            mentions_me: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(None, NotificationLevel::All, true, &mention, true, now),
+            NotificationDecision::Suppress
+        );
+        let notice = MessageSemantics {
+            is_notice: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(None, NotificationLevel::All, true, &notice, false, now),
+            NotificationDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_notices_in_muted_groups_notify_during_quiet_hours() {
+        let now = Timestamp::from(1_000);
+        let notice = MessageSemantics {
+            is_notice: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(
+                Some(Timestamp::from(2_000)),
+                NotificationLevel::Muted,
+                true,
+                &notice,
+                false,
+                now
+            ),
+            NotificationDecision::Notify
+        );
+    }
+
+    #[test]
+    fn test_quiet_hours_window() {
+        let day = QuietHours {
+            start_minute: 9 * 60,
+            end_minute: 17 * 60,
+        };
+        assert!(day.contains(9 * 60));
+        assert!(!day.contains(17 * 60));
+        assert!(!day.contains(0));
+
+        let night = QuietHours {
+            start_minute: 22 * 60,
+            end_minute: 7 * 60,
+        };
+        assert!(night.contains(23 * 60));
+        assert!(night.contains(0));
+        assert!(!night.contains(7 * 60));
+        assert!(!night.contains(12 * 60));
+
+        assert!(night.validate().is_ok());
+        assert!(QuietHours {
+            start_minute: 60,
+            end_minute: 60
+        }
+        .validate()
+        .is_err());
+        assert!(QuietHours {
+            start_minute: 0,
+            end_minute: MINUTES_PER_DAY
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,560 @@
+//! Delivery status of outgoing group messages.
+//!
+//! Every message sent with `send_mls_message` gets an outbox entry before it's published. The
+//! entry keeps the signed outer event (the encrypted MLS ciphertext) and records which relays
+//! acknowledged it, so frontends can show whether a message is pending, sent (and to how many
+//! relays) or failed. Status changes are emitted as `message_status_changed`.
+//!
+//! Messages that no relay accepted stay pending and are republished by a background task with
+//! exponential backoff. When the client regains a relay connection after being offline (the relay
+//! monitor emits `relay_connected`), every pending message is retried right away. Under a
+//! throttled sync mode (see `sync_throttle`) the task checks less often. Relays that rate limited
+//! us are left out of retries until the relay monitor is done backing off from them.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError};
+use crate::profiling::{self, OperationKind};
+use crate::relay_blacklist::RelayBlacklist;
+use crate::sync_throttle;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+use thiserror::Error;
+
+/// Publish attempts before a message is marked as failed
+pub const MAX_PUBLISH_ATTEMPTS: u32 = 10;
+
+/// Delay before the first retry; doubled after every failed attempt
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Upper bound for the delay between retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// How often the background task checks connectivity and due retries
+const RETRY_TICK: Duration = Duration::from_secs(5);
+
+#[derive(Error, Debug)]
+pub enum OutboxError {
+    #[error("Message not found in the outbox")]
+    NotFound,
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, OutboxError>;
+
+/// Delivery state of an outgoing message
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryState {
+    /// Not acknowledged by any relay yet; queued for (re)publishing
+    Pending,
+    /// Accepted by at least one relay
+    Sent,
+    /// No relay accepted the message after `MAX_PUBLISH_ATTEMPTS` attempts
+    Failed,
+}
+
+impl DeliveryState {
+    /// The state of a message accepted by `acked` relays after `attempts` publish attempts
+    pub fn after_publish(acked: usize, attempts: u32) -> Self {
+        if acked > 0 {
+            Self::Sent
+        } else if attempts >= MAX_PUBLISH_ATTEMPTS {
+            Self::Failed
+        } else {
+            Self::Pending
+        }
+    }
+}
+
+impl From<String> for DeliveryState {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Sent" => Self::Sent,
+            "Failed" => Self::Failed,
+            _ => Self::Pending,
+        }
+    }
+}
+
+impl From<DeliveryState> for String {
+    fn from(state: DeliveryState) -> Self {
+        match state {
+            DeliveryState::Pending => "Pending".to_string(),
+            DeliveryState::Sent => "Sent".to_string(),
+            DeliveryState::Failed => "Failed".to_string(),
+        }
+    }
+}
+
+/// The delay before the next publish attempt, after `attempts` failed attempts
+pub fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    BASE_RETRY_DELAY
+        .saturating_mul(2u32.pow(exponent))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct OutboxRow {
+    event_id: String,
+    mls_group_id: Vec<u8>,
+    outer_event_id: String,
+    state: String,
+    acked_relays: String,
+    failed_relays: String,
+    attempts: u32,
+    next_attempt_at: Option<u64>,
+    updated_at: u64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct DueRow {
+    #[sqlx(flatten)]
+    status: OutboxRow,
+    outer_event: String,
+}
+
+/// Delivery status of an outgoing message. Payload of the `message_status_changed` event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DeliveryStatus {
+    /// The inner event ID of the message
+    pub event_id: EventId,
+    /// Hex encoded MLS group ID
+    pub group_id: String,
+    pub outer_event_id: EventId,
+    pub state: DeliveryState,
+    /// Relays that accepted the message
+    pub acked_relays: BTreeSet<String>,
+    /// Relays that rejected the message, with the reason they gave
+    pub failed_relays: BTreeMap<String, String>,
+    /// Number of publish attempts so far
+    pub attempts: u32,
+    /// When a pending message will be republished
+    pub next_attempt_at: Option<Timestamp>,
+    pub updated_at: Timestamp,
+}
+
+impl TryFrom<OutboxRow> for DeliveryStatus {
+    type Error = OutboxError;
+
+    fn try_from(row: OutboxRow) -> Result<Self> {
+        Ok(Self {
+            event_id: EventId::from_hex(&row.event_id)?,
+            group_id: hex::encode(&row.mls_group_id),
+            outer_event_id: EventId::from_hex(&row.outer_event_id)?,
+            state: row.state.into(),
+            acked_relays: serde_json::from_str(&row.acked_relays)?,
+            failed_relays: serde_json::from_str(&row.failed_relays)?,
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at.map(Timestamp::from),
+            updated_at: Timestamp::from(row.updated_at),
+        })
+    }
+}
+
+impl DeliveryStatus {
+    /// Adds a pending outbox entry for a message that is about to be published
+    pub async fn enqueue(
+        group: &Group,
+        event_id: &EventId,
+        outer_event: &Event,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Self> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+        let now = Timestamp::now();
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO message_outbox (event_id, account_pubkey, mls_group_id, outer_event_id, outer_event, state, next_attempt_at, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(event_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .bind(&group.mls_group_id)
+        .bind(outer_event.id.to_hex())
+        .bind(serde_json::to_string(outer_event)?)
+        .bind(String::from(DeliveryState::Pending))
+        .bind(now.as_u64() as i64)
+        .bind(now.as_u64() as i64)
+        .bind(now.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+
+        Ok(Self {
+            event_id: *event_id,
+            group_id: hex::encode(&group.mls_group_id),
+            outer_event_id: outer_event.id,
+            state: DeliveryState::Pending,
+            acked_relays: BTreeSet::new(),
+            failed_relays: BTreeMap::new(),
+            attempts: 0,
+            next_attempt_at: Some(now),
+            updated_at: now,
+        })
+    }
+
+    /// Records the relay acknowledgments from a publish attempt and emits
+    /// `message_status_changed`
+    ///
+    /// Acknowledgments accumulate across attempts: a relay that accepted the message once stays
+    /// in `acked_relays` and is removed from `failed_relays`.
+    pub async fn record_publish(
+        &mut self,
+        output: &Output<EventId>,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        for relay in &output.success {
+            self.failed_relays.remove(relay.as_str());
+            self.acked_relays.insert(relay.to_string());
+        }
+        for (relay, error) in &output.failed {
+            if !self.acked_relays.contains(relay.as_str()) {
+                self.failed_relays.insert(relay.to_string(), error.clone());
+            }
+        }
+        self.finish_attempt();
+        self.save(wn, app_handle).await
+    }
+
+    /// Records a publish attempt that failed before any relay could answer (e.g. while
+    /// offline) and emits `message_status_changed`
+    pub async fn record_failure(
+        &mut self,
+        error: &str,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        tracing::warn!(
+            target: "whitenoise::outbox::record_failure",
+            "Failed to publish message {}: {}",
+            self.event_id,
+            error
+        );
+        self.finish_attempt();
+        self.save(wn, app_handle).await
+    }
+
+    fn finish_attempt(&mut self) {
+        self.attempts += 1;
+        self.state = DeliveryState::after_publish(self.acked_relays.len(), self.attempts);
+        self.next_attempt_at = match self.state {
+            DeliveryState::Pending => Some(Timestamp::now() + retry_delay(self.attempts)),
+            _ => None,
+        };
+    }
+
+    async fn save(
+        &mut self,
+        wn: tauri::State<'_, Whitenoise>,
+        app_handle: &tauri::AppHandle,
+    ) -> Result<()> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+        self.updated_at = Timestamp::now();
+
+        sqlx::query(
+            "UPDATE message_outbox SET state = ?, acked_relays = ?, failed_relays = ?, attempts = ?, next_attempt_at = ?, updated_at = ?
+             WHERE event_id = ? AND account_pubkey = ?",
+        )
+        .bind(String::from(self.state))
+        .bind(serde_json::to_string(&self.acked_relays)?)
+        .bind(serde_json::to_string(&self.failed_relays)?)
+        .bind(self.attempts)
+        .bind(self.next_attempt_at.map(|t| t.as_u64() as i64))
+        .bind(self.updated_at.as_u64() as i64)
+        .bind(self.event_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+        app_handle.emit("message_status_changed", self.clone())?;
+        Ok(())
+    }
+
+    /// Finds the delivery status of a message sent by the active account
+    pub async fn find(
+        mls_group_id: &[u8],
+        event_id: &EventId,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Self> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let row = sqlx::query_as::<_, OutboxRow>(
+            "SELECT event_id, mls_group_id, outer_event_id, state, acked_relays, failed_relays, attempts, next_attempt_at, updated_at
+             FROM message_outbox WHERE event_id = ? AND account_pubkey = ? AND mls_group_id = ?",
+        )
+        .bind(event_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .bind(mls_group_id)
+        .fetch_optional(&wn.database.pool)
+        .await?
+        .ok_or(OutboxError::NotFound)?;
+
+        row.try_into()
+    }
+
+    /// Returns the active account's pending messages that are due for another attempt, with
+    /// their signed outer events
+    async fn due(now: Timestamp, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<(Self, Event)>> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let rows = sqlx::query_as::<_, DueRow>(
+            "SELECT event_id, mls_group_id, outer_event_id, state, acked_relays, failed_relays, attempts, next_attempt_at, updated_at, outer_event
+             FROM message_outbox
+             WHERE account_pubkey = ? AND state = ? AND next_attempt_at <= ?
+             ORDER BY created_at ASC",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(String::from(DeliveryState::Pending))
+        .bind(now.as_u64() as i64)
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        // A corrupt entry is skipped rather than holding back the rest of the queue
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let event_id = row.status.event_id.clone();
+                let outer_event =
+                    serde_json::from_str::<Event>(&row.outer_event).map_err(OutboxError::from);
+                match DeliveryStatus::try_from(row.status)
+                    .and_then(|status| Ok((status, outer_event?)))
+                {
+                    Ok(due) => Some(due),
+                    Err(e) => {
+                        tracing::warn!(
+                            target: "whitenoise::outbox::due",
+                            "Skipping malformed outbox entry {}: {}",
+                            event_id,
+                            e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Makes every pending message of the active account due immediately
+    async fn retry_all_now(wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        sqlx::query(
+            "UPDATE message_outbox SET next_attempt_at = ? WHERE account_pubkey = ? AND state = ?",
+        )
+        .bind(Timestamp::now().as_u64() as i64)
+        .bind(account_pubkey.to_hex())
+        .bind(String::from(DeliveryState::Pending))
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Starts the background task that republishes pending messages
+pub fn start(app_handle: AppHandle) {
+    listen_for_reconnects(&app_handle);
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(RETRY_TICK);
+        let mut ticks: u32 = 0;
+        loop {
+            interval.tick().await;
+            let wn = app_handle.state::<Whitenoise>();
+
+            // Poll less often while the device is on battery or a metered connection
+            ticks = ticks.wrapping_add(1);
+            if ticks % sync_throttle::current_mode(&wn).await.tick_multiplier() != 0 {
+                continue;
+            }
+
+            if !wn.nostr.relay_monitor.is_online() {
+                continue;
+            }
+
+            if let Err(e) = resend_due(&app_handle).await {
+                tracing::error!(
+                    target: "whitenoise::outbox::start",
+                    "Failed to resend pending messages: {}",
+                    e
+                );
+            }
+        }
+    });
+}
+
+/// Retries every pending message right away when the first relay connects after the client was
+/// offline
+fn listen_for_reconnects(app_handle: &AppHandle) {
+    static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+    let handle = app_handle.clone();
+    app_handle.listen("relay_disconnected", move |_| {
+        if !handle.state::<Whitenoise>().nostr.relay_monitor.is_online() {
+            OFFLINE.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let handle = app_handle.clone();
+    app_handle.listen("relay_connected", move |_| {
+        if !OFFLINE.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        let handle = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tracing::debug!(
+                target: "whitenoise::outbox::listen_for_reconnects",
+                "Relay connection restored, retrying pending messages"
+            );
+            let wn = handle.state::<Whitenoise>();
+            if let Err(e) = DeliveryStatus::retry_all_now(wn.clone()).await {
+                tracing::error!(
+                    target: "whitenoise::outbox::listen_for_reconnects",
+                    "Failed to reschedule pending messages: {}",
+                    e
+                );
+                return;
+            }
+            if let Err(e) = resend_due(&handle).await {
+                tracing::error!(
+                    target: "whitenoise::outbox::listen_for_reconnects",
+                    "Failed to resend pending messages: {}",
+                    e
+                );
+            }
+        });
+    });
+}
+
+/// Republishes every due message. A message that can't be resent is logged and left for the
+/// next attempt, so it doesn't hold back the ones after it.
+async fn resend_due(app_handle: &AppHandle) -> Result<()> {
+    let wn = app_handle.state::<Whitenoise>();
+
+    for (status, outer_event) in DeliveryStatus::due(Timestamp::now(), wn.clone()).await? {
+        let Ok(mls_group_id) = hex::decode(&status.group_id) else {
+            tracing::warn!(
+                target: "whitenoise::outbox::resend_due",
+                "Skipping message {}: invalid group id {}",
+                status.event_id,
+                status.group_id
+            );
+            continue;
+        };
+        let event_id = status.event_id;
+        if let Err(e) = resend(status, &mls_group_id, &outer_event, app_handle).await {
+            tracing::warn!(
+                target: "whitenoise::outbox::resend_due",
+                "Failed to resend message {}: {}",
+                event_id,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn resend(
+    mut status: DeliveryStatus,
+    mls_group_id: &[u8],
+    outer_event: &Event,
+    app_handle: &AppHandle,
+) -> Result<()> {
+    let wn = app_handle.state::<Whitenoise>();
+    let group = Group::find_by_mls_group_id(mls_group_id, wn.clone()).await?;
+    let relays = wn
+        .nostr
+        .relay_monitor
+        .without_backed_off(group.publish_relays(wn.clone()).await?);
+    // Not an attempt: the message is picked up again once a relay is done backing off
+    if relays.is_empty() {
+        return Ok(());
+    }
+
+    tracing::debug!(
+        target: "whitenoise::outbox::resend",
+        "Republishing message {} (attempt {})",
+        status.event_id,
+        status.attempts + 1
+    );
+
+    wn.nostr
+        .relay_monitor
+        .track_delivery(outer_event.id, &relays);
+    match profiling::time_async(
+        "relay.publish",
+        OperationKind::Relay,
+        wn.nostr.client.send_event_to(relays, outer_event),
+    )
+    .await
+    {
+        Ok(output) => {
+            RelayBlacklist::record_rejections(&output, wn.clone()).await;
+            status.record_publish(&output, wn.clone(), app_handle).await
+        }
+        Err(e) => {
+            status
+                .record_failure(&e.to_string(), wn.clone(), app_handle)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_after_publish() {
+        assert_eq!(DeliveryState::after_publish(0, 1), DeliveryState::Pending);
+        assert_eq!(
+            DeliveryState::after_publish(0, MAX_PUBLISH_ATTEMPTS),
+            DeliveryState::Failed
+        );
+        assert_eq!(DeliveryState::after_publish(1, 1), DeliveryState::Sent);
+        assert_eq!(
+            DeliveryState::after_publish(2, MAX_PUBLISH_ATTEMPTS),
+            DeliveryState::Sent
+        );
+    }
+
+    #[test]
+    fn test_state_round_trips_through_string() {
+        for state in [
+            DeliveryState::Pending,
+            DeliveryState::Sent,
+            DeliveryState::Failed,
+        ] {
+            assert_eq!(DeliveryState::from(String::from(state)), state);
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(5));
+        assert_eq!(retry_delay(2), Duration::from_secs(10));
+        assert_eq!(retry_delay(3), Duration::from_secs(20));
+        assert_eq!(retry_delay(9), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+}
@@ -0,0 +1,155 @@
+//! Typed command parameters that are validated when a command's arguments are deserialized.
+//!
+//! Commands used to take pubkeys and group IDs as raw strings and parse them somewhere in their
+//! body, so a typo surfaced as whatever error the first lookup happened to fail with. Taking a
+//! [`PubkeyParam`] or [`GroupIdParam`] instead rejects a malformed value before the command runs,
+//! with an error naming the argument, e.g.
+//! ``invalid args `groupId` for command `get_group`: Invalid group id `zz`: ...``.
+//!
+//! Tauri rejects those as plain strings rather than a `WhitenoiseError`; the frontend's
+//! `toCommandError` turns them into `invalid_key` or `invalid_input` errors.
+
+use nostr_sdk::prelude::*;
+use serde::de::{self, Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParamError {
+    #[error("Invalid pubkey `{0}`: {1}")]
+    InvalidPubkey(String, String),
+
+    #[error("Invalid group id `{0}`: {1}")]
+    InvalidGroupId(String, String),
+}
+
+/// A hex or npub encoded public key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PubkeyParam(PublicKey);
+
+impl PubkeyParam {
+    pub fn public_key(&self) -> PublicKey {
+        self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+}
+
+impl From<PublicKey> for PubkeyParam {
+    fn from(public_key: PublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+impl FromStr for PubkeyParam {
+    type Err = ParamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PublicKey::parse(s.trim())
+            .map(Self)
+            .map_err(|e| ParamError::InvalidPubkey(s.to_string(), e.to_string()))
+    }
+}
+
+impl fmt::Display for PubkeyParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for PubkeyParam {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A hex encoded MLS group ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupIdParam(Vec<u8>);
+
+impl GroupIdParam {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for GroupIdParam {
+    fn from(mls_group_id: Vec<u8>) -> Self {
+        Self(mls_group_id)
+    }
+}
+
+impl FromStr for GroupIdParam {
+    type Err = ParamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParamError::InvalidGroupId(
+                s.to_string(),
+                "empty".to_string(),
+            ));
+        }
+        hex::decode(s)
+            .map(Self)
+            .map_err(|e| ParamError::InvalidGroupId(s.to_string(), e.to_string()))
+    }
+}
+
+impl fmt::Display for GroupIdParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupIdParam {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pubkey_param_accepts_hex_and_npub() {
+        let keys = Keys::generate();
+        let hex = keys.public_key().to_hex();
+        let npub = keys.public_key().to_bech32().unwrap();
+
+        let from_hex: PubkeyParam = serde_json::from_str(&format!("\"{}\"", hex)).unwrap();
+        let from_npub: PubkeyParam = serde_json::from_str(&format!("\"{}\"", npub)).unwrap();
+        assert_eq!(from_hex.public_key(), keys.public_key());
+        assert_eq!(from_hex, from_npub);
+        assert_eq!(from_npub.to_string(), hex);
+    }
+
+    #[test]
+    fn test_pubkey_param_rejects_garbage() {
+        assert!(matches!(
+            "not a key".parse::<PubkeyParam>(),
+            Err(ParamError::InvalidPubkey(..))
+        ));
+        assert!(serde_json::from_str::<PubkeyParam>("\"abcd\"").is_err());
+        assert!(serde_json::from_str::<PubkeyParam>("42").is_err());
+    }
+
+    #[test]
+    fn test_group_id_param() {
+        let group_id: GroupIdParam = serde_json::from_str("\"00ff10\"").unwrap();
+        assert_eq!(group_id.to_string(), "00ff10");
+        assert_eq!(group_id.into_bytes(), vec![0x00, 0xff, 0x10]);
+
+        assert!(matches!(
+            "zz".parse::<GroupIdParam>(),
+            Err(ParamError::InvalidGroupId(..))
+        ));
+        assert!("abc".parse::<GroupIdParam>().is_err());
+        assert!("".parse::<GroupIdParam>().is_err());
+    }
+}
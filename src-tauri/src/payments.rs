@@ -1,6 +1,7 @@
 use lightning_invoice::Bolt11Invoice;
+use nostr_sdk::Tags;
 use nwc::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -12,6 +13,8 @@ pub enum PaymentError {
     ExpiredInvoice,
     #[error("Invalid NWC URI: {0}")]
     InvalidNwcUri(String),
+    #[error("Wallet unreachable: {0}")]
+    WalletUnreachable(String),
     #[error("Payment failed: {0}")]
     PaymentFailure(String),
 }
@@ -51,13 +54,164 @@ pub async fn pay_bolt11_invoice(bolt11: &str, nwc_uri: &str) -> Result<String, P
     Ok(payment_response.preimage)
 }
 
+/// Checks that a NWC URI is valid and that its wallet answers
+///
+/// # Arguments
+/// * `nwc_uri` - The Nostr Wallet Connect URI to check
+///
+/// # Returns
+/// * `Ok(())` - If the wallet answered an info request
+/// * `Err(PaymentError)` - If the URI can't be parsed or the wallet can't be reached
+pub async fn check_wallet_connection(nwc_uri: &str) -> Result<(), PaymentError> {
+    let uri = NostrWalletConnectURI::parse(nwc_uri)
+        .map_err(|e| PaymentError::InvalidNwcUri(e.to_string()))?;
+    let nwc = NWC::new(uri);
+    nwc.get_info()
+        .await
+        .map_err(|e| PaymentError::WalletUnreachable(e.to_string()))?;
+    Ok(())
+}
+
+/// What a shared payment request asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentRequestKind {
+    /// A BOLT11 invoice (`lnbc...`)
+    Bolt11Invoice,
+    /// A reusable BOLT12 offer (`lno1...`)
+    Bolt12Offer,
+    /// A BOLT12 invoice (`lni1...`)
+    Bolt12Invoice,
+}
+
+/// A payment request shared in a message, parsed so frontends can render it as a payment card
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    pub kind: PaymentRequestKind,
+    /// The request as it appears in the message
+    pub request: String,
+    pub amount_msats: Option<u64>,
+    pub description: Option<String>,
+    /// Unix timestamp after which the request can no longer be paid
+    pub expires_at: Option<u64>,
+    /// Whether `expires_at` had passed when the message was loaded
+    pub expired: bool,
+    /// Hex encoded payment hash, for BOLT11 invoices
+    pub payment_hash: Option<String>,
+}
+
+/// Parses a single word as a payment request
+///
+/// BOLT11 invoices are fully decoded and must carry a valid signature. BOLT12 offers and invoices
+/// are recognized by their prefix only; decoding them needs a Lightning implementation we don't
+/// ship, so their amount, description and expiry are left for the wallet to show.
+pub fn parse_payment_request(word: &str, now: u64) -> Option<PaymentRequest> {
+    let lower = word.to_lowercase();
+    let bolt12_kind = if lower.starts_with("lno1") {
+        Some(PaymentRequestKind::Bolt12Offer)
+    } else if lower.starts_with("lni1") {
+        Some(PaymentRequestKind::Bolt12Invoice)
+    } else {
+        None
+    };
+    if let Some(kind) = bolt12_kind {
+        return Some(PaymentRequest {
+            kind,
+            request: word.to_string(),
+            amount_msats: None,
+            description: None,
+            expires_at: None,
+            expired: false,
+            payment_hash: None,
+        });
+    }
+
+    let invoice = Bolt11Invoice::from_str(word).ok()?;
+    let amount_msats = invoice.amount_milli_satoshis();
+    let expires_at = invoice.expires_at().map(|expiry| expiry.as_secs());
+    let payment_hash = invoice.payment_hash().to_string();
+    let signed = invoice.into_signed_raw();
+    let description = signed
+        .raw_invoice()
+        .description()
+        .map(|description| description.to_string())
+        .filter(|description| !description.is_empty());
+
+    Some(PaymentRequest {
+        kind: PaymentRequestKind::Bolt11Invoice,
+        request: word.to_string(),
+        amount_msats,
+        description,
+        expires_at,
+        expired: expires_at.is_some_and(|expires_at| now > expires_at),
+        payment_hash: Some(payment_hash),
+    })
+}
+
+/// Finds the payment request in a message's `bolt11` tag (`["bolt11", <invoice>, ...]`)
+///
+/// Only the tag is looked at, never the content: invoices pasted in a message stay text, so a
+/// payment card is only ever shown for what the sender shared as one. The amount and description
+/// are taken from the invoice, not from the rest of the tag.
+pub fn payment_request_from_tags(tags: &Tags, now: u64) -> Option<PaymentRequest> {
+    tags.iter().find_map(|tag| match tag.as_slice() {
+        [name, request, ..] if name == "bolt11" => parse_payment_request(request, now),
+        _ => None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nostr_sdk::{Keys, Tag, TagKind};
 
     #[tokio::test]
     async fn test_pay_bolt11_invoice_invalid_bolt11() {
         let result = pay_bolt11_invoice("invalid_bolt11", "nostr+walletconnect://test").await;
         assert!(matches!(result, Err(PaymentError::InvalidInvoice(_))));
     }
+
+    const EXPIRED_INVOICE: &str = "lnbc15u1p3xnhl2pp5jptserfk3zk4qy42tlucycrfwxhydvlemu9pqr93tuzlv9cc7g3sdqsvfhkcap3xyhx7un8cqzpgxqzjcsp5f8c52y2stc300gl6s4xswtjpc37hrnnr3c9wvtgjfuvqmpm35evq9qyyssqy4lgd8tj637qcjp05rdpxxykjenthxftej7a2zzmwrmrl70fyj9hvj0rewhzj7jfyuwkwcg9g2jpwtk3wkjtwnkdks84hsnu8xps5vsq4gj5hs";
+
+    fn bolt11_tag(request: &str) -> Tag {
+        Tag::custom(
+            TagKind::custom("bolt11"),
+            [
+                request.to_string(),
+                "21000".to_string(),
+                "Lunch".to_string(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_payment_request_from_tags_parses_bolt11() {
+        let tags = Tags::from_list(vec![
+            Tag::public_key(Keys::generate().public_key()),
+            bolt11_tag(EXPIRED_INVOICE),
+        ]);
+        let request = payment_request_from_tags(&tags, 0).unwrap();
+        assert_eq!(request.kind, PaymentRequestKind::Bolt11Invoice);
+        // The amount and description come from the invoice, not the tag
+        assert_eq!(request.amount_msats, Some(1_500_000));
+        assert_eq!(request.description.as_deref(), Some("bolt11.org"));
+        assert!(!request.expired);
+
+        let request = payment_request_from_tags(&tags, u64::MAX).unwrap();
+        assert!(request.expired);
+    }
+
+    #[test]
+    fn test_payment_request_from_tags_ignores_invalid_invoices() {
+        let tags = Tags::from_list(vec![bolt11_tag("lnbc1invalid")]);
+        assert!(payment_request_from_tags(&tags, 0).is_none());
+        assert!(payment_request_from_tags(&Tags::new(), 0).is_none());
+    }
+
+    #[test]
+    fn test_parse_payment_request_recognizes_bolt12() {
+        let request = parse_payment_request("lno1qcp4256ypq", 0).unwrap();
+        assert_eq!(request.kind, PaymentRequestKind::Bolt12Offer);
+        assert_eq!(request.request, "lno1qcp4256ypq");
+    }
 }
@@ -0,0 +1,173 @@
+//! Welcomes to new group members that couldn't be sent.
+//!
+//! A group is created as long as at least one member could be welcomed (see `create_group`).
+//! The welcomes that failed are kept here, with the serialized MLS welcome and the key package it
+//! was made for, so `retry_pending_welcomes` can wrap and send them again later. A welcome is
+//! removed once it's sent, once the group moved past the epoch it was made at, or along with its
+//! group.
+
+use crate::accounts::Account;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PendingWelcomeError {
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Failed to parse public key: {0}")]
+    PublicKeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+
+    #[error("Failed to decode welcome: {0}")]
+    HexError(#[from] hex::FromHexError),
+}
+
+pub type Result<T> = std::result::Result<T, PendingWelcomeError>;
+
+#[derive(Debug, sqlx::FromRow)]
+struct PendingWelcomeRow {
+    mls_group_id: Vec<u8>,
+    member_pubkey: String,
+    key_package_event_id: String,
+    welcome: String,
+    error: String,
+    attempts: i64,
+    updated_at: u64,
+    epoch: Option<i64>,
+}
+
+/// A welcome that still has to reach a member of a group
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PendingWelcome {
+    pub mls_group_id: Vec<u8>,
+    pub member_pubkey: PublicKey,
+    /// The member's key package the welcome was made for
+    pub key_package_event_id: EventId,
+    /// The serialized MLS welcome message
+    pub welcome: Vec<u8>,
+    /// Why the last attempt failed
+    pub error: String,
+    pub attempts: u32,
+    pub updated_at: Timestamp,
+    /// The group epoch the welcome was made at, unknown for welcomes stored before it was kept
+    pub epoch: Option<u64>,
+}
+
+impl TryFrom<PendingWelcomeRow> for PendingWelcome {
+    type Error = PendingWelcomeError;
+
+    fn try_from(row: PendingWelcomeRow) -> Result<Self> {
+        Ok(Self {
+            mls_group_id: row.mls_group_id,
+            member_pubkey: PublicKey::from_hex(&row.member_pubkey)?,
+            key_package_event_id: EventId::from_hex(&row.key_package_event_id)?,
+            welcome: hex::decode(&row.welcome)?,
+            error: row.error,
+            attempts: row.attempts as u32,
+            updated_at: Timestamp::from(row.updated_at),
+            epoch: row.epoch.map(|epoch| epoch as u64),
+        })
+    }
+}
+
+impl PendingWelcome {
+    /// Stores a welcome made at `epoch` that failed to send, or counts another failed attempt
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record(
+        account: &Account,
+        mls_group_id: &[u8],
+        member_pubkey: &PublicKey,
+        key_package_event_id: &EventId,
+        welcome: &[u8],
+        epoch: u64,
+        error: &str,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO pending_welcomes
+                 (account_pubkey, mls_group_id, member_pubkey, key_package_event_id, welcome, error, attempts, updated_at, epoch)
+             VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?)
+             ON CONFLICT(account_pubkey, mls_group_id, member_pubkey) DO UPDATE SET
+                 key_package_event_id = excluded.key_package_event_id,
+                 welcome = excluded.welcome,
+                 error = excluded.error,
+                 attempts = pending_welcomes.attempts + 1,
+                 updated_at = excluded.updated_at,
+                 epoch = excluded.epoch",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(mls_group_id)
+        .bind(member_pubkey.to_hex())
+        .bind(key_package_event_id.to_hex())
+        .bind(hex::encode(welcome))
+        .bind(error)
+        .bind(Timestamp::now().as_u64() as i64)
+        .bind(epoch as i64)
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The welcomes of a group that haven't been sent yet
+    pub async fn for_group(
+        account: &Account,
+        mls_group_id: &[u8],
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<Self>> {
+        let rows = sqlx::query_as::<_, PendingWelcomeRow>(
+            "SELECT mls_group_id, member_pubkey, key_package_event_id, welcome, error, attempts, updated_at, epoch
+             FROM pending_welcomes WHERE account_pubkey = ? AND mls_group_id = ?
+             ORDER BY updated_at",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(mls_group_id)
+        .fetch_all(&wn.database.pool)
+        .await?;
+        rows.into_iter().map(Self::try_from).collect()
+    }
+
+    /// Forgets the welcomes of a group made before its current epoch, which can't be used to join
+    /// it anymore, and returns them
+    pub async fn remove_stale(
+        account: &Account,
+        mls_group_id: &[u8],
+        current_epoch: u64,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Vec<Self>> {
+        let rows = sqlx::query_as::<_, PendingWelcomeRow>(
+            "DELETE FROM pending_welcomes
+             WHERE account_pubkey = ? AND mls_group_id = ? AND (epoch IS NULL OR epoch != ?)
+             RETURNING mls_group_id, member_pubkey, key_package_event_id, welcome, error, attempts, updated_at, epoch",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(mls_group_id)
+        .bind(current_epoch as i64)
+        .fetch_all(&wn.database.pool)
+        .await?;
+        rows.into_iter().map(Self::try_from).collect()
+    }
+
+    /// Forgets a welcome once it's sent
+    pub async fn remove(
+        account: &Account,
+        mls_group_id: &[u8],
+        member_pubkey: &PublicKey,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM pending_welcomes
+             WHERE account_pubkey = ? AND mls_group_id = ? AND member_pubkey = ?",
+        )
+        .bind(account.pubkey.to_hex())
+        .bind(mls_group_id)
+        .bind(member_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+//! Timing of expensive operations for troubleshooting.
+//!
+//! MLS operations, NIP-44 encryption, database writes and relay round trips are timed with
+//! [`time`] and [`time_async`] and recorded in a histogram per operation. Operations that take
+//! longer than the threshold of their [`OperationKind`] are logged and emitted as
+//! `slow_operation`. [`report`] summarizes the histograms as percentiles; they are kept in memory
+//! only and start empty on every launch.
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+/// Upper bounds of the histogram buckets in milliseconds. Anything slower lands in a final
+/// overflow bucket.
+const BUCKET_BOUNDS_MS: [u64; 14] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000,
+];
+
+/// What an operation spends its time on, which decides when it counts as slow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Mls,
+    Crypto,
+    Database,
+    Relay,
+}
+
+impl OperationKind {
+    /// How long an operation of this kind may take before it's reported as slow
+    pub fn threshold(self) -> Duration {
+        match self {
+            OperationKind::Mls => Duration::from_millis(500),
+            OperationKind::Crypto => Duration::from_millis(50),
+            OperationKind::Database => Duration::from_millis(200),
+            OperationKind::Relay => Duration::from_secs(5),
+        }
+    }
+}
+
+/// The payload of `slow_operation`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SlowOperation {
+    pub operation: String,
+    pub kind: OperationKind,
+    pub duration_ms: u64,
+    pub threshold_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    kind: OperationKind,
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    count: u64,
+    total_ms: u64,
+    max_ms: u64,
+    slow: u64,
+}
+
+impl Histogram {
+    fn new(kind: OperationKind) -> Self {
+        Self {
+            kind,
+            buckets: [0; BUCKET_BOUNDS_MS.len() + 1],
+            count: 0,
+            total_ms: 0,
+            max_ms: 0,
+            slow: 0,
+        }
+    }
+
+    fn record(&mut self, duration_ms: u64, slow: bool) {
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| duration_ms <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total_ms += duration_ms;
+        self.max_ms = self.max_ms.max(duration_ms);
+        if slow {
+            self.slow += 1;
+        }
+    }
+
+    /// The upper bound of the bucket holding the given percentile, capped at the slowest recorded
+    /// duration
+    fn percentile(&self, percentile: u64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = (self.count * percentile).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS
+                    .get(bucket)
+                    .map_or(self.max_ms, |bound| (*bound).min(self.max_ms));
+            }
+        }
+        self.max_ms
+    }
+}
+
+/// Timing summary of one operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OperationStats {
+    pub operation: String,
+    pub kind: OperationKind,
+    pub count: u64,
+    pub mean_ms: u64,
+    /// Approximate: the upper bound of the histogram bucket the percentile falls in
+    pub p50_ms: u64,
+    /// Approximate: the upper bound of the histogram bucket the percentile falls in
+    pub p95_ms: u64,
+    pub max_ms: u64,
+    /// How many runs exceeded the threshold of the operation's kind
+    pub slow_count: u64,
+}
+
+/// Timing summaries of every operation recorded since launch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PerformanceReport {
+    pub operations: Vec<OperationStats>,
+}
+
+static HISTOGRAMS: Lazy<Mutex<BTreeMap<&'static str, Histogram>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Where `slow_operation` is emitted, set once the app has started
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+
+/// Starts emitting `slow_operation` events
+pub fn init(app_handle: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app_handle);
+}
+
+/// Records how long a run of an operation took
+pub fn record(operation: &'static str, kind: OperationKind, elapsed: Duration) {
+    let duration_ms = elapsed.as_millis() as u64;
+    let slow = elapsed > kind.threshold();
+
+    HISTOGRAMS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(operation)
+        .or_insert_with(|| Histogram::new(kind))
+        .record(duration_ms, slow);
+
+    if slow {
+        let slow_operation = SlowOperation {
+            operation: operation.to_string(),
+            kind,
+            duration_ms,
+            threshold_ms: kind.threshold().as_millis() as u64,
+        };
+        tracing::warn!(
+            target: "whitenoise::profiling::record",
+            "Slow operation {}: {}ms (threshold {}ms)",
+            operation,
+            duration_ms,
+            slow_operation.threshold_ms
+        );
+        if let Some(app_handle) = APP_HANDLE.get() {
+            let _ = app_handle.emit("slow_operation", slow_operation);
+        }
+    }
+}
+
+/// Runs and times a synchronous operation
+pub fn time<T>(operation: &'static str, kind: OperationKind, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    record(operation, kind, started.elapsed());
+    result
+}
+
+/// Awaits and times an asynchronous operation
+pub async fn time_async<T>(
+    operation: &'static str,
+    kind: OperationKind,
+    future: impl Future<Output = T>,
+) -> T {
+    let started = Instant::now();
+    let result = future.await;
+    record(operation, kind, started.elapsed());
+    result
+}
+
+/// Summarizes the timings recorded since launch, by operation name
+pub fn report() -> PerformanceReport {
+    let histograms = HISTOGRAMS.lock().unwrap_or_else(|e| e.into_inner());
+    PerformanceReport {
+        operations: histograms
+            .iter()
+            .map(|(operation, histogram)| OperationStats {
+                operation: operation.to_string(),
+                kind: histogram.kind,
+                count: histogram.count,
+                mean_ms: histogram.total_ms / histogram.count.max(1),
+                p50_ms: histogram.percentile(50),
+                p95_ms: histogram.percentile(95),
+                max_ms: histogram.max_ms,
+                slow_count: histogram.slow,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_use_bucket_bounds() {
+        let mut histogram = Histogram::new(OperationKind::Database);
+        for _ in 0..90 {
+            histogram.record(3, false);
+        }
+        for _ in 0..10 {
+            histogram.record(700, true);
+        }
+        assert_eq!(histogram.percentile(50), 5);
+        assert_eq!(histogram.percentile(95), 700);
+        assert_eq!(histogram.slow, 10);
+    }
+
+    #[test]
+    fn test_empty_histogram_percentiles_are_zero() {
+        let histogram = Histogram::new(OperationKind::Relay);
+        assert_eq!(histogram.percentile(50), 0);
+        assert_eq!(histogram.percentile(95), 0);
+    }
+
+    #[test]
+    fn test_overflow_bucket_reports_max() {
+        let mut histogram = Histogram::new(OperationKind::Relay);
+        histogram.record(45_000, true);
+        assert_eq!(histogram.percentile(95), 45_000);
+    }
+
+    #[test]
+    fn test_time_records_operation() {
+        let value = time("test.profiling.time", OperationKind::Crypto, || 42);
+        assert_eq!(value, 42);
+
+        let stats = report()
+            .operations
+            .into_iter()
+            .find(|stats| stats.operation == "test.profiling.time")
+            .unwrap();
+        assert_eq!(stats.count, 1);
+        assert_eq!(stats.kind, OperationKind::Crypto);
+    }
+}
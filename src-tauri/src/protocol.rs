@@ -0,0 +1,243 @@
+//! Inner event kinds and tag requirements of group messages.
+//!
+//! Every payload a group message can carry has one entry in [`PAYLOAD_POLICIES`]: the kind it's
+//! sent with, the kinds older clients used for it, and the tags it can't do without. Senders ask
+//! [`kind_for`] instead of hard-coding kinds, so moving a payload to a new kind as the spec
+//! evolves is a change to the table. Received events go through [`normalize`], which maps legacy
+//! kinds onto the current ones and rejects events missing a required tag, before anything else
+//! looks at them.
+
+use crate::messages::{
+    DELETION_KIND, EDIT_KIND, GROUP_CUSTOM_DATA_KIND, GROUP_MOVED_KIND, GROUP_NOTE_KIND,
+    GROUP_NOTICE_KIND, GROUP_SETTINGS_KIND, GROUP_TASK_KIND,
+};
+use crate::reactions::REACTION_KIND;
+use crate::read_receipts::READ_RECEIPT_KIND;
+use crate::typing::TYPING_INDICATOR_KIND;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The kind of chat messages (NIP-C7)
+pub const CHAT_MESSAGE_KIND: u16 = 9;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    #[error("{payload:?} event is missing its `{tag}` tag")]
+    MissingTag { payload: PayloadType, tag: String },
+}
+
+pub type Result<T> = std::result::Result<T, ProtocolError>;
+
+/// What a group message carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadType {
+    ChatMessage,
+    Reaction,
+    Deletion,
+    Edit,
+    ReadReceipt,
+    TypingIndicator,
+    GroupSettings,
+    GroupMoved,
+    GroupNotice,
+    GroupNote,
+    GroupTask,
+    GroupCustomData,
+}
+
+/// How a payload type is encoded as an inner event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadPolicy {
+    pub payload: PayloadType,
+    /// The kind events are sent with
+    pub kind: u16,
+    /// Kinds older clients sent the payload with, still accepted on receive
+    pub legacy_kinds: &'static [u16],
+    /// Tags an event of this payload is dropped without
+    pub required_tags: &'static [&'static str],
+}
+
+/// The encoding of every payload type
+pub const PAYLOAD_POLICIES: [PayloadPolicy; 12] = [
+    PayloadPolicy {
+        payload: PayloadType::ChatMessage,
+        kind: CHAT_MESSAGE_KIND,
+        // Early clients sent chat messages as kind 1 text notes
+        legacy_kinds: &[1],
+        required_tags: &[],
+    },
+    PayloadPolicy {
+        payload: PayloadType::Reaction,
+        kind: REACTION_KIND,
+        legacy_kinds: &[],
+        required_tags: &["e"],
+    },
+    PayloadPolicy {
+        payload: PayloadType::Deletion,
+        kind: DELETION_KIND,
+        legacy_kinds: &[],
+        required_tags: &["e"],
+    },
+    PayloadPolicy {
+        payload: PayloadType::Edit,
+        kind: EDIT_KIND,
+        legacy_kinds: &[],
+        required_tags: &["e"],
+    },
+    PayloadPolicy {
+        payload: PayloadType::ReadReceipt,
+        kind: READ_RECEIPT_KIND,
+        legacy_kinds: &[],
+        required_tags: &["e"],
+    },
+    PayloadPolicy {
+        payload: PayloadType::TypingIndicator,
+        kind: TYPING_INDICATOR_KIND,
+        legacy_kinds: &[],
+        required_tags: &[],
+    },
+    PayloadPolicy {
+        payload: PayloadType::GroupSettings,
+        kind: GROUP_SETTINGS_KIND,
+        legacy_kinds: &[],
+        required_tags: &[],
+    },
+    PayloadPolicy {
+        payload: PayloadType::GroupMoved,
+        kind: GROUP_MOVED_KIND,
+        legacy_kinds: &[],
+        required_tags: &["moved_to"],
+    },
+    PayloadPolicy {
+        payload: PayloadType::GroupNotice,
+        kind: GROUP_NOTICE_KIND,
+        legacy_kinds: &[],
+        required_tags: &[],
+    },
+    PayloadPolicy {
+        payload: PayloadType::GroupNote,
+        kind: GROUP_NOTE_KIND,
+        legacy_kinds: &[],
+        required_tags: &[],
+    },
+    PayloadPolicy {
+        payload: PayloadType::GroupTask,
+        kind: GROUP_TASK_KIND,
+        legacy_kinds: &[],
+        required_tags: &[],
+    },
+    PayloadPolicy {
+        payload: PayloadType::GroupCustomData,
+        kind: GROUP_CUSTOM_DATA_KIND,
+        legacy_kinds: &[],
+        required_tags: &[],
+    },
+];
+
+/// The policy of a payload type
+pub fn policy(payload: PayloadType) -> &'static PayloadPolicy {
+    PAYLOAD_POLICIES
+        .iter()
+        .find(|policy| policy.payload == payload)
+        .expect("every payload type has a policy")
+}
+
+/// The kind to send a payload with
+pub fn kind_for(payload: PayloadType) -> u16 {
+    policy(payload).kind
+}
+
+/// The payload an inner event kind carries, current or legacy
+pub fn payload_of(kind: u16) -> Option<PayloadType> {
+    PAYLOAD_POLICIES
+        .iter()
+        .find(|policy| policy.kind == kind || policy.legacy_kinds.contains(&kind))
+        .map(|policy| policy.payload)
+}
+
+/// Brings a received inner event in line with the current policy: a legacy kind is replaced by
+/// the payload's current kind, and an event missing a required tag is rejected. The event keeps
+/// the ID it was sent with. Kinds no policy knows are left alone.
+pub fn normalize(event: &mut UnsignedEvent) -> Result<()> {
+    let Some(payload) = payload_of(event.kind.as_u16()) else {
+        return Ok(());
+    };
+    let policy = policy(payload);
+    for tag in policy.required_tags {
+        let present = event
+            .tags
+            .iter()
+            .any(|t| t.as_slice().first().map(String::as_str) == Some(*tag));
+        if !present {
+            return Err(ProtocolError::MissingTag {
+                payload,
+                tag: tag.to_string(),
+            });
+        }
+    }
+    event.kind = Kind::from(policy.kind);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rumor(kind: u16, tags: Vec<Tag>) -> UnsignedEvent {
+        let mut event = EventBuilder::new(Kind::from(kind), "hi")
+            .tags(tags)
+            .build(Keys::generate().public_key());
+        event.ensure_id();
+        event
+    }
+
+    #[test]
+    fn test_every_payload_has_one_policy_and_kinds_dont_overlap() {
+        let mut kinds = Vec::new();
+        for policy in PAYLOAD_POLICIES {
+            assert_eq!(
+                PAYLOAD_POLICIES
+                    .iter()
+                    .filter(|other| other.payload == policy.payload)
+                    .count(),
+                1
+            );
+            kinds.push(policy.kind);
+            kinds.extend(policy.legacy_kinds);
+        }
+        let count = kinds.len();
+        kinds.sort();
+        kinds.dedup();
+        assert_eq!(kinds.len(), count);
+    }
+
+    #[test]
+    fn test_legacy_kinds_are_normalized() {
+        let mut legacy = rumor(1, vec![]);
+        let id = legacy.id;
+        normalize(&mut legacy).unwrap();
+        assert_eq!(legacy.kind, Kind::from(CHAT_MESSAGE_KIND));
+        assert_eq!(legacy.id, id);
+
+        let mut unknown = rumor(30_000, vec![]);
+        normalize(&mut unknown).unwrap();
+        assert_eq!(unknown.kind, Kind::from(30_000));
+    }
+
+    #[test]
+    fn test_required_tags() {
+        let mut reaction = rumor(REACTION_KIND, vec![]);
+        assert_eq!(
+            normalize(&mut reaction),
+            Err(ProtocolError::MissingTag {
+                payload: PayloadType::Reaction,
+                tag: "e".to_string()
+            })
+        );
+
+        let mut reaction = rumor(REACTION_KIND, vec![Tag::event(EventId::all_zeros())]);
+        assert!(normalize(&mut reaction).is_ok());
+    }
+}
@@ -0,0 +1,252 @@
+//! Data for the command-palette style quick switcher.
+//!
+//! Groups and contacts of every account are matched against the query in a single backend call
+//! so the frontend doesn't have to load each account's groups and contacts to filter them.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum QuickSwitcherError {
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] crate::nostr_manager::NostrManagerError),
+}
+
+pub type Result<T> = std::result::Result<T, QuickSwitcherError>;
+
+/// What a quick switcher entry opens
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickSwitcherEntryKind {
+    Group,
+    Contact,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct QuickSwitcherEntry {
+    pub kind: QuickSwitcherEntryKind,
+    /// Hex encoded MLS group ID for groups, hex encoded pubkey for contacts
+    pub id: String,
+    /// Hex encoded pubkey of the account the group or contact belongs to
+    pub account_pubkey: String,
+    /// The group name, or the contact's display name
+    pub title: String,
+    /// The contact's npub. Not set for groups.
+    pub npub: Option<String>,
+    /// Unread messages in the group. Always 0 for contacts.
+    pub unread_count: u64,
+    /// How well the entry matched the query, higher is better
+    pub score: u32,
+}
+
+/// Scores how well `candidate` matches `query`, or returns `None` if it doesn't match at all.
+///
+/// Matching is case insensitive. Every character of the query has to appear in the candidate
+/// in order; exact matches rank above prefixes, prefixes above substrings and substrings above
+/// scattered matches, which lose points for every skipped character. An empty query matches
+/// everything with a score of 0.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate = candidate.to_lowercase();
+
+    if candidate == query {
+        return Some(1000);
+    }
+    if candidate.starts_with(&query) {
+        return Some(800);
+    }
+    if let Some(position) = candidate.find(&query) {
+        // Matches at the start of a word rank above matches in the middle of one
+        let word_start = candidate[..position]
+            .chars()
+            .last()
+            .is_some_and(|c| !c.is_alphanumeric());
+        return Some(if word_start { 700 } else { 600 });
+    }
+
+    let mut gaps = 0u32;
+    let mut candidate_chars = candidate.chars();
+    for query_char in query.chars() {
+        loop {
+            match candidate_chars.next() {
+                Some(c) if c == query_char => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(500u32.saturating_sub(gaps * 5).max(1))
+}
+
+/// Returns the best score of any of the candidates
+fn best_score<'a>(query: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| fuzzy_score(query, candidate))
+        .max()
+}
+
+/// Sorts entries by score, then unread count, then title, and keeps the first `limit`
+pub fn rank(mut entries: Vec<QuickSwitcherEntry>, limit: usize) -> Vec<QuickSwitcherEntry> {
+    entries.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.unread_count.cmp(&a.unread_count))
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+    entries.truncate(limit);
+    entries
+}
+
+/// Builds the ranked quick switcher entries matching `query` across all accounts
+///
+/// Groups are matched by name, contacts by display name, name and npub. Archived groups are
+/// left out. Contacts come from each account's cached contact list.
+pub async fn entries(
+    query: &str,
+    limit: usize,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<QuickSwitcherEntry>> {
+    let mut entries = Vec::new();
+
+    for account in Account::all(wn.clone()).await? {
+        let account_pubkey = account.pubkey.to_hex();
+        let unread_counts = Group::unread_counts_for_account(&account.pubkey, wn.clone()).await?;
+
+        for group in account.groups(wn.clone()).await? {
+            if group.archived_at.is_some() {
+                continue;
+            }
+            if let Some(score) = fuzzy_score(query, &group.name) {
+                let id = hex::encode(&group.mls_group_id);
+                entries.push(QuickSwitcherEntry {
+                    kind: QuickSwitcherEntryKind::Group,
+                    unread_count: unread_counts.get(&id).copied().unwrap_or(0),
+                    id,
+                    account_pubkey: account_pubkey.clone(),
+                    title: group.name,
+                    npub: None,
+                    score,
+                });
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for contact in wn
+            .nostr
+            .query_contact_list_pubkeys_for(account.pubkey)
+            .await?
+        {
+            if !seen.insert(contact) {
+                continue;
+            }
+            let metadata = wn
+                .nostr
+                .query_user_metadata(contact)
+                .await?
+                .unwrap_or_default();
+            let npub = contact.to_bech32().unwrap_or_else(|_| contact.to_hex());
+            let title = metadata
+                .display_name
+                .clone()
+                .filter(|name| !name.is_empty())
+                .or_else(|| metadata.name.clone().filter(|name| !name.is_empty()))
+                .unwrap_or_else(|| npub.clone());
+
+            let names = [metadata.display_name.as_deref(), metadata.name.as_deref()];
+            let candidates = names.into_iter().flatten().chain([npub.as_str()]);
+            if let Some(score) = best_score(query, candidates) {
+                entries.push(QuickSwitcherEntry {
+                    kind: QuickSwitcherEntryKind::Contact,
+                    id: contact.to_hex(),
+                    account_pubkey: account_pubkey.clone(),
+                    title,
+                    npub: Some(npub),
+                    unread_count: 0,
+                    score,
+                });
+            }
+        }
+    }
+
+    Ok(rank(entries, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, score: u32, unread_count: u64) -> QuickSwitcherEntry {
+        QuickSwitcherEntry {
+            kind: QuickSwitcherEntryKind::Group,
+            id: title.to_string(),
+            account_pubkey: String::new(),
+            title: title.to_string(),
+            npub: None,
+            unread_count,
+            score,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score_ordering() {
+        let exact = fuzzy_score("team", "Team").unwrap();
+        let prefix = fuzzy_score("team", "Team chat").unwrap();
+        let word = fuzzy_score("team", "Dev team").unwrap();
+        let substring = fuzzy_score("team", "Steamroller").unwrap();
+        let scattered = fuzzy_score("team", "The early adopters meetup").unwrap();
+
+        assert!(exact > prefix);
+        assert!(prefix > word);
+        assert!(word > substring);
+        assert!(substring > scattered);
+    }
+
+    #[test]
+    fn test_fuzzy_score_no_match() {
+        assert_eq!(fuzzy_score("xyz", "Team chat"), None);
+        assert_eq!(fuzzy_score("chat team", "Team chat"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_score_empty_query_matches_everything() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("  ", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_score_fewer_gaps_rank_higher() {
+        let tight = fuzzy_score("npb", "npub1abc").unwrap();
+        let loose = fuzzy_score("npb", "n-p-------b").unwrap();
+        assert!(tight > loose);
+    }
+
+    #[test]
+    fn test_rank() {
+        let ranked = rank(
+            vec![
+                entry("b", 500, 0),
+                entry("a", 500, 0),
+                entry("unread", 500, 3),
+                entry("best", 800, 0),
+            ],
+            3,
+        );
+        let titles: Vec<_> = ranked.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["best", "unread", "a"]);
+    }
+}
@@ -0,0 +1,331 @@
+//! NIP-25 style reactions sent inside MLS groups.
+//!
+//! Reactions are regular kind 7 rumors routed through the group like any other message and
+//! stored in the messages table. This module knows how to find the message a reaction targets
+//! and how to aggregate the reactions on a message for display.
+//!
+//! A reaction is retracted by deleting it with a kind 5 deletion, like any other message.
+//! Retracted reactions are tombstoned and no longer counted; changing a reaction is a
+//! retraction followed by a new reaction.
+
+use crate::accounts::{Account, AccountError};
+use crate::messages::{Message, MessageRow};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The inner event kind used for reactions
+pub const REACTION_KIND: u16 = 7;
+
+/// Maximum length (in chars) of a reaction's content
+const MAX_REACTION_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum ReactionError {
+    #[error("Invalid reaction: {0}")]
+    InvalidReaction(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ReactionError>;
+
+/// The aggregated reactions with a given emoji on a single message
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    pub count: usize,
+    pub reactors: Vec<PublicKey>,
+    /// Whether the active account is one of the reactors
+    pub reacted_by_me: bool,
+}
+
+/// A member who reacted to a message with a given emoji
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Reactor {
+    pub pubkey: PublicKey,
+    pub reacted_at: Timestamp,
+    /// The reaction message, e.g. for retracting it
+    pub reaction_event_id: EventId,
+}
+
+/// Payload of the `mls_reactions_updated` event, emitted when a reaction is retracted
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MlsReactionsUpdatedEvent {
+    pub group_id: Vec<u8>,
+    /// The message whose reactions changed
+    pub target_event_id: EventId,
+    /// The updated aggregate of all reactions on the target message
+    pub reactions: Vec<ReactionSummary>,
+}
+
+/// Payload of the `mls_reaction_received` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MlsReactionReceivedEvent {
+    pub group_id: Vec<u8>,
+    /// The message that was reacted to
+    pub target_event_id: EventId,
+    /// The reaction message itself
+    pub reaction: Message,
+    /// The updated aggregate of all reactions on the target message
+    pub reactions: Vec<ReactionSummary>,
+}
+
+/// Validates the content of a reaction before it's sent
+pub fn validate_reaction_content(content: &str) -> Result<()> {
+    if content.trim().is_empty() {
+        return Err(ReactionError::InvalidReaction(
+            "Reaction can't be empty".to_string(),
+        ));
+    }
+    if content.chars().count() > MAX_REACTION_LENGTH {
+        return Err(ReactionError::InvalidReaction(format!(
+            "Reaction can't be longer than {} characters",
+            MAX_REACTION_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the event a reaction targets. Per NIP-25 this is the last `e` tag.
+pub fn reaction_target(tags: &Tags) -> Option<EventId> {
+    tags.event_ids().last().copied()
+}
+
+/// Aggregates reactions into per-emoji summaries, ordered by count (then first appearance).
+///
+/// Each author is counted at most once per emoji. Reactions are expected in chronological order.
+pub fn aggregate(reactions: &[Message], me: &PublicKey) -> Vec<ReactionSummary> {
+    let mut summaries: Vec<ReactionSummary> = Vec::new();
+    for reaction in reactions {
+        let emoji = reaction.content.trim().to_string();
+        match summaries.iter_mut().find(|s| s.emoji == emoji) {
+            Some(summary) => {
+                if !summary.reactors.contains(&reaction.author_pubkey) {
+                    summary.reactors.push(reaction.author_pubkey);
+                    summary.count += 1;
+                }
+            }
+            None => summaries.push(ReactionSummary {
+                emoji,
+                count: 1,
+                reactors: vec![reaction.author_pubkey],
+                reacted_by_me: false,
+            }),
+        }
+    }
+    for summary in summaries.iter_mut() {
+        summary.reacted_by_me = summary.reactors.contains(me);
+    }
+    // Stable sort keeps first-appearance order for equal counts
+    summaries.sort_by(|a, b| b.count.cmp(&a.count));
+    summaries
+}
+
+/// Groups reactions by emoji, listing who reacted and when.
+///
+/// Like [`aggregate`], each author is listed at most once per emoji, with their first reaction.
+/// Reactions are expected in chronological order.
+pub fn reactors_by_emoji(reactions: &[Message]) -> HashMap<String, Vec<Reactor>> {
+    let mut reactors: HashMap<String, Vec<Reactor>> = HashMap::new();
+    for reaction in reactions {
+        let entry = reactors
+            .entry(reaction.content.trim().to_string())
+            .or_default();
+        if entry.iter().all(|r| r.pubkey != reaction.author_pubkey) {
+            entry.push(Reactor {
+                pubkey: reaction.author_pubkey,
+                reacted_at: reaction.created_at,
+                reaction_event_id: reaction.event_id,
+            });
+        }
+    }
+    reactors
+}
+
+/// Loads all active (not retracted) reactions on a message in a group, oldest first
+pub async fn reactions_for_message(
+    mls_group_id: &[u8],
+    target_event_id: &EventId,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Message>> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+    let rows = sqlx::query_as::<_, MessageRow>(
+        "SELECT * FROM messages
+         WHERE mls_group_id = ? AND account_pubkey = ? AND event_kind = ?
+           AND deleted_at IS NULL
+           AND EXISTS (
+               SELECT 1 FROM json_each(messages.tags) AS t
+               WHERE json_extract(t.value, '$[0]') = 'e' AND json_extract(t.value, '$[1]') = ?
+           )
+         ORDER BY created_at ASC, id ASC",
+    )
+    .bind(mls_group_id)
+    .bind(account_pubkey.to_hex())
+    .bind(REACTION_KIND as i64)
+    .bind(target_event_id.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(Message::from)
+        .filter(|reaction| reaction_target(&reaction.tags) == Some(*target_event_id))
+        .collect())
+}
+
+/// Loads and aggregates the reactions on a message for the active account
+pub async fn summarize_message_reactions(
+    mls_group_id: &[u8],
+    target_event_id: &EventId,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<ReactionSummary>> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let reactions = reactions_for_message(mls_group_id, target_event_id, wn).await?;
+    Ok(aggregate(&reactions, &account_pubkey))
+}
+
+/// Loads who reacted to a message with each emoji, for the active account
+pub async fn message_reactors(
+    mls_group_id: &[u8],
+    target_event_id: &EventId,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<HashMap<String, Vec<Reactor>>> {
+    let reactions = reactions_for_message(mls_group_id, target_event_id, wn).await?;
+    Ok(reactors_by_emoji(&reactions))
+}
+
+/// Returns the active account's own reactions on a message, optionally only those with a
+/// given emoji
+pub async fn own_reactions(
+    mls_group_id: &[u8],
+    target_event_id: &EventId,
+    emoji: Option<&str>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<Message>> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let reactions = reactions_for_message(mls_group_id, target_event_id, wn).await?;
+    Ok(reactions
+        .into_iter()
+        .filter(|reaction| reaction.author_pubkey == account_pubkey)
+        .filter(|reaction| emoji.is_none_or(|emoji| reaction.content.trim() == emoji.trim()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reaction(author: &Keys, content: &str, target: EventId) -> Message {
+        let mut event = UnsignedEvent::new(
+            author.public_key(),
+            Timestamp::now(),
+            Kind::Reaction,
+            vec![Tag::event(target)],
+            content,
+        );
+        event.ensure_id();
+        Message {
+            event_id: event.id.unwrap(),
+            account_pubkey: author.public_key(),
+            author_pubkey: author.public_key(),
+            event_kind: REACTION_KIND,
+            mls_group_id: vec![],
+            created_at: event.created_at,
+            content: content.to_string(),
+            tags: event.tags.clone(),
+            event,
+            outer_event_id: EventId::all_zeros(),
+            tokens: vec![],
+            semantics: Default::default(),
+            reply_to: None,
+            thread_root: None,
+            deleted_at: None,
+            edited_at: None,
+            author_migrated_to: None,
+            origin_group_id: None,
+            expires_at: None,
+            pending: false,
+            display_content: None,
+            payment_request: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_reaction_content() {
+        assert!(validate_reaction_content("👍").is_ok());
+        assert!(validate_reaction_content("+").is_ok());
+        assert!(validate_reaction_content("").is_err());
+        assert!(validate_reaction_content("   ").is_err());
+        assert!(validate_reaction_content(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_reaction_target_uses_last_e_tag() {
+        let first = EventId::all_zeros();
+        let last =
+            EventId::from_hex("1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef")
+                .unwrap();
+        let tags = Tags::from_list(vec![Tag::event(first), Tag::event(last)]);
+        assert_eq!(reaction_target(&tags), Some(last));
+        assert_eq!(reaction_target(&Tags::new()), None);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let target = EventId::all_zeros();
+
+        let reactions = vec![
+            reaction(&alice, "🔥", target),
+            reaction(&alice, "👍", target),
+            reaction(&bob, "👍", target),
+            // Duplicate reactions from the same author are only counted once
+            reaction(&bob, "👍", target),
+        ];
+
+        let summaries = aggregate(&reactions, &bob.public_key());
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].emoji, "👍");
+        assert_eq!(summaries[0].count, 2);
+        assert!(summaries[0].reacted_by_me);
+        assert_eq!(summaries[1].emoji, "🔥");
+        assert_eq!(summaries[1].count, 1);
+        assert!(!summaries[1].reacted_by_me);
+    }
+
+    #[test]
+    fn test_reactors_by_emoji() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let target = EventId::all_zeros();
+
+        let first = reaction(&alice, "👍", target);
+        let reactions = vec![
+            first.clone(),
+            reaction(&bob, " 👍 ", target),
+            reaction(&alice, "👍", target),
+            reaction(&bob, "🔥", target),
+        ];
+
+        let reactors = reactors_by_emoji(&reactions);
+        assert_eq!(reactors.len(), 2);
+
+        let thumbs_up = &reactors["👍"];
+        assert_eq!(thumbs_up.len(), 2);
+        assert_eq!(thumbs_up[0].pubkey, alice.public_key());
+        assert_eq!(thumbs_up[0].reaction_event_id, first.event_id);
+        assert_eq!(thumbs_up[0].reacted_at, first.created_at);
+        assert_eq!(thumbs_up[1].pubkey, bob.public_key());
+
+        assert_eq!(reactors["🔥"].len(), 1);
+    }
+}
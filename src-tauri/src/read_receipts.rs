@@ -0,0 +1,169 @@
+//! Read receipts.
+//!
+//! When the active account has read receipts enabled, marking a group as read sends a receipt
+//! rumor into the group: an inner event of kind [`READ_RECEIPT_KIND`] with an `e` tag for the
+//! latest message read. Receipts aren't part of the transcript; we keep the latest receipt from
+//! each member and emit `read_receipt` so frontends can show read status.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::Group;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The inner event kind used for read receipts
+pub const READ_RECEIPT_KIND: u16 = 1013;
+
+#[derive(Error, Debug)]
+pub enum ReadReceiptError {
+    #[error("Invalid read receipt: {0}")]
+    InvalidReceipt(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Failed to parse public key: {0}")]
+    PublicKeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ReadReceiptError>;
+
+#[derive(Debug, sqlx::FromRow)]
+struct ReadReceiptRow {
+    reader_pubkey: String,
+    event_id: String,
+    read_at: u64,
+}
+
+/// The latest message a group member has read. Payload of the `read_receipt` event.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ReadReceipt {
+    /// Hex encoded MLS group ID
+    pub group_id: String,
+    pub reader_pubkey: PublicKey,
+    /// The latest message the member has read
+    pub event_id: EventId,
+    pub read_at: Timestamp,
+}
+
+/// Builds the tags of a read receipt for `event_id`
+pub fn receipt_tags(event_id: EventId) -> Vec<Tag> {
+    vec![Tag::event(event_id)]
+}
+
+/// Returns the message a read receipt points at
+pub fn parse_receipt(rumor: &UnsignedEvent) -> Result<EventId> {
+    if rumor.kind.as_u16() != READ_RECEIPT_KIND {
+        return Err(ReadReceiptError::InvalidReceipt(format!(
+            "Unexpected kind {}",
+            rumor.kind
+        )));
+    }
+    rumor
+        .tags
+        .event_ids()
+        .next()
+        .copied()
+        .ok_or_else(|| ReadReceiptError::InvalidReceipt("Missing e tag".to_string()))
+}
+
+impl ReadReceipt {
+    /// Stores a read receipt received in `group`
+    ///
+    /// # Returns
+    /// * `Ok(Some(ReadReceipt))` - The receipt, if it's newer than the last one from its author
+    /// * `Ok(None)` - If we already have a newer receipt from the same member
+    pub async fn record(
+        group: &Group,
+        rumor: &UnsignedEvent,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Option<Self>> {
+        let event_id = parse_receipt(rumor)?;
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let result = sqlx::query(
+            "INSERT INTO read_receipts (account_pubkey, mls_group_id, reader_pubkey, event_id, read_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(account_pubkey, mls_group_id, reader_pubkey) DO UPDATE SET
+                 event_id = excluded.event_id,
+                 read_at = excluded.read_at
+             WHERE excluded.read_at > read_receipts.read_at",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(&group.mls_group_id)
+        .bind(rumor.pubkey.to_hex())
+        .bind(event_id.to_hex())
+        .bind(rumor.created_at.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            group_id: hex::encode(&group.mls_group_id),
+            reader_pubkey: rumor.pubkey,
+            event_id,
+            read_at: rumor.created_at,
+        }))
+    }
+
+    /// Returns the latest read receipt from each member of a group
+    pub async fn for_group(group: &Group, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Self>> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let rows = sqlx::query_as::<_, ReadReceiptRow>(
+            "SELECT reader_pubkey, event_id, read_at FROM read_receipts
+             WHERE account_pubkey = ? AND mls_group_id = ? ORDER BY read_at DESC",
+        )
+        .bind(account_pubkey.to_hex())
+        .bind(&group.mls_group_id)
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        let group_id = hex::encode(&group.mls_group_id);
+        rows.into_iter()
+            .map(|row| {
+                Ok(Self {
+                    group_id: group_id.clone(),
+                    reader_pubkey: PublicKey::from_hex(&row.reader_pubkey)?,
+                    event_id: EventId::from_hex(&row.event_id)?,
+                    read_at: Timestamp::from(row.read_at),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rumor(kind: u16, tags: Vec<Tag>) -> UnsignedEvent {
+        EventBuilder::new(Kind::Custom(kind), "")
+            .tags(tags)
+            .build(Keys::generate().public_key())
+    }
+
+    #[test]
+    fn test_parse_receipt() {
+        let event_id = EventId::all_zeros();
+        let receipt = rumor(READ_RECEIPT_KIND, receipt_tags(event_id));
+        assert_eq!(parse_receipt(&receipt).unwrap(), event_id);
+    }
+
+    #[test]
+    fn test_parse_receipt_rejects_invalid_receipts() {
+        assert!(parse_receipt(&rumor(READ_RECEIPT_KIND, vec![])).is_err());
+        let tags = receipt_tags(EventId::all_zeros());
+        assert!(parse_receipt(&rumor(9, tags)).is_err());
+    }
+}
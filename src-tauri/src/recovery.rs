@@ -0,0 +1,938 @@
+//! Account recovery through trusted contacts.
+//!
+//! An account can designate recovery contacts and a threshold (see
+//! `designate_recovery_contacts`). A random recovery secret is split into one Shamir share per
+//! contact, any `threshold` of which rebuild it, and each contact is sent their share
+//! gift-wrapped, along with the commitment: the public key of a key pair derived from the secret
+//! (see [`proof_keys`]). Nothing secret stays on the device.
+//!
+//! After losing the device, the user creates a new identity and asks the contacts for help
+//! (`request_account_recovery`). Each contact checks that the request is genuine, out of band,
+//! and approves it, which releases their share to the new identity. Once the new identity holds
+//! enough shares it rebuilds the secret and sends each contact a proof: an event signed with the
+//! derived key that names the old and the new identity. The secret itself never leaves the
+//! device, and a proof can't be replayed for another identity. A contact that approved the
+//! sender's request and can verify the proof against its commitment links the new identity to
+//! the old one like a key migration and is prompted to re-invite it to the groups they shared
+//! (see `key_migrations`). The share is then deleted, so a secret only recovers an account once.
+
+use crate::accounts::{Account, AccountError};
+use crate::key_migrations::{KeyMigration, KeyMigrationError};
+use crate::nostr_manager::NostrManagerError;
+use crate::relay_blacklist::{self, RelayBlacklistError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tauri::Emitter;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// The rumor kind of recovery messages
+pub const RECOVERY_KIND: u16 = 1778;
+
+/// Most recovery contacts an account can have
+pub const MAX_RECOVERY_CONTACTS: usize = 16;
+
+/// Length of the recovery secret in bytes
+const SECRET_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum RecoveryError {
+    #[error("Invalid parameters: {0}")]
+    InvalidParameters(String),
+
+    #[error("Invalid recovery message: {0}")]
+    InvalidMessage(String),
+
+    #[error("No recovery request from {0}")]
+    RequestNotFound(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Key migration error: {0}")]
+    KeyMigrationError(#[from] KeyMigrationError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+
+    #[error("Nostr event error: {0}")]
+    NostrEventError(#[from] nostr_sdk::event::builder::Error),
+
+    #[error("Failed to parse public key: {0}")]
+    PublicKeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RecoveryError>;
+
+/// The recovery contacts of an account, kept in its settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryContacts {
+    pub contacts: Vec<PublicKey>,
+    /// How many of the contacts it takes to recover the account
+    pub threshold: u8,
+    /// Hex encoded public key derived from the recovery secret, see [`proof_keys`]
+    pub commitment: Option<String>,
+    pub designated_at: Option<Timestamp>,
+}
+
+/// One Shamir share of a recovery secret
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RecoveryShare {
+    /// Where the share was taken, from 1
+    pub index: u8,
+    /// Hex encoded share
+    pub share: String,
+    pub threshold: u8,
+    /// Hex encoded public key derived from the secret, see [`proof_keys`]
+    pub commitment: String,
+}
+
+/// The content of a recovery rumor
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecoveryMessage {
+    /// From an account to one of its recovery contacts
+    Share { share: RecoveryShare },
+    /// From an account to a contact that's no longer one of its recovery contacts
+    Revoke,
+    /// From a new identity to the recovery contacts of the account it recovers
+    Request { old_pubkey: PublicKey },
+    /// From a recovery contact to the new identity, once they approved its request
+    Release {
+        old_pubkey: PublicKey,
+        share: RecoveryShare,
+    },
+    /// From the new identity to the recovery contacts, once it rebuilt the secret
+    Proof {
+        old_pubkey: PublicKey,
+        /// Signed with the key derived from the secret, see [`sign_proof`]
+        proof: Event,
+    },
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct RecoveryRequestRow {
+    old_pubkey: String,
+    new_pubkey: String,
+    requested_at: u64,
+    approved_at: Option<u64>,
+}
+
+/// A request to help recover an account we hold a share for. Payload of `recovery_requested`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RecoveryRequest {
+    pub old_pubkey: PublicKey,
+    pub new_pubkey: PublicKey,
+    pub requested_at: Timestamp,
+    pub approved_at: Option<Timestamp>,
+}
+
+impl TryFrom<RecoveryRequestRow> for RecoveryRequest {
+    type Error = RecoveryError;
+
+    fn try_from(row: RecoveryRequestRow) -> Result<Self> {
+        Ok(Self {
+            old_pubkey: PublicKey::from_hex(&row.old_pubkey)?,
+            new_pubkey: PublicKey::from_hex(&row.new_pubkey)?,
+            requested_at: Timestamp::from(row.requested_at),
+            approved_at: row.approved_at.map(Timestamp::from),
+        })
+    }
+}
+
+/// How far the recovery of an old account got. Payload of `recovery_share_received`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RecoveryProgress {
+    pub old_pubkey: PublicKey,
+    /// Shares released to us so far
+    pub received: usize,
+    /// Shares needed, as far as the released shares tell
+    pub threshold: u8,
+}
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_inv(a: u8) -> u8 {
+    // a^254 is the inverse of a in GF(256)
+    let (mut result, mut base, mut exp) = (1, a, 254u8);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Splits a secret into `count` shares, any `threshold` of which rebuild it
+///
+/// # Returns
+/// * `Vec<(u8, Vec<u8>)>` - The index, from 1, and bytes of each share
+pub fn split(secret: &[u8], count: u8, threshold: u8) -> Vec<(u8, Vec<u8>)> {
+    let mut shares: Vec<(u8, Vec<u8>)> = (1..=count)
+        .map(|index| (index, Vec::with_capacity(secret.len())))
+        .collect();
+    let mut coefficients = vec![0u8; threshold.saturating_sub(1) as usize];
+    for &byte in secret {
+        // A random polynomial of degree threshold - 1 through the secret byte at 0
+        rand::rng().fill_bytes(&mut coefficients);
+        for (index, share) in shares.iter_mut() {
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, &coefficient| gf_mul(acc, *index) ^ coefficient);
+            share.push(gf_mul(y, *index) ^ byte);
+        }
+    }
+    shares
+}
+
+/// Rebuilds a secret from shares of it. Returns `None` if the shares don't fit together.
+///
+/// With fewer shares than the threshold the result is some unrelated value, so it has to be
+/// checked against the commitment.
+pub fn combine(shares: &[(u8, Vec<u8>)]) -> Option<Vec<u8>> {
+    let len = shares.first()?.1.len();
+    for (i, (index, share)) in shares.iter().enumerate() {
+        if *index == 0 || share.len() != len || shares[..i].iter().any(|(other, _)| other == index)
+        {
+            return None;
+        }
+    }
+
+    let mut secret = vec![0u8; len];
+    for (i, (xi, share)) in shares.iter().enumerate() {
+        // The Lagrange basis polynomial of the share, at 0
+        let basis = shares
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .fold(1, |acc, (_, (xj, _))| {
+                gf_mul(acc, gf_mul(*xj, gf_inv(xj ^ xi)))
+            });
+        for (byte, y) in secret.iter_mut().zip(share) {
+            *byte ^= gf_mul(*y, basis);
+        }
+    }
+    Some(secret)
+}
+
+/// The key pair derived from a recovery secret. Its public key is the commitment contacts keep
+/// with their share; its secret key signs the proof that the secret was rebuilt.
+fn proof_keys(secret: &[u8]) -> Option<Keys> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"whitenoise-recovery:");
+    hasher.update(secret);
+    let derived = Zeroizing::new(<[u8; 32]>::from(hasher.finalize()));
+    SecretKey::from_slice(derived.as_slice())
+        .ok()
+        .map(Keys::new)
+}
+
+/// The commitment to a recovery secret, see [`proof_keys`]
+fn commitment(secret: &[u8]) -> Option<String> {
+    proof_keys(secret).map(|keys| keys.public_key().to_hex())
+}
+
+/// Signs the proof that `new_pubkey` rebuilt the recovery secret of `old_pubkey`
+fn sign_proof(secret: &[u8], old_pubkey: PublicKey, new_pubkey: PublicKey) -> Result<Event> {
+    let keys = proof_keys(secret)
+        .ok_or_else(|| RecoveryError::InvalidParameters("Invalid recovery secret".to_string()))?;
+    Ok(EventBuilder::new(Kind::Custom(RECOVERY_KIND), "")
+        .tags(vec![
+            Tag::public_key(old_pubkey),
+            Tag::public_key(new_pubkey),
+        ])
+        .sign_with_keys(&keys)?)
+}
+
+/// Whether `proof` was signed with the key committed to and names both identities
+fn verify_proof(
+    proof: &Event,
+    commitment: &str,
+    old_pubkey: &PublicKey,
+    new_pubkey: &PublicKey,
+) -> bool {
+    let named: Vec<&PublicKey> = proof.tags.public_keys().collect();
+    proof.kind == Kind::Custom(RECOVERY_KIND)
+        && proof.pubkey.to_hex() == commitment
+        && proof.verify().is_ok()
+        && named.contains(&old_pubkey)
+        && named.contains(&new_pubkey)
+}
+
+/// Parses a recovery rumor
+pub fn parse(rumor: &UnsignedEvent) -> Result<RecoveryMessage> {
+    if rumor.kind != Kind::Custom(RECOVERY_KIND) {
+        return Err(RecoveryError::InvalidMessage(format!(
+            "Unexpected kind {}",
+            rumor.kind
+        )));
+    }
+    let message: RecoveryMessage = serde_json::from_str(&rumor.content)
+        .map_err(|e| RecoveryError::InvalidMessage(e.to_string()))?;
+    if let RecoveryMessage::Share { share } | RecoveryMessage::Release { share, .. } = &message {
+        let valid = share.index > 0
+            && share.threshold > 0
+            && hex::decode(&share.share).is_ok_and(|bytes| bytes.len() == SECRET_LEN)
+            && hex::decode(&share.commitment).is_ok_and(|bytes| bytes.len() == 32);
+        if !valid {
+            return Err(RecoveryError::InvalidMessage("Invalid share".to_string()));
+        }
+    }
+    Ok(message)
+}
+
+/// Gift-wraps a recovery message to `receiver` and publishes it to their inbox relays, or ours
+/// if they have none
+async fn send(
+    account: &Account,
+    receiver: &PublicKey,
+    message: &RecoveryMessage,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let rumor = EventBuilder::new(Kind::Custom(RECOVERY_KIND), serde_json::to_string(message)?)
+        .build(account.pubkey);
+    let signer = wn.nostr.client.signer().await?;
+    let wrapped = EventBuilder::gift_wrap(&signer, receiver, rumor, vec![]).await?;
+
+    let relays = wn.nostr.fetch_user_inbox_relays(*receiver).await?;
+    relay_blacklist::send_event(&wrapped, relays, wn).await?;
+    Ok(())
+}
+
+/// Makes `contact_pubkeys` the recovery contacts of the active account, any `threshold` of whom
+/// can help recover it. Contacts that are dropped are asked to delete their share.
+pub async fn designate(
+    contact_pubkeys: &[String],
+    threshold: u8,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account> {
+    let mut account = Account::get_active(wn.clone()).await?;
+
+    let mut contacts: Vec<PublicKey> = Vec::new();
+    for pubkey in contact_pubkeys {
+        let pubkey = PublicKey::parse(pubkey)?;
+        if pubkey == account.pubkey {
+            return Err(RecoveryError::InvalidParameters(
+                "You can't be your own recovery contact".to_string(),
+            ));
+        }
+        if !contacts.contains(&pubkey) {
+            contacts.push(pubkey);
+        }
+    }
+    if contacts.len() > MAX_RECOVERY_CONTACTS {
+        return Err(RecoveryError::InvalidParameters(format!(
+            "At most {} recovery contacts are supported",
+            MAX_RECOVERY_CONTACTS
+        )));
+    }
+    if contacts.is_empty() && threshold != 0 {
+        return Err(RecoveryError::InvalidParameters(
+            "Choose at least one recovery contact".to_string(),
+        ));
+    }
+    if !contacts.is_empty() && (threshold == 0 || threshold as usize > contacts.len()) {
+        return Err(RecoveryError::InvalidParameters(format!(
+            "The threshold must be between 1 and {}",
+            contacts.len()
+        )));
+    }
+
+    let mut secret = Zeroizing::new([0u8; SECRET_LEN]);
+    let secret_commitment = loop {
+        rand::rng().fill_bytes(secret.as_mut());
+        // Fails for about one in 2^128 secrets
+        if let Some(commitment) = commitment(secret.as_ref()) {
+            break commitment;
+        }
+    };
+    let shares = split(secret.as_ref(), contacts.len() as u8, threshold);
+    for (contact, (index, share)) in contacts.iter().zip(shares) {
+        let share = Zeroizing::new(share);
+        let message = RecoveryMessage::Share {
+            share: RecoveryShare {
+                index,
+                share: hex::encode(share.as_slice()),
+                threshold,
+                commitment: secret_commitment.clone(),
+            },
+        };
+        send(&account, contact, &message, wn.clone()).await?;
+    }
+
+    let previous = std::mem::take(&mut account.settings.recovery_contacts);
+    for contact in previous.contacts.iter().filter(|c| !contacts.contains(c)) {
+        if let Err(e) = send(&account, contact, &RecoveryMessage::Revoke, wn.clone()).await {
+            tracing::warn!(
+                target: "whitenoise::recovery::designate",
+                "Failed to revoke the recovery share of {}: {}",
+                contact.to_hex(),
+                e
+            );
+        }
+    }
+
+    account.settings.recovery_contacts = if contacts.is_empty() {
+        RecoveryContacts::default()
+    } else {
+        RecoveryContacts {
+            contacts,
+            threshold,
+            commitment: Some(secret_commitment),
+            designated_at: Some(Timestamp::now()),
+        }
+    };
+    Ok(account.save(wn.clone()).await?)
+}
+
+/// Asks the recovery contacts of `old_pubkey` to help the active account, a new identity,
+/// recover it
+pub async fn request(
+    old_pubkey: &PublicKey,
+    contact_pubkeys: &[PublicKey],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let account = Account::get_active(wn.clone()).await?;
+    if *old_pubkey == account.pubkey {
+        return Err(RecoveryError::InvalidParameters(
+            "Recover the account from a new identity".to_string(),
+        ));
+    }
+    if contact_pubkeys.is_empty() {
+        return Err(RecoveryError::InvalidParameters(
+            "Choose the recovery contacts to ask".to_string(),
+        ));
+    }
+
+    sqlx::query(
+        "INSERT INTO account_recoveries (account_pubkey, old_pubkey, contacts, requested_at)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT(account_pubkey, old_pubkey) DO UPDATE SET
+             contacts = excluded.contacts,
+             requested_at = excluded.requested_at,
+             completed_at = NULL",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .bind(serde_json::to_string(contact_pubkeys)?)
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+
+    let message = RecoveryMessage::Request {
+        old_pubkey: *old_pubkey,
+    };
+    for contact in contact_pubkeys {
+        send(&account, contact, &message, wn.clone()).await?;
+    }
+    Ok(())
+}
+
+/// The recovery requests the active account hasn't approved yet, newest first
+pub async fn pending_requests(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<RecoveryRequest>> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let rows = sqlx::query_as::<_, RecoveryRequestRow>(
+        "SELECT old_pubkey, new_pubkey, requested_at, approved_at FROM recovery_requests
+         WHERE account_pubkey = ? AND approved_at IS NULL ORDER BY requested_at DESC",
+    )
+    .bind(account_pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+    rows.into_iter().map(RecoveryRequest::try_from).collect()
+}
+
+/// The share the active account holds for `owner_pubkey`, if any
+async fn held_share(
+    account_pubkey: &PublicKey,
+    owner_pubkey: &PublicKey,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<Option<RecoveryShare>> {
+    let row = sqlx::query_as::<_, (i64, String, i64, String)>(
+        "SELECT share_index, share, threshold, commitment FROM recovery_shares
+         WHERE account_pubkey = ? AND owner_pubkey = ?",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(owner_pubkey.to_hex())
+    .fetch_optional(&wn.database.pool)
+    .await?;
+    Ok(
+        row.map(|(index, share, threshold, commitment)| RecoveryShare {
+            index: index as u8,
+            share,
+            threshold: threshold as u8,
+            commitment,
+        }),
+    )
+}
+
+/// Approves a recovery request, releasing our share of the old account to the new identity
+pub async fn approve(
+    old_pubkey: &PublicKey,
+    new_pubkey: &PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let account = Account::get_active(wn.clone()).await?;
+    let requested = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM recovery_requests
+         WHERE account_pubkey = ? AND old_pubkey = ? AND new_pubkey = ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .bind(new_pubkey.to_hex())
+    .fetch_one(&wn.database.pool)
+    .await?;
+    let share = held_share(&account.pubkey, old_pubkey, &wn).await?;
+    let (true, Some(share)) = (requested, share) else {
+        return Err(RecoveryError::RequestNotFound(new_pubkey.to_hex()));
+    };
+
+    let message = RecoveryMessage::Release {
+        old_pubkey: *old_pubkey,
+        share,
+    };
+    send(&account, new_pubkey, &message, wn.clone()).await?;
+
+    sqlx::query(
+        "UPDATE recovery_requests SET approved_at = ?
+         WHERE account_pubkey = ? AND old_pubkey = ? AND new_pubkey = ?",
+    )
+    .bind(Timestamp::now().as_u64() as i64)
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .bind(new_pubkey.to_hex())
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(())
+}
+
+/// Rebuilds the secret of an old account from the shares released to us, if there are enough
+/// of them that agree on it
+fn rebuild(shares: &[RecoveryShare]) -> Option<Zeroizing<Vec<u8>>> {
+    let mut by_commitment: HashMap<&str, Vec<&RecoveryShare>> = HashMap::new();
+    for share in shares {
+        by_commitment
+            .entry(share.commitment.as_str())
+            .or_default()
+            .push(share);
+    }
+    by_commitment.into_iter().find_map(|(expected, shares)| {
+        let threshold = shares.iter().map(|share| share.threshold).max()? as usize;
+        if shares.len() < threshold {
+            return None;
+        }
+        let points: Vec<(u8, Vec<u8>)> = shares
+            .iter()
+            .take(threshold)
+            .map(|share| Some((share.index, hex::decode(&share.share).ok()?)))
+            .collect::<Option<_>>()?;
+        combine(&points)
+            .map(Zeroizing::new)
+            .filter(|secret| commitment(secret).is_some_and(|commitment| commitment == expected))
+    })
+}
+
+/// Stores a share released to the active account and finishes the recovery once there are
+/// enough, sending the proof to the contacts
+async fn receive_release(
+    sender: PublicKey,
+    old_pubkey: PublicKey,
+    share: RecoveryShare,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let account = Account::get_active(wn.clone()).await?;
+    let Some(contacts) = sqlx::query_scalar::<_, String>(
+        "SELECT contacts FROM account_recoveries
+         WHERE account_pubkey = ? AND old_pubkey = ? AND completed_at IS NULL",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .fetch_optional(&wn.database.pool)
+    .await?
+    else {
+        return Ok(());
+    };
+    let contacts: Vec<PublicKey> = serde_json::from_str(&contacts)?;
+    if !contacts.contains(&sender) {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO released_recovery_shares
+             (account_pubkey, old_pubkey, holder_pubkey, share_index, share, threshold, commitment, received_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .bind(sender.to_hex())
+    .bind(share.index as i64)
+    .bind(&share.share)
+    .bind(share.threshold as i64)
+    .bind(&share.commitment)
+    .bind(Timestamp::now().as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+
+    let shares: Vec<RecoveryShare> = sqlx::query_as::<_, (i64, String, i64, String)>(
+        "SELECT share_index, share, threshold, commitment FROM released_recovery_shares
+         WHERE account_pubkey = ? AND old_pubkey = ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?
+    .into_iter()
+    .map(|(index, share, threshold, commitment)| RecoveryShare {
+        index: index as u8,
+        share,
+        threshold: threshold as u8,
+        commitment,
+    })
+    .collect();
+
+    let Some(secret) = rebuild(&shares) else {
+        app_handle.emit(
+            "recovery_share_received",
+            RecoveryProgress {
+                old_pubkey,
+                received: shares.len(),
+                threshold: shares
+                    .iter()
+                    .map(|share| share.threshold)
+                    .max()
+                    .unwrap_or(0),
+            },
+        )?;
+        return Ok(());
+    };
+
+    let proof = RecoveryMessage::Proof {
+        old_pubkey,
+        proof: sign_proof(&secret, old_pubkey, account.pubkey)?,
+    };
+    drop(secret);
+    for contact in &contacts {
+        if let Err(e) = send(&account, contact, &proof, wn.clone()).await {
+            tracing::warn!(
+                target: "whitenoise::recovery::receive_release",
+                "Failed to send the recovery proof to {}: {}",
+                contact.to_hex(),
+                e
+            );
+        }
+    }
+
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query(
+        "UPDATE account_recoveries SET completed_at = ? WHERE account_pubkey = ? AND old_pubkey = ?",
+    )
+    .bind(Timestamp::now().as_u64() as i64)
+    .bind(account.pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .execute(&mut *txn)
+    .await?;
+    sqlx::query("DELETE FROM released_recovery_shares WHERE account_pubkey = ? AND old_pubkey = ?")
+        .bind(account.pubkey.to_hex())
+        .bind(old_pubkey.to_hex())
+        .execute(&mut *txn)
+        .await?;
+    txn.commit().await?;
+
+    tracing::info!(
+        target: "whitenoise::recovery::receive_release",
+        "Recovered {} with {} shares",
+        old_pubkey.to_hex(),
+        shares.len()
+    );
+    app_handle.emit("account_recovery_completed", old_pubkey)?;
+    Ok(())
+}
+
+/// Checks a recovery proof against the share we hold for the old account, and if it's valid and
+/// comes from an identity whose request we approved, links that identity to the old one and
+/// prompts the user to re-invite it
+async fn receive_proof(
+    rumor: &UnsignedEvent,
+    sender: PublicKey,
+    old_pubkey: PublicKey,
+    proof: &Event,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    let Some(share) = held_share(&account_pubkey, &old_pubkey, &wn).await? else {
+        return Ok(());
+    };
+    let approved = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM recovery_requests
+         WHERE account_pubkey = ? AND old_pubkey = ? AND new_pubkey = ? AND approved_at IS NOT NULL)",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(old_pubkey.to_hex())
+    .bind(sender.to_hex())
+    .fetch_one(&wn.database.pool)
+    .await?;
+    if !approved {
+        tracing::warn!(
+            target: "whitenoise::recovery::receive_proof",
+            "{} sent a recovery proof for {} without an approved request",
+            sender.to_hex(),
+            old_pubkey.to_hex()
+        );
+        return Ok(());
+    }
+    if !verify_proof(proof, &share.commitment, &old_pubkey, &sender) {
+        tracing::warn!(
+            target: "whitenoise::recovery::receive_proof",
+            "{} sent a wrong recovery proof for {}",
+            sender.to_hex(),
+            old_pubkey.to_hex()
+        );
+        return Ok(());
+    }
+
+    let event_id = rumor
+        .id
+        .ok_or_else(|| RecoveryError::InvalidMessage("Rumor has no ID".to_string()))?;
+    let migration =
+        KeyMigration::link(old_pubkey, sender, event_id, rumor.created_at, wn.clone()).await?;
+
+    // The secret is known now, so the share is useless
+    sqlx::query("DELETE FROM recovery_shares WHERE account_pubkey = ? AND owner_pubkey = ?")
+        .bind(account_pubkey.to_hex())
+        .bind(old_pubkey.to_hex())
+        .execute(&wn.database.pool)
+        .await?;
+
+    if let Some(migration) = migration {
+        tracing::debug!(
+            target: "whitenoise::recovery::receive_proof",
+            "Contact {} recovered as {}",
+            migration.old_pubkey,
+            migration.new_pubkey
+        );
+        let prompt = migration.prompt(wn.clone()).await?;
+        app_handle.emit("contact_key_migrated", prompt)?;
+    }
+    Ok(())
+}
+
+/// Handles a recovery rumor sent to the active account
+pub async fn handle(
+    rumor: &UnsignedEvent,
+    sender: PublicKey,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+    if sender == account_pubkey {
+        return Ok(());
+    }
+
+    match parse(rumor)? {
+        RecoveryMessage::Share { share } => {
+            sqlx::query(
+                "INSERT OR REPLACE INTO recovery_shares
+                     (account_pubkey, owner_pubkey, share_index, share, threshold, commitment, received_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(account_pubkey.to_hex())
+            .bind(sender.to_hex())
+            .bind(share.index as i64)
+            .bind(&share.share)
+            .bind(share.threshold as i64)
+            .bind(&share.commitment)
+            .bind(rumor.created_at.as_u64() as i64)
+            .execute(&wn.database.pool)
+            .await?;
+        }
+        RecoveryMessage::Revoke => {
+            sqlx::query(
+                "DELETE FROM recovery_shares WHERE account_pubkey = ? AND owner_pubkey = ?",
+            )
+            .bind(account_pubkey.to_hex())
+            .bind(sender.to_hex())
+            .execute(&wn.database.pool)
+            .await?;
+        }
+        RecoveryMessage::Request { old_pubkey } => {
+            if held_share(&account_pubkey, &old_pubkey, &wn)
+                .await?
+                .is_none()
+            {
+                return Ok(());
+            }
+            let inserted = sqlx::query(
+                "INSERT OR IGNORE INTO recovery_requests (account_pubkey, old_pubkey, new_pubkey, requested_at)
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(account_pubkey.to_hex())
+            .bind(old_pubkey.to_hex())
+            .bind(sender.to_hex())
+            .bind(rumor.created_at.as_u64() as i64)
+            .execute(&wn.database.pool)
+            .await?;
+            if inserted.rows_affected() > 0 {
+                app_handle.emit(
+                    "recovery_requested",
+                    RecoveryRequest {
+                        old_pubkey,
+                        new_pubkey: sender,
+                        requested_at: rumor.created_at,
+                        approved_at: None,
+                    },
+                )?;
+            }
+        }
+        RecoveryMessage::Release { old_pubkey, share } => {
+            receive_release(sender, old_pubkey, share, wn, app_handle).await?;
+        }
+        RecoveryMessage::Proof { old_pubkey, proof } => {
+            receive_proof(rumor, sender, old_pubkey, &proof, wn, app_handle).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_threshold_shares_rebuild_the_secret() {
+        let secret: Vec<u8> = (0..SECRET_LEN as u8).collect();
+        let shares = split(&secret, 5, 3);
+        assert_eq!(shares.len(), 5);
+
+        assert_eq!(combine(&shares[..3]).unwrap(), secret);
+        assert_eq!(combine(&shares[2..]).unwrap(), secret);
+        let picked = vec![shares[4].clone(), shares[0].clone(), shares[2].clone()];
+        assert_eq!(combine(&picked).unwrap(), secret);
+        // Fewer shares give something else
+        assert_ne!(combine(&shares[..2]).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_combine_rejects_mismatched_shares() {
+        let shares = split(&[1, 2, 3], 3, 2);
+        assert!(combine(&[]).is_none());
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_none());
+        assert!(combine(&[shares[0].clone(), (2, vec![1])]).is_none());
+    }
+
+    #[test]
+    fn test_rebuild_checks_the_commitment() {
+        let secret = [7u8; SECRET_LEN];
+        let shares: Vec<RecoveryShare> = split(&secret, 3, 2)
+            .into_iter()
+            .map(|(index, share)| RecoveryShare {
+                index,
+                share: hex::encode(share),
+                threshold: 2,
+                commitment: commitment(&secret).unwrap(),
+            })
+            .collect();
+
+        assert!(rebuild(&shares[..1]).is_none());
+        assert_eq!(rebuild(&shares[1..]).unwrap().as_slice(), secret);
+
+        let mut tampered = shares.clone();
+        tampered[0].share = hex::encode([0u8; SECRET_LEN]);
+        assert!(rebuild(&tampered[..2]).is_none());
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_the_new_identity() {
+        let secret = [7u8; SECRET_LEN];
+        let committed = commitment(&secret).unwrap();
+        let old_pubkey = Keys::generate().public_key();
+        let new_pubkey = Keys::generate().public_key();
+        let proof = sign_proof(&secret, old_pubkey, new_pubkey).unwrap();
+
+        assert!(verify_proof(&proof, &committed, &old_pubkey, &new_pubkey));
+        assert!(!proof.content.contains(&hex::encode(secret)));
+
+        // Replayed for another identity or account
+        let other = Keys::generate().public_key();
+        assert!(!verify_proof(&proof, &committed, &old_pubkey, &other));
+        assert!(!verify_proof(&proof, &committed, &other, &new_pubkey));
+
+        // Signed with a key derived from another secret
+        let forged = sign_proof(&[8u8; SECRET_LEN], old_pubkey, new_pubkey).unwrap();
+        assert!(!verify_proof(&forged, &committed, &old_pubkey, &new_pubkey));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_shares() {
+        let keys = Keys::generate();
+        let rumor = |message: &RecoveryMessage| {
+            EventBuilder::new(
+                Kind::Custom(RECOVERY_KIND),
+                serde_json::to_string(message).unwrap(),
+            )
+            .build(keys.public_key())
+        };
+        let share = RecoveryShare {
+            index: 1,
+            share: hex::encode([1u8; SECRET_LEN]),
+            threshold: 2,
+            commitment: commitment(&[0u8; SECRET_LEN]).unwrap(),
+        };
+
+        assert!(parse(&rumor(&RecoveryMessage::Share {
+            share: share.clone()
+        }))
+        .is_ok());
+        assert!(parse(&rumor(&RecoveryMessage::Share {
+            share: RecoveryShare {
+                index: 0,
+                ..share.clone()
+            }
+        }))
+        .is_err());
+        assert!(parse(&rumor(&RecoveryMessage::Release {
+            old_pubkey: keys.public_key(),
+            share: RecoveryShare {
+                share: "zz".to_string(),
+                ..share
+            }
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! Relays that welcomes and messages must never be routed through.
+//!
+//! Users can blacklist relays themselves. Relays are also blacklisted automatically once they
+//! have rejected our events as `blocked` [`VIOLATION_THRESHOLD`] times, which is how censoring
+//! relays show up. `restricted` rejections don't count: they come from relays that want a paid
+//! membership or authentication, not from relays that refuse us. Everything that picks relays to
+//! publish to filters its candidates through [`RelayBlacklist::filter`], or publishes through
+//! [`send_event`] which does it for them.
+
+use crate::accounts::{Account, AccountError};
+use crate::nostr_manager::relay_rejections::{RejectionReason, RelayRejection};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// Protocol violations after which a relay is blacklisted automatically
+pub const VIOLATION_THRESHOLD: u32 = 3;
+
+#[derive(Error, Debug)]
+pub enum RelayBlacklistError {
+    #[error("Invalid relay URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("No relays to publish to that aren't blacklisted")]
+    NoRelays,
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RelayBlacklistError>;
+
+/// Why a relay is on the blacklist
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BlacklistSource {
+    /// Added by the user
+    User,
+    /// Added after repeated protocol violations
+    Automatic,
+}
+
+impl From<String> for BlacklistSource {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "automatic" => Self::Automatic,
+            _ => Self::User,
+        }
+    }
+}
+
+impl From<BlacklistSource> for String {
+    fn from(source: BlacklistSource) -> Self {
+        match source {
+            BlacklistSource::User => "user".to_string(),
+            BlacklistSource::Automatic => "automatic".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct BlacklistedRelayRow {
+    url: String,
+    source: String,
+    reason: Option<String>,
+    created_at: u64,
+}
+
+/// A blacklisted relay
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BlacklistedRelay {
+    pub url: String,
+    pub source: BlacklistSource,
+    pub reason: Option<String>,
+    pub created_at: Timestamp,
+}
+
+impl From<BlacklistedRelayRow> for BlacklistedRelay {
+    fn from(row: BlacklistedRelayRow) -> Self {
+        Self {
+            url: row.url,
+            source: row.source.into(),
+            reason: row.reason,
+            created_at: Timestamp::from(row.created_at),
+        }
+    }
+}
+
+/// Normalizes a relay URL so the same relay always has the same blacklist entry
+pub fn normalize_url(url: &str) -> Result<String> {
+    RelayUrl::parse(url.trim())
+        .map(|url| url.to_string())
+        .map_err(|e| RelayBlacklistError::InvalidUrl(format!("{}: {}", url, e)))
+}
+
+/// Whether a relay's rejection message counts as a protocol violation
+pub fn is_violation(message: &str) -> bool {
+    RelayRejection::parse(message).reason == RejectionReason::Blocked
+}
+
+/// Sends an event to `relays`, or to the client's relays if `relays` is empty, leaving out the
+/// active account's blacklisted relays and recording the violations among the rejections
+///
+/// Relays that aren't in the client's pool are only connected for the send.
+///
+/// # Errors
+/// Returns [`RelayBlacklistError::NoRelays`] if every relay is blacklisted.
+pub async fn send_event(
+    event: &Event,
+    relays: Vec<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Output<EventId>> {
+    let relays = if relays.is_empty() {
+        wn.nostr
+            .client
+            .relays()
+            .await
+            .into_keys()
+            .map(|url| url.to_string())
+            .collect()
+    } else {
+        relays
+    };
+    let relays = RelayBlacklist::load(wn.clone()).await?.filter(relays);
+    if relays.is_empty() {
+        return Err(RelayBlacklistError::NoRelays);
+    }
+
+    let mut added = Vec::new();
+    for url in &relays {
+        if wn.nostr.client.add_relay(url).await? {
+            added.push(url.clone());
+        }
+    }
+    let result = wn.nostr.client.send_event_to(relays, event).await;
+    for url in added {
+        if let Err(e) = wn.nostr.client.remove_relay(&url).await {
+            tracing::warn!(
+                target: "whitenoise::relay_blacklist::send_event",
+                "Failed to remove relay {}: {}",
+                url,
+                e
+            );
+        }
+    }
+
+    let output = result?;
+    RelayBlacklist::record_rejections(&output, wn).await;
+    Ok(output)
+}
+
+/// The active account's blacklisted relay URLs, loaded once per relay selection
+#[derive(Debug, Clone, Default)]
+pub struct RelayBlacklist {
+    urls: HashSet<String>,
+}
+
+impl RelayBlacklist {
+    /// Loads the active account's blacklist
+    pub async fn load(wn: tauri::State<'_, Whitenoise>) -> Result<Self> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let urls = sqlx::query_scalar::<_, String>(
+            "SELECT url FROM relay_blacklist WHERE account_pubkey = ?",
+        )
+        .bind(account_pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        Ok(Self {
+            urls: urls.into_iter().collect(),
+        })
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        normalize_url(url).is_ok_and(|url| self.urls.contains(&url))
+    }
+
+    /// Removes blacklisted relays from a set of candidate relays
+    pub fn filter(&self, relays: Vec<String>) -> Vec<String> {
+        relays
+            .into_iter()
+            .filter(|url| {
+                let blacklisted = self.contains(url);
+                if blacklisted {
+                    tracing::debug!(
+                        target: "whitenoise::relay_blacklist::filter",
+                        "Skipping blacklisted relay {}",
+                        url
+                    );
+                }
+                !blacklisted
+            })
+            .collect()
+    }
+
+    /// Returns the active account's blacklisted relays, newest first
+    pub async fn list(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<BlacklistedRelay>> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let rows = sqlx::query_as::<_, BlacklistedRelayRow>(
+            "SELECT url, source, reason, created_at FROM relay_blacklist
+             WHERE account_pubkey = ? ORDER BY created_at DESC",
+        )
+        .bind(account_pubkey.to_hex())
+        .fetch_all(&wn.database.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(BlacklistedRelay::from).collect())
+    }
+
+    /// Adds a relay to the active account's blacklist
+    pub async fn add(
+        url: &str,
+        source: BlacklistSource,
+        reason: Option<String>,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<BlacklistedRelay> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+        let relay = BlacklistedRelay {
+            url: normalize_url(url)?,
+            source,
+            reason,
+            created_at: Timestamp::now(),
+        };
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO relay_blacklist (url, account_pubkey, source, reason, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&relay.url)
+        .bind(account_pubkey.to_hex())
+        .bind(String::from(relay.source))
+        .bind(&relay.reason)
+        .bind(relay.created_at.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+
+        tracing::info!(
+            target: "whitenoise::relay_blacklist::add",
+            "Blacklisted relay {} ({:?})",
+            relay.url,
+            relay.source
+        );
+        Ok(relay)
+    }
+
+    /// Removes a relay from the active account's blacklist and forgets its violations
+    pub async fn remove(url: &str, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+        let url = normalize_url(url)?;
+
+        let mut txn = wn.database.pool.begin().await?;
+        sqlx::query("DELETE FROM relay_blacklist WHERE url = ? AND account_pubkey = ?")
+            .bind(&url)
+            .bind(account_pubkey.to_hex())
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM relay_violations WHERE url = ? AND account_pubkey = ?")
+            .bind(&url)
+            .bind(account_pubkey.to_hex())
+            .execute(&mut *txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Counts a protocol violation by a relay, blacklisting it once it reaches
+    /// [`VIOLATION_THRESHOLD`]
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the relay was blacklisted by this violation
+    pub async fn record_violation(
+        url: &str,
+        message: &str,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<bool> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+        let url = normalize_url(url)?;
+
+        let count: u32 = sqlx::query_scalar(
+            "INSERT INTO relay_violations (url, account_pubkey, count, last_message, last_violation_at)
+             VALUES (?, ?, 1, ?, ?)
+             ON CONFLICT(url, account_pubkey) DO UPDATE SET
+                 count = count + 1,
+                 last_message = excluded.last_message,
+                 last_violation_at = excluded.last_violation_at
+             RETURNING count",
+        )
+        .bind(&url)
+        .bind(account_pubkey.to_hex())
+        .bind(message)
+        .bind(Timestamp::now().as_u64() as i64)
+        .fetch_one(&wn.database.pool)
+        .await?;
+
+        tracing::warn!(
+            target: "whitenoise::relay_blacklist::record_violation",
+            "Relay {} rejected an event ({} violations): {}",
+            url,
+            count,
+            message
+        );
+
+        if count != VIOLATION_THRESHOLD {
+            return Ok(false);
+        }
+        Self::add(
+            &url,
+            BlacklistSource::Automatic,
+            Some(message.to_string()),
+            wn,
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Records the violations among the rejections of a publish attempt. Errors are logged,
+    /// never returned, so they can't fail the publish itself.
+    pub async fn record_rejections(output: &Output<EventId>, wn: tauri::State<'_, Whitenoise>) {
+        for (relay, message) in &output.failed {
+            if !is_violation(message) {
+                continue;
+            }
+            if let Err(e) = Self::record_violation(relay.as_str(), message, wn.clone()).await {
+                tracing::error!(
+                    target: "whitenoise::relay_blacklist::record_rejections",
+                    "Failed to record violation by {}: {}",
+                    relay,
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_violation() {
+        assert!(is_violation("blocked: you are banned"));
+        assert!(!is_violation("restricted: not allowed to write"));
+        assert!(is_violation("BLOCKED: kind 445 not accepted"));
+        assert!(!is_violation("rate-limited: slow down"));
+        assert!(!is_violation("duplicate: already have this event"));
+        assert!(!is_violation("timeout"));
+    }
+
+    #[test]
+    fn test_filter_skips_blacklisted_relays() {
+        let blacklist = RelayBlacklist {
+            urls: [normalize_url("wss://bad.example").unwrap()]
+                .into_iter()
+                .collect(),
+        };
+        assert_eq!(
+            blacklist.filter(vec![
+                "wss://good.example".to_string(),
+                " wss://bad.example".to_string(),
+            ]),
+            vec!["wss://good.example".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_normalize_url_rejects_invalid_urls() {
+        assert!(normalize_url("not a relay").is_err());
+    }
+
+    #[test]
+    fn test_source_round_trips_through_string() {
+        for source in [BlacklistSource::User, BlacklistSource::Automatic] {
+            assert_eq!(BlacklistSource::from(String::from(source)), source);
+        }
+    }
+}
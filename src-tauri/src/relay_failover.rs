@@ -0,0 +1,325 @@
+//! Publishing key packages and welcomes with relay failover.
+//!
+//! Key packages and welcomes are only useful if the people who need them can find them. When
+//! none of the primary relays accept one (they reject it, time out or can't be reached) it's
+//! published to the account's fallback relays instead (see `set_fallback_relays`).
+//!
+//! The relays that acknowledged each artifact are recorded in `published_artifacts`, so later
+//! deletions and resends go to the relays that actually hold it rather than whatever the relay
+//! lists say now.
+
+use crate::accounts::{Account, AccountError};
+use crate::relay_blacklist::{RelayBlacklist, RelayBlacklistError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long to wait for a set of relays to acknowledge an event
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Attempts on the primary relays before failing over
+const PRIMARY_ATTEMPTS: u32 = 3;
+
+/// Delay between attempts on the primary relays
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Error, Debug)]
+pub enum RelayFailoverError {
+    #[error("No relay accepted the event. Last error: {0}")]
+    NotPublished(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("Failed to parse event ID: {0}")]
+    EventIdError(#[from] nostr_sdk::event::Error),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+}
+
+pub type Result<T> = std::result::Result<T, RelayFailoverError>;
+
+/// The kind of artifact that was published
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    KeyPackage,
+    Welcome,
+}
+
+impl From<String> for ArtifactKind {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "welcome" => Self::Welcome,
+            _ => Self::KeyPackage,
+        }
+    }
+}
+
+impl From<ArtifactKind> for String {
+    fn from(kind: ArtifactKind) -> Self {
+        match kind {
+            ArtifactKind::KeyPackage => "key_package".to_string(),
+            ArtifactKind::Welcome => "welcome".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct PublishedArtifactRow {
+    event_id: String,
+    kind: String,
+    recipient_pubkey: Option<String>,
+    relays: String,
+    used_fallback: bool,
+    published_at: u64,
+}
+
+/// Where a key package or welcome was published
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PublishedArtifact {
+    /// ID of the published event (for welcomes, the gift wrap)
+    pub event_id: EventId,
+    pub kind: ArtifactKind,
+    /// The member a welcome was sent to
+    pub recipient_pubkey: Option<PublicKey>,
+    /// Relays that acknowledged the event
+    pub relays: BTreeSet<String>,
+    /// Whether the primary relays failed and the event went to the fallback relays
+    pub used_fallback: bool,
+    pub published_at: Timestamp,
+}
+
+impl TryFrom<PublishedArtifactRow> for PublishedArtifact {
+    type Error = RelayFailoverError;
+
+    fn try_from(row: PublishedArtifactRow) -> Result<Self> {
+        Ok(Self {
+            event_id: EventId::from_hex(&row.event_id)?,
+            kind: row.kind.into(),
+            recipient_pubkey: row
+                .recipient_pubkey
+                .and_then(|pubkey| PublicKey::from_hex(&pubkey).ok()),
+            relays: serde_json::from_str(&row.relays)?,
+            used_fallback: row.used_fallback,
+            published_at: Timestamp::from(row.published_at),
+        })
+    }
+}
+
+impl PublishedArtifact {
+    /// Finds where the active account published an event
+    pub async fn find(
+        event_id: &EventId,
+        wn: tauri::State<'_, Whitenoise>,
+    ) -> Result<Option<Self>> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        let row = sqlx::query_as::<_, PublishedArtifactRow>(
+            "SELECT event_id, kind, recipient_pubkey, relays, used_fallback, published_at
+             FROM published_artifacts WHERE event_id = ? AND account_pubkey = ?",
+        )
+        .bind(event_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .fetch_optional(&wn.database.pool)
+        .await?;
+
+        row.map(Self::try_from).transpose()
+    }
+
+    async fn save(&self, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+        let account_pubkey = Account::get_active_pubkey(wn.clone()).await?;
+
+        sqlx::query(
+            "INSERT OR REPLACE INTO published_artifacts (event_id, account_pubkey, kind, recipient_pubkey, relays, used_fallback, published_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(self.event_id.to_hex())
+        .bind(account_pubkey.to_hex())
+        .bind(String::from(self.kind))
+        .bind(self.recipient_pubkey.map(|pubkey| pubkey.to_hex()))
+        .bind(serde_json::to_string(&self.relays)?)
+        .bind(self.used_fallback)
+        .bind(self.published_at.as_u64() as i64)
+        .execute(&wn.database.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// The fallback relays to try, leaving out the primary relays that already failed
+pub fn fallback_targets(primary: &[String], fallback: &[String]) -> Vec<String> {
+    let mut seen: BTreeSet<&str> = primary.iter().map(String::as_str).collect();
+    fallback
+        .iter()
+        .filter(|url| seen.insert(url.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Publishes a key package or welcome, failing over to the active account's fallback relays
+/// when no primary relay accepts it, and records the relays that hold it.
+///
+/// The primary relays get [`PRIMARY_ATTEMPTS`] attempts. Blacklisted relays are skipped and
+/// relays that aren't in the client's pool are only connected for the publish.
+pub async fn publish_with_failover(
+    event: &Event,
+    primary_relays: Vec<String>,
+    kind: ArtifactKind,
+    recipient_pubkey: Option<PublicKey>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<PublishedArtifact> {
+    let blacklist = RelayBlacklist::load(wn.clone()).await?;
+    let primary_relays = blacklist.filter(primary_relays);
+    let mut last_error = "no relays to publish to".to_string();
+    let mut acked = BTreeSet::new();
+
+    for attempt in 1..=PRIMARY_ATTEMPTS {
+        match publish_to(event, &primary_relays, wn.clone()).await {
+            Ok(relays) if !relays.is_empty() => {
+                acked = relays;
+                break;
+            }
+            Ok(_) => last_error = "rejected by all primary relays".to_string(),
+            Err(e) => last_error = e,
+        }
+        tracing::warn!(
+            target: "whitenoise::relay_failover::publish_with_failover",
+            "Attempt {} to publish {} to primary relays failed: {}",
+            attempt,
+            event.id,
+            last_error
+        );
+        if attempt < PRIMARY_ATTEMPTS {
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+
+    let mut used_fallback = false;
+    if acked.is_empty() {
+        let account = Account::get_active(wn.clone()).await?;
+        let fallback = blacklist.filter(fallback_targets(
+            &primary_relays,
+            &account.settings.fallback_relays,
+        ));
+        if !fallback.is_empty() {
+            tracing::info!(
+                target: "whitenoise::relay_failover::publish_with_failover",
+                "Failing over to {} fallback relays for {}",
+                fallback.len(),
+                event.id
+            );
+            used_fallback = true;
+            match publish_to(event, &fallback, wn.clone()).await {
+                Ok(relays) if !relays.is_empty() => acked = relays,
+                Ok(_) => last_error = "rejected by all fallback relays".to_string(),
+                Err(e) => last_error = e,
+            }
+        }
+    }
+
+    if acked.is_empty() {
+        return Err(RelayFailoverError::NotPublished(last_error));
+    }
+
+    let artifact = PublishedArtifact {
+        event_id: event.id,
+        kind,
+        recipient_pubkey,
+        relays: acked,
+        used_fallback,
+        published_at: Timestamp::now(),
+    };
+    artifact.save(wn).await?;
+    Ok(artifact)
+}
+
+/// Sends an event to a set of relays and returns the ones that acknowledged it. Relays that
+/// weren't already in the pool are removed again afterwards.
+async fn publish_to(
+    event: &Event,
+    relays: &[String],
+    wn: tauri::State<'_, Whitenoise>,
+) -> std::result::Result<BTreeSet<String>, String> {
+    if relays.is_empty() {
+        return Err("no relays to publish to".to_string());
+    }
+
+    let mut added = Vec::new();
+    for url in relays {
+        if wn
+            .nostr
+            .client
+            .add_relay(url)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            added.push(url.clone());
+        }
+    }
+
+    let result = tokio::time::timeout(
+        PUBLISH_TIMEOUT,
+        wn.nostr.client.send_event_to(relays.to_vec(), event),
+    )
+    .await;
+
+    for url in added {
+        if let Err(e) = wn.nostr.client.remove_relay(&url).await {
+            tracing::warn!(
+                target: "whitenoise::relay_failover::publish_to",
+                "Failed to remove relay {}: {}",
+                url,
+                e
+            );
+        }
+    }
+
+    match result {
+        Ok(Ok(output)) => {
+            RelayBlacklist::record_rejections(&output, wn).await;
+            Ok(output.success.iter().map(|url| url.to_string()).collect())
+        }
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("timed out".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_targets_skip_primary_relays() {
+        let primary = vec!["wss://a.example".to_string(), "wss://b.example".to_string()];
+        let fallback = vec![
+            "wss://b.example".to_string(),
+            "wss://c.example".to_string(),
+            "wss://c.example".to_string(),
+            "wss://d.example".to_string(),
+        ];
+        assert_eq!(
+            fallback_targets(&primary, &fallback),
+            vec!["wss://c.example".to_string(), "wss://d.example".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_artifact_kind_round_trip() {
+        for kind in [ArtifactKind::KeyPackage, ArtifactKind::Welcome] {
+            assert_eq!(ArtifactKind::from(String::from(kind)), kind);
+        }
+    }
+}
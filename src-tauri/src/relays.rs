@@ -1,5 +1,28 @@
+//! Account and group relays.
+//!
+//! The account's general purpose (NIP-65) relays are stored in `account_relays` with the `nostr`
+//! type, along with whether the account reads from and writes to each. Adding or removing one
+//! also adds it to or removes it from the client, unless the relay is still one of the account's
+//! inbox, key package or group relays. The stored relays are reconnected whenever the account's
+//! Nostr identity is set, and then replace the default relays. Relays that are neither read from
+//! nor written to are kept but not connected, and blacklisted relays are never connected.
+//!
+//! The stored relays are published as the account's NIP-65 relay list (kind 10002) with
+//! [`publish_relay_list`], so that others know where to send events the account should read.
+//!
+//! Group relays live in `group_relays`. Admins can replace them with a group settings update, an
+//! application message rather than an MLS commit, so the group data extension isn't changed.
+//! The relays of the account's groups are added to the client as read relays so that the group
+//! message subscription reaches them.
+
+use crate::accounts::{Account, AccountError};
+use crate::relay_blacklist::{self, RelayBlacklist, RelayBlacklistError};
+use crate::Whitenoise;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use thiserror::Error;
 
 /// A row in the relays table
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
@@ -48,3 +71,475 @@ impl From<RelayType> for String {
         }
     }
 }
+
+#[derive(Error, Debug)]
+pub enum RelayError {
+    #[error("Relay is blacklisted: {0}")]
+    Blacklisted(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Relay blacklist error: {0}")]
+    RelayBlacklistError(#[from] RelayBlacklistError),
+
+    #[error("Nostr client error: {0}")]
+    NostrClientError(#[from] nostr_sdk::client::Error),
+
+    #[error("The account has no relays to publish")]
+    NoRelays,
+
+    #[error("A group needs at least one relay")]
+    NoGroupRelays,
+
+    #[error("Invalid relay URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RelayError>;
+
+/// How long a relay test waits for the connection
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One of an account's relays, with the client's connection status
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountRelay {
+    pub url: String,
+    pub read: bool,
+    pub write: bool,
+    /// Connection status in the client, `None` if the client doesn't know the relay
+    pub status: Option<String>,
+}
+
+/// The outcome of connecting to a relay
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RelayTestResult {
+    pub url: String,
+    pub reachable: bool,
+    /// How long the connection took
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Returns the account's relays with their connection status
+pub async fn list(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<AccountRelay>> {
+    let rows = sqlx::query_as::<_, (String, bool, bool)>(
+        "SELECT url, MAX(read), MAX(write) FROM account_relays
+         WHERE relay_type = ? AND account_pubkey = ?
+         GROUP BY url ORDER BY url",
+    )
+    .bind(String::from(RelayType::Nostr))
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let statuses: HashMap<String, String> = wn
+        .nostr
+        .client
+        .relays()
+        .await
+        .into_iter()
+        .map(|(url, relay)| (url.to_string(), relay.status().to_string()))
+        .collect();
+
+    Ok(rows
+        .into_iter()
+        .map(|(url, read, write)| AccountRelay {
+            status: statuses.get(&url).cloned(),
+            url,
+            read,
+            write,
+        })
+        .collect())
+}
+
+/// Adds a relay to the client with the given direction and connects to it. A relay that's
+/// neither read from nor written to isn't added.
+async fn connect(
+    url: &str,
+    read: bool,
+    write: bool,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let client = &wn.nostr.client;
+    match (read, write) {
+        (true, true) => client.add_relay(url).await?,
+        (true, false) => client.add_read_relay(url).await?,
+        (false, true) => client.add_write_relay(url).await?,
+        (false, false) => return Ok(()),
+    };
+    client.connect_relay(url).await?;
+    Ok(())
+}
+
+/// Whether the client still needs a relay for the account's inbox, key package or active group
+/// relays once it's no longer one of its general purpose relays
+async fn used_elsewhere(
+    url: &str,
+    account: &Account,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<bool> {
+    let used = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(
+             SELECT 1 FROM account_relays
+             WHERE url = ? AND account_pubkey = ? AND relay_type != ?
+         ) OR EXISTS(
+             SELECT 1 FROM group_relays
+             JOIN groups ON groups.mls_group_id = group_relays.group_id
+                AND groups.account_pubkey = group_relays.account_pubkey
+             WHERE group_relays.url = ? AND group_relays.account_pubkey = ?
+                AND groups.state = 'Active'
+         )",
+    )
+    .bind(url)
+    .bind(account.pubkey.to_hex())
+    .bind(String::from(RelayType::Nostr))
+    .bind(url)
+    .bind(account.pubkey.to_hex())
+    .fetch_one(&wn.database.pool)
+    .await?;
+    Ok(used)
+}
+
+/// Saves a relay for the active account, replacing its previous read/write setting, and
+/// connects to it
+pub async fn add(
+    url: &str,
+    read: bool,
+    write: bool,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<AccountRelay> {
+    let url = relay_blacklist::normalize_url(url)?;
+    if RelayBlacklist::load(wn.clone()).await?.contains(&url) {
+        return Err(RelayError::Blacklisted(url));
+    }
+    let account = Account::get_active(wn.clone()).await?;
+
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query(
+        "DELETE FROM account_relays WHERE url = ? AND relay_type = ? AND account_pubkey = ?",
+    )
+    .bind(&url)
+    .bind(String::from(RelayType::Nostr))
+    .bind(account.pubkey.to_hex())
+    .execute(&mut *txn)
+    .await?;
+    sqlx::query(
+        "INSERT INTO account_relays (url, relay_type, account_pubkey, read, write) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&url)
+    .bind(String::from(RelayType::Nostr))
+    .bind(account.pubkey.to_hex())
+    .bind(read)
+    .bind(write)
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+
+    // Re-add the relay so a changed read/write setting takes effect. Inbox, key package and group
+    // relays are read from whatever the account's setting is.
+    let _ = wn.nostr.client.force_remove_relay(url.as_str()).await;
+    let read_elsewhere = used_elsewhere(&url, &account, &wn).await?;
+    connect(&url, read || read_elsewhere, write, &wn).await?;
+
+    tracing::debug!(
+        target: "whitenoise::relays::add",
+        "Added relay {} (read: {}, write: {})",
+        url,
+        read,
+        write
+    );
+    Ok(AccountRelay {
+        status: wn
+            .nostr
+            .client
+            .relay(url.as_str())
+            .await
+            .ok()
+            .map(|relay| relay.status().to_string()),
+        url,
+        read,
+        write,
+    })
+}
+
+/// Removes a relay from the active account and disconnects from it, unless the account still
+/// uses it for something else
+pub async fn remove(url: &str, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    let url = relay_blacklist::normalize_url(url)?;
+    let account = Account::get_active(wn.clone()).await?;
+
+    sqlx::query(
+        "DELETE FROM account_relays WHERE url = ? AND relay_type = ? AND account_pubkey = ?",
+    )
+    .bind(&url)
+    .bind(String::from(RelayType::Nostr))
+    .bind(account.pubkey.to_hex())
+    .execute(&wn.database.pool)
+    .await?;
+
+    if used_elsewhere(&url, &account, &wn).await? {
+        tracing::debug!(
+            target: "whitenoise::relays::remove",
+            "Relay {} is still used by the account, keeping it connected",
+            url
+        );
+        return Ok(());
+    }
+    if let Err(e) = wn.nostr.client.force_remove_relay(url.as_str()).await {
+        tracing::debug!(
+            target: "whitenoise::relays::remove",
+            "Relay {} wasn't connected: {}",
+            url,
+            e
+        );
+    }
+    Ok(())
+}
+
+/// Adds the active account's stored relays to the client and connects to them. Blacklisted
+/// relays are skipped.
+pub async fn connect_account_relays(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let blacklist = RelayBlacklist::load(wn.clone()).await?;
+    for relay in list(account, wn.clone()).await? {
+        if blacklist.contains(&relay.url) {
+            continue;
+        }
+        if let Err(e) = connect(&relay.url, relay.read, relay.write, &wn).await {
+            tracing::warn!(
+                target: "whitenoise::relays::connect_account_relays",
+                "Couldn't connect to relay {}: {}",
+                relay.url,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Normalizes a group's new relay URLs, dropping duplicates
+pub fn normalize_group_relays(urls: &[String]) -> Result<Vec<String>> {
+    let mut relays: Vec<String> = Vec::new();
+    for url in urls {
+        let url = relay_blacklist::normalize_url(url)?;
+        if !relays.contains(&url) {
+            relays.push(url);
+        }
+    }
+    if relays.is_empty() {
+        return Err(RelayError::NoGroupRelays);
+    }
+    Ok(relays)
+}
+
+/// Replaces the relays stored for a group
+pub async fn set_group_relays(
+    mls_group_id: &[u8],
+    account_pubkey: &PublicKey,
+    relays: &[String],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query("DELETE FROM group_relays WHERE group_id = ? AND account_pubkey = ?")
+        .bind(mls_group_id)
+        .bind(account_pubkey.to_hex())
+        .execute(&mut *txn)
+        .await?;
+    for relay in relays {
+        sqlx::query(
+            "INSERT OR REPLACE INTO group_relays (url, relay_type, account_pubkey, group_id) VALUES (?, ?, ?, ?)",
+        )
+        .bind(relay)
+        .bind(String::from(RelayType::Group))
+        .bind(account_pubkey.to_hex())
+        .bind(mls_group_id)
+        .execute(&mut *txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Adds the relays of the account's active groups to the client as read relays and connects to
+/// them. Blacklisted relays are skipped.
+pub async fn connect_group_relays(
+    account: &Account,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let urls = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT group_relays.url FROM group_relays
+         JOIN groups ON groups.mls_group_id = group_relays.group_id
+            AND groups.account_pubkey = group_relays.account_pubkey
+         WHERE group_relays.account_pubkey = ? AND groups.state = 'Active'",
+    )
+    .bind(account.pubkey.to_hex())
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let blacklist = RelayBlacklist::load(wn.clone()).await?;
+    for url in blacklist.filter(urls) {
+        if let Err(e) = connect(&url, true, false, &wn).await {
+            tracing::warn!(
+                target: "whitenoise::relays::connect_group_relays",
+                "Couldn't connect to group relay {}: {}",
+                url,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Stores a NIP-65 relay list as the account's relays, keeping each relay's read/write marker.
+/// The list replaces the account's relays, so relays removed from it elsewhere are removed here
+/// too. An empty list is ignored rather than leaving the account without relays.
+pub async fn save_relay_list(
+    account: &Account,
+    relay_list: &[(String, Option<RelayMetadata>)],
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    if relay_list.is_empty() {
+        return Ok(());
+    }
+    let mut txn = wn.database.pool.begin().await?;
+    sqlx::query("DELETE FROM account_relays WHERE relay_type = ? AND account_pubkey = ?")
+        .bind(String::from(RelayType::Nostr))
+        .bind(account.pubkey.to_hex())
+        .execute(&mut *txn)
+        .await?;
+    for (url, metadata) in relay_list {
+        sqlx::query(
+            "INSERT INTO account_relays (url, relay_type, account_pubkey, read, write) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(url)
+        .bind(String::from(RelayType::Nostr))
+        .bind(account.pubkey.to_hex())
+        .bind(*metadata != Some(RelayMetadata::Write))
+        .bind(*metadata != Some(RelayMetadata::Read))
+        .execute(&mut *txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// The NIP-65 marker for a relay: relays that are both read from and written to are unmarked
+fn relay_metadata(relay: &AccountRelay) -> Option<RelayMetadata> {
+    match (relay.read, relay.write) {
+        (true, false) => Some(RelayMetadata::Read),
+        (false, true) => Some(RelayMetadata::Write),
+        _ => None,
+    }
+}
+
+/// Publishes the active account's relays as its NIP-65 relay list
+///
+/// # Returns
+///
+/// * `Ok(EventId)` - The ID of the published relay list event
+pub async fn publish_relay_list(wn: tauri::State<'_, Whitenoise>) -> Result<EventId> {
+    let account = Account::get_active(wn.clone()).await?;
+    let relays = list(&account, wn.clone()).await?;
+    // An empty list would tell everyone the account reads from nowhere
+    if relays.is_empty() {
+        return Err(RelayError::NoRelays);
+    }
+
+    let relay_list = relays
+        .iter()
+        .filter_map(|relay| Some((RelayUrl::parse(&relay.url).ok()?, relay_metadata(relay))))
+        .collect::<Vec<_>>();
+    let event = wn
+        .nostr
+        .client
+        .sign_event_builder(EventBuilder::relay_list(relay_list))
+        .await?;
+    let output = relay_blacklist::send_event(&event, Vec::new(), wn.clone()).await?;
+
+    tracing::debug!(
+        target: "whitenoise::relays::publish_relay_list",
+        "Published relay list with {} relays: {}",
+        relays.len(),
+        output.id()
+    );
+    Ok(*output.id())
+}
+
+/// Connects to a relay outside the client's pool and measures how long it takes
+pub async fn test(url: &str) -> Result<RelayTestResult> {
+    let relay_url = RelayUrl::parse(url.trim())
+        .map_err(|e| RelayError::InvalidUrl(format!("{}: {}", url, e)))?;
+    let relay = Relay::new(relay_url.clone());
+
+    let started = Instant::now();
+    let outcome = relay.try_connect(TEST_TIMEOUT).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    relay.disconnect();
+
+    Ok(match outcome {
+        Ok(()) => RelayTestResult {
+            url: relay_url.to_string(),
+            reachable: true,
+            latency_ms: Some(latency_ms),
+            error: None,
+        },
+        Err(e) => RelayTestResult {
+            url: relay_url.to_string(),
+            reachable: false,
+            latency_ms: None,
+            error: Some(e.to_string()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relay(read: bool, write: bool) -> AccountRelay {
+        AccountRelay {
+            url: "wss://relay.example.com".to_string(),
+            read,
+            write,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_relay_metadata_markers() {
+        assert_eq!(relay_metadata(&relay(true, true)), None);
+        assert_eq!(
+            relay_metadata(&relay(true, false)),
+            Some(RelayMetadata::Read)
+        );
+        assert_eq!(
+            relay_metadata(&relay(false, true)),
+            Some(RelayMetadata::Write)
+        );
+    }
+
+    #[test]
+    fn test_group_relays_are_normalized() {
+        let relays = normalize_group_relays(&[
+            "wss://relay.example.com".to_string(),
+            " wss://relay.example.com ".to_string(),
+            "wss://other.example.com".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(relays.len(), 2);
+        assert!(matches!(
+            normalize_group_relays(&[]),
+            Err(RelayError::NoGroupRelays)
+        ));
+        assert!(normalize_group_relays(&["not a url".to_string()]).is_err());
+    }
+}
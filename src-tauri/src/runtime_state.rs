@@ -0,0 +1,228 @@
+//! Runtime state snapshots for diagnosing hangs.
+//!
+//! The locks a stall usually comes down to, the `nostr_mls` state and the event processor, are
+//! [`TrackedMutex`]es: they remember where they were last locked from, since when, and how many
+//! tasks are waiting for them. `dump_runtime_state` puts that together with the event queue, the
+//! sync tasks, the async runtime and the relay subscriptions, without waiting on any lock, so it
+//! still answers while something is stuck.
+
+use crate::nostr_manager::relay_monitor::ConnectionState;
+use crate::sync_scheduler::SyncSchedulerState;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::sync::Mutex as StdMutex;
+use std::time::Instant;
+use tokio::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Default)]
+struct LockState {
+    /// Where the lock was taken from and when
+    holder: Option<(&'static Location<'static>, Instant)>,
+    waiters: usize,
+    acquisitions: u64,
+}
+
+/// A tokio mutex that keeps track of who holds it and who waits for it
+pub struct TrackedMutex<T> {
+    name: &'static str,
+    inner: Mutex<T>,
+    state: StdMutex<LockState>,
+}
+
+impl<T> fmt::Debug for TrackedMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedMutex")
+            .field("name", &self.name)
+            .field("state", &self.snapshot())
+            .finish()
+    }
+}
+
+/// Counts a task as waiting until it gets the lock or stops waiting
+struct Waiting<'a>(&'a StdMutex<LockState>);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.waiters = state.waiters.saturating_sub(1);
+    }
+}
+
+impl<T> TrackedMutex<T> {
+    pub fn new(name: &'static str, value: T) -> Self {
+        Self {
+            name,
+            inner: Mutex::new(value),
+            state: StdMutex::new(LockState::default()),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Locks the mutex, recording the caller as the holder
+    #[track_caller]
+    pub fn lock(&self) -> impl Future<Output = TrackedMutexGuard<'_, T>> + '_ {
+        let location = Location::caller();
+        async move {
+            self.state().waiters += 1;
+            let waiting = Waiting(&self.state);
+            let guard = self.inner.lock().await;
+            drop(waiting);
+            self.acquired(guard, location)
+        }
+    }
+
+    /// Locks the mutex if it's free, recording the caller as the holder
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<TrackedMutexGuard<'_, T>> {
+        let location = Location::caller();
+        let guard = self.inner.try_lock().ok()?;
+        Some(self.acquired(guard, location))
+    }
+
+    fn acquired<'a>(
+        &'a self,
+        guard: MutexGuard<'a, T>,
+        location: &'static Location<'static>,
+    ) -> TrackedMutexGuard<'a, T> {
+        let mut state = self.state();
+        state.holder = Some((location, Instant::now()));
+        state.acquisitions += 1;
+        TrackedMutexGuard {
+            guard,
+            state: &self.state,
+        }
+    }
+
+    /// Who holds the lock and how many are waiting, right now
+    pub fn snapshot(&self) -> LockSnapshot {
+        let state = self.state();
+        LockSnapshot {
+            name: self.name.to_string(),
+            holder: state.holder.map(|(location, _)| location.to_string()),
+            held_for_ms: state
+                .holder
+                .map(|(_, since)| since.elapsed().as_millis() as u64),
+            waiters: state.waiters,
+            acquisitions: state.acquisitions,
+        }
+    }
+}
+
+/// A held [`TrackedMutex`]; releasing it clears the holder
+pub struct TrackedMutexGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    state: &'a StdMutex<LockState>,
+}
+
+impl<T> Deref for TrackedMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TrackedMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for TrackedMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).holder = None;
+    }
+}
+
+/// The state of a [`TrackedMutex`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockSnapshot {
+    pub name: String,
+    /// Source location that holds the lock, if it's held
+    pub holder: Option<String>,
+    pub held_for_ms: Option<u64>,
+    pub waiters: usize,
+    /// How many times the lock was taken since launch
+    pub acquisitions: u64,
+}
+
+/// What the app is doing right now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeState {
+    pub captured_at: Timestamp,
+    pub locks: Vec<LockSnapshot>,
+    /// Events waiting for the event processor, unknown while its lock is held
+    pub event_queue_depth: Option<usize>,
+    pub sync: SyncSchedulerState,
+    /// Tasks alive on the async runtime
+    pub runtime_tasks: usize,
+    /// Tasks scheduled on the async runtime's global queue
+    pub runtime_queue_depth: usize,
+    pub relays: usize,
+    pub connected_relays: usize,
+    pub subscriptions: usize,
+}
+
+/// Captures the runtime state without waiting on any of the tracked locks
+pub async fn capture(wn: tauri::State<'_, Whitenoise>) -> RuntimeState {
+    let (event_processor, event_queue_depth) = wn.nostr.event_processor_state();
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let relays = wn.nostr.relay_monitor.statuses();
+
+    RuntimeState {
+        captured_at: Timestamp::now(),
+        locks: vec![wn.nostr_mls.snapshot(), event_processor],
+        event_queue_depth,
+        sync: wn.sync_scheduler.state(),
+        runtime_tasks: metrics.num_alive_tasks(),
+        runtime_queue_depth: metrics.global_queue_depth(),
+        relays: relays.len(),
+        connected_relays: relays
+            .iter()
+            .filter(|relay| relay.state == ConnectionState::Connected)
+            .count(),
+        subscriptions: wn.nostr.client.subscriptions().await.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tracks_holder_and_waiters() {
+        let mutex = std::sync::Arc::new(TrackedMutex::new("test", 0));
+        assert_eq!(mutex.snapshot().holder, None);
+
+        let mut guard = mutex.lock().await;
+        *guard += 1;
+        let snapshot = mutex.snapshot();
+        assert!(snapshot.holder.unwrap().contains("runtime_state.rs"));
+        assert_eq!(snapshot.acquisitions, 1);
+        assert!(mutex.try_lock().is_none());
+
+        let waiter = {
+            let mutex = mutex.clone();
+            tokio::spawn(async move { *mutex.lock().await += 1 })
+        };
+        while mutex.snapshot().waiters == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        drop(guard);
+        waiter.await.unwrap();
+        let snapshot = mutex.snapshot();
+        assert_eq!(snapshot.holder, None);
+        assert_eq!(snapshot.waiters, 0);
+        assert_eq!(snapshot.acquisitions, 2);
+        assert_eq!(*mutex.lock().await, 2);
+    }
+}
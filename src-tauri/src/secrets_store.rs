@@ -1,12 +1,18 @@
+use crate::atomic_file;
+use crate::capabilities::{self, KeyStorageBackend};
 use base64::{engine::general_purpose, Engine as _};
-// use keyring::Entry;
+use keyring::Entry;
 use nostr_sdk::{util::hex, Keys};
+use once_cell::sync::Lazy;
 use serde_json::{json, Value};
-use std::fs;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::is_dev;
 use thiserror::Error;
 use uuid::Uuid;
+use zeroize::{Zeroize, Zeroizing};
 
 #[derive(Error, Debug)]
 pub enum SecretsStoreError {
@@ -23,7 +29,7 @@ pub enum SecretsStoreError {
     Base64Error(#[from] base64::DecodeError),
 
     #[error("UTF-8 error: {0}")]
-    Utf8Error(#[from] std::string::FromUtf8Error),
+    InvalidUtf8(#[from] std::str::Utf8Error),
 
     #[error("Keyring error: {0}")]
     KeyringError(#[from] keyring::Error),
@@ -33,11 +39,103 @@ pub enum SecretsStoreError {
 
     #[error("Key not found")]
     KeyNotFound,
+
+    #[error("Unsupported secrets backend: {0:?}")]
+    UnsupportedBackend(KeyStorageBackend),
+
+    #[error("The OS keychain is required but unavailable")]
+    KeychainUnavailable,
 }
 
 pub type Result<T> = std::result::Result<T, SecretsStoreError>;
 
-#[allow(dead_code)]
+/// Key of the selected backend in the secrets file
+const BACKEND_KEY: &str = "backend";
+
+/// Key under which entries that failed verification are kept in the secrets file
+const QUARANTINE_KEY: &str = "quarantine";
+
+/// Key of the database key, which only ever lives in the OS keychain
+const DATABASE_KEY: &str = "database";
+
+/// How long an export secret read from the store is kept in memory for the next message
+const SECRET_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How often expired secrets are swept from the cache, whether or not it's read
+const SECRET_CACHE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most secrets kept in the cache. The oldest is evicted to make room for a new one.
+const MAX_CACHED_SECRETS: usize = 256;
+
+/// A cached secret's data directory and key in its secrets file
+type SecretCacheKey = (PathBuf, String);
+
+/// Export secrets read recently, so decrypting a burst of messages doesn't read the secrets file
+/// for each. Secrets are zeroized when they expire, are evicted or are removed.
+static SECRET_CACHE: Lazy<Mutex<HashMap<SecretCacheKey, (Zeroizing<String>, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn cache_key(key: &str, data_dir: &Path) -> SecretCacheKey {
+    (data_dir.to_path_buf(), key.to_string())
+}
+
+fn cached_secret(key: &str, data_dir: &Path) -> Option<Zeroizing<String>> {
+    let cache = SECRET_CACHE.lock().ok()?;
+    cache
+        .get(&cache_key(key, data_dir))
+        .filter(|(_, cached_at)| cached_at.elapsed() < SECRET_CACHE_TTL)
+        .map(|(secret, _)| secret.clone())
+}
+
+fn cache_secret(key: &str, secret: &Zeroizing<String>, data_dir: &Path) {
+    if let Ok(mut cache) = SECRET_CACHE.lock() {
+        cache.retain(|_, (_, cached_at)| cached_at.elapsed() < SECRET_CACHE_TTL);
+        if cache.len() >= MAX_CACHED_SECRETS {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, (_, cached_at))| *cached_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(cache_key(key, data_dir), (secret.clone(), Instant::now()));
+    }
+}
+
+fn forget_cached_secret(key: &str, data_dir: &Path) {
+    if let Ok(mut cache) = SECRET_CACHE.lock() {
+        cache.remove(&cache_key(key, data_dir));
+    }
+}
+
+/// Drops the cached secrets that expired
+fn sweep_secret_cache() {
+    if let Ok(mut cache) = SECRET_CACHE.lock() {
+        cache.retain(|_, (_, cached_at)| cached_at.elapsed() < SECRET_CACHE_TTL);
+    }
+}
+
+/// Starts the background task that sweeps expired secrets from the cache, so they don't stay in
+/// memory until the next read
+pub fn start_cache_sweep() {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(SECRET_CACHE_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweep_secret_cache();
+        }
+    });
+}
+
+/// Drops every cached secret, e.g. when the app locks, an account logs out or all data is
+/// deleted
+pub fn clear_secret_cache() {
+    if let Ok(mut cache) = SECRET_CACHE.lock() {
+        cache.clear();
+    }
+}
+
 fn get_service_name() -> String {
     match is_dev() {
         true => "White Noise Dev".to_string(),
@@ -46,43 +144,68 @@ fn get_service_name() -> String {
 }
 
 fn get_device_key(data_dir: &Path) -> Vec<u8> {
-    let uuid_file = data_dir.join("whitenoise_uuid");
+    let uuid_file = atomic_file::lock(&data_dir.join("whitenoise_uuid"));
 
-    let uuid = if uuid_file.exists() {
+    let uuid = match atomic_file::read_checked(&uuid_file) {
         // Read existing UUID
-        std::fs::read_to_string(&uuid_file)
-            .map_err(SecretsStoreError::FileError)
-            .and_then(|s| s.parse::<Uuid>().map_err(SecretsStoreError::UuidError))
-    } else {
-        // Generate new UUID
-        let new_uuid = Uuid::new_v4();
-        let _ = std::fs::create_dir_all(data_dir).map_err(SecretsStoreError::FileError);
-        let _ =
-            std::fs::write(uuid_file, new_uuid.to_string()).map_err(SecretsStoreError::FileError);
-        Ok(new_uuid)
+        Ok(Some(content)) => String::from_utf8_lossy(&content)
+            .parse::<Uuid>()
+            .map_err(SecretsStoreError::UuidError),
+        Ok(None) => {
+            // Generate new UUID
+            let new_uuid = Uuid::new_v4();
+            let _ = std::fs::create_dir_all(data_dir).map_err(SecretsStoreError::FileError);
+            let _ = atomic_file::write_checked(&uuid_file, new_uuid.to_string())
+                .map_err(SecretsStoreError::FileError);
+            Ok(new_uuid)
+        }
+        Err(e) => Err(SecretsStoreError::FileError(e)),
     };
 
     uuid.expect("Couldn't unwrap UUID").as_bytes().to_vec()
 }
 
+/// Returns the ID of this installation, which tells the account's devices apart. It's published
+/// in device sync messages, so it's random and unrelated to the key secrets are obfuscated with.
+pub fn get_device_id(data_dir: &Path) -> String {
+    let id_file = atomic_file::lock(&data_dir.join("whitenoise_device_id"));
+    if let Ok(Some(content)) = atomic_file::read_checked(&id_file) {
+        if let Ok(id) = String::from_utf8_lossy(&content).parse::<Uuid>() {
+            return id.simple().to_string();
+        }
+    }
+
+    let id = Uuid::new_v4();
+    let _ = std::fs::create_dir_all(data_dir);
+    if let Err(e) = atomic_file::write_checked(&id_file, id.to_string()) {
+        tracing::warn!(
+            target: "whitenoise::secrets_store::get_device_id",
+            "Failed to save device ID: {}",
+            e
+        );
+    }
+    id.simple().to_string()
+}
+
 fn get_file_path(data_dir: &Path) -> PathBuf {
     data_dir.join("whitenoise.json")
 }
 
 fn obfuscate(data: &str, data_dir: &Path) -> String {
     let device_key = get_device_key(data_dir);
-    let xored: Vec<u8> = data
-        .as_bytes()
-        .iter()
-        .zip(device_key.iter().cycle())
-        .map(|(&x1, &x2)| x1 ^ x2)
-        .collect();
-    general_purpose::STANDARD_NO_PAD.encode(xored)
+    let xored: Zeroizing<Vec<u8>> = Zeroizing::new(
+        data.as_bytes()
+            .iter()
+            .zip(device_key.iter().cycle())
+            .map(|(&x1, &x2)| x1 ^ x2)
+            .collect(),
+    );
+    general_purpose::STANDARD_NO_PAD.encode(xored.as_slice())
 }
 
-fn deobfuscate(data: &str, data_dir: &Path) -> Result<String> {
+fn deobfuscate(data: &str, data_dir: &Path) -> Result<Zeroizing<String>> {
     let device_key = get_device_key(data_dir);
-    let decoded = general_purpose::STANDARD_NO_PAD
+    let mut decoded = general_purpose::STANDARD_NO_PAD
         .decode(data)
         .map_err(SecretsStoreError::Base64Error)?;
     let xored: Vec<u8> = decoded
@@ -90,24 +213,279 @@ fn deobfuscate(data: &str, data_dir: &Path) -> Result<String> {
         .zip(device_key.iter().cycle())
         .map(|(&x1, &x2)| x1 ^ x2)
         .collect();
-    String::from_utf8(xored).map_err(SecretsStoreError::Utf8Error)
+    decoded.zeroize();
+    match String::from_utf8(xored) {
+        Ok(secret) => Ok(Zeroizing::new(secret)),
+        Err(e) => {
+            let error = e.utf8_error();
+            e.into_bytes().zeroize();
+            Err(SecretsStoreError::InvalidUtf8(error))
+        }
+    }
 }
 
-fn read_secrets_file(data_dir: &Path) -> Result<Value> {
-    let content = match fs::read_to_string(get_file_path(data_dir)) {
-        Ok(content) => content,
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::from("{}"),
-        Err(e) => return Err(e.into()),
-    };
-    Ok(serde_json::from_str(&content)?)
+/// Locks the secrets file, to be held from reading it until writing it back
+fn lock_secrets_file(data_dir: &Path) -> atomic_file::FileLock {
+    atomic_file::lock(&get_file_path(data_dir))
+}
+
+fn read_secrets_file(file: &atomic_file::FileLock) -> Result<Value> {
+    match atomic_file::read_checked(file)? {
+        Some(content) => Ok(serde_json::from_slice(&content)?),
+        None => Ok(json!({})),
+    }
+}
+
+fn write_secrets_file(file: &atomic_file::FileLock, secrets: &Value) -> Result<()> {
+    let content = serde_json::to_string_pretty(secrets)?;
+    atomic_file::write_checked(file, content)?;
+    Ok(())
 }
 
-fn write_secrets_file(data_dir: &Path, secrets: &Value) -> Result<()> {
+/// Writes the secrets file after secrets were removed from it, without keeping them in its backup
+fn write_secrets_file_after_removal(file: &atomic_file::FileLock, secrets: &Value) -> Result<()> {
     let content = serde_json::to_string_pretty(secrets)?;
-    fs::write(get_file_path(data_dir), content)?;
+    atomic_file::write_checked_discarding_backup(file, content)?;
     Ok(())
 }
 
+/// The placeholder kept in the secrets file for a secret that lives in the OS keychain, so
+/// secrets can still be listed without the keychain
+fn keychain_marker() -> Value {
+    json!({ "keychain": true })
+}
+
+fn is_keychain_entry(value: &Value) -> bool {
+    value["keychain"].as_bool().unwrap_or(false)
+}
+
+fn keychain_entry(key: &str) -> Result<Entry> {
+    Ok(Entry::new(&get_service_name(), key)?)
+}
+
+fn backend_of(secrets: &Value) -> KeyStorageBackend {
+    serde_json::from_value(secrets[BACKEND_KEY].clone()).unwrap_or(KeyStorageBackend::File)
+}
+
+/// Reads a secret from its entry in the secrets file, following it to the OS keychain if needed
+fn read_entry(key: &str, value: &Value, data_dir: &Path) -> Result<Option<Zeroizing<String>>> {
+    if let Some(obfuscated) = value.as_str() {
+        return Ok(Some(deobfuscate(obfuscated, data_dir)?));
+    }
+    if !is_keychain_entry(value) {
+        return Ok(None);
+    }
+    match keychain_entry(key)?.get_password() {
+        Ok(secret) => Ok(Some(Zeroizing::new(secret))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn get_secret(key: &str, data_dir: &Path) -> Result<Option<Zeroizing<String>>> {
+    let secrets = read_secrets_file(&lock_secrets_file(data_dir))?;
+    read_entry(key, &secrets[key], data_dir)
+}
+
+/// Stores a secret with the selected backend. If the OS keychain rejects it the secret is kept in
+/// the file instead, so a broken keychain never loses secrets.
+fn put_secret(key: &str, secret: &str, data_dir: &Path) -> Result<()> {
+    forget_cached_secret(key, data_dir);
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file).unwrap_or(json!({}));
+    if backend_of(&secrets) == KeyStorageBackend::OsKeychain {
+        match keychain_entry(key).and_then(|entry| Ok(entry.set_password(secret)?)) {
+            Ok(()) => {
+                secrets[key] = keychain_marker();
+                return write_secrets_file(&file, &secrets);
+            }
+            Err(e) => tracing::warn!(
+                target: "whitenoise::secrets_store::put_secret",
+                "Failed to store secret in the OS keychain, falling back to file storage: {}",
+                e
+            ),
+        }
+    }
+    secrets[key] = json!(obfuscate(secret, data_dir));
+    write_secrets_file(&file, &secrets)
+}
+
+fn remove_secret(key: &str, data_dir: &Path) -> Result<()> {
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file)?;
+    remove_secrets(&file, &mut secrets, &[key.to_string()], data_dir)
+}
+
+/// Removes secrets from the loaded secrets file and the OS keychain with a single write
+fn remove_secrets(
+    file: &atomic_file::FileLock,
+    secrets: &mut Value,
+    keys: &[String],
+    data_dir: &Path,
+) -> Result<()> {
+    let mut removed = Vec::new();
+    for key in keys {
+        forget_cached_secret(key, data_dir);
+        if let Some(value) = secrets.as_object_mut().and_then(|obj| obj.remove(key)) {
+            removed.push((key, value));
+        }
+    }
+    write_secrets_file_after_removal(file, secrets)?;
+    for (key, value) in removed {
+        if is_keychain_entry(&value) {
+            if let Ok(entry) = keychain_entry(key) {
+                let _ = entry.delete_credential();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the OS keychain can be used on this device. Besides the platform having one, this
+/// round-trips a probe secret since e.g. Linux desktops may not run a Secret Service.
+pub fn keychain_available() -> bool {
+    if !capabilities::platform_has_keychain() {
+        return false;
+    }
+    let probe = || -> Result<bool> {
+        let entry = keychain_entry("whitenoise_probe")?;
+        entry.set_password("probe")?;
+        let ok = entry.get_password()? == "probe";
+        let _ = entry.delete_credential();
+        Ok(ok)
+    };
+    probe().unwrap_or(false)
+}
+
+/// Returns the backend new secrets are stored with
+pub fn get_backend(data_dir: &Path) -> Result<KeyStorageBackend> {
+    Ok(backend_of(&read_secrets_file(&lock_secrets_file(
+        data_dir,
+    ))?))
+}
+
+/// Selects the backend secrets are stored with and moves every existing secret (private keys,
+/// MLS export secrets, NWC URIs and the app lock config) to it.
+///
+/// Selecting the OS keychain where it isn't available keeps file storage.
+///
+/// # Returns
+///
+/// * `Result<KeyStorageBackend>` - The backend in use afterwards
+///
+/// # Errors
+///
+/// Returns an error for backends that can't hold secrets, or if a secret can't be moved. Secrets
+/// are only removed from their old backend once the secrets file points to the new one.
+pub fn set_backend(backend: KeyStorageBackend, data_dir: &Path) -> Result<KeyStorageBackend> {
+    let backend = match backend {
+        KeyStorageBackend::Hardware => return Err(SecretsStoreError::UnsupportedBackend(backend)),
+        KeyStorageBackend::OsKeychain if !keychain_available() => {
+            tracing::warn!(
+                target: "whitenoise::secrets_store::set_backend",
+                "OS keychain unavailable, keeping file storage"
+            );
+            KeyStorageBackend::File
+        }
+        backend => backend,
+    };
+
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file)?;
+    let keys: Vec<String> = secrets
+        .as_object()
+        .map(|obj| obj.keys().filter(|k| *k != BACKEND_KEY).cloned().collect())
+        .unwrap_or_default();
+
+    let mut moved_from_keychain = Vec::new();
+    for key in keys {
+        let value = secrets[&key].clone();
+        match backend {
+            KeyStorageBackend::OsKeychain if value.is_string() => {
+                if let Some(secret) = read_entry(&key, &value, data_dir)? {
+                    keychain_entry(&key)?.set_password(&secret)?;
+                    secrets[&key] = keychain_marker();
+                }
+            }
+            // The database key never moves to the file next to the database it unlocks
+            KeyStorageBackend::File if is_keychain_entry(&value) && key != DATABASE_KEY => {
+                match read_entry(&key, &value, data_dir)? {
+                    Some(secret) => secrets[&key] = json!(obfuscate(&secret, data_dir)),
+                    None => {
+                        secrets.as_object_mut().map(|obj| obj.remove(&key));
+                    }
+                }
+                moved_from_keychain.push(key);
+            }
+            _ => {}
+        }
+    }
+    secrets[BACKEND_KEY] = json!(backend);
+    write_secrets_file(&file, &secrets)?;
+
+    for key in moved_from_keychain {
+        if let Ok(entry) = keychain_entry(&key) {
+            let _ = entry.delete_credential();
+        }
+    }
+    Ok(backend)
+}
+
+/// Why a secrets file entry is corrupt, or `None` if it decodes. Private keys, stored under their
+/// pubkey, must also belong to that pubkey.
+fn check_entry(key: &str, value: &Value, data_dir: &Path) -> Option<String> {
+    let obfuscated = value.as_str()?;
+    let secret = match deobfuscate(obfuscated, data_dir) {
+        Ok(secret) => secret,
+        Err(e) => return Some(e.to_string()),
+    };
+    let pubkey = nostr_sdk::PublicKey::from_hex(key).ok()?;
+    match Keys::parse(&secret) {
+        Ok(keys) if keys.public_key() == pubkey => None,
+        Ok(_) => Some("Private key doesn't match its pubkey".to_string()),
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+/// Moves the secrets file entries that can't be decoded under the quarantine key, so reading
+/// them no longer fails. Entries kept in the OS keychain aren't checked.
+///
+/// # Returns
+///
+/// * `Result<(usize, Vec<(String, String)>)>` - The number of entries checked and the key and
+///   reason of every quarantined entry
+pub fn quarantine_corrupt_entries(data_dir: &Path) -> Result<(usize, Vec<(String, String)>)> {
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file)?;
+    let Some(entries) = secrets.as_object() else {
+        return Ok((0, Vec::new()));
+    };
+
+    let mut checked = 0;
+    let mut corrupt = Vec::new();
+    for (key, value) in entries {
+        if key == BACKEND_KEY || key == QUARANTINE_KEY {
+            continue;
+        }
+        checked += 1;
+        if let Some(reason) = check_entry(key, value, data_dir) {
+            corrupt.push((key.clone(), reason));
+        }
+    }
+
+    if !corrupt.is_empty() {
+        for (key, _) in &corrupt {
+            let value = secrets.as_object_mut().and_then(|obj| obj.remove(key));
+            if !secrets[QUARANTINE_KEY].is_object() {
+                secrets[QUARANTINE_KEY] = json!({});
+            }
+            secrets[QUARANTINE_KEY][key] = value.unwrap_or(Value::Null);
+        }
+        write_secrets_file(&file, &secrets)?;
+    }
+    Ok((checked, corrupt))
+}
+
 /// Stores the private key associated with the given Keys in the system's keyring.
 ///
 /// This function takes a reference to a `Keys` object and stores the private key
@@ -129,26 +507,11 @@ fn write_secrets_file(data_dir: &Path, secrets: &Value) -> Result<()> {
 /// * Setting the password in the keyring fails
 /// * The secret key cannot be retrieved from the keypair
 pub fn store_private_key(keys: &Keys, data_dir: &Path) -> Result<()> {
-    let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
-    let obfuscated_key = obfuscate(keys.secret_key().to_secret_hex().as_str(), data_dir);
-    secrets[keys.public_key().to_hex()] = json!(obfuscated_key);
-    write_secrets_file(data_dir, &secrets)?;
-
-    // if cfg!(target_os = "android") {
-    //     let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
-    //     let obfuscated_key = obfuscate(keys.secret_key().to_secret_hex().as_str(), data_dir);
-    //     secrets[keys.public_key().to_hex()] = json!(obfuscated_key);
-    //     write_secrets_file(data_dir, &secrets)?;
-    // } else {
-    //     let service = get_service_name();
-    //     let entry = Entry::new(service.as_str(), keys.public_key().to_hex().as_str())
-    //         .map_err(SecretsStoreError::KeyringError)?;
-    //     entry
-    //         .set_password(keys.secret_key().to_secret_hex().as_str())
-    //         .map_err(SecretsStoreError::KeyringError)?;
-    // }
-
-    Ok(())
+    put_secret(
+        &keys.public_key().to_hex(),
+        keys.secret_key().to_secret_hex().as_str(),
+        data_dir,
+    )
 }
 
 /// Retrieves the Nostr keys associated with a given public key from the system's keyring.
@@ -172,29 +535,8 @@ pub fn store_private_key(keys: &Keys, data_dir: &Path) -> Result<()> {
 /// * Retrieving the password from the keyring fails
 /// * Parsing the private key into a `Keys` object fails
 pub fn get_nostr_keys_for_pubkey(pubkey: &str, data_dir: &Path) -> Result<Keys> {
-    let secrets = read_secrets_file(data_dir)?;
-    let obfuscated_key = secrets[pubkey]
-        .as_str()
-        .ok_or(SecretsStoreError::KeyNotFound)?;
-    let private_key = deobfuscate(obfuscated_key, data_dir)?;
+    let private_key = get_secret(pubkey, data_dir)?.ok_or(SecretsStoreError::KeyNotFound)?;
     Keys::parse(&private_key).map_err(SecretsStoreError::KeyError)
-
-    // if cfg!(target_os = "android") {
-    //     let secrets = read_secrets_file(data_dir)?;
-    //     let obfuscated_key = secrets[pubkey]
-    //         .as_str()
-    //         .ok_or(SecretsStoreError::KeyNotFound)?;
-    //     let private_key = deobfuscate(obfuscated_key, data_dir)?;
-    //     Keys::parse(private_key).map_err(SecretsStoreError::KeyError)
-    // } else {
-    //     let service = get_service_name();
-    //     let entry =
-    //         Entry::new(service.as_str(), pubkey).map_err(SecretsStoreError::KeyringError)?;
-    //     let private_key = entry
-    //         .get_password()
-    //         .map_err(SecretsStoreError::KeyringError)?;
-    //     Keys::parse(private_key).map_err(SecretsStoreError::KeyError)
-    // }
 }
 
 /// Removes the private key associated with a given public key from the system's keyring.
@@ -218,22 +560,7 @@ pub fn get_nostr_keys_for_pubkey(pubkey: &str, data_dir: &Path) -> Result<Keys>
 /// This function will return an error if:
 /// * The Entry creation fails
 pub fn remove_private_key_for_pubkey(pubkey: &str, data_dir: &Path) -> Result<()> {
-    let mut secrets = read_secrets_file(data_dir)?;
-    secrets.as_object_mut().map(|obj| obj.remove(pubkey));
-    write_secrets_file(data_dir, &secrets)?;
-
-    // if cfg!(target_os = "android") {
-    //     let mut secrets = read_secrets_file(data_dir)?;
-    //     secrets.as_object_mut().map(|obj| obj.remove(pubkey));
-    //     write_secrets_file(data_dir, &secrets)?;
-    // } else {
-    //     let service = get_service_name();
-    //     let entry = Entry::new(service.as_str(), pubkey);
-    //     if let Ok(entry) = entry {
-    //         let _ = entry.delete_credential();
-    //     }
-    // }
-    Ok(())
+    remove_secret(pubkey, data_dir)
 }
 
 /// Stores the MLS export secret for a specific group and epoch in the system's keyring.
@@ -266,22 +593,7 @@ pub fn store_mls_export_secret(
     let mls_group_id_hex = hex::encode(&mls_group_id);
     let key = format!("{mls_group_id_hex}:{epoch}");
 
-    let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
-    let obfuscated_secret = obfuscate(&secret, data_dir);
-    secrets[key] = json!(obfuscated_secret);
-    write_secrets_file(data_dir, &secrets)?;
-
-    // if cfg!(target_os = "android") {
-    //     let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
-    //     let obfuscated_secret = obfuscate(&secret, data_dir);
-    //     secrets[key] = json!(obfuscated_secret);
-    //     write_secrets_file(data_dir, &secrets)?;
-    // } else {
-    // let service = get_service_name();
-    //     let entry = Entry::new(service.as_str(), key.as_str())?;
-    //     entry.set_password(&secret)?;
-    // }
-    Ok(())
+    put_secret(&key, &secret, data_dir)
 }
 
 /// Retrieves the export secret keys for a specific MLS group and epoch from the system's keyring.
@@ -310,34 +622,164 @@ pub fn get_export_secret_keys_for_group(
     epoch: u64,
     data_dir: &Path,
 ) -> Result<Keys> {
-    let mls_group_id_hex = hex::encode(&mls_group_id);
-    let key = format!("{mls_group_id_hex}:{epoch}");
-
-    let secrets = read_secrets_file(data_dir)?;
-    let obfuscated_secret = secrets[key]
-        .as_str()
+    let secret = get_secret_for_epoch(&mls_group_id, epoch, data_dir)?
         .ok_or(SecretsStoreError::KeyNotFound)?;
-    let secret = deobfuscate(obfuscated_secret, data_dir)?;
     let keys = Keys::parse(&secret).map_err(SecretsStoreError::KeyError)?;
     Ok(keys)
+}
 
-    // if cfg!(target_os = "android") {
-    //     let secrets = read_secrets_file(data_dir)?;
-    //     let obfuscated_secret = secrets[key]
-    //         .as_str()
-    //         .ok_or(SecretsStoreError::KeyNotFound)?;
-    //     let secret = deobfuscate(obfuscated_secret, data_dir)?;
-    //     let keys = Keys::parse(secret).map_err(SecretsStoreError::KeyError)?;
-    //     Ok(keys)
-    // } else {
-    // let service = get_service_name();
-    //     let entry = Entry::new(service.as_str(), key.as_str())?;
-    //     let secret = entry
-    //         .get_password()
-    //         .map_err(SecretsStoreError::KeyringError)?;
-    //     let keys = Keys::parse(secret).map_err(SecretsStoreError::KeyError)?;
-    //     Ok(keys)
-    // }
+/// Retrieves the export secret of one epoch of an MLS group.
+///
+/// # Arguments
+///
+/// * `mls_group_id` - A vector of bytes containing the ID of the MLS group.
+/// * `epoch` - The epoch number as a u64.
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Option<Zeroizing<String>>>` - The hex encoded secret, or `None` if it isn't stored.
+///   Secrets are cached in memory for a few minutes.
+pub fn get_secret_for_epoch(
+    mls_group_id: &[u8],
+    epoch: u64,
+    data_dir: &Path,
+) -> Result<Option<Zeroizing<String>>> {
+    let key = format!("{}:{epoch}", hex::encode(mls_group_id));
+    if let Some(secret) = cached_secret(&key, data_dir) {
+        return Ok(Some(secret));
+    }
+    let secret = get_secret(&key, data_dir)?;
+    if let Some(secret) = &secret {
+        cache_secret(&key, secret, data_dir);
+    }
+    Ok(secret)
+}
+
+/// Removes the export secrets of each group's epochs more than its retention before the latest
+/// epoch stored for it, reading and writing the secrets file once.
+///
+/// # Arguments
+///
+/// * `retention_epochs` - The MLS group IDs to prune and how many epochs to keep for each
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of secrets removed
+pub fn prune_export_secrets(
+    retention_epochs: &HashMap<Vec<u8>, u64>,
+    data_dir: &Path,
+) -> Result<usize> {
+    let retention_epochs: HashMap<String, u64> = retention_epochs
+        .iter()
+        .map(|(mls_group_id, epochs)| (hex::encode(mls_group_id), *epochs))
+        .collect();
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file)?;
+    let stored: Vec<(String, String, u64)> = secrets
+        .as_object()
+        .map(|entries| {
+            entries
+                .keys()
+                .filter_map(|key| {
+                    let (group, epoch) = key.split_once(':')?;
+                    let epoch = epoch.parse::<u64>().ok()?;
+                    retention_epochs
+                        .contains_key(group)
+                        .then(|| (key.clone(), group.to_string(), epoch))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut latest_epochs: HashMap<&str, u64> = HashMap::new();
+    for (_, group, epoch) in &stored {
+        let latest = latest_epochs.entry(group.as_str()).or_default();
+        *latest = (*latest).max(*epoch);
+    }
+    let expired: Vec<String> = stored
+        .iter()
+        .filter(|(_, group, epoch)| {
+            *epoch < latest_epochs[group.as_str()].saturating_sub(retention_epochs[group])
+        })
+        .map(|(key, _, _)| key.clone())
+        .collect();
+
+    if !expired.is_empty() {
+        remove_secrets(&file, &mut secrets, &expired, data_dir)?;
+    }
+    Ok(expired.len())
+}
+
+/// Removes every stored export secret of an MLS group.
+///
+/// # Arguments
+///
+/// * `mls_group_id` - A vector of bytes containing the ID of the MLS group.
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of secrets removed
+pub fn remove_export_secrets_for_group(mls_group_id: &[u8], data_dir: &Path) -> Result<usize> {
+    let prefix = format!("{}:", hex::encode(mls_group_id));
+
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file)?;
+    let keys: Vec<String> = secrets
+        .as_object()
+        .map(|entries| {
+            entries
+                .keys()
+                .filter(|key| {
+                    key.strip_prefix(&prefix)
+                        .is_some_and(|epoch| epoch.parse::<u64>().is_ok())
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    if !keys.is_empty() {
+        remove_secrets(&file, &mut secrets, &keys, data_dir)?;
+    }
+    Ok(keys.len())
+}
+
+/// Retrieves every stored export secret for a specific MLS group.
+///
+/// # Arguments
+///
+/// * `mls_group_id` - A vector of bytes containing the ID of the MLS group.
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Vec<(u64, Zeroizing<String>)>>` - The epochs and their hex encoded secrets, oldest
+///   epoch first
+pub fn get_export_secrets_for_group(
+    mls_group_id: &[u8],
+    data_dir: &Path,
+) -> Result<Vec<(u64, Zeroizing<String>)>> {
+    let prefix = format!("{}:", hex::encode(mls_group_id));
+
+    let secrets = read_secrets_file(&lock_secrets_file(data_dir))?;
+    let mut export_secrets = Vec::new();
+    if let Some(entries) = secrets.as_object() {
+        for (key, value) in entries {
+            let Some(epoch) = key
+                .strip_prefix(&prefix)
+                .and_then(|e| e.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if let Some(secret) = read_entry(key, value, data_dir)? {
+                export_secrets.push((epoch, secret));
+            }
+        }
+    }
+    export_secrets.sort_by_key(|(epoch, _)| *epoch);
+    Ok(export_secrets)
 }
 
 /// Stores the NWC (Nostr Wallet Connect) URI for a specific public key in the secrets store.
@@ -356,12 +798,8 @@ pub fn store_nostr_wallet_connect_uri(
     nostr_wallet_connect_uri: &str,
     data_dir: &Path,
 ) -> Result<()> {
-    let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
     let key = format!("nwc:{}", pubkey);
-    let obfuscated_uri = obfuscate(nostr_wallet_connect_uri, data_dir);
-    secrets[key] = json!(obfuscated_uri);
-    write_secrets_file(data_dir, &secrets)?;
-    Ok(())
+    put_secret(&key, nostr_wallet_connect_uri, data_dir)
 }
 
 /// Retrieves the NWC URI for a specific public key from the secrets store.
@@ -373,15 +811,14 @@ pub fn store_nostr_wallet_connect_uri(
 ///
 /// # Returns
 ///
-/// * `Result<Option<String>>` - Some(uri) if found, None if not found, or an error if operation fails
-pub fn get_nostr_wallet_connect_uri(pubkey: &str, data_dir: &Path) -> Result<Option<String>> {
-    let secrets = read_secrets_file(data_dir)?;
+/// * `Result<Option<Zeroizing<String>>>` - Some(uri) if found, None if not found, or an error if
+///   operation fails
+pub fn get_nostr_wallet_connect_uri(
+    pubkey: &str,
+    data_dir: &Path,
+) -> Result<Option<Zeroizing<String>>> {
     let key = format!("nwc:{}", pubkey);
-
-    match secrets[key].as_str() {
-        Some(obfuscated_uri) => Ok(Some(deobfuscate(obfuscated_uri, data_dir)?)),
-        None => Ok(None),
-    }
+    get_secret(&key, data_dir)
 }
 
 /// Removes the NWC URI for a specific public key from the secrets store.
@@ -395,11 +832,78 @@ pub fn get_nostr_wallet_connect_uri(pubkey: &str, data_dir: &Path) -> Result<Opt
 ///
 /// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
 pub fn remove_nostr_wallet_connect_uri(pubkey: &str, data_dir: &Path) -> Result<()> {
-    let mut secrets = read_secrets_file(data_dir)?;
     let key = format!("nwc:{}", pubkey);
-    secrets.as_object_mut().map(|obj| obj.remove(&key));
-    write_secrets_file(data_dir, &secrets)?;
-    Ok(())
+    remove_secret(&key, data_dir)
+}
+
+/// Stores the serialized app lock configuration (passphrase hashes, duress settings).
+///
+/// # Arguments
+///
+/// * `config` - The serialized app lock configuration
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+pub fn store_app_lock_config(config: &str, data_dir: &Path) -> Result<()> {
+    put_secret("app_lock", config, data_dir)
+}
+
+/// Retrieves the serialized app lock configuration from the secrets store.
+///
+/// # Arguments
+///
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Option<Zeroizing<String>>>` - Some(config) if found, None if the app lock was never
+///   configured
+pub fn get_app_lock_config(data_dir: &Path) -> Result<Option<Zeroizing<String>>> {
+    get_secret("app_lock", data_dir)
+}
+
+/// Stores the key the local database is encrypted with in the OS keychain, whichever backend is
+/// selected. Unlike other secrets it never falls back to the secrets file, which sits next to the
+/// database.
+///
+/// # Arguments
+///
+/// * `key` - The hex encoded database key
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+///
+/// # Errors
+///
+/// Returns `KeychainUnavailable` if the OS keychain can't be used on this device.
+pub fn store_database_key(key: &str, data_dir: &Path) -> Result<()> {
+    if !keychain_available() {
+        return Err(SecretsStoreError::KeychainUnavailable);
+    }
+    forget_cached_secret(DATABASE_KEY, data_dir);
+    keychain_entry(DATABASE_KEY)?.set_password(key)?;
+    let file = lock_secrets_file(data_dir);
+    let mut secrets = read_secrets_file(&file).unwrap_or(json!({}));
+    secrets[DATABASE_KEY] = keychain_marker();
+    write_secrets_file(&file, &secrets)
+}
+
+/// Retrieves the key the local database is encrypted with.
+///
+/// # Arguments
+///
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Option<Zeroizing<String>>>` - Some(key) if found, None if the database was never
+///   encrypted
+pub fn get_database_key(data_dir: &Path) -> Result<Option<Zeroizing<String>>> {
+    get_secret(DATABASE_KEY, data_dir)
 }
 
 #[cfg(test)]
@@ -482,6 +986,97 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_export_secrets_for_group() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let group_id = vec![1u8; 32];
+        let other_group_id = vec![2u8; 32];
+
+        store_mls_export_secret(group_id.clone(), 2, "b".repeat(64), temp_dir.path())?;
+        store_mls_export_secret(group_id.clone(), 1, "a".repeat(64), temp_dir.path())?;
+        store_mls_export_secret(other_group_id, 1, "c".repeat(64), temp_dir.path())?;
+
+        assert_eq!(
+            get_export_secrets_for_group(&group_id, temp_dir.path())?,
+            vec![
+                (1, Zeroizing::new("a".repeat(64))),
+                (2, Zeroizing::new("b".repeat(64)))
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_secret_is_replaced_when_stored_again() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let group_id = vec![5u8; 32];
+
+        store_mls_export_secret(group_id.clone(), 1, "a".repeat(64), temp_dir.path())?;
+        assert_eq!(
+            get_secret_for_epoch(&group_id, 1, temp_dir.path())?.as_deref(),
+            Some(&"a".repeat(64))
+        );
+
+        store_mls_export_secret(group_id.clone(), 1, "b".repeat(64), temp_dir.path())?;
+        assert_eq!(
+            get_secret_for_epoch(&group_id, 1, temp_dir.path())?.as_deref(),
+            Some(&"b".repeat(64))
+        );
+
+        store_mls_export_secret(group_id.clone(), 2, "c".repeat(64), temp_dir.path())?;
+        prune_export_secrets(&HashMap::from([(group_id.clone(), 0)]), temp_dir.path())?;
+        assert_eq!(get_secret_for_epoch(&group_id, 1, temp_dir.path())?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_secrets_are_kept_per_data_dir() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let other_temp_dir = setup_temp_dir();
+        let group_id = vec![6u8; 32];
+
+        store_mls_export_secret(group_id.clone(), 1, "a".repeat(64), temp_dir.path())?;
+        assert!(get_secret_for_epoch(&group_id, 1, temp_dir.path())?.is_some());
+        assert_eq!(
+            get_secret_for_epoch(&group_id, 1, other_temp_dir.path())?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_export_secrets() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let group_id = vec![3u8; 32];
+        let other_group_id = vec![4u8; 32];
+
+        for epoch in 1..=4 {
+            store_mls_export_secret(group_id.clone(), epoch, "a".repeat(64), temp_dir.path())?;
+        }
+        store_mls_export_secret(other_group_id.clone(), 1, "b".repeat(64), temp_dir.path())?;
+
+        let retention_epochs = HashMap::from([(group_id.clone(), 1), (other_group_id.clone(), 1)]);
+        assert_eq!(prune_export_secrets(&retention_epochs, temp_dir.path())?, 2);
+        assert_eq!(get_secret_for_epoch(&group_id, 2, temp_dir.path())?, None);
+        assert_eq!(
+            get_secret_for_epoch(&group_id, 3, temp_dir.path())?.as_deref(),
+            Some(&"a".repeat(64))
+        );
+        // The latest epoch of each group counts, so a group with one secret keeps it
+        assert!(get_secret_for_epoch(&other_group_id, 1, temp_dir.path())?.is_some());
+
+        // Pruned secrets can't be restored from the backup either
+        std::fs::remove_file(get_file_path(temp_dir.path()))?;
+        assert!(get_export_secrets_for_group(&group_id, temp_dir.path())?
+            .iter()
+            .all(|(epoch, _)| *epoch >= 3));
+
+        Ok(())
+    }
+
     #[test]
     fn test_get_nonexistent_mls_export_secret() {
         let temp_dir = setup_temp_dir();
@@ -514,14 +1109,14 @@ mod tests {
         assert_eq!(keys.secret_key(), retrieved_keys.secret_key());
 
         // Verify that the key is stored in the file
-        let secrets = read_secrets_file(temp_dir.path())?;
+        let secrets = read_secrets_file(&lock_secrets_file(temp_dir.path()))?;
         assert!(secrets.get(&pubkey).is_some());
 
         // Clean up
         remove_private_key_for_pubkey(&pubkey, temp_dir.path())?;
 
         // Verify that the key is removed from the file
-        let secrets = read_secrets_file(temp_dir.path())?;
+        let secrets = read_secrets_file(&lock_secrets_file(temp_dir.path()))?;
         assert!(secrets.get(&pubkey).is_none());
 
         Ok(())
@@ -545,7 +1140,7 @@ mod tests {
         assert_eq!(retrieved_keys.secret_key().to_secret_hex(), secret);
 
         // Verify that the secret is stored in the file
-        let secrets = read_secrets_file(temp_dir.path())?;
+        let secrets = read_secrets_file(&lock_secrets_file(temp_dir.path()))?;
         let key = format!("{group_id}:{epoch}");
         assert!(secrets.get(&key).is_some());
 
@@ -568,7 +1163,7 @@ mod tests {
         // Retrieve the NWC URI
         let retrieved_uri =
             get_nostr_wallet_connect_uri(pubkey, temp_dir.path())?.expect("URI should exist");
-        assert_eq!(nostr_wallet_connect_uri, retrieved_uri);
+        assert_eq!(nostr_wallet_connect_uri, retrieved_uri.as_str());
 
         // Clean up
         remove_nostr_wallet_connect_uri(pubkey, temp_dir.path())?;
@@ -579,4 +1174,95 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_backend_defaults_to_file() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        assert_eq!(get_backend(temp_dir.path())?, KeyStorageBackend::File);
+
+        let keys = Keys::generate();
+        store_private_key(&keys, temp_dir.path())?;
+        assert_eq!(
+            set_backend(KeyStorageBackend::File, temp_dir.path())?,
+            KeyStorageBackend::File
+        );
+        assert_eq!(
+            get_nostr_keys_for_pubkey(&keys.public_key().to_hex(), temp_dir.path())?.secret_key(),
+            keys.secret_key()
+        );
+
+        assert!(matches!(
+            set_backend(KeyStorageBackend::Hardware, temp_dir.path()),
+            Err(SecretsStoreError::UnsupportedBackend(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keychain_markers_are_not_file_secrets() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let group_id = vec![3u8; 32];
+        store_mls_export_secret(group_id.clone(), 1, "a".repeat(64), temp_dir.path())?;
+
+        let file = lock_secrets_file(temp_dir.path());
+        let mut secrets = read_secrets_file(&file)?;
+        assert!(secrets[format!("{}:1", hex::encode(&group_id))].is_string());
+        secrets["unrelated"] = json!(42);
+        write_secrets_file(&file, &secrets)?;
+        drop(file);
+
+        assert!(is_keychain_entry(&keychain_marker()));
+        assert!(!is_keychain_entry(&secrets["unrelated"]));
+        assert_eq!(get_secret("unrelated", temp_dir.path())?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_and_retrieve_app_lock_config() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+
+        assert!(get_app_lock_config(temp_dir.path())?.is_none());
+
+        let config = r#"{"passphrase":null,"duress":null}"#;
+        store_app_lock_config(config, temp_dir.path())?;
+        assert_eq!(
+            get_app_lock_config(temp_dir.path())?.as_deref(),
+            Some(&config.to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quarantine_corrupt_entries() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let keys = Keys::generate();
+        store_private_key(&keys, temp_dir.path())?;
+
+        // A private key stored under someone else's pubkey and an entry that isn't valid base64
+        let other = Keys::generate().public_key().to_hex();
+        let file = lock_secrets_file(temp_dir.path());
+        let mut secrets = read_secrets_file(&file)?;
+        secrets[&other] = secrets[keys.public_key().to_hex()].clone();
+        secrets["garbage"] = json!("not base64!");
+        write_secrets_file(&file, &secrets)?;
+        drop(file);
+
+        let (checked, corrupt) = quarantine_corrupt_entries(temp_dir.path())?;
+        assert_eq!(checked, 3);
+        let mut keys_quarantined: Vec<_> = corrupt.into_iter().map(|(key, _)| key).collect();
+        keys_quarantined.sort();
+        let mut expected = vec![other.clone(), "garbage".to_string()];
+        expected.sort();
+        assert_eq!(keys_quarantined, expected);
+
+        // The intact key is still readable and the bad entries are set aside
+        get_nostr_keys_for_pubkey(&keys.public_key().to_hex(), temp_dir.path())?;
+        let secrets = read_secrets_file(&lock_secrets_file(temp_dir.path()))?;
+        assert!(secrets[&other].is_null());
+        assert!(secrets[QUARANTINE_KEY]["garbage"].is_string());
+        Ok(())
+    }
 }
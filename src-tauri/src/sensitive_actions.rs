@@ -0,0 +1,245 @@
+//! Confirmation tokens for destructive commands.
+//!
+//! Wiping all data, exporting keys and deleting an account can't be undone, so the commands
+//! doing them don't act on a bare invoke from the webview. The frontend first asks for a token
+//! with [`request`], which re-verifies the app passphrase, and passes it along. Tokens are bound
+//! to one action, can be used once and expire after [`TOKEN_TTL`]; locking the app drops them.
+//!
+//! Without an app passphrase there's nothing to verify, so the user confirms the action in a
+//! native dialog instead, which a script in the webview can't click through.
+
+use crate::app_lock::{self, AppLockConfig, AppLockError, UnlockOutcome};
+use crate::Whitenoise;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+/// How long a confirmation token can be used for
+pub const TOKEN_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Error, Debug)]
+pub enum SensitiveActionError {
+    #[error("Incorrect passphrase")]
+    IncorrectPassphrase,
+
+    #[error("The action wasn't confirmed")]
+    Declined,
+
+    #[error("Missing, expired or already used confirmation token")]
+    InvalidToken,
+
+    #[error("App lock error: {0}")]
+    AppLockError(#[from] AppLockError),
+}
+
+pub type Result<T> = std::result::Result<T, SensitiveActionError>;
+
+/// Commands that need a confirmation token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveAction {
+    /// `delete_all_data`
+    WipeAllData,
+    /// `export_nsec`
+    ExportNsec,
+    /// `logout`, which removes the account and its keys from the device
+    DeleteAccount,
+    /// `export_account`
+    ExportAccount,
+    /// `export_app_data`
+    ExportAppData,
+    /// `export_account_data`
+    ExportAccountData,
+}
+
+impl SensitiveAction {
+    /// What the native confirmation dialog asks
+    fn prompt(&self) -> &'static str {
+        match self {
+            Self::WipeAllData => "Delete all accounts, groups and messages from this device?",
+            Self::ExportNsec => "Show the private key of this account?",
+            Self::DeleteAccount => "Sign out and remove this account's keys from this device?",
+            Self::ExportAccount => "Export this account's private key and groups to a backup file?",
+            Self::ExportAppData => "Export every account's private keys and messages to a file?",
+            Self::ExportAccountData => {
+                "Export this account's messages to an unencrypted file anyone can read?"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationToken {
+    pub token: String,
+    pub action: SensitiveAction,
+    /// Seconds the token can be used for
+    pub expires_in: u64,
+}
+
+/// Tokens handed out and not used yet
+#[derive(Debug, Default)]
+pub struct ConfirmationTokens {
+    pending: HashMap<String, (SensitiveAction, Instant)>,
+}
+
+impl ConfirmationTokens {
+    fn mint(&mut self, action: SensitiveAction, now: Instant) -> String {
+        self.pending
+            .retain(|_, (_, issued_at)| now.duration_since(*issued_at) < TOKEN_TTL);
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        let token = hex::encode(bytes);
+        self.pending.insert(token.clone(), (action, now));
+        token
+    }
+
+    /// Uses up a token, whether or not it was valid for the action
+    fn consume(&mut self, token: &str, action: SensitiveAction, now: Instant) -> bool {
+        self.pending
+            .remove(token)
+            .is_some_and(|(issued_for, issued_at)| {
+                issued_for == action && now.duration_since(issued_at) < TOKEN_TTL
+            })
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Asks the user to confirm an action in a native dialog
+async fn confirm_natively(action: SensitiveAction, app_handle: &AppHandle) -> bool {
+    let (tx, rx) = oneshot::channel();
+    app_handle
+        .dialog()
+        .message(action.prompt())
+        .title("White Noise")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .show(move |confirmed| {
+            let _ = tx.send(confirmed);
+        });
+    rx.await.unwrap_or(false)
+}
+
+/// Hands out a token for one sensitive action once the app passphrase checks out. The passphrase
+/// has to be the one the current session was unlocked with, i.e. the duress passphrase in the
+/// decoy profile. Without an app passphrase the user has to confirm the action in a native
+/// dialog.
+///
+/// # Arguments
+/// * `action` - The action the token is for
+/// * `passphrase` - The app passphrase, required when one is set
+/// * `app_handle` - The Tauri app handle, used to show the confirmation dialog
+/// * `wn` - The Whitenoise application state
+pub async fn request(
+    action: SensitiveAction,
+    passphrase: Option<&str>,
+    app_handle: &AppHandle,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<ConfirmationToken> {
+    app_lock::ensure_unlocked(&wn).await?;
+    let config = AppLockConfig::load(&wn.data_dir)?;
+    if config.passphrase.is_none() && !confirm_natively(action, app_handle).await {
+        return Err(SensitiveActionError::Declined);
+    }
+
+    let mut state = wn.app_lock.lock().await;
+    if state.locked {
+        return Err(AppLockError::Locked.into());
+    }
+    if config.passphrase.is_some() {
+        let expected = if state.decoy_active {
+            UnlockOutcome::Duress
+        } else {
+            UnlockOutcome::Unlocked
+        };
+        if !passphrase.is_some_and(|passphrase| config.check(passphrase) == expected) {
+            return Err(SensitiveActionError::IncorrectPassphrase);
+        }
+    }
+
+    let token = state.confirmations.mint(action, Instant::now());
+    tracing::debug!(
+        target: "whitenoise::sensitive_actions::request",
+        "Issued confirmation token for {:?}",
+        action
+    );
+    Ok(ConfirmationToken {
+        token,
+        action,
+        expires_in: TOKEN_TTL.as_secs(),
+    })
+}
+
+/// Checks and uses up the token passed to a sensitive command
+pub async fn authorize(
+    action: SensitiveAction,
+    token: &str,
+    wn: &tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    let mut state = wn.app_lock.lock().await;
+    if state.locked {
+        return Err(AppLockError::Locked.into());
+    }
+    if !state.confirmations.consume(token, action, Instant::now()) {
+        tracing::warn!(
+            target: "whitenoise::sensitive_actions::authorize",
+            "Rejected {:?} without a valid confirmation token",
+            action
+        );
+        return Err(SensitiveActionError::InvalidToken);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_single_use() {
+        let mut tokens = ConfirmationTokens::default();
+        let now = Instant::now();
+        let token = tokens.mint(SensitiveAction::ExportNsec, now);
+
+        assert!(tokens.consume(&token, SensitiveAction::ExportNsec, now));
+        assert!(!tokens.consume(&token, SensitiveAction::ExportNsec, now));
+    }
+
+    #[test]
+    fn test_token_is_bound_to_its_action() {
+        let mut tokens = ConfirmationTokens::default();
+        let now = Instant::now();
+        let token = tokens.mint(SensitiveAction::ExportNsec, now);
+
+        assert!(!tokens.consume(&token, SensitiveAction::WipeAllData, now));
+        // A token presented for the wrong action is used up too
+        assert!(!tokens.consume(&token, SensitiveAction::ExportNsec, now));
+    }
+
+    #[test]
+    fn test_token_expires() {
+        let mut tokens = ConfirmationTokens::default();
+        let now = Instant::now();
+        let token = tokens.mint(SensitiveAction::DeleteAccount, now);
+
+        assert!(!tokens.consume(&token, SensitiveAction::DeleteAccount, now + TOKEN_TTL));
+        assert!(!tokens.consume("unknown", SensitiveAction::DeleteAccount, now));
+    }
+
+    #[test]
+    fn test_clear_drops_tokens() {
+        let mut tokens = ConfirmationTokens::default();
+        let now = Instant::now();
+        let token = tokens.mint(SensitiveAction::WipeAllData, now);
+        tokens.clear();
+
+        assert!(!tokens.consume(&token, SensitiveAction::WipeAllData, now));
+    }
+}
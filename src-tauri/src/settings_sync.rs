@@ -0,0 +1,357 @@
+//! Sync of the account's settings between its devices.
+//!
+//! With device sync on, a device shares every synced setting it changes as a [`SettingChange`]
+//! inside a device sync delta, gift-wrapped to the account's own pubkey like the rest of device
+//! sync. Changes are merged last-write-wins per key: a change only applies if it's newer than the
+//! last change this device knows of for the same key, ties going to the larger value so all
+//! devices settle on the same one. The last change of each key is kept in `synced_settings`.
+//!
+//! Settings are synced by [`SettingScope`]. Scopes the user keeps device-local (see
+//! `set_device_local_settings`) are neither shared nor merged.
+
+use crate::accounts::{Account, AccountError};
+use crate::bulk_group_actions::{self, BulkGroupAction};
+use crate::device_sync::{self, SyncDelta};
+use crate::groups::{Group, GroupError};
+use crate::notifications::{NotificationLevel, NotificationPreferences};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SettingsSyncError {
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("SQLx error: {0}")]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SettingsSyncError>;
+
+/// A group of settings that is synced, or kept device-local, as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingScope {
+    /// Quiet hours and whether invites notify
+    NotificationPreferences,
+    /// The notification level of each group
+    GroupNotificationLevels,
+    /// Which groups are archived
+    ArchivedGroups,
+}
+
+/// The value of one synced setting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "setting", rename_all = "snake_case")]
+pub enum SyncedSetting {
+    NotificationPreferences {
+        preferences: NotificationPreferences,
+    },
+    GroupNotificationLevel {
+        /// Hex encoded MLS group ID
+        mls_group_id: String,
+        level: NotificationLevel,
+    },
+    GroupArchived {
+        /// Hex encoded MLS group ID
+        mls_group_id: String,
+        archived: bool,
+    },
+}
+
+impl SyncedSetting {
+    /// The key changes to this setting are merged under
+    pub fn key(&self) -> String {
+        match self {
+            Self::NotificationPreferences { .. } => "notifications".to_string(),
+            Self::GroupNotificationLevel { mls_group_id, .. } => {
+                format!("group_notification_level:{}", mls_group_id)
+            }
+            Self::GroupArchived { mls_group_id, .. } => format!("group_archived:{}", mls_group_id),
+        }
+    }
+
+    pub fn scope(&self) -> SettingScope {
+        match self {
+            Self::NotificationPreferences { .. } => SettingScope::NotificationPreferences,
+            Self::GroupNotificationLevel { .. } => SettingScope::GroupNotificationLevels,
+            Self::GroupArchived { .. } => SettingScope::ArchivedGroups,
+        }
+    }
+
+    /// The notification level of a group
+    pub fn group_notification_level(group: &Group) -> Self {
+        Self::GroupNotificationLevel {
+            mls_group_id: hex::encode(&group.mls_group_id),
+            level: group.notification_level(),
+        }
+    }
+
+    /// Whether a group is archived
+    pub fn group_archived(group: &Group, archived: bool) -> Self {
+        Self::GroupArchived {
+            mls_group_id: hex::encode(&group.mls_group_id),
+            archived,
+        }
+    }
+}
+
+/// A setting as changed on one of the account's devices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingChange {
+    #[serde(flatten)]
+    pub setting: SyncedSetting,
+    pub updated_at: Timestamp,
+}
+
+/// The last change this device knows of for a key
+#[derive(Debug, sqlx::FromRow)]
+struct StoredChange {
+    value: String,
+    updated_at: i64,
+}
+
+/// Whether a change wins over the last known change of its key: the later one wins, and between
+/// changes made at the same second the one with the larger value
+fn wins(updated_at: Timestamp, value: &str, stored: Option<&StoredChange>) -> bool {
+    match stored {
+        None => true,
+        Some(stored) => {
+            (updated_at.as_u64() as i64, value) > (stored.updated_at, stored.value.as_str())
+        }
+    }
+}
+
+async fn stored_change(
+    account: &Account,
+    key: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Option<StoredChange>> {
+    Ok(sqlx::query_as::<_, StoredChange>(
+        "SELECT value, updated_at FROM synced_settings WHERE account_pubkey = ? AND key = ?",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(key)
+    .fetch_optional(&wn.database.pool)
+    .await?)
+}
+
+async fn store_change(
+    account: &Account,
+    change: &SettingChange,
+    value: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO synced_settings (account_pubkey, key, value, updated_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT(account_pubkey, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+    )
+    .bind(account.pubkey.to_hex())
+    .bind(change.setting.key())
+    .bind(value)
+    .bind(change.updated_at.as_u64() as i64)
+    .execute(&wn.database.pool)
+    .await?;
+    Ok(())
+}
+
+/// Records settings the user just changed on this device and shares them with the account's
+/// other devices. Settings in a device-local scope are skipped. Failures are logged rather than
+/// returned, so that syncing can never fail the change itself.
+pub async fn share(settings: Vec<SyncedSetting>, wn: tauri::State<'_, Whitenoise>) {
+    if let Err(e) = record(settings, wn).await {
+        tracing::error!(
+            target: "whitenoise::settings_sync::share",
+            "Failed to share settings with other devices: {}",
+            e
+        );
+    }
+}
+
+async fn record(settings: Vec<SyncedSetting>, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    let account = Account::get_active(wn.clone()).await?;
+    let updated_at = Timestamp::now();
+    let mut changes = Vec::with_capacity(settings.len());
+    for setting in settings {
+        if account
+            .settings
+            .device_local_settings
+            .contains(&setting.scope())
+        {
+            continue;
+        }
+        let change = SettingChange {
+            setting,
+            updated_at,
+        };
+        let value = serde_json::to_string(&change.setting)?;
+        store_change(&account, &change, &value, wn.clone()).await?;
+        changes.push(change);
+    }
+    if !changes.is_empty() {
+        device_sync::share(vec![SyncDelta::Settings { changes }], wn).await;
+    }
+    Ok(())
+}
+
+/// Merges settings another device of `account` changed. Changes in a device-local scope, changes
+/// older than the last known change of their key, and changes to groups this device doesn't have
+/// are skipped. Emits `account_updated` if the notification preferences changed and
+/// `group_updated` for every group that changed.
+pub async fn merge(
+    account: &Account,
+    changes: Vec<SettingChange>,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<()> {
+    let mut account = account.clone();
+    for change in changes {
+        if account
+            .settings
+            .device_local_settings
+            .contains(&change.setting.scope())
+        {
+            continue;
+        }
+        let value = serde_json::to_string(&change.setting)?;
+        let stored = stored_change(&account, &change.setting.key(), wn.clone()).await?;
+        if !wins(change.updated_at, &value, stored.as_ref()) {
+            continue;
+        }
+
+        match &change.setting {
+            SyncedSetting::NotificationPreferences { preferences } => {
+                if let Some(quiet_hours) = &preferences.quiet_hours {
+                    if quiet_hours.validate().is_err() {
+                        continue;
+                    }
+                }
+                // Only the preferences change, the rest of the settings may have moved on since
+                // the account was loaded
+                account = account
+                    .update_settings(
+                        |settings| settings.notifications = preferences.clone(),
+                        wn.clone(),
+                    )
+                    .await?;
+                app_handle.emit("account_updated", ())?;
+            }
+            SyncedSetting::GroupNotificationLevel {
+                mls_group_id,
+                level,
+            } => {
+                let Some(group) = find_group(&account, mls_group_id, wn.clone()).await else {
+                    continue;
+                };
+                group.set_notification_level(*level, wn.clone()).await?;
+                let group = Group::find_by_mls_group_id_for_account(
+                    &group.mls_group_id,
+                    &account.pubkey,
+                    wn.clone(),
+                )
+                .await?;
+                app_handle.emit("group_updated", group)?;
+            }
+            SyncedSetting::GroupArchived {
+                mls_group_id,
+                archived,
+            } => {
+                let Some(group) = find_group(&account, mls_group_id, wn.clone()).await else {
+                    continue;
+                };
+                let action = if *archived {
+                    BulkGroupAction::Archive
+                } else {
+                    BulkGroupAction::Unarchive
+                };
+                if bulk_group_actions::validate(action, &group).is_err() {
+                    continue;
+                }
+                bulk_group_actions::apply(std::slice::from_ref(&group), action, wn.clone()).await?;
+                let group = Group::find_by_mls_group_id_for_account(
+                    &group.mls_group_id,
+                    &account.pubkey,
+                    wn.clone(),
+                )
+                .await?;
+                app_handle.emit("group_updated", group)?;
+            }
+        }
+        store_change(&account, &change, &value, wn.clone()).await?;
+    }
+    Ok(())
+}
+
+/// The account's group with a hex encoded MLS group ID, if this device has it
+async fn find_group(
+    account: &Account,
+    mls_group_id: &str,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Option<Group> {
+    let mls_group_id = hex::decode(mls_group_id).ok()?;
+    Group::find_by_mls_group_id_for_account(&mls_group_id, &account.pubkey, wn)
+        .await
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(updated_at: i64, value: &str) -> StoredChange {
+        StoredChange {
+            value: value.to_string(),
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn test_later_change_wins() {
+        let at = Timestamp::from(1_000);
+        assert!(wins(at, "a", None));
+        assert!(wins(at, "a", Some(&stored(999, "b"))));
+        assert!(!wins(at, "b", Some(&stored(1_001, "a"))));
+    }
+
+    #[test]
+    fn test_ties_go_to_the_larger_value() {
+        let at = Timestamp::from(1_000);
+        assert!(wins(at, "b", Some(&stored(1_000, "a"))));
+        assert!(!wins(at, "a", Some(&stored(1_000, "b"))));
+        // Merging the same change twice is a no-op
+        assert!(!wins(at, "a", Some(&stored(1_000, "a"))));
+    }
+
+    #[test]
+    fn test_change_serialization() {
+        let change = SettingChange {
+            setting: SyncedSetting::GroupArchived {
+                mls_group_id: "00ff".to_string(),
+                archived: true,
+            },
+            updated_at: Timestamp::from(1_700_000_000),
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["setting"], "group_archived");
+        assert_eq!(json["archived"], true);
+        assert_eq!(json["updated_at"], 1_700_000_000);
+        assert_eq!(
+            serde_json::from_value::<SettingChange>(json).unwrap(),
+            change
+        );
+        assert_eq!(change.setting.key(), "group_archived:00ff");
+        assert_eq!(change.setting.scope(), SettingScope::ArchivedGroups);
+    }
+}
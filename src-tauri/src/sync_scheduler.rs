@@ -0,0 +1,442 @@
+//! Scheduled background syncing.
+//!
+//! Subscriptions deliver new events while the relays stay connected, but anything a relay dropped
+//! or that was published while the app was suspended is only caught by fetching again. Each
+//! [`SyncTask`] runs in its own background task on the interval the active account configured
+//! (see [`SyncSchedule`]), stretched by the current sync mode like the other polling tasks.
+//!
+//! `pause_sync` stops the scheduled runs until `resume_sync`, and `sync_now` runs tasks right
+//! away, paused or not. Every run emits `sync_started` with its task and `sync_finished` with a
+//! [`SyncReport`].
+
+use crate::accounts::{Account, AccountError};
+use crate::background_refresh::{self, BackgroundRefreshError};
+use crate::epoch_recovery;
+use crate::group_security;
+use crate::groups::{Group, GroupState};
+use crate::key_packages::{self, KeyPackageError};
+use crate::nostr_manager::NostrManagerError;
+use crate::sync_throttle;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// How often the background tasks check whether their task is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(30);
+
+/// Shortest interval a task can be scheduled at
+pub const MIN_SYNC_INTERVAL_SECS: u64 = 60;
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Key package error: {0}")]
+    KeyPackageError(#[from] KeyPackageError),
+
+    #[error("Background refresh error: {0}")]
+    BackgroundRefreshError(#[from] BackgroundRefreshError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SyncError>;
+
+/// Something the scheduler keeps up to date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncTask {
+    /// Fetches the messages of the account's groups since the last sync
+    GroupMessages,
+    /// Fetches gift-wrapped events, which carry welcomes to new groups
+    Welcomes,
+    /// Refreshes the metadata of the account's contacts
+    Contacts,
+    /// Publishes key packages until the account's pool is full again
+    KeyPackages,
+}
+
+impl SyncTask {
+    pub const ALL: [SyncTask; 4] = [
+        SyncTask::GroupMessages,
+        SyncTask::Welcomes,
+        SyncTask::Contacts,
+        SyncTask::KeyPackages,
+    ];
+}
+
+/// How often each task runs, in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSchedule {
+    #[serde(default = "default_group_messages_secs")]
+    pub group_messages_secs: u64,
+    #[serde(default = "default_welcomes_secs")]
+    pub welcomes_secs: u64,
+    #[serde(default = "default_contacts_secs")]
+    pub contacts_secs: u64,
+    #[serde(default = "default_key_packages_secs")]
+    pub key_packages_secs: u64,
+}
+
+fn default_group_messages_secs() -> u64 {
+    5 * 60
+}
+
+fn default_welcomes_secs() -> u64 {
+    10 * 60
+}
+
+fn default_contacts_secs() -> u64 {
+    60 * 60
+}
+
+fn default_key_packages_secs() -> u64 {
+    30 * 60
+}
+
+impl Default for SyncSchedule {
+    fn default() -> Self {
+        Self {
+            group_messages_secs: default_group_messages_secs(),
+            welcomes_secs: default_welcomes_secs(),
+            contacts_secs: default_contacts_secs(),
+            key_packages_secs: default_key_packages_secs(),
+        }
+    }
+}
+
+impl SyncSchedule {
+    /// How long to wait between runs of a task
+    pub fn interval(&self, task: SyncTask) -> Duration {
+        let secs = match task {
+            SyncTask::GroupMessages => self.group_messages_secs,
+            SyncTask::Welcomes => self.welcomes_secs,
+            SyncTask::Contacts => self.contacts_secs,
+            SyncTask::KeyPackages => self.key_packages_secs,
+        };
+        Duration::from_secs(secs)
+    }
+
+    /// Checks that no task runs more often than every [`MIN_SYNC_INTERVAL_SECS`]
+    pub fn validate(&self) -> Result<()> {
+        for task in SyncTask::ALL {
+            if self.interval(task).as_secs() < MIN_SYNC_INTERVAL_SECS {
+                return Err(SyncError::InvalidSchedule(format!(
+                    "{:?} can't run more often than every {} seconds",
+                    task, MIN_SYNC_INTERVAL_SECS
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of one run of a task, emitted as `sync_finished`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub task: SyncTask,
+    pub started_at: Timestamp,
+    pub duration_ms: u64,
+    /// Why the run failed, if it did
+    pub error: Option<String>,
+}
+
+/// What a task is up to, for `dump_runtime_state`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncTaskState {
+    pub task: SyncTask,
+    pub running: bool,
+    /// Seconds since the task last started, unknown while it runs
+    pub last_run_secs_ago: Option<u64>,
+}
+
+/// Whether scheduled syncing is paused and what each task is up to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSchedulerState {
+    pub paused: bool,
+    pub tasks: Vec<SyncTaskState>,
+}
+
+/// Whether scheduled syncing is paused and when each task last ran
+pub struct SyncScheduler {
+    paused: AtomicBool,
+    /// Held while a task runs, so runs of the same task never overlap
+    last_runs: HashMap<SyncTask, Mutex<Option<Instant>>>,
+}
+
+impl Default for SyncScheduler {
+    fn default() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            // Signing in fetches everything, so the first scheduled runs wait a full interval
+            last_runs: SyncTask::ALL
+                .into_iter()
+                .map(|task| (task, Mutex::new(Some(Instant::now()))))
+                .collect(),
+        }
+    }
+}
+
+impl SyncScheduler {
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    fn last_run(&self, task: SyncTask) -> &Mutex<Option<Instant>> {
+        &self.last_runs[&task]
+    }
+
+    /// What each task is up to, without waiting for running ones
+    pub fn state(&self) -> SyncSchedulerState {
+        let tasks = SyncTask::ALL
+            .into_iter()
+            .map(|task| match self.last_run(task).try_lock() {
+                Ok(last_run) => SyncTaskState {
+                    task,
+                    running: false,
+                    last_run_secs_ago: last_run.map(|at| at.elapsed().as_secs()),
+                },
+                Err(_) => SyncTaskState {
+                    task,
+                    running: true,
+                    last_run_secs_ago: None,
+                },
+            })
+            .collect();
+        SyncSchedulerState {
+            paused: self.is_paused(),
+            tasks,
+        }
+    }
+}
+
+/// Whether a task that last ran at `last_run` is due again
+fn is_due(last_run: Option<Instant>, interval: Duration, now: Instant) -> bool {
+    last_run.is_none_or(|last_run| now.duration_since(last_run) >= interval)
+}
+
+/// Does the work of a task for the active account
+async fn execute(task: SyncTask, app_handle: &AppHandle) -> Result<()> {
+    let wn = app_handle.state::<Whitenoise>();
+    let account = Account::get_active(wn.clone()).await?;
+    match task {
+        SyncTask::GroupMessages => {
+            let started = Timestamp::now();
+            let groups: Vec<Group> = account
+                .groups(wn.clone())
+                .await?
+                .into_iter()
+                .filter(|group| matches!(group.state, GroupState::Active))
+                .collect();
+            // Each group is fetched from its own checkpoint
+            let (_, complete) = background_refresh::sync_groups(&groups, &wn).await?;
+            // Only the messages up to the start of the fetch are known to be in, and only if
+            // every group's fetch finished
+            if complete {
+                account.update_last_synced(started, wn.clone()).await?;
+            }
+            // Secrets that arrived since the last run may decrypt quarantined messages
+            if let Err(e) = epoch_recovery::retry(app_handle).await {
+                tracing::warn!(
+                    target: "whitenoise::sync_scheduler::execute",
+                    "Failed to retry quarantined messages: {}",
+                    e
+                );
+            }
+            // Pruned after the retry, which may still need the oldest secrets
+            if let Err(e) = epoch_recovery::prune_all_secrets(wn.clone()).await {
+                tracing::warn!(
+                    target: "whitenoise::sync_scheduler::execute",
+                    "Failed to prune export secrets: {}",
+                    e
+                );
+            }
+            flag_stale_members(app_handle).await;
+        }
+        SyncTask::Welcomes => {
+            wn.nostr
+                .fetch_user_giftwrapped_events(account.pubkey)
+                .await?;
+        }
+        SyncTask::Contacts => {
+            wn.nostr.fetch_contacts().await?;
+        }
+        SyncTask::KeyPackages => {
+            key_packages::replenish(wn.clone()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Indexes the members of the active account's active groups and lets admins know about members
+/// that went stale. Failures are logged, so they don't fail the sync.
+async fn flag_stale_members(app_handle: &AppHandle) {
+    let wn = app_handle.state::<Whitenoise>();
+    let groups = match Group::get_all_groups(wn.clone()).await {
+        Ok(groups) => groups,
+        Err(e) => {
+            tracing::warn!(
+                target: "whitenoise::sync_scheduler::flag_stale_members",
+                "Failed to load groups: {}",
+                e
+            );
+            return;
+        }
+    };
+    let active_groups = groups
+        .into_iter()
+        .filter(|group| matches!(group.state, GroupState::Active) && group.archived_at.is_none());
+    for group in active_groups {
+        if let Err(e) = group_security::flag_stale_members(&group, wn.clone(), app_handle).await {
+            tracing::warn!(
+                target: "whitenoise::sync_scheduler::flag_stale_members",
+                "Failed to flag stale members of group {}: {}",
+                hex::encode(&group.mls_group_id),
+                e
+            );
+        }
+    }
+}
+
+/// Runs a task now, after any run of it that's already going, emitting `sync_started` and
+/// `sync_finished`
+pub async fn run(task: SyncTask, app_handle: &AppHandle) -> Result<SyncReport> {
+    let wn = app_handle.state::<Whitenoise>();
+    let mut last_run = wn.sync_scheduler.last_run(task).lock().await;
+    run_locked(task, &mut last_run, app_handle).await
+}
+
+async fn run_locked(
+    task: SyncTask,
+    last_run: &mut Option<Instant>,
+    app_handle: &AppHandle,
+) -> Result<SyncReport> {
+    let started = Instant::now();
+    let started_at = Timestamp::now();
+    *last_run = Some(started);
+    app_handle.emit("sync_started", task)?;
+
+    let result = execute(task, app_handle).await;
+    if let Err(e) = &result {
+        tracing::warn!(
+            target: "whitenoise::sync_scheduler::run",
+            "Sync of {:?} failed: {}",
+            task,
+            e
+        );
+    }
+    let report = SyncReport {
+        task,
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        error: result.err().map(|e| e.to_string()),
+    };
+    app_handle.emit("sync_finished", report.clone())?;
+    Ok(report)
+}
+
+/// Runs a task if it's due, unless syncing is paused, the client is offline or has no account
+/// signed in, or the task is already running
+async fn run_if_due(task: SyncTask, app_handle: &AppHandle) -> Result<()> {
+    let wn = app_handle.state::<Whitenoise>();
+    if wn.sync_scheduler.is_paused()
+        || !wn.nostr.relay_monitor.is_online()
+        || wn.nostr.client.signer().await.is_err()
+    {
+        return Ok(());
+    }
+    let Ok(mut last_run) = wn.sync_scheduler.last_run(task).try_lock() else {
+        return Ok(());
+    };
+
+    let schedule = Account::get_active(wn.clone())
+        .await?
+        .settings
+        .sync_schedule;
+    let mode = sync_throttle::current_mode(&wn).await;
+    let interval = schedule.interval(task) * mode.tick_multiplier();
+    if is_due(*last_run, interval, Instant::now()) {
+        run_locked(task, &mut last_run, app_handle).await?;
+    }
+    Ok(())
+}
+
+/// Starts a background task per [`SyncTask`] that runs it on the active account's schedule
+pub fn start(app_handle: AppHandle) {
+    for task in SyncTask::ALL {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(SCHEDULER_TICK);
+            loop {
+                interval.tick().await;
+                if let Err(e) = run_if_due(task, &app_handle).await {
+                    tracing::debug!(
+                        target: "whitenoise::sync_scheduler::start",
+                        "Couldn't run scheduled sync of {:?}: {}",
+                        task,
+                        e
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_defaults_fill_missing_fields() {
+        let schedule: SyncSchedule = serde_json::from_str(r#"{"contacts_secs":120}"#).unwrap();
+        assert_eq!(
+            schedule.interval(SyncTask::Contacts),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            schedule.interval(SyncTask::GroupMessages),
+            Duration::from_secs(default_group_messages_secs())
+        );
+        assert!(schedule.validate().is_ok());
+    }
+
+    #[test]
+    fn test_schedule_rejects_short_intervals() {
+        let schedule = SyncSchedule {
+            welcomes_secs: MIN_SYNC_INTERVAL_SECS - 1,
+            ..SyncSchedule::default()
+        };
+        assert!(schedule.validate().is_err());
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = Instant::now();
+        let interval = Duration::from_secs(60);
+        assert!(is_due(None, interval, now));
+        assert!(!is_due(Some(now), interval, now + Duration::from_secs(59)));
+        assert!(is_due(Some(now), interval, now + interval));
+    }
+}
@@ -0,0 +1,221 @@
+//! Battery and network aware sync throttling.
+//!
+//! Platforms report whether the device runs on battery, is low on battery or uses a metered
+//! connection through `set_power_state`. Each account's [`SyncPolicy`] maps those conditions to a
+//! [`SyncMode`], and the most restrictive applicable mode wins:
+//!
+//! - `full`: relays are polled and subscribed to as usual.
+//! - `reduced`: background polling (outbox retries) runs less often.
+//! - `minimal`: polling is reduced further and the subscriptions to contacts' metadata and key
+//!   migrations are closed. Gift wraps and group messages keep flowing.
+//!
+//! Mode changes are emitted as `sync_mode_changed`.
+
+use crate::accounts::{Account, AccountError};
+use crate::nostr_manager::NostrManagerError;
+use crate::Whitenoise;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SyncThrottleError {
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SyncThrottleError>;
+
+/// Power and network conditions reported by the platform
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerState {
+    #[serde(default)]
+    pub on_battery: bool,
+    #[serde(default)]
+    pub low_battery: bool,
+    /// Whether the active connection is metered (e.g. cellular data)
+    #[serde(default)]
+    pub metered: bool,
+}
+
+/// How aggressively the app syncs, from least to most restrictive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    #[default]
+    Full,
+    Reduced,
+    Minimal,
+}
+
+impl SyncMode {
+    /// How many ticks of a background polling task make up one run in this mode
+    pub fn tick_multiplier(self) -> u32 {
+        match self {
+            SyncMode::Full => 1,
+            SyncMode::Reduced => 4,
+            SyncMode::Minimal => 12,
+        }
+    }
+
+    /// Whether contacts' metadata and key migrations are subscribed to
+    pub fn contact_subscriptions(self) -> bool {
+        self != SyncMode::Minimal
+    }
+}
+
+/// The sync mode an account uses under each condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncPolicy {
+    #[serde(default)]
+    pub on_battery: SyncMode,
+    #[serde(default = "default_low_battery_mode")]
+    pub low_battery: SyncMode,
+    #[serde(default = "default_metered_mode")]
+    pub metered: SyncMode,
+}
+
+fn default_low_battery_mode() -> SyncMode {
+    SyncMode::Minimal
+}
+
+fn default_metered_mode() -> SyncMode {
+    SyncMode::Reduced
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        Self {
+            on_battery: SyncMode::Full,
+            low_battery: default_low_battery_mode(),
+            metered: default_metered_mode(),
+        }
+    }
+}
+
+impl SyncPolicy {
+    /// The most restrictive mode among the conditions that apply
+    pub fn mode_for(&self, state: &PowerState) -> SyncMode {
+        let mut mode = SyncMode::Full;
+        if state.on_battery {
+            mode = mode.max(self.on_battery);
+        }
+        if state.low_battery {
+            mode = mode.max(self.low_battery);
+        }
+        if state.metered {
+            mode = mode.max(self.metered);
+        }
+        mode
+    }
+}
+
+/// The sync mode for the current power state and the active account's policy. Without an active
+/// account the default policy applies.
+pub async fn current_mode(wn: &tauri::State<'_, Whitenoise>) -> SyncMode {
+    let state = *wn.power_state.lock().await;
+    let policy = Account::get_active(wn.clone())
+        .await
+        .map(|account| account.settings.sync_policy)
+        .unwrap_or_default();
+    policy.mode_for(&state)
+}
+
+/// Records a new power state and applies the resulting sync mode
+pub async fn set_power_state(
+    state: PowerState,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<SyncMode> {
+    let previous = current_mode(&wn).await;
+    *wn.power_state.lock().await = state;
+    apply(previous, wn, app_handle).await
+}
+
+/// Applies the current sync mode if it differs from `previous`: opens or closes the contact
+/// subscriptions and emits `sync_mode_changed`
+pub async fn apply(
+    previous: SyncMode,
+    wn: tauri::State<'_, Whitenoise>,
+    app_handle: &tauri::AppHandle,
+) -> Result<SyncMode> {
+    let mode = current_mode(&wn).await;
+    if mode == previous {
+        return Ok(mode);
+    }
+
+    tracing::info!(
+        target: "whitenoise::sync_throttle::apply",
+        "Sync mode changed from {:?} to {:?}",
+        previous,
+        mode
+    );
+
+    if mode.contact_subscriptions() != previous.contact_subscriptions() {
+        // Without a signer the subscriptions are set up on login with the current mode
+        if wn.nostr.client.signer().await.is_ok() {
+            if mode.contact_subscriptions() {
+                wn.nostr.resume_contact_subscriptions().await?;
+            } else {
+                wn.nostr.pause_contact_subscriptions().await;
+            }
+        }
+    }
+
+    app_handle.emit("sync_mode_changed", mode)?;
+    Ok(mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unconstrained_device_syncs_fully() {
+        let policy = SyncPolicy {
+            on_battery: SyncMode::Minimal,
+            low_battery: SyncMode::Minimal,
+            metered: SyncMode::Minimal,
+        };
+        assert_eq!(policy.mode_for(&PowerState::default()), SyncMode::Full);
+    }
+
+    #[test]
+    fn test_most_restrictive_condition_wins() {
+        let policy = SyncPolicy::default();
+        let state = PowerState {
+            on_battery: true,
+            low_battery: false,
+            metered: true,
+        };
+        assert_eq!(policy.mode_for(&state), SyncMode::Reduced);
+
+        let state = PowerState {
+            low_battery: true,
+            ..state
+        };
+        assert_eq!(policy.mode_for(&state), SyncMode::Minimal);
+    }
+
+    #[test]
+    fn test_policy_defaults_fill_missing_fields() {
+        let policy: SyncPolicy = serde_json::from_str(r#"{"on_battery":"reduced"}"#).unwrap();
+        assert_eq!(policy.on_battery, SyncMode::Reduced);
+        assert_eq!(policy.low_battery, SyncMode::Minimal);
+        assert_eq!(policy.metered, SyncMode::Reduced);
+    }
+
+    #[test]
+    fn test_minimal_mode_drops_contact_subscriptions() {
+        assert!(SyncMode::Full.contact_subscriptions());
+        assert!(SyncMode::Reduced.contact_subscriptions());
+        assert!(!SyncMode::Minimal.contact_subscriptions());
+        assert!(SyncMode::Minimal.tick_multiplier() > SyncMode::Reduced.tick_multiplier());
+    }
+}
@@ -12,10 +12,16 @@ pub struct EnrichedContact {
     pub nip104: bool,
     /// The relays for the user. NIP-65
     pub nostr_relays: Vec<String>,
+    /// The relays the user reads from, including unmarked ones. NIP-65
+    #[serde(default)]
+    pub nostr_read_relays: Vec<String>,
     /// The relays for the contact's inbox. NIP-17
     pub inbox_relays: Vec<String>,
     /// The relays for the contact's key package. NIP-104
     pub key_package_relays: Vec<String>,
+    /// Whether the domain in the metadata's `nip05` vouches for the contact. NIP-05
+    #[serde(default)]
+    pub nip05_verified: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
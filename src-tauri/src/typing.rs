@@ -0,0 +1,24 @@
+//! Typing indicators.
+//!
+//! A typing indicator is an ephemeral rumor sent through the group like any other message, but
+//! it's never stored: receivers only emit a `peer_typing` event to the frontend. Indicators carry
+//! a short NIP-40 expiration so late deliveries (and relays) can drop them.
+
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The inner event kind used for typing indicators (ephemeral range)
+pub const TYPING_INDICATOR_KIND: u16 = 20_067;
+
+/// How long a typing indicator is valid for, in seconds
+pub const TYPING_INDICATOR_TTL_SECS: u64 = 10;
+
+/// Payload of the `peer_typing` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerTypingEvent {
+    /// Hex encoded MLS group ID
+    pub group_id: String,
+    pub pubkey: PublicKey,
+    /// Frontends should stop showing the indicator at this time unless a new one arrives
+    pub expires_at: Timestamp,
+}
@@ -0,0 +1,232 @@
+//! Local usage statistics for the insights screen.
+//!
+//! Counters are kept per account, group and UTC day in `usage_stats` and bumped in the same
+//! transaction that stores a message, so the totals never need a scan of the transcript. They
+//! are never sent anywhere. Deleting a group drops its counters with it.
+
+use crate::accounts::Account;
+use crate::media::attachments::AttachmentMeta;
+use crate::messages::SYSTEM_MESSAGE_KINDS;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How many groups the most active list holds
+const MOST_ACTIVE_GROUPS: i64 = 5;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The span of time usage is summarized over, ending today
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsagePeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+impl UsagePeriod {
+    /// How many days the period covers, today included. `None` for all time.
+    pub fn days(self) -> Option<u64> {
+        match self {
+            UsagePeriod::Day => Some(1),
+            UsagePeriod::Week => Some(7),
+            UsagePeriod::Month => Some(30),
+            UsagePeriod::Year => Some(365),
+            UsagePeriod::All => None,
+        }
+    }
+
+    /// The first day (in days since the epoch) the period covers
+    fn first_day(self, today: u64) -> u64 {
+        self.days()
+            .map_or(0, |days| today.saturating_sub(days.saturating_sub(1)))
+    }
+}
+
+/// Messages sent and received on one day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DailyUsage {
+    /// UTC date as `YYYY-MM-DD`
+    pub date: String,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+}
+
+/// A group and how many messages it saw in the period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GroupUsage {
+    /// Hex encoded MLS group ID
+    pub mls_group_id: String,
+    pub name: String,
+    pub messages: u64,
+}
+
+/// The account's usage over a period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsageStats {
+    pub period: UsagePeriod,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    /// Days without messages are left out
+    pub daily: Vec<DailyUsage>,
+    pub most_active_groups: Vec<GroupUsage>,
+    /// Attachments sent and received
+    pub media_count: u64,
+    pub media_bytes: u64,
+}
+
+fn day_of(timestamp: Timestamp) -> u64 {
+    timestamp.as_u64() / SECONDS_PER_DAY
+}
+
+fn date_of_day(day: u64) -> String {
+    chrono::DateTime::from_timestamp((day * SECONDS_PER_DAY) as i64, 0)
+        .map(|datetime| datetime.date_naive().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether messages of an inner event kind count as messages. System messages (reactions,
+/// edits, deletions, admin notices...) don't.
+fn is_counted(kind: u16) -> bool {
+    !SYSTEM_MESSAGE_KINDS.contains(&kind)
+}
+
+/// Counts a newly stored message towards its day, unless it's a system message
+pub async fn record_message(
+    conn: &mut sqlx::SqliteConnection,
+    account_pubkey: &PublicKey,
+    mls_group_id: &[u8],
+    message: &UnsignedEvent,
+) -> sqlx::Result<()> {
+    if !is_counted(message.kind.as_u16()) {
+        return Ok(());
+    }
+
+    let sent = message.pubkey == *account_pubkey;
+    let attachments = AttachmentMeta::from_tags(&message.tags);
+    let media_bytes: u64 = attachments.iter().map(|meta| meta.size).sum();
+
+    sqlx::query(
+        "INSERT INTO usage_stats (account_pubkey, mls_group_id, day, messages_sent, messages_received, media_count, media_bytes)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT (account_pubkey, mls_group_id, day) DO UPDATE SET
+            messages_sent = messages_sent + excluded.messages_sent,
+            messages_received = messages_received + excluded.messages_received,
+            media_count = media_count + excluded.media_count,
+            media_bytes = media_bytes + excluded.media_bytes",
+    )
+    .bind(account_pubkey.to_hex())
+    .bind(mls_group_id)
+    .bind(day_of(message.created_at) as i64)
+    .bind(sent as i64)
+    .bind(!sent as i64)
+    .bind(attachments.len() as i64)
+    .bind(media_bytes as i64)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Summarizes the active account's usage over a period
+pub async fn get(
+    account: &Account,
+    period: UsagePeriod,
+    wn: tauri::State<'_, Whitenoise>,
+) -> sqlx::Result<UsageStats> {
+    let first_day = period.first_day(day_of(Timestamp::now())) as i64;
+    let account_pubkey = account.pubkey.to_hex();
+
+    let days = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT day, SUM(messages_sent), SUM(messages_received) FROM usage_stats
+         WHERE account_pubkey = ? AND day >= ?
+         GROUP BY day ORDER BY day",
+    )
+    .bind(&account_pubkey)
+    .bind(first_day)
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let (media_count, media_bytes) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COALESCE(SUM(media_count), 0), COALESCE(SUM(media_bytes), 0) FROM usage_stats
+         WHERE account_pubkey = ? AND day >= ?",
+    )
+    .bind(&account_pubkey)
+    .bind(first_day)
+    .fetch_one(&wn.database.pool)
+    .await?;
+
+    let groups = sqlx::query_as::<_, (Vec<u8>, String, i64)>(
+        "SELECT usage_stats.mls_group_id, groups.name, SUM(messages_sent + messages_received) AS messages
+         FROM usage_stats
+         JOIN groups ON groups.mls_group_id = usage_stats.mls_group_id
+            AND groups.account_pubkey = usage_stats.account_pubkey
+         WHERE usage_stats.account_pubkey = ? AND day >= ?
+         GROUP BY usage_stats.mls_group_id
+         ORDER BY messages DESC
+         LIMIT ?",
+    )
+    .bind(&account_pubkey)
+    .bind(first_day)
+    .bind(MOST_ACTIVE_GROUPS)
+    .fetch_all(&wn.database.pool)
+    .await?;
+
+    let daily: Vec<DailyUsage> = days
+        .into_iter()
+        .map(|(day, sent, received)| DailyUsage {
+            date: date_of_day(day as u64),
+            messages_sent: sent as u64,
+            messages_received: received as u64,
+        })
+        .collect();
+
+    Ok(UsageStats {
+        period,
+        messages_sent: daily.iter().map(|day| day.messages_sent).sum(),
+        messages_received: daily.iter().map(|day| day.messages_received).sum(),
+        daily,
+        most_active_groups: groups
+            .into_iter()
+            .map(|(mls_group_id, name, messages)| GroupUsage {
+                mls_group_id: hex::encode(mls_group_id),
+                name,
+                messages: messages as u64,
+            })
+            .collect(),
+        media_count: media_count as u64,
+        media_bytes: media_bytes as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{EDIT_KIND, GROUP_NOTICE_KIND};
+    use crate::protocol::CHAT_MESSAGE_KIND;
+    use crate::reactions::REACTION_KIND;
+
+    #[test]
+    fn test_period_first_day_includes_today() {
+        assert_eq!(UsagePeriod::Day.first_day(100), 100);
+        assert_eq!(UsagePeriod::Week.first_day(100), 94);
+        assert_eq!(UsagePeriod::All.first_day(100), 0);
+        assert_eq!(UsagePeriod::Year.first_day(3), 0);
+    }
+
+    #[test]
+    fn test_system_messages_are_not_counted() {
+        assert!(is_counted(CHAT_MESSAGE_KIND));
+        assert!(!is_counted(GROUP_NOTICE_KIND));
+        assert!(!is_counted(REACTION_KIND));
+        assert!(!is_counted(EDIT_KIND));
+    }
+
+    #[test]
+    fn test_days_are_utc_dates() {
+        let timestamp = Timestamp::from(1_700_000_000);
+        assert_eq!(date_of_day(day_of(timestamp)), "2023-11-14");
+    }
+}
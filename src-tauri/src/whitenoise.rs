@@ -1,5 +1,11 @@
+use crate::app_lock::AppLockState;
 use crate::database::Database;
+use crate::db_encryption;
 use crate::nostr_manager::NostrManager;
+use crate::runtime_state::TrackedMutex;
+use crate::secrets_store;
+use crate::sync_scheduler::SyncScheduler;
+use crate::sync_throttle::PowerState;
 use nostr_openmls::NostrMls;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -10,7 +16,10 @@ use tokio::sync::Mutex;
 pub struct Whitenoise {
     pub database: Arc<Database>,
     pub nostr: NostrManager,
-    pub nostr_mls: Arc<Mutex<NostrMls>>,
+    pub nostr_mls: Arc<TrackedMutex<NostrMls>>,
+    pub app_lock: Arc<Mutex<AppLockState>>,
+    pub power_state: Arc<Mutex<PowerState>>,
+    pub sync_scheduler: Arc<SyncScheduler>,
     pub data_dir: PathBuf,
     pub logs_dir: PathBuf,
 }
@@ -23,16 +32,33 @@ impl Whitenoise {
             &data_dir
         );
 
+        let db_path = data_dir.join("whitenoise.sqlite");
+        if let Err(e) = db_encryption::scrub_leftovers(&db_path) {
+            tracing::error!(
+                target: "whitenoise::whitenoise::new",
+                "Failed to scrub plaintext database copy: {}",
+                e
+            );
+        }
+        let db_key =
+            db_encryption::key_for(&db_path, &data_dir).expect("Failed to read database key");
+
         Self {
             database: Arc::new(
-                Database::new(data_dir.join("whitenoise.sqlite"), app_handle.clone())
+                Database::new(db_path, db_key, app_handle.clone())
                     .await
                     .expect("Failed to create database"),
             ),
             nostr: NostrManager::new(data_dir.clone(), app_handle.clone())
                 .await
                 .expect("Failed to create Nostr manager"),
-            nostr_mls: Arc::new(Mutex::new(NostrMls::new(data_dir.clone(), None))),
+            nostr_mls: Arc::new(TrackedMutex::new(
+                "nostr_mls",
+                NostrMls::new(data_dir.clone(), None),
+            )),
+            app_lock: Arc::new(Mutex::new(AppLockState::new(&data_dir))),
+            power_state: Arc::new(Mutex::new(PowerState::default())),
+            sync_scheduler: Arc::new(SyncScheduler::default()),
             data_dir,
             logs_dir,
         }
@@ -45,6 +71,7 @@ impl Whitenoise {
         self.nostr.delete_all_data().await?;
         self.database.delete_all_data().await?;
         self.nostr_mls.lock().await.delete_all_data()?;
+        secrets_store::clear_secret_cache();
 
         // Remove logs
         if self.logs_dir.exists() {
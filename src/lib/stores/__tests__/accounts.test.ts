@@ -73,8 +73,25 @@ describe("accounts store", () => {
         });
 
         it("should handle logout", async () => {
-            mockInvoke.mockResolvedValue(undefined);
+            mockInvoke.mockImplementation(async (command: string) =>
+                command === "request_sensitive_action" ? { token: "token" } : undefined
+            );
             await expect(logout(mockAccount.pubkey)).resolves.not.toThrow();
+            expect(mockInvoke).toHaveBeenCalledWith("logout", {
+                hexPubkey: mockAccount.pubkey,
+                confirmationToken: "token",
+            });
+        });
+
+        it("should pass the app passphrase along when confirming logout", async () => {
+            mockInvoke.mockImplementation(async (command: string) =>
+                command === "request_sensitive_action" ? { token: "token" } : undefined
+            );
+            await logout(mockAccount.pubkey, "correct horse");
+            expect(mockInvoke).toHaveBeenCalledWith("request_sensitive_action", {
+                action: "delete_account",
+                passphrase: "correct horse",
+            });
         });
 
         it("should throw LogoutError when account not found", async () => {
@@ -376,6 +376,21 @@ describe("Chat Store", () => {
                     },
                 ]);
             });
+
+            it("saves the reaction in a target message loaded afterwards", () => {
+                const reactionEvent = createReactionEvent(
+                    "reaction-1",
+                    "👍",
+                    2000,
+                    "msg-1",
+                    "other-pubkey"
+                );
+                chatStore.handleCachedMessage(reactionEvent);
+                const messageEvent = createMessageEvent("msg-1", "Hello world", 1000);
+                chatStore.handleCachedMessage(messageEvent);
+                const message = chatStore.findMessage("msg-1");
+                expect(message?.reactions.map((reaction) => reaction.id)).toEqual(["reaction-1"]);
+            });
         });
 
         describe("with deletion event", () => {
@@ -2,6 +2,8 @@ import { invoke } from "@tauri-apps/api/core";
 import { emit } from "@tauri-apps/api/event";
 import { type Writable, derived, get, writable } from "svelte/store";
 import type { NMetadata } from "../types/nostr";
+import { errorMessage } from "../utils/error";
+import { requestSensitiveAction } from "../utils/sensitive-action";
 
 export type Account = {
     pubkey: string;
@@ -84,13 +86,12 @@ export async function createAccount(): Promise<void> {
     });
 }
 
-export async function logout(pubkey: string): Promise<void> {
-    await invoke("logout", { hexPubkey: pubkey }).catch((e) => {
-        if (e === "No account found") {
-            throw new LogoutError("No account found");
-        }
-        throw new LogoutError(e);
-    });
+export async function logout(pubkey: string, passphrase?: string): Promise<void> {
+    await requestSensitiveAction("delete_account", passphrase)
+        .then((confirmationToken) => invoke("logout", { hexPubkey: pubkey, confirmationToken }))
+        .catch((e) => {
+            throw new LogoutError(errorMessage(e));
+        });
     await updateAccountsStore();
     await fetchRelays();
 }
@@ -145,7 +146,7 @@ export async function hasNostrWalletConnectUri(): Promise<boolean> {
     try {
         return await invoke("has_nostr_wallet_connect_uri");
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to check NWC URI: ${error}`);
+        throw new NostrWalletConnectError(`Failed to check NWC URI: ${errorMessage(error)}`);
     }
 }
 
@@ -154,7 +155,7 @@ export async function getNostrWalletConnectBalance(): Promise<number> {
         const msats: number = await invoke("get_nostr_wallet_connect_balance");
         return msats / 1000;
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to get NWC balance: ${error}`);
+        throw new NostrWalletConnectError(`Failed to get NWC balance: ${errorMessage(error)}`);
     }
 }
 
@@ -238,7 +239,7 @@ export async function setNostrWalletConnectUri(uri: string): Promise<void> {
     try {
         await invoke("set_nostr_wallet_connect_uri", { nostrWalletConnectUri: uri });
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to set NWC URI: ${error}`);
+        throw new NostrWalletConnectError(`Failed to set NWC URI: ${errorMessage(error)}`);
     }
 }
 
@@ -246,6 +247,6 @@ export async function removeNostrWalletConnectUri(): Promise<void> {
     try {
         await invoke("remove_nostr_wallet_connect_uri");
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to remove NWC URI: ${error}`);
+        throw new NostrWalletConnectError(`Failed to remove NWC URI: ${errorMessage(error)}`);
     }
 }
@@ -70,6 +70,21 @@ export function createChatStore() {
                 messagesToUpdate.push(replyToMessage);
             }
 
+            // Older pages are loaded after the reactions and payments that refer to them
+            newMessage.reactions = Array.from(get(reactionsMap).values()).filter(
+                (reaction) => reaction.targetId === newMessage.id
+            );
+            if (newMessage.lightningInvoice) {
+                const payment = Array.from(get(messagesMap).values()).find(
+                    (message) => message.replyToId === newMessage.id && message.lightningPayment
+                );
+                if (payment?.lightningPayment) {
+                    newMessage.lightningInvoice.isPaid = true;
+                    payment.lightningPayment.isPaid = true;
+                    messagesToUpdate.push(payment);
+                }
+            }
+
             messagesMap.update((messages) => {
                 for (const message of messagesToUpdate) {
                     messages.set(message.id, message);
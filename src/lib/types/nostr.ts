@@ -12,6 +12,19 @@ export type EnrichedContact = {
     key_package_relays: string[];
 };
 
+export type GroupMember = {
+    pubkey: string;
+    is_admin: boolean;
+    first_seen_epoch?: number;
+    last_message_at?: number;
+    leaf_updated_at?: number;
+    stale_keys: boolean;
+    inactive: boolean;
+    petname?: string;
+    enriched?: EnrichedContact;
+    metadata: NMetadata;
+};
+
 export type EnrichedContactsMap = {
     [keys: string]: EnrichedContact;
 };
@@ -0,0 +1,43 @@
+import { describe, expect, it } from "vitest";
+import { errorMessage, isCommandError, isRetryable, toCommandError } from "../error";
+
+describe("command errors", () => {
+    const relayError = {
+        code: "relay_unreachable",
+        message: "Failed to send message: relay timed out",
+        retryable: true,
+    };
+
+    it("should recognize structured errors", () => {
+        expect(isCommandError(relayError)).toBe(true);
+        expect(isCommandError("Error fetching group")).toBe(false);
+        expect(isCommandError(null)).toBe(false);
+    });
+
+    it("should get the message of any rejection", () => {
+        expect(errorMessage(relayError)).toBe("Failed to send message: relay timed out");
+        expect(errorMessage("Error fetching group")).toBe("Error fetching group");
+        expect(errorMessage(new Error("boom"))).toBe("boom");
+    });
+
+    it("should turn rejected arguments into command errors", () => {
+        const badPubkey =
+            "invalid args `pubkey` for command `export_nsec`: Invalid pubkey `abcd`: invalid length";
+        const badGroupId =
+            "invalid args `groupId` for command `get_group`: Invalid group id `zz`: invalid character";
+        expect(toCommandError(relayError)).toBe(relayError);
+        expect(toCommandError(badPubkey)).toEqual({
+            code: "invalid_key",
+            message: badPubkey,
+            retryable: false,
+        });
+        expect(toCommandError(badGroupId)?.code).toBe("invalid_input");
+        expect(toCommandError("Error fetching group")).toBeUndefined();
+    });
+
+    it("should only retry retryable errors", () => {
+        expect(isRetryable(relayError)).toBe(true);
+        expect(isRetryable({ ...relayError, code: "invalid_key", retryable: false })).toBe(false);
+        expect(isRetryable("Relay error")).toBe(false);
+    });
+});
@@ -0,0 +1,34 @@
+import { describe, expect, it, vi } from "vitest";
+import { isAppPassphraseSet, requestSensitiveAction } from "../sensitive-action";
+
+const mockInvoke = vi.hoisted(() => vi.fn());
+vi.mock("@tauri-apps/api/core", () => ({
+    invoke: mockInvoke,
+}));
+
+describe("requestSensitiveAction", () => {
+    it("should return the token for the action", async () => {
+        mockInvoke.mockResolvedValue({ token: "abc", action: "export_nsec", expires_in: 60 });
+        const token = await requestSensitiveAction("export_nsec");
+        expect(token).toBe("abc");
+        expect(mockInvoke).toHaveBeenCalledWith("request_sensitive_action", {
+            action: "export_nsec",
+            passphrase: null,
+        });
+    });
+
+    it("should reject when the backend refuses", async () => {
+        mockInvoke.mockRejectedValue("Error confirming action: Incorrect passphrase");
+        await expect(requestSensitiveAction("wipe_all_data", "nope")).rejects.toBe(
+            "Error confirming action: Incorrect passphrase"
+        );
+    });
+});
+
+describe("isAppPassphraseSet", () => {
+    it("should ask the backend", async () => {
+        mockInvoke.mockResolvedValue(true);
+        expect(await isAppPassphraseSet()).toBe(true);
+        expect(mockInvoke).toHaveBeenCalledWith("is_app_passphrase_set");
+    });
+});
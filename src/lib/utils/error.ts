@@ -0,0 +1,66 @@
+export type CommandErrorCode =
+    | "invalid_input"
+    | "invalid_key"
+    | "not_found"
+    | "no_active_account"
+    | "app_locked"
+    | "unauthorized"
+    | "relay_unreachable"
+    | "mls"
+    | "storage"
+    | "internal";
+
+/** Error commands reject with */
+export type CommandError = {
+    code: CommandErrorCode;
+    message: string;
+    retryable: boolean;
+};
+
+export function isCommandError(e: unknown): e is CommandError {
+    return (
+        typeof e === "object" &&
+        e !== null &&
+        typeof (e as CommandError).code === "string" &&
+        typeof (e as CommandError).message === "string"
+    );
+}
+
+// Tauri rejects arguments that fail to deserialize with a plain string before the command runs
+const INVALID_ARGS = /^invalid args `\w+` for command `\w+`: /;
+
+/**
+ * Gets the CommandError a command rejected with, including the arguments Tauri rejected, e.g. a
+ * malformed pubkey or group id.
+ * @param e - The rejection
+ * @returns CommandError | undefined - The error, or undefined if it isn't a command error
+ */
+export function toCommandError(e: unknown): CommandError | undefined {
+    if (isCommandError(e)) return e;
+    if (typeof e !== "string" || !INVALID_ARGS.test(e)) return undefined;
+    return {
+        code: e.includes("Invalid pubkey") ? "invalid_key" : "invalid_input",
+        message: e,
+        retryable: false,
+    };
+}
+
+/**
+ * Gets a displayable message from whatever a command rejected with.
+ * @param e - The rejection, a CommandError, a string or an Error
+ * @returns string - The error message
+ */
+export function errorMessage(e: unknown): string {
+    if (isCommandError(e)) return e.message;
+    if (e instanceof Error) return e.message;
+    return String(e);
+}
+
+/**
+ * Whether trying a failed command again might work, e.g. once a relay is reachable.
+ * @param e - The rejection
+ * @returns boolean - True for retryable CommandErrors
+ */
+export function isRetryable(e: unknown): boolean {
+    return isCommandError(e) && e.retryable;
+}
@@ -0,0 +1,43 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type SensitiveAction =
+    | "wipe_all_data"
+    | "export_nsec"
+    | "delete_account"
+    | "export_account"
+    | "export_app_data"
+    | "export_account_data";
+
+type ConfirmationToken = {
+    token: string;
+    action: SensitiveAction;
+    expires_in: number;
+};
+
+/**
+ * Whether an app passphrase is set, in which case it has to be passed to `requestSensitiveAction`.
+ * @returns Promise<boolean>
+ */
+export async function isAppPassphraseSet(): Promise<boolean> {
+    return invoke<boolean>("is_app_passphrase_set");
+}
+
+/**
+ * Requests a single-use confirmation token for a destructive command
+ * (`delete_all_data`, `export_nsec`, `logout`, `export_account`, `export_app_data` or
+ * `export_account_data`).
+ * Without an app passphrase the backend asks the user to confirm in a native dialog.
+ * @param action - The action the token is for
+ * @param passphrase - The app passphrase, required when one is set
+ * @returns Promise<string> - The token to pass to the command as `confirmationToken`
+ */
+export async function requestSensitiveAction(
+    action: SensitiveAction,
+    passphrase?: string
+): Promise<string> {
+    const confirmation = await invoke<ConfirmationToken>("request_sensitive_action", {
+        action,
+        passphrase: passphrase ?? null,
+    });
+    return confirmation.token;
+}